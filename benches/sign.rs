@@ -0,0 +1,23 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use pqc_algo::sign::{self, SigAlgorithm, SignOptions};
+
+fn bench_sign(c: &mut Criterion) {
+    let message = [0x5a; 1024];
+    for algorithm in [SigAlgorithm::Dilithium3, SigAlgorithm::MlDsa65] {
+        let keypair = sign::generate_keypair_with(algorithm).unwrap();
+        let plain = SignOptions::new();
+        let verified = SignOptions::new().verify_after_sign(&keypair.public_key);
+
+        let mut group = c.benchmark_group(format!("sign/{}", algorithm));
+        group.bench_function("plain", |b| {
+            b.iter(|| sign::sign_with_options(algorithm, &message, &keypair.secret_key, &plain).unwrap())
+        });
+        group.bench_function("verify-after-sign", |b| {
+            b.iter(|| sign::sign_with_options(algorithm, &message, &keypair.secret_key, &verified).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_sign);
+criterion_main!(benches);
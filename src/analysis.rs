@@ -0,0 +1,287 @@
+//! Linting of stored provisioning artifacts
+//!
+//! [`lint`] inspects one artifact and reports weak or malformed input:
+//! low-entropy seeds, expired or not-yet-valid certificates, pre-standard
+//! algorithms, ciphertexts whose metadata is not bound to them, and formats
+//! written by another version of this crate. [`lint_bytes`] recognises an
+//! artifact from its stored bytes first, so CI can run it over a directory.
+//! Unlike `policy`, linting needs no deployment rules: the findings hold
+//! for every deployment.
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::api::EncryptedEntropy;
+use crate::backup::{self, BackupParams, BACKUP_VERSION};
+use crate::binary_loader::hex_to_bytes;
+use crate::cert::Certificate;
+use crate::clock::{self, ValidityOptions};
+use crate::envelope::{unix_now, SignedEnvelope};
+use crate::kem::KemAlgorithm;
+use crate::policy::Artifact;
+use crate::randomness_tests::shannon_entropy;
+use crate::sign::SigAlgorithm;
+use crate::suite::CipherSuite;
+
+/// Shortest seed accepted without an error
+pub const MIN_SEED_LEN: usize = 32;
+
+/// Lowest Argon2id memory cost not reported as weak (OWASP minimum, 19 MiB)
+const MIN_BACKUP_MEMORY_KIB: u32 = 19 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One problem found in an artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Stable rule identifier such as `low-entropy-seed`
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} [{}] {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Findings for `artifact`, most severe first
+pub fn lint(artifact: &Artifact<'_>) -> Vec<Finding> {
+    lint_at(artifact, unix_now(), &clock::validity())
+}
+
+/// [`lint`] at an explicit time and tolerance
+pub fn lint_at(artifact: &Artifact<'_>, now: u64, validity: &ValidityOptions) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    match artifact {
+        Artifact::Seed(seed) => lint_seed(seed, &mut findings),
+        Artifact::Entropy(entropy) => lint_entropy(entropy, &mut findings),
+        Artifact::Envelope(envelope) => lint_envelope(envelope, &mut findings),
+        Artifact::Certificate(certificate) => lint_certificate(certificate, now, validity, &mut findings),
+        Artifact::Backup(blob) => lint_backup(blob, &mut findings),
+        Artifact::Device(record) => lint_suite(&record.suite, &mut findings),
+        Artifact::Suite(suite) => lint_suite(suite, &mut findings),
+    }
+    findings.sort_by_key(|f| Reverse(f.severity));
+    findings
+}
+
+/// Recognise a stored artifact and lint it
+///
+/// JSON certificates, signed envelopes, entropy envelopes and backups are
+/// told apart by their fields; anything else is treated as a seed, hex
+/// decoded if it is all hex digits.
+pub fn lint_bytes(bytes: &[u8]) -> Vec<Finding> {
+    lint_bytes_at(bytes, unix_now(), &clock::validity())
+}
+
+/// [`lint_bytes`] at an explicit time and tolerance
+pub fn lint_bytes_at(bytes: &[u8], now: u64, validity: &ValidityOptions) -> Vec<Finding> {
+    if let Ok(certificate) = serde_json::from_slice::<Certificate>(bytes) {
+        return lint_at(&Artifact::Certificate(&certificate), now, validity);
+    }
+    if let Ok(envelope) = serde_json::from_slice::<SignedEnvelope>(bytes) {
+        return lint_at(&Artifact::Envelope(&envelope), now, validity);
+    }
+    if let Ok(entropy) = serde_json::from_slice::<EncryptedEntropy>(bytes) {
+        return lint_at(&Artifact::Entropy(&entropy), now, validity);
+    }
+    if backup::inspect(bytes).is_ok() {
+        return lint_at(&Artifact::Backup(bytes), now, validity);
+    }
+    if bytes.first() == Some(&b'{') {
+        return vec![finding("malformed", Severity::Error, "JSON artifact of unknown type".to_string())];
+    }
+    let text = String::from_utf8_lossy(bytes);
+    let trimmed = text.trim();
+    if !trimmed.is_empty() && trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return match hex_to_bytes(trimmed) {
+            Ok(seed) => lint_at(&Artifact::Seed(&seed), now, validity),
+            Err(e) => vec![finding("malformed", Severity::Error, e.to_string())],
+        };
+    }
+    lint_at(&Artifact::Seed(bytes), now, validity)
+}
+
+fn lint_seed(seed: &[u8], out: &mut Vec<Finding>) {
+    if seed.len() < MIN_SEED_LEN {
+        out.push(finding("short-seed", Severity::Error, format!("Seed is {} bytes, need at least {}", seed.len(), MIN_SEED_LEN)));
+        if seed.is_empty() {
+            return;
+        }
+    }
+    // A sample of n bytes cannot show more than log2(n) bits per byte, and
+    // short random samples fall noticeably below even that
+    let ceiling = (seed.len() as f64).log2().min(8.0);
+    let entropy = shannon_entropy(seed);
+    if entropy < 0.8 * ceiling {
+        out.push(finding("low-entropy-seed", Severity::Error, format!(
+            "Seed has {:.2} bits of entropy per byte, expected close to {:.2}", entropy, ceiling
+        )));
+    }
+    let blocks: Vec<&[u8]> = seed.chunks_exact(16).collect();
+    let unique: HashSet<&[u8]> = blocks.iter().copied().collect();
+    if unique.len() < blocks.len() {
+        out.push(finding("repeated-blocks", Severity::Warning, format!(
+            "{} of {} 16-byte blocks repeat earlier ones", blocks.len() - unique.len(), blocks.len()
+        )));
+    }
+}
+
+fn lint_entropy(entropy: &EncryptedEntropy, out: &mut Vec<Finding>) {
+    match &entropy.suite {
+        Some(suite) => lint_suite(suite, out),
+        None => out.push(finding("version-skew", Severity::Warning,
+            "Envelope predates suite markers; decrypting it relies on the default suite".to_string())),
+    }
+    if entropy.recipient.is_none() && (entropy.suite.is_some() || entropy.fips_mode) {
+        out.push(finding("missing-aad", Severity::Info,
+            "Suite and FIPS markers are not authenticated by the AEAD; trust them only as hints".to_string()));
+    }
+    if entropy.label.as_ref().is_some_and(|label| label.degraded) {
+        out.push(finding("degraded-entropy", Severity::Warning,
+            "Entropy was drawn from OS entropy while the quantum seeds were missing".to_string()));
+    }
+}
+
+fn lint_envelope(envelope: &SignedEnvelope, out: &mut Vec<Finding>) {
+    match envelope.algorithm.parse::<SigAlgorithm>() {
+        Ok(algorithm) => lint_sig(algorithm, out),
+        Err(_) => out.push(finding("unknown-algorithm", Severity::Error, format!("Unknown signature algorithm {}", envelope.algorithm))),
+    }
+    if envelope.purpose.is_empty() {
+        out.push(finding("missing-aad", Severity::Warning,
+            "Envelope has an empty purpose, so its signature is not bound to any context".to_string()));
+    }
+    if envelope.signature.is_empty() {
+        out.push(finding("malformed", Severity::Error, "Envelope has no signature".to_string()));
+    }
+}
+
+fn lint_certificate(certificate: &Certificate, now: u64, validity: &ValidityOptions, out: &mut Vec<Finding>) {
+    lint_sig(certificate.algorithm, out);
+    if validity.is_expired(certificate.not_after, now) {
+        out.push(finding("expired-certificate", Severity::Error, format!(
+            "Certificate for {} expired at {}", certificate.subject, certificate.not_after
+        )));
+    } else if validity.is_premature(certificate.not_before, now) {
+        out.push(finding("not-yet-valid", Severity::Warning, format!(
+            "Certificate for {} is not valid before {}", certificate.subject, certificate.not_before
+        )));
+    } else if validity.needs_renewal(certificate.not_after, now) {
+        out.push(finding("renewal-due", Severity::Info, format!(
+            "Certificate for {} expires at {} and is due for renewal", certificate.subject, certificate.not_after
+        )));
+    }
+    if certificate.not_after < certificate.not_before {
+        out.push(finding("malformed", Severity::Error, "Certificate validity window is empty".to_string()));
+    }
+}
+
+fn lint_backup(blob: &[u8], out: &mut Vec<Finding>) {
+    let (version, kdf, params) = match backup::inspect(blob) {
+        Ok(header) => header,
+        Err(e) => return out.push(finding("malformed", Severity::Error, e.to_string())),
+    };
+    if version != BACKUP_VERSION || kdf != "argon2id" {
+        out.push(finding("version-skew", Severity::Error, format!(
+            "Backup format {} ({}) is not the supported version {}", version, kdf, BACKUP_VERSION
+        )));
+    }
+    let BackupParams { memory_kib, iterations, .. } = params;
+    if memory_kib < MIN_BACKUP_MEMORY_KIB || iterations < 2 {
+        out.push(finding("weak-kdf", Severity::Warning, format!(
+            "Argon2id costs of {} KiB and {} passes are below the recommended minimum", memory_kib, iterations
+        )));
+    }
+}
+
+fn lint_suite(suite: &CipherSuite, out: &mut Vec<Finding>) {
+    lint_kem(suite.kem, out);
+    lint_sig(suite.sig, out);
+}
+
+/// Round-3 Kyber is superseded by ML-KEM (FIPS 203)
+fn lint_kem(kem: KemAlgorithm, out: &mut Vec<Finding>) {
+    if matches!(kem, KemAlgorithm::Kyber512 | KemAlgorithm::Kyber768 | KemAlgorithm::Kyber1024) {
+        out.push(finding("deprecated-algorithm", Severity::Warning, format!("{} is pre-standard; migrate to ML-KEM", kem)));
+    }
+}
+
+/// Round-3 Dilithium is superseded by ML-DSA (FIPS 204)
+fn lint_sig(sig: SigAlgorithm, out: &mut Vec<Finding>) {
+    if matches!(sig, SigAlgorithm::Dilithium2 | SigAlgorithm::Dilithium3 | SigAlgorithm::Dilithium5) {
+        out.push(finding("deprecated-algorithm", Severity::Warning, format!("{} is pre-standard; migrate to ML-DSA", sig)));
+    }
+}
+
+fn finding(rule: &str, severity: Severity, message: String) -> Finding {
+    Finding { rule: rule.to_string(), severity, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    fn rules(findings: &[Finding]) -> Vec<&str> {
+        findings.iter().map(|f| f.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn test_seed_lint() {
+        let mut good = vec![0u8; 256];
+        rand::thread_rng().fill_bytes(&mut good);
+        assert!(lint(&Artifact::Seed(&good)).is_empty());
+        assert!(lint_bytes(hex::encode(&good).as_bytes()).is_empty());
+
+        let patterned = "0123456789abcdef".repeat(32);
+        assert_eq!(rules(&lint_bytes(patterned.as_bytes())), ["low-entropy-seed", "repeated-blocks"]);
+        assert_eq!(rules(&lint(&Artifact::Seed(&good[..16]))), ["short-seed"]);
+    }
+
+    #[test]
+    fn test_certificate_and_envelope_lint() {
+        let authority = crate::cert::CertificateAuthority::new(
+            "CA", SigAlgorithm::MlDsa65, Vec::new(), crate::sign::generate_keypair_with(SigAlgorithm::MlDsa65).unwrap().secret_key.into(),
+        );
+        let certificate = authority.issue_at("sensor-7", b"key", 1000, 2000, false).unwrap();
+        let strict = ValidityOptions::STRICT;
+        assert!(lint_at(&Artifact::Certificate(&certificate), 1500, &strict).is_empty());
+        let stored = certificate.to_bytes().unwrap();
+        assert_eq!(rules(&lint_bytes_at(&stored, 3000, &strict)), ["expired-certificate"]);
+
+        // Renewal and skew tolerance only apply when a deployment opts in
+        let tolerant = ValidityOptions::TOLERANT;
+        let long_lived = authority.issue_at("sensor-8", b"key", 1000, 10_000_000, false).unwrap();
+        assert!(lint_at(&Artifact::Certificate(&long_lived), 1500, &tolerant).is_empty());
+        assert_eq!(rules(&lint_at(&Artifact::Certificate(&certificate), 1500, &tolerant)), ["renewal-due"]);
+        assert_eq!(rules(&lint_at(&Artifact::Certificate(&certificate), 2100, &tolerant)), ["renewal-due"]);
+        assert_eq!(rules(&lint_at(&Artifact::Certificate(&certificate), 2100, &strict)), ["expired-certificate"]);
+
+        let legacy = EncryptedEntropy { ciphertext: vec![1], encrypted_data: vec![2], suite: None, fips_mode: false, label: None, recipient: None };
+        assert_eq!(rules(&lint(&Artifact::Entropy(&legacy))), ["version-skew"]);
+        let current = EncryptedEntropy { suite: Some(CipherSuite::DEFAULT), ..legacy };
+        let findings = lint(&Artifact::Entropy(&current));
+        assert!(rules(&findings).contains(&"deprecated-algorithm"));
+        assert_eq!(findings.last().unwrap().rule, "missing-aad");
+
+        let label = crate::entropy_label::EntropyLabel::new("local-pool", &"ab".repeat(32), 1).with_degraded(true);
+        let degraded = EncryptedEntropy { label: Some(label), ..current };
+        assert!(rules(&lint(&Artifact::Entropy(&degraded))).contains(&"degraded-entropy"));
+        let bound = EncryptedEntropy { recipient: Some("ab".repeat(32)), ..degraded };
+        assert!(!rules(&lint(&Artifact::Entropy(&bound))).contains(&"missing-aad"));
+
+        // The same artifacts a policy judges can be linted
+        assert_eq!(rules(&lint(&Artifact::Suite(CipherSuite::DEFAULT))), ["deprecated-algorithm", "deprecated-algorithm"]);
+        assert!(lint(&Artifact::Suite(crate::suite::SecurityLevel::L3.fips_suite())).is_empty());
+    }
+}
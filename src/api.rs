@@ -1,88 +1,488 @@
-use crate::error::{PqcError, Result};
-use crate::{kem, sign};
+use crate::envelope::key_fingerprint;
+use crate::error::{ErrorContext, PqcError, Result, ResultExt};
+use crate::lifecycle::{self, LifecycleEvent, LifecycleEventKind};
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::{backup, canonical, fips, kdf, kem, sign};
+use crate::backup::BackupParams;
+use crate::cancel::{CancelToken, Partial};
+use crate::decap_cache::DecapsulationCache;
+use crate::entropy_label::EntropyLabel;
+use crate::extensions::{ExtensionFields, ExtensionValidators, Extensions};
+use crate::secret::{expose_secret_serde, SecretKey};
+use crate::suite::{AeadAlgorithm, CipherSuite, SEAL_OVERHEAD};
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
 use serde::{Deserialize, Serialize};
-use aes_gcm::{aead::{Aead, KeyInit}, Aes256Gcm, Nonce};
-use rand::Rng;
+use zeroize::Zeroizing;
 
+/// Domain label for signed authentication requests
+const AUTH_REQUEST_DOMAIN: &str = "PQC-Algo/v1/auth-request";
+
+/// Domain label for the entropy envelope header authenticated by the AEAD
+const ENTROPY_AAD_DOMAIN: &str = "PQC-Algo/v1/entropy-envelope-aad";
+
+/// A device's Kyber and Dilithium keypairs
+///
+/// Serializing writes the public keys only; use
+/// [`DeviceCredentials::expose_secret_serde`] to include the secret keys.
+/// Deserializing expects the full form.
 #[derive(Serialize, Deserialize)]
 pub struct DeviceCredentials {
+    #[serde(with = "crate::util::bytes")]
     pub kyber_public_key: Vec<u8>,
-    pub kyber_secret_key: Vec<u8>,
+    #[serde(skip_serializing, deserialize_with = "expose_secret_serde::deserialize")]
+    pub kyber_secret_key: SecretKey,
+    #[serde(with = "crate::util::bytes")]
     pub dilithium_public_key: Vec<u8>,
-    pub dilithium_secret_key: Vec<u8>,
+    #[serde(skip_serializing, deserialize_with = "expose_secret_serde::deserialize")]
+    pub dilithium_secret_key: SecretKey,
+}
+
+/// [`DeviceCredentials`] serialized with their secret keys
+#[derive(Serialize)]
+pub struct ExposedCredentials<'a> {
+    kyber_public_key: &'a [u8],
+    #[serde(with = "expose_secret_serde")]
+    kyber_secret_key: &'a SecretKey,
+    dilithium_public_key: &'a [u8],
+    #[serde(with = "expose_secret_serde")]
+    dilithium_secret_key: &'a SecretKey,
+}
+
+impl fmt::Debug for DeviceCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceCredentials")
+            .field("kyber_fingerprint", &key_fingerprint(&self.kyber_public_key))
+            .field("kyber_secret_key", &self.kyber_secret_key)
+            .field("dilithium_fingerprint", &key_fingerprint(&self.dilithium_public_key))
+            .field("dilithium_secret_key", &self.dilithium_secret_key)
+            .finish()
+    }
 }
 
 impl DeviceCredentials {
     pub fn generate() -> Result<Self> {
-        let kyber_keys = kem::generate_keypair()?;
-        let dilithium_keys = sign::generate_keypair()?;
+        Self::generate_with_suite(&CipherSuite::active_default())
+    }
+    
+    /// Generate credentials for the suite's KEM and signature algorithms
+    pub fn generate_with_suite(suite: &CipherSuite) -> Result<Self> {
+        fips::check_suite(suite)?;
+        let kyber_keys = kem::generate_keypair_with(suite.kem)?;
+        let dilithium_keys = sign::generate_keypair_with(suite.sig)?;
+        lifecycle::emit(LifecycleEvent::generated(&kyber_keys.public_key, suite.kem.name()));
+        lifecycle::emit(LifecycleEvent::generated(&dilithium_keys.public_key, suite.sig.name()));
         
         Ok(Self {
             kyber_public_key: kyber_keys.public_key,
-            kyber_secret_key: kyber_keys.secret_key,
+            kyber_secret_key: SecretKey::new(kyber_keys.secret_key),
             dilithium_public_key: dilithium_keys.public_key,
-            dilithium_secret_key: dilithium_keys.secret_key,
+            dilithium_secret_key: SecretKey::new(dilithium_keys.secret_key),
         })
     }
+    
+    /// Serialize including the secret keys, for protected storage only
+    pub fn expose_secret_serde(&self) -> ExposedCredentials<'_> {
+        ExposedCredentials {
+            kyber_public_key: &self.kyber_public_key,
+            kyber_secret_key: &self.kyber_secret_key,
+            dilithium_public_key: &self.dilithium_public_key,
+            dilithium_secret_key: &self.dilithium_secret_key,
+        }
+    }
+
+    /// Passphrase-protected backup of the full credentials
+    pub fn export_encrypted(&self, passphrase: &str) -> Result<Vec<u8>> {
+        self.export_encrypted_with(passphrase, BackupParams::default())
+    }
+
+    /// Like [`DeviceCredentials::export_encrypted`] with explicit Argon2id costs
+    pub fn export_encrypted_with(&self, passphrase: &str, params: BackupParams) -> Result<Vec<u8>> {
+        let json = Zeroizing::new(serde_json::to_vec(&self.expose_secret_serde())
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode credentials: {}", e)))?);
+        let blob = backup::seal(&json, passphrase, params)?;
+        lifecycle::emit(LifecycleEvent::new(LifecycleEventKind::Exported, &key_fingerprint(&self.dilithium_public_key))
+            .with_detail("passphrase-encrypted backup"));
+        Ok(blob)
+    }
+
+    /// Restore credentials from [`DeviceCredentials::export_encrypted`] output
+    pub fn import_encrypted(blob: &[u8], passphrase: &str) -> Result<Self> {
+        let json = backup::open(blob, passphrase)?;
+        serde_json::from_slice(&json)
+            .map_err(|e| PqcError::InvalidInput(format!("Backup does not hold device credentials: {}", e)))
+    }
+
+    /// Generate credentials for `n` devices in parallel
+    pub fn generate_many(n: usize) -> Result<Vec<Self>> {
+        Self::generate_many_with_progress(n, |_, _| {})
+    }
+    
+    /// Generate credentials for `n` devices in parallel, reporting `(completed, total)` progress
+    pub fn generate_many_with_progress<P>(n: usize, progress: P) -> Result<Vec<Self>>
+    where
+        P: Fn(usize, usize) + Sync,
+    {
+        crate::pool::run_parallel(n, |_| Self::generate(), progress)
+    }
+
+    /// [`DeviceCredentials::generate_many_with_progress`] that stops early once `token` is cancelled
+    pub fn generate_many_cancellable<P>(n: usize, progress: P, token: &CancelToken) -> Partial<Self>
+    where
+        P: Fn(usize, usize) + Sync,
+    {
+        crate::pool::run_parallel_cancellable(n, |_| Self::generate(), progress, token)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct EncryptedEntropy {
+    #[serde(with = "crate::util::bytes")]
     pub ciphertext: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
     pub encrypted_data: Vec<u8>,
+    /// Suite the entropy was sealed under; absent in envelopes from older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suite: Option<CipherSuite>,
+    /// Set when the envelope was sealed in FIPS mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fips_mode: bool,
+    /// Signed record of the entropy source; see `entropy_label`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<EntropyLabel>,
+    /// Fingerprint of the recipient's KEM public key, set by [`encrypt_entropy_bound`]
+    ///
+    /// When present, the AEAD also authenticates it, the suite and the FIPS
+    /// marker, and anyone who sees the envelope can tell which key it is for.
+    /// Absent in envelopes from [`encrypt_entropy_with_suite`] and older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+}
+
+/// Envelope header fields authenticated as AEAD associated data
+#[derive(Serialize)]
+struct EntropyAad<'a> {
+    recipient: &'a str,
+    suite: Option<&'a CipherSuite>,
+    fips_mode: bool,
+}
+
+impl EncryptedEntropy {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode entropy envelope: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "entropy envelope")
+    }
+
+    /// Associated data for the AEAD; empty for envelopes without a recipient
+    pub(crate) fn aad(&self) -> Result<Vec<u8>> {
+        match &self.recipient {
+            Some(recipient) => canonical::signing_bytes(ENTROPY_AAD_DOMAIN, &EntropyAad {
+                recipient,
+                suite: self.suite.as_ref(),
+                fips_mode: self.fips_mode,
+            }),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Fail if the envelope names a recipient other than `kyber_public_key`
+    pub(crate) fn check_recipient(&self, kyber_public_key: &[u8]) -> Result<()> {
+        match &self.recipient {
+            Some(recipient) if !ct::ct_eq_hex(recipient, &key_fingerprint(kyber_public_key)) => {
+                Err(PqcError::Decryption("Envelope is addressed to a different key".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Validate for EncryptedEntropy {
+    /// Envelopes without a suite marker are checked against the default suite
+    fn validate(&self) -> Result<()> {
+        let suite = self.suite.unwrap_or(CipherSuite::DEFAULT);
+        validation::check_exact_len("KEM ciphertext", self.ciphertext.len(), suite.kem.ciphertext_len()?)?;
+        validation::check_sealed_len("Encrypted entropy", self.encrypted_data.len())?;
+        if let Some(recipient) = &self.recipient {
+            validation::check_fingerprint("Envelope recipient", recipient)?;
+        }
+        self.label.as_ref().map_or(Ok(()), Validate::validate)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct AuthRequest {
     pub device_id: String,
+    #[serde(with = "crate::util::bytes")]
     pub nonce: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
     pub signature: Vec<u8>,
+    /// Custom metadata; covered by the signature
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
+}
+
+impl AuthRequest {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode auth request: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "auth request")
+    }
+}
+
+impl Validate for AuthRequest {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_max_len("Auth nonce", self.nonce.len(), validation::MAX_NONCE_LEN)?;
+        self.extensions.validate()?;
+        validation::check_any_signature_len("Auth signature", self.signature.len())
+    }
 }
 
 pub fn encrypt_entropy_for_device(entropy: &[u8], device_kyber_public_key: &[u8]) -> Result<EncryptedEntropy> {
-    let (ciphertext, shared_secret) = kem::encapsulate(device_kyber_public_key)?;
-    let encrypted_data = encrypt_with_aes(&shared_secret, entropy)?;
-    Ok(EncryptedEntropy { ciphertext, encrypted_data })
+    encrypt_entropy_with_suite(entropy, device_kyber_public_key, &CipherSuite::active_default())
 }
 
 pub fn decrypt_entropy(encrypted: &EncryptedEntropy, device_kyber_secret_key: &[u8]) -> Result<Vec<u8>> {
-    let shared_secret = kem::decapsulate(device_kyber_secret_key, &encrypted.ciphertext)?;
-    decrypt_with_aes(&shared_secret, &encrypted.encrypted_data)
+    decrypt_entropy_with_suite(encrypted, device_kyber_secret_key, &CipherSuite::active_default())
+}
+
+/// Encrypt entropy using the suite's KEM and AEAD
+///
+/// The header is not authenticated, so every release can open the result.
+/// Use [`encrypt_entropy_bound_with_suite`] once all readers are upgraded.
+pub fn encrypt_entropy_with_suite(entropy: &[u8], device_kyber_public_key: &[u8], suite: &CipherSuite) -> Result<EncryptedEntropy> {
+    seal_entropy(entropy, device_kyber_public_key, suite, false)
+}
+
+/// [`encrypt_entropy_for_device`], with the header bound to the recipient
+pub fn encrypt_entropy_bound(entropy: &[u8], device_kyber_public_key: &[u8]) -> Result<EncryptedEntropy> {
+    encrypt_entropy_bound_with_suite(entropy, device_kyber_public_key, &CipherSuite::active_default())
+}
+
+/// [`encrypt_entropy_with_suite`], recording the recipient's key fingerprint
+///
+/// The AEAD authenticates the fingerprint, suite and FIPS marker, so a
+/// rewritten header fails to decrypt and [`decrypt_entropy_inspect`] can
+/// reject envelopes for other keys before decapsulating. Releases before
+/// 0.1.0 cannot open the result, and the fingerprint links every envelope
+/// sent to the same device.
+pub fn encrypt_entropy_bound_with_suite(entropy: &[u8], device_kyber_public_key: &[u8], suite: &CipherSuite) -> Result<EncryptedEntropy> {
+    seal_entropy(entropy, device_kyber_public_key, suite, true)
+}
+
+fn seal_entropy(entropy: &[u8], device_kyber_public_key: &[u8], suite: &CipherSuite, bind_recipient: bool) -> Result<EncryptedEntropy> {
+    fips::check_suite(suite)?;
+    let (ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, device_kyber_public_key)?;
+    let mut encrypted = EncryptedEntropy {
+        ciphertext,
+        encrypted_data: Vec::new(),
+        suite: Some(*suite),
+        fips_mode: fips::fips_mode(),
+        label: None,
+        recipient: bind_recipient.then(|| key_fingerprint(device_kyber_public_key)),
+    };
+    encrypted.encrypted_data = suite.aead.seal_with_aad(&kdf::envelope_key(&shared_secret)?, entropy, &encrypted.aad()?)?;
+    Ok(encrypted)
+}
+
+/// Decrypt entropy produced by [`encrypt_entropy_with_suite`] with the same suite
+pub fn decrypt_entropy_with_suite(encrypted: &EncryptedEntropy, device_kyber_secret_key: &[u8], suite: &CipherSuite) -> Result<Vec<u8>> {
+    check_sealed_suite(encrypted, suite)?;
+    let shared_secret = kem::decapsulate_with(suite.kem, device_kyber_secret_key, &encrypted.ciphertext)?;
+    suite.aead.open_with_aad(&kdf::envelope_key(&shared_secret)?, &encrypted.encrypted_data, &encrypted.aad()?)
+}
+
+/// [`decrypt_entropy_with_suite`], reusing shared secrets from `cache`
+///
+/// For gateways that receive the same envelope repeatedly; only the AEAD
+/// runs on a cache hit.
+pub fn decrypt_entropy_cached(encrypted: &EncryptedEntropy, device_kyber_secret_key: &[u8], suite: &CipherSuite, cache: &DecapsulationCache) -> Result<Vec<u8>> {
+    check_sealed_suite(encrypted, suite)?;
+    let aad = encrypted.aad()?;
+    cache.decapsulate_and_open(suite.kem, device_kyber_secret_key, &encrypted.ciphertext, |shared_secret| {
+        suite.aead.open_with_aad(&kdf::envelope_key(shared_secret)?, &encrypted.encrypted_data, &aad)
+    })
+}
+
+/// What [`decrypt_entropy_inspect`] learned about an envelope without decrypting it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvelopeMetadata {
+    pub suite: CipherSuite,
+    /// False for envelopes from before suites were recorded
+    pub suite_recorded: bool,
+    pub fips_mode: bool,
+    /// The header is bound to the recipient key and authenticated by the AEAD
+    pub recipient_bound: bool,
+    pub plaintext_len: usize,
+    /// Entropy source named by the label, which is not verified here
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_source: Option<String>,
+}
+
+/// An envelope that passed every check short of decapsulation
+pub struct InspectedEntropy<'a> {
+    envelope: &'a EncryptedEntropy,
+    suite: CipherSuite,
+    metadata: EnvelopeMetadata,
+}
+
+impl InspectedEntropy<'_> {
+    pub fn metadata(&self) -> &EnvelopeMetadata {
+        &self.metadata
+    }
+
+    /// Decapsulate and release the plaintext
+    pub fn decrypt(self, device_kyber_secret_key: &[u8]) -> Result<Vec<u8>> {
+        decrypt_entropy_with_suite(self.envelope, device_kyber_secret_key, &self.suite)
+    }
+}
+
+/// First stage of decryption: check `encrypted` without touching the secret key
+///
+/// Validates the structure and lengths, the suite against the active
+/// default and FIPS mode, and that the envelope is addressed to
+/// `device_kyber_public_key`. Costs no decapsulation, so gateways can drop
+/// mismatched envelopes cheaply; [`InspectedEntropy::decrypt`] releases the
+/// plaintext.
+pub fn decrypt_entropy_inspect<'a>(encrypted: &'a EncryptedEntropy, device_kyber_public_key: &[u8]) -> Result<InspectedEntropy<'a>> {
+    decrypt_entropy_inspect_with_suite(encrypted, device_kyber_public_key, &CipherSuite::active_default())
+}
+
+/// [`decrypt_entropy_inspect`] for a recipient key of `suite`
+pub fn decrypt_entropy_inspect_with_suite<'a>(
+    encrypted: &'a EncryptedEntropy,
+    device_kyber_public_key: &[u8],
+    suite: &CipherSuite,
+) -> Result<InspectedEntropy<'a>> {
+    check_sealed_suite(encrypted, suite)?;
+    // Not `validate`, which checks legacy envelopes against the default suite
+    validation::check_exact_len("KEM ciphertext", encrypted.ciphertext.len(), suite.kem.ciphertext_len()?)?;
+    validation::check_sealed_len("Encrypted entropy", encrypted.encrypted_data.len())?;
+    encrypted.label.as_ref().map_or(Ok(()), Validate::validate)?;
+    validation::check_exact_len("Recipient public key", device_kyber_public_key.len(), suite.kem.public_key_len()?)?;
+    encrypted.check_recipient(device_kyber_public_key)?;
+    let metadata = EnvelopeMetadata {
+        suite: *suite,
+        suite_recorded: encrypted.suite.is_some(),
+        fips_mode: encrypted.fips_mode,
+        recipient_bound: encrypted.recipient.is_some(),
+        plaintext_len: encrypted.encrypted_data.len() - SEAL_OVERHEAD,
+        label_source: encrypted.label.as_ref().map(|label| label.source.clone()),
+    };
+    Ok(InspectedEntropy { envelope: encrypted, suite: *suite, metadata })
+}
+
+fn check_sealed_suite(encrypted: &EncryptedEntropy, suite: &CipherSuite) -> Result<()> {
+    fips::check_suite(suite)?;
+    match &encrypted.suite {
+        Some(sealed) if sealed != suite => {
+            Err(PqcError::Decryption(format!("Sealed under {}, expected {}", sealed, suite)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Re-encrypt stored entropy to a new recipient key under a new suite
+///
+/// The old envelope is opened with the suite it records, or the default
+/// suite for envelopes written before suites were recorded. The plaintext
+/// is zeroized before returning.
+pub fn reencrypt_envelope(
+    old_envelope: &EncryptedEntropy,
+    old_kyber_secret_key: &[u8],
+    new_recipient_public_key: &[u8],
+    new_suite: &CipherSuite,
+) -> Result<EncryptedEntropy> {
+    let old_suite = old_envelope.suite.unwrap_or(CipherSuite::DEFAULT);
+    let entropy = decrypt_entropy_with_suite(old_envelope, old_kyber_secret_key, &old_suite)
+        .map(Zeroizing::new)
+        .context(|| ErrorContext::new("open envelope for re-encryption"))?;
+    encrypt_entropy_with_suite(&entropy, new_recipient_public_key, new_suite)
+        .context(|| ErrorContext::new("re-encrypt envelope").with_key_fingerprint(&key_fingerprint(new_recipient_public_key)))
+}
+
+/// Fields of an `AuthRequest` covered by its signature
+#[derive(Serialize)]
+struct AuthMessage<'a> {
+    device_id: &'a str,
+    nonce: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    extensions: BTreeMap<&'a str, ExtensionFields>,
+}
+
+fn auth_signing_bytes(device_id: &str, nonce: &[u8], extensions: &Extensions) -> Result<Vec<u8>> {
+    canonical::signing_bytes(AUTH_REQUEST_DOMAIN, &AuthMessage {
+        device_id,
+        nonce: hex::encode(nonce),
+        extensions: extensions.signing_fields(),
+    })
 }
 
 pub fn create_auth_request(device_id: &str, nonce: &[u8], device_dilithium_secret_key: &[u8]) -> Result<AuthRequest> {
-    let message = format!("{}|{}", device_id, hex::encode(nonce));
-    let signature = sign::sign(message.as_bytes(), device_dilithium_secret_key)?;
-    Ok(AuthRequest { device_id: device_id.to_string(), nonce: nonce.to_vec(), signature })
+    create_auth_request_with_suite(device_id, nonce, device_dilithium_secret_key, &CipherSuite::active_default())
 }
 
 pub fn verify_auth_request(request: &AuthRequest, device_dilithium_public_key: &[u8]) -> Result<bool> {
-    let message = format!("{}|{}", request.device_id, hex::encode(&request.nonce));
-    sign::verify(message.as_bytes(), &request.signature, device_dilithium_public_key)
+    verify_auth_request_with_suite(request, device_dilithium_public_key, &CipherSuite::active_default())
+}
+
+/// Sign an authentication request with the suite's signature algorithm
+pub fn create_auth_request_with_suite(device_id: &str, nonce: &[u8], device_dilithium_secret_key: &[u8], suite: &CipherSuite) -> Result<AuthRequest> {
+    create_auth_request_with_extensions(device_id, nonce, Extensions::new(), device_dilithium_secret_key, suite)
+}
+
+/// [`create_auth_request_with_suite`], carrying `extensions` under the signature
+pub fn create_auth_request_with_extensions(device_id: &str, nonce: &[u8], extensions: Extensions, device_dilithium_secret_key: &[u8], suite: &CipherSuite) -> Result<AuthRequest> {
+    fips::check_suite(suite)?;
+    validate_device_id(device_id)?;
+    extensions.validate()?;
+    let message = auth_signing_bytes(device_id, nonce, &extensions)?;
+    let signature = sign::sign_with(suite.sig, &message, device_dilithium_secret_key)?;
+    Ok(AuthRequest { device_id: device_id.to_string(), nonce: nonce.to_vec(), signature, extensions })
 }
 
-fn encrypt_with_aes(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
-    let cipher = Aes256Gcm::new_from_slice(&key[..32])
-        .map_err(|e| PqcError::Encryption(format!("{}", e)))?;
-    let mut nonce_bytes = [0u8; 12];
-    rand::thread_rng().fill(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
-    let ciphertext = cipher.encrypt(nonce, plaintext)
-        .map_err(|e| PqcError::Encryption(format!("{}", e)))?;
-    let mut result = nonce_bytes.to_vec();
-    result.extend_from_slice(&ciphertext);
-    Ok(result)
+/// Verify an authentication request with the suite's signature algorithm
+///
+/// A signed request with a critical extension is an error; use
+/// [`verify_auth_request_with_extensions`] to accept some.
+pub fn verify_auth_request_with_suite(request: &AuthRequest, device_dilithium_public_key: &[u8], suite: &CipherSuite) -> Result<bool> {
+    verify_auth_request_with_extensions(request, device_dilithium_public_key, suite, &ExtensionValidators::new())
 }
 
-fn decrypt_with_aes(key: &[u8], ciphertext_with_nonce: &[u8]) -> Result<Vec<u8>> {
-    if ciphertext_with_nonce.len() < 12 {
-        return Err(PqcError::Decryption("Invalid ciphertext".to_string()));
+/// [`verify_auth_request_with_suite`], checking extensions with `validators`
+///
+/// A signed request whose extensions fail [`Extensions::check`] is an error.
+pub fn verify_auth_request_with_extensions(
+    request: &AuthRequest,
+    device_dilithium_public_key: &[u8],
+    suite: &CipherSuite,
+    validators: &ExtensionValidators,
+) -> Result<bool> {
+    fips::check_suite(suite)?;
+    request.validate()?;
+    validation::check_signature_len("Auth signature", suite.sig, request.signature.len())?;
+    let message = auth_signing_bytes(&request.device_id, &request.nonce, &request.extensions)?;
+    if !sign::verify_with(suite.sig, &message, &request.signature, device_dilithium_public_key)? {
+        return Ok(false);
     }
-    let (nonce_bytes, ciphertext) = ciphertext_with_nonce.split_at(12);
-    let cipher = Aes256Gcm::new_from_slice(&key[..32])
-        .map_err(|e| PqcError::Decryption(format!("{}", e)))?;
-    cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
-        .map_err(|e| PqcError::Decryption(format!("{}", e)))
+    request.extensions.check(validators)?;
+    Ok(true)
+}
+
+pub(crate) fn encrypt_with_aes(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    AeadAlgorithm::Aes256Gcm.seal(key, plaintext)
+}
+
+pub(crate) fn decrypt_with_aes(key: &[u8], ciphertext_with_nonce: &[u8]) -> Result<Vec<u8>> {
+    AeadAlgorithm::Aes256Gcm.open(key, ciphertext_with_nonce)
 }
 
 #[cfg(test)]
@@ -98,6 +498,24 @@ mod tests {
         assert_eq!(entropy.as_slice(), decrypted.as_slice());
     }
 
+    #[test]
+    fn test_cached_decryption() {
+        let device = DeviceCredentials::generate().unwrap();
+        let suite = CipherSuite::DEFAULT;
+        let cache = DecapsulationCache::default();
+        let mut encrypted = encrypt_entropy_with_suite(b"broadcast", &device.kyber_public_key, &suite).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(decrypt_entropy_cached(&encrypted, &device.kyber_secret_key, &suite, &cache).unwrap(), b"broadcast");
+        }
+        assert_eq!(cache.len(), 1);
+
+        // A forged ciphertext fails the AEAD and does not take a cache slot
+        encrypted.ciphertext[0] ^= 1;
+        assert!(decrypt_entropy_cached(&encrypted, &device.kyber_secret_key, &suite, &cache).is_err());
+        assert_eq!(cache.len(), 1);
+    }
+
     #[test]
     fn test_full_auth_flow() {
         let device = DeviceCredentials::generate().unwrap();
@@ -105,4 +523,182 @@ mod tests {
         let is_valid = verify_auth_request(&auth_request, &device.dilithium_public_key).unwrap();
         assert!(is_valid);
     }
+
+    #[test]
+    fn test_credentials_redact_secrets() {
+        let device = DeviceCredentials::generate().unwrap();
+        let public = serde_json::to_value(&device).unwrap();
+        assert!(public.get("kyber_public_key").is_some());
+        assert!(public.get("kyber_secret_key").is_none() && public.get("dilithium_secret_key").is_none());
+        assert!(serde_json::from_value::<DeviceCredentials>(public).is_err());
+        assert!(format!("{:?}", device).contains("redacted"));
+
+        let full = serde_json::to_vec(&device.expose_secret_serde()).unwrap();
+        let restored: DeviceCredentials = serde_json::from_slice(&full).unwrap();
+        assert_eq!(restored.kyber_secret_key, device.kyber_secret_key);
+        assert_eq!(restored.dilithium_public_key, device.dilithium_public_key);
+    }
+
+    #[test]
+    fn test_encrypted_export() {
+        let device = DeviceCredentials::generate().unwrap();
+        let params = BackupParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let blob = device.export_encrypted_with("hunter2", params).unwrap();
+
+        let restored = DeviceCredentials::import_encrypted(&blob, "hunter2").unwrap();
+        assert_eq!(restored.dilithium_secret_key, device.dilithium_secret_key);
+        assert!(DeviceCredentials::import_encrypted(&blob, "hunter3").is_err());
+    }
+
+    #[test]
+    fn test_level5_suite_flow() {
+        let suite = crate::suite::SecurityLevel::L5.suite();
+        let device = DeviceCredentials::generate_with_suite(&suite).unwrap();
+        
+        let encrypted = encrypt_entropy_with_suite(b"entropy", &device.kyber_public_key, &suite).unwrap();
+        assert_eq!(decrypt_entropy_with_suite(&encrypted, &device.kyber_secret_key, &suite).unwrap(), b"entropy");
+        
+        let request = create_auth_request_with_suite("device-5", b"nonce", &device.dilithium_secret_key, &suite).unwrap();
+        assert!(verify_auth_request_with_suite(&request, &device.dilithium_public_key, &suite).unwrap());
+        assert!(verify_auth_request(&request, &device.dilithium_public_key).is_err());
+        
+        // The recorded suite is checked before any decryption is attempted
+        assert!(matches!(decrypt_entropy(&encrypted, &device.kyber_secret_key), Err(PqcError::Decryption(_))));
+    }
+
+    #[test]
+    fn test_reencrypt_to_ml_kem() {
+        let old = DeviceCredentials::generate().unwrap();
+        let legacy = encrypt_entropy_for_device(b"stored entropy", &old.kyber_public_key).unwrap();
+        
+        let new_suite = CipherSuite { kem: kem::KemAlgorithm::MlKem1024, ..CipherSuite::DEFAULT };
+        let new_keys = kem::generate_keypair_with(new_suite.kem).unwrap();
+        let upgraded = reencrypt_envelope(&legacy, &old.kyber_secret_key, &new_keys.public_key, &new_suite).unwrap();
+        
+        assert_eq!(upgraded.suite, Some(new_suite));
+        assert_eq!(decrypt_entropy_with_suite(&upgraded, &new_keys.secret_key, &new_suite).unwrap(), b"stored entropy");
+    }
+
+    #[test]
+    fn test_auth_fields_cannot_be_reshuffled() {
+        let device = DeviceCredentials::generate().unwrap();
+        let auth_request = create_auth_request("device_6e", b"nonce", &device.dilithium_secret_key).unwrap();
+        
+        // Moving bytes between device_id and nonce must break the signature
+        let shifted = AuthRequest {
+            device_id: "device_".to_string(),
+            nonce: [&[0x6e], b"nonce".as_slice()].concat(),
+            signature: auth_request.signature.clone(),
+            extensions: Extensions::new(),
+        };
+        assert!(!verify_auth_request(&shifted, &device.dilithium_public_key).unwrap());
+    }
+
+    #[test]
+    fn test_auth_extensions_are_signed() {
+        let device = DeviceCredentials::generate().unwrap();
+        let suite = CipherSuite::active_default();
+        let mut extensions = Extensions::new();
+        extensions.insert("1.3.6.1.4.1.55555.10", false, b"tenant=acme").unwrap();
+        let request = create_auth_request_with_extensions("sensor-7", b"nonce", extensions, &device.dilithium_secret_key, &suite).unwrap();
+        let decoded = AuthRequest::from_bytes(&request.to_bytes().unwrap()).unwrap();
+        assert!(verify_auth_request(&decoded, &device.dilithium_public_key).unwrap());
+
+        let mut tampered = AuthRequest::from_bytes(&request.to_bytes().unwrap()).unwrap();
+        tampered.extensions.insert("1.3.6.1.4.1.55555.10", false, b"tenant=evil").unwrap();
+        assert!(!verify_auth_request(&tampered, &device.dilithium_public_key).unwrap());
+        tampered.extensions = Extensions::new();
+        assert!(!verify_auth_request(&tampered, &device.dilithium_public_key).unwrap());
+    }
+
+    #[test]
+    fn test_auth_rejects_malformed_device_id() {
+        let device = DeviceCredentials::generate().unwrap();
+        let result = create_auth_request("device|123", b"nonce", &device.dilithium_secret_key);
+        assert!(matches!(result, Err(PqcError::InvalidInput(_))));
+        
+        let forged = AuthRequest { device_id: "device|123".to_string(), nonce: b"nonce".to_vec(), signature: vec![0; 16], extensions: Extensions::new() };
+        assert!(matches!(verify_auth_request(&forged, &device.dilithium_public_key), Err(PqcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_deserializers_enforce_limits() {
+        let device = DeviceCredentials::generate().unwrap();
+        let request = create_auth_request("device-1", b"nonce", &device.dilithium_secret_key).unwrap();
+        assert!(AuthRequest::from_bytes(&request.to_bytes().unwrap()).is_ok());
+
+        let oversized = vec![b' '; validation::MAX_MESSAGE_LEN + 1];
+        assert!(matches!(AuthRequest::from_bytes(&oversized), Err(PqcError::InvalidInput(_))));
+        let padded = AuthRequest { signature: vec![0; 64 * 1024], ..request };
+        assert!(AuthRequest::from_bytes(&padded.to_bytes().unwrap()).is_err());
+        // Within the largest algorithm's limit but beyond the suite's own
+        let max = CipherSuite::DEFAULT.sig.signature_len().unwrap();
+        let padded = AuthRequest { signature: vec![0; max + 1], ..padded };
+        assert!(matches!(verify_auth_request(&padded, &device.dilithium_public_key), Err(PqcError::InvalidInput(_))));
+
+        let encrypted = encrypt_entropy_for_device(b"entropy", &device.kyber_public_key).unwrap();
+        assert!(EncryptedEntropy::from_bytes(&encrypted.to_bytes().unwrap()).is_ok());
+        let truncated = EncryptedEntropy { ciphertext: encrypted.ciphertext[1..].to_vec(), ..encrypted };
+        assert!(EncryptedEntropy::from_bytes(&truncated.to_bytes().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inspect_before_decrypting() {
+        let device = DeviceCredentials::generate().unwrap();
+        let other = DeviceCredentials::generate().unwrap();
+        let encrypted = encrypt_entropy_bound(b"entropy for sensor-7", &device.kyber_public_key).unwrap();
+
+        let inspected = decrypt_entropy_inspect(&encrypted, &device.kyber_public_key).unwrap();
+        assert!(inspected.metadata().recipient_bound);
+        assert_eq!(inspected.metadata().plaintext_len, b"entropy for sensor-7".len());
+        assert_eq!(inspected.decrypt(&device.kyber_secret_key).unwrap(), b"entropy for sensor-7");
+        assert!(matches!(decrypt_entropy_inspect(&encrypted, &other.kyber_public_key), Err(PqcError::Decryption(_))));
+
+        // The header is authenticated: a rewritten marker fails at the AEAD
+        let relabelled = EncryptedEntropy { fips_mode: !encrypted.fips_mode, ..EncryptedEntropy::from_bytes(&encrypted.to_bytes().unwrap()).unwrap() };
+        let inspected = decrypt_entropy_inspect(&relabelled, &device.kyber_public_key).unwrap();
+        assert!(inspected.decrypt(&device.kyber_secret_key).is_err());
+
+        // Legacy envelopes have no recipient to check but still open
+        let legacy = encrypt_legacy(&device);
+        let inspected = decrypt_entropy_inspect_with_suite(&legacy, &other.kyber_public_key, &CipherSuite::DEFAULT).unwrap();
+        assert!(!inspected.metadata().recipient_bound && !inspected.metadata().suite_recorded);
+        assert_eq!(inspected.decrypt(&device.kyber_secret_key).unwrap(), b"old");
+    }
+
+    /// How releases before recipient binding opened an envelope: no AAD
+    fn open_like_old_reader(encrypted: &EncryptedEntropy, device: &DeviceCredentials) -> Result<Vec<u8>> {
+        let shared_secret = kem::decapsulate_with(CipherSuite::DEFAULT.kem, &device.kyber_secret_key, &encrypted.ciphertext)?;
+        CipherSuite::DEFAULT.aead.open(&kdf::envelope_key(&shared_secret)?, &encrypted.encrypted_data)
+    }
+
+    #[test]
+    fn test_recipient_binding_is_opt_in() {
+        let device = DeviceCredentials::generate().unwrap();
+        let unbound = encrypt_entropy_with_suite(b"entropy", &device.kyber_public_key, &CipherSuite::DEFAULT).unwrap();
+        let bound = encrypt_entropy_bound_with_suite(b"entropy", &device.kyber_public_key, &CipherSuite::DEFAULT).unwrap();
+        assert!(unbound.recipient.is_none());
+        assert_eq!(bound.recipient, Some(key_fingerprint(&device.kyber_public_key)));
+
+        // Old reader, new writer: only the bound envelope needs an upgraded reader
+        assert_eq!(open_like_old_reader(&unbound, &device).unwrap(), b"entropy");
+        assert!(matches!(open_like_old_reader(&bound, &device), Err(PqcError::Decryption(_))));
+        assert_eq!(decrypt_entropy_with_suite(&bound, &device.kyber_secret_key, &CipherSuite::DEFAULT).unwrap(), b"entropy");
+
+        // New reader, old writer: a fixed payload sealed with no AAD, as earlier releases did
+        let shared_secret = [0x42u8; 32];
+        let old_payload = hex::decode(OLD_WRITER_PAYLOAD).unwrap();
+        let key = kdf::envelope_key(&shared_secret).unwrap();
+        let old = EncryptedEntropy { ciphertext: Vec::new(), encrypted_data: old_payload, suite: None, fips_mode: false, label: None, recipient: None };
+        assert_eq!(CipherSuite::DEFAULT.aead.open_with_aad(&key, &old.encrypted_data, &old.aad().unwrap()).unwrap(), b"entropy");
+    }
+
+    /// `aead.seal(envelope_key([0x42; 32]), b"entropy")` from before recipient binding
+    const OLD_WRITER_PAYLOAD: &str = "9e242a3b85a5dae93336b2c2395f3902f828e0f225fb29bff8948090a41c4d6457e2c7";
+
+    fn encrypt_legacy(device: &DeviceCredentials) -> EncryptedEntropy {
+        let (ciphertext, shared_secret) = kem::encapsulate_with(CipherSuite::DEFAULT.kem, &device.kyber_public_key).unwrap();
+        let encrypted_data = CipherSuite::DEFAULT.aead.seal(&kdf::envelope_key(&shared_secret).unwrap(), b"old").unwrap();
+        EncryptedEntropy { ciphertext, encrypted_data, suite: None, fips_mode: false, label: None, recipient: None }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,648 @@
+//! Signed archive manifests, verified file by file as the archive streams in
+//!
+//! An [`ArchiveManifest`] lists every file of an OTA bundle with its size
+//! and SHA-256 and is signed with the active suite's signature algorithm.
+//! A device first checks the manifest with [`ArchiveVerifier::new`], then
+//! feeds each file through [`ArchiveVerifier::begin_file`],
+//! [`ArchiveVerifier::update`] and [`ArchiveVerifier::finish_file`] as it
+//! arrives. Only one hash state is
+//! held at a time, so memory use does not depend on file sizes, and a file
+//! that grows past its recorded size is rejected at the first extra byte.
+//!
+//! With the `tar` or `zip` feature, [`sign_dir`] packs a directory into a
+//! `.tar` or `.zip` bundle with the signed manifest embedded as its first
+//! entry, [`MANIFEST_NAME`], and [`verify`] checks such a bundle.
+
+use std::collections::BTreeSet;
+use std::io::Read;
+#[cfg(any(feature = "tar", feature = "zip"))]
+use std::{fs, path::{Path, PathBuf}};
+#[cfg(any(feature = "tar", feature = "zip"))]
+use crate::validation::MAX_MESSAGE_LEN;
+use serde::{Deserialize, Serialize};
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::hash::{HashAlgorithm, Hasher};
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, Validate};
+use crate::{canonical, fips};
+
+/// Domain label for archive manifest signatures
+const ARCHIVE_DOMAIN: &str = "PQC-Algo/v1/archive-manifest";
+
+/// Most files one manifest may list
+pub const MAX_ARCHIVE_ENTRIES: usize = 4096;
+
+/// Read buffer of [`ArchiveVerifier::verify_reader`]
+const READ_CHUNK: usize = 4096;
+
+/// One file of an archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Relative path inside the archive, `/`-separated
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the contents
+    pub sha256: String,
+}
+
+impl ArchiveEntry {
+    pub fn new(path: &str, contents: &[u8]) -> Self {
+        Self {
+            path: path.to_string(),
+            size: contents.len() as u64,
+            sha256: hex::encode(crate::hash::sha256(&[contents])),
+        }
+    }
+}
+
+/// Signed list of the files in an archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub entries: Vec<ArchiveEntry>,
+    pub signer_fingerprint: String,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of an [`ArchiveManifest`] covered by its signature
+#[derive(Serialize)]
+struct ArchiveFields<'a> {
+    entries: &'a [ArchiveEntry],
+    signer_fingerprint: &'a str,
+    algorithm: &'a str,
+}
+
+impl ArchiveManifest {
+    /// Sign with [`CipherSuite::active_default`]'s signature algorithm
+    pub fn sign(entries: Vec<ArchiveEntry>, secret_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        Self::sign_with(CipherSuite::active_default().sig, entries, secret_key, public_key)
+    }
+
+    pub fn sign_with(algorithm: SigAlgorithm, entries: Vec<ArchiveEntry>, secret_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        fips::check_sig(algorithm)?;
+        let mut manifest = Self { entries, signer_fingerprint: key_fingerprint(public_key), algorithm, signature: Vec::new() };
+        manifest.check_entries()?;
+        manifest.signature = sign::sign_with(algorithm, &manifest.signing_bytes()?, secret_key)?;
+        Ok(manifest)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode archive manifest: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "archive manifest")
+    }
+
+    pub fn entry(&self, path: &str) -> Option<&ArchiveEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Sum of all file sizes
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(ARCHIVE_DOMAIN, &ArchiveFields {
+            entries: &self.entries,
+            signer_fingerprint: &self.signer_fingerprint,
+            algorithm: self.algorithm.name(),
+        })
+    }
+
+    fn check_entries(&self) -> Result<()> {
+        validation::check_max_len("Archive manifest entries", self.entries.len(), MAX_ARCHIVE_ENTRIES)?;
+        let mut seen = BTreeSet::new();
+        for entry in &self.entries {
+            check_path(&entry.path)?;
+            validation::check_fingerprint("Archive entry SHA-256", &entry.sha256)?;
+            if !seen.insert(entry.path.as_str()) {
+                return Err(PqcError::InvalidInput(format!("{} is listed twice", entry.path)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for ArchiveManifest {
+    fn validate(&self) -> Result<()> {
+        self.check_entries()?;
+        validation::check_fingerprint("Archive signer fingerprint", &self.signer_fingerprint)?;
+        validation::check_signature_len("Archive signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// Relative, `/`-separated, and unable to climb out of the install directory
+fn check_path(path: &str) -> Result<()> {
+    let bad = path.is_empty()
+        || path.len() > validation::MAX_LABEL_LEN
+        || path.starts_with('/')
+        || path.contains('\\')
+        || path.chars().any(char::is_control)
+        || path.split('/').any(|part| part.is_empty() || part == "." || part == "..");
+    if bad {
+        return Err(PqcError::InvalidInput(format!("Archive path {:?} is not a plain relative path", path)));
+    }
+    Ok(())
+}
+
+/// Outcome for one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    Verified,
+    /// Stopped at the first byte past the recorded size, or ended short of it
+    SizeMismatch { expected: u64, actual: u64 },
+    DigestMismatch,
+    /// Not listed in the manifest
+    Unexpected,
+    /// Received more than once
+    Duplicate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileResult {
+    pub path: String,
+    #[serde(flatten)]
+    pub status: FileStatus,
+}
+
+impl FileResult {
+    pub fn is_verified(&self) -> bool {
+        self.status == FileStatus::Verified
+    }
+}
+
+/// File being hashed
+struct OpenFile {
+    index: usize,
+    hasher: Hasher,
+    received: u64,
+}
+
+/// Checks an archive against its manifest as the files arrive
+pub struct ArchiveVerifier {
+    manifest: ArchiveManifest,
+    current: Option<OpenFile>,
+    /// Indices of entries that already have a result
+    done: BTreeSet<usize>,
+    results: Vec<FileResult>,
+}
+
+impl ArchiveVerifier {
+    /// Parse `manifest` and verify its signature under `trusted_public_key`
+    pub fn new(manifest: &[u8], trusted_public_key: &[u8]) -> Result<Self> {
+        let manifest = ArchiveManifest::from_bytes(manifest)?;
+        fips::check_sig(manifest.algorithm)?;
+        if !ct::ct_eq_hex(&manifest.signer_fingerprint, &key_fingerprint(trusted_public_key))
+            || !sign::verify_with(manifest.algorithm, &manifest.signing_bytes()?, &manifest.signature, trusted_public_key)?
+        {
+            return Err(PqcError::Verification("Archive manifest signature is invalid".to_string()));
+        }
+        Ok(Self { manifest, current: None, done: BTreeSet::new(), results: Vec::new() })
+    }
+
+    pub fn manifest(&self) -> &ArchiveManifest {
+        &self.manifest
+    }
+
+    /// Start receiving `path`
+    ///
+    /// A path the manifest does not list, or one already received, fails
+    /// here, before any of its bytes are accepted, and is recorded as
+    /// [`FileStatus::Unexpected`] or [`FileStatus::Duplicate`].
+    pub fn begin_file(&mut self, path: &str) -> Result<()> {
+        if let Some(open) = &self.current {
+            return Err(PqcError::InvalidInput(format!("{} is still open", self.manifest.entries[open.index].path)));
+        }
+        let Some(index) = self.manifest.entries.iter().position(|entry| entry.path == path) else {
+            self.results.push(FileResult { path: path.to_string(), status: FileStatus::Unexpected });
+            return Err(PqcError::Verification(format!("{} is not in the archive manifest", path)));
+        };
+        if self.done.contains(&index) {
+            self.results.push(FileResult { path: path.to_string(), status: FileStatus::Duplicate });
+            return Err(PqcError::Verification(format!("{} was already received", path)));
+        }
+        self.current = Some(OpenFile { index, hasher: Hasher::new(HashAlgorithm::Sha256), received: 0 });
+        Ok(())
+    }
+
+    /// Hash the next bytes of the open file
+    ///
+    /// Fails as soon as the file exceeds its recorded size; the file is then
+    /// closed with a [`FileStatus::SizeMismatch`] result.
+    pub fn update(&mut self, data: &[u8]) -> Result<()> {
+        let open = self.current.as_mut()
+            .ok_or_else(|| PqcError::InvalidInput("No archive file is open".to_string()))?;
+        let entry = &self.manifest.entries[open.index];
+        open.received += data.len() as u64;
+        if open.received > entry.size {
+            let result = FileResult {
+                path: entry.path.clone(),
+                status: FileStatus::SizeMismatch { expected: entry.size, actual: open.received },
+            };
+            self.done.insert(open.index);
+            self.current = None;
+            self.results.push(result);
+            return Err(PqcError::Verification(format!("{} is longer than the manifest allows", entry.path)));
+        }
+        open.hasher.update(data);
+        Ok(())
+    }
+
+    /// Close the open file and return its result
+    pub fn finish_file(&mut self) -> Result<FileResult> {
+        let open = self.current.take()
+            .ok_or_else(|| PqcError::InvalidInput("No archive file is open".to_string()))?;
+        let entry = &self.manifest.entries[open.index];
+        let status = if open.received != entry.size {
+            FileStatus::SizeMismatch { expected: entry.size, actual: open.received }
+        } else if !ct::ct_eq_hex(&hex::encode(open.hasher.finalize()), &entry.sha256) {
+            FileStatus::DigestMismatch
+        } else {
+            FileStatus::Verified
+        };
+        let result = FileResult { path: entry.path.clone(), status };
+        self.done.insert(open.index);
+        self.results.push(result.clone());
+        Ok(result)
+    }
+
+    /// Receive a whole file from `reader` through a small fixed buffer
+    pub fn verify_reader<R: Read>(&mut self, path: &str, mut reader: R) -> Result<FileResult> {
+        self.begin_file(path)?;
+        let mut buffer = [0u8; READ_CHUNK];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(read) => read,
+                Err(e) => {
+                    self.current = None;
+                    return Err(PqcError::Io(format!("Failed to read {}: {}", path, e)));
+                }
+            };
+            if read == 0 {
+                return self.finish_file();
+            }
+            if let Err(e) = self.update(&buffer[..read]) {
+                return match self.results.last() {
+                    Some(result) if result.path == path => Ok(result.clone()),
+                    _ => Err(e),
+                };
+            }
+        }
+    }
+
+    /// Results so far, in the order the files arrived
+    pub fn results(&self) -> &[FileResult] {
+        &self.results
+    }
+
+    /// Finish the archive; succeeds only if every listed file arrived and verified
+    pub fn finish(self) -> Result<Vec<FileResult>> {
+        if let Some(open) = &self.current {
+            return Err(PqcError::Verification(format!("{} was not finished", self.manifest.entries[open.index].path)));
+        }
+        if let Some(failed) = self.results.iter().find(|result| !result.is_verified()) {
+            return Err(PqcError::Verification(format!("{} failed verification: {:?}", failed.path, failed.status)));
+        }
+        let missing: Vec<&str> = self.manifest.entries.iter()
+            .enumerate()
+            .filter(|(index, _)| !self.done.contains(index))
+            .map(|(_, entry)| entry.path.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(PqcError::Verification(format!("Archive is missing {}", missing.join(", "))));
+        }
+        Ok(self.results)
+    }
+}
+
+/// Name of the embedded manifest inside a bundle
+pub const MANIFEST_NAME: &str = ".pqc-manifest.json";
+
+/// Pack `dir` into a bundle at `out` with an embedded signed manifest
+///
+/// The format follows the extension of `out`, `.tar` or `.zip`. Regular
+/// files are included recursively in sorted order; symbolic links and other
+/// special files are refused. A [`MANIFEST_NAME`] left at the top of `dir`
+/// by an earlier extraction is skipped. Each file is read once, and the
+/// bytes that were hashed are the bytes packed.
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub fn sign_dir(dir: &Path, out: &Path, secret_key: &[u8], public_key: &[u8]) -> Result<ArchiveManifest> {
+    sign_dir_with(CipherSuite::active_default().sig, dir, out, secret_key, public_key)
+}
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub fn sign_dir_with(algorithm: SigAlgorithm, dir: &Path, out: &Path, secret_key: &[u8], public_key: &[u8]) -> Result<ArchiveManifest> {
+    let format = BundleFormat::for_path(out)?;
+    let files = collect_files(dir)?
+        .into_iter()
+        .map(|(path, full)| PackedFile::read(path, &full))
+        .collect::<Result<Vec<_>>>()?;
+    let entries = files.iter().map(|file| ArchiveEntry::new(&file.path, &file.contents)).collect();
+    let manifest = ArchiveManifest::sign_with(algorithm, entries, secret_key, public_key)?;
+    let manifest_bytes = manifest.to_bytes()?;
+
+    let output = fs::File::create(out).map_err(|e| io_error("create", out, e))?;
+    let written = match format {
+        #[cfg(feature = "tar")]
+        BundleFormat::Tar => write_tar(output, &manifest_bytes, &files),
+        #[cfg(feature = "zip")]
+        BundleFormat::Zip => write_zip(output, &manifest_bytes, &files),
+    };
+    written.map_err(|e| io_error("write", out, e))?;
+    Ok(manifest)
+}
+
+/// Check every file of a bundle written by [`sign_dir`]
+///
+/// Returns the per-file results when the manifest is signed by
+/// `trusted_public_key` and every listed file, and nothing else, is present
+/// and intact.
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub fn verify(path: &Path, trusted_public_key: &[u8]) -> Result<Vec<FileResult>> {
+    let format = BundleFormat::for_path(path)?;
+    let file = fs::File::open(path).map_err(|e| io_error("read", path, e))?;
+    match format {
+        #[cfg(feature = "tar")]
+        BundleFormat::Tar => verify_tar(file, trusted_public_key),
+        #[cfg(feature = "zip")]
+        BundleFormat::Zip => verify_zip(file, trusted_public_key),
+    }
+}
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+enum BundleFormat {
+    #[cfg(feature = "tar")]
+    Tar,
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+impl BundleFormat {
+    fn for_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "tar")]
+            Some("tar") => Ok(BundleFormat::Tar),
+            #[cfg(feature = "zip")]
+            Some("zip") => Ok(BundleFormat::Zip),
+            _ => Err(PqcError::InvalidInput(format!(
+                "{} is not a bundle format this build supports", path.display()
+            ))),
+        }
+    }
+}
+
+/// A file's contents as hashed into the manifest and written to the bundle
+#[cfg(any(feature = "tar", feature = "zip"))]
+struct PackedFile {
+    path: String,
+    contents: Vec<u8>,
+    mode: u32,
+}
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+impl PackedFile {
+    fn read(path: String, full: &Path) -> Result<Self> {
+        let mut file = fs::File::open(full).map_err(|e| io_error("read", full, e))?;
+        let metadata = file.metadata().map_err(|e| io_error("read", full, e))?;
+        let mut contents = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut contents).map_err(|e| io_error("read", full, e))?;
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777;
+        #[cfg(not(unix))]
+        let mode = 0o644;
+        Ok(Self { path, contents, mode })
+    }
+}
+
+/// Regular files under `dir` as (archive path, file path), sorted, without a top-level [`MANIFEST_NAME`]
+#[cfg(any(feature = "tar", feature = "zip"))]
+fn collect_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![(String::new(), dir.to_path_buf())];
+    while let Some((prefix, current)) = pending.pop() {
+        let listing = fs::read_dir(&current).map_err(|e| io_error("read", &current, e))?;
+        for item in listing {
+            let item = item.map_err(|e| io_error("read", &current, e))?;
+            let name = item.file_name().into_string()
+                .map_err(|name| PqcError::InvalidInput(format!("{:?} is not valid UTF-8", name)))?;
+            let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            let kind = item.file_type().map_err(|e| io_error("read", &item.path(), e))?;
+            if path == MANIFEST_NAME {
+                continue;
+            }
+            if kind.is_dir() {
+                pending.push((path, item.path()));
+            } else if kind.is_file() {
+                files.push((path, item.path()));
+            } else {
+                return Err(PqcError::InvalidInput(format!("{} is not a regular file", item.path().display())));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(feature = "tar")]
+fn write_tar(output: fs::File, manifest: &[u8], files: &[PackedFile]) -> std::io::Result<()> {
+    let mut builder = tar::Builder::new(output);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_NAME, manifest)?;
+    for file in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(file.contents.len() as u64);
+        header.set_mode(file.mode);
+        header.set_cksum();
+        builder.append_data(&mut header, &file.path, file.contents.as_slice())?;
+    }
+    builder.into_inner()?.sync_all()
+}
+
+#[cfg(feature = "tar")]
+fn verify_tar(file: fs::File, trusted_public_key: &[u8]) -> Result<Vec<FileResult>> {
+    let malformed = |e: std::io::Error| PqcError::InvalidInput(format!("Malformed tar bundle: {}", e));
+    let mut archive = tar::Archive::new(file);
+    let mut entries = archive.entries().map_err(malformed)?;
+    let mut first = entries.next()
+        .ok_or_else(|| PqcError::InvalidInput("Bundle is empty".to_string()))?
+        .map_err(malformed)?;
+    if first.path_bytes().as_ref() != MANIFEST_NAME.as_bytes() {
+        return Err(PqcError::Verification(format!("Bundle does not start with {}", MANIFEST_NAME)));
+    }
+    let mut manifest = Vec::new();
+    first.by_ref().take(MAX_MESSAGE_LEN as u64 + 1).read_to_end(&mut manifest).map_err(malformed)?;
+    let mut verifier = ArchiveVerifier::new(&manifest, trusted_public_key)?;
+
+    for entry in entries {
+        let entry = entry.map_err(malformed)?;
+        let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => record(verifier.verify_reader(&path, entry))?,
+            _ => {
+                return Err(PqcError::Verification(format!("{} is not a regular file", path)));
+            }
+        }
+    }
+    verifier.finish()
+}
+
+#[cfg(feature = "zip")]
+fn write_zip(output: fs::File, manifest: &[u8], files: &[PackedFile]) -> std::io::Result<()> {
+    use std::io::Write;
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut writer = zip::ZipWriter::new(output);
+    writer.start_file(MANIFEST_NAME, options)?;
+    writer.write_all(manifest)?;
+    for file in files {
+        writer.start_file(file.path.as_str(), options.unix_permissions(file.mode))?;
+        writer.write_all(&file.contents)?;
+    }
+    writer.finish()?.sync_all()
+}
+
+#[cfg(feature = "zip")]
+fn verify_zip(file: fs::File, trusted_public_key: &[u8]) -> Result<Vec<FileResult>> {
+    let malformed = |e: zip::result::ZipError| PqcError::InvalidInput(format!("Malformed zip bundle: {}", e));
+    let mut archive = zip::ZipArchive::new(file).map_err(malformed)?;
+    let mut manifest = Vec::new();
+    archive.by_name(MANIFEST_NAME).map_err(malformed)?
+        .take(MAX_MESSAGE_LEN as u64 + 1)
+        .read_to_end(&mut manifest)
+        .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", MANIFEST_NAME, e)))?;
+    let mut verifier = ArchiveVerifier::new(&manifest, trusted_public_key)?;
+
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(malformed)?;
+        let path = entry.name().to_string();
+        if path == MANIFEST_NAME || entry.is_dir() {
+            continue;
+        }
+        if !entry.is_file() || entry.is_symlink() {
+            return Err(PqcError::Verification(format!("{} is not a regular file", path)));
+        }
+        record(verifier.verify_reader(&path, entry))?;
+    }
+    verifier.finish()
+}
+
+/// Keep going past files that failed; [`ArchiveVerifier::finish`] reports them
+#[cfg(any(feature = "tar", feature = "zip"))]
+fn record(result: Result<FileResult>) -> Result<()> {
+    match result {
+        Ok(_) | Err(PqcError::Verification(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> PqcError {
+    PqcError::Io(format!("Failed to {} {}: {}", action, path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(files: &[(&str, &[u8])]) -> (Vec<u8>, sign::DilithiumKeyPair) {
+        let keypair = sign::generate_keypair().unwrap();
+        let entries = files.iter().map(|(path, contents)| ArchiveEntry::new(path, contents)).collect();
+        let manifest = ArchiveManifest::sign(entries, &keypair.secret_key, &keypair.public_key).unwrap();
+        (manifest.to_bytes().unwrap(), keypair)
+    }
+
+    #[test]
+    fn test_streams_and_verifies_each_file() {
+        let kernel = vec![0x11u8; 10_000];
+        let (manifest, keypair) = signed(&[("boot/kernel.bin", &kernel), ("etc/version", b"2.4.1")]);
+        let mut verifier = ArchiveVerifier::new(&manifest, &keypair.public_key).unwrap();
+
+        verifier.begin_file("boot/kernel.bin").unwrap();
+        for chunk in kernel.chunks(777) {
+            verifier.update(chunk).unwrap();
+        }
+        assert!(verifier.finish_file().unwrap().is_verified());
+        assert!(verifier.verify_reader("etc/version", &b"2.4.1"[..]).unwrap().is_verified());
+        assert_eq!(verifier.finish().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_reports_bad_files() {
+        let (manifest, keypair) = signed(&[("a", b"alpha"), ("b", b"bravo"), ("c", b"charlie")]);
+        let mut verifier = ArchiveVerifier::new(&manifest, &keypair.public_key).unwrap();
+
+        let result = verifier.verify_reader("a", &b"alphabet"[..]).unwrap();
+        assert_eq!(result.status, FileStatus::SizeMismatch { expected: 5, actual: 8 });
+        assert_eq!(verifier.verify_reader("b", &b"BRAVO"[..]).unwrap().status, FileStatus::DigestMismatch);
+        assert!(verifier.begin_file("b").is_err());
+        assert_eq!(verifier.results().last().unwrap().status, FileStatus::Duplicate);
+        assert!(verifier.begin_file("evil.sh").is_err());
+        assert_eq!(verifier.results().last().unwrap().status, FileStatus::Unexpected);
+        assert!(verifier.finish().is_err());
+
+        let mut incomplete = ArchiveVerifier::new(&manifest, &keypair.public_key).unwrap();
+        incomplete.verify_reader("a", &b"alpha"[..]).unwrap();
+        assert!(matches!(incomplete.finish(), Err(PqcError::Verification(message)) if message.contains("b, c")));
+    }
+
+    #[test]
+    fn test_rejects_forged_manifest_and_unsafe_paths() {
+        let (manifest, keypair) = signed(&[("a", b"alpha")]);
+        let other = sign::generate_keypair().unwrap();
+        assert!(ArchiveVerifier::new(&manifest, &other.public_key).is_err());
+        let tampered = String::from_utf8(manifest).unwrap().replace("\"size\":5", "\"size\":6");
+        assert!(ArchiveVerifier::new(tampered.as_bytes(), &keypair.public_key).is_err());
+
+        for path in ["../etc/passwd", "/etc/passwd", "a//b", "a\\b", ""] {
+            let entries = vec![ArchiveEntry::new(path, b"x")];
+            assert!(ArchiveManifest::sign(entries, &keypair.secret_key, &keypair.public_key).is_err(), "{}", path);
+        }
+    }
+
+    #[cfg(any(feature = "tar", feature = "zip"))]
+    #[test]
+    fn test_bundles_round_trip_and_catch_tampering() {
+        let dir = std::env::temp_dir().join(format!("pqc_archive_bundle_{}", std::process::id()));
+        fs::create_dir_all(dir.join("src/tools")).unwrap();
+        fs::write(dir.join("README"), b"factory toolchain").unwrap();
+        fs::write(dir.join("src/tools/flash.py"), vec![b'#'; 20_000]).unwrap();
+        // Left over from extracting an earlier bundle
+        fs::write(dir.join(MANIFEST_NAME), b"{}").unwrap();
+        let keypair = sign::generate_keypair().unwrap();
+        let other = sign::generate_keypair().unwrap();
+
+        let formats: Vec<&str> = vec![
+            #[cfg(feature = "tar")]
+            "tar",
+            #[cfg(feature = "zip")]
+            "zip",
+        ];
+        for extension in formats {
+            let bundle = dir.with_extension(extension);
+            let manifest = sign_dir(&dir, &bundle, &keypair.secret_key, &keypair.public_key).unwrap();
+            assert_eq!(manifest.entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["README", "src/tools/flash.py"]);
+            assert_eq!(verify(&bundle, &keypair.public_key).unwrap().len(), 2);
+            assert!(verify(&bundle, &other.public_key).is_err());
+
+            let mut bytes = fs::read(&bundle).unwrap();
+            let middle = bytes.len() / 3;
+            bytes[middle] ^= 0x20;
+            fs::write(&bundle, bytes).unwrap();
+            assert!(verify(&bundle, &keypair.public_key).is_err());
+            fs::remove_file(&bundle).unwrap();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+//! Attestation of keys generated inside a secure element
+//!
+//! A secure element that generates a device's Kyber key pair can sign a
+//! [`KeyAttestation`]: "this public key was generated in hardware X and
+//! cannot be exported". It signs with its own attestation key, which the
+//! hardware vendor certified at manufacture, so the provisioning service
+//! checks the statement against the vendor's [`TrustAnchors`] rather than
+//! trusting the device.
+//!
+//! Once checked, the statement is recorded in the device's certificate as
+//! the non-critical [`ATTESTATION_EXTENSION`]. Relying parties that require
+//! hardware-backed keys read it back with [`AttestationRecord::from_certificate`].
+
+use serde::{Deserialize, Serialize};
+use crate::cert::{self, Certificate, TrustAnchors};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::extensions::Extensions;
+use crate::kem::KemAlgorithm;
+use crate::secret::SecretKey;
+use crate::sign::{self, SigAlgorithm};
+use crate::util::ct;
+use crate::validation::{self, Validate};
+use crate::{canonical, fips};
+
+/// Domain label for key attestation signatures
+const ATTESTATION_DOMAIN: &str = "PQC-Algo/v1/key-attestation";
+
+/// Certificate extension holding an [`AttestationRecord`]
+///
+/// A UUID-based identifier under the `2.25` arc, which needs no registration.
+pub const ATTESTATION_EXTENSION: &str = "2.25.121516043083133263925399004165043621233";
+
+/// The attestation key inside a secure element
+pub trait AttestationKey {
+    /// The vendor-issued certificate for this key, leaf first, then any intermediates
+    fn certificate_chain(&self) -> &[Certificate];
+
+    fn algorithm(&self) -> SigAlgorithm;
+
+    /// Sign `message` inside the element
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// An attestation key held in memory, for simulators and tests
+pub struct SoftwareAttestationKey {
+    chain: Vec<Certificate>,
+    algorithm: SigAlgorithm,
+    secret_key: SecretKey,
+}
+
+impl SoftwareAttestationKey {
+    pub fn new(chain: Vec<Certificate>, algorithm: SigAlgorithm, secret_key: SecretKey) -> Result<Self> {
+        if chain.is_empty() {
+            return Err(PqcError::InvalidInput("Attestation key needs a certificate".to_string()));
+        }
+        Ok(Self { chain, algorithm, secret_key })
+    }
+}
+
+impl AttestationKey for SoftwareAttestationKey {
+    fn certificate_chain(&self) -> &[Certificate] {
+        &self.chain
+    }
+
+    fn algorithm(&self) -> SigAlgorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        sign::sign_with(self.algorithm, message, &self.secret_key)
+    }
+}
+
+/// A secure element's signed statement about a KEM key it generated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyAttestation {
+    pub kem_algorithm: KemAlgorithm,
+    /// Hex SHA-256 of the attested public key
+    pub key_fingerprint: String,
+    /// Hardware model as the vendor names it
+    pub hardware: String,
+    /// The secret key cannot leave the element
+    pub non_exportable: bool,
+    pub attested_at: u64,
+    /// Vendor certificate for the attestation key, leaf first
+    pub certificate_chain: Vec<Certificate>,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`KeyAttestation`] covered by its signature
+#[derive(Serialize)]
+struct AttestationFields<'a> {
+    kem_algorithm: &'a str,
+    key_fingerprint: &'a str,
+    hardware: &'a str,
+    non_exportable: bool,
+    attested_at: u64,
+    attestation_key_fingerprint: String,
+    algorithm: &'a str,
+}
+
+impl KeyAttestation {
+    /// Attest `public_key` with the element's attestation key
+    pub fn sign<K: AttestationKey>(key: &K, kem_algorithm: KemAlgorithm, public_key: &[u8], hardware: &str, non_exportable: bool) -> Result<Self> {
+        fips::check_sig(key.algorithm())?;
+        let mut attestation = Self {
+            kem_algorithm,
+            key_fingerprint: key_fingerprint(public_key),
+            hardware: hardware.to_string(),
+            non_exportable,
+            attested_at: unix_now(),
+            certificate_chain: key.certificate_chain().to_vec(),
+            algorithm: key.algorithm(),
+            signature: Vec::new(),
+        };
+        attestation.validate_fields()?;
+        attestation.signature = key.sign(&attestation.signing_bytes()?)?;
+        Ok(attestation)
+    }
+
+    /// Check the statement covers `public_key` and is signed by a key `vendors` certified
+    pub fn verify(&self, public_key: &[u8], vendors: &TrustAnchors) -> Result<()> {
+        fips::check_sig(self.algorithm)?;
+        self.validate()?;
+        if !ct::ct_eq_hex(&self.key_fingerprint, &key_fingerprint(public_key)) {
+            return Err(PqcError::Verification("Attestation is for a different key".to_string()));
+        }
+        let (leaf, intermediates) = self.certificate_chain.split_first()
+            .ok_or_else(|| PqcError::Verification("Attestation carries no certificate".to_string()))?;
+        cert::verify_chain(leaf, intermediates, vendors)?;
+        if !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, &leaf.public_key)? {
+            return Err(PqcError::Verification(format!("Attestation from {} is not signed by its certified key", leaf.subject)));
+        }
+        Ok(())
+    }
+
+    /// What gets recorded in the device certificate
+    pub fn record(&self) -> AttestationRecord {
+        AttestationRecord {
+            kem_algorithm: self.kem_algorithm,
+            key_fingerprint: self.key_fingerprint.clone(),
+            hardware: self.hardware.clone(),
+            non_exportable: self.non_exportable,
+            attested_at: self.attested_at,
+            attestation_key_fingerprint: self.attestation_key_fingerprint(),
+        }
+    }
+
+    fn attestation_key_fingerprint(&self) -> String {
+        self.certificate_chain.first().map(Certificate::fingerprint).unwrap_or_default()
+    }
+
+    fn validate_fields(&self) -> Result<()> {
+        validation::check_max_len("Attested hardware", self.hardware.len(), validation::MAX_LABEL_LEN)?;
+        validation::check_fingerprint("Attested key fingerprint", &self.key_fingerprint)?;
+        validation::check_max_len("Attestation chain", self.certificate_chain.len(), cert::MAX_CHAIN_LEN)?;
+        self.certificate_chain.iter().try_for_each(Validate::validate)
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(ATTESTATION_DOMAIN, &AttestationFields {
+            kem_algorithm: self.kem_algorithm.name(),
+            key_fingerprint: &self.key_fingerprint,
+            hardware: &self.hardware,
+            non_exportable: self.non_exportable,
+            attested_at: self.attested_at,
+            attestation_key_fingerprint: self.attestation_key_fingerprint(),
+            algorithm: self.algorithm.name(),
+        })
+    }
+}
+
+impl Validate for KeyAttestation {
+    fn validate(&self) -> Result<()> {
+        self.validate_fields()?;
+        validation::check_signature_len("Attestation signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// A verified attestation as recorded in a device certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationRecord {
+    pub kem_algorithm: KemAlgorithm,
+    pub key_fingerprint: String,
+    pub hardware: String,
+    pub non_exportable: bool,
+    pub attested_at: u64,
+    pub attestation_key_fingerprint: String,
+}
+
+impl AttestationRecord {
+    /// Add this record to `extensions` as [`ATTESTATION_EXTENSION`]
+    pub fn insert_into(&self, extensions: &mut Extensions) -> Result<()> {
+        extensions.insert(ATTESTATION_EXTENSION, false, &canonical::to_canonical_json(self)?)
+    }
+
+    /// The record in a certificate the caller has already verified, if it has one
+    pub fn from_certificate(certificate: &Certificate) -> Result<Option<Self>> {
+        certificate.extensions.get(ATTESTATION_EXTENSION)
+            .map(|extension| serde_json::from_slice(&extension.value)
+                .map_err(|e| PqcError::InvalidInput(format!("Malformed attestation record: {}", e))))
+            .transpose()
+    }
+
+    /// Whether the certificate attests that `kyber_public_key` never leaves its hardware
+    pub fn is_hardware_backed(certificate: &Certificate, kyber_public_key: &[u8]) -> bool {
+        matches!(Self::from_certificate(certificate), Ok(Some(record))
+            if record.non_exportable && ct::ct_eq_hex(&record.key_fingerprint, &key_fingerprint(kyber_public_key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::CertificateAuthority;
+    use crate::kem;
+    use crate::suite::CipherSuite;
+    use std::time::Duration;
+
+    const YEAR: Duration = Duration::from_secs(365 * 24 * 3600);
+
+    fn element(vendor: &CertificateAuthority) -> SoftwareAttestationKey {
+        let keypair = sign::generate_keypair_with(vendor.algorithm).unwrap();
+        let certificate = vendor.issue("SE-1000 #4711", &keypair.public_key, YEAR, false).unwrap();
+        SoftwareAttestationKey::new(vec![certificate], vendor.algorithm, keypair.secret_key.into()).unwrap()
+    }
+
+    #[test]
+    fn test_attestation_round_trip() {
+        let vendor = CertificateAuthority::generate("SE Vendor Root").unwrap();
+        let mut vendors = TrustAnchors::new();
+        vendors.add("SE Vendor Root", vendor.public_key());
+        let element = element(&vendor);
+        let keypair = kem::generate_keypair_with(CipherSuite::active_default().kem).unwrap();
+
+        let attestation = KeyAttestation::sign(&element, CipherSuite::active_default().kem, &keypair.public_key, "SE-1000", true).unwrap();
+        attestation.verify(&keypair.public_key, &vendors).unwrap();
+
+        let other = kem::generate_keypair_with(CipherSuite::active_default().kem).unwrap();
+        assert!(matches!(attestation.verify(&other.public_key, &vendors), Err(PqcError::Verification(_))));
+        let mut claimed = attestation.clone();
+        claimed.hardware = "SE-9000".to_string();
+        assert!(claimed.verify(&keypair.public_key, &vendors).is_err());
+        assert!(attestation.verify(&keypair.public_key, &TrustAnchors::new()).is_err());
+    }
+
+    #[test]
+    fn test_record_in_certificate() {
+        let vendor = CertificateAuthority::generate("SE Vendor Root").unwrap();
+        let keypair = kem::generate_keypair_with(CipherSuite::active_default().kem).unwrap();
+        let attestation = KeyAttestation::sign(&element(&vendor), CipherSuite::active_default().kem, &keypair.public_key, "SE-1000", true).unwrap();
+
+        let mut extensions = Extensions::new();
+        attestation.record().insert_into(&mut extensions).unwrap();
+        let device = sign::generate_keypair().unwrap();
+        let ca = CertificateAuthority::generate("Provisioning CA").unwrap();
+        let certificate = ca.issue_with_extensions("sensor-7", &device.public_key, YEAR, false, extensions).unwrap();
+
+        assert!(certificate.verify_signature(ca.public_key()).unwrap());
+        assert_eq!(AttestationRecord::from_certificate(&certificate).unwrap(), Some(attestation.record()));
+        assert!(AttestationRecord::is_hardware_backed(&certificate, &keypair.public_key));
+        let plain = ca.issue("sensor-8", &device.public_key, YEAR, false).unwrap();
+        assert!(!AttestationRecord::is_hardware_backed(&plain, &keypair.public_key));
+    }
+}
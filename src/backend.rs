@@ -0,0 +1,158 @@
+//! liboqs initialization and build information
+//!
+//! Every KEM and signature operation goes through [`init`], which runs
+//! `oqs::init()` exactly once per process and checks that the linked liboqs
+//! can actually serve this crate. A broken or stripped-down build then fails
+//! with one [`PqcError::BackendInit`] instead of an opaque error from the
+//! first algorithm that happens to be missing.
+//!
+//! Without the `liboqs` feature nothing is linked and [`init`] always
+//! fails, so only the fakes in `test_utils` can stand in for the primitives.
+
+#[cfg(feature = "liboqs")]
+use std::ffi::CStr;
+use std::sync::OnceLock;
+use serde::Serialize;
+use crate::error::{PqcError, Result};
+use crate::kem::KemAlgorithm;
+use crate::sign::SigAlgorithm;
+use crate::suite::CipherSuite;
+
+static INIT: OnceLock<std::result::Result<(), String>> = OnceLock::new();
+
+/// Initialize liboqs once; later calls return the first call's outcome
+pub fn init() -> Result<()> {
+    INIT.get_or_init(|| {
+        #[cfg(feature = "liboqs")]
+        oqs::init();
+        probe()
+    })
+    .clone()
+    .map_err(PqcError::BackendInit)
+}
+
+/// The error every primitive returns in a build without liboqs
+#[cfg(not(feature = "liboqs"))]
+pub(crate) fn unavailable() -> PqcError {
+    init().expect_err("init fails without liboqs")
+}
+
+/// Map a status returned by a raw liboqs call to a [`PqcError`]
+#[cfg(feature = "liboqs")]
+pub(crate) fn check_status(status: oqs_sys::common::OQS_STATUS, error: impl FnOnce(String) -> PqcError) -> Result<()> {
+    match status {
+        oqs_sys::common::OQS_STATUS::OQS_SUCCESS => Ok(()),
+        other => Err(error(format!("liboqs returned {:?}", other))),
+    }
+}
+
+fn probe() -> std::result::Result<(), String> {
+    if cfg!(not(feature = "liboqs")) {
+        return Err("this build has no liboqs; enable the `liboqs` feature".to_string());
+    }
+    let capabilities = capabilities();
+    if capabilities.kems.is_empty() {
+        return Err("liboqs was built without any supported KEM".to_string());
+    }
+    if capabilities.signatures.is_empty() {
+        return Err("liboqs was built without any supported signature scheme".to_string());
+    }
+    Ok(())
+}
+
+/// Parameter sets this crate can use with the linked liboqs build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    pub kems: Vec<KemAlgorithm>,
+    pub signatures: Vec<SigAlgorithm>,
+}
+
+impl Capabilities {
+    pub fn supports_kem(&self, algorithm: KemAlgorithm) -> bool {
+        self.kems.contains(&algorithm)
+    }
+
+    pub fn supports_sig(&self, algorithm: SigAlgorithm) -> bool {
+        self.signatures.contains(&algorithm)
+    }
+
+    /// Reject a suite whose KEM or signature scheme was compiled out
+    ///
+    /// Call this when loading configuration so a missing algorithm is
+    /// reported at startup rather than on first use.
+    pub fn check_suite(&self, suite: &CipherSuite) -> Result<()> {
+        if !self.supports_kem(suite.kem) {
+            return Err(PqcError::InvalidInput(format!("{} is not available in the linked liboqs", suite.kem)));
+        }
+        if !self.supports_sig(suite.sig) {
+            return Err(PqcError::InvalidInput(format!("{} is not available in the linked liboqs", suite.sig)));
+        }
+        Ok(())
+    }
+}
+
+/// Which KEMs and signature schemes the linked liboqs supports
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        kems: KemAlgorithm::all().iter().copied().filter(|alg| alg.is_enabled()).collect(),
+        signatures: SigAlgorithm::all().iter().copied().filter(|alg| alg.is_enabled()).collect(),
+    }
+}
+
+/// The linked liboqs and what it was built with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackendInfo {
+    pub liboqs_version: String,
+    #[serde(flatten)]
+    pub capabilities: Capabilities,
+}
+
+/// Initialize liboqs and describe it
+pub fn backend_info() -> Result<BackendInfo> {
+    init()?;
+    Ok(BackendInfo { liboqs_version: liboqs_version(), capabilities: capabilities() })
+}
+
+#[cfg(feature = "liboqs")]
+fn liboqs_version() -> String {
+    // SAFETY: OQS_version returns a pointer to a static NUL-terminated string
+    unsafe { CStr::from_ptr(oqs_sys::common::OQS_version()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(not(feature = "liboqs"))]
+fn liboqs_version() -> String {
+    "none".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_info() {
+        init().unwrap();
+        let info = backend_info().unwrap();
+        assert!(!info.liboqs_version.is_empty());
+        assert!(info.capabilities.supports_kem(KemAlgorithm::Kyber1024));
+        assert!(info.capabilities.supports_sig(SigAlgorithm::Dilithium3));
+    }
+
+    #[test]
+    fn test_check_suite() {
+        let capabilities = capabilities();
+        capabilities.check_suite(&CipherSuite::DEFAULT).unwrap();
+
+        let stripped = Capabilities { kems: vec![KemAlgorithm::MlKem768], ..capabilities };
+        assert!(matches!(stripped.check_suite(&CipherSuite::DEFAULT), Err(PqcError::InvalidInput(_))));
+    }
+
+    #[cfg(not(feature = "liboqs"))]
+    #[test]
+    fn test_primitives_fail_without_liboqs() {
+        assert!(matches!(crate::kem::generate_keypair(), Err(PqcError::BackendInit(_))));
+        assert!(matches!(crate::sign::verify(b"m", &[0; 64], &[0; 1952]), Err(PqcError::BackendInit(_))));
+        assert!(capabilities().kems.is_empty());
+    }
+}
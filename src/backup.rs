@@ -0,0 +1,159 @@
+//! Passphrase-protected backups of secret material
+//!
+//! A backup is a JSON blob holding the format version, the Argon2id cost
+//! parameters and salt, and the AES-256-GCM sealed plaintext. The AEAD key
+//! is derived from the passphrase with Argon2id, so the salt and costs are
+//! implicitly authenticated: changing either yields a different key and the
+//! blob no longer opens. The costs are read before anything is authenticated,
+//! so [`open`] refuses any above [`BackupParams::MAX`] instead of running them.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::error::{PqcError, Result};
+use crate::suite::AeadAlgorithm;
+use crate::validation::{self, Validate};
+
+/// Backup format version written by this crate
+pub const BACKUP_VERSION: u32 = 1;
+
+const SALT_LEN: usize = 16;
+const AEAD: AeadAlgorithm = AeadAlgorithm::Aes256Gcm;
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupParams {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    /// Number of passes
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for BackupParams {
+    /// 64 MiB, 3 passes, 1 lane
+    fn default() -> Self {
+        Self { memory_kib: 64 * 1024, iterations: 3, parallelism: 1 }
+    }
+}
+
+impl BackupParams {
+    /// Highest costs [`open`] will run: 1 GiB, 16 passes, 16 lanes
+    pub const MAX: BackupParams = BackupParams { memory_kib: 1024 * 1024, iterations: 16, parallelism: 16 };
+
+    /// Fail if any cost exceeds `limit`
+    pub fn check_within(&self, limit: &BackupParams) -> Result<()> {
+        if self.memory_kib > limit.memory_kib || self.iterations > limit.iterations || self.parallelism > limit.parallelism {
+            return Err(PqcError::InvalidInput(format!(
+                "Backup asks for Argon2id m={} KiB, t={}, p={}; the limit is m={} KiB, t={}, p={}",
+                self.memory_kib, self.iterations, self.parallelism, limit.memory_kib, limit.iterations, limit.parallelism
+            )));
+        }
+        Ok(())
+    }
+
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(AEAD.key_len()))
+            .map_err(|e| PqcError::InvalidInput(format!("Invalid Argon2id parameters: {}", e)))?;
+        let mut key = Zeroizing::new(vec![0u8; AEAD.key_len()]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| PqcError::InvalidInput(format!("Argon2id failed: {}", e)))?;
+        Ok(key)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupBlob {
+    version: u32,
+    kdf: String,
+    params: BackupParams,
+    #[serde(with = "crate::util::bytes")]
+    salt: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    sealed: Vec<u8>,
+}
+
+impl Validate for BackupBlob {
+    fn validate(&self) -> Result<()> {
+        validation::check_exact_len("Backup salt", self.salt.len(), SALT_LEN)?;
+        validation::check_sealed_len("Backup", self.sealed.len())
+    }
+}
+
+/// Encrypt `plaintext` under `passphrase`
+pub fn seal(plaintext: &[u8], passphrase: &str, params: BackupParams) -> Result<Vec<u8>> {
+    if passphrase.is_empty() {
+        return Err(PqcError::InvalidInput("Backup passphrase must not be empty".to_string()));
+    }
+    let mut salt = vec![0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = params.derive_key(passphrase, &salt)?;
+
+    let blob = BackupBlob {
+        version: BACKUP_VERSION,
+        kdf: "argon2id".to_string(),
+        params,
+        sealed: AEAD.seal(&key, plaintext)?,
+        salt,
+    };
+    serde_json::to_vec(&blob)
+        .map_err(|e| PqcError::InvalidInput(format!("Failed to encode backup: {}", e)))
+}
+
+/// Version, KDF name and costs of a backup blob, read without the passphrase
+pub(crate) fn inspect(blob: &[u8]) -> Result<(u32, String, BackupParams)> {
+    let blob: BackupBlob = validation::from_json(blob, "backup")?;
+    Ok((blob.version, blob.kdf, blob.params))
+}
+
+/// Decrypt a blob produced by [`seal`] whose costs are within [`BackupParams::MAX`]
+pub fn open(blob: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    open_with_limit(blob, passphrase, &BackupParams::MAX)
+}
+
+/// [`open`] with a different ceiling on the costs, e.g. lower on small devices
+pub fn open_with_limit(blob: &[u8], passphrase: &str, limit: &BackupParams) -> Result<Zeroizing<Vec<u8>>> {
+    let blob: BackupBlob = validation::from_json(blob, "backup")?;
+    if blob.version != BACKUP_VERSION || blob.kdf != "argon2id" {
+        return Err(PqcError::InvalidInput(format!("Unsupported backup version {} ({})", blob.version, blob.kdf)));
+    }
+    blob.params.check_within(limit)?;
+    let key = blob.params.derive_key(passphrase, &blob.salt)?;
+    AEAD.open(&key, &blob.sealed)
+        .map(Zeroizing::new)
+        .map_err(|_| PqcError::Decryption("Wrong passphrase or corrupted backup".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: BackupParams = BackupParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let blob = seal(b"master secret", "correct horse", FAST).unwrap();
+        assert_eq!(open(&blob, "correct horse").unwrap().as_slice(), b"master secret");
+        assert!(matches!(open(&blob, "wrong horse"), Err(PqcError::Decryption(_))));
+
+        let mut tampered: serde_json::Value = serde_json::from_slice(&blob).unwrap();
+        tampered["params"]["iterations"] = 2.into();
+        assert!(open(&serde_json::to_vec(&tampered).unwrap(), "correct horse").is_err());
+        assert!(seal(b"x", "", FAST).is_err());
+    }
+
+    #[test]
+    fn test_open_refuses_excessive_costs() {
+        let blob = seal(b"master secret", "correct horse", FAST).unwrap();
+        for (field, value) in [("memory_kib", 64 * 1024 * 1024u32), ("iterations", u32::MAX), ("parallelism", 1024)] {
+            let mut crafted: serde_json::Value = serde_json::from_slice(&blob).unwrap();
+            crafted["params"][field] = value.into();
+            let result = open(&serde_json::to_vec(&crafted).unwrap(), "correct horse");
+            assert!(matches!(result, Err(PqcError::InvalidInput(message)) if message.contains("limit")), "{}", field);
+        }
+        let limit = BackupParams { memory_kib: 32, ..BackupParams::MAX };
+        assert!(open_with_limit(&blob, "correct horse", &limit).is_err());
+    }
+}
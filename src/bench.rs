@@ -0,0 +1,109 @@
+//! Throughput and latency of the primitives on this host
+//!
+//! [`measure`] times key generation, encapsulation and signing under a
+//! suite, one operation at a time on the calling thread. Services run it at
+//! startup to size worker pools and to report capacity; multiply
+//! `ops_per_sec` by the number of worker threads for a rough host total.
+
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::suite::CipherSuite;
+use crate::{fips, kem, sign};
+
+/// Most iterations [`measure`] runs per operation
+pub const MAX_ITERATIONS: u32 = 100_000;
+
+/// Message signed while measuring; the size of a SHA-256 digest
+const SIGNED_MESSAGE: [u8; 32] = [0x5a; 32];
+
+/// Timing of one operation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OperationTiming {
+    /// Sustained rate on one thread
+    pub ops_per_sec: f64,
+    pub mean_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl OperationTiming {
+    fn from_latencies(latencies: &[Duration]) -> Self {
+        let total: Duration = latencies.iter().sum();
+        let mut micros: Vec<u64> = latencies.iter()
+            .map(|d| u64::try_from(d.as_micros()).unwrap_or(u64::MAX))
+            .collect();
+        micros.sort_unstable();
+        Self {
+            ops_per_sec: latencies.len() as f64 / total.as_secs_f64().max(f64::EPSILON),
+            mean_us: u64::try_from(total.as_micros() / latencies.len() as u128).unwrap_or(u64::MAX),
+            p99_us: micros[(micros.len() - 1) * 99 / 100],
+            max_us: micros[micros.len() - 1],
+        }
+    }
+}
+
+/// Results of [`measure`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub suite: CipherSuite,
+    pub iterations: u32,
+    /// A KEM and a signature key pair, as for one device's credentials
+    pub keygen: OperationTiming,
+    pub encapsulate: OperationTiming,
+    pub sign: OperationTiming,
+}
+
+/// Time `iterations` runs of each operation under `suite`
+pub fn measure(suite: &CipherSuite, iterations: u32) -> Result<BenchReport> {
+    fips::check_suite(suite)?;
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(PqcError::InvalidInput(format!("Benchmark iterations must be 1 to {}", MAX_ITERATIONS)));
+    }
+
+    let kem_keypair = kem::generate_keypair_with(suite.kem)?;
+    let sig_keypair = sign::generate_keypair_with(suite.sig)?;
+    let keygen = time(iterations, || {
+        kem::generate_keypair_with(suite.kem)?;
+        sign::generate_keypair_with(suite.sig).map(drop)
+    })?;
+    let encapsulate = time(iterations, || kem::encapsulate_with(suite.kem, &kem_keypair.public_key).map(drop))?;
+    let sign = time(iterations, || sign::sign_with(suite.sig, &SIGNED_MESSAGE, &sig_keypair.secret_key).map(drop))?;
+
+    Ok(BenchReport { suite: *suite, iterations, keygen, encapsulate, sign })
+}
+
+fn time(iterations: u32, mut op: impl FnMut() -> Result<()>) -> Result<OperationTiming> {
+    let mut latencies = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        op()?;
+        latencies.push(start.elapsed());
+    }
+    Ok(OperationTiming::from_latencies(&latencies))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_reports_each_operation() {
+        let report = measure(&CipherSuite::DEFAULT, 5).unwrap();
+        assert_eq!(report.iterations, 5);
+        for timing in [report.keygen, report.encapsulate, report.sign] {
+            assert!(timing.ops_per_sec > 0.0);
+            assert!(timing.p99_us <= timing.max_us);
+        }
+        assert!(measure(&CipherSuite::DEFAULT, 0).is_err());
+        assert!(measure(&CipherSuite::DEFAULT, MAX_ITERATIONS + 1).is_err());
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+        let timing = OperationTiming::from_latencies(&latencies);
+        assert_eq!((timing.p99_us, timing.max_us, timing.mean_us), (99, 100, 50));
+        assert!((timing.ops_per_sec - 100.0 / 0.00505).abs() < 1.0);
+    }
+}
@@ -1,145 +1,190 @@
 use pqc_algo::binary_loader::{load_kyber_binary, hex_to_bytes};
+use pqc_algo::porcelain::to_porcelain;
+use pqc_algo::randomness_tests::shannon_entropy;
+use serde::Serialize;
 use std::env;
+use std::fmt;
 use std::fs;
 
-fn analyze_binary_type(data: &[u8], filename: &str) -> String {
-    let mut analysis = String::new();
-    
-    analysis.push_str(&format!("Analysis of {}\n", filename));
-    analysis.push_str(&format!("- File size: {} bytes\n", data.len()));
-    analysis.push_str(&format!("- Hex size: {} characters\n", data.len() * 2));
-    
+/// What the analyzer found out about one file
+///
+/// `--porcelain` prints these fields; the text report may be reworded.
+#[derive(Serialize)]
+struct FileAnalysis {
+    file: String,
+    /// `hex` if the file decoded as hex, otherwise `raw`
+    encoding: &'static str,
+    size: usize,
+    /// `elf`, `mach-o`, `pe`, `hex` or `binary`; absent for files of 4 bytes or less
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_type: Option<&'static str>,
+    entropy: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_suggests: Option<&'static str>,
+    first_bytes: String,
+    last_bytes: String,
+    /// Only run for hex-encoded files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compatibility: Option<Compatibility>,
+}
+
+#[derive(Serialize)]
+struct Compatibility {
+    kyber_public_key: bool,
+    signature_verification: bool,
+    recommendation: &'static str,
+}
+
+fn analyze_binary_type(data: &[u8], filename: &str, encoding: &'static str) -> FileAnalysis {
     // Check if it looks like an executable binary
-    if data.len() > 4 {
-        let magic = &data[0..4];
-        match magic {
-            [0x7f, 0x45, 0x4c, 0x46] => analysis.push_str("- Type: ELF executable (Linux)\n"),
-            [0xfe, 0xed, 0xfa, 0xce] | [0xfe, 0xed, 0xfa, 0xcf] => analysis.push_str("- Type: Mach-O executable (macOS)\n"),
-            [0x4d, 0x5a, _, _] => analysis.push_str("- Type: PE executable (Windows)\n"),
-            _ => {
-                // Check if it's all printable hex characters
-                let is_hex = data.iter().all(|&b| b.is_ascii_hexdigit());
-                if is_hex {
-                    analysis.push_str("- Type: Hex-encoded data (likely cryptographic key)\n");
-                } else {
-                    analysis.push_str("- Type: Binary data (unknown format)\n");
-                }
-            }
-        }
-    }
-    
-    // Check entropy/randomness
-    let mut byte_counts = [0u32; 256];
-    for &byte in data {
-        byte_counts[byte as usize] += 1;
-    }
-    
-    let entropy = calculate_entropy(&byte_counts, data.len());
-    analysis.push_str(&format!("- Entropy: {:.2} (0=structured, 8=random)\n", entropy));
-    
+    let file_type = (data.len() > 4).then(|| match &data[0..4] {
+        [0x7f, 0x45, 0x4c, 0x46] => "elf",
+        [0xfe, 0xed, 0xfa, 0xce] | [0xfe, 0xed, 0xfa, 0xcf] => "mach-o",
+        [0x4d, 0x5a, _, _] => "pe",
+        // Check if it's all printable hex characters
+        _ if data.iter().all(|&b| b.is_ascii_hexdigit()) => "hex",
+        _ => "binary",
+    });
+
     // Check for common patterns
-    if data.len() == 256 {
-        analysis.push_str("- Size suggests: Possible 256-byte key or hash\n");
-    } else if data.len() == 1568 {
-        analysis.push_str("- Size suggests: Kyber1024 public key\n");
-    } else if data.len() == 3168 {
-        analysis.push_str("- Size suggests: Kyber1024 secret key\n");
-    } else if data.len() == 1312 {
-        analysis.push_str("- Size suggests: Dilithium2 public key\n");
-    } else if data.len() == 2544 {
-        analysis.push_str("- Size suggests: Dilithium2 secret key\n");
-    } else if data.len() == 1952 {
-        analysis.push_str("- Size suggests: Dilithium3 public key\n");
-    } else if data.len() == 4000 {
-        analysis.push_str("- Size suggests: Dilithium3 secret key\n");
+    let size_suggests = match data.len() {
+        256 => Some("Possible 256-byte key or hash"),
+        1568 => Some("Kyber1024 public key"),
+        3168 => Some("Kyber1024 secret key"),
+        1312 => Some("Dilithium2 public key"),
+        2544 => Some("Dilithium2 secret key"),
+        1952 => Some("Dilithium3 public key"),
+        4000 => Some("Dilithium3 secret key"),
+        _ => None,
+    };
+
+    FileAnalysis {
+        file: filename.to_string(),
+        encoding,
+        size: data.len(),
+        file_type,
+        entropy: shannon_entropy(data),
+        size_suggests,
+        first_bytes: hex::encode(&data[..data.len().min(16)]),
+        last_bytes: hex::encode(&data[data.len().saturating_sub(16)..]),
+        compatibility: None,
     }
-    
-    // Sample data
-    analysis.push_str(&format!("- First 16 bytes: {}\n", 
-        data.iter().take(16).map(|b| format!("{:02x}", b)).collect::<String>()));
-    analysis.push_str(&format!("- Last 16 bytes: {}\n", 
-        data.iter().rev().take(16).rev().map(|b| format!("{:02x}", b)).collect::<String>()));
-    
-    analysis
 }
 
-fn calculate_entropy(counts: &[u32; 256], total: usize) -> f64 {
-    let mut entropy = 0.0;
-    for &count in counts {
-        if count > 0 {
-            let p = count as f64 / total as f64;
-            entropy -= p * p.log2();
+impl fmt::Display for FileAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Analysis of {}", self.file)?;
+        writeln!(f, "- File size: {} bytes", self.size)?;
+        writeln!(f, "- Hex size: {} characters", self.size * 2)?;
+        if let Some(file_type) = self.file_type {
+            let description = match file_type {
+                "elf" => "ELF executable (Linux)",
+                "mach-o" => "Mach-O executable (macOS)",
+                "pe" => "PE executable (Windows)",
+                "hex" => "Hex-encoded data (likely cryptographic key)",
+                _ => "Binary data (unknown format)",
+            };
+            writeln!(f, "- Type: {}", description)?;
         }
+        writeln!(f, "- Entropy: {:.2} (0=structured, 8=random)", self.entropy)?;
+        if let Some(suggests) = self.size_suggests {
+            writeln!(f, "- Size suggests: {}", suggests)?;
+        }
+        writeln!(f, "- First 16 bytes: {}", self.first_bytes)?;
+        writeln!(f, "- Last 16 bytes: {}", self.last_bytes)
     }
-    entropy
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    
+    let mut args: Vec<String> = env::args().collect();
+    let porcelain = args.iter().any(|arg| arg == "--porcelain");
+    args.retain(|arg| arg != "--porcelain");
+
     if args.len() < 2 {
-        println!("Usage: {} <binary_file> [binary_file2] ...", args[0]);
+        println!("Usage: {} [--porcelain] <binary_file> [binary_file2] ...", args[0]);
         println!("Example: {} kyber.bin dilithium.bin", args[0]);
         return;
     }
-    
+
     for file_path in &args[1..] {
-        println!("Processing: {}", file_path);
-        
+        if !porcelain {
+            println!("Processing: {}", file_path);
+        }
+
         // Try to read as raw binary first
-        match fs::read(file_path) {
+        let analysis = match fs::read(file_path) {
             Ok(raw_data) => {
                 // Check if it's a hex file by trying to parse it
-                if let Ok(hex_string) = std::str::from_utf8(&raw_data) {
-                    if let Ok(decoded_data) = hex_to_bytes(hex_string.trim()) {
-                        println!("Successfully decoded as hex data");
-                        println!("{}", analyze_binary_type(&decoded_data, file_path));
-                        
+                match std::str::from_utf8(&raw_data).ok().and_then(|text| hex_to_bytes(text.trim()).ok()) {
+                    Some(decoded_data) => {
+                        let mut analysis = analyze_binary_type(&decoded_data, file_path, "hex");
                         // Test cryptographic compatibility
-                        test_crypto_compatibility(&decoded_data, file_path);
-                    } else {
-                        println!("Raw binary data (not hex-encoded)");
-                        println!("{}", analyze_binary_type(&raw_data, file_path));
+                        analysis.compatibility = Some(test_crypto_compatibility(&decoded_data));
+                        analysis
                     }
-                } else {
-                    println!("Raw binary data (not text)");
-                    println!("{}", analyze_binary_type(&raw_data, file_path));
+                    None => analyze_binary_type(&raw_data, file_path, "raw"),
                 }
             },
             Err(e) => {
-                println!("Failed to read {}: {}", file_path, e);
+                if porcelain {
+                    eprintln!("Failed to read {}: {}", file_path, e);
+                } else {
+                    println!("Failed to read {}: {}", file_path, e);
+                    println!();
+                }
+                continue;
             }
+        };
+
+        if porcelain {
+            match to_porcelain(&analysis) {
+                Ok(out) => print!("{}", out),
+                Err(e) => eprintln!("{}", e),
+            }
+            continue;
+        }
+        match analysis.encoding {
+            "hex" => println!("Successfully decoded as hex data"),
+            _ => println!("Raw binary data (not hex-encoded)"),
+        }
+        println!("{}", analysis);
+        if let Some(compatibility) = &analysis.compatibility {
+            print_compatibility(compatibility, analysis.size);
         }
         println!();
     }
 }
 
-fn test_crypto_compatibility(data: &[u8], _filename: &str) {
-    println!("Cryptographic Compatibility Test:");
-    
-    // Test as Kyber public key
-    match pqc_algo::kem::encapsulate(data) {
-        Ok(_) => println!("  Valid as Kyber public key"),
-        Err(_) => println!("  Invalid as Kyber public key"),
-    }
-    
-    // Test as signature verification (we'd need a message and signature for full test)
-    match pqc_algo::sign::verify(b"test message", data, data) {
-        Ok(_) => println!("  Could be used for signature verification"),
-        Err(_) => println!("  Invalid for signature verification"),
-    }
-    
-    println!("  Recommendation: {} bytes suggests {}", 
-        data.len(),
-        match data.len() {
+fn test_crypto_compatibility(data: &[u8]) -> Compatibility {
+    Compatibility {
+        // Test as Kyber public key
+        kyber_public_key: pqc_algo::kem::encapsulate(data).is_ok(),
+        // Test as signature verification (we'd need a message and signature for full test)
+        signature_verification: pqc_algo::sign::verify(b"test message", data, data).is_ok(),
+        recommendation: match data.len() {
             256 => "custom key format or truncated key",
             1568 => "Kyber1024 public key",
-            3168 => "Kyber1024 secret key", 
+            3168 => "Kyber1024 secret key",
             1312 => "Dilithium2 public key",
             2544 => "Dilithium2 secret key",
             1952 => "Dilithium3 public key",
             4000 => "Dilithium3 secret key",
             _ => "unknown key format"
-        }
-    );
-}
\ No newline at end of file
+        },
+    }
+}
+
+fn print_compatibility(compatibility: &Compatibility, size: usize) {
+    println!("Cryptographic Compatibility Test:");
+    if compatibility.kyber_public_key {
+        println!("  Valid as Kyber public key");
+    } else {
+        println!("  Invalid as Kyber public key");
+    }
+    if compatibility.signature_verification {
+        println!("  Could be used for signature verification");
+    } else {
+        println!("  Invalid for signature verification");
+    }
+    println!("  Recommendation: {} bytes suggests {}", size, compatibility.recommendation);
+}
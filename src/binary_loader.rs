@@ -1,21 +1,83 @@
+use std::fmt;
 use std::fs;
+use std::io::{BufReader, Read};
 use std::path::Path;
+use serde::Serialize;
 use crate::error::{PqcError, Result};
 
+/// Default cap on the size of a hex-encoded key or seed file (1 MiB)
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Chunk size used by the streaming hex decoder
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
 /// Load Kyber key data from a binary file
-pub fn load_kyber_binary(file_path: &str) -> Result<Vec<u8>> {
-    if !Path::new(file_path).exists() {
-        return Err(PqcError::Io(format!("File not found: {}", file_path)));
+pub fn load_kyber_binary<P: AsRef<Path>>(file_path: P) -> Result<Vec<u8>> {
+    load_hex_file(file_path)
+}
+
+/// Load any hex-encoded key or seed file up to [`DEFAULT_MAX_FILE_SIZE`]
+pub fn load_hex_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<u8>> {
+    load_kyber_binary_with_limit(file_path, DEFAULT_MAX_FILE_SIZE)
+}
+
+/// Load hex-encoded key data, refusing files larger than `max_file_size` bytes
+pub fn load_kyber_binary_with_limit<P: AsRef<Path>>(file_path: P, max_file_size: u64) -> Result<Vec<u8>> {
+    let file_path = file_path.as_ref();
+    if !file_path.exists() {
+        return Err(PqcError::Io(format!("File not found: {}", file_path.display())));
     }
     
-    let hex_content = fs::read_to_string(file_path)
+    let file = fs::File::open(file_path)
         .map_err(|e| PqcError::Io(format!("Failed to read file: {}", e)))?;
+    let file_size = file.metadata()
+        .map_err(|e| PqcError::Io(format!("Failed to read file: {}", e)))?
+        .len();
+    if file_size > max_file_size {
+        return Err(PqcError::InvalidInput(format!(
+            "File too large: {} bytes (limit {})", file_size, max_file_size
+        )));
+    }
+    
+    decode_hex_stream(BufReader::new(file), max_file_size)
+}
+
+/// Decode hex from a reader in fixed-size chunks
+///
+/// ASCII whitespace is skipped. Reading stops with an error once more than
+/// `max_input` bytes have been consumed, so the input can't grow unbounded
+/// while it is being read.
+pub fn decode_hex_stream<R: Read>(mut reader: R, max_input: u64) -> Result<Vec<u8>> {
+    let mut chunk = [0u8; READ_CHUNK_SIZE];
+    let mut digits = Vec::with_capacity(READ_CHUNK_SIZE + 1);
+    let mut bytes = Vec::new();
+    let mut consumed: u64 = 0;
+    
+    loop {
+        let read = reader.read(&mut chunk)
+            .map_err(|e| PqcError::Io(format!("Failed to read file: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        
+        consumed += read as u64;
+        if consumed > max_input {
+            return Err(PqcError::InvalidInput(format!("Input exceeds {} bytes", max_input)));
+        }
+        
+        digits.extend(chunk[..read].iter().copied().filter(|b| !b.is_ascii_whitespace()));
+        
+        // Decode every complete pair and carry an odd digit over to the next chunk
+        let complete = digits.len() & !1;
+        decode_hex_into(&digits[..complete], &mut bytes)?;
+        digits.drain(..complete);
+    }
     
-    // Remove any whitespace/newlines
-    let hex_content = hex_content.trim();
+    if !digits.is_empty() {
+        return Err(PqcError::InvalidKey("Hex string must have even length".to_string()));
+    }
     
-    // Convert hex string to bytes
-    hex_to_bytes(hex_content)
+    Ok(bytes)
 }
 
 /// Convert hex string to bytes
@@ -26,41 +88,83 @@ pub fn hex_to_bytes(hex_str: &str) -> Result<Vec<u8>> {
         return Err(PqcError::InvalidKey("Hex string must have even length".to_string()));
     }
     
-    let mut bytes = Vec::new();
-    for i in (0..cleaned.len()).step_by(2) {
-        let hex_pair = &cleaned[i..i+2];
-        let byte = u8::from_str_radix(hex_pair, 16)
-            .map_err(|_| PqcError::InvalidKey(format!("Invalid hex pair: {}", hex_pair)))?;
-        bytes.push(byte);
-    }
-    
+    let mut bytes = Vec::with_capacity(cleaned.len() / 2);
+    decode_hex_into(cleaned.as_bytes(), &mut bytes)?;
     Ok(bytes)
 }
 
+/// Decode an even-length run of hex digits and append the result to `out`
+fn decode_hex_into(digits: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    let start = out.len();
+    out.resize(start + digits.len() / 2, 0);
+    hex::decode_to_slice(digits, &mut out[start..]).map_err(|e| match e {
+        hex::FromHexError::InvalidHexCharacter { index, .. } => {
+            let pair_start = index & !1;
+            let pair = String::from_utf8_lossy(&digits[pair_start..pair_start + 2]);
+            PqcError::InvalidKey(format!("Invalid hex pair: {}", pair))
+        }
+        other => PqcError::InvalidKey(other.to_string()),
+    })
+}
+
+/// Size-based description of key data, as printed by [`analyze_kyber_data`]
+///
+/// The `Display` output is for people and may be reworded; scripts should
+/// read the fields, or the [`porcelain`](crate::porcelain) rendering, whose
+/// keys are the field names below.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KyberDataAnalysis {
+    pub size: usize,
+    /// Hex of up to the first 32 bytes
+    pub first_bytes: String,
+    /// Hex of up to the last 32 bytes
+    pub last_bytes: String,
+    /// Kyber parameter set whose key size matches, e.g. `Kyber768`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<&'static str>,
+    /// `public` or `secret` when `algorithm` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<&'static str>,
+}
+
+impl KyberDataAnalysis {
+    pub fn new(data: &[u8]) -> Self {
+        let (algorithm, key_type) = match data.len() {
+            800 => (Some("Kyber512"), Some("public")),
+            1632 => (Some("Kyber512"), Some("secret")),
+            1184 => (Some("Kyber768"), Some("public")),
+            2400 => (Some("Kyber768"), Some("secret")),
+            1568 => (Some("Kyber1024"), Some("public")),
+            3168 => (Some("Kyber1024"), Some("secret")),
+            _ => (None, None),
+        };
+        Self {
+            size: data.len(),
+            first_bytes: hex::encode(&data[..data.len().min(32)]),
+            last_bytes: hex::encode(&data[data.len().saturating_sub(32)..]),
+            algorithm,
+            key_type,
+        }
+    }
+}
+
+impl fmt::Display for KyberDataAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Kyber Binary Analysis:")?;
+        writeln!(f, "- Data size: {} bytes", self.size)?;
+        writeln!(f, "- First 32 bytes (hex): {}", self.first_bytes)?;
+        writeln!(f, "- Last 32 bytes (hex): {}", self.last_bytes)?;
+        match (self.algorithm, self.key_type) {
+            (Some(algorithm), Some(key_type)) => writeln!(f, "- Matches {} {} key size", algorithm, key_type),
+            _ if self.size == 256 => writeln!(f, "- Matches 256-byte data (custom format?)"),
+            _ => writeln!(f, "- Non-standard size for Kyber keys"),
+        }
+    }
+}
+
 /// Analyze the loaded Kyber binary data
 pub fn analyze_kyber_data(data: &[u8]) -> String {
-    let mut analysis = String::new();
-    
-    analysis.push_str(&format!("Kyber Binary Analysis:\n"));
-    analysis.push_str(&format!("- Data size: {} bytes\n", data.len()));
-    analysis.push_str(&format!("- First 32 bytes (hex): {}\n", 
-        data.iter().take(32).map(|b| format!("{:02x}", b)).collect::<String>()));
-    analysis.push_str(&format!("- Last 32 bytes (hex): {}\n", 
-        data.iter().rev().take(32).rev().map(|b| format!("{:02x}", b)).collect::<String>()));
-    
-    // Check if it's a valid Kyber key size
-    match data.len() {
-        1568 => analysis.push_str("- Matches Kyber1024 public key size\n"),
-        3168 => analysis.push_str("- Matches Kyber1024 secret key size\n"),
-        800 => analysis.push_str("- Matches Kyber512 public key size\n"),
-        1632 => analysis.push_str("- Matches Kyber512 secret key size\n"),
-        1184 => analysis.push_str("- Matches Kyber768 public key size\n"),
-        2400 => analysis.push_str("- Matches Kyber768 secret key size\n"),
-        256 => analysis.push_str("- Matches 256-byte data (custom format?)\n"),
-        _ => analysis.push_str(&format!("- Non-standard size for Kyber keys\n")),
-    }
-    
-    analysis
+    KyberDataAnalysis::new(data).to_string()
 }
 
 /// Test if the binary data can be used as a Kyber key
@@ -96,6 +200,39 @@ mod tests {
         assert_eq!(bytes, b"Hello");
     }
 
+    #[test]
+    fn test_hex_conversion_rejects_invalid_pair() {
+        let err = hex_to_bytes("48zz").unwrap_err();
+        assert!(matches!(err, PqcError::InvalidKey(ref msg) if msg == "Invalid hex pair: zz"));
+    }
+
+    #[test]
+    fn test_decode_hex_stream_skips_whitespace() {
+        let input = "4865\n6c6c\r\n 6f\n";
+        let bytes = decode_hex_stream(input.as_bytes(), DEFAULT_MAX_FILE_SIZE).unwrap();
+        assert_eq!(bytes, b"Hello");
+    }
+
+    #[test]
+    fn test_decode_hex_stream_enforces_limit() {
+        let input = "ab".repeat(READ_CHUNK_SIZE);
+        let result = decode_hex_stream(input.as_bytes(), 1024);
+        assert!(matches!(result, Err(PqcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_kyber_data_analysis() {
+        let analysis = KyberDataAnalysis::new(&[0xab; 1184]);
+        assert_eq!((analysis.algorithm, analysis.key_type), (Some("Kyber768"), Some("public")));
+        assert_eq!(analysis.first_bytes, "ab".repeat(32));
+        assert!(analyze_kyber_data(&[0xab; 1184]).contains("- Matches Kyber768 public key size\n"));
+
+        let short = KyberDataAnalysis::new(&[1, 2, 3]);
+        assert_eq!((short.first_bytes.as_str(), short.last_bytes.as_str()), ("010203", "010203"));
+        let porcelain = crate::porcelain::to_porcelain(&short).unwrap();
+        assert_eq!(porcelain, "porcelain\t1\nfirst_bytes\t010203\nlast_bytes\t010203\nsize\t3\n");
+    }
+
     #[test]
     fn test_load_kyber_binary() {
         // This test will only work if kyber.bin exists
@@ -0,0 +1,383 @@
+//! Bluetooth LE GATT provisioning helpers
+//!
+//! GATT writes and notifications carry at most `ATT_MTU - 3` bytes, so
+//! protocol messages are split into chunks with a two-byte header
+//! (`seq`, `flags`); the first chunk also carries the total length as a
+//! big-endian `u32`. [`ProvisioningSession`] runs the exchange
+//! hello → cert → kyber ciphertext → confirm on top of that chunking and
+//! rejects anything out of order. No BLE stack is bundled; the app moves
+//! the chunks over its own characteristic.
+
+use crate::error::{PqcError, Result};
+use crate::transport::MAX_FRAME_LEN;
+
+/// Default ATT MTU before any MTU exchange
+pub const DEFAULT_ATT_MTU: usize = 23;
+
+/// ATT opcode and handle bytes taken out of every write or notification
+const ATT_HEADER_LEN: usize = 3;
+
+const CHUNK_HEADER_LEN: usize = 2;
+const LENGTH_PREFIX_LEN: usize = 4;
+
+const FLAG_FIRST: u8 = 0x01;
+const FLAG_LAST: u8 = 0x02;
+
+/// Split a message into chunks that each fit one GATT write at `mtu`
+pub fn chunk_message(message: &[u8], mtu: usize) -> Result<Vec<Vec<u8>>> {
+    let capacity = chunk_capacity(mtu)?;
+    if message.len() > MAX_FRAME_LEN {
+        return Err(PqcError::InvalidInput(format!(
+            "Message of {} bytes exceeds limit of {}", message.len(), MAX_FRAME_LEN
+        )));
+    }
+
+    let first_len = message.len().min(capacity - LENGTH_PREFIX_LEN);
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    let mut seq = 0u8;
+
+    loop {
+        let first = offset == 0;
+        let take = if first { first_len } else { (message.len() - offset).min(capacity) };
+        let last = offset + take == message.len();
+
+        let mut chunk = Vec::with_capacity(capacity + CHUNK_HEADER_LEN);
+        chunk.push(seq);
+        chunk.push(if first { FLAG_FIRST } else { 0 } | if last { FLAG_LAST } else { 0 });
+        if first {
+            chunk.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        }
+        chunk.extend_from_slice(&message[offset..offset + take]);
+        chunks.push(chunk);
+
+        offset += take;
+        seq = seq.wrapping_add(1);
+        if last {
+            return Ok(chunks);
+        }
+    }
+}
+
+/// Payload bytes available per chunk after the ATT and chunk headers
+fn chunk_capacity(mtu: usize) -> Result<usize> {
+    let min_mtu = ATT_HEADER_LEN + CHUNK_HEADER_LEN + LENGTH_PREFIX_LEN + 1;
+    if mtu < min_mtu {
+        return Err(PqcError::InvalidInput(format!("ATT MTU {} is below the minimum of {}", mtu, min_mtu)));
+    }
+    Ok(mtu - ATT_HEADER_LEN - CHUNK_HEADER_LEN)
+}
+
+/// Rebuilds messages from chunks received in order
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    buffer: Vec<u8>,
+    expected_len: usize,
+    next_seq: u8,
+    active: bool,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one chunk; returns the message once its last chunk arrives
+    ///
+    /// Any malformed or out-of-order chunk discards the partial message.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Option<Vec<u8>>> {
+        let result = self.push_inner(chunk);
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    fn push_inner(&mut self, chunk: &[u8]) -> Result<Option<Vec<u8>>> {
+        if chunk.len() < CHUNK_HEADER_LEN {
+            return Err(PqcError::Protocol("Chunk shorter than its header".to_string()));
+        }
+        let (seq, flags) = (chunk[0], chunk[1]);
+        let mut data = &chunk[CHUNK_HEADER_LEN..];
+
+        if flags & FLAG_FIRST != 0 {
+            if seq != 0 || data.len() < LENGTH_PREFIX_LEN {
+                return Err(PqcError::Protocol("Malformed first chunk".to_string()));
+            }
+            let mut len = [0u8; LENGTH_PREFIX_LEN];
+            len.copy_from_slice(&data[..LENGTH_PREFIX_LEN]);
+            let expected_len = u32::from_be_bytes(len) as usize;
+            if expected_len > MAX_FRAME_LEN {
+                return Err(PqcError::Protocol(format!("Announced length {} exceeds limit", expected_len)));
+            }
+            data = &data[LENGTH_PREFIX_LEN..];
+            self.reset();
+            self.expected_len = expected_len;
+            self.active = true;
+        } else if !self.active {
+            return Err(PqcError::Protocol("Continuation chunk without a first chunk".to_string()));
+        } else if seq != self.next_seq {
+            return Err(PqcError::Protocol(format!("Expected chunk {}, got {}", self.next_seq, seq)));
+        }
+
+        if self.buffer.len() + data.len() > self.expected_len {
+            return Err(PqcError::Protocol("Chunks exceed the announced length".to_string()));
+        }
+        self.buffer.extend_from_slice(data);
+        self.next_seq = seq.wrapping_add(1);
+
+        if flags & FLAG_LAST == 0 {
+            return Ok(None);
+        }
+        if self.buffer.len() != self.expected_len {
+            return Err(PqcError::Protocol("Message ended before the announced length".to_string()));
+        }
+        let message = std::mem::take(&mut self.buffer);
+        self.reset();
+        Ok(Some(message))
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.expected_len = 0;
+        self.next_seq = 0;
+        self.active = false;
+    }
+}
+
+/// Which side of the provisioning exchange we are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Device,
+    Provisioner,
+}
+
+/// Messages of the provisioning exchange, in protocol order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// Device announces its ID and nonce
+    Hello = 1,
+    /// Provisioner presents its certificate and Kyber public key
+    Cert = 2,
+    /// Device returns a Kyber ciphertext for the provisioner's key
+    KyberCiphertext = 3,
+    /// Provisioner proves it derived the shared secret
+    Confirm = 4,
+}
+
+impl MessageKind {
+    /// The role allowed to send this message
+    pub fn sender(self) -> Role {
+        match self {
+            MessageKind::Hello | MessageKind::KyberCiphertext => Role::Device,
+            MessageKind::Cert | MessageKind::Confirm => Role::Provisioner,
+        }
+    }
+
+    fn next(self) -> Option<MessageKind> {
+        match self {
+            MessageKind::Hello => Some(MessageKind::Cert),
+            MessageKind::Cert => Some(MessageKind::KyberCiphertext),
+            MessageKind::KyberCiphertext => Some(MessageKind::Confirm),
+            MessageKind::Confirm => None,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            1 => Ok(MessageKind::Hello),
+            2 => Ok(MessageKind::Cert),
+            3 => Ok(MessageKind::KyberCiphertext),
+            4 => Ok(MessageKind::Confirm),
+            other => Err(PqcError::Protocol(format!("Unknown message kind {}", other))),
+        }
+    }
+}
+
+/// One protocol message: a kind byte followed by an opaque body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvisioningMessage {
+    pub kind: MessageKind,
+    pub body: Vec<u8>,
+}
+
+impl ProvisioningMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.body.len() + 1);
+        bytes.push(self.kind as u8);
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (&kind, body) = bytes.split_first()
+            .ok_or_else(|| PqcError::Protocol("Empty message".to_string()))?;
+        Ok(Self { kind: MessageKind::from_byte(kind)?, body: body.to_vec() })
+    }
+}
+
+/// Progress through the provisioning exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Waiting for this message to be sent or received
+    Awaiting(MessageKind),
+    Complete,
+    /// A protocol violation ended the session
+    Failed,
+}
+
+/// Provisioning state machine for one side of a BLE link
+#[derive(Debug)]
+pub struct ProvisioningSession {
+    role: Role,
+    mtu: usize,
+    state: SessionState,
+    reassembler: Reassembler,
+}
+
+impl ProvisioningSession {
+    pub fn new(role: Role, mtu: usize) -> Result<Self> {
+        chunk_capacity(mtu)?;
+        Ok(Self {
+            role,
+            mtu,
+            state: SessionState::Awaiting(MessageKind::Hello),
+            reassembler: Reassembler::new(),
+        })
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// Update the MTU after an ATT MTU exchange
+    pub fn set_mtu(&mut self, mtu: usize) -> Result<()> {
+        chunk_capacity(mtu)?;
+        self.mtu = mtu;
+        Ok(())
+    }
+
+    /// Whether the next message is ours to send
+    pub fn is_our_turn(&self) -> bool {
+        matches!(self.state, SessionState::Awaiting(kind) if kind.sender() == self.role)
+    }
+
+    /// Produce the chunks for our next message
+    pub fn send(&mut self, kind: MessageKind, body: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.expect(kind, self.role)?;
+        let message = ProvisioningMessage { kind, body: body.to_vec() };
+        let chunks = chunk_message(&message.to_bytes(), self.mtu)?;
+        self.advance(kind);
+        Ok(chunks)
+    }
+
+    /// Feed one chunk from the peer; returns the message once complete
+    pub fn receive_chunk(&mut self, chunk: &[u8]) -> Result<Option<ProvisioningMessage>> {
+        if matches!(self.state, SessionState::Complete | SessionState::Failed) {
+            return Err(PqcError::Protocol(format!("Session is {:?}", self.state)));
+        }
+        let Some(bytes) = self.fail_on_err(|s| s.reassembler.push(chunk))? else {
+            return Ok(None);
+        };
+        let message = self.fail_on_err(|_| ProvisioningMessage::from_bytes(&bytes))?;
+        let peer = match self.role {
+            Role::Device => Role::Provisioner,
+            Role::Provisioner => Role::Device,
+        };
+        self.expect(message.kind, peer)?;
+        self.advance(message.kind);
+        Ok(Some(message))
+    }
+
+    fn expect(&mut self, kind: MessageKind, sender: Role) -> Result<()> {
+        match self.state {
+            SessionState::Awaiting(expected) if expected == kind && kind.sender() == sender => Ok(()),
+            state => {
+                self.state = SessionState::Failed;
+                Err(PqcError::Protocol(format!("{:?} from {:?} not allowed while {:?}", kind, sender, state)))
+            }
+        }
+    }
+
+    fn advance(&mut self, kind: MessageKind) {
+        self.state = match kind.next() {
+            Some(next) => SessionState::Awaiting(next),
+            None => SessionState::Complete,
+        };
+    }
+
+    fn fail_on_err<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let result = f(self);
+        if result.is_err() {
+            self.state = SessionState::Failed;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_and_reassemble() {
+        let message: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let chunks = chunk_message(&message, DEFAULT_ATT_MTU).unwrap();
+        assert!(chunks.iter().all(|c| c.len() <= DEFAULT_ATT_MTU - ATT_HEADER_LEN));
+
+        let mut reassembler = Reassembler::new();
+        let (last, rest) = chunks.split_last().unwrap();
+        for chunk in rest {
+            assert!(reassembler.push(chunk).unwrap().is_none());
+        }
+        assert_eq!(reassembler.push(last).unwrap().unwrap(), message);
+
+        // Dropping a chunk is detected
+        let mut reassembler = Reassembler::new();
+        reassembler.push(&chunks[0]).unwrap();
+        assert!(reassembler.push(&chunks[2]).is_err());
+    }
+
+    #[test]
+    fn test_provisioning_exchange() {
+        let mut device = ProvisioningSession::new(Role::Device, 185).unwrap();
+        let mut provisioner = ProvisioningSession::new(Role::Provisioner, 185).unwrap();
+
+        let steps = [
+            (MessageKind::Hello, vec![1u8; 40]),
+            (MessageKind::Cert, vec![2u8; 1568]),
+            (MessageKind::KyberCiphertext, vec![3u8; 1568]),
+            (MessageKind::Confirm, vec![4u8; 32]),
+        ];
+        for (kind, body) in steps {
+            let (sender, receiver) = if kind.sender() == Role::Device {
+                (&mut device, &mut provisioner)
+            } else {
+                (&mut provisioner, &mut device)
+            };
+            assert!(sender.is_our_turn());
+            let mut received = None;
+            for chunk in sender.send(kind, &body).unwrap() {
+                received = receiver.receive_chunk(&chunk).unwrap();
+            }
+            assert_eq!(received, Some(ProvisioningMessage { kind, body }));
+        }
+
+        assert_eq!(device.state(), SessionState::Complete);
+        assert_eq!(provisioner.state(), SessionState::Complete);
+    }
+
+    #[test]
+    fn test_out_of_order_message_fails_session() {
+        let mut provisioner = ProvisioningSession::new(Role::Provisioner, DEFAULT_ATT_MTU).unwrap();
+        let mut device = ProvisioningSession::new(Role::Device, DEFAULT_ATT_MTU).unwrap();
+
+        // The device may not skip straight to the ciphertext
+        let bogus = ProvisioningMessage { kind: MessageKind::KyberCiphertext, body: vec![0; 8] };
+        let chunks = chunk_message(&bogus.to_bytes(), DEFAULT_ATT_MTU).unwrap();
+        assert!(matches!(provisioner.receive_chunk(&chunks[0]), Err(PqcError::Protocol(_))));
+        assert_eq!(provisioner.state(), SessionState::Failed);
+
+        assert!(device.send(MessageKind::Cert, b"cert").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Memory budget for service mode
+//!
+//! A [`MemoryBudget`] caps the bytes in-flight requests may hold at once,
+//! typically the largest envelope times the number of requests served
+//! concurrently. Each request takes a [`Reservation`] before allocating and
+//! gives it back when dropped. When the budget is spent, new requests fail
+//! fast with [`PqcError::Overloaded`] so callers can back off and retry,
+//! instead of the process growing until the OOM killer ends it.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use crate::error::{PqcError, Result};
+
+#[derive(Debug)]
+struct Usage {
+    limit: usize,
+    in_use: AtomicUsize,
+    peak: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Shared cap on bytes held by in-flight requests; clones share one budget
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    usage: Arc<Usage>,
+}
+
+impl MemoryBudget {
+    /// A budget of `limit` bytes
+    pub fn new(limit: usize) -> Self {
+        Self {
+            usage: Arc::new(Usage {
+                limit,
+                in_use: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                rejected: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Room for `max_concurrent` requests of up to `max_request_len` bytes each
+    pub fn for_requests(max_request_len: usize, max_concurrent: usize) -> Self {
+        Self::new(max_request_len.saturating_mul(max_concurrent))
+    }
+
+    /// Reserve `bytes` until the returned guard is dropped
+    ///
+    /// Fails with [`PqcError::Overloaded`] while other requests hold too
+    /// much, and with [`PqcError::InvalidInput`] if `bytes` exceeds the whole
+    /// budget and could never be served.
+    pub fn try_reserve(&self, bytes: usize) -> Result<Reservation> {
+        let limit = self.usage.limit;
+        if bytes > limit {
+            self.usage.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(PqcError::InvalidInput(format!(
+                "Request needs {} bytes, memory budget is {}", bytes, limit
+            )));
+        }
+        let reserved = self.usage.in_use.fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_use| {
+            in_use.checked_add(bytes).filter(|&total| total <= limit)
+        });
+        match reserved {
+            Ok(previous) => {
+                self.usage.peak.fetch_max(previous + bytes, Ordering::Relaxed);
+                Ok(Reservation { usage: Arc::clone(&self.usage), bytes })
+            }
+            Err(in_use) => {
+                self.usage.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(PqcError::Overloaded(format!(
+                    "{} of {} bytes in use, cannot reserve {} more", in_use, limit, bytes
+                )))
+            }
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.usage.limit
+    }
+
+    /// Bytes currently reserved
+    pub fn in_use(&self) -> usize {
+        self.usage.in_use.load(Ordering::Acquire)
+    }
+
+    /// Most bytes ever reserved at once
+    pub fn peak(&self) -> usize {
+        self.usage.peak.load(Ordering::Relaxed)
+    }
+
+    /// Reservations refused so far
+    pub fn rejected(&self) -> u64 {
+        self.usage.rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Bytes held against a [`MemoryBudget`], released on drop
+#[derive(Debug)]
+pub struct Reservation {
+    usage: Arc<Usage>,
+    bytes: usize,
+}
+
+impl Reservation {
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.usage.in_use.fetch_sub(self.bytes, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reservations_apply_backpressure() {
+        let budget = MemoryBudget::for_requests(1000, 2);
+        let first = budget.try_reserve(1000).unwrap();
+        let second = budget.clone().try_reserve(600).unwrap();
+        assert!(matches!(budget.try_reserve(600), Err(PqcError::Overloaded(_))));
+        assert_eq!(budget.in_use(), 1600);
+
+        drop(first);
+        let third = budget.try_reserve(1400).unwrap();
+        assert_eq!(third.bytes(), 1400);
+        drop((second, third));
+        assert_eq!(budget.in_use(), 0);
+        assert_eq!(budget.peak(), 2000);
+        assert_eq!(budget.rejected(), 1);
+    }
+
+    #[test]
+    fn test_oversized_request_is_not_retryable() {
+        let budget = MemoryBudget::new(100);
+        assert!(matches!(budget.try_reserve(101), Err(PqcError::InvalidInput(_))));
+        assert_eq!(budget.in_use(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_reservations_stay_within_limit() {
+        let budget = MemoryBudget::new(10);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        if let Ok(reservation) = budget.try_reserve(3) {
+                            assert!(budget.in_use() <= 10);
+                            drop(reservation);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(budget.in_use(), 0);
+        assert!(budget.peak() <= 9);
+    }
+}
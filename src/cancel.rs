@@ -0,0 +1,128 @@
+//! Cooperative cancellation and timeouts for long operations
+//!
+//! Batch key generation, batch verification and batch provisioning take a
+//! [`CancelToken`] and check it before starting each item. Cancelling the
+//! token, or passing its deadline, stops them from starting new work. They
+//! then return a [`Partial`] holding everything finished so far instead of
+//! blocking until the whole batch is done. An item that has already started
+//! runs to completion; cancellation never interrupts a primitive midway.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::error::{PqcError, Result};
+
+/// Shared cancel flag with an optional deadline; clones cancel together
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    /// A token that stops only when cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that also stops `timeout` from now
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::new().deadline(Instant::now() + timeout)
+    }
+
+    /// Stop at `deadline` as well; clones made earlier keep their own deadline
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Ask every operation holding this token or a clone to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.is_expired()
+    }
+
+    /// `PqcError::Cancelled` once cancelled or past the deadline
+    pub fn check(&self) -> Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(PqcError::Cancelled("cancelled by caller".to_string()));
+        }
+        if self.is_expired() {
+            return Err(PqcError::Cancelled("deadline exceeded".to_string()));
+        }
+        Ok(())
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Results of a batch that may have stopped early
+#[derive(Debug)]
+pub struct Partial<T> {
+    /// One slot per input, in input order; `None` where the item never ran
+    pub results: Vec<Option<T>>,
+    /// Why the batch stopped early, or `None` if every item ran
+    pub stopped: Option<PqcError>,
+}
+
+impl<T> Partial<T> {
+    pub fn is_complete(&self) -> bool {
+        self.stopped.is_none()
+    }
+
+    /// Number of items that finished
+    pub fn completed(&self) -> usize {
+        self.results.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Every result, or the error that stopped the batch
+    pub fn into_result(self) -> Result<Vec<T>> {
+        match self.stopped {
+            Some(e) => Err(e),
+            None => Ok(self.results.into_iter().flatten().collect()),
+        }
+    }
+
+    /// Finished items with their input index, dropping the rest
+    pub fn into_completed(self) -> Vec<(usize, T)> {
+        self.results.into_iter().enumerate().filter_map(|(i, slot)| slot.map(|value| (i, value))).collect()
+    }
+
+    /// Transform each finished item, given its input index
+    pub(crate) fn map<U, F: FnMut(usize, T) -> U>(self, mut f: F) -> Partial<U> {
+        let results = self.results.into_iter().enumerate().map(|(i, slot)| slot.map(|value| f(i, value))).collect();
+        Partial { results, stopped: self.stopped }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_cancel_and_deadline() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+        clone.cancel();
+        assert!(matches!(token.check(), Err(PqcError::Cancelled(_))));
+
+        let expired = CancelToken::with_timeout(Duration::ZERO);
+        assert!(expired.is_cancelled());
+        assert!(!CancelToken::with_timeout(Duration::from_secs(60)).is_cancelled());
+    }
+
+    #[test]
+    fn test_partial_results() {
+        let partial = Partial { results: vec![Some(1), None, Some(3)], stopped: Some(PqcError::Cancelled("x".to_string())) };
+        assert_eq!(partial.completed(), 2);
+        assert_eq!(partial.map(|_, v| v * 10).into_completed(), vec![(0, 10), (2, 30)]);
+        let complete = Partial::<u8> { results: vec![Some(1)], stopped: None };
+        assert_eq!(complete.into_result().unwrap(), vec![1]);
+    }
+}
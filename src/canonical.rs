@@ -0,0 +1,113 @@
+//! Canonical JSON (RFC 8785, JCS) for data that gets signed
+//!
+//! Signing structured data instead of hand-joined strings means no field can
+//! contain a separator that shifts bytes into its neighbour, and the encoding
+//! is byte-for-byte reproducible by any JCS implementation.
+
+use serde::Serialize;
+use serde_json::Value;
+use crate::error::{PqcError, Result};
+
+/// Encode `value` as canonical JSON
+///
+/// Object keys are sorted by UTF-16 code units and no whitespace is emitted.
+/// Floating-point numbers are rejected since nothing signed here needs them.
+pub fn to_canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(value)
+        .map_err(|e| PqcError::InvalidInput(format!("Cannot encode value: {}", e)))?;
+    let mut out = Vec::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+/// Bytes to sign for `value` under a domain label
+///
+/// The label is followed by a NUL byte, so signatures made for one message
+/// type can never verify as another.
+pub fn signing_bytes<T: Serialize>(domain: &str, value: &T) -> Result<Vec<u8>> {
+    let mut bytes = domain.as_bytes().to_vec();
+    bytes.push(0);
+    bytes.extend_from_slice(&to_canonical_json(value)?);
+    Ok(bytes)
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Value::Number(n) => {
+            if n.is_f64() {
+                return Err(PqcError::InvalidInput("Floating-point numbers are not canonicalized".to_string()));
+            }
+            out.extend_from_slice(n.to_string().as_bytes());
+        }
+        Value::String(s) => write_string(s, out)?,
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_string(key, out)?;
+                out.push(b':');
+                write_value(item, out)?;
+            }
+            out.push(b'}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut Vec<u8>) -> Result<()> {
+    // serde_json escapes exactly the characters JCS requires, in the same form
+    let encoded = serde_json::to_vec(s)
+        .map_err(|e| PqcError::InvalidInput(format!("Cannot encode string: {}", e)))?;
+    out.extend_from_slice(&encoded);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keys_sorted_without_whitespace() {
+        let value = json!({ "b": 1, "a": [true, null, "x"], "c": { "z": 0, "y": -5 } });
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            br#"{"a":[true,null,"x"],"b":1,"c":{"y":-5,"z":0}}"#
+        );
+    }
+
+    #[test]
+    fn test_utf16_key_order() {
+        // U+1F600 sorts before U+FB01 in UTF-16 but after it in UTF-8
+        let value = json!({ "\u{fb01}": 1, "\u{1f600}": 2 });
+        let encoded = String::from_utf8(to_canonical_json(&value).unwrap()).unwrap();
+        assert!(encoded.find('\u{1f600}').unwrap() < encoded.find('\u{fb01}').unwrap());
+    }
+
+    #[test]
+    fn test_string_escaping() {
+        let value = json!({ "k": "a|b\n\"\u{0001}" });
+        assert_eq!(to_canonical_json(&value).unwrap(), br#"{"k":"a|b\n\"\u0001"}"#);
+    }
+
+    #[test]
+    fn test_floats_rejected() {
+        assert!(to_canonical_json(&json!({ "f": 1.5 })).is_err());
+    }
+}
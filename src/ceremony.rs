@@ -0,0 +1,656 @@
+//! Root key generation ceremonies
+//!
+//! A [`Ceremony`] collects entropy from several parties: QRNG seed files and
+//! operator-supplied bytes. [`Ceremony::generate`] mixes them with fresh
+//! host entropy and derives a Dilithium root key from the result, so the key
+//! is unpredictable as long as any one contribution stays secret. The host
+//! contribution is never stored, so the contributors together cannot
+//! recompute the key either.
+//!
+//! Liboqs key generation draws its randomness from `OQS_randombytes`. For
+//! the length of key generation only, the ceremony installs a callback
+//! there. The callback serves a ChaCha20 stream keyed by the combined seed
+//! to the generating thread and the operating system RNG to every other
+//! caller. Afterwards liboqs is switched back to its system RNG, replacing
+//! any custom algorithm the application had installed. Ceremonies in one
+//! process run their key generation one at a time.
+//!
+//! The secret key is returned with a Shamir `threshold`-of-`n` split over
+//! GF(256) as [`KeyShare`]s for backup. The [`CeremonyTranscript`] records
+//! every contribution's commitment, the public key and a SHA-256 of each
+//! share, and is signed by the new key. Witnesses compare
+//! [`witness_fingerprint`] with the value read out during the ceremony.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt;
+#[cfg(feature = "liboqs")]
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+use crate::analysis::{self, Severity};
+use crate::binary_loader::load_kyber_binary;
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::policy::Artifact;
+use crate::sign::{self, DilithiumKeyPair, SigAlgorithm};
+use crate::util::ct;
+use crate::validation::{self, Validate};
+use crate::{canonical, fips, hash, kdf};
+
+/// Domain label for transcript signatures
+const TRANSCRIPT_DOMAIN: &str = "PQC-Algo/v1/ceremony-transcript";
+
+/// Domain label for contribution and share commitments
+const COMMITMENT_DOMAIN: &[u8] = b"PQC-Algo/v1/ceremony-commitment";
+
+/// Shortest accepted contribution, in bytes
+pub const MIN_CONTRIBUTION_LEN: usize = 32;
+
+/// Distinct contributors required besides the host
+pub const MIN_CONTRIBUTORS: usize = 2;
+
+/// Most contributions one ceremony accepts
+pub const MAX_CONTRIBUTIONS: usize = 64;
+
+/// Name under which the host's own entropy is recorded
+const HOST_CONTRIBUTOR: &str = "host";
+
+thread_local! {
+    static CEREMONY_RNG: RefCell<Option<ChaCha20Rng>> = const { RefCell::new(None) };
+}
+
+/// Held while a ceremony owns liboqs's RNG
+static RANDOMBYTES_OVERRIDE: Mutex<()> = Mutex::new(());
+
+/// Where a contribution came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContributionSource {
+    SeedFile,
+    Operator,
+    Host,
+}
+
+/// Public record of one contribution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContributionRecord {
+    pub contributor: String,
+    pub source: ContributionSource,
+    pub len: usize,
+    /// Hex SHA-256 binding the contributor to their bytes
+    pub commitment: String,
+}
+
+struct Contribution {
+    record: ContributionRecord,
+    entropy: Zeroizing<Vec<u8>>,
+}
+
+/// Contributions gathered for one root key
+pub struct Ceremony {
+    name: String,
+    algorithm: SigAlgorithm,
+    contributions: Vec<Contribution>,
+}
+
+/// Everything a ceremony produces
+pub struct CeremonyOutput {
+    pub keypair: DilithiumKeyPair,
+    pub shares: Vec<KeyShare>,
+    pub transcript: CeremonyTranscript,
+}
+
+impl Ceremony {
+    pub fn new(name: &str, algorithm: SigAlgorithm) -> Result<Self> {
+        fips::check_sig(algorithm)?;
+        validate_name("Ceremony name", name)?;
+        Ok(Self { name: name.to_string(), algorithm, contributions: Vec::new() })
+    }
+
+    /// Contribute a QRNG seed file, hex or binary; it must pass the seed lint
+    pub fn add_seed_file<P: AsRef<Path>>(&mut self, contributor: &str, path: P) -> Result<&ContributionRecord> {
+        let seed = Zeroizing::new(load_kyber_binary(path)?);
+        if let Some(finding) = analysis::lint(&Artifact::Seed(&seed)).into_iter().find(|f| f.severity == Severity::Error) {
+            return Err(PqcError::InvalidInput(format!("Seed file from {} rejected: {}", contributor, finding.message)));
+        }
+        self.add(contributor, ContributionSource::SeedFile, &seed)
+    }
+
+    /// Contribute bytes an operator brought, such as dice rolls or their own RNG output
+    pub fn add_operator_entropy(&mut self, operator: &str, entropy: &[u8]) -> Result<&ContributionRecord> {
+        self.add(operator, ContributionSource::Operator, entropy)
+    }
+
+    pub fn contributions(&self) -> impl Iterator<Item = &ContributionRecord> {
+        self.contributions.iter().map(|c| &c.record)
+    }
+
+    fn add(&mut self, contributor: &str, source: ContributionSource, entropy: &[u8]) -> Result<&ContributionRecord> {
+        validate_name("Contributor name", contributor)?;
+        if contributor == HOST_CONTRIBUTOR {
+            return Err(PqcError::InvalidInput(format!("Contributor name '{}' is reserved", HOST_CONTRIBUTOR)));
+        }
+        if self.contributions.len() >= MAX_CONTRIBUTIONS {
+            return Err(PqcError::InvalidInput(format!("A ceremony takes at most {} contributions", MAX_CONTRIBUTIONS)));
+        }
+        if entropy.len() < MIN_CONTRIBUTION_LEN {
+            return Err(PqcError::InvalidInput(format!(
+                "Contribution from {} is {} bytes; at least {} are required", contributor, entropy.len(), MIN_CONTRIBUTION_LEN
+            )));
+        }
+        if self.contributions.iter().any(|c| ct::ct_eq(&c.entropy, entropy)) {
+            return Err(PqcError::InvalidInput(format!("Contribution from {} was already added", contributor)));
+        }
+        self.push(contributor, source, entropy);
+        Ok(&self.contributions[self.contributions.len() - 1].record)
+    }
+
+    fn push(&mut self, contributor: &str, source: ContributionSource, entropy: &[u8]) {
+        let record = ContributionRecord {
+            contributor: contributor.to_string(),
+            source,
+            len: entropy.len(),
+            commitment: hex::encode(hash::sha256(&[COMMITMENT_DOMAIN, &[0], contributor.as_bytes(), &[0], entropy])),
+        };
+        self.contributions.push(Contribution { record, entropy: Zeroizing::new(entropy.to_vec()) });
+    }
+
+    /// Mix in host entropy, generate the root key and split it into `share_count` shares
+    ///
+    /// Any `threshold` of the shares recover the secret key with [`recover_secret_key`].
+    pub fn generate(mut self, threshold: u8, share_count: u8) -> Result<CeremonyOutput> {
+        if threshold < 2 || threshold > share_count {
+            return Err(PqcError::InvalidInput(format!(
+                "Threshold must be between 2 and the share count, got {} of {}", threshold, share_count
+            )));
+        }
+        let contributors: BTreeSet<&str> = self.contributions.iter().map(|c| c.record.contributor.as_str()).collect();
+        if contributors.len() < MIN_CONTRIBUTORS {
+            return Err(PqcError::InvalidInput(format!(
+                "A ceremony needs contributions from at least {} parties, got {}", MIN_CONTRIBUTORS, contributors.len()
+            )));
+        }
+
+        let mut host = Zeroizing::new(vec![0u8; MIN_CONTRIBUTION_LEN]);
+        rand::rngs::OsRng.fill_bytes(&mut host);
+        self.push(HOST_CONTRIBUTOR, ContributionSource::Host, &host);
+
+        let seed = self.combined_seed()?;
+        let keypair = keypair_from_seed(self.algorithm, &seed)?;
+        let fingerprint = key_fingerprint(&keypair.public_key);
+        let shares: Vec<KeyShare> = shamir::split(&keypair.secret_key, threshold, share_count)
+            .into_iter()
+            .enumerate()
+            .map(|(i, data)| KeyShare {
+                ceremony: self.name.clone(),
+                key_fingerprint: fingerprint.clone(),
+                algorithm: self.algorithm,
+                index: i as u8 + 1,
+                threshold,
+                share_count,
+                data,
+            })
+            .collect();
+
+        let mut transcript = CeremonyTranscript {
+            ceremony: self.name.clone(),
+            algorithm: self.algorithm,
+            performed_at: unix_now(),
+            contributions: self.contributions.iter().map(|c| c.record.clone()).collect(),
+            public_key: keypair.public_key.clone(),
+            key_fingerprint: fingerprint,
+            threshold,
+            share_commitments: shares.iter().map(KeyShare::commitment).collect(),
+            signature: Vec::new(),
+        };
+        transcript.signature = sign::sign_with(self.algorithm, &transcript.signing_bytes()?, &keypair.secret_key)?;
+        Ok(CeremonyOutput { keypair, shares, transcript })
+    }
+
+    /// Length-prefixed contributions through HKDF, salted with the ceremony name
+    fn combined_seed(&self) -> Result<Zeroizing<[u8; 32]>> {
+        let mut ikm = Zeroizing::new(Vec::new());
+        for contribution in &self.contributions {
+            for part in [contribution.record.contributor.as_bytes(), &contribution.entropy] {
+                ikm.extend_from_slice(&(part.len() as u32).to_be_bytes());
+                ikm.extend_from_slice(part);
+            }
+        }
+        let okm = Zeroizing::new(kdf::hkdf_sha256(&ikm, self.name.as_bytes(), &kdf::label("ceremony-seed"), 32)?);
+        let mut seed = Zeroizing::new([0u8; 32]);
+        seed.copy_from_slice(&okm);
+        Ok(seed)
+    }
+}
+
+/// Liboqs randomness: the ceremony stream on its thread, the OS RNG everywhere else
+#[cfg(feature = "liboqs")]
+unsafe extern "C" fn ceremony_randombytes(random_array: *mut u8, bytes_to_read: usize) {
+    if random_array.is_null() || bytes_to_read == 0 {
+        return;
+    }
+    // SAFETY: liboqs passes a buffer valid for `bytes_to_read` bytes
+    let out = unsafe { std::slice::from_raw_parts_mut(random_array, bytes_to_read) };
+    let filled = panic::catch_unwind(AssertUnwindSafe(|| CEREMONY_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(rng) => {
+            rng.fill_bytes(out);
+            Ok(())
+        }
+        None => rand::rngs::OsRng.try_fill_bytes(out),
+    })));
+    // Unwinding into C is undefined and an unfilled buffer would yield a
+    // predictable key, so fail the way liboqs's own system RNG does
+    if !matches!(filled, Ok(Ok(()))) {
+        std::process::abort();
+    }
+}
+
+/// Routes `OQS_randombytes` to [`ceremony_randombytes`] until dropped
+struct RandombytesOverride {
+    _exclusive: MutexGuard<'static, ()>,
+}
+
+impl RandombytesOverride {
+    fn install(seed: &[u8; 32]) -> Self {
+        let exclusive = RANDOMBYTES_OVERRIDE.lock().unwrap_or_else(|p| p.into_inner());
+        CEREMONY_RNG.with(|rng| *rng.borrow_mut() = Some(ChaCha20Rng::from_seed(*seed)));
+        // SAFETY: the callback is a plain function that only writes within the buffer it is given
+        #[cfg(feature = "liboqs")]
+        unsafe { oqs_sys::rand::OQS_randombytes_custom_algorithm(Some(ceremony_randombytes)) };
+        Self { _exclusive: exclusive }
+    }
+}
+
+impl Drop for RandombytesOverride {
+    fn drop(&mut self) {
+        // SAFETY: the algorithm name is a NUL-terminated constant
+        #[cfg(feature = "liboqs")]
+        unsafe { oqs_sys::rand::OQS_randombytes_switch_algorithm(oqs_sys::rand::OQS_RAND_alg_system.as_ptr().cast()) };
+        CEREMONY_RNG.with(|rng| *rng.borrow_mut() = None);
+    }
+}
+
+fn keypair_from_seed(algorithm: SigAlgorithm, seed: &[u8; 32]) -> Result<DilithiumKeyPair> {
+    crate::backend::init()?;
+    let _override = RandombytesOverride::install(seed);
+    sign::generate_keypair_with(algorithm)
+}
+
+/// Signed record of a ceremony, for witnesses and auditors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CeremonyTranscript {
+    pub ceremony: String,
+    pub algorithm: SigAlgorithm,
+    pub performed_at: u64,
+    pub contributions: Vec<ContributionRecord>,
+    #[serde(with = "crate::util::bytes")]
+    pub public_key: Vec<u8>,
+    pub key_fingerprint: String,
+    pub threshold: u8,
+    /// [`KeyShare::commitment`] of each share, in index order
+    pub share_commitments: Vec<String>,
+    /// By the generated root key
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`CeremonyTranscript`] covered by its signature
+#[derive(Serialize)]
+struct TranscriptFields<'a> {
+    ceremony: &'a str,
+    algorithm: &'a str,
+    performed_at: u64,
+    contributions: &'a [ContributionRecord],
+    public_key: String,
+    key_fingerprint: &'a str,
+    threshold: u8,
+    share_commitments: &'a [String],
+}
+
+impl CeremonyTranscript {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode ceremony transcript: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "ceremony transcript")
+    }
+
+    /// Check the self-signature and that the fingerprint names the public key
+    pub fn verify(&self) -> Result<()> {
+        self.validate()?;
+        fips::check_sig(self.algorithm)?;
+        if !ct::ct_eq_hex(&key_fingerprint(&self.public_key), &self.key_fingerprint) {
+            return Err(PqcError::Verification("Transcript fingerprint does not match its public key".to_string()));
+        }
+        if !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, &self.public_key)? {
+            return Err(PqcError::Verification("Ceremony transcript signature is invalid".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Check that `share` is one of the shares this ceremony produced
+    pub fn check_share(&self, share: &KeyShare) -> Result<()> {
+        let expected = (share.index as usize).checked_sub(1).and_then(|i| self.share_commitments.get(i));
+        let matches = expected.is_some_and(|commitment| ct::ct_eq_hex(commitment, &share.commitment()));
+        if !matches || share.ceremony != self.ceremony || share.threshold != self.threshold {
+            return Err(PqcError::Verification(format!("Share {} does not belong to ceremony {}", share.index, self.ceremony)));
+        }
+        Ok(())
+    }
+
+    pub fn witness_fingerprint(&self) -> String {
+        witness_fingerprint(&self.public_key)
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(TRANSCRIPT_DOMAIN, &TranscriptFields {
+            ceremony: &self.ceremony,
+            algorithm: self.algorithm.name(),
+            performed_at: self.performed_at,
+            contributions: &self.contributions,
+            public_key: hex::encode(&self.public_key),
+            key_fingerprint: &self.key_fingerprint,
+            threshold: self.threshold,
+            share_commitments: &self.share_commitments,
+        })
+    }
+}
+
+impl Validate for CeremonyTranscript {
+    fn validate(&self) -> Result<()> {
+        validate_name("Ceremony name", &self.ceremony)?;
+        validation::check_max_len("Ceremony contributions", self.contributions.len(), MAX_CONTRIBUTIONS + 1)?;
+        for contribution in &self.contributions {
+            validate_name("Contributor name", &contribution.contributor)?;
+            validation::check_fingerprint("Contribution commitment", &contribution.commitment)?;
+        }
+        validation::check_exact_len("Root public key", self.public_key.len(), self.algorithm.public_key_len()?)?;
+        validation::check_fingerprint("Root key fingerprint", &self.key_fingerprint)?;
+        if self.threshold < 2 || self.threshold as usize > self.share_commitments.len() {
+            return Err(PqcError::InvalidInput("Transcript threshold exceeds its share count".to_string()));
+        }
+        for commitment in &self.share_commitments {
+            validation::check_fingerprint("Share commitment", commitment)?;
+        }
+        validation::check_signature_len("Transcript signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// Public key fingerprint in groups of four, for reading aloud
+pub fn witness_fingerprint(public_key: &[u8]) -> String {
+    let fingerprint = key_fingerprint(public_key);
+    fingerprint.as_bytes()
+        .chunks(4)
+        .map(|group| std::str::from_utf8(group).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// One custodian's share of the root secret key
+#[derive(Serialize, Deserialize)]
+pub struct KeyShare {
+    pub ceremony: String,
+    pub key_fingerprint: String,
+    pub algorithm: SigAlgorithm,
+    /// 1-based x coordinate of the share
+    pub index: u8,
+    pub threshold: u8,
+    pub share_count: u8,
+    #[serde(with = "crate::util::bytes")]
+    pub data: Vec<u8>,
+}
+
+/// Prints the share's coordinates, never its data
+impl fmt::Debug for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyShare")
+            .field("ceremony", &self.ceremony)
+            .field("key_fingerprint", &self.key_fingerprint)
+            .field("index", &format_args!("{} of {}", self.index, self.share_count))
+            .field("threshold", &self.threshold)
+            .field("data", &format_args!("[redacted; {} bytes]", self.data.len()))
+            .finish()
+    }
+}
+
+impl Drop for KeyShare {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+impl KeyShare {
+    pub fn to_bytes(&self) -> Result<Zeroizing<Vec<u8>>> {
+        serde_json::to_vec_pretty(self)
+            .map(Zeroizing::new)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode key share: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "key share")
+    }
+
+    /// Hex SHA-256 of the share, as listed in the transcript
+    pub fn commitment(&self) -> String {
+        hex::encode(hash::sha256(&[
+            COMMITMENT_DOMAIN, &[0], self.key_fingerprint.as_bytes(), &[0, self.index, self.threshold, self.share_count], &self.data,
+        ]))
+    }
+}
+
+impl Validate for KeyShare {
+    fn validate(&self) -> Result<()> {
+        validate_name("Ceremony name", &self.ceremony)?;
+        validation::check_fingerprint("Share key fingerprint", &self.key_fingerprint)?;
+        if self.index == 0 || self.index > self.share_count || self.threshold < 2 || self.threshold > self.share_count {
+            return Err(PqcError::InvalidInput(format!(
+                "Share {} of {} with threshold {} is inconsistent", self.index, self.share_count, self.threshold
+            )));
+        }
+        validation::check_exact_len("Share data", self.data.len(), self.algorithm.sizes().secret_key)
+    }
+}
+
+/// Recombine `threshold` shares checked against the transcript
+///
+/// The recovered key is tested by signing and verifying under the
+/// transcript's public key before it is returned.
+pub fn recover_secret_key(shares: &[KeyShare], transcript: &CeremonyTranscript) -> Result<Zeroizing<Vec<u8>>> {
+    transcript.verify()?;
+    let mut indices = BTreeSet::new();
+    for share in shares {
+        transcript.check_share(share)?;
+        if !indices.insert(share.index) {
+            return Err(PqcError::InvalidInput(format!("Share {} was given twice", share.index)));
+        }
+    }
+    let threshold = transcript.threshold as usize;
+    if shares.len() < threshold {
+        return Err(PqcError::InvalidInput(format!(
+            "Recovery needs {} shares, got {}", threshold, shares.len()
+        )));
+    }
+
+    let points: Vec<(u8, &[u8])> = shares[..threshold].iter().map(|s| (s.index, s.data.as_slice())).collect();
+    let secret_key = Zeroizing::new(shamir::combine(&points));
+    let probe = kdf::label("ceremony-recovery-check");
+    let signature = sign::sign_with(transcript.algorithm, &probe, &secret_key)?;
+    if !sign::verify_with(transcript.algorithm, &probe, &signature, &transcript.public_key)? {
+        return Err(PqcError::Verification("Recovered key does not match the ceremony public key".to_string()));
+    }
+    Ok(secret_key)
+}
+
+fn validate_name(what: &str, name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > validation::MAX_LABEL_LEN || name.chars().any(char::is_control) {
+        return Err(PqcError::InvalidInput(format!(
+            "{} must be 1 to {} bytes without control characters", what, validation::MAX_LABEL_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Shamir secret sharing over GF(2^8) with the AES polynomial
+mod shamir {
+    use rand::RngCore;
+    use zeroize::Zeroizing;
+
+    /// Shares for x = 1..=count, each as long as `secret`
+    pub(super) fn split(secret: &[u8], threshold: u8, count: u8) -> Vec<Vec<u8>> {
+        let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+        let mut shares = vec![Vec::with_capacity(secret.len()); count as usize];
+        for &byte in secret {
+            coefficients[0] = byte;
+            rand::rngs::OsRng.fill_bytes(&mut coefficients[1..]);
+            for (i, share) in shares.iter_mut().enumerate() {
+                let x = i as u8 + 1;
+                // Horner's rule from the highest coefficient down
+                share.push(coefficients.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c));
+            }
+        }
+        shares
+    }
+
+    /// Lagrange interpolation at x = 0; the points must have distinct, non-zero x
+    pub(super) fn combine(points: &[(u8, &[u8])]) -> Vec<u8> {
+        let len = points.first().map_or(0, |(_, y)| y.len());
+        let mut secret = vec![0u8; len];
+        for (i, &(xi, yi)) in points.iter().enumerate() {
+            let mut basis = 1u8;
+            for (j, &(xj, _)) in points.iter().enumerate() {
+                if i != j {
+                    basis = mul(basis, mul(xj, inverse(xj ^ xi)));
+                }
+            }
+            for (out, &y) in secret.iter_mut().zip(yi) {
+                *out ^= mul(y, basis);
+            }
+        }
+        secret
+    }
+
+    /// Branch-free multiplication, so share bytes do not steer timing
+    fn mul(mut a: u8, mut b: u8) -> u8 {
+        let mut product = 0u8;
+        for _ in 0..8 {
+            product ^= a & 0u8.wrapping_sub(b & 1);
+            let carry = 0u8.wrapping_sub(a >> 7);
+            a = (a << 1) ^ (0x1b & carry);
+            b >>= 1;
+        }
+        product
+    }
+
+    /// a^254, the multiplicative inverse for non-zero a
+    fn inverse(a: u8) -> u8 {
+        let mut result = 1u8;
+        let mut base = a;
+        let mut exponent = 254u8;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = mul(result, base);
+            }
+            base = mul(base, base);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_any_threshold_subset_recovers() {
+            let secret = b"root key material".to_vec();
+            let shares = split(&secret, 3, 5);
+            for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+                let points: Vec<(u8, &[u8])> = subset.iter().map(|&i| (i as u8 + 1, shares[i].as_slice())).collect();
+                assert_eq!(combine(&points), secret);
+            }
+            let too_few: Vec<(u8, &[u8])> = vec![(1, &shares[0]), (2, &shares[1])];
+            assert_ne!(combine(&too_few), secret);
+            assert_eq!((1..=255u8).filter(|&a| mul(a, inverse(a)) != 1).count(), 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ceremony() -> Ceremony {
+        let mut ceremony = Ceremony::new("root-2026", SigAlgorithm::MlDsa65).unwrap();
+        let mut entropy = [0u8; 64];
+        rand::rngs::OsRng.fill_bytes(&mut entropy);
+        ceremony.add_operator_entropy("alice", &entropy).unwrap();
+        rand::rngs::OsRng.fill_bytes(&mut entropy);
+        ceremony.add_operator_entropy("bob", &entropy).unwrap();
+        ceremony
+    }
+
+    #[test]
+    fn test_ceremony_transcript_and_recovery() {
+        let output = ceremony().generate(2, 3).unwrap();
+        let transcript = CeremonyTranscript::from_bytes(&output.transcript.to_bytes().unwrap()).unwrap();
+        transcript.verify().unwrap();
+        assert_eq!(transcript.contributions.len(), 3);
+        assert_eq!(transcript.contributions[2].source, ContributionSource::Host);
+        assert_eq!(transcript.witness_fingerprint().replace(' ', ""), key_fingerprint(&output.keypair.public_key));
+
+        let shares: Vec<KeyShare> = output.shares.iter()
+            .map(|share| KeyShare::from_bytes(&share.to_bytes().unwrap()).unwrap())
+            .collect();
+        let recovered = recover_secret_key(&shares[1..], &transcript).unwrap();
+        assert_eq!(recovered.as_slice(), output.keypair.secret_key.as_slice());
+        assert!(recover_secret_key(&shares[..1], &transcript).is_err());
+
+        let mut tampered = transcript.clone();
+        tampered.threshold = 3;
+        assert!(matches!(tampered.verify(), Err(PqcError::Verification(_))));
+        let mut forged = KeyShare::from_bytes(&shares[0].to_bytes().unwrap()).unwrap();
+        forged.data[0] ^= 1;
+        assert!(transcript.check_share(&forged).is_err());
+    }
+
+    #[test]
+    fn test_rejects_weak_or_missing_contributions() {
+        let mut ceremony = Ceremony::new("root-2026", SigAlgorithm::MlDsa65).unwrap();
+        assert!(ceremony.add_operator_entropy("alice", &[7u8; 16]).is_err());
+        let mut entropy = [0u8; 64];
+        rand::rngs::OsRng.fill_bytes(&mut entropy);
+        ceremony.add_operator_entropy("alice", &entropy).unwrap();
+        assert!(ceremony.add_operator_entropy("bob", &entropy).is_err());
+        assert!(ceremony.add_operator_entropy(HOST_CONTRIBUTOR, &[1u8; 64]).is_err());
+
+        let path = std::env::temp_dir().join(format!("pqc_ceremony_seed_{}", std::process::id()));
+        std::fs::write(&path, [0u8; 64]).unwrap();
+        assert!(ceremony.add_seed_file("carol", &path).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        let mut lonely = Ceremony::new("root-2026", SigAlgorithm::MlDsa65).unwrap();
+        lonely.add_operator_entropy("alice", &entropy).unwrap();
+        assert!(lonely.generate(2, 3).is_err());
+        assert!(self::ceremony().generate(4, 3).is_err());
+    }
+
+    #[test]
+    fn test_seeded_keygen_is_deterministic() {
+        let seed = [42u8; 32];
+        let first = keypair_from_seed(SigAlgorithm::MlDsa44, &seed).unwrap();
+        let second = keypair_from_seed(SigAlgorithm::MlDsa44, &seed).unwrap();
+        let other = keypair_from_seed(SigAlgorithm::MlDsa44, &[43u8; 32]).unwrap();
+        assert_eq!(first.public_key, second.public_key);
+        assert_ne!(first.public_key, other.public_key);
+        // Outside a ceremony liboqs gets fresh randomness again
+        let fresh = sign::generate_keypair_with(SigAlgorithm::MlDsa44).unwrap();
+        assert_ne!(fresh.public_key, first.public_key);
+    }
+}
@@ -0,0 +1,492 @@
+//! Certificates issued by provisioning authorities
+//!
+//! A [`Certificate`] binds a subject name to a Dilithium public key under an
+//! issuer's signature. Several authorities can coexist: our OEM root and a
+//! customer's root each issue their own chains, and each can cross-sign the
+//! other's root with [`CertificateAuthority::cross_sign`]. A verifier trusts
+//! only the anchors in its [`TrustAnchors`], and [`verify_chain`] builds a
+//! path from the leaf to any of them through whatever intermediates and
+//! cross-certificates it is given.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::lifecycle::{self, LifecycleEvent};
+use crate::error::{PqcError, Result};
+use crate::extensions::{ExtensionFields, ExtensionValidators, Extensions};
+use crate::secret::SecretKey;
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, Validate};
+use crate::clock::{self, ValidityOptions};
+use crate::{canonical, fips};
+
+/// Domain label for certificate signatures
+const CERTIFICATE_DOMAIN: &str = "PQC-Algo/v1/certificate";
+
+/// Longest path [`verify_chain`] will build, leaf included
+pub const MAX_CHAIN_LEN: usize = 8;
+
+/// Most signature checks one [`verify_chain`] call makes before giving up
+pub const MAX_SIGNATURE_CHECKS: usize = 64;
+
+/// A subject's public key, signed by its issuer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Certificate {
+    pub serial: String,
+    pub subject: String,
+    #[serde(with = "crate::util::bytes")]
+    pub public_key: Vec<u8>,
+    pub issuer: String,
+    pub issuer_fingerprint: String,
+    /// Algorithm of the issuer's signature
+    pub algorithm: SigAlgorithm,
+    pub not_before: u64,
+    pub not_after: u64,
+    /// Whether the subject may issue certificates
+    pub is_authority: bool,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+    /// Custom metadata; covered by the signature
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
+}
+
+/// Fields of a [`Certificate`] covered by its signature
+#[derive(Serialize)]
+struct CertificateFields<'a> {
+    serial: &'a str,
+    subject: &'a str,
+    public_key: String,
+    issuer: &'a str,
+    issuer_fingerprint: &'a str,
+    algorithm: &'a str,
+    not_before: u64,
+    not_after: u64,
+    is_authority: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    extensions: BTreeMap<&'a str, ExtensionFields>,
+}
+
+impl Certificate {
+    /// Hex SHA-256 of the subject's public key
+    pub fn fingerprint(&self) -> String {
+        key_fingerprint(&self.public_key)
+    }
+
+    /// Whether `now` is inside the validity window, give or take `validity`'s skew
+    pub fn is_valid_at(&self, now: u64, validity: &ValidityOptions) -> bool {
+        validity.contains(self.not_before, self.not_after, now)
+    }
+
+    /// Whether the certificate is within `validity`'s renewal margin of expiring at `now`
+    pub fn needs_renewal_at(&self, now: u64, validity: &ValidityOptions) -> bool {
+        validity.needs_renewal(self.not_after, now)
+    }
+
+    /// Check the signature under the issuer's public key
+    ///
+    /// A signed certificate with a critical extension is an error; use
+    /// [`Certificate::verify_signature_with_extensions`] to accept some.
+    pub fn verify_signature(&self, issuer_public_key: &[u8]) -> Result<bool> {
+        self.verify_signature_with_extensions(issuer_public_key, &ExtensionValidators::new())
+    }
+
+    /// [`Certificate::verify_signature`], checking extensions with `validators`
+    ///
+    /// A signed certificate whose extensions fail [`Extensions::check`] is an error.
+    pub fn verify_signature_with_extensions(&self, issuer_public_key: &[u8], validators: &ExtensionValidators) -> Result<bool> {
+        fips::check_sig(self.algorithm)?;
+        if !ct::ct_eq_hex(&self.issuer_fingerprint, &key_fingerprint(issuer_public_key))
+            || !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, issuer_public_key)?
+        {
+            return Ok(false);
+        }
+        self.extensions.check(validators)?;
+        Ok(true)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode certificate: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "certificate")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(CERTIFICATE_DOMAIN, &CertificateFields {
+            serial: &self.serial,
+            subject: &self.subject,
+            public_key: hex::encode(&self.public_key),
+            issuer: &self.issuer,
+            issuer_fingerprint: &self.issuer_fingerprint,
+            algorithm: self.algorithm.name(),
+            not_before: self.not_before,
+            not_after: self.not_after,
+            is_authority: self.is_authority,
+            extensions: self.extensions.signing_fields(),
+        })
+    }
+}
+
+impl Validate for Certificate {
+    fn validate(&self) -> Result<()> {
+        for (what, text) in [("Certificate serial", &self.serial), ("Certificate subject", &self.subject), ("Certificate issuer", &self.issuer)] {
+            validation::check_max_len(what, text.len(), validation::MAX_LABEL_LEN)?;
+        }
+        validation::check_fingerprint("Issuer fingerprint", &self.issuer_fingerprint)?;
+        validation::check_any_public_key_len("Certificate public key", self.public_key.len())?;
+        self.extensions.validate()?;
+        validation::check_signature_len("Certificate signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// An authority's name and signing key
+pub struct CertificateAuthority {
+    pub name: String,
+    pub algorithm: SigAlgorithm,
+    public_key: Vec<u8>,
+    secret_key: SecretKey,
+}
+
+impl CertificateAuthority {
+    pub fn new(name: &str, algorithm: SigAlgorithm, public_key: Vec<u8>, secret_key: SecretKey) -> Self {
+        Self { name: name.to_string(), algorithm, public_key, secret_key }
+    }
+
+    /// New authority with a fresh key under the active default suite
+    pub fn generate(name: &str) -> Result<Self> {
+        let algorithm = CipherSuite::active_default().sig;
+        let keypair = sign::generate_keypair_with(algorithm)?;
+        lifecycle::emit(LifecycleEvent::generated(&keypair.public_key, algorithm.name()).with_subject(name));
+        Ok(Self::new(name, algorithm, keypair.public_key, keypair.secret_key.into()))
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn fingerprint(&self) -> String {
+        key_fingerprint(&self.public_key)
+    }
+
+    /// Sign `message` with the authority key
+    pub(crate) fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        fips::check_sig(self.algorithm)?;
+        sign::sign_with(self.algorithm, message, &self.secret_key)
+    }
+
+    /// Self-signed root certificate for this authority
+    pub fn root_certificate(&self, lifetime: Duration) -> Result<Certificate> {
+        self.issue(&self.name, &self.public_key, lifetime, true)
+    }
+
+    /// Certify `subject_public_key`, valid from now for `lifetime`
+    pub fn issue(&self, subject: &str, subject_public_key: &[u8], lifetime: Duration, is_authority: bool) -> Result<Certificate> {
+        let now = unix_now();
+        self.issue_at(subject, subject_public_key, now, now.saturating_add(lifetime.as_secs()), is_authority)
+    }
+
+    /// [`CertificateAuthority::issue`] with extensions under the signature
+    pub fn issue_with_extensions(&self, subject: &str, subject_public_key: &[u8], lifetime: Duration, is_authority: bool, extensions: Extensions) -> Result<Certificate> {
+        let now = unix_now();
+        self.sign_certificate(subject, subject_public_key, now, now.saturating_add(lifetime.as_secs()), is_authority, extensions)
+    }
+
+    /// [`CertificateAuthority::issue`] with an explicit validity window
+    pub fn issue_at(&self, subject: &str, subject_public_key: &[u8], not_before: u64, not_after: u64, is_authority: bool) -> Result<Certificate> {
+        self.sign_certificate(subject, subject_public_key, not_before, not_after, is_authority, Extensions::new())
+    }
+
+    fn sign_certificate(&self, subject: &str, subject_public_key: &[u8], not_before: u64, not_after: u64, is_authority: bool, extensions: Extensions) -> Result<Certificate> {
+        fips::check_sig(self.algorithm)?;
+        extensions.validate()?;
+        if subject.is_empty() || not_after < not_before {
+            return Err(PqcError::InvalidInput("Certificate needs a subject and a non-empty validity window".to_string()));
+        }
+        let mut serial = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut serial);
+
+        let mut certificate = Certificate {
+            serial: hex::encode(serial),
+            subject: subject.to_string(),
+            public_key: subject_public_key.to_vec(),
+            issuer: self.name.clone(),
+            issuer_fingerprint: self.fingerprint(),
+            algorithm: self.algorithm,
+            not_before,
+            not_after,
+            is_authority,
+            signature: Vec::new(),
+            extensions,
+        };
+        certificate.signature = sign::sign_with(self.algorithm, &certificate.signing_bytes()?, &self.secret_key)?;
+        Ok(certificate)
+    }
+
+    /// Vouch for another authority, so our relying parties accept its chains
+    ///
+    /// The cross-certificate carries `other`'s subject and key under our
+    /// signature; hand it out alongside that authority's intermediates.
+    pub fn cross_sign(&self, other: &Certificate, lifetime: Duration) -> Result<Certificate> {
+        if !other.is_authority {
+            return Err(PqcError::InvalidInput(format!("{} is not an authority certificate", other.subject)));
+        }
+        self.issue(&other.subject, &other.public_key, lifetime, true)
+    }
+}
+
+/// A public key trusted by configuration rather than by certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustAnchor {
+    pub name: String,
+    #[serde(with = "crate::util::bytes")]
+    pub public_key: Vec<u8>,
+}
+
+/// The authorities a verifier trusts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustAnchors {
+    anchors: Vec<TrustAnchor>,
+    /// Extensions this verifier understands on every certificate of a path
+    #[serde(skip)]
+    validators: ExtensionValidators,
+}
+
+impl TrustAnchors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check certificate extensions on verified paths with `validators`
+    pub fn with_extension_validators(mut self, validators: ExtensionValidators) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    pub fn add(&mut self, name: &str, public_key: &[u8]) {
+        self.anchors.push(TrustAnchor { name: name.to_string(), public_key: public_key.to_vec() });
+    }
+
+    /// Trust the subject key of a root certificate obtained out of band
+    pub fn add_certificate(&mut self, certificate: &Certificate) {
+        self.add(&certificate.subject, &certificate.public_key);
+    }
+
+    pub fn anchors(&self) -> &[TrustAnchor] {
+        &self.anchors
+    }
+
+    fn find(&self, fingerprint: &str) -> impl Iterator<Item = &TrustAnchor> {
+        let fingerprint = fingerprint.to_string();
+        self.anchors.iter().filter(move |anchor| ct::ct_eq_hex(&key_fingerprint(&anchor.public_key), &fingerprint))
+    }
+}
+
+/// A verified path from a leaf certificate to a trust anchor
+#[derive(Debug, Clone)]
+pub struct CertificationPath {
+    /// Leaf first; each certificate was issued by the key of the next
+    pub certificates: Vec<Certificate>,
+    /// The anchor whose key signed the last certificate
+    pub anchor: TrustAnchor,
+}
+
+/// Build and verify a path from `leaf` to one of `anchors` at the current time
+///
+/// `intermediates` can hold intermediate, cross- and root certificates from
+/// any number of authorities in any order. Self-signed certificates are
+/// never used as links; trust in a root comes only from `anchors`. No key
+/// appears twice on a path, so authorities that cross-sign each other
+/// cannot send the search in circles. Duplicate certificates are tried
+/// once, and the search fails after [`MAX_SIGNATURE_CHECKS`] signature
+/// checks, so a crafted bundle cannot make it run for long.
+pub fn verify_chain(leaf: &Certificate, intermediates: &[Certificate], anchors: &TrustAnchors) -> Result<CertificationPath> {
+    verify_chain_at(leaf, intermediates, anchors, unix_now(), &clock::validity())
+}
+
+/// [`verify_chain`] at an explicit time and tolerance
+pub fn verify_chain_at(
+    leaf: &Certificate,
+    intermediates: &[Certificate],
+    anchors: &TrustAnchors,
+    now: u64,
+    validity: &ValidityOptions,
+) -> Result<CertificationPath> {
+    if !leaf.is_valid_at(now, validity) {
+        return Err(PqcError::Verification(format!("Certificate for {} is outside its validity window", leaf.subject)));
+    }
+    let mut path = vec![leaf.clone()];
+    PathSearch { intermediates, anchors, now, validity, checks: 0 }.extend(&mut path)?
+        .map(|anchor| CertificationPath { certificates: path, anchor })
+        .ok_or_else(|| PqcError::Verification(format!("No path from {} to a trust anchor", leaf.subject)))
+}
+
+/// State of one depth-first path search
+struct PathSearch<'a> {
+    intermediates: &'a [Certificate],
+    anchors: &'a TrustAnchors,
+    now: u64,
+    validity: &'a ValidityOptions,
+    /// Signature checks made so far
+    checks: usize,
+}
+
+impl PathSearch<'_> {
+    fn verify(&mut self, certificate: &Certificate, issuer_public_key: &[u8]) -> Result<bool> {
+        if self.checks == MAX_SIGNATURE_CHECKS {
+            return Err(PqcError::Verification(format!(
+                "Gave up building a path for {} after {} signature checks", certificate.subject, MAX_SIGNATURE_CHECKS
+            )));
+        }
+        self.checks += 1;
+        certificate.verify_signature_with_extensions(issuer_public_key, &self.anchors.validators)
+    }
+
+    /// Extend `path` from its last certificate until it reaches an anchor
+    fn extend(&mut self, path: &mut Vec<Certificate>) -> Result<Option<TrustAnchor>> {
+        let current = path.last().expect("path starts with the leaf").clone();
+        for anchor in self.anchors.find(&current.issuer_fingerprint) {
+            if self.verify(&current, &anchor.public_key)? {
+                return Ok(Some(anchor.clone()));
+            }
+        }
+        if path.len() == MAX_CHAIN_LEN || path.iter().any(|c| ct::ct_eq_hex(&c.fingerprint(), &current.issuer_fingerprint)) {
+            return Ok(None);
+        }
+
+        // Candidates all carry the issuer key `current` names, so one check covers
+        // them; those with the same issuer lead to the same place, so keep one
+        let mut issuers = HashSet::new();
+        let candidates: Vec<&Certificate> = self.intermediates.iter()
+            .filter(|candidate| candidate.is_authority
+                && candidate.fingerprint() != candidate.issuer_fingerprint
+                && candidate.is_valid_at(self.now, self.validity)
+                && ct::ct_eq_hex(&candidate.fingerprint(), &current.issuer_fingerprint))
+            .filter(|candidate| issuers.insert(candidate.issuer_fingerprint.clone()))
+            .collect();
+        let Some(first) = candidates.first() else {
+            return Ok(None);
+        };
+        if !self.verify(&current, &first.public_key)? {
+            return Ok(None);
+        }
+        for candidate in candidates {
+            path.push(candidate.clone());
+            if let Some(anchor) = self.extend(path)? {
+                return Ok(Some(anchor));
+            }
+            path.pop();
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const YEAR: Duration = Duration::from_secs(365 * 24 * 3600);
+
+    #[test]
+    fn test_cross_signed_chains() {
+        let oem = CertificateAuthority::generate("OEM Root").unwrap();
+        let customer = CertificateAuthority::generate("Customer Root").unwrap();
+        let oem_root = oem.root_certificate(YEAR).unwrap();
+        let customer_root = customer.root_certificate(YEAR).unwrap();
+
+        let factory_key = sign::generate_keypair().unwrap();
+        let factory = CertificateAuthority::new("OEM Factory CA", oem.algorithm, factory_key.public_key.clone(), factory_key.secret_key.into());
+        let factory_cert = oem.issue("OEM Factory CA", factory.public_key(), YEAR, true).unwrap();
+        let device_key = sign::generate_keypair().unwrap();
+        let device = factory.issue("acme/sensor-7", &device_key.public_key, YEAR, false).unwrap();
+
+        let mut oem_anchors = TrustAnchors::new();
+        oem_anchors.add_certificate(&oem_root);
+        let path = verify_chain(&device, std::slice::from_ref(&factory_cert), &oem_anchors).unwrap();
+        assert_eq!(path.certificates.len(), 2);
+        assert_eq!(path.anchor.name, "OEM Root");
+
+        // The customer trusts only its own root until it cross-signs the OEM's
+        let mut customer_anchors = TrustAnchors::new();
+        customer_anchors.add_certificate(&customer_root);
+        assert!(verify_chain(&device, &[factory_cert.clone(), oem_root.clone()], &customer_anchors).is_err());
+
+        let cross = customer.cross_sign(&oem_root, YEAR).unwrap();
+        let back = oem.cross_sign(&customer_root, YEAR).unwrap();
+        let pool = [back, oem_root, factory_cert, cross];
+        let path = verify_chain(&device, &pool, &customer_anchors).unwrap();
+        assert_eq!(path.anchor.name, "Customer Root");
+        assert_eq!(path.certificates.iter().map(|c| c.subject.as_str()).collect::<Vec<_>>(), ["acme/sensor-7", "OEM Factory CA", "OEM Root"]);
+    }
+
+    #[test]
+    fn test_cross_signing_mesh_is_bounded() {
+        let authorities: Vec<CertificateAuthority> = (0..6).map(|i| CertificateAuthority::generate(&format!("CA {}", i)).unwrap()).collect();
+        let mut bundle = Vec::new();
+        for subject in &authorities {
+            for issuer in authorities.iter().filter(|issuer| issuer.name != subject.name) {
+                let cross = issuer.issue(&subject.name, subject.public_key(), YEAR, true).unwrap();
+                // Duplicates in the bundle are tried once
+                bundle.push(cross.clone());
+                bundle.push(cross);
+            }
+        }
+        let device_key = sign::generate_keypair().unwrap();
+        let device = authorities[0].issue("acme/sensor-7", &device_key.public_key, YEAR, false).unwrap();
+        let unrelated = CertificateAuthority::generate("Unrelated Root").unwrap();
+        let mut anchors = TrustAnchors::new();
+        anchors.add_certificate(&unrelated.root_certificate(YEAR).unwrap());
+
+        let started = std::time::Instant::now();
+        assert!(matches!(verify_chain(&device, &bundle, &anchors), Err(PqcError::Verification(_))));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // A path through the mesh is still found when one exists
+        anchors.add_certificate(&authorities[5].root_certificate(YEAR).unwrap());
+        let path = verify_chain(&device, &bundle, &anchors).unwrap();
+        assert_eq!(path.anchor.public_key, authorities[5].public_key());
+    }
+
+    #[test]
+    fn test_chain_rejects_bad_certificates() {
+        let root = CertificateAuthority::generate("Root").unwrap();
+        let mut anchors = TrustAnchors::new();
+        anchors.add("Root", root.public_key());
+        let device_key = sign::generate_keypair().unwrap();
+
+        let expired = root.issue_at("sensor-7", &device_key.public_key, 0, 10, false).unwrap();
+        assert!(verify_chain(&expired, &[], &anchors).is_err());
+        // A certificate from an issuer whose clock runs a minute fast is only fine with skew allowed
+        let now = unix_now();
+        let early = root.issue_at("sensor-7", &device_key.public_key, now + 60, now + 3600, false).unwrap();
+        assert!(verify_chain(&early, &[], &anchors).is_err());
+        verify_chain_at(&early, &[], &anchors, now, &ValidityOptions::TOLERANT).unwrap();
+        let future = root.issue_at("sensor-7", &device_key.public_key, now + 3600, now + 7200, false).unwrap();
+        assert!(verify_chain_at(&future, &[], &anchors, now, &ValidityOptions::TOLERANT).is_err());
+
+        let mut critical = Extensions::new();
+        critical.insert("1.3.6.1.4.1.55555.30", true, b"hardware-backed").unwrap();
+        let unknown = root.issue_with_extensions("sensor-7", &device_key.public_key, YEAR, false, critical).unwrap();
+        assert!(matches!(verify_chain(&unknown, &[], &anchors), Err(PqcError::Verification(_))));
+        let hardware = ExtensionValidators::new().with("1.3.6.1.4.1.55555.30", Arc::new(|_: &[u8]| Ok(()))).unwrap();
+        verify_chain(&unknown, &[], &anchors.clone().with_extension_validators(hardware)).unwrap();
+
+        let mut forged = root.issue("sensor-7", &device_key.public_key, YEAR, false).unwrap();
+        verify_chain(&forged, &[], &anchors).unwrap();
+        forged.subject = "sensor-8".to_string();
+        assert!(matches!(verify_chain(&forged, &[], &anchors), Err(PqcError::Verification(_))));
+
+        // A leaf certificate cannot act as an issuer
+        let device = CertificateAuthority::new("sensor-7", root.algorithm, device_key.public_key.clone(), device_key.secret_key.into());
+        let leaf = root.issue("sensor-7", device.public_key(), YEAR, false).unwrap();
+        let child = device.issue("sensor-7/child", &[1, 2, 3], YEAR, false).unwrap();
+        assert!(verify_chain(&child, &[leaf], &anchors).is_err());
+    }
+}
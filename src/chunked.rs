@@ -0,0 +1,382 @@
+//! Erasure-coded entropy delivery for lossy links
+//!
+//! [`split`] serializes an [`EncryptedEntropy`] envelope, cuts it into K
+//! data chunks and adds P Reed-Solomon parity chunks. A device that
+//! receives any K of the K+P chunks can rebuild the envelope, so LPWAN
+//! links that drop frames don't need retransmission rounds.
+//!
+//! One signed [`ChunkManifest`] lists the SHA-256 of every chunk, and each
+//! [`Chunk`] on the link is a bare binary frame: transfer ID, index and
+//! data. Send the manifest first and repeat it, since chunks are useless
+//! without it. A collector only accepts chunks for transfers whose manifest
+//! it has verified, so a forged or corrupted chunk is discarded on arrival
+//! instead of poisoning the reconstruction or blocking the real transfer.
+//! The rebuilt envelope is checked against the transfer ID, its SHA-256.
+
+use std::collections::{HashMap, VecDeque};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use serde::{Deserialize, Serialize};
+use crate::api::EncryptedEntropy;
+use crate::error::{PqcError, Result};
+use crate::hash::{self, HashAlgorithm};
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, Validate};
+use crate::{canonical, fips};
+
+/// Domain label for chunk manifest signatures
+const MANIFEST_DOMAIN: &str = "PQC-Algo/v1/entropy-chunks";
+
+/// Most chunks one envelope can be split into (GF(2^8) limit)
+pub const MAX_CHUNKS: usize = 256;
+
+/// Transfers a collector tracks at once; the oldest is dropped beyond this
+pub const MAX_PENDING_TRANSFERS: usize = 16;
+
+/// Bytes of a chunk frame before the data: transfer ID and big-endian index
+const CHUNK_FRAME_HEADER_LEN: usize = 32 + 2;
+
+/// Signed description of a chunked envelope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// Hex SHA-256 of the serialized envelope
+    pub transfer_id: String,
+    pub envelope_len: u32,
+    /// Chunks needed to rebuild the envelope (K)
+    pub data_chunks: u16,
+    /// Data plus parity chunks (K + P)
+    pub total_chunks: u16,
+    /// Hex SHA-256 of each chunk's data, in index order
+    pub chunk_digests: Vec<String>,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`ChunkManifest`] covered by its signature
+#[derive(Serialize)]
+struct ManifestFields<'a> {
+    transfer_id: &'a str,
+    envelope_len: u32,
+    data_chunks: u16,
+    total_chunks: u16,
+    chunk_digests: &'a [String],
+    algorithm: SigAlgorithm,
+}
+
+impl ChunkManifest {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode chunk manifest: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "chunk manifest")
+    }
+
+    /// Check the layout and the signature
+    pub fn verify(&self, signer_public_key: &[u8]) -> Result<()> {
+        self.validate()?;
+        fips::check_sig(self.algorithm)?;
+        if !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, signer_public_key)? {
+            return Err(PqcError::Verification("Chunk manifest has an invalid signature".to_string()));
+        }
+        Ok(())
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(MANIFEST_DOMAIN, &ManifestFields {
+            transfer_id: &self.transfer_id,
+            envelope_len: self.envelope_len,
+            data_chunks: self.data_chunks,
+            total_chunks: self.total_chunks,
+            chunk_digests: &self.chunk_digests,
+            algorithm: self.algorithm,
+        })
+    }
+}
+
+impl Validate for ChunkManifest {
+    fn validate(&self) -> Result<()> {
+        validation::check_fingerprint("Transfer ID", &self.transfer_id)?;
+        codec(self.data_chunks as usize, self.total_chunks.saturating_sub(self.data_chunks) as usize)?;
+        if self.chunk_digests.len() != self.total_chunks as usize {
+            return Err(PqcError::InvalidInput(format!(
+                "Chunk manifest lists {} digests for {} chunks", self.chunk_digests.len(), self.total_chunks
+            )));
+        }
+        for digest in &self.chunk_digests {
+            validation::check_fingerprint("Chunk digest", digest)?;
+        }
+        validation::check_signature_len("Chunk manifest signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// One piece of a chunked envelope as sent over the link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// The manifest's transfer ID
+    pub transfer_id: String,
+    pub index: u16,
+    pub data: Vec<u8>,
+}
+
+impl Chunk {
+    /// Binary frame: 32-byte transfer ID, big-endian index, then the data
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        validation::check_fingerprint("Transfer ID", &self.transfer_id)?;
+        let mut frame = Vec::with_capacity(CHUNK_FRAME_HEADER_LEN + self.data.len());
+        frame.extend_from_slice(&hex::decode(&self.transfer_id)
+            .map_err(|e| PqcError::InvalidInput(format!("Invalid transfer ID: {}", e)))?);
+        frame.extend_from_slice(&self.index.to_be_bytes());
+        frame.extend_from_slice(&self.data);
+        Ok(frame)
+    }
+
+    pub fn from_bytes(frame: &[u8]) -> Result<Self> {
+        if frame.len() <= CHUNK_FRAME_HEADER_LEN {
+            return Err(PqcError::InvalidInput(format!("Chunk frame is {} bytes, too short", frame.len())));
+        }
+        Ok(Self {
+            transfer_id: hex::encode(&frame[..32]),
+            index: u16::from_be_bytes([frame[32], frame[33]]),
+            data: frame[CHUNK_FRAME_HEADER_LEN..].to_vec(),
+        })
+    }
+}
+
+/// A split envelope: the signed manifest and every chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedEnvelope {
+    pub manifest: ChunkManifest,
+    pub chunks: Vec<Chunk>,
+}
+
+/// Split `envelope` into `data_chunks` + `parity_chunks` chunks under one signed manifest
+pub fn split(envelope: &EncryptedEntropy, data_chunks: usize, parity_chunks: usize, signer_secret_key: &[u8]) -> Result<ChunkedEnvelope> {
+    split_with_suite(envelope, data_chunks, parity_chunks, signer_secret_key, &CipherSuite::active_default())
+}
+
+/// [`split`], signing the manifest with the suite's signature algorithm
+pub fn split_with_suite(
+    envelope: &EncryptedEntropy,
+    data_chunks: usize,
+    parity_chunks: usize,
+    signer_secret_key: &[u8],
+    suite: &CipherSuite,
+) -> Result<ChunkedEnvelope> {
+    fips::check_suite(suite)?;
+    let codec = codec(data_chunks, parity_chunks)?;
+    let bytes = serde_json::to_vec(envelope)
+        .map_err(|e| PqcError::InvalidInput(format!("Failed to encode envelope: {}", e)))?;
+    let envelope_len = u32::try_from(bytes.len())
+        .map_err(|_| PqcError::InvalidInput("Envelope too large to chunk".to_string()))?;
+    let transfer_id = hex::encode(hash::digest(HashAlgorithm::Sha256, &bytes));
+
+    let chunk_len = bytes.len().div_ceil(data_chunks);
+    let mut shards: Vec<Vec<u8>> = (0..data_chunks + parity_chunks)
+        .map(|i| {
+            let start = (i * chunk_len).min(bytes.len());
+            let mut shard = bytes[start..(start + chunk_len).min(bytes.len())].to_vec();
+            shard.resize(chunk_len, 0);
+            shard
+        })
+        .collect();
+    codec.encode(&mut shards)
+        .map_err(|e| PqcError::Encryption(format!("Erasure coding failed: {:?}", e)))?;
+
+    let mut manifest = ChunkManifest {
+        transfer_id: transfer_id.clone(),
+        envelope_len,
+        data_chunks: data_chunks as u16,
+        total_chunks: (data_chunks + parity_chunks) as u16,
+        chunk_digests: shards.iter().map(|data| hex::encode(hash::digest(HashAlgorithm::Sha256, data))).collect(),
+        algorithm: suite.sig,
+        signature: Vec::new(),
+    };
+    manifest.signature = sign::sign_with(suite.sig, &manifest.signing_bytes()?, signer_secret_key)?;
+    let chunks = shards.into_iter().enumerate()
+        .map(|(index, data)| Chunk { transfer_id: transfer_id.clone(), index: index as u16, data })
+        .collect();
+    Ok(ChunkedEnvelope { manifest, chunks })
+}
+
+/// Chunks received so far for one verified manifest
+struct Transfer {
+    manifest: ChunkManifest,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+/// Collects chunks as they arrive and rebuilds each envelope once enough are in
+pub struct ChunkCollector {
+    signer_public_key: Vec<u8>,
+    transfers: HashMap<String, Transfer>,
+    /// Transfer IDs, oldest first
+    order: VecDeque<String>,
+}
+
+impl ChunkCollector {
+    /// Accept only manifests signed by `signer_public_key`
+    pub fn new(signer_public_key: &[u8]) -> Self {
+        Self { signer_public_key: signer_public_key.to_vec(), transfers: HashMap::new(), order: VecDeque::new() }
+    }
+
+    /// Verify `manifest` and start collecting its chunks; repeats are ignored
+    ///
+    /// Beyond [`MAX_PENDING_TRANSFERS`], the oldest unfinished transfer is dropped.
+    pub fn add_manifest(&mut self, manifest: &ChunkManifest) -> Result<()> {
+        manifest.verify(&self.signer_public_key)?;
+        if self.transfers.contains_key(&manifest.transfer_id) {
+            return Ok(());
+        }
+        if self.order.len() >= MAX_PENDING_TRANSFERS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.transfers.remove(&oldest);
+            }
+        }
+        self.order.push_back(manifest.transfer_id.clone());
+        self.transfers.insert(manifest.transfer_id.clone(), Transfer {
+            manifest: manifest.clone(),
+            chunks: vec![None; manifest.total_chunks as usize],
+            received: 0,
+        });
+        Ok(())
+    }
+
+    /// Add one chunk; returns the envelope once any K valid chunks of its transfer are in
+    ///
+    /// Chunks for a transfer without a verified manifest, or that don't
+    /// match their manifest's digest, are rejected with an error and leave
+    /// the collector unchanged. Duplicates are ignored. A rebuilt transfer
+    /// is forgotten.
+    pub fn push(&mut self, chunk: &Chunk) -> Result<Option<EncryptedEntropy>> {
+        let transfer = self.transfers.get_mut(&chunk.transfer_id)
+            .ok_or_else(|| PqcError::Protocol(format!("Chunk {} is for a transfer without a verified manifest", chunk.index)))?;
+        let expected = transfer.manifest.chunk_digests.get(chunk.index as usize)
+            .ok_or_else(|| PqcError::Protocol(format!("Chunk index {} out of range", chunk.index)))?;
+        if !ct::ct_eq_hex(expected, &hex::encode(hash::digest(HashAlgorithm::Sha256, &chunk.data))) {
+            return Err(PqcError::Verification(format!("Chunk {} does not match its manifest", chunk.index)));
+        }
+        let slot = &mut transfer.chunks[chunk.index as usize];
+        if slot.is_none() {
+            *slot = Some(chunk.data.clone());
+            transfer.received += 1;
+        }
+
+        let rebuilt = transfer.try_rebuild()?;
+        if rebuilt.is_some() {
+            self.transfers.remove(&chunk.transfer_id);
+            self.order.retain(|id| id != &chunk.transfer_id);
+        }
+        Ok(rebuilt)
+    }
+
+    /// Number of distinct valid chunks received so far for `transfer_id`
+    pub fn received(&self, transfer_id: &str) -> usize {
+        self.transfers.get(transfer_id).map_or(0, |transfer| transfer.received)
+    }
+}
+
+impl Transfer {
+    fn try_rebuild(&self) -> Result<Option<EncryptedEntropy>> {
+        let data_chunks = self.manifest.data_chunks as usize;
+        if self.received < data_chunks {
+            return Ok(None);
+        }
+
+        let mut shards = self.chunks.clone();
+        codec(data_chunks, self.manifest.total_chunks as usize - data_chunks)?
+            .reconstruct_data(&mut shards)
+            .map_err(|e| PqcError::Decryption(format!("Erasure decoding failed: {:?}", e)))?;
+        let mut bytes: Vec<u8> = shards.into_iter().take(data_chunks).flatten().flatten().collect();
+        bytes.truncate(self.manifest.envelope_len as usize);
+
+        if !ct::ct_eq_hex(&self.manifest.transfer_id, &hex::encode(hash::digest(HashAlgorithm::Sha256, &bytes))) {
+            return Err(PqcError::Verification("Rebuilt envelope does not match its transfer ID".to_string()));
+        }
+        EncryptedEntropy::from_bytes(&bytes).map(Some)
+    }
+}
+
+/// Rebuild an envelope from its manifest and whichever chunks arrived, skipping invalid ones
+pub fn reassemble(manifest: &ChunkManifest, chunks: &[Chunk], signer_public_key: &[u8]) -> Result<EncryptedEntropy> {
+    let mut collector = ChunkCollector::new(signer_public_key);
+    collector.add_manifest(manifest)?;
+    let mut received = 0;
+    for chunk in chunks {
+        if let Ok(Some(envelope)) = collector.push(chunk) {
+            return Ok(envelope);
+        }
+        received = collector.received(&manifest.transfer_id);
+    }
+    Err(PqcError::Decryption(format!("Only {} valid chunks received, not enough to rebuild", received)))
+}
+
+fn codec(data_chunks: usize, parity_chunks: usize) -> Result<ReedSolomon> {
+    if data_chunks == 0 || parity_chunks == 0 || data_chunks + parity_chunks > MAX_CHUNKS {
+        return Err(PqcError::InvalidInput(format!(
+            "Need at least one data and one parity chunk and at most {} in total", MAX_CHUNKS
+        )));
+    }
+    ReedSolomon::new(data_chunks, parity_chunks)
+        .map_err(|e| PqcError::InvalidInput(format!("Invalid chunk layout: {:?}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, DeviceCredentials};
+
+    #[test]
+    fn test_rebuild_from_any_k_chunks() {
+        let device = DeviceCredentials::generate().unwrap();
+        let service = sign::generate_keypair().unwrap();
+        let envelope = api::encrypt_entropy_for_device(&[7u8; 64], &device.kyber_public_key).unwrap();
+
+        let split = split(&envelope, 4, 3, &service.secret_key).unwrap();
+        assert_eq!(split.chunks.len(), 7);
+        let manifest = ChunkManifest::from_bytes(&split.manifest.to_bytes().unwrap()).unwrap();
+        let frames: Vec<Chunk> = split.chunks.iter().map(|c| Chunk::from_bytes(&c.to_bytes().unwrap()).unwrap()).collect();
+        assert_eq!(frames, split.chunks);
+
+        // Lose three chunks, including two data chunks
+        let survivors = [&frames[1], &frames[3], &frames[4], &frames[6]].map(Clone::clone);
+        let rebuilt = reassemble(&manifest, &survivors, &service.public_key).unwrap();
+        assert_eq!(api::decrypt_entropy(&rebuilt, &device.kyber_secret_key).unwrap(), vec![7u8; 64]);
+
+        let mut corrupted = frames[0].clone();
+        corrupted.data[0] ^= 1;
+        let mut collector = ChunkCollector::new(&service.public_key);
+        collector.add_manifest(&manifest).unwrap();
+        assert!(matches!(collector.push(&corrupted), Err(PqcError::Verification(_))));
+        assert!(reassemble(&manifest, &frames[..3], &service.public_key).is_err());
+
+        let mut forged = manifest.clone();
+        forged.envelope_len += 1;
+        assert!(matches!(collector.add_manifest(&forged), Err(PqcError::Verification(_))));
+    }
+
+    #[test]
+    fn test_bogus_chunks_do_not_block_the_transfer() {
+        let device = DeviceCredentials::generate().unwrap();
+        let service = sign::generate_keypair().unwrap();
+        let envelope = api::encrypt_entropy_for_device(&[9u8; 64], &device.kyber_public_key).unwrap();
+        let split = split(&envelope, 2, 1, &service.secret_key).unwrap();
+
+        // An attacker's chunks, before and after the manifest, change nothing
+        let mut collector = ChunkCollector::new(&service.public_key);
+        let bogus = Chunk { transfer_id: "ab".repeat(32), index: 0, data: vec![0u8; 16] };
+        assert!(matches!(collector.push(&bogus), Err(PqcError::Protocol(_))));
+        collector.add_manifest(&split.manifest).unwrap();
+        assert!(collector.push(&bogus).is_err());
+        let spoofed = Chunk { transfer_id: split.manifest.transfer_id.clone(), index: 1, data: vec![0u8; 16] };
+        assert!(collector.push(&spoofed).is_err());
+
+        assert!(collector.push(&split.chunks[2]).unwrap().is_none());
+        let rebuilt = collector.push(&split.chunks[1]).unwrap().unwrap();
+        assert_eq!(api::decrypt_entropy(&rebuilt, &device.kyber_secret_key).unwrap(), vec![9u8; 64]);
+        assert_eq!(collector.received(&split.manifest.transfer_id), 0);
+    }
+}
@@ -0,0 +1,56 @@
+//! Shell completions and man pages generated from the command tree
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use clap_complete::Shell;
+use serde::Serialize;
+use pqc_algo::PqcError;
+use crate::output::Report;
+
+/// Write the completion script for `shell` to stdout
+pub fn completions(mut command: clap::Command, shell: Shell) -> Result<(), PqcError> {
+    let name = command.get_name().to_string();
+    let mut stdout = std::io::stdout().lock();
+    clap_complete::generate(shell, &mut command, name, &mut stdout);
+    stdout.flush().map_err(|e| PqcError::Io(format!("Failed to write output: {}", e)))
+}
+
+#[derive(Serialize)]
+pub struct ManPages {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl fmt::Display for ManPages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wrote {} man page(s) to {}", self.files.len(), self.dir.display())
+    }
+}
+
+impl Report for ManPages {
+    fn succeeded(&self) -> bool {
+        true
+    }
+}
+
+/// Write a page for `command` and one per subcommand, e.g. `pqc-algo-inspect.1`, into `dir`
+pub fn man_pages(command: clap::Command, dir: &Path) -> Result<ManPages, PqcError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| PqcError::Io(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let mut command = command.disable_help_subcommand(true);
+    command.build();
+    let mut files = Vec::new();
+    write_pages(command, dir, &mut files)?;
+    Ok(ManPages { dir: dir.to_path_buf(), files })
+}
+
+fn write_pages(command: clap::Command, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), PqcError> {
+    for subcommand in command.get_subcommands().filter(|s| !s.is_hide_set()) {
+        write_pages(subcommand.clone(), dir, files)?;
+    }
+    let path = clap_mangen::Man::new(command).generate_to(dir)
+        .map_err(|e| PqcError::Io(format!("Failed to write man page to {}: {}", dir.display(), e)))?;
+    files.push(path);
+    Ok(())
+}
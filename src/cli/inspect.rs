@@ -0,0 +1,332 @@
+//! `pqc-algo inspect`: describe a stored artifact
+//!
+//! With `--porcelain` the report is `kind`, one `fields.<id>` line per
+//! field and one `problems.<n>` line per problem. Kinds and field ids are
+//! fixed snake_case identifiers such as `auth_request` and `device_id`, and
+//! are part of the format. The labels in the text report are not. Values are
+//! as in the text report.
+
+use std::fmt;
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use pqc_algo::api::{self, AuthRequest, EncryptedEntropy};
+use pqc_algo::deniable::{MacAuthenticated, TAG_LEN};
+use pqc_algo::envelope::SignedEnvelope;
+use pqc_algo::extensions::Extensions;
+use pqc_algo::manifest::SeedManifest;
+use pqc_algo::sealed_box::{AnonymousBox, AuthBox};
+use pqc_algo::validation::validate_device_id;
+use pqc_algo::{CipherSuite, PqcError, SigAlgorithm};
+
+/// What `inspect` found out about one artifact
+#[derive(Serialize)]
+pub struct Inspection {
+    /// Stable identifier of the artifact type
+    pub kind: &'static str,
+    /// Heading of the text report
+    #[serde(skip)]
+    pub title: &'static str,
+    #[serde(serialize_with = "ordered_map")]
+    pub fields: Vec<Field>,
+    pub problems: Vec<String>,
+}
+
+/// One line of an [`Inspection`]
+pub struct Field {
+    /// Stable snake_case key for JSON and porcelain output
+    pub id: String,
+    /// Wording for the text report
+    pub label: String,
+    pub value: String,
+}
+
+impl Inspection {
+    fn new(kind: &'static str, title: &'static str) -> Self {
+        Self { kind, title, fields: Vec::new(), problems: Vec::new() }
+    }
+
+    fn field(&mut self, id: impl Into<String>, label: impl Into<String>, value: impl fmt::Display) {
+        self.fields.push(Field { id: id.into(), label: label.into(), value: value.to_string() });
+    }
+
+    fn problem(&mut self, problem: impl Into<String>) {
+        self.problems.push(problem.into());
+    }
+
+    /// Record a signature check; `None` means no key was given
+    fn signature_check(&mut self, result: Option<pqc_algo::Result<bool>>) {
+        match result {
+            None => self.field("signature_check", "Signature check", "skipped (pass --public-key)"),
+            Some(Ok(true)) => self.field("signature_check", "Signature check", "valid"),
+            Some(Ok(false)) => {
+                self.field("signature_check", "Signature check", "INVALID");
+                self.problem("signature does not verify under the given key");
+            }
+            Some(Err(e)) => {
+                self.field("signature_check", "Signature check", "error");
+                self.problem(e.to_string());
+            }
+        }
+    }
+
+    /// Compare a length against what the algorithm produces
+    fn expect_len(&mut self, id: &'static str, label: &'static str, actual: usize, expected: pqc_algo::Result<usize>) {
+        match expected {
+            Ok(expected) if expected == actual => self.field(id, label, format!("{} bytes", actual)),
+            Ok(expected) => {
+                self.field(id, label, format!("{} bytes (expected {})", actual, expected));
+                self.problem(format!("{} is {} bytes, expected {}", label.to_lowercase(), actual, expected));
+            }
+            Err(e) => {
+                self.field(id, label, format!("{} bytes", actual));
+                self.problem(e.to_string());
+            }
+        }
+    }
+}
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title)?;
+        let width = self.fields.iter().map(|field| field.label.len()).max().unwrap_or(0);
+        for field in &self.fields {
+            writeln!(f, "  {:width$}  {}", field.label, field.value, width = width)?;
+        }
+        if self.problems.is_empty() {
+            write!(f, "OK")
+        } else {
+            for problem in &self.problems {
+                writeln!(f, "problem: {}", problem)?;
+            }
+            write!(f, "{} problem(s) found", self.problems.len())
+        }
+    }
+}
+
+/// Serialize fields as an id-to-value map in report order
+fn ordered_map<S: Serializer>(fields: &[Field], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(fields.iter().map(|field| (&field.id, &field.value)))
+}
+
+/// Identify and describe a JSON artifact produced by this crate
+///
+/// `public_key` is the signer's Dilithium public key, if known.
+pub fn inspect(bytes: &[u8], public_key: Option<&[u8]>) -> Result<Inspection, PqcError> {
+    let value: Value = serde_json::from_slice(bytes)
+        .map_err(|e| PqcError::InvalidInput(format!("Not a JSON artifact: {}", e)))?;
+    let has = |keys: &[&str]| keys.iter().all(|key| value.get(key).is_some());
+
+    if has(&["signer_fingerprint", "purpose", "signature"]) {
+        Ok(signed_envelope(parse(&value)?, public_key))
+    } else if has(&["ciphertext", "encrypted_data"]) {
+        Ok(encrypted_entropy(parse(&value)?))
+    } else if has(&["device_id", "nonce", "signature"]) {
+        Ok(auth_request(parse(&value)?, public_key))
+    } else if has(&["sender_fingerprint", "kem_ciphertext", "signature"]) {
+        Ok(auth_box(parse(&value)?))
+    } else if has(&["suite", "kem_ciphertext", "sealed"]) {
+        Ok(anonymous_box(parse(&value)?))
+    } else if has(&["key_id", "device_id", "counter", "tag"]) {
+        Ok(mac_message(parse(&value)?))
+    } else if has(&["entries", "signature"]) {
+        Ok(seed_manifest(parse(&value)?, public_key))
+    } else if has(&["version", "kdf", "params", "salt", "sealed"]) {
+        Ok(credential_backup(&value))
+    } else {
+        Err(PqcError::InvalidInput("Unrecognized artifact".to_string()))
+    }
+}
+
+fn parse<T: DeserializeOwned>(value: &Value) -> Result<T, PqcError> {
+    serde_json::from_value(value.clone())
+        .map_err(|e| PqcError::InvalidInput(format!("Malformed artifact: {}", e)))
+}
+
+fn signed_envelope(envelope: SignedEnvelope, public_key: Option<&[u8]>) -> Inspection {
+    let mut report = Inspection::new("signed_envelope", "Signed envelope");
+    report.field("algorithm", "Algorithm", &envelope.algorithm);
+    report.field("signer", "Signer", &envelope.signer_fingerprint);
+    report.field("purpose", "Purpose", &envelope.purpose);
+    report.field("timestamp", "Timestamp", envelope.timestamp);
+    report.field("payload", "Payload", format!("{} bytes", envelope.payload.len()));
+    match envelope.algorithm.parse::<SigAlgorithm>() {
+        Ok(algorithm) => report.expect_len("signature", "Signature", envelope.signature.len(), algorithm.signature_len()),
+        Err(e) => report.problem(e.to_string()),
+    }
+    if envelope.fips_mode {
+        report.field("fips_mode", "FIPS mode", "yes");
+    }
+    extensions(&mut report, &envelope.extensions);
+    report.signature_check(public_key.map(|pk| envelope.verify(pk, &envelope.purpose)));
+    report
+}
+
+fn encrypted_entropy(envelope: EncryptedEntropy) -> Inspection {
+    let mut report = Inspection::new("encrypted_entropy", "Encrypted entropy");
+    let suite = match envelope.suite {
+        Some(suite) => {
+            report.field("suite", "Suite", suite);
+            suite
+        }
+        None => {
+            report.field("suite", "Suite", format!("not recorded (legacy, {})", CipherSuite::DEFAULT));
+            CipherSuite::DEFAULT
+        }
+    };
+    report.expect_len("kem_ciphertext", "KEM ciphertext", envelope.ciphertext.len(), suite.kem.ciphertext_len());
+    sealed_size(&mut report, envelope.encrypted_data.len());
+    if envelope.fips_mode {
+        report.field("fips_mode", "FIPS mode", "yes");
+    }
+    match &envelope.recipient {
+        Some(recipient) => report.field("recipient", "Recipient", recipient),
+        None => report.field("recipient", "Recipient", "not recorded (legacy, header not authenticated)"),
+    }
+    report
+}
+
+fn auth_request(request: AuthRequest, public_key: Option<&[u8]>) -> Inspection {
+    let mut report = Inspection::new("auth_request", "Authentication request");
+    report.field("device_id", "Device ID", &request.device_id);
+    if let Err(e) = validate_device_id(&request.device_id) {
+        report.problem(e.to_string());
+    }
+    report.field("nonce", "Nonce", format!("{} bytes", request.nonce.len()));
+    report.field("signature", "Signature", format!("{} bytes", request.signature.len()));
+    extensions(&mut report, &request.extensions);
+
+    // Requests don't record their algorithm; signature lengths tell them apart
+    let candidates: Vec<SigAlgorithm> = SigAlgorithm::all().iter().copied()
+        .filter(|alg| alg.signature_len().is_ok_and(|len| len == request.signature.len()))
+        .collect();
+    if candidates.is_empty() {
+        report.problem("signature length matches no supported algorithm");
+    } else {
+        report.field("algorithm", "Algorithm", candidates.iter().map(|alg| alg.name()).collect::<Vec<_>>().join(" or "));
+    }
+
+    report.signature_check(public_key.map(|pk| {
+        let mut valid = Ok(false);
+        for &sig in &candidates {
+            let suite = CipherSuite { sig, ..CipherSuite::DEFAULT };
+            valid = api::verify_auth_request_with_suite(&request, pk, &suite);
+            if matches!(valid, Ok(true)) {
+                break;
+            }
+        }
+        valid
+    }));
+    report
+}
+
+fn auth_box(sealed_box: AuthBox) -> Inspection {
+    let mut report = Inspection::new("auth_box", "Authenticated box");
+    report.field("suite", "Suite", sealed_box.suite);
+    report.field("sender", "Sender", &sealed_box.sender_fingerprint);
+    report.field("recipient", "Recipient", &sealed_box.recipient_fingerprint);
+    report.expect_len("kem_ciphertext", "KEM ciphertext", sealed_box.kem_ciphertext.len(), sealed_box.suite.kem.ciphertext_len());
+    sealed_size(&mut report, sealed_box.sealed.len());
+    report.expect_len("signature", "Signature", sealed_box.signature.len(), sealed_box.suite.sig.signature_len());
+    report
+}
+
+fn anonymous_box(sealed_box: AnonymousBox) -> Inspection {
+    let mut report = Inspection::new("anonymous_box", "Anonymous box");
+    report.field("suite", "Suite", sealed_box.suite);
+    report.expect_len("kem_ciphertext", "KEM ciphertext", sealed_box.kem_ciphertext.len(), sealed_box.suite.kem.ciphertext_len());
+    sealed_size(&mut report, sealed_box.sealed.len());
+    report
+}
+
+fn mac_message(message: MacAuthenticated) -> Inspection {
+    let mut report = Inspection::new("mac_message", "MAC-authenticated telemetry");
+    report.field("key_id", "Key ID", &message.key_id);
+    report.field("device_id", "Device ID", &message.device_id);
+    report.field("counter", "Counter", message.counter);
+    report.field("payload", "Payload", format!("{} bytes", message.payload.len()));
+    report.expect_len("tag", "Tag", message.tag.len(), Ok(TAG_LEN));
+    report
+}
+
+fn seed_manifest(manifest: SeedManifest, public_key: Option<&[u8]>) -> Inspection {
+    let mut report = Inspection::new("seed_manifest", "Seed manifest");
+    report.field("entries", "Entries", manifest.entries.len());
+    for (i, entry) in manifest.entries.iter().enumerate() {
+        report.field(format!("seed_{}", i), format!("Seed {}", entry.filename), format!("sha256={} source={} file={}", entry.sha256, entry.source, entry.filename));
+        if entry.sha256.len() != 64 || hex::decode(&entry.sha256).is_err() {
+            report.problem(format!("{} has a malformed SHA-256", entry.filename));
+        }
+    }
+    if manifest.signature.is_empty() {
+        report.problem("manifest is unsigned");
+    } else {
+        report.field("signature", "Signature", format!("{} bytes", manifest.signature.len()));
+    }
+    report.signature_check(public_key.map(|pk| manifest.verify_signature(pk)));
+    report
+}
+
+fn credential_backup(value: &Value) -> Inspection {
+    let mut report = Inspection::new("credential_backup", "Encrypted credential backup");
+    report.field("version", "Version", &value["version"]);
+    report.field("kdf", "KDF", value["kdf"].as_str().unwrap_or("?"));
+    report.field("kdf_params", "KDF params", &value["params"]);
+    report.field("salt", "Salt", format!("{} bytes", value["salt"].as_array().map_or(0, |a| a.len())));
+    if value["version"] != 1 || value["kdf"] != "argon2id" {
+        report.problem("unsupported backup version");
+    }
+    report
+}
+
+/// List extension identifiers, marking critical ones
+fn extensions(report: &mut Inspection, extensions: &Extensions) {
+    if extensions.is_empty() {
+        return;
+    }
+    let ids: Vec<String> = extensions.iter()
+        .map(|(id, extension)| if extension.critical { format!("{} (critical)", id) } else { id.to_string() })
+        .collect();
+    report.field("extensions", "Extensions", ids.join(", "));
+}
+
+/// AEAD output is `nonce (12) || ciphertext || tag (16)`
+fn sealed_size(report: &mut Inspection, len: usize) {
+    if len < 28 {
+        report.field("sealed_data", "Sealed data", format!("{} bytes", len));
+        report.problem("sealed data is shorter than nonce and tag");
+    } else {
+        report.field("sealed_data", "Sealed data", format!("{} bytes ({} byte plaintext)", len, len - 28));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pqc_algo::sign;
+
+    #[test]
+    fn test_inspect_envelope_and_request() {
+        let keypair = sign::generate_keypair().unwrap();
+        let envelope = SignedEnvelope::sign(b"fw", "firmware-release", &keypair.secret_key, &keypair.public_key).unwrap();
+        let report = inspect(&envelope.to_bytes().unwrap(), Some(&keypair.public_key)).unwrap();
+        assert_eq!(report.kind, "signed_envelope");
+        assert!(report.to_string().starts_with("Signed envelope\n"));
+        assert!(report.problems.is_empty(), "{}", report);
+
+        let request = api::create_auth_request("sensor-7", b"nonce", &keypair.secret_key).unwrap();
+        let mut json = serde_json::to_vec(&request).unwrap();
+        let report = inspect(&json, Some(&keypair.public_key)).unwrap();
+        assert!(report.problems.is_empty(), "{}", report);
+        let porcelain = pqc_algo::porcelain::to_porcelain(&report).unwrap();
+        assert!(porcelain.contains("kind\tauth_request\n"));
+        assert!(porcelain.contains("fields.device_id\tsensor-7\n"));
+        assert!(porcelain.contains("fields.signature_check\tvalid\n"));
+        assert!(report.to_string().contains("Device ID"));
+
+        json = serde_json::to_vec(&AuthRequest { signature: vec![0; 10], ..request }).unwrap();
+        assert!(!inspect(&json, None).unwrap().problems.is_empty());
+        assert!(inspect(b"{\"hello\": 1}", None).is_err());
+        assert!(inspect(b"not json", None).is_err());
+    }
+}
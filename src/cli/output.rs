@@ -0,0 +1,55 @@
+//! `--output` formats shared by every subcommand
+
+use std::fmt;
+use std::io::Write;
+use clap::ValueEnum;
+use serde::Serialize;
+use pqc_algo::{porcelain, PqcError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable report
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    /// Hex of the binary result
+    Hex,
+    /// The binary result, for commands that have one: `sign` and `ceremony`
+    Raw,
+    /// Stable `key<TAB>value` lines for scripts; see `pqc_algo::porcelain`
+    Porcelain,
+}
+
+/// Result of a subcommand
+pub trait Report: Serialize + fmt::Display {
+    /// False makes the process exit with status 1
+    fn succeeded(&self) -> bool;
+
+    /// Binary result written by `--output raw` and `--output hex`; `None` makes those formats an error
+    fn payload(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Print `report` in `format` and return whether the command succeeded
+pub fn emit<R: Report>(report: &R, format: OutputFormat) -> Result<bool, PqcError> {
+    let encode_error = |e: &dyn fmt::Display| PqcError::InvalidInput(format!("Failed to encode output: {}", e));
+    let raw = || report.payload()
+        .ok_or_else(|| PqcError::InvalidInput("This command has no binary result; use --output json".to_string()));
+
+    match format {
+        OutputFormat::Text => println!("{}", report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report).map_err(|e| encode_error(&e))?),
+        OutputFormat::Yaml => print!("{}", serde_yaml_ng::to_string(report).map_err(|e| encode_error(&e))?),
+        OutputFormat::Hex => println!("{}", hex::encode(raw()?)),
+        OutputFormat::Porcelain => print!("{}", porcelain::to_porcelain(report)?),
+        OutputFormat::Raw => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&raw()?)
+                .and_then(|_| stdout.flush())
+                .map_err(|e| PqcError::Io(format!("Failed to write output: {}", e)))?;
+        }
+    }
+    Ok(report.succeeded())
+}
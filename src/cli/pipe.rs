@@ -0,0 +1,216 @@
+//! `encrypt`, `decrypt` and `sign` as filters over files or stdin/stdout
+//!
+//! Input is streamed, never held in memory whole. Output files are written
+//! through an owner-only temporary file and only appear once the command
+//! succeeds.
+//! Standard output cannot be taken back: `decrypt --stdout` may have written
+//! part of the plaintext before a corrupt segment is found, so pipelines
+//! must check the exit status, e.g. with `set -o pipefail`.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use pqc_algo::hash::{self, HashAlgorithm};
+use pqc_algo::paths;
+use pqc_algo::streaming::{self, StreamEncryptor};
+use pqc_algo::{sign, CipherSuite, PqcError};
+use crate::output::Report;
+
+/// Where a filter reads from and writes to; `None` is stdin or stdout
+pub struct Pipe<'a> {
+    pub input: Option<&'a Path>,
+    pub output: Option<&'a Path>,
+}
+
+impl Pipe<'_> {
+    fn reader(&self) -> Result<Box<dyn Read>, PqcError> {
+        Ok(match self.input {
+            Some(path) => Box::new(BufReader::new(File::open(path)
+                .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?)),
+            None => Box::new(io::stdin().lock()),
+        })
+    }
+
+    /// Run `write` against the output, publishing a file only if it succeeds
+    fn write<T>(&self, write: impl FnOnce(&mut dyn Write) -> Result<T, PqcError>) -> Result<T, PqcError> {
+        let Some(path) = self.output else {
+            let mut stdout = BufWriter::new(io::stdout().lock());
+            let result = write(&mut stdout)?;
+            stdout.flush().map_err(|e| PqcError::Io(format!("Failed to write output: {}", e)))?;
+            return Ok(result);
+        };
+        let io_error = |e: io::Error| PqcError::Io(format!("Failed to write {}: {}", path.display(), e));
+        let (file, tmp) = paths::create_private_temp(path)?;
+        let mut file = BufWriter::new(file);
+        let result = write(&mut file).and_then(|result| {
+            let file = file.into_inner().map_err(|e| io_error(e.into_error()))?;
+            paths::persist_private_temp(file, &tmp, path)?;
+            Ok(result)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn report(&self, operation: &'static str, bytes: u64) -> PipeReport {
+        PipeReport { operation, input: self.input.map(Path::to_path_buf), output: self.output.map(Path::to_path_buf), bytes, signature: None }
+    }
+}
+
+/// Summary printed when the result went to a file
+#[derive(Serialize)]
+pub struct PipeReport {
+    operation: &'static str,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    /// Plaintext bytes processed
+    bytes: u64,
+    /// The signature written by `sign`, for `--output raw` and `--output hex`
+    #[serde(skip)]
+    signature: Option<Vec<u8>>,
+}
+
+impl fmt::Display for PipeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |path: &Option<PathBuf>, fallback: &str| path.as_ref().map_or(fallback.to_string(), |p| p.display().to_string());
+        write!(f, "{} {} bytes from {} to {}", self.operation, self.bytes, name(&self.input, "stdin"), name(&self.output, "stdout"))
+    }
+}
+
+impl Report for PipeReport {
+    fn succeeded(&self) -> bool {
+        true
+    }
+
+    fn payload(&self) -> Option<Vec<u8>> {
+        self.signature.clone()
+    }
+}
+
+/// Encrypt the input as a [`streaming`] stream to `public_key`
+pub fn encrypt(pipe: &Pipe, public_key: &[u8], suite: &CipherSuite) -> Result<PipeReport, PqcError> {
+    let mut reader = pipe.reader()?;
+    let bytes = pipe.write(|writer| {
+        let mut encryptor = StreamEncryptor::new_with_suite(writer, public_key, suite, streaming::DEFAULT_SEGMENT_LEN)?;
+        let bytes = io::copy(&mut reader, &mut encryptor).map_err(from_io)?;
+        encryptor.finish()?;
+        Ok(bytes)
+    })?;
+    Ok(pipe.report("Encrypted", bytes))
+}
+
+/// Decrypt a stream written by [`encrypt`]
+pub fn decrypt(pipe: &Pipe, secret_key: &[u8]) -> Result<PipeReport, PqcError> {
+    let reader = pipe.reader()?;
+    let bytes = pipe.write(|writer| streaming::decrypt(reader, writer, secret_key))?;
+    Ok(pipe.report("Decrypted", bytes))
+}
+
+/// Write a hex signature over the input's `algorithm` digest, checkable with `sign::verify_prehashed`
+pub fn sign(pipe: &Pipe, secret_key: &[u8], algorithm: HashAlgorithm) -> Result<PipeReport, PqcError> {
+    let mut counted = Counted { inner: pipe.reader()?, bytes: 0 };
+    let digest = hash::digest_reader(algorithm, &mut counted)?;
+    let signature = sign::sign_prehashed(&digest, algorithm, secret_key)?;
+    pipe.write(|writer| writeln!(writer, "{}", hex::encode(&signature))
+        .map_err(|e| PqcError::Io(format!("Failed to write output: {}", e))))?;
+    Ok(PipeReport { signature: Some(signature), ..pipe.report("Signed", counted.bytes) })
+}
+
+/// Counts the bytes passing through a reader
+struct Counted<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// Recover the [`PqcError`] a stream reported through `io::Error`
+fn from_io(e: io::Error) -> PqcError {
+    let message = e.to_string();
+    match e.into_inner().map(|inner| inner.downcast::<PqcError>()) {
+        Some(Ok(inner)) => *inner,
+        _ => PqcError::Io(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pqc_algo::kem;
+
+    /// A fresh directory for one test's files
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = PathBuf::from(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let dir = test_dir("test_pipe_round_trip");
+        let (plain, sealed, opened) = (dir.join("plain.bin"), dir.join("sealed.bin"), dir.join("opened.bin"));
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        fs::write(&plain, &data).unwrap();
+        let keypair = kem::generate_keypair().unwrap();
+
+        let report = encrypt(&Pipe { input: Some(&plain), output: Some(&sealed) }, &keypair.public_key, &CipherSuite::active_default()).unwrap();
+        assert_eq!(report.bytes, data.len() as u64);
+        let report = decrypt(&Pipe { input: Some(&sealed), output: Some(&opened) }, &keypair.secret_key).unwrap();
+        assert_eq!(report.bytes, data.len() as u64);
+        assert_eq!(fs::read(&opened).unwrap(), data);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&opened).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_failed_decrypt_leaves_no_output() {
+        let dir = test_dir("test_pipe_failed_decrypt");
+        let (plain, sealed, opened) = (dir.join("plain.bin"), dir.join("sealed.bin"), dir.join("opened.bin"));
+        fs::write(&plain, vec![7u8; 100_000]).unwrap();
+        let keypair = kem::generate_keypair().unwrap();
+        encrypt(&Pipe { input: Some(&plain), output: Some(&sealed) }, &keypair.public_key, &CipherSuite::active_default()).unwrap();
+
+        let mut corrupt = fs::read(&sealed).unwrap();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        fs::write(&sealed, corrupt).unwrap();
+        assert!(decrypt(&Pipe { input: Some(&sealed), output: Some(&opened) }, &keypair.secret_key).is_err());
+
+        // Neither the output nor its temporary file is left behind
+        let mut names: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names, ["plain.bin", "sealed.bin"]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_sign_round_trip() {
+        let dir = test_dir("test_pipe_sign");
+        let (input, signature) = (dir.join("firmware.bin"), dir.join("firmware.sig"));
+        fs::write(&input, b"firmware image").unwrap();
+        let keypair = sign::generate_keypair().unwrap();
+
+        let report = sign(&Pipe { input: Some(&input), output: Some(&signature) }, &keypair.secret_key, HashAlgorithm::Sha256).unwrap();
+        assert_eq!(report.bytes, 14);
+        let signature = hex::decode(fs::read_to_string(&signature).unwrap().trim()).unwrap();
+        assert_eq!(report.payload(), Some(signature.clone()));
+        let digest = hash::digest_reader(HashAlgorithm::Sha256, &mut File::open(&input).unwrap()).unwrap();
+        assert!(sign::verify_prehashed(&digest, HashAlgorithm::Sha256, &signature, &keypair.public_key).unwrap());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
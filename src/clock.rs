@@ -0,0 +1,214 @@
+//! Wall-clock time for expiry and validity checks
+//!
+//! Every check that depends on the current time reads the process clock:
+//! certificate issuance, validity and chain verification, status
+//! responses, delegations, key policies, policy preflight and the
+//! enrollment server. By default it is the operating system clock. A device
+//! without a battery-backed RTC boots at an arbitrary time, so install an
+//! [`AdjustedClock`] and [`sync`](AdjustedClock::sync) it once a trusted
+//! time is known, for example from an authenticated server response.
+//!
+//! Functions with an `_at` variant take the time and the [`ValidityOptions`]
+//! explicitly; prefer those in tests over replacing the process clock.
+//!
+//! Clocks on a fleet never agree exactly, so a deployment can widen every
+//! validity window by [`ValidityOptions::max_skew`] on both ends. Checks
+//! compare against the bare clock reading unless it opts in, either per
+//! call through the `_at` functions or for the whole process with
+//! [`set_validity`].
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of the current Unix time in seconds
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// The operating system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// The system clock corrected by an offset; clones share one offset
+#[derive(Debug, Clone, Default)]
+pub struct AdjustedClock {
+    offset: Arc<AtomicI64>,
+}
+
+impl AdjustedClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Correct the clock so that it reads `trusted_now` at this moment
+    pub fn sync(&self, trusted_now: u64) {
+        let system = SystemClock.now() as i64;
+        self.offset.store(trusted_now as i64 - system, Ordering::Relaxed);
+    }
+
+    /// Seconds added to the system clock
+    pub fn offset(&self) -> i64 {
+        self.offset.load(Ordering::Relaxed)
+    }
+}
+
+impl Clock for AdjustedClock {
+    fn now(&self) -> u64 {
+        SystemClock.now().saturating_add_signed(self.offset())
+    }
+}
+
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+/// Use `clock` for every time-dependent check in this process
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    *CLOCK.write().unwrap_or_else(|p| p.into_inner()) = Some(clock);
+}
+
+/// Go back to the operating system clock
+pub fn reset_clock() {
+    *CLOCK.write().unwrap_or_else(|p| p.into_inner()) = None;
+}
+
+/// Current Unix time according to the process clock
+pub fn now() -> u64 {
+    match CLOCK.read().unwrap_or_else(|p| p.into_inner()).as_ref() {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+/// Tolerances applied by every expiry and validity check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidityOptions {
+    /// How far apart two clocks may be and still agree on validity
+    pub max_skew: Duration,
+    /// How long before expiry a certificate is due for renewal
+    pub renewal_margin: Duration,
+}
+
+impl ValidityOptions {
+    /// Exact comparisons against the clock, with no tolerance; the default
+    pub const STRICT: Self = Self { max_skew: Duration::ZERO, renewal_margin: Duration::ZERO };
+
+    /// Five minutes of skew either way and a seven-day renewal margin
+    pub const TOLERANT: Self = Self {
+        max_skew: Duration::from_secs(5 * 60),
+        renewal_margin: Duration::from_secs(7 * 24 * 3600),
+    };
+
+    pub fn with_max_skew(mut self, max_skew: Duration) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    pub fn with_renewal_margin(mut self, renewal_margin: Duration) -> Self {
+        self.renewal_margin = renewal_margin;
+        self
+    }
+
+    /// Whether `now` falls in `not_before..=not_after`, give or take the skew
+    pub fn contains(&self, not_before: u64, not_after: u64, now: u64) -> bool {
+        !self.is_premature(not_before, now) && !self.is_expired(not_after, now)
+    }
+
+    /// Whether `now` is past `not_after` by more than the skew
+    pub fn is_expired(&self, not_after: u64, now: u64) -> bool {
+        now > not_after.saturating_add(self.max_skew.as_secs())
+    }
+
+    /// Whether `now` is before `not_before` by more than the skew
+    pub fn is_premature(&self, not_before: u64, now: u64) -> bool {
+        now.saturating_add(self.max_skew.as_secs()) < not_before
+    }
+
+    /// Whether something valid until `not_after` is within the renewal margin at `now`
+    pub fn needs_renewal(&self, not_after: u64, now: u64) -> bool {
+        now.saturating_add(self.renewal_margin.as_secs()) >= not_after
+    }
+}
+
+impl Default for ValidityOptions {
+    fn default() -> Self {
+        Self::STRICT
+    }
+}
+
+static VALIDITY: RwLock<ValidityOptions> = RwLock::new(ValidityOptions::STRICT);
+
+/// Use `options` for validity checks that read the process clock
+///
+/// The `_at` functions take their options as an argument and ignore this.
+pub fn set_validity(options: ValidityOptions) {
+    *VALIDITY.write().unwrap_or_else(|p| p.into_inner()) = options;
+}
+
+/// The tolerances checks reading the process clock apply; [`ValidityOptions::STRICT`] unless set
+pub fn validity() -> ValidityOptions {
+    *VALIDITY.read().unwrap_or_else(|p| p.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    /// System time, counting how often it is read
+    struct Counting(Arc<AtomicU64>);
+
+    impl Clock for Counting {
+        fn now(&self) -> u64 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            SystemClock.now()
+        }
+    }
+
+    #[test]
+    fn test_adjusted_clock_syncs_to_trusted_time() {
+        let clock = AdjustedClock::new();
+        let trusted = SystemClock.now() + 3600;
+        clock.clone().sync(trusted);
+        assert!((3599..=3601).contains(&clock.offset()));
+        assert!(clock.now() >= trusted);
+
+        clock.sync(0);
+        assert!(clock.now() <= 1);
+    }
+
+    #[test]
+    fn test_process_clock_is_used_by_checks() {
+        // Other tests run concurrently, so the installed clock keeps real time
+        let reads = Arc::new(AtomicU64::new(0));
+        set_clock(Arc::new(Counting(reads.clone())));
+        let envelope = crate::envelope::unix_now();
+        reset_clock();
+        assert!(reads.load(Ordering::Relaxed) >= 1);
+        assert!(envelope.abs_diff(SystemClock.now()) <= 1);
+    }
+
+    #[test]
+    fn test_validity_window_tolerates_skew() {
+        assert_eq!(ValidityOptions::default(), ValidityOptions::STRICT);
+        let options = ValidityOptions::default().with_max_skew(Duration::from_secs(60));
+        assert!(options.contains(1000, 2000, 941));
+        assert!(options.contains(1000, 2000, 2060));
+        assert!(!options.contains(1000, 2000, 939));
+        assert!(options.is_expired(2000, 2061));
+        assert!(!ValidityOptions::STRICT.contains(1000, 2000, 2001));
+        assert!(!options.is_expired(u64::MAX - 1, u64::MAX));
+
+        let options = options.with_renewal_margin(Duration::from_secs(100));
+        assert!(!options.needs_renewal(2000, 1899));
+        assert!(options.needs_renewal(2000, 1900));
+    }
+}
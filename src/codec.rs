@@ -0,0 +1,135 @@
+//! Moving signed artifacts between JSON, CBOR and MessagePack
+//!
+//! Every signature in the crate covers the canonical JSON of the signed
+//! fields behind a domain label (see `canonical`), never the bytes on the
+//! wire. An envelope, certificate or token can therefore be re-encoded for
+//! a CBOR device link or a MessagePack store and back with
+//! [`transcode`], and still verify without being signed again.
+//!
+//! Byte fields are written with [`util::bytes`](crate::util::bytes): a byte
+//! string in CBOR and MessagePack, an array of integers in JSON. Arrays of
+//! integers are still accepted from CBOR and MessagePack written before.
+
+use std::fmt;
+use std::str::FromStr;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::validation::{self, Validate, MAX_MESSAGE_LEN};
+
+/// Wire encoding of an artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// What `to_bytes`/`from_bytes` read and write
+    Json,
+    /// RFC 8949 CBOR
+    Cbor,
+    /// MessagePack with named fields, so optional fields survive
+    Binary,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Cbor => "cbor",
+            Format::Binary => "binary",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [Format::Json, Format::Cbor, Format::Binary]
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| PqcError::InvalidInput(format!("Unknown format: {}", s)))
+    }
+}
+
+/// Encode `value` in `format`
+pub fn encode<T: Serialize>(value: &T, format: Format) -> Result<Vec<u8>> {
+    let encode_error = |e: &dyn fmt::Display| PqcError::InvalidInput(format!("Failed to encode {}: {}", format, e));
+    match format {
+        Format::Json => serde_json::to_vec(value).map_err(|e| encode_error(&e)),
+        Format::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(value, &mut bytes).map_err(|e| encode_error(&e))?;
+            Ok(bytes)
+        }
+        Format::Binary => rmp_serde::to_vec_named(value).map_err(|e| encode_error(&e)),
+    }
+}
+
+/// Decode at most [`MAX_MESSAGE_LEN`] bytes of `format` and validate the result
+pub fn decode<T: DeserializeOwned + Validate>(bytes: &[u8], format: Format) -> Result<T> {
+    validation::check_max_len(format.name(), bytes.len(), MAX_MESSAGE_LEN)?;
+    let malformed = |e: &dyn fmt::Display| PqcError::InvalidInput(format!("Malformed {}: {}", format, e));
+    let value: T = match format {
+        Format::Json => serde_json::from_slice(bytes).map_err(|e| malformed(&e))?,
+        Format::Cbor => ciborium::from_reader(bytes).map_err(|e| malformed(&e))?,
+        Format::Binary => rmp_serde::from_slice(bytes).map_err(|e| malformed(&e))?,
+    };
+    value.validate()?;
+    Ok(value)
+}
+
+/// Re-encode an artifact of type `T` from one format to another
+///
+/// The artifact is decoded and validated, so malformed input is rejected
+/// rather than copied. Signatures are carried unchanged.
+pub fn transcode<T: Serialize + DeserializeOwned + Validate>(bytes: &[u8], from: Format, to: Format) -> Result<Vec<u8>> {
+    encode(&decode::<T>(bytes, from)?, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, DeviceCredentials, EncryptedEntropy};
+    use crate::envelope::SignedEnvelope;
+    use crate::sign;
+
+    #[test]
+    fn test_transcoded_envelope_still_verifies() {
+        let keypair = sign::generate_keypair().unwrap();
+        let envelope = SignedEnvelope::sign(b"config v7", "config", &keypair.secret_key, &keypair.public_key).unwrap();
+        let json = envelope.to_bytes().unwrap();
+
+        let cbor = transcode::<SignedEnvelope>(&json, Format::Json, Format::Cbor).unwrap();
+        let binary = transcode::<SignedEnvelope>(&cbor, Format::Cbor, Format::Binary).unwrap();
+        assert!(binary.len() < json.len());
+        let back = transcode::<SignedEnvelope>(&binary, Format::Binary, Format::Json).unwrap();
+        let decoded = SignedEnvelope::from_bytes(&back).unwrap();
+        assert_eq!(decoded, envelope);
+        assert!(decoded.verify(&keypair.public_key, "config").unwrap());
+    }
+
+    #[test]
+    fn test_optional_fields_survive_every_format() {
+        let device = DeviceCredentials::generate().unwrap();
+        let sealed = api::encrypt_entropy_for_device(b"entropy", &device.kyber_public_key).unwrap();
+        for format in [Format::Json, Format::Cbor, Format::Binary] {
+            let decoded: EncryptedEntropy = decode(&encode(&sealed, format).unwrap(), format).unwrap();
+            assert_eq!(decoded.suite, sealed.suite);
+            assert_eq!(api::decrypt_entropy(&decoded, &device.kyber_secret_key).unwrap(), b"entropy");
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        assert!(transcode::<SignedEnvelope>(b"{}", Format::Json, Format::Cbor).is_err());
+        assert!(decode::<SignedEnvelope>(&[0xff, 0x00], Format::Cbor).is_err());
+        assert!(decode::<SignedEnvelope>(b"not msgpack", Format::Binary).is_err());
+        assert_eq!("CBOR".parse::<Format>().unwrap(), Format::Cbor);
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! Backward compatibility against artifacts from earlier releases
+//!
+//! The corpus under `tests/corpus` holds one directory per crate version,
+//! each with a `manifest.json` naming its artifacts and the keys needed to
+//! open them. [`check_corpus`] decrypts, verifies or unwraps every artifact
+//! with the current code, so a release that can no longer read what an
+//! earlier one wrote fails CI. [`write_corpus`] snapshots the current
+//! version; it only ever adds artifacts, never rewrites existing ones.
+//!
+//! Each artifact file is one line of hex: the CBOR encoding of the wire
+//! type, which keeps keys and signatures as byte strings, or the exported
+//! blob itself for backups. Vectors must be written by a build linked
+//! against liboqs; keys from any other backend would not open under it.
+//!
+//! Manifests carry secret keys in the clear. Use throwaway keys only.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::api::{self, AuthRequest, DeviceCredentials, EncryptedEntropy};
+use crate::backup::BackupParams;
+use crate::cert::{Certificate, CertificateAuthority};
+use crate::codec::{self, Format};
+use crate::csr::CertificateRequest;
+use crate::envelope::{key_fingerprint, SignedEnvelope};
+use crate::error::{PqcError, Result};
+use crate::hash;
+use crate::key_transport::{self, TransportKey, WrappedCredentials};
+
+/// File in each version directory listing its artifacts
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+const PURPOSE: &str = "compat-corpus";
+const PASSPHRASE: &str = "compat corpus passphrase";
+const PLAINTEXT: &[u8] = b"PQC-Algo compatibility corpus entropy";
+const CHALLENGE: &[u8] = b"compat corpus challenge";
+
+/// How to check one artifact; hex fields hold keys, digests and fingerprints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Check {
+    /// `EncryptedEntropy` that must decrypt to a known plaintext
+    Entropy { device_secret_key: String, plaintext_sha256: String },
+    /// `SignedEnvelope` that must verify
+    Envelope { public_key: String, purpose: String },
+    /// `Certificate` whose signature must verify; expiry is ignored
+    Certificate { issuer_public_key: String },
+    /// `AuthRequest` that must verify under the default suite
+    AuthRequest { public_key: String },
+    /// `CertificateRequest` that must answer `challenge`
+    CertificateRequest { challenge: String },
+    /// `export_encrypted` blob that must restore the named credentials
+    Backup { passphrase: String, dilithium_fingerprint: String },
+    /// PSK-wrapped credentials, signed by `signer_public_key`, that must unwrap to the named credentials
+    WrappedCredentials { psk: String, signer_public_key: String, dilithium_fingerprint: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusEntry {
+    /// Artifact file name, relative to the version directory
+    pub file: String,
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+/// Contents of a version directory's [`MANIFEST_FILE`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusManifest {
+    /// Crate version that wrote the artifacts
+    pub crate_version: String,
+    pub entries: Vec<CorpusEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatFailure {
+    pub crate_version: String,
+    pub file: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompatReport {
+    /// Number of artifacts checked
+    pub checked: usize,
+    pub failures: Vec<CompatFailure>,
+}
+
+impl CompatReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl CorpusManifest {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let bytes = fs::read(&path)
+            .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| PqcError::InvalidInput(format!("Malformed corpus manifest {}: {}", path.display(), e)))
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode corpus manifest: {}", e)))?;
+        fs::write(&path, json)
+            .map_err(|e| PqcError::Io(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+/// Check every version directory under `root`
+pub fn check_corpus(root: &Path) -> Result<CompatReport> {
+    let mut dirs: Vec<_> = fs::read_dir(root)
+        .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", root.display(), e)))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.join(MANIFEST_FILE).is_file())
+        .collect();
+    dirs.sort();
+    let mut report = CompatReport::default();
+    for dir in dirs {
+        let version = check_version(&dir)?;
+        report.checked += version.checked;
+        report.failures.extend(version.failures);
+    }
+    Ok(report)
+}
+
+/// Check the artifacts of one version directory
+pub fn check_version(dir: &Path) -> Result<CompatReport> {
+    let manifest = CorpusManifest::load(dir)?;
+    let mut report = CompatReport::default();
+    for entry in &manifest.entries {
+        report.checked += 1;
+        let outcome = fs::read(dir.join(&entry.file))
+            .map_err(|e| PqcError::Io(format!("Failed to read artifact: {}", e)))
+            .and_then(|file| decode(String::from_utf8_lossy(&file).trim()))
+            .and_then(|bytes| check_artifact(&entry.check, &bytes));
+        if let Err(e) = outcome {
+            report.failures.push(CompatFailure {
+                crate_version: manifest.crate_version.clone(),
+                file: entry.file.clone(),
+                error: e.to_string(),
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Check one artifact's bytes, already decoded from hex, against its manifest entry
+pub fn check_artifact(check: &Check, bytes: &[u8]) -> Result<()> {
+    match check {
+        Check::Entropy { device_secret_key, plaintext_sha256 } => {
+            let encrypted: EncryptedEntropy = codec::decode(bytes, Format::Cbor)?;
+            let plaintext = api::decrypt_entropy(&encrypted, &decode(device_secret_key)?)?;
+            expect(hex::encode(hash::sha256(&[&plaintext])) == *plaintext_sha256, "Decrypted entropy does not match")
+        }
+        Check::Envelope { public_key, purpose } => {
+            let envelope: SignedEnvelope = codec::decode(bytes, Format::Cbor)?;
+            expect(envelope.verify(&decode(public_key)?, purpose)?, "Envelope signature does not verify")
+        }
+        Check::Certificate { issuer_public_key } => {
+            let certificate: Certificate = codec::decode(bytes, Format::Cbor)?;
+            expect(certificate.verify_signature(&decode(issuer_public_key)?)?, "Certificate signature does not verify")
+        }
+        Check::AuthRequest { public_key } => {
+            let request: AuthRequest = codec::decode(bytes, Format::Cbor)?;
+            expect(api::verify_auth_request(&request, &decode(public_key)?)?, "Auth request signature does not verify")
+        }
+        Check::CertificateRequest { challenge } => {
+            codec::decode::<CertificateRequest>(bytes, Format::Cbor)?.verify(&decode(challenge)?)
+        }
+        Check::Backup { passphrase, dilithium_fingerprint } => {
+            let credentials = DeviceCredentials::import_encrypted(bytes, passphrase)?;
+            expect(key_fingerprint(&credentials.dilithium_public_key) == *dilithium_fingerprint, "Backup restored other credentials")
+        }
+        Check::WrappedCredentials { psk, signer_public_key, dilithium_fingerprint } => {
+            let wrapped: WrappedCredentials = codec::decode(bytes, Format::Cbor)?;
+            let credentials = wrapped.unwrap(&decode(psk)?, &decode(signer_public_key)?)?;
+            expect(key_fingerprint(&credentials.dilithium_public_key) == *dilithium_fingerprint, "Unwrapped other credentials")
+        }
+    }
+}
+
+/// Add artifacts written by this crate version to `dir`
+///
+/// Files already listed in the manifest are left alone, so the corpus for
+/// a released version only grows. Returns the updated manifest.
+pub fn write_corpus(dir: &Path) -> Result<CorpusManifest> {
+    fs::create_dir_all(dir)
+        .map_err(|e| PqcError::Io(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let mut manifest = if dir.join(MANIFEST_FILE).exists() {
+        CorpusManifest::load(dir)?
+    } else {
+        CorpusManifest { crate_version: env!("CARGO_PKG_VERSION").to_string(), entries: Vec::new() }
+    };
+
+    for (file, bytes, check) in snapshot()? {
+        if manifest.entries.iter().any(|entry| entry.file == file) {
+            continue;
+        }
+        let path = dir.join(file);
+        fs::write(&path, hex::encode(bytes) + "\n")
+            .map_err(|e| PqcError::Io(format!("Failed to write {}: {}", path.display(), e)))?;
+        manifest.entries.push(CorpusEntry { file: file.to_string(), check });
+    }
+    manifest.save(dir)?;
+    Ok(manifest)
+}
+
+/// One artifact of every kind, made with fresh throwaway keys
+fn snapshot() -> Result<Vec<(&'static str, Vec<u8>, Check)>> {
+    let credentials = DeviceCredentials::generate()?;
+    let fingerprint = key_fingerprint(&credentials.dilithium_public_key);
+
+    let entropy = api::encrypt_entropy_for_device(PLAINTEXT, &credentials.kyber_public_key)?;
+    let envelope = SignedEnvelope::sign(PLAINTEXT, PURPOSE, &credentials.dilithium_secret_key, &credentials.dilithium_public_key)?;
+    let authority = CertificateAuthority::generate("compat-corpus-ca")?;
+    let certificate = authority.issue("compat-device", &credentials.dilithium_public_key, std::time::Duration::from_secs(3600), false)?;
+    let auth_request = api::create_auth_request("compat-device", CHALLENGE, &credentials.dilithium_secret_key)?;
+    let csr = CertificateRequest::new("compat-device", &credentials, CHALLENGE)?;
+    // Cheap costs keep the corpus check fast; the format is what is under test
+    let backup = credentials.export_encrypted_with(PASSPHRASE, BackupParams { memory_kib: 1024, iterations: 1, parallelism: 1 })?;
+    let psk = [0x5au8; key_transport::MIN_PSK_LEN];
+    let wrapped = key_transport::wrap_credentials(
+        "compat-device", &credentials, &TransportKey::Psk { id: PURPOSE.to_string(), key: psk.to_vec().into() }, &authority,
+    )?;
+
+    Ok(vec![
+        ("entropy.cbor.hex", codec::encode(&entropy, Format::Cbor)?, Check::Entropy {
+            device_secret_key: hex::encode(&*credentials.kyber_secret_key),
+            plaintext_sha256: hex::encode(hash::sha256(&[PLAINTEXT])),
+        }),
+        ("envelope.cbor.hex", codec::encode(&envelope, Format::Cbor)?, Check::Envelope {
+            public_key: hex::encode(&credentials.dilithium_public_key),
+            purpose: PURPOSE.to_string(),
+        }),
+        ("certificate.cbor.hex", codec::encode(&certificate, Format::Cbor)?, Check::Certificate {
+            issuer_public_key: hex::encode(authority.public_key()),
+        }),
+        ("auth-request.cbor.hex", codec::encode(&auth_request, Format::Cbor)?, Check::AuthRequest {
+            public_key: hex::encode(&credentials.dilithium_public_key),
+        }),
+        ("certificate-request.cbor.hex", codec::encode(&csr, Format::Cbor)?, Check::CertificateRequest {
+            challenge: hex::encode(CHALLENGE),
+        }),
+        ("backup.hex", backup, Check::Backup {
+            passphrase: PASSPHRASE.to_string(),
+            dilithium_fingerprint: fingerprint.clone(),
+        }),
+        ("wrapped-psk.cbor.hex", codec::encode(&wrapped, Format::Cbor)?, Check::WrappedCredentials {
+            psk: hex::encode(psk),
+            signer_public_key: hex::encode(authority.public_key()),
+            dilithium_fingerprint: fingerprint,
+        }),
+    ])
+}
+
+fn decode(hex_field: &str) -> Result<Vec<u8>> {
+    hex::decode(hex_field).map_err(|e| PqcError::InvalidInput(format!("Malformed hex in corpus: {}", e)))
+}
+
+fn expect(ok: bool, message: &str) -> Result<()> {
+    if ok { Ok(()) } else { Err(PqcError::Verification(message.to_string())) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_check_corpus() {
+        let root = std::env::temp_dir().join(format!("pqc_compat_test_{}", std::process::id()));
+        let dir = root.join("0.0.0");
+        let manifest = write_corpus(&dir).unwrap();
+        assert_eq!(manifest.entries.len(), 7);
+        // Rewriting adds nothing and leaves existing artifacts untouched
+        let before = fs::read(dir.join("envelope.cbor.hex")).unwrap();
+        assert_eq!(write_corpus(&dir).unwrap(), manifest);
+        assert_eq!(fs::read(dir.join("envelope.cbor.hex")).unwrap(), before);
+
+        let report = check_corpus(&root).unwrap();
+        assert_eq!(report.checked, 7);
+        assert!(report.is_ok(), "{:?}", report.failures);
+
+        fs::write(dir.join("backup.hex"), hex::encode(b"{}")).unwrap();
+        fs::write(dir.join("certificate.cbor.hex"), b"not hex").unwrap();
+        let report = check_corpus(&root).unwrap();
+        let mut failed: Vec<_> = report.failures.iter().map(|failure| failure.file.as_str()).collect();
+        failed.sort();
+        assert_eq!(failed, ["backup.hex", "certificate.cbor.hex"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_format() {
+        let entry = CorpusEntry {
+            file: "backup.hex".to_string(),
+            check: Check::Backup { passphrase: "p".to_string(), dilithium_fingerprint: "ab".to_string() },
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"file":"backup.hex","kind":"backup","passphrase":"p","dilithium_fingerprint":"ab"}"#);
+        assert_eq!(serde_json::from_str::<CorpusEntry>(&json).unwrap(), entry);
+    }
+}
@@ -0,0 +1,212 @@
+//! Conditioning stages for raw quantum seed material
+//!
+//! QRNG vendors deliver raw output with different biases: some ship
+//! debiased bits, others raw detector counts. A [`ConditioningPipeline`]
+//! runs each seed file through the stages chosen for its vendor before the
+//! seeds are combined into the master seed.
+//!
+//! | stage | input → output | use for |
+//! |-------|----------------|---------|
+//! | [`Conditioner::VonNeumann`] | bit pairs, about 4:1 | independent but biased bits |
+//! | [`Conditioner::Sha256`] | 64-byte blocks → 32 bytes | correlated or low-entropy-rate data |
+//! | [`Conditioner::AesCbcMac`] | 64-byte blocks → 16 bytes | SP 800-90B CBC-MAC extractor |
+
+use std::fmt;
+use std::str::FromStr;
+use aes_gcm::aes::cipher::{BlockEncrypt, KeyInit};
+use aes_gcm::aes::{Aes256, Block};
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::hash;
+
+/// Input consumed per output block by the SHA-256 and CBC-MAC stages
+pub const CONDITIONING_BLOCK_LEN: usize = 64;
+
+const AES_BLOCK_LEN: usize = 16;
+
+/// One conditioning stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Conditioner {
+    /// Von Neumann debiasing: `01` → 0, `10` → 1, `00` and `11` dropped
+    VonNeumann,
+    /// SHA-256 over each full 64-byte block; a shorter tail is dropped
+    Sha256,
+    /// AES-256 CBC-MAC over each 64-byte block under a fixed public key
+    AesCbcMac,
+}
+
+impl Conditioner {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Conditioner::VonNeumann => "von-neumann",
+            Conditioner::Sha256 => "sha256",
+            Conditioner::AesCbcMac => "aes-cbc-mac",
+        }
+    }
+
+    /// Run this stage over `input`
+    pub fn apply(&self, input: &[u8]) -> Vec<u8> {
+        match self {
+            Conditioner::VonNeumann => von_neumann(input),
+            Conditioner::Sha256 => input
+                .chunks_exact(CONDITIONING_BLOCK_LEN)
+                .flat_map(|block| hash::sha256(&[block]))
+                .collect(),
+            Conditioner::AesCbcMac => cbc_mac_extract(input),
+        }
+    }
+}
+
+impl fmt::Display for Conditioner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Conditioner {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [Conditioner::VonNeumann, Conditioner::Sha256, Conditioner::AesCbcMac]
+            .into_iter()
+            .find(|stage| stage.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| PqcError::InvalidInput(format!("Unknown conditioning stage: {}", s)))
+    }
+}
+
+fn von_neumann(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 4);
+    let (mut byte, mut bits) = (0u8, 0);
+    for &raw in input {
+        for pair in (0..4).rev() {
+            let (first, second) = ((raw >> (pair * 2 + 1)) & 1, (raw >> (pair * 2)) & 1);
+            if first == second {
+                continue;
+            }
+            byte = (byte << 1) | first;
+            bits += 1;
+            if bits == 8 {
+                output.push(byte);
+                (byte, bits) = (0, 0);
+            }
+        }
+    }
+    output
+}
+
+/// CBC-MAC each 64-byte block; trailing bytes short of an AES block are dropped
+fn cbc_mac_extract(input: &[u8]) -> Vec<u8> {
+    let key = hash::sha256(&[b"PQC-Algo/v1/cbc-mac-extractor"]);
+    let cipher = Aes256::new(&key.into());
+    let mut output = Vec::new();
+    for block in input.chunks(CONDITIONING_BLOCK_LEN) {
+        if block.len() < AES_BLOCK_LEN {
+            break;
+        }
+        let mut state = Block::default();
+        for aes_block in block.chunks_exact(AES_BLOCK_LEN) {
+            for (s, b) in state.iter_mut().zip(aes_block) {
+                *s ^= b;
+            }
+            cipher.encrypt_block(&mut state);
+        }
+        output.extend_from_slice(&state);
+    }
+    output
+}
+
+/// Ordered conditioning stages; empty means the raw seeds are used as-is
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConditioningPipeline {
+    stages: Vec<Conditioner>,
+}
+
+impl ConditioningPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `stage` to the pipeline
+    pub fn then(mut self, stage: Conditioner) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn stages(&self) -> &[Conditioner] {
+        &self.stages
+    }
+
+    /// Run every stage in order over `raw`
+    ///
+    /// Fails if a stage leaves nothing, e.g. von Neumann over constant input.
+    pub fn apply(&self, raw: &[u8]) -> Result<Vec<u8>> {
+        let mut data = raw.to_vec();
+        for stage in &self.stages {
+            data = stage.apply(&data);
+            if data.is_empty() {
+                return Err(PqcError::InvalidInput(format!(
+                    "Conditioning stage {} left no output from {} raw bytes", stage, raw.len()
+                )));
+            }
+        }
+        Ok(data)
+    }
+
+    /// Human-readable description, e.g. `raw -> von-neumann -> sha256`
+    pub fn describe(&self) -> String {
+        std::iter::once("raw")
+            .chain(self.stages.iter().map(Conditioner::name))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// Parses a comma-separated stage list such as `von-neumann,sha256`
+impl FromStr for ConditioningPipeline {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let stages = s
+            .split(',')
+            .filter(|stage| !stage.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_>>()?;
+        Ok(Self { stages })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_von_neumann_debiases_pairs() {
+        // 0b01_10_00_11 keeps bits 0 and 1, so 16 such bytes give four 0b01010101
+        let output = Conditioner::VonNeumann.apply(&[0b0110_0011; 16]);
+        assert_eq!(output, vec![0b0101_0101; 4]);
+        assert!(Conditioner::VonNeumann.apply(&[0xff; 64]).is_empty());
+    }
+
+    #[test]
+    fn test_block_stages_compress() {
+        let raw = [0x5au8; 256];
+        assert_eq!(Conditioner::Sha256.apply(&raw).len(), 128);
+        // A partial block would yield more output than it holds entropy
+        assert_eq!(Conditioner::Sha256.apply(&raw[..100]).len(), 32);
+        assert!(Conditioner::Sha256.apply(&raw[..63]).is_empty());
+        assert_eq!(Conditioner::AesCbcMac.apply(&raw).len(), 64);
+        assert_eq!(Conditioner::AesCbcMac.apply(&raw[..70]).len(), 16);
+        assert_ne!(Conditioner::AesCbcMac.apply(&raw[..64]), Conditioner::AesCbcMac.apply(&[0xa5; 64]));
+    }
+
+    #[test]
+    fn test_pipeline_parse_and_describe() {
+        let pipeline: ConditioningPipeline = "von-neumann, SHA256".parse().unwrap();
+        assert_eq!(pipeline, ConditioningPipeline::new().then(Conditioner::VonNeumann).then(Conditioner::Sha256));
+        assert_eq!(pipeline.describe(), "raw -> von-neumann -> sha256");
+        assert_eq!(ConditioningPipeline::new().apply(b"seed").unwrap(), b"seed");
+        assert!(pipeline.apply(&[0u8; 64]).is_err());
+        assert!("xor".parse::<ConditioningPipeline>().is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Encrypted configuration push with signed acknowledgment
+//!
+//! The service seals a configuration blob to one device with [`push`]: a
+//! fresh KEM encapsulation to the device's key, AEAD under a key derived
+//! from it, and the device ID and config version as associated data, so a
+//! push cannot be replayed to another device or passed off as another
+//! version. The service signs the whole push. The device checks the
+//! signature and decrypts with [`open`], applies the config, and answers
+//! with an [`acknowledge`]ment signed over the SHA-256 of what it applied.
+//! [`verify_ack`] tells the service that this exact config is in place.
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::hash;
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::{canonical, fips, kdf, kem};
+
+/// Domain label for config push signatures
+const PUSH_DOMAIN: &str = "PQC-Algo/v1/config-push";
+
+/// Domain label for the associated data of the sealed config
+const PUSH_AAD_DOMAIN: &str = "PQC-Algo/v1/config-push-aad";
+
+/// Domain label for acknowledgment signatures
+const ACK_DOMAIN: &str = "PQC-Algo/v1/config-ack";
+
+/// A configuration sealed to one device and signed by the service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigPush {
+    pub device_id: String,
+    pub version: u64,
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kem_ciphertext: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub sealed: Vec<u8>,
+    pub signer_fingerprint: String,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`ConfigPush`] covered by its signature
+#[derive(Serialize)]
+struct PushFields<'a> {
+    device_id: &'a str,
+    version: u64,
+    suite: &'a CipherSuite,
+    kem_ciphertext: String,
+    sealed: String,
+    signer_fingerprint: &'a str,
+}
+
+/// Associated data of the sealed config
+#[derive(Serialize)]
+struct PushAad<'a> {
+    device_id: &'a str,
+    version: u64,
+}
+
+impl ConfigPush {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode config push: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "config push")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(PUSH_DOMAIN, &PushFields {
+            device_id: &self.device_id,
+            version: self.version,
+            suite: &self.suite,
+            kem_ciphertext: hex::encode(&self.kem_ciphertext),
+            sealed: hex::encode(&self.sealed),
+            signer_fingerprint: &self.signer_fingerprint,
+        })
+    }
+}
+
+impl Validate for ConfigPush {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_exact_len("KEM ciphertext", self.kem_ciphertext.len(), self.suite.kem.ciphertext_len()?)?;
+        validation::check_sealed_len("Sealed config", self.sealed.len())?;
+        validation::check_fingerprint("Config signer fingerprint", &self.signer_fingerprint)?;
+        validation::check_signature_len("Config signature", self.suite.sig, self.signature.len())
+    }
+}
+
+/// A device's signed statement that it applied a config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigAck {
+    pub device_id: String,
+    pub version: u64,
+    /// Hex SHA-256 of the applied config
+    pub config_sha256: String,
+    pub signer_fingerprint: String,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`ConfigAck`] covered by its signature
+#[derive(Serialize)]
+struct AckFields<'a> {
+    device_id: &'a str,
+    version: u64,
+    config_sha256: &'a str,
+    signer_fingerprint: &'a str,
+    algorithm: &'a str,
+}
+
+impl ConfigAck {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode config acknowledgment: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "config acknowledgment")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(ACK_DOMAIN, &AckFields {
+            device_id: &self.device_id,
+            version: self.version,
+            config_sha256: &self.config_sha256,
+            signer_fingerprint: &self.signer_fingerprint,
+            algorithm: self.algorithm.name(),
+        })
+    }
+}
+
+impl Validate for ConfigAck {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_fingerprint("Config digest", &self.config_sha256)?;
+        validation::check_fingerprint("Acknowledgment signer fingerprint", &self.signer_fingerprint)?;
+        validation::check_signature_len("Acknowledgment signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// A config the device has decrypted and authenticated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedConfig {
+    pub device_id: String,
+    pub version: u64,
+    pub config: Zeroizing<Vec<u8>>,
+}
+
+/// Seal `config` version `version` to a device and sign it as the service
+pub fn push(device_id: &str, version: u64, config: &[u8], device_kyber_public_key: &[u8], service_secret_key: &[u8], service_public_key: &[u8]) -> Result<ConfigPush> {
+    push_with_suite(device_id, version, config, device_kyber_public_key, service_secret_key, service_public_key, &CipherSuite::active_default())
+}
+
+/// [`push`] under an explicit suite; the service key must belong to it
+pub fn push_with_suite(
+    device_id: &str,
+    version: u64,
+    config: &[u8],
+    device_kyber_public_key: &[u8],
+    service_secret_key: &[u8],
+    service_public_key: &[u8],
+    suite: &CipherSuite,
+) -> Result<ConfigPush> {
+    fips::check_suite(suite)?;
+    validate_device_id(device_id)?;
+    let (kem_ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, device_kyber_public_key)?;
+    let key = push_key(&Zeroizing::new(shared_secret), suite)?;
+    let sealed = suite.aead.seal_with_aad(&key, config, &push_aad(device_id, version)?)?;
+
+    let mut pushed = ConfigPush {
+        device_id: device_id.to_string(),
+        version,
+        suite: *suite,
+        kem_ciphertext,
+        sealed,
+        signer_fingerprint: key_fingerprint(service_public_key),
+        signature: Vec::new(),
+    };
+    pushed.signature = sign::sign_with(suite.sig, &pushed.signing_bytes()?, service_secret_key)?;
+    Ok(pushed)
+}
+
+/// Check the service signature and decrypt a push addressed to `device_id`
+///
+/// Versions below `min_version`, normally the version currently applied,
+/// are refused so an old config cannot be pushed again.
+pub fn open(pushed: &ConfigPush, device_id: &str, min_version: u64, device_kyber_secret_key: &[u8], service_public_key: &[u8]) -> Result<ReceivedConfig> {
+    pushed.validate()?;
+    let suite = &pushed.suite;
+    fips::check_suite(suite)?;
+    if !ct::ct_eq_hex(&pushed.signer_fingerprint, &key_fingerprint(service_public_key))
+        || !sign::verify_with(suite.sig, &pushed.signing_bytes()?, &pushed.signature, service_public_key)?
+    {
+        return Err(PqcError::Verification("Config push was not signed by the service".to_string()));
+    }
+    if pushed.device_id != device_id {
+        return Err(PqcError::Verification(format!("Config push is addressed to {}", pushed.device_id)));
+    }
+    if pushed.version < min_version {
+        return Err(PqcError::Verification(format!(
+            "Config version {} is older than {}", pushed.version, min_version
+        )));
+    }
+
+    let shared_secret = Zeroizing::new(kem::decapsulate_with(suite.kem, device_kyber_secret_key, &pushed.kem_ciphertext)?);
+    let key = push_key(&shared_secret, suite)?;
+    let config = suite.aead.open_with_aad(&key, &pushed.sealed, &push_aad(&pushed.device_id, pushed.version)?)?;
+    Ok(ReceivedConfig { device_id: pushed.device_id.clone(), version: pushed.version, config: Zeroizing::new(config) })
+}
+
+/// Sign that `received` was applied, with the device's signing key
+pub fn acknowledge(received: &ReceivedConfig, device_secret_key: &[u8], device_public_key: &[u8]) -> Result<ConfigAck> {
+    let algorithm = SigAlgorithm::for_secret_key(device_secret_key)?;
+    fips::check_sig(algorithm)?;
+    let mut ack = ConfigAck {
+        device_id: received.device_id.clone(),
+        version: received.version,
+        config_sha256: hex::encode(hash::sha256(&[&received.config])),
+        signer_fingerprint: key_fingerprint(device_public_key),
+        algorithm,
+        signature: Vec::new(),
+    };
+    ack.signature = sign::sign_with(algorithm, &ack.signing_bytes()?, device_secret_key)?;
+    Ok(ack)
+}
+
+/// Check that the device applied exactly `config` as version `version`
+pub fn verify_ack(ack: &ConfigAck, device_id: &str, version: u64, config: &[u8], device_public_key: &[u8]) -> Result<()> {
+    ack.validate()?;
+    fips::check_sig(ack.algorithm)?;
+    if !ct::ct_eq_hex(&ack.signer_fingerprint, &key_fingerprint(device_public_key))
+        || !sign::verify_with(ack.algorithm, &ack.signing_bytes()?, &ack.signature, device_public_key)?
+    {
+        return Err(PqcError::Verification("Config acknowledgment was not signed by the device".to_string()));
+    }
+    if ack.device_id != device_id || ack.version != version {
+        return Err(PqcError::Verification(format!(
+            "Acknowledgment is for {} version {}, expected {} version {}", ack.device_id, ack.version, device_id, version
+        )));
+    }
+    if !ct::ct_eq_hex(&ack.config_sha256, &hex::encode(hash::sha256(&[config]))) {
+        return Err(PqcError::Verification("Device applied a different config".to_string()));
+    }
+    Ok(())
+}
+
+fn push_key(shared_secret: &[u8], suite: &CipherSuite) -> Result<Zeroizing<Vec<u8>>> {
+    kdf::hkdf_sha256(shared_secret, &[], &kdf::label("config-push"), suite.aead.key_len()).map(Zeroizing::new)
+}
+
+fn push_aad(device_id: &str, version: u64) -> Result<Vec<u8>> {
+    canonical::signing_bytes(PUSH_AAD_DOMAIN, &PushAad { device_id, version })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceCredentials;
+
+    #[test]
+    fn test_push_open_acknowledge() {
+        let service = DeviceCredentials::generate().unwrap();
+        let device = DeviceCredentials::generate().unwrap();
+        let config = br#"{"sample_rate":10}"#;
+
+        let pushed = push("sensor-1", 7, config, &device.kyber_public_key, &service.dilithium_secret_key, &service.dilithium_public_key).unwrap();
+        let pushed = ConfigPush::from_bytes(&pushed.to_bytes().unwrap()).unwrap();
+        let received = open(&pushed, "sensor-1", 6, &device.kyber_secret_key, &service.dilithium_public_key).unwrap();
+        assert_eq!(received.config.as_slice(), config);
+        assert_eq!(received.version, 7);
+
+        let ack = acknowledge(&received, &device.dilithium_secret_key, &device.dilithium_public_key).unwrap();
+        let ack = ConfigAck::from_bytes(&ack.to_bytes().unwrap()).unwrap();
+        verify_ack(&ack, "sensor-1", 7, config, &device.dilithium_public_key).unwrap();
+        assert!(verify_ack(&ack, "sensor-1", 7, b"other config", &device.dilithium_public_key).is_err());
+        assert!(verify_ack(&ack, "sensor-1", 8, config, &device.dilithium_public_key).is_err());
+        assert!(verify_ack(&ack, "sensor-1", 7, config, &service.dilithium_public_key).is_err());
+
+        // A device key outside the active suite still signs under its own algorithm
+        let algorithm = if CipherSuite::active_default().sig == SigAlgorithm::MlDsa87 { SigAlgorithm::MlDsa44 } else { SigAlgorithm::MlDsa87 };
+        let other_key = sign::generate_keypair_with(algorithm).unwrap();
+        let ack = acknowledge(&received, &other_key.secret_key, &other_key.public_key).unwrap();
+        assert_eq!(ack.algorithm, algorithm);
+        verify_ack(&ack, "sensor-1", 7, config, &other_key.public_key).unwrap();
+    }
+
+    #[test]
+    fn test_push_is_bound_to_device_and_version() {
+        let service = DeviceCredentials::generate().unwrap();
+        let device = DeviceCredentials::generate().unwrap();
+        let pushed = push("sensor-1", 3, b"config", &device.kyber_public_key, &service.dilithium_secret_key, &service.dilithium_public_key).unwrap();
+
+        assert!(open(&pushed, "sensor-2", 0, &device.kyber_secret_key, &service.dilithium_public_key).is_err());
+        assert!(open(&pushed, "sensor-1", 4, &device.kyber_secret_key, &service.dilithium_public_key).is_err());
+        assert!(open(&pushed, "sensor-1", 0, &device.kyber_secret_key, &device.dilithium_public_key).is_err());
+
+        // Relabelling the version breaks the signature and the AAD
+        let mut relabelled = pushed.clone();
+        relabelled.version = 9;
+        assert!(matches!(open(&relabelled, "sensor-1", 0, &device.kyber_secret_key, &service.dilithium_public_key), Err(PqcError::Verification(_))));
+        relabelled.signature = sign::sign_with(relabelled.suite.sig, &relabelled.signing_bytes().unwrap(), &service.dilithium_secret_key).unwrap();
+        assert!(matches!(open(&relabelled, "sensor-1", 0, &device.kyber_secret_key, &service.dilithium_public_key), Err(PqcError::Decryption(_))));
+    }
+}
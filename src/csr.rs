@@ -0,0 +1,174 @@
+//! Certificate requests with proof of possession
+//!
+//! Instead of taking server-generated secrets from `provision_device`, a
+//! device that can generate keys builds its own [`DeviceCredentials`] and
+//! sends only a [`CertificateRequest`]: its public keys and a challenge from
+//! the service, signed with the new Dilithium key. The signature proves the
+//! device holds that key, and the challenge stops a captured request from
+//! being replayed to certify the same key again. The Kyber key is bound by
+//! the same signature and recorded in the issued certificate under
+//! [`KYBER_KEY_EXTENSION`]; possession of its secret shows the first time
+//! the device decrypts entropy sent to it.
+
+use serde::{Deserialize, Serialize};
+use crate::api::DeviceCredentials;
+use crate::cert::Certificate;
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::extensions::Extensions;
+use crate::sign;
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::{canonical, fips};
+
+/// Domain label for certificate request signatures
+const REQUEST_DOMAIN: &str = "PQC-Algo/v1/certificate-request";
+
+/// Certificate extension holding the hex SHA-256 of the device's Kyber key
+pub const KYBER_KEY_EXTENSION: &str = "2.25.246744774272014273217104064343793671603";
+
+/// Self-signed request to certify a device's own keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateRequest {
+    pub device_id: String,
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kyber_public_key: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub dilithium_public_key: Vec<u8>,
+    /// Service-issued challenge this request answers
+    #[serde(with = "crate::util::bytes")]
+    pub challenge: Vec<u8>,
+    /// Signature by `dilithium_public_key`'s secret key
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`CertificateRequest`] covered by its signature
+#[derive(Serialize)]
+struct RequestFields<'a> {
+    device_id: &'a str,
+    suite: &'a CipherSuite,
+    kyber_public_key: String,
+    dilithium_public_key: String,
+    challenge: String,
+}
+
+impl CertificateRequest {
+    /// Sign a request for `credentials` under the active default suite
+    pub fn new(device_id: &str, credentials: &DeviceCredentials, challenge: &[u8]) -> Result<Self> {
+        Self::new_with_suite(device_id, credentials, challenge, CipherSuite::active_default())
+    }
+
+    /// [`CertificateRequest::new`] for credentials generated under `suite`
+    pub fn new_with_suite(device_id: &str, credentials: &DeviceCredentials, challenge: &[u8], suite: CipherSuite) -> Result<Self> {
+        validate_device_id(device_id)?;
+        fips::check_suite(&suite)?;
+        let mut request = Self {
+            device_id: device_id.to_string(),
+            suite,
+            kyber_public_key: credentials.kyber_public_key.clone(),
+            dilithium_public_key: credentials.dilithium_public_key.clone(),
+            challenge: challenge.to_vec(),
+            signature: Vec::new(),
+        };
+        request.signature = sign::sign_with(suite.sig, &request.signing_bytes()?, &credentials.dilithium_secret_key)?;
+        Ok(request)
+    }
+
+    /// Hex SHA-256 of the Dilithium key being certified
+    pub fn fingerprint(&self) -> String {
+        key_fingerprint(&self.dilithium_public_key)
+    }
+
+    /// Hex SHA-256 of the Kyber key the request binds
+    pub fn kyber_fingerprint(&self) -> String {
+        key_fingerprint(&self.kyber_public_key)
+    }
+
+    /// Extensions recording the request's Kyber key in the certificate issued for it
+    pub fn certificate_extensions(&self) -> Result<Extensions> {
+        let mut extensions = Extensions::new();
+        extensions.insert(KYBER_KEY_EXTENSION, false, self.kyber_fingerprint().as_bytes())?;
+        Ok(extensions)
+    }
+
+    /// Check the request answers `challenge` and is signed by the key it carries
+    pub fn verify(&self, challenge: &[u8]) -> Result<()> {
+        fips::check_suite(&self.suite)?;
+        self.validate()?;
+        if challenge.is_empty() || !ct::ct_eq(&self.challenge, challenge) {
+            return Err(PqcError::Verification("Certificate request does not answer this challenge".to_string()));
+        }
+        if !sign::verify_with(self.suite.sig, &self.signing_bytes()?, &self.signature, &self.dilithium_public_key)? {
+            return Err(PqcError::Verification("Certificate request proof of possession does not verify".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode certificate request: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "certificate request")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(REQUEST_DOMAIN, &RequestFields {
+            device_id: &self.device_id,
+            suite: &self.suite,
+            kyber_public_key: hex::encode(&self.kyber_public_key),
+            dilithium_public_key: hex::encode(&self.dilithium_public_key),
+            challenge: hex::encode(&self.challenge),
+        })
+    }
+}
+
+/// Fingerprint of the Kyber key `certificate` was issued for, if it records one
+pub fn certified_kyber_fingerprint(certificate: &Certificate) -> Option<&str> {
+    certificate.extensions.get(KYBER_KEY_EXTENSION)
+        .and_then(|extension| std::str::from_utf8(&extension.value).ok())
+}
+
+impl Validate for CertificateRequest {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_exact_len("Request Kyber public key", self.kyber_public_key.len(), self.suite.kem.public_key_len()?)?;
+        validation::check_exact_len("Request Dilithium public key", self.dilithium_public_key.len(), self.suite.sig.public_key_len()?)?;
+        validation::check_max_len("Request challenge", self.challenge.len(), validation::MAX_NONCE_LEN)?;
+        validation::check_signature_len("Request signature", self.suite.sig, self.signature.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_round_trip() {
+        let credentials = DeviceCredentials::generate().unwrap();
+        let request = CertificateRequest::new("sensor-7", &credentials, b"challenge-1").unwrap();
+        let decoded = CertificateRequest::from_bytes(&request.to_bytes().unwrap()).unwrap();
+        decoded.verify(b"challenge-1").unwrap();
+        assert_eq!(decoded.fingerprint(), key_fingerprint(&credentials.dilithium_public_key));
+    }
+
+    #[test]
+    fn test_request_rejects_replay_and_substitution() {
+        let credentials = DeviceCredentials::generate().unwrap();
+        let attacker = DeviceCredentials::generate().unwrap();
+        let request = CertificateRequest::new("sensor-7", &credentials, b"challenge-1").unwrap();
+        assert!(matches!(request.verify(b"challenge-2"), Err(PqcError::Verification(_))));
+
+        let mut swapped = request.clone();
+        swapped.dilithium_public_key = attacker.dilithium_public_key.clone();
+        assert!(matches!(swapped.verify(b"challenge-1"), Err(PqcError::Verification(_))));
+
+        let mut renamed = request;
+        renamed.device_id = "sensor-8".to_string();
+        assert!(renamed.verify(b"challenge-1").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Cache of KEM shared secrets keyed by ciphertext
+//!
+//! A gateway that receives the same broadcast envelope from many retries
+//! would otherwise decapsulate it every time. Entries are keyed by a hash of
+//! the KEM, the recipient secret key and the ciphertext, so a ciphertext
+//! replayed against a different key never hits another key's entry. A
+//! secret is only cached once the caller has authenticated the envelope with
+//! it, so ciphertexts that fail the AEAD never take a slot. Shared secrets
+//! are zeroized when evicted, removed or dropped with the cache.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use zeroize::Zeroizing;
+use crate::error::Result;
+use crate::hash::{self, HashAlgorithm};
+use crate::kem::{self, KemAlgorithm};
+
+/// Default maximum number of cached shared secrets
+pub const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+struct CacheEntry {
+    shared_secret: Zeroizing<Vec<u8>>,
+    last_used: Instant,
+}
+
+/// Thread-safe LRU cache of decapsulation results
+pub struct DecapsulationCache {
+    max_entries: usize,
+    entries: Mutex<HashMap<Vec<u8>, CacheEntry>>,
+}
+
+impl Default for DecapsulationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl DecapsulationCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self { max_entries: max_entries.max(1), entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Decapsulate `ciphertext`, reusing the shared secret from an earlier call, and pass the secret to `open`
+    ///
+    /// A new secret is cached only if `open` succeeds. ML-KEM and Kyber
+    /// decapsulate any well-formed ciphertext, so `open` should check the AEAD
+    /// tag before returning.
+    pub fn decapsulate_and_open<T>(
+        &self,
+        algorithm: KemAlgorithm,
+        secret_key: &[u8],
+        ciphertext: &[u8],
+        open: impl FnOnce(&[u8]) -> Result<T>,
+    ) -> Result<T> {
+        let id = Self::entry_id(algorithm, secret_key, ciphertext);
+        let cached = self.lock().get_mut(&id).map(|entry| {
+            entry.last_used = Instant::now();
+            entry.shared_secret.clone()
+        });
+        if let Some(shared_secret) = cached {
+            return open(&shared_secret);
+        }
+
+        // Decapsulate and open without holding the lock; a racing miss just computes it twice
+        let shared_secret = Zeroizing::new(kem::decapsulate_with(algorithm, secret_key, ciphertext)?);
+        let opened = open(&shared_secret)?;
+        self.insert(id, shared_secret);
+        Ok(opened)
+    }
+
+    fn insert(&self, id: Vec<u8>, shared_secret: Zeroizing<Vec<u8>>) {
+        let mut entries = self.lock();
+        if !entries.contains_key(&id) && entries.len() >= self.max_entries {
+            let oldest = entries.iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(id, _)| id.clone());
+            if let Some(id) = oldest {
+                entries.remove(&id);
+            }
+        }
+        entries.insert(id, CacheEntry { shared_secret, last_used: Instant::now() });
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn entry_id(algorithm: KemAlgorithm, secret_key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        let mut input = hash::digest(HashAlgorithm::Sha256, secret_key);
+        input.extend_from_slice(algorithm.name().as_bytes());
+        input.push(0);
+        input.extend_from_slice(ciphertext);
+        hash::digest(HashAlgorithm::Sha256, &input)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, CacheEntry>> {
+        self.entries.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PqcError;
+
+    /// Returns the shared secret, as if the AEAD opened with it
+    fn accept(shared_secret: &[u8]) -> Result<Vec<u8>> {
+        Ok(shared_secret.to_vec())
+    }
+
+    #[test]
+    fn test_cache_hits_and_eviction() {
+        let recipient = kem::generate_keypair().unwrap();
+        let other = kem::generate_keypair().unwrap();
+        let cache = DecapsulationCache::new(2);
+
+        let (ct1, ss1) = kem::encapsulate(&recipient.public_key).unwrap();
+        let (ct2, _) = kem::encapsulate(&recipient.public_key).unwrap();
+        let (ct3, _) = kem::encapsulate(&recipient.public_key).unwrap();
+
+        assert_eq!(cache.decapsulate_and_open(KemAlgorithm::Kyber1024, &recipient.secret_key, &ct1, accept).unwrap(), ss1);
+        assert_eq!(cache.decapsulate_and_open(KemAlgorithm::Kyber1024, &recipient.secret_key, &ct1, accept).unwrap(), ss1);
+        assert_eq!(cache.len(), 1);
+
+        // The same ciphertext under another key is a separate entry
+        let wrong = cache.decapsulate_and_open(KemAlgorithm::Kyber1024, &other.secret_key, &ct1, accept).unwrap();
+        assert_ne!(wrong, ss1);
+
+        cache.decapsulate_and_open(KemAlgorithm::Kyber1024, &recipient.secret_key, &ct2, accept).unwrap();
+        cache.decapsulate_and_open(KemAlgorithm::Kyber1024, &recipient.secret_key, &ct3, accept).unwrap();
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_failed_open_is_not_cached() {
+        let recipient = kem::generate_keypair().unwrap();
+        let cache = DecapsulationCache::new(2);
+        let garbage = vec![0x5a; KemAlgorithm::Kyber1024.sizes().ciphertext];
+
+        for _ in 0..3 {
+            let result = cache.decapsulate_and_open(KemAlgorithm::Kyber1024, &recipient.secret_key, &garbage, |_| {
+                Err::<(), _>(PqcError::Decryption("tag mismatch".to_string()))
+            });
+            assert!(result.is_err());
+        }
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,458 @@
+//! Short-lived delegated credentials for field access
+//!
+//! A technician generates a temporary Dilithium key and the device signs a
+//! [`Delegation`] to it: which device, which scopes (for example `unlock`
+//! or `read-logs`) and for how many minutes. The technician then signs
+//! actions with the temporary key, and whoever trusts the device key checks
+//! the whole chain with [`DelegatedSignature::verify`]. A delegate can pass a
+//! narrower, shorter-lived delegation on to someone else, but never widen
+//! the scopes or extend the expiry it was given.
+//!
+//! Each action carries a timestamp and a random nonce under its signature.
+//! Verification refuses actions older than [`MAX_ACTION_AGE`], and an
+//! [`ActionReplayGuard`] remembers the nonces it has seen for that long, so
+//! a captured action cannot be played again.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::secret::SecretKey;
+use crate::sign::{self, SigAlgorithm};
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::clock::{self, ValidityOptions};
+use crate::{canonical, fips};
+
+/// Domain label for delegation signatures
+const DELEGATION_DOMAIN: &str = "PQC-Algo/v1/delegation";
+
+/// Domain label for actions signed with a delegated key
+const DELEGATED_ACTION_DOMAIN: &str = "PQC-Algo/v1/delegated-action";
+
+/// Longest lifetime a single delegation may have
+pub const MAX_LIFETIME: Duration = Duration::from_secs(24 * 3600);
+
+/// Most delegations in one chain, the device's own included
+pub const MAX_CHAIN_LEN: usize = 4;
+
+/// How long after signing a delegated action is still accepted
+pub const MAX_ACTION_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Random bytes in an action's nonce
+const NONCE_LEN: usize = 16;
+
+/// Most scopes one delegation may grant
+pub const MAX_SCOPES: usize = 32;
+
+/// One signed hand-over of limited authority to a temporary key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delegation {
+    pub device_id: String,
+    pub scopes: Vec<String>,
+    pub not_before: u64,
+    pub not_after: u64,
+    pub issuer_fingerprint: String,
+    #[serde(with = "crate::util::bytes")]
+    pub delegate_public_key: Vec<u8>,
+    /// Algorithm of the issuer's signature
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`Delegation`] covered by its signature
+#[derive(Serialize)]
+struct DelegationFields<'a> {
+    device_id: &'a str,
+    scopes: &'a [String],
+    not_before: u64,
+    not_after: u64,
+    issuer_fingerprint: &'a str,
+    delegate_public_key: String,
+    algorithm: &'a str,
+}
+
+impl Delegation {
+    /// Delegate `scopes` on `device_id` to `delegate_public_key` for `lifetime`
+    ///
+    /// The issuer is the device itself for the first link of a chain. The
+    /// link is signed with the issuer key's own algorithm.
+    pub fn issue(device_id: &str, scopes: &[&str], lifetime: Duration, delegate_public_key: &[u8], issuer_secret_key: &[u8], issuer_public_key: &[u8]) -> Result<Self> {
+        validate_device_id(device_id)?;
+        if scopes.is_empty() || lifetime.is_zero() || lifetime > MAX_LIFETIME {
+            return Err(PqcError::InvalidInput(format!(
+                "Delegations need at least one scope and a lifetime of at most {:?}", MAX_LIFETIME
+            )));
+        }
+        let algorithm = SigAlgorithm::for_secret_key(issuer_secret_key)?;
+        fips::check_sig(algorithm)?;
+        let now = unix_now();
+
+        let mut delegation = Self {
+            device_id: device_id.to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            not_before: now,
+            not_after: now + lifetime.as_secs(),
+            issuer_fingerprint: key_fingerprint(issuer_public_key),
+            delegate_public_key: delegate_public_key.to_vec(),
+            algorithm,
+            signature: Vec::new(),
+        };
+        delegation.signature = sign::sign_with(algorithm, &delegation.signing_bytes()?, issuer_secret_key)?;
+        Ok(delegation)
+    }
+
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode delegation: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "delegation")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(DELEGATION_DOMAIN, &DelegationFields {
+            device_id: &self.device_id,
+            scopes: &self.scopes,
+            not_before: self.not_before,
+            not_after: self.not_after,
+            issuer_fingerprint: &self.issuer_fingerprint,
+            delegate_public_key: hex::encode(&self.delegate_public_key),
+            algorithm: self.algorithm.name(),
+        })
+    }
+}
+
+impl Validate for Delegation {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_max_len("Delegation scopes", self.scopes.len(), MAX_SCOPES)?;
+        for scope in &self.scopes {
+            validation::check_max_len("Delegation scope", scope.len(), validation::MAX_LABEL_LEN)?;
+        }
+        validation::check_fingerprint("Delegation issuer fingerprint", &self.issuer_fingerprint)?;
+        validation::check_any_public_key_len("Delegate public key", self.delegate_public_key.len())?;
+        validation::check_signature_len("Delegation signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// Check a chain rooted at the device key and return the final delegate's key
+///
+/// Every link must be signed by the key the previous link delegated to,
+/// name the same device, be valid at `now` under `validity`, and grant
+/// `scope` without widening its parent's scopes or outliving it.
+pub fn verify_chain<'a>(
+    chain: &'a [Delegation],
+    device_public_key: &'a [u8],
+    scope: &str,
+    now: u64,
+    validity: &ValidityOptions,
+) -> Result<&'a [u8]> {
+    let (first, _) = chain.split_first()
+        .ok_or_else(|| PqcError::Verification("Empty delegation chain".to_string()))?;
+    if chain.len() > MAX_CHAIN_LEN {
+        return Err(PqcError::Verification(format!("Delegation chain is longer than {}", MAX_CHAIN_LEN)));
+    }
+
+    let mut issuer_key = device_public_key;
+    let mut parent: Option<&Delegation> = None;
+    for link in chain {
+        fips::check_sig(link.algorithm)?;
+        if link.device_id != first.device_id {
+            return Err(PqcError::Verification("Delegation chain mixes devices".to_string()));
+        }
+        if !ct::ct_eq_hex(&link.issuer_fingerprint, &key_fingerprint(issuer_key))
+            || !sign::verify_with(link.algorithm, &link.signing_bytes()?, &link.signature, issuer_key)?
+        {
+            return Err(PqcError::Verification("Delegation was not signed by its issuer".to_string()));
+        }
+        if !validity.contains(link.not_before, link.not_after, now) || link.not_after - link.not_before > MAX_LIFETIME.as_secs() {
+            return Err(PqcError::Verification("Delegation is expired or not yet valid".to_string()));
+        }
+        if !link.allows(scope) {
+            return Err(PqcError::Verification(format!("Delegation does not grant {:?}", scope)));
+        }
+        if let Some(parent) = parent {
+            if link.not_after > parent.not_after || !link.scopes.iter().all(|s| parent.allows(s)) {
+                return Err(PqcError::Verification("Delegation grants more than its issuer holds".to_string()));
+            }
+        }
+        issuer_key = &link.delegate_public_key;
+        parent = Some(link);
+    }
+    Ok(issuer_key)
+}
+
+/// A temporary key together with the chain that authorizes it
+pub struct DelegatedCredential {
+    pub chain: Vec<Delegation>,
+    secret_key: SecretKey,
+}
+
+impl DelegatedCredential {
+    /// `secret_key` must belong to the last link's delegate key
+    pub fn new(chain: Vec<Delegation>, secret_key: SecretKey) -> Result<Self> {
+        if chain.is_empty() {
+            return Err(PqcError::InvalidInput("Empty delegation chain".to_string()));
+        }
+        Ok(Self { chain, secret_key })
+    }
+
+    fn last(&self) -> &Delegation {
+        self.chain.last().expect("chain is never empty")
+    }
+
+    /// Pass a narrower delegation on to `delegate_public_key`
+    pub fn delegate(&self, scopes: &[&str], lifetime: Duration, delegate_public_key: &[u8]) -> Result<Vec<Delegation>> {
+        let last = self.last();
+        if let Some(scope) = scopes.iter().find(|scope| !last.allows(scope)) {
+            return Err(PqcError::InvalidInput(format!("Cannot delegate {:?}, which this credential lacks", scope)));
+        }
+        let remaining = last.not_after.saturating_sub(unix_now());
+        let lifetime = lifetime.min(Duration::from_secs(remaining));
+        let link = Delegation::issue(&last.device_id, scopes, lifetime, delegate_public_key, &self.secret_key, &last.delegate_public_key)?;
+
+        let mut chain = self.chain.clone();
+        chain.push(link);
+        Ok(chain)
+    }
+
+    /// Sign `payload` as an action under `scope`
+    pub fn sign(&self, scope: &str, payload: &[u8]) -> Result<DelegatedSignature> {
+        if !self.last().allows(scope) {
+            return Err(PqcError::InvalidInput(format!("Credential does not grant {:?}", scope)));
+        }
+        let algorithm = SigAlgorithm::for_secret_key(&self.secret_key)?;
+        fips::check_sig(algorithm)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let mut action = DelegatedSignature {
+            scope: scope.to_string(),
+            payload: payload.to_vec(),
+            timestamp: unix_now(),
+            nonce: hex::encode(nonce),
+            chain: self.chain.clone(),
+            algorithm,
+            signature: Vec::new(),
+        };
+        action.signature = sign::sign_with(algorithm, &action.signing_bytes()?, &self.secret_key)?;
+        Ok(action)
+    }
+}
+
+/// An action signed with a delegated key, carrying its authorization chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegatedSignature {
+    pub scope: String,
+    #[serde(with = "crate::util::bytes")]
+    pub payload: Vec<u8>,
+    pub timestamp: u64,
+    /// Random hex value identifying this action for replay checks
+    pub nonce: String,
+    pub chain: Vec<Delegation>,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`DelegatedSignature`] covered by its signature
+#[derive(Serialize)]
+struct DelegatedActionFields<'a> {
+    device_id: &'a str,
+    scope: &'a str,
+    payload: String,
+    timestamp: u64,
+    nonce: &'a str,
+    algorithm: &'a str,
+}
+
+impl DelegatedSignature {
+    /// Check the chain back to `device_public_key`, the action's signature and its age
+    ///
+    /// This alone does not stop the same action being presented twice
+    /// within [`MAX_ACTION_AGE`]; verify through an [`ActionReplayGuard`]
+    /// for that.
+    pub fn verify(&self, device_public_key: &[u8]) -> Result<()> {
+        self.verify_at(device_public_key, unix_now(), &clock::validity())
+    }
+
+    /// [`DelegatedSignature::verify`] at an explicit time and tolerance
+    ///
+    /// The action must have been signed while the last delegation was valid
+    /// and no more than [`MAX_ACTION_AGE`] before `now`, within the clock skew.
+    pub fn verify_at(&self, device_public_key: &[u8], now: u64, validity: &ValidityOptions) -> Result<()> {
+        fips::check_sig(self.algorithm)?;
+        self.validate()?;
+        let delegate_key = verify_chain(&self.chain, device_public_key, &self.scope, now, validity)?;
+        if !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, delegate_key)? {
+            return Err(PqcError::Verification("Action was not signed by the delegated key".to_string()));
+        }
+
+        let last = self.chain.last().expect("verify_chain rejects empty chains");
+        let max_age = MAX_ACTION_AGE.saturating_add(validity.max_skew).as_secs();
+        if !validity.contains(last.not_before, last.not_after, self.timestamp)
+            || validity.is_premature(self.timestamp, now)
+            || now.saturating_sub(self.timestamp) > max_age
+        {
+            return Err(PqcError::Verification(format!("Action signed at {} is stale or outside its delegation", self.timestamp)));
+        }
+        Ok(())
+    }
+
+    pub fn device_id(&self) -> &str {
+        self.chain.first().map_or("", |link| link.device_id.as_str())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode delegated action: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "delegated action")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(DELEGATED_ACTION_DOMAIN, &DelegatedActionFields {
+            device_id: self.device_id(),
+            scope: &self.scope,
+            payload: hex::encode(&self.payload),
+            timestamp: self.timestamp,
+            nonce: &self.nonce,
+            algorithm: self.algorithm.name(),
+        })
+    }
+}
+
+impl Validate for DelegatedSignature {
+    fn validate(&self) -> Result<()> {
+        validation::check_max_len("Action scope", self.scope.len(), validation::MAX_LABEL_LEN)?;
+        validation::check_exact_len("Action nonce", self.nonce.len(), 2 * NONCE_LEN)?;
+        validation::check_max_len("Delegation chain", self.chain.len(), MAX_CHAIN_LEN)?;
+        self.chain.iter().try_for_each(Delegation::validate)?;
+        validation::check_signature_len("Action signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// Remembers recently accepted actions so none is accepted twice
+///
+/// Nonces are kept for as long as their actions could still pass the age
+/// check, so memory grows only with the rate of genuine actions.
+#[derive(Debug, Default)]
+pub struct ActionReplayGuard {
+    /// Accepted nonces and the timestamps of their actions
+    seen: HashMap<String, u64>,
+}
+
+impl ActionReplayGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify `action` and record it; a second presentation is refused
+    pub fn verify(&mut self, action: &DelegatedSignature, device_public_key: &[u8]) -> Result<()> {
+        self.verify_at(action, device_public_key, unix_now(), &clock::validity())
+    }
+
+    /// [`ActionReplayGuard::verify`] at an explicit time and tolerance
+    pub fn verify_at(&mut self, action: &DelegatedSignature, device_public_key: &[u8], now: u64, validity: &ValidityOptions) -> Result<()> {
+        action.verify_at(device_public_key, now, validity)?;
+        let max_age = MAX_ACTION_AGE.saturating_add(validity.max_skew).as_secs();
+        self.seen.retain(|_, timestamp| now.saturating_sub(*timestamp) <= max_age);
+        if self.seen.contains_key(&action.nonce) {
+            return Err(PqcError::Verification(format!("Action {} was already used", action.nonce)));
+        }
+        self.seen.insert(action.nonce.clone(), action.timestamp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIFTEEN_MINUTES: Duration = Duration::from_secs(15 * 60);
+
+    #[test]
+    fn test_technician_access() {
+        let device = sign::generate_keypair().unwrap();
+        let technician = sign::generate_keypair().unwrap();
+        let delegation = Delegation::issue("lock-42", &["unlock", "read-logs"], FIFTEEN_MINUTES, &technician.public_key, &device.secret_key, &device.public_key).unwrap();
+
+        let credential = DelegatedCredential::new(vec![delegation], technician.secret_key.into()).unwrap();
+        let action = credential.sign("unlock", b"door 1").unwrap();
+        action.verify(&device.public_key).unwrap();
+        assert_eq!(action.device_id(), "lock-42");
+
+        assert!(credential.sign("factory-reset", b"").is_err());
+        let later = unix_now() + FIFTEEN_MINUTES.as_secs() + 1;
+        assert!(matches!(action.verify_at(&device.public_key, later, &ValidityOptions::STRICT), Err(PqcError::Verification(_))));
+
+        let other_device = sign::generate_keypair().unwrap();
+        assert!(action.verify(&other_device.public_key).is_err());
+
+        let mut widened = action.clone();
+        widened.scope = "factory-reset".to_string();
+        assert!(widened.verify(&device.public_key).is_err());
+    }
+
+    #[test]
+    fn test_actions_cannot_be_replayed() {
+        let device = sign::generate_keypair_with(SigAlgorithm::MlDsa87).unwrap();
+        let technician = sign::generate_keypair().unwrap();
+        let delegation = Delegation::issue("lock-42", &["unlock"], MAX_LIFETIME, &technician.public_key, &device.secret_key, &device.public_key).unwrap();
+        assert_eq!(delegation.algorithm, SigAlgorithm::MlDsa87);
+        let credential = DelegatedCredential::new(vec![delegation], technician.secret_key.into()).unwrap();
+        let action = credential.sign("unlock", b"door 1").unwrap();
+
+        let mut guard = ActionReplayGuard::new();
+        guard.verify(&action, &device.public_key).unwrap();
+        assert!(matches!(guard.verify(&action, &device.public_key), Err(PqcError::Verification(_))));
+        guard.verify(&credential.sign("unlock", b"door 1").unwrap(), &device.public_key).unwrap();
+
+        // Well inside the delegation, but too long after the action was signed
+        let stale = action.timestamp + MAX_ACTION_AGE.as_secs() + 1;
+        assert!(matches!(action.verify_at(&device.public_key, stale, &ValidityOptions::STRICT), Err(PqcError::Verification(_))));
+        let skew = ValidityOptions::TOLERANT.max_skew.as_secs();
+        action.verify_at(&device.public_key, stale + skew - 1, &ValidityOptions::TOLERANT).unwrap();
+
+        assert_eq!(DelegatedSignature::from_bytes(&action.to_bytes().unwrap()).unwrap(), action);
+        let mut padded = action.clone();
+        padded.signature.resize(padded.signature.len() + 1, 0);
+        assert!(matches!(DelegatedSignature::from_bytes(&padded.to_bytes().unwrap()), Err(PqcError::InvalidInput(_))));
+
+        let mut renonced = action.clone();
+        renonced.nonce = hex::encode([0u8; NONCE_LEN]);
+        assert!(renonced.verify(&device.public_key).is_err());
+    }
+
+    #[test]
+    fn test_sub_delegation_only_narrows() {
+        let device = sign::generate_keypair().unwrap();
+        let technician = sign::generate_keypair().unwrap();
+        let helper = sign::generate_keypair().unwrap();
+        let delegation = Delegation::issue("lock-42", &["unlock", "read-logs"], FIFTEEN_MINUTES, &technician.public_key, &device.secret_key, &device.public_key).unwrap();
+        let credential = DelegatedCredential::new(vec![delegation], technician.secret_key.clone().into()).unwrap();
+
+        let chain = credential.delegate(&["read-logs"], MAX_LIFETIME, &helper.public_key).unwrap();
+        assert!(chain[1].not_after <= chain[0].not_after);
+        let helper_credential = DelegatedCredential::new(chain.clone(), helper.secret_key.clone().into()).unwrap();
+        helper_credential.sign("read-logs", b"since boot").unwrap().verify(&device.public_key).unwrap();
+        assert!(helper_credential.sign("unlock", b"").is_err());
+        assert!(credential.delegate(&["factory-reset"], FIFTEEN_MINUTES, &helper.public_key).is_err());
+
+        // A hand-made link that widens the scope is caught by the verifier
+        let mut widened = chain;
+        widened[1] = Delegation::issue("lock-42", &["read-logs", "factory-reset"], FIFTEEN_MINUTES, &helper.public_key, &technician.secret_key, &technician.public_key).unwrap();
+        let forged = DelegatedCredential::new(widened, helper.secret_key.into()).unwrap();
+        assert!(forged.sign("factory-reset", b"").unwrap().verify(&device.public_key).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! Flows shown by the demo binaries, as reusable library functions
+//!
+//! `pqc-demo` and `qrng-demo` print the results of these; integration tests
+//! and other tools can call them directly instead of duplicating the steps.
+
+use crate::error::{PqcError, Result};
+use crate::qrng::QRNGEntropyService;
+use crate::roles::{Authenticator, EntropyConsumer, EntropyProducer, Verifier};
+use crate::util::ct;
+
+/// What happened to one payload encrypted to a device and decrypted again
+#[derive(Debug, Clone)]
+pub struct EntropyRoundtrip {
+    pub entropy: Vec<u8>,
+    pub decrypted: Vec<u8>,
+    /// Size of the KEM ciphertext
+    pub ciphertext_len: usize,
+    /// Size of the AEAD-sealed entropy
+    pub encrypted_len: usize,
+}
+
+impl EntropyRoundtrip {
+    /// True when the decrypted bytes match the original
+    pub fn intact(&self) -> bool {
+        ct::ct_eq(&self.entropy, &self.decrypted)
+    }
+}
+
+/// Encrypt `entropy` with the service's `producer` and decrypt it with the device's `consumer`
+pub fn run_entropy_roundtrip(
+    entropy: &[u8],
+    producer: &EntropyProducer,
+    consumer: &EntropyConsumer,
+) -> Result<EntropyRoundtrip> {
+    let encrypted = producer.encrypt(entropy)?;
+    let decrypted = consumer.decrypt(&encrypted)?;
+    Ok(EntropyRoundtrip {
+        entropy: entropy.to_vec(),
+        decrypted,
+        ciphertext_len: encrypted.ciphertext.len(),
+        encrypted_len: encrypted.encrypted_data.len(),
+    })
+}
+
+/// Sign an authentication request with the device's `authenticator` and check it with the service's `verifier`
+pub fn run_auth_roundtrip(
+    nonce: &[u8],
+    authenticator: &Authenticator,
+    verifier: &Verifier,
+) -> Result<bool> {
+    let request = authenticator.authenticate(nonce)?;
+    match verifier.verify(&request, nonce) {
+        Ok(()) => Ok(true),
+        Err(PqcError::Verification(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// The entropy-as-a-service flow: provision a device, then deliver `size`
+/// bytes of device-specific quantum entropy to it
+pub fn run_entropy_service(
+    service: &mut QRNGEntropyService,
+    device_id: &str,
+    size: usize,
+) -> Result<EntropyRoundtrip> {
+    let device = service.provision_device(device_id)?;
+    let entropy = service.generate_entropy_for_device(device_id, size)?;
+    let (consumer, _) = device.into_roles(device_id)?;
+    run_entropy_roundtrip(&entropy, &consumer.producer(), &consumer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceCredentials;
+
+    #[test]
+    fn test_roundtrips() {
+        let device = DeviceCredentials::generate().unwrap();
+        let (producer, verifier) = device.service_roles("sensor-7").unwrap();
+        let (consumer, authenticator) = device.into_roles("sensor-7").unwrap();
+        let roundtrip =
+            run_entropy_roundtrip(b"entropy for sensor-7", &producer, &consumer).unwrap();
+        assert!(roundtrip.intact());
+        assert_eq!(roundtrip.encrypted_len, 20 + 28);
+        assert!(run_auth_roundtrip(b"nonce", &authenticator, &verifier).unwrap());
+    }
+}
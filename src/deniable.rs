@@ -0,0 +1,217 @@
+//! Deniable MAC authentication for routine telemetry
+//!
+//! A device encapsulates to the service's Kyber key once and both sides
+//! derive the same MAC key from the shared secret and the device ID, so a
+//! key established by one device is useless under another ID. Telemetry is then
+//! authenticated with a 32-byte KMAC256 tag instead of a multi-kilobyte
+//! Dilithium signature. Either party could have computed any tag, so a tag
+//! convinces the service but proves nothing to anyone else. Keep signatures
+//! for operations that must be attributable later, such as authentication
+//! requests and firmware approvals.
+//!
+//! Each key remembers the highest counter it has verified and refuses
+//! anything at or below it. A service that restarts must persist
+//! [`MacKey::last_counter`] and hand it back with [`MacKey::with_last_counter`].
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::error::{PqcError, Result};
+use crate::hash::{self, HashAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::validate_device_id;
+use crate::{canonical, fips, kdf, kem};
+
+/// Domain label for telemetry MACs
+const TELEMETRY_MAC_DOMAIN: &str = "PQC-Algo/v1/telemetry-mac";
+
+/// Length of a telemetry tag in bytes
+pub const TAG_LEN: usize = 32;
+
+const MAC_KEY_LEN: usize = 32;
+
+/// Symmetric key shared by one device and the service
+#[derive(Clone)]
+pub struct MacKey {
+    id: String,
+    device_id: String,
+    key: Zeroizing<Vec<u8>>,
+    /// Highest counter accepted by [`MacKey::verify`]
+    last_counter: Option<u64>,
+}
+
+impl MacKey {
+    /// Device side: encapsulate to the service and derive the MAC key
+    ///
+    /// Send the returned KEM ciphertext to the service once, e.g. inside a
+    /// signed enrollment message.
+    pub fn establish(device_id: &str, service_kyber_public_key: &[u8]) -> Result<(Self, Vec<u8>)> {
+        Self::establish_with_suite(device_id, service_kyber_public_key, &CipherSuite::active_default())
+    }
+
+    pub fn establish_with_suite(device_id: &str, service_kyber_public_key: &[u8], suite: &CipherSuite) -> Result<(Self, Vec<u8>)> {
+        fips::check_suite(suite)?;
+        validate_device_id(device_id)?;
+        let (kem_ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, service_kyber_public_key)?;
+        let key = Self::derive(device_id, &Zeroizing::new(shared_secret), &kem_ciphertext)?;
+        Ok((key, kem_ciphertext))
+    }
+
+    /// Service side: recover the MAC key `device_id` established with its KEM ciphertext
+    pub fn accept(device_id: &str, service_kyber_secret_key: &[u8], kem_ciphertext: &[u8]) -> Result<Self> {
+        Self::accept_with_suite(device_id, service_kyber_secret_key, kem_ciphertext, &CipherSuite::active_default())
+    }
+
+    pub fn accept_with_suite(device_id: &str, service_kyber_secret_key: &[u8], kem_ciphertext: &[u8], suite: &CipherSuite) -> Result<Self> {
+        fips::check_suite(suite)?;
+        validate_device_id(device_id)?;
+        let shared_secret = Zeroizing::new(kem::decapsulate_with(suite.kem, service_kyber_secret_key, kem_ciphertext)?);
+        Self::derive(device_id, &shared_secret, kem_ciphertext)
+    }
+
+    /// Refuse counters up to and including `counter`, e.g. the value persisted before a restart
+    pub fn with_last_counter(mut self, counter: u64) -> Self {
+        self.last_counter = Some(counter);
+        self
+    }
+
+    /// Highest counter verified so far
+    pub fn last_counter(&self) -> Option<u64> {
+        self.last_counter
+    }
+
+    /// Public identifier of this key: hex SHA-256 of the KEM ciphertext, truncated
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Authenticate one telemetry message
+    ///
+    /// `counter` must increase with every message; the service rejects
+    /// repeats and older values as replays.
+    pub fn authenticate(&self, counter: u64, payload: &[u8]) -> Result<MacAuthenticated> {
+        let mut message = MacAuthenticated {
+            key_id: self.id.clone(),
+            device_id: self.device_id.clone(),
+            counter,
+            payload: payload.to_vec(),
+            tag: Vec::new(),
+        };
+        message.tag = self.tag(&message)?;
+        Ok(message)
+    }
+
+    /// Check a message's tag under this key and that its counter is new
+    ///
+    /// A message that passes raises [`MacKey::last_counter`] to its counter.
+    pub fn verify(&mut self, message: &MacAuthenticated) -> Result<()> {
+        if message.key_id != self.id {
+            return Err(PqcError::Verification(format!("Message is for key {}, not {}", message.key_id, self.id)));
+        }
+        if message.device_id != self.device_id {
+            return Err(PqcError::Verification(format!("Message is from {}, key belongs to {}", message.device_id, self.device_id)));
+        }
+        ct::verify_tag(&self.tag(message)?, &message.tag)?;
+        if let Some(last) = self.last_counter.filter(|&last| message.counter <= last) {
+            return Err(PqcError::Verification(format!("Counter {} replays or precedes {}", message.counter, last)));
+        }
+        self.last_counter = Some(message.counter);
+        Ok(())
+    }
+
+    fn derive(device_id: &str, shared_secret: &[u8], kem_ciphertext: &[u8]) -> Result<Self> {
+        let mut info = kdf::label("telemetry-mac-key");
+        info.push(0);
+        info.extend_from_slice(device_id.as_bytes());
+        let key = Zeroizing::new(kdf::hkdf_sha256(shared_secret, &[], &info, MAC_KEY_LEN)?);
+        let id = hex::encode(&hash::digest(HashAlgorithm::Sha256, kem_ciphertext)[..16]);
+        Ok(Self { id, device_id: device_id.to_string(), key, last_counter: None })
+    }
+
+    fn tag(&self, message: &MacAuthenticated) -> Result<Vec<u8>> {
+        let bytes = canonical::signing_bytes(TELEMETRY_MAC_DOMAIN, &MacFields {
+            key_id: &message.key_id,
+            device_id: &message.device_id,
+            counter: message.counter,
+            payload: hex::encode(&message.payload),
+        })?;
+        Ok(kdf::kmac256(&self.key, &bytes, b"telemetry", TAG_LEN))
+    }
+}
+
+impl fmt::Debug for MacKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MacKey").field("id", &self.id).field("device_id", &self.device_id).finish_non_exhaustive()
+    }
+}
+
+/// A telemetry message authenticated with a [`MacKey`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacAuthenticated {
+    pub key_id: String,
+    pub device_id: String,
+    pub counter: u64,
+    #[serde(with = "crate::util::bytes")]
+    pub payload: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub tag: Vec<u8>,
+}
+
+/// Fields of a [`MacAuthenticated`] covered by its tag
+#[derive(Serialize)]
+struct MacFields<'a> {
+    key_id: &'a str,
+    device_id: &'a str,
+    counter: u64,
+    payload: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telemetry_mac() {
+        let service = kem::generate_keypair().unwrap();
+        let (device_key, kem_ciphertext) = MacKey::establish("sensor-7", &service.public_key).unwrap();
+        let mut service_key = MacKey::accept("sensor-7", &service.secret_key, &kem_ciphertext).unwrap();
+        assert_eq!(device_key.id(), service_key.id());
+
+        let message = device_key.authenticate(1, b"temp=21.5").unwrap();
+        assert_eq!(message.tag.len(), TAG_LEN);
+        service_key.verify(&message).unwrap();
+
+        let mut altered = message.clone();
+        altered.counter = 2;
+        assert!(matches!(service_key.verify(&altered), Err(PqcError::Verification(_))));
+
+        // Deniability: the service can produce tags the device's key accepts
+        device_key.clone().verify(&service_key.authenticate(3, b"forged").unwrap()).unwrap();
+
+        let (mut other_key, _) = MacKey::establish("sensor-7", &service.public_key).unwrap();
+        assert!(other_key.verify(&message).is_err());
+    }
+
+    #[test]
+    fn test_key_is_bound_to_device_and_counters_only_increase() {
+        let service = kem::generate_keypair().unwrap();
+        let (device_key, kem_ciphertext) = MacKey::establish("sensor-7", &service.public_key).unwrap();
+        let mut service_key = MacKey::accept("sensor-7", &service.secret_key, &kem_ciphertext).unwrap();
+
+        service_key.verify(&device_key.authenticate(5, b"a").unwrap()).unwrap();
+        assert!(service_key.verify(&device_key.authenticate(5, b"a").unwrap()).is_err());
+        assert!(service_key.verify(&device_key.authenticate(4, b"b").unwrap()).is_err());
+        service_key.verify(&device_key.authenticate(6, b"c").unwrap()).unwrap();
+        assert_eq!(service_key.last_counter(), Some(6));
+
+        // The same ciphertext presented under another ID derives an unrelated key
+        let mut impostor = MacKey::accept("sensor-8", &service.secret_key, &kem_ciphertext).unwrap();
+        let mut relabelled = device_key.authenticate(7, b"d").unwrap();
+        relabelled.device_id = "sensor-8".to_string();
+        assert!(impostor.verify(&relabelled).is_err());
+
+        let mut restarted = MacKey::accept("sensor-7", &service.secret_key, &kem_ciphertext).unwrap().with_last_counter(6);
+        assert!(restarted.verify(&device_key.authenticate(6, b"c").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,750 @@
+//! Certificate enrollment over a [`Transport`], in the spirit of EST (RFC 7030)
+//!
+//! Three operations, each one request frame and one response frame of JSON:
+//!
+//! - **CA certificates**: the authority's chain, which the client checks
+//!   against trust anchors it was given out of band.
+//! - **Enroll**: the client fetches a one-time challenge, answers it with a
+//!   [`CertificateRequest`] signed by its new key, and gets a certificate.
+//!   The request must also carry a MAC under the enrollment token the server
+//!   issued for that device ID, delivered to the device out of band.
+//! - **Renew**: as enroll, plus a signature by the key of the client's current,
+//!   still-valid certificate vouching for the new request.
+//!
+//! Either request may carry a [`KeyAttestation`] for the device's Kyber key.
+//! A server set up with [`EnrollmentServer::with_attestation`] checks it and
+//! records it in the issued certificate, and can refuse keys without one.
+//!
+//! Challenges expire and a pending one is never replaced, so a third party
+//! asking for challenges cannot lock a device out.
+//!
+//! No secret ever crosses the link and every request is bound to a fresh
+//! challenge, so the protocol does not rely on the transport for integrity.
+//! Run it over an encrypted link anyway if subject names are sensitive.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use crate::api::DeviceCredentials;
+use crate::attestation::KeyAttestation;
+use crate::budget::{MemoryBudget, Reservation};
+use crate::cert::{self, Certificate, CertificateAuthority, TrustAnchors};
+use crate::csr::CertificateRequest;
+use crate::envelope::unix_now;
+use crate::error::{PqcError, Result};
+use crate::extensions::Extensions;
+use crate::lifecycle::{self, LifecycleEvent, LifecycleEventKind};
+use crate::roles::Authenticator;
+use crate::sign::{self, SigAlgorithm};
+use crate::transport::Transport;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::{canonical, clock, kdf};
+use zeroize::Zeroizing;
+
+/// Domain label for the current key's endorsement of a renewal request
+const RENEW_DOMAIN: &str = "PQC-Algo/v1/enrollment-renew";
+
+/// Domain label for the enrollment token's MAC over a first enrollment
+const ENROLL_DOMAIN: &str = "PQC-Algo/v1/enrollment-enroll";
+
+const CHALLENGE_LEN: usize = 32;
+const TOKEN_LEN: usize = 32;
+const TOKEN_MAC_LEN: usize = 32;
+
+/// How long an unanswered challenge stays valid
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(300);
+
+/// Outstanding challenges a server holds at most
+pub const MAX_PENDING_CHALLENGES: usize = 4096;
+
+/// Client → server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnrollmentRequest {
+    GetCaCerts,
+    GetChallenge { device_id: String },
+    Enroll {
+        request: CertificateRequest,
+        /// Secure element's statement about `request.kyber_public_key`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attestation: Option<Box<KeyAttestation>>,
+        /// MAC over the request under the device's enrollment token
+        #[serde(with = "crate::util::bytes")]
+        token_mac: Vec<u8>,
+    },
+    Renew {
+        request: CertificateRequest,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attestation: Option<Box<KeyAttestation>>,
+        current: Box<Certificate>,
+        /// Algorithm of the current certificate's key
+        algorithm: SigAlgorithm,
+        /// Signature by the current key over the new request
+        #[serde(with = "crate::util::bytes")]
+        endorsement: Vec<u8>,
+    },
+}
+
+/// Server → client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnrollmentResponse {
+    CaCerts { certificates: Vec<Certificate> },
+    Challenge {
+        #[serde(with = "crate::util::bytes")]
+        challenge: Vec<u8>,
+    },
+    Issued { certificate: Certificate },
+    Error { message: String },
+}
+
+impl Validate for EnrollmentRequest {
+    fn validate(&self) -> Result<()> {
+        match self {
+            EnrollmentRequest::GetCaCerts => Ok(()),
+            EnrollmentRequest::GetChallenge { device_id } => validate_device_id(device_id),
+            EnrollmentRequest::Enroll { request, attestation, token_mac } => {
+                request.validate()?;
+                attestation.as_ref().map_or(Ok(()), |attestation| attestation.validate())?;
+                validation::check_exact_len("Enrollment token MAC", token_mac.len(), TOKEN_MAC_LEN)
+            }
+            EnrollmentRequest::Renew { request, attestation, current, algorithm, endorsement } => {
+                request.validate()?;
+                attestation.as_ref().map_or(Ok(()), |attestation| attestation.validate())?;
+                current.validate()?;
+                validation::check_signature_len("Renewal endorsement", *algorithm, endorsement.len())
+            }
+        }
+    }
+}
+
+impl Validate for EnrollmentResponse {
+    fn validate(&self) -> Result<()> {
+        match self {
+            EnrollmentResponse::CaCerts { certificates } => certificates.iter().try_for_each(Certificate::validate),
+            EnrollmentResponse::Challenge { challenge } => {
+                validation::check_max_len("Enrollment challenge", challenge.len(), validation::MAX_NONCE_LEN)
+            }
+            EnrollmentResponse::Issued { certificate } => certificate.validate(),
+            EnrollmentResponse::Error { .. } => Ok(()),
+        }
+    }
+}
+
+/// Fields of a renewal covered by the endorsement
+#[derive(Serialize)]
+struct RenewFields<'a> {
+    current_serial: &'a str,
+    device_id: &'a str,
+    new_fingerprint: String,
+    challenge: String,
+}
+
+fn renew_signing_bytes(current: &Certificate, request: &CertificateRequest) -> Result<Vec<u8>> {
+    canonical::signing_bytes(RENEW_DOMAIN, &RenewFields {
+        current_serial: &current.serial,
+        device_id: &request.device_id,
+        new_fingerprint: request.fingerprint(),
+        challenge: hex::encode(&request.challenge),
+    })
+}
+
+/// Fields of a first enrollment covered by the token MAC
+#[derive(Serialize)]
+struct EnrollFields<'a> {
+    device_id: &'a str,
+    fingerprint: String,
+    challenge: String,
+}
+
+fn token_mac(token: &[u8], request: &CertificateRequest) -> Result<Vec<u8>> {
+    let bytes = canonical::signing_bytes(ENROLL_DOMAIN, &EnrollFields {
+        device_id: &request.device_id,
+        fingerprint: request.fingerprint(),
+        challenge: hex::encode(&request.challenge),
+    })?;
+    Ok(kdf::kmac256(token, &bytes, b"enrollment-token", TOKEN_MAC_LEN))
+}
+
+struct PendingChallenge {
+    challenge: Vec<u8>,
+    expires_at: u64,
+}
+
+/// Server half: issues certificates from one authority
+pub struct EnrollmentServer {
+    authority: CertificateAuthority,
+    ca_certificates: Vec<Certificate>,
+    lifetime: Duration,
+    /// Outstanding challenges by device ID; each is used at most once
+    challenges: HashMap<String, PendingChallenge>,
+    challenge_ttl: Duration,
+    /// Unused enrollment tokens by device ID
+    tokens: HashMap<String, Zeroizing<Vec<u8>>>,
+    budget: Option<MemoryBudget>,
+    /// Roots of the secure element vendors whose attestations are accepted
+    attestation_vendors: Option<TrustAnchors>,
+    attestation_required: bool,
+}
+
+impl EnrollmentServer {
+    /// `ca_certificates` is the chain clients need to verify what `authority` issues
+    pub fn new(authority: CertificateAuthority, ca_certificates: Vec<Certificate>, lifetime: Duration) -> Self {
+        Self {
+            authority,
+            ca_certificates,
+            lifetime,
+            challenges: HashMap::new(),
+            challenge_ttl: DEFAULT_CHALLENGE_TTL,
+            tokens: HashMap::new(),
+            budget: None,
+            attestation_vendors: None,
+            attestation_required: false,
+        }
+    }
+
+    /// Accept key attestations from secure elements certified by `vendors`
+    ///
+    /// A valid attestation is recorded in the issued certificate. With
+    /// `required`, requests without one, or whose key is exportable, are
+    /// refused.
+    pub fn with_attestation(mut self, vendors: TrustAnchors, required: bool) -> Self {
+        self.attestation_vendors = Some(vendors);
+        self.attestation_required = required;
+        self
+    }
+
+    /// Let challenges stay valid for `ttl` instead of [`DEFAULT_CHALLENGE_TTL`]
+    pub fn with_challenge_ttl(mut self, ttl: Duration) -> Self {
+        self.challenge_ttl = ttl;
+        self
+    }
+
+    /// Create the one-time token `device_id` needs for its first enrollment
+    ///
+    /// The hex token must reach the device out of band, e.g. at the factory.
+    /// Issuing again replaces an unused token. Renewals need no token.
+    pub fn issue_enrollment_token(&mut self, device_id: &str) -> Result<String> {
+        validate_device_id(device_id)?;
+        let mut token = Zeroizing::new(vec![0u8; TOKEN_LEN]);
+        rand::thread_rng().fill_bytes(&mut token);
+        let encoded = hex::encode(&*token);
+        self.tokens.insert(device_id.to_string(), token);
+        Ok(encoded)
+    }
+
+    /// Charge each served request against `budget`
+    ///
+    /// Requests arriving while it is spent get an [`EnrollmentResponse::Error`]
+    /// saying the service is overloaded, without being parsed.
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Answer one request; failures become [`EnrollmentResponse::Error`]
+    pub fn handle(&mut self, request: EnrollmentRequest) -> EnrollmentResponse {
+        self.try_handle(request).unwrap_or_else(|e| EnrollmentResponse::Error { message: e.to_string() })
+    }
+
+    /// Read one request from `transport` and send the response
+    ///
+    /// Frames the budget can't hold and frames that don't parse are answered
+    /// with [`EnrollmentResponse::Error`].
+    pub fn serve_one<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        // Reserve for the frame and the request parsed from it before the body is read
+        let response = match transport.receive_admitted(|len| self.reserve(2 * len))? {
+            Ok((frame, _reservation)) => match validation::from_json(&frame, "frame") {
+                Ok(request) => self.handle(request),
+                Err(e) => EnrollmentResponse::Error { message: e.to_string() },
+            },
+            Err(e) => EnrollmentResponse::Error { message: e.to_string() },
+        };
+        transport.send_json(&response)
+    }
+
+    fn reserve(&self, bytes: usize) -> Result<Option<Reservation>> {
+        self.budget.as_ref().map(|budget| budget.try_reserve(bytes)).transpose()
+    }
+
+    fn try_handle(&mut self, request: EnrollmentRequest) -> Result<EnrollmentResponse> {
+        match request {
+            EnrollmentRequest::GetCaCerts => Ok(EnrollmentResponse::CaCerts { certificates: self.ca_certificates.clone() }),
+            EnrollmentRequest::GetChallenge { device_id } => {
+                validate_device_id(&device_id)?;
+                Ok(EnrollmentResponse::Challenge { challenge: self.challenge_for(device_id)? })
+            }
+            EnrollmentRequest::Enroll { request, attestation, token_mac: mac } => {
+                let token = self.tokens.get(&request.device_id)
+                    .ok_or_else(|| PqcError::Verification(format!("{} has no enrollment token", request.device_id)))?;
+                if !ct::ct_eq(&token_mac(token, &request)?, &mac) {
+                    return Err(PqcError::Verification(format!("Enrollment token for {} does not match", request.device_id)));
+                }
+                self.check_request(&request)?;
+                let response = self.issue(&request, attestation.as_deref())?;
+                self.tokens.remove(&request.device_id);
+                Ok(response)
+            }
+            EnrollmentRequest::Renew { request, attestation, current, algorithm, endorsement } => {
+                self.check_request(&request)?;
+                if current.subject != request.device_id || current.is_authority {
+                    return Err(PqcError::Verification("Current certificate is not this device's".to_string()));
+                }
+                if !current.is_valid_at(unix_now(), &clock::validity()) || !current.verify_signature(self.authority.public_key())? {
+                    return Err(PqcError::Verification("Current certificate is expired or was not issued here".to_string()));
+                }
+                if !sign::verify_with(algorithm, &renew_signing_bytes(&current, &request)?, &endorsement, &current.public_key)? {
+                    return Err(PqcError::Verification("Renewal is not endorsed by the current key".to_string()));
+                }
+                let response = self.issue(&request, attestation.as_deref())?;
+                lifecycle::emit(LifecycleEvent::new(LifecycleEventKind::Rotated, &request.fingerprint())
+                    .with_algorithm(request.suite.sig.name())
+                    .with_subject(&request.device_id)
+                    .with_detail(&format!("replaces {} (certificate {})", current.fingerprint(), current.serial)));
+                Ok(response)
+            }
+        }
+    }
+
+    /// The device's pending challenge, or a new one if it has none
+    ///
+    /// A pending challenge is handed out again rather than replaced, so asking
+    /// for one on another device's behalf cannot void the one it holds.
+    fn challenge_for(&mut self, device_id: String) -> Result<Vec<u8>> {
+        let now = unix_now();
+        self.challenges.retain(|_, pending| pending.expires_at > now);
+        if let Some(pending) = self.challenges.get(&device_id) {
+            return Ok(pending.challenge.clone());
+        }
+        if self.challenges.len() >= MAX_PENDING_CHALLENGES {
+            return Err(PqcError::Protocol("Too many outstanding challenges; try again later".to_string()));
+        }
+        let mut challenge = vec![0u8; CHALLENGE_LEN];
+        rand::thread_rng().fill_bytes(&mut challenge);
+        let expires_at = now.saturating_add(self.challenge_ttl.as_secs());
+        self.challenges.insert(device_id, PendingChallenge { challenge: challenge.clone(), expires_at });
+        Ok(challenge)
+    }
+
+    /// Consume the device's challenge and check the request answers it
+    fn check_request(&mut self, request: &CertificateRequest) -> Result<()> {
+        let pending = self.challenges.remove(&request.device_id)
+            .filter(|pending| pending.expires_at > unix_now())
+            .ok_or_else(|| PqcError::Protocol(format!("No outstanding challenge for {}", request.device_id)))?;
+        request.verify(&pending.challenge)
+    }
+
+    fn issue(&self, request: &CertificateRequest, attestation: Option<&KeyAttestation>) -> Result<EnrollmentResponse> {
+        let extensions = self.attestation_extensions(request, attestation)?;
+        let certificate = self.authority.issue_with_extensions(&request.device_id, &request.dilithium_public_key, self.lifetime, false, extensions)?;
+        Ok(EnrollmentResponse::Issued { certificate })
+    }
+
+    /// Check `attestation` covers the request's Kyber key and record it
+    fn attestation_extensions(&self, request: &CertificateRequest, attestation: Option<&KeyAttestation>) -> Result<Extensions> {
+        let mut extensions = Extensions::new();
+        match (attestation, &self.attestation_vendors) {
+            (Some(attestation), Some(vendors)) => {
+                attestation.verify(&request.kyber_public_key, vendors)?;
+                if attestation.kem_algorithm != request.suite.kem {
+                    return Err(PqcError::Verification(format!(
+                        "Attestation is for a {} key, request is for {}",
+                        attestation.kem_algorithm.name(),
+                        request.suite.kem.name()
+                    )));
+                }
+                if self.attestation_required && !attestation.non_exportable {
+                    return Err(PqcError::Verification(format!("{}'s Kyber key is exportable", request.device_id)));
+                }
+                attestation.record().insert_into(&mut extensions)?;
+            }
+            (Some(_), None) => return Err(PqcError::Protocol("This server does not accept key attestations".to_string())),
+            (None, _) if self.attestation_required => {
+                return Err(PqcError::Verification(format!("{} has no hardware key attestation", request.device_id)));
+            }
+            (None, _) => {}
+        }
+        Ok(extensions)
+    }
+}
+
+/// Where an [`EnrollmentClient`] is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnrollmentState {
+    /// The CA certificates have not been fetched and checked yet
+    Bootstrapping,
+    /// CA certificates are trusted; ready to enroll
+    Ready,
+    /// Holds a certificate; may renew
+    Enrolled,
+}
+
+/// Client half, run by the device
+pub struct EnrollmentClient<T: Transport> {
+    transport: T,
+    device_id: String,
+    anchors: TrustAnchors,
+    ca_certificates: Vec<Certificate>,
+    certificate: Option<Certificate>,
+    token: Option<Zeroizing<Vec<u8>>>,
+}
+
+impl<T: Transport> EnrollmentClient<T> {
+    /// `anchors` are the roots the device trusts to vouch for the enrollment server
+    pub fn new(transport: T, device_id: &str, anchors: TrustAnchors) -> Result<Self> {
+        validate_device_id(device_id)?;
+        Ok(Self { transport, device_id: device_id.to_string(), anchors, ca_certificates: Vec::new(), certificate: None, token: None })
+    }
+
+    /// Use the hex enrollment token the server issued for this device
+    pub fn with_enrollment_token(mut self, token: &str) -> Result<Self> {
+        let token = Zeroizing::new(hex::decode(token)
+            .map_err(|e| PqcError::InvalidInput(format!("Invalid enrollment token: {}", e)))?);
+        if token.len() != TOKEN_LEN {
+            return Err(PqcError::InvalidInput(format!("Enrollment token must be {} bytes, got {}", TOKEN_LEN, token.len())));
+        }
+        self.token = Some(token);
+        Ok(self)
+    }
+
+    pub fn state(&self) -> EnrollmentState {
+        match (&self.certificate, self.ca_certificates.is_empty()) {
+            (Some(_), _) => EnrollmentState::Enrolled,
+            (None, false) => EnrollmentState::Ready,
+            (None, true) => EnrollmentState::Bootstrapping,
+        }
+    }
+
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.certificate.as_ref()
+    }
+
+    /// Whether the certificate is within the process renewal margin of expiring
+    pub fn needs_renewal(&self) -> bool {
+        self.certificate.as_ref().is_some_and(|certificate| certificate.needs_renewal_at(unix_now(), &clock::validity()))
+    }
+
+    /// Fetch the CA certificates and keep them if each chains to our anchors
+    pub fn fetch_ca_certs(&mut self) -> Result<&[Certificate]> {
+        let EnrollmentResponse::CaCerts { certificates } = self.call(&EnrollmentRequest::GetCaCerts)? else {
+            return Err(PqcError::Protocol("Expected CA certificates".to_string()));
+        };
+        if certificates.is_empty() {
+            return Err(PqcError::Protocol("Server sent no CA certificates".to_string()));
+        }
+        for certificate in &certificates {
+            if !certificate.is_authority {
+                return Err(PqcError::Verification(format!("{} is not an authority certificate", certificate.subject)));
+            }
+            let self_signed = certificate.fingerprint() == certificate.issuer_fingerprint;
+            let anchored = self.anchors.anchors().iter().any(|a| a.public_key == certificate.public_key);
+            if !(self_signed && anchored) {
+                cert::verify_chain(certificate, &certificates, &self.anchors)?;
+            }
+        }
+        self.ca_certificates = certificates;
+        Ok(&self.ca_certificates)
+    }
+
+    /// Certify `credentials`, which the device generated itself
+    pub fn enroll(&mut self, credentials: &DeviceCredentials) -> Result<&Certificate> {
+        self.enroll_inner(credentials, None)
+    }
+
+    /// Certify `credentials` whose Kyber key a secure element generated and attested
+    pub fn enroll_with_attestation(&mut self, credentials: &DeviceCredentials, attestation: KeyAttestation) -> Result<&Certificate> {
+        self.enroll_inner(credentials, Some(Box::new(attestation)))
+    }
+
+    fn enroll_inner(&mut self, credentials: &DeviceCredentials, attestation: Option<Box<KeyAttestation>>) -> Result<&Certificate> {
+        self.require(EnrollmentState::Ready)?;
+        let token = self.token.clone()
+            .ok_or_else(|| PqcError::Protocol("Enrolling needs an enrollment token".to_string()))?;
+        let challenge = self.challenge()?;
+        let request = CertificateRequest::new(&self.device_id, credentials, &challenge)?;
+        let token_mac = token_mac(&token, &request)?;
+        let response = self.call(&EnrollmentRequest::Enroll { request, attestation, token_mac })?;
+        self.accept(response, credentials)
+    }
+
+    /// Certify `new_credentials`, endorsed with `algorithm` by `current`, the certified key's signing role
+    pub fn renew(&mut self, current: &Authenticator, algorithm: SigAlgorithm, new_credentials: &DeviceCredentials) -> Result<&Certificate> {
+        self.renew_inner(current, algorithm, new_credentials, None)
+    }
+
+    /// As [`renew`](Self::renew), with an attestation for the new Kyber key
+    pub fn renew_with_attestation(
+        &mut self,
+        current: &Authenticator,
+        algorithm: SigAlgorithm,
+        new_credentials: &DeviceCredentials,
+        attestation: KeyAttestation,
+    ) -> Result<&Certificate> {
+        self.renew_inner(current, algorithm, new_credentials, Some(Box::new(attestation)))
+    }
+
+    fn renew_inner(
+        &mut self,
+        endorser: &Authenticator,
+        algorithm: SigAlgorithm,
+        new_credentials: &DeviceCredentials,
+        attestation: Option<Box<KeyAttestation>>,
+    ) -> Result<&Certificate> {
+        self.require(EnrollmentState::Enrolled)?;
+        let current = self.certificate.clone().expect("enrolled client has a certificate");
+        let challenge = self.challenge()?;
+        let request = CertificateRequest::new(&self.device_id, new_credentials, &challenge)?;
+        let endorsement = sign::sign_with(algorithm, &renew_signing_bytes(&current, &request)?, endorser.secret_key())?;
+        let response = self.call(&EnrollmentRequest::Renew { request, attestation, current: Box::new(current), algorithm, endorsement })?;
+        self.accept(response, new_credentials)
+    }
+
+    fn challenge(&mut self) -> Result<Vec<u8>> {
+        match self.call(&EnrollmentRequest::GetChallenge { device_id: self.device_id.clone() })? {
+            EnrollmentResponse::Challenge { challenge } if !challenge.is_empty() => Ok(challenge),
+            _ => Err(PqcError::Protocol("Expected an enrollment challenge".to_string())),
+        }
+    }
+
+    /// Check an issued certificate covers our key and chains to our anchors
+    fn accept(&mut self, response: EnrollmentResponse, credentials: &DeviceCredentials) -> Result<&Certificate> {
+        let EnrollmentResponse::Issued { certificate } = response else {
+            return Err(PqcError::Protocol("Expected an issued certificate".to_string()));
+        };
+        if certificate.subject != self.device_id || certificate.public_key != credentials.dilithium_public_key || certificate.is_authority {
+            return Err(PqcError::Verification("Issued certificate does not match the request".to_string()));
+        }
+        cert::verify_chain(&certificate, &self.ca_certificates, &self.anchors)?;
+        Ok(self.certificate.insert(certificate))
+    }
+
+    fn require(&self, state: EnrollmentState) -> Result<()> {
+        if self.state() != state {
+            return Err(PqcError::Protocol(format!("Enrollment client is {:?}, needs {:?}", self.state(), state)));
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, request: &EnrollmentRequest) -> Result<EnrollmentResponse> {
+        self.transport.send_json(request)?;
+        match self.transport.receive_json()? {
+            EnrollmentResponse::Error { message } => Err(PqcError::Protocol(format!("Enrollment server refused: {}", message))),
+            response => Ok(response),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::CipherSuite;
+    use crate::transport::{MemoryTransport, StreamTransport};
+
+    const YEAR: Duration = Duration::from_secs(365 * 24 * 3600);
+
+    fn server() -> (EnrollmentServer, TrustAnchors) {
+        let root = CertificateAuthority::generate("Root").unwrap();
+        let root_cert = root.root_certificate(YEAR).unwrap();
+        let issuing = CertificateAuthority::generate("Enrollment CA").unwrap();
+        let issuing_cert = root.issue("Enrollment CA", issuing.public_key(), YEAR, true).unwrap();
+        let mut anchors = TrustAnchors::new();
+        anchors.add_certificate(&root_cert);
+        (EnrollmentServer::new(issuing, vec![issuing_cert, root_cert], YEAR), anchors)
+    }
+
+    #[test]
+    fn test_enroll_and_renew() {
+        let (mut server, anchors) = server();
+        let token = server.issue_enrollment_token("sensor-7").unwrap();
+        let (mut server_link, client_link) = MemoryTransport::pair();
+        let handle = std::thread::spawn(move || {
+            for _ in 0..5 {
+                server.serve_one(&mut server_link).unwrap();
+            }
+        });
+
+        let mut client = EnrollmentClient::new(client_link, "sensor-7", anchors).unwrap().with_enrollment_token(&token).unwrap();
+        let credentials = DeviceCredentials::generate().unwrap();
+        assert!(client.enroll(&credentials).is_err());
+        assert_eq!(client.state(), EnrollmentState::Bootstrapping);
+
+        client.fetch_ca_certs().unwrap();
+        let first = client.enroll(&credentials).unwrap().clone();
+        assert_eq!(client.state(), EnrollmentState::Enrolled);
+        assert!(!client.needs_renewal());
+        assert!(first.needs_renewal_at(first.not_after - 3600, &clock::ValidityOptions::TOLERANT));
+        assert!(!first.needs_renewal_at(first.not_after - 3600, &clock::ValidityOptions::STRICT));
+
+        let rotated = DeviceCredentials::generate().unwrap();
+        let algorithm = CipherSuite::active_default().sig;
+        let (_, current) = credentials.into_roles("sensor-7").unwrap();
+        let second = client.renew(&current, algorithm, &rotated).unwrap();
+        assert_eq!(second.public_key, rotated.dilithium_public_key);
+        assert_ne!(second.serial, first.serial);
+        handle.join().unwrap();
+    }
+
+    fn get_challenge(server: &mut EnrollmentServer, device_id: &str) -> Vec<u8> {
+        let EnrollmentResponse::Challenge { challenge } = server.handle(EnrollmentRequest::GetChallenge { device_id: device_id.to_string() }) else {
+            panic!("expected a challenge");
+        };
+        challenge
+    }
+
+    fn enroll_request(token: &str, request: CertificateRequest, attestation: Option<KeyAttestation>) -> EnrollmentRequest {
+        let token_mac = token_mac(&hex::decode(token).unwrap(), &request).unwrap();
+        EnrollmentRequest::Enroll { request, attestation: attestation.map(Box::new), token_mac }
+    }
+
+    #[test]
+    fn test_server_rejects_unendorsed_and_replayed_requests() {
+        let (mut server, _) = server();
+        let token = server.issue_enrollment_token("sensor-7").unwrap();
+        let device = DeviceCredentials::generate().unwrap();
+        let challenge = get_challenge(&mut server, "sensor-7");
+        let request = CertificateRequest::new("sensor-7", &device, &challenge).unwrap();
+        let EnrollmentResponse::Issued { certificate } = server.handle(enroll_request(&token, request.clone(), None)) else {
+            panic!("expected a certificate");
+        };
+        assert!(matches!(server.handle(enroll_request(&token, request, None)), EnrollmentResponse::Error { .. }));
+
+        // A renewal signed by some other key is refused
+        let attacker = DeviceCredentials::generate().unwrap();
+        let challenge = get_challenge(&mut server, "sensor-7");
+        let request = CertificateRequest::new("sensor-7", &attacker, &challenge).unwrap();
+        let algorithm = CipherSuite::active_default().sig;
+        let endorsement = sign::sign_with(algorithm, &renew_signing_bytes(&certificate, &request).unwrap(), &attacker.dilithium_secret_key).unwrap();
+        let renew = EnrollmentRequest::Renew { request, attestation: None, current: Box::new(certificate), algorithm, endorsement };
+        assert!(matches!(server.handle(renew), EnrollmentResponse::Error { .. }));
+    }
+
+    #[test]
+    fn test_enrollment_needs_token_and_keeps_pending_challenge() {
+        let ((fresh, _), (expiring, _)) = (server(), server());
+        let mut server = fresh.with_challenge_ttl(Duration::from_secs(60));
+        let token = server.issue_enrollment_token("sensor-7").unwrap();
+        let device = DeviceCredentials::generate().unwrap();
+
+        // Asking again, e.g. on the device's behalf, hands out the same challenge
+        let pending = get_challenge(&mut server, "sensor-7");
+        assert_eq!(get_challenge(&mut server, "sensor-7"), pending);
+
+        let request = CertificateRequest::new("sensor-7", &device, &pending).unwrap();
+        let wrong_token = hex::encode([7u8; TOKEN_LEN]);
+        assert!(matches!(server.handle(enroll_request(&wrong_token, request.clone(), None)),
+            EnrollmentResponse::Error { message } if message.contains("does not match")));
+        let unknown = CertificateRequest::new("sensor-8", &device, &get_challenge(&mut server, "sensor-8")).unwrap();
+        assert!(matches!(server.handle(enroll_request(&token, unknown, None)),
+            EnrollmentResponse::Error { message } if message.contains("no enrollment token")));
+        assert!(matches!(server.handle(enroll_request(&token, request, None)), EnrollmentResponse::Issued { .. }));
+
+        // Expired challenges are refused and dropped
+        let mut server = expiring.with_challenge_ttl(Duration::ZERO);
+        let token = server.issue_enrollment_token("sensor-7").unwrap();
+        let request = CertificateRequest::new("sensor-7", &device, &get_challenge(&mut server, "sensor-7")).unwrap();
+        assert!(matches!(server.handle(enroll_request(&token, request, None)),
+            EnrollmentResponse::Error { message } if message.contains("No outstanding challenge")));
+        assert!(server.challenges.is_empty());
+    }
+
+    #[test]
+    fn test_required_attestation_is_recorded() {
+        use crate::attestation::{AttestationRecord, SoftwareAttestationKey};
+
+        let vendor = CertificateAuthority::generate("SE Vendor Root").unwrap();
+        let element_keypair = sign::generate_keypair_with(vendor.algorithm).unwrap();
+        let element_cert = vendor.issue("SE-1000 #4711", &element_keypair.public_key, YEAR, false).unwrap();
+        let element = SoftwareAttestationKey::new(vec![element_cert], vendor.algorithm, element_keypair.secret_key.into()).unwrap();
+        let mut vendors = TrustAnchors::new();
+        vendors.add("SE Vendor Root", vendor.public_key());
+        let (server, _) = server();
+        let mut server = server.with_attestation(vendors, true);
+        let token = server.issue_enrollment_token("sensor-7").unwrap();
+
+        let device = DeviceCredentials::generate().unwrap();
+        let mut enroll = |attestation: Option<KeyAttestation>| {
+            let challenge = get_challenge(&mut server, "sensor-7");
+            let request = CertificateRequest::new("sensor-7", &device, &challenge).unwrap();
+            server.handle(enroll_request(&token, request, attestation))
+        };
+        assert!(matches!(enroll(None), EnrollmentResponse::Error { message } if message.contains("no hardware key attestation")));
+        let exportable = KeyAttestation::sign(&element, CipherSuite::active_default().kem, &device.kyber_public_key, "SE-1000", false).unwrap();
+        assert!(matches!(enroll(Some(exportable)), EnrollmentResponse::Error { .. }));
+
+        let attestation = KeyAttestation::sign(&element, CipherSuite::active_default().kem, &device.kyber_public_key, "SE-1000", true).unwrap();
+        let EnrollmentResponse::Issued { certificate } = enroll(Some(attestation.clone())) else {
+            panic!("expected a certificate");
+        };
+        assert_eq!(AttestationRecord::from_certificate(&certificate).unwrap(), Some(attestation.record()));
+        assert!(AttestationRecord::is_hardware_backed(&certificate, &device.kyber_public_key));
+    }
+
+    #[test]
+    fn test_overloaded_server_refuses_requests() {
+        let (server, anchors) = server();
+        let budget = MemoryBudget::new(64 * 1024);
+        let mut server = server.with_memory_budget(budget.clone());
+        let (mut server_link, client_link) = MemoryTransport::pair();
+        let mut client = EnrollmentClient::new(client_link, "sensor-7", anchors).unwrap();
+
+        let busy = budget.try_reserve(budget.limit()).unwrap();
+        client.transport.send_json(&EnrollmentRequest::GetCaCerts).unwrap();
+        server.serve_one(&mut server_link).unwrap();
+        let response: EnrollmentResponse = client.transport.receive_json().unwrap();
+        assert!(matches!(response, EnrollmentResponse::Error { message } if message.contains("overloaded")));
+
+        drop(busy);
+        let handle = std::thread::spawn(move || server.serve_one(&mut server_link).unwrap());
+        client.fetch_ca_certs().unwrap();
+        handle.join().unwrap();
+        assert_eq!(budget.rejected(), 1);
+    }
+
+    /// Reads from `input` and collects writes in `output`
+    struct Duplex {
+        input: std::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl std::io::Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl std::io::Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_oversized_and_malformed_frames_get_errors() {
+        let (server, _) = server();
+        let budget = MemoryBudget::new(64 * 1024);
+        let mut server = server.with_memory_budget(budget.clone());
+
+        // Refused from its length prefix alone, then a frame that is not a request
+        let mut input = Vec::new();
+        for frame in [vec![b' '; 40 * 1024], b"not json".to_vec()] {
+            input.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            input.extend_from_slice(&frame);
+        }
+        let mut link = StreamTransport::new(Duplex { input: std::io::Cursor::new(input), output: Vec::new() });
+        server.serve_one(&mut link).unwrap();
+        server.serve_one(&mut link).unwrap();
+        assert_eq!(budget.rejected(), 1);
+
+        let mut replies = StreamTransport::new(std::io::Cursor::new(link.into_inner().output));
+        let refused: EnrollmentResponse = replies.receive_json().unwrap();
+        assert!(matches!(refused, EnrollmentResponse::Error { message } if message.contains("memory budget")));
+        let malformed: EnrollmentResponse = replies.receive_json().unwrap();
+        assert!(matches!(malformed, EnrollmentResponse::Error { .. }));
+    }
+}
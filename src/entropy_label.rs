@@ -0,0 +1,228 @@
+//! Signed labels naming the entropy source behind an envelope
+//!
+//! An [`EntropyLabel`] records which source and pool generation produced
+//! the payload of an [`EncryptedEntropy`], and whether the service was in
+//! degraded mode at the time. The producing service signs it together with
+//! a digest of the envelope's ciphertexts, so a label cannot be moved to
+//! another envelope. Devices and auditors check it with the service's
+//! public key, without the device's KEM secret key.
+//!
+//! A gateway that re-encrypts an envelope to a device carries the label over
+//! and records the digest it was signed over in `forwarded_from`. Such labels
+//! fail [`EncryptedEntropy::verify_label`] and are checked with
+//! [`EncryptedEntropy::verify_forwarded_label`], which trusts the gateway to
+//! have forwarded the payload unchanged.
+
+use serde::{Deserialize, Serialize};
+use crate::api::EncryptedEntropy;
+use crate::canonical;
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::fips;
+use crate::hash;
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, Validate};
+
+/// Domain label for entropy label signatures
+const LABEL_DOMAIN: &str = "PQC-Algo/v1/entropy-label";
+
+/// Which pool produced an envelope's payload, signed by the producing service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntropyLabel {
+    /// Entropy source name, e.g. `local-pool` or `idq-quantis`
+    pub source: String,
+    /// Fingerprint of the seed material the pool was built from
+    pub seed_fingerprint: String,
+    /// Pool counter when the payload was drawn
+    pub pool_generation: u64,
+    /// Drawn from OS entropy while the quantum seeds were missing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+    pub labelled_at: u64,
+    pub signer_fingerprint: String,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+    /// Digest of the envelope the label was signed over, set by a forwarding
+    /// gateway; not covered by the signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<String>,
+}
+
+/// Fields of an `EntropyLabel` covered by its signature
+#[derive(Serialize)]
+struct LabelFields<'a> {
+    source: &'a str,
+    seed_fingerprint: &'a str,
+    pool_generation: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    degraded: bool,
+    labelled_at: u64,
+    signer_fingerprint: &'a str,
+    algorithm: &'a str,
+    payload_sha256: String,
+}
+
+impl EntropyLabel {
+    /// An unsigned label; [`EncryptedEntropy::sign_label`] fills in the rest
+    pub fn new(source: &str, seed_fingerprint: &str, pool_generation: u64) -> Self {
+        Self {
+            source: source.to_string(),
+            seed_fingerprint: seed_fingerprint.to_string(),
+            pool_generation,
+            degraded: false,
+            labelled_at: 0,
+            signer_fingerprint: String::new(),
+            algorithm: CipherSuite::active_default().sig,
+            signature: Vec::new(),
+            forwarded_from: None,
+        }
+    }
+
+    pub fn with_degraded(mut self, degraded: bool) -> Self {
+        self.degraded = degraded;
+        self
+    }
+
+    /// The label carried onto an envelope re-encrypted from `held`
+    pub(crate) fn forwarded(&self, held: &EncryptedEntropy) -> Self {
+        let forwarded_from = self.forwarded_from.clone().unwrap_or_else(|| payload_digest(held));
+        Self { forwarded_from: Some(forwarded_from), ..self.clone() }
+    }
+
+    fn signing_bytes(&self, payload_sha256: String) -> Result<Vec<u8>> {
+        canonical::signing_bytes(LABEL_DOMAIN, &LabelFields {
+            source: &self.source,
+            seed_fingerprint: &self.seed_fingerprint,
+            pool_generation: self.pool_generation,
+            degraded: self.degraded,
+            labelled_at: self.labelled_at,
+            signer_fingerprint: &self.signer_fingerprint,
+            algorithm: self.algorithm.name(),
+            payload_sha256,
+        })
+    }
+}
+
+impl Validate for EntropyLabel {
+    fn validate(&self) -> Result<()> {
+        validation::check_max_len("Entropy source", self.source.len(), validation::MAX_LABEL_LEN)?;
+        validation::check_fingerprint("Seed fingerprint", &self.seed_fingerprint)?;
+        validation::check_fingerprint("Signer fingerprint", &self.signer_fingerprint)?;
+        if let Some(forwarded_from) = &self.forwarded_from {
+            validation::check_fingerprint("Forwarded payload digest", forwarded_from)?;
+        }
+        validation::check_signature_len("Entropy label signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// Hex SHA-256 over both ciphertexts, length-prefixed
+fn payload_digest(envelope: &EncryptedEntropy) -> String {
+    let kem_len = (envelope.ciphertext.len() as u64).to_be_bytes();
+    hex::encode(hash::sha256(&[&kem_len, &envelope.ciphertext, &envelope.encrypted_data]))
+}
+
+impl EncryptedEntropy {
+    /// Sign `label` over this envelope and attach it, replacing any existing label
+    pub fn sign_label(&mut self, label: EntropyLabel, secret_key: &[u8], public_key: &[u8]) -> Result<()> {
+        self.sign_label_with(CipherSuite::active_default().sig, label, secret_key, public_key)
+    }
+
+    pub fn sign_label_with(&mut self, algorithm: SigAlgorithm, mut label: EntropyLabel, secret_key: &[u8], public_key: &[u8]) -> Result<()> {
+        fips::check_sig(algorithm)?;
+        label.labelled_at = unix_now();
+        label.signer_fingerprint = key_fingerprint(public_key);
+        label.algorithm = algorithm;
+        label.forwarded_from = None;
+        label.signature = sign::sign_with(algorithm, &label.signing_bytes(payload_digest(self))?, secret_key)?;
+        self.label = Some(label);
+        Ok(())
+    }
+
+    /// The label, if it is present and signed over this envelope by `trusted_public_key`
+    pub fn verify_label(&self, trusted_public_key: &[u8]) -> Result<&EntropyLabel> {
+        let label = self.present_label()?;
+        if label.forwarded_from.is_some() {
+            return Err(PqcError::Verification(format!(
+                "Entropy label from {} was signed over the envelope a gateway forwarded", label.source
+            )));
+        }
+        check_signature(label, payload_digest(self), trusted_public_key)?;
+        Ok(label)
+    }
+
+    /// The label of an envelope a gateway re-encrypted, if `trusted_public_key` signed it
+    ///
+    /// The signature covers the envelope the gateway received, not this one,
+    /// so this proves the source only as far as the gateway is trusted.
+    pub fn verify_forwarded_label(&self, trusted_public_key: &[u8]) -> Result<&EntropyLabel> {
+        let label = self.present_label()?;
+        let forwarded_from = label.forwarded_from.clone()
+            .ok_or_else(|| PqcError::Verification(format!("Entropy label from {} was not forwarded", label.source)))?;
+        check_signature(label, forwarded_from, trusted_public_key)?;
+        Ok(label)
+    }
+
+    fn present_label(&self) -> Result<&EntropyLabel> {
+        let label = self.label.as_ref()
+            .ok_or_else(|| PqcError::Verification("Entropy envelope carries no source label".to_string()))?;
+        label.validate()?;
+        Ok(label)
+    }
+}
+
+/// Check `label` is signed by `trusted_public_key` over `payload_sha256`
+fn check_signature(label: &EntropyLabel, payload_sha256: String, trusted_public_key: &[u8]) -> Result<()> {
+    fips::check_sig(label.algorithm)?;
+    if !ct::ct_eq_hex(&label.signer_fingerprint, &key_fingerprint(trusted_public_key))
+        || !sign::verify_with(label.algorithm, &label.signing_bytes(payload_sha256)?, &label.signature, trusted_public_key)?
+    {
+        return Err(PqcError::Verification(format!(
+            "Entropy label from {} is not signed by the trusted key", label.source
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, DeviceCredentials};
+
+    fn labelled() -> (EncryptedEntropy, sign::DilithiumKeyPair) {
+        let device = DeviceCredentials::generate().unwrap();
+        let service = sign::generate_keypair().unwrap();
+        let mut sealed = api::encrypt_entropy_for_device(b"entropy", &device.kyber_public_key).unwrap();
+        let label = EntropyLabel::new("local-pool", &"ab".repeat(32), 7).with_degraded(true);
+        sealed.sign_label(label, &service.secret_key, &service.public_key).unwrap();
+        (sealed, service)
+    }
+
+    #[test]
+    fn test_label_round_trip() {
+        let (sealed, service) = labelled();
+        let decoded = EncryptedEntropy::from_bytes(&sealed.to_bytes().unwrap()).unwrap();
+        let label = decoded.verify_label(&service.public_key).unwrap();
+        assert_eq!((label.source.as_str(), label.pool_generation, label.degraded), ("local-pool", 7, true));
+
+        let other = sign::generate_keypair().unwrap();
+        assert!(matches!(decoded.verify_label(&other.public_key), Err(PqcError::Verification(_))));
+    }
+
+    #[test]
+    fn test_label_is_bound_to_envelope_and_fields() {
+        let (sealed, service) = labelled();
+        let (mut other, _) = labelled();
+        other.label = sealed.label.clone();
+        assert!(other.verify_label(&service.public_key).is_err());
+
+        let mut forged = EncryptedEntropy::from_bytes(&sealed.to_bytes().unwrap()).unwrap();
+        forged.label.as_mut().unwrap().degraded = false;
+        assert!(forged.verify_label(&service.public_key).is_err());
+
+        forged.label = None;
+        assert!(forged.verify_label(&service.public_key).is_err());
+    }
+}
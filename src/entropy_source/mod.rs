@@ -0,0 +1,365 @@
+//! Entropy providers with retry, circuit breaking and local failover
+//!
+//! An [`EntropySource`] hands out random bytes and reports its health. A
+//! remote provider, such as a network QRNG appliance, can stall or fail, so
+//! [`ResilientSource`] wraps it:
+//!
+//! - Failed calls are retried with exponential backoff.
+//! - After repeated failures the circuit opens and the remote is left alone
+//!   for a cool-down period.
+//! - While the remote is unavailable, requests are served from a fallback,
+//!   normally the local seed pool (`QRNG`).
+//!
+//! After the cool-down a single trial request decides whether the circuit
+//! closes again. [`EntropySource::status`] reports which state the wrapper
+//! is in.
+//!
+//! With the `idquantique` or `comscire` feature, `vendors` reads QRNG
+//! hardware directly through the character device its driver exposes.
+
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::error::{PqcError, Result};
+use crate::qrng::QRNG;
+
+#[cfg(any(feature = "idquantique", feature = "comscire"))]
+pub mod vendors;
+
+/// Overall health as seen by monitoring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceHealth {
+    Healthy,
+    /// Serving, but from a fallback or after recent failures
+    Degraded,
+    Unavailable,
+}
+
+/// Circuit breaker position of a [`ResilientSource`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go to the remote provider
+    Closed,
+    /// The remote is skipped until the cool-down ends
+    Open,
+    /// Cool-down over; the next request is a trial
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceStatus {
+    pub name: String,
+    pub health: SourceHealth,
+    /// `None` for sources without a breaker
+    pub circuit: Option<CircuitState>,
+    /// Failed calls since the last success
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Requests served by the fallback instead of the remote
+    pub failovers: u64,
+}
+
+impl SourceStatus {
+    /// Status of a source that tracks nothing about itself
+    pub fn healthy(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            health: SourceHealth::Healthy,
+            circuit: None,
+            consecutive_failures: 0,
+            last_error: None,
+            failovers: 0,
+        }
+    }
+}
+
+/// Something that produces random bytes
+pub trait EntropySource {
+    fn name(&self) -> &str;
+
+    /// Exactly `size` random bytes
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>>;
+
+    fn status(&self) -> SourceStatus {
+        SourceStatus::healthy(self.name())
+    }
+}
+
+/// The local seed pool; degraded while it runs on OS entropy
+impl EntropySource for QRNG {
+    fn name(&self) -> &str {
+        "local-pool"
+    }
+
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.generate_entropy(size)
+    }
+
+    fn status(&self) -> SourceStatus {
+        if !self.is_degraded() {
+            return SourceStatus::healthy(self.name());
+        }
+        SourceStatus {
+            health: SourceHealth::Degraded,
+            last_error: Some("quantum seeds missing, running on OS entropy".to_string()),
+            ..SourceStatus::healthy(self.name())
+        }
+    }
+}
+
+/// How often and how patiently to retry a failed call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per call, the first included
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, backing off 100 ms then 200 ms, capped at 2 s
+    fn default() -> Self {
+        Self { max_attempts: 3, initial_backoff: Duration::from_millis(100), max_backoff: Duration::from_secs(2) }
+    }
+}
+
+/// When to stop calling a failing provider, and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerPolicy {
+    /// Failed calls in a row that open the circuit
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a trial request
+    pub open_for: Duration,
+}
+
+impl Default for BreakerPolicy {
+    /// Open after 5 failed calls, for 30 s
+    fn default() -> Self {
+        Self { failure_threshold: 5, open_for: Duration::from_secs(30) }
+    }
+}
+
+/// A remote source guarded by retries and a circuit breaker, with failover
+pub struct ResilientSource<R, F> {
+    remote: R,
+    fallback: F,
+    name: String,
+    retry: RetryPolicy,
+    breaker: BreakerPolicy,
+    open_until: Option<Instant>,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    failovers: u64,
+    last_served_by_fallback: bool,
+}
+
+impl<R: EntropySource, F: EntropySource> ResilientSource<R, F> {
+    pub fn new(remote: R, fallback: F) -> Self {
+        let name = format!("{} (fallback {})", remote.name(), fallback.name());
+        Self {
+            remote,
+            fallback,
+            name,
+            retry: RetryPolicy::default(),
+            breaker: BreakerPolicy::default(),
+            open_until: None,
+            consecutive_failures: 0,
+            last_error: None,
+            failovers: 0,
+            last_served_by_fallback: false,
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_breaker(mut self, breaker: BreakerPolicy) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn circuit(&self) -> CircuitState {
+        match self.open_until {
+            None => CircuitState::Closed,
+            Some(until) if Instant::now() < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Call the remote with retries; `attempts` is 1 for a half-open trial
+    fn try_remote(&mut self, size: usize, attempts: u32) -> Result<Vec<u8>> {
+        let mut backoff = self.retry.initial_backoff;
+        let mut last = None;
+        for attempt in 0..attempts.max(1) {
+            if attempt > 0 {
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(self.retry.max_backoff);
+            }
+            match self.remote.generate(size) {
+                Ok(bytes) if bytes.len() == size => return Ok(bytes),
+                Ok(bytes) => {
+                    last = Some(PqcError::Protocol(format!(
+                        "{} returned {} bytes, requested {}", self.remote.name(), bytes.len(), size
+                    )))
+                }
+                Err(e) => last = Some(e),
+            }
+        }
+        Err(last.unwrap_or_else(|| PqcError::Protocol(format!("{} was not called", self.remote.name()))))
+    }
+}
+
+impl<R: EntropySource, F: EntropySource> EntropySource for ResilientSource<R, F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+        let attempts = match self.circuit() {
+            CircuitState::Closed => Some(self.retry.max_attempts),
+            CircuitState::HalfOpen => Some(1),
+            CircuitState::Open => None,
+        };
+        if let Some(attempts) = attempts {
+            match self.try_remote(size, attempts) {
+                Ok(bytes) => {
+                    self.open_until = None;
+                    self.consecutive_failures = 0;
+                    self.last_served_by_fallback = false;
+                    return Ok(bytes);
+                }
+                Err(e) => {
+                    self.consecutive_failures += 1;
+                    self.last_error = Some(e.to_string());
+                    // A failed trial reopens at once; otherwise wait for the threshold
+                    if self.open_until.is_some() || self.consecutive_failures >= self.breaker.failure_threshold {
+                        self.open_until = Some(Instant::now() + self.breaker.open_for);
+                    }
+                }
+            }
+        }
+
+        self.failovers += 1;
+        self.last_served_by_fallback = true;
+        self.fallback.generate(size).map_err(|e| {
+            PqcError::Protocol(format!(
+                "{} unavailable ({}) and fallback failed: {}",
+                self.remote.name(),
+                self.last_error.as_deref().unwrap_or("circuit open"),
+                e
+            ))
+        })
+    }
+
+    fn status(&self) -> SourceStatus {
+        let fallback = self.fallback.status();
+        let health = if fallback.health == SourceHealth::Unavailable && self.circuit() == CircuitState::Open {
+            SourceHealth::Unavailable
+        } else if self.circuit() != CircuitState::Closed || self.consecutive_failures > 0 || self.last_served_by_fallback {
+            SourceHealth::Degraded
+        } else {
+            SourceHealth::Healthy
+        };
+        SourceStatus {
+            name: self.name.clone(),
+            health,
+            circuit: Some(self.circuit()),
+            consecutive_failures: self.consecutive_failures,
+            last_error: self.last_error.clone(),
+            failovers: self.failovers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Fails its first `failures` calls, then succeeds
+    struct Flaky {
+        calls: Arc<AtomicU32>,
+        failures: u32,
+    }
+
+    impl EntropySource for Flaky {
+        fn name(&self) -> &str {
+            "remote-qrng"
+        }
+
+        fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+            if self.calls.fetch_add(1, Ordering::Relaxed) < self.failures {
+                return Err(PqcError::Io("connection refused".to_string()));
+            }
+            Ok(vec![0xaa; size])
+        }
+    }
+
+    struct Local;
+
+    impl EntropySource for Local {
+        fn name(&self) -> &str {
+            "local-pool"
+        }
+
+        fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+            Ok(vec![0x55; size])
+        }
+    }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy { max_attempts: 3, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(2) }
+    }
+
+    #[test]
+    fn test_retry_recovers_transient_failure() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let mut source = ResilientSource::new(Flaky { calls: calls.clone(), failures: 2 }, Local).with_retry(fast_retry());
+        assert_eq!(source.generate(16).unwrap(), vec![0xaa; 16]);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        assert_eq!(source.status().health, SourceHealth::Healthy);
+    }
+
+    #[test]
+    fn test_breaker_opens_and_fails_over() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let breaker = BreakerPolicy { failure_threshold: 2, open_for: Duration::from_secs(60) };
+        let mut source = ResilientSource::new(Flaky { calls: calls.clone(), failures: u32::MAX }, Local)
+            .with_retry(fast_retry())
+            .with_breaker(breaker);
+
+        assert_eq!(source.generate(8).unwrap(), vec![0x55; 8]);
+        assert_eq!(source.circuit(), CircuitState::Closed);
+        source.generate(8).unwrap();
+        assert_eq!(source.circuit(), CircuitState::Open);
+
+        // While open the remote is not called at all
+        let before = calls.load(Ordering::Relaxed);
+        assert_eq!(source.generate(8).unwrap(), vec![0x55; 8]);
+        assert_eq!(calls.load(Ordering::Relaxed), before);
+
+        let status = source.status();
+        assert_eq!(status.health, SourceHealth::Degraded);
+        assert_eq!(status.failovers, 3);
+        assert!(status.last_error.unwrap().contains("connection refused"));
+    }
+
+    #[test]
+    fn test_half_open_trial_closes_circuit() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let breaker = BreakerPolicy { failure_threshold: 1, open_for: Duration::ZERO };
+        let mut source = ResilientSource::new(Flaky { calls: calls.clone(), failures: 3 }, Local)
+            .with_retry(fast_retry())
+            .with_breaker(breaker);
+
+        source.generate(4).unwrap();
+        assert_eq!(source.circuit(), CircuitState::HalfOpen);
+        assert_eq!(source.generate(4).unwrap(), vec![0xaa; 4]);
+        assert_eq!(source.circuit(), CircuitState::Closed);
+        assert_eq!(source.status().health, SourceHealth::Healthy);
+    }
+}
@@ -0,0 +1,216 @@
+//! Adapters for commercial QRNG hardware
+//!
+//! Vendor drivers expose the generator as a character device that returns
+//! raw random bytes on `read`. [`CharDeviceSource`] reads any such device;
+//! the vendor types add the device names their drivers create:
+//!
+//! | feature | type | device |
+//! |---------|------|--------|
+//! | `idquantique` | [`Quantis`] | `/dev/qrandom0` from the Quantis PCIe driver |
+//! | `comscire` | [`ComScire`] | the node your ComScire driver or udev rule creates |
+//!
+//! Every read passes the SP 800-90B repetition count and adaptive
+//! proportion tests ([`selftest::rng_health_check`]) before it is returned,
+//! so a failed device shows up as an error, and through
+//! [`EntropySource::status`], instead of as biased or constant "random"
+//! bytes. The adaptive proportion test looks at whole 512-byte windows, so
+//! shorter reads only get the repetition count test.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::error::{PqcError, Result};
+use crate::qrng::MAX_ENTROPY_REQUEST;
+use crate::selftest;
+use super::{EntropySource, SourceHealth, SourceStatus};
+
+/// A QRNG read through its character device
+#[derive(Debug)]
+pub struct CharDeviceSource {
+    name: String,
+    path: PathBuf,
+    device: File,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+impl CharDeviceSource {
+    pub fn open<P: AsRef<Path>>(name: &str, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let device = File::open(&path)
+            .map_err(|e| PqcError::Io(format!("Failed to open {} at {}: {}", name, path.display(), e)))?;
+        Ok(Self { name: name.to_string(), path, device, consecutive_failures: 0, last_error: None })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&mut self, size: usize) -> Result<Vec<u8>> {
+        if size > MAX_ENTROPY_REQUEST {
+            return Err(PqcError::InvalidInput(format!(
+                "Entropy request of {} bytes exceeds the {} byte limit", size, MAX_ENTROPY_REQUEST
+            )));
+        }
+        let mut bytes = vec![0u8; size];
+        self.device
+            .read_exact(&mut bytes)
+            .map_err(|e| PqcError::Io(format!("Failed to read {} from {}: {}", self.name, self.path.display(), e)))?;
+        selftest::rng_health_check(&bytes).map_err(|e| PqcError::Protocol(format!(
+            "{} failed its health tests ({}); the device may be stuck or biased", self.name, e
+        )))?;
+        Ok(bytes)
+    }
+}
+
+impl EntropySource for CharDeviceSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+        let result = self.read(size);
+        match &result {
+            Ok(_) => self.consecutive_failures = 0,
+            Err(e) => {
+                self.consecutive_failures += 1;
+                self.last_error = Some(e.to_string());
+            }
+        }
+        result
+    }
+
+    fn status(&self) -> SourceStatus {
+        SourceStatus {
+            health: if self.consecutive_failures > 0 { SourceHealth::Unavailable } else { SourceHealth::Healthy },
+            consecutive_failures: self.consecutive_failures,
+            last_error: self.last_error.clone(),
+            ..SourceStatus::healthy(&self.name)
+        }
+    }
+}
+
+/// ID Quantique Quantis QRNG
+#[cfg(feature = "idquantique")]
+#[derive(Debug)]
+pub struct Quantis(CharDeviceSource);
+
+#[cfg(feature = "idquantique")]
+impl Quantis {
+    /// Device node of the first Quantis card
+    pub const DEFAULT_DEVICE: &'static str = "/dev/qrandom0";
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        CharDeviceSource::open("idq-quantis", path).map(Self)
+    }
+
+    pub fn open_default() -> Result<Self> {
+        Self::open(Self::DEFAULT_DEVICE)
+    }
+}
+
+#[cfg(feature = "idquantique")]
+impl EntropySource for Quantis {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.0.generate(size)
+    }
+
+    fn status(&self) -> SourceStatus {
+        self.0.status()
+    }
+}
+
+/// ComScire PQ-series QRNG
+///
+/// The device node depends on how the driver is installed, so there is no
+/// default path.
+#[cfg(feature = "comscire")]
+#[derive(Debug)]
+pub struct ComScire(CharDeviceSource);
+
+#[cfg(feature = "comscire")]
+impl ComScire {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        CharDeviceSource::open("comscire-qng", path).map(Self)
+    }
+}
+
+#[cfg(feature = "comscire")]
+impl EntropySource for ComScire {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.0.generate(size)
+    }
+
+    fn status(&self) -> SourceStatus {
+        self.0.status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    fn fake_device(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pqc_vendor_{}_{}", name, std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reads_device_and_reports_exhaustion() {
+        let mut random = vec![0u8; 64];
+        rand::thread_rng().fill_bytes(&mut random);
+        let path = fake_device("read", &random);
+
+        let mut source = CharDeviceSource::open("test-qrng", &path).unwrap();
+        assert_eq!(source.generate(32).unwrap(), random[..32]);
+        assert_eq!(source.generate(32).unwrap(), random[32..]);
+        assert_eq!(source.status().health, SourceHealth::Healthy);
+
+        // A short read is an error, not a short buffer
+        assert!(matches!(source.generate(1), Err(PqcError::Io(_))));
+        let status = source.status();
+        assert_eq!(status.health, SourceHealth::Unavailable);
+        assert_eq!(status.consecutive_failures, 1);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_rejects_unhealthy_output() {
+        let path = fake_device("stuck", &[0u8; 64]);
+        let mut source = CharDeviceSource::open("test-qrng", &path).unwrap();
+        assert!(matches!(source.generate(16), Err(PqcError::Protocol(_))));
+        assert!(source.status().last_error.unwrap().contains("repeated"));
+        std::fs::remove_file(path).ok();
+
+        // No runs, but zero is every eighth byte of a 512-byte window
+        let biased: Vec<u8> = (0..512usize).map(|i| if i % 8 == 0 { 0 } else { (i % 251 + 1) as u8 }).collect();
+        let path = fake_device("biased", &biased);
+        let mut source = CharDeviceSource::open("test-qrng", &path).unwrap();
+        assert!(matches!(source.generate(512), Err(PqcError::Protocol(_))));
+        assert!(source.status().last_error.unwrap().contains("seen 64 times"));
+        assert!(CharDeviceSource::open("test-qrng", path.with_extension("missing")).is_err());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[cfg(feature = "idquantique")]
+    #[test]
+    fn test_quantis_wraps_device() {
+        let mut random = vec![0u8; 32];
+        rand::thread_rng().fill_bytes(&mut random);
+        let path = fake_device("quantis", &random);
+        let mut quantis = Quantis::open(&path).unwrap();
+        assert_eq!(quantis.name(), "idq-quantis");
+        assert_eq!(quantis.generate(32).unwrap(), random);
+        std::fs::remove_file(path).ok();
+    }
+}
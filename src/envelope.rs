@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::{canonical, fips};
+use crate::error::{ErrorContext, PqcError, Result, ResultExt};
+use crate::extensions::{ExtensionFields, ExtensionValidators, Extensions};
+use crate::hash::{self, HashAlgorithm};
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::validation::{self, Validate};
+
+/// Signature algorithm identifier written into envelopes
+pub const DILITHIUM3: &str = "Dilithium3";
+
+/// Version of the envelope signing format, as in the domain label
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// Domain label for envelope signatures
+const ENVELOPE_DOMAIN: &str = "PQC-Algo/v1/signed-envelope";
+
+/// Hex SHA-256 of a public key, used to name the signer
+pub fn key_fingerprint(public_key: &[u8]) -> String {
+    hex::encode(hash::digest(HashAlgorithm::Sha256, public_key))
+}
+
+/// Current time in seconds since the Unix epoch, from the process clock
+pub(crate) fn unix_now() -> u64 {
+    crate::clock::now()
+}
+
+/// Fields of a `SignedEnvelope` covered by its signature
+#[derive(Serialize)]
+struct EnvelopeFields<'a> {
+    signer_fingerprint: &'a str,
+    algorithm: &'a str,
+    timestamp: u64,
+    purpose: &'a str,
+    payload: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    fips_mode: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    extensions: BTreeMap<&'a str, ExtensionFields>,
+}
+
+/// A signed payload that says who signed it, when, and for what
+///
+/// Every metadata field is covered by the signature, so a verifier can pick
+/// the public key by `signer_fingerprint` and reject envelopes signed for a
+/// different `purpose` without trusting any out-of-band context.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    pub signer_fingerprint: String,
+    pub algorithm: String,
+    pub timestamp: u64,
+    pub purpose: String,
+    #[serde(with = "crate::util::bytes")]
+    pub payload: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+    /// Set when the envelope was signed in FIPS mode; covered by the signature
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fips_mode: bool,
+    /// Custom metadata; covered by the signature
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
+}
+
+impl SignedEnvelope {
+    /// Sign `payload` for `purpose` with the current time
+    pub fn sign(payload: &[u8], purpose: &str, secret_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        Self::sign_at(payload, purpose, unix_now(), secret_key, public_key)
+    }
+
+    /// Sign `payload` for `purpose` with an explicit timestamp
+    pub fn sign_at(payload: &[u8], purpose: &str, timestamp: u64, secret_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        Self::sign_with(CipherSuite::active_default().sig, payload, purpose, timestamp, secret_key, public_key)
+    }
+
+    /// Sign with a specific Dilithium parameter set, e.g. from a `CipherSuite`
+    pub fn sign_with(algorithm: SigAlgorithm, payload: &[u8], purpose: &str, timestamp: u64, secret_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        Self::sign_with_extensions(algorithm, payload, purpose, timestamp, Extensions::new(), secret_key, public_key)
+    }
+
+    /// [`SignedEnvelope::sign_with`], carrying `extensions` under the signature
+    pub fn sign_with_extensions(algorithm: SigAlgorithm, payload: &[u8], purpose: &str, timestamp: u64, extensions: Extensions, secret_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        fips::check_sig(algorithm)?;
+        extensions.validate()?;
+        let mut envelope = Self {
+            signer_fingerprint: key_fingerprint(public_key),
+            algorithm: algorithm.name().to_string(),
+            timestamp,
+            purpose: purpose.to_string(),
+            payload: payload.to_vec(),
+            signature: Vec::new(),
+            fips_mode: fips::fips_mode(),
+            extensions,
+        };
+        envelope.signature = sign::sign_with(algorithm, &envelope.signing_bytes()?, secret_key)?;
+        Ok(envelope)
+    }
+
+    /// Verify the envelope was signed by `public_key` for `expected_purpose`
+    ///
+    /// A signed envelope with a critical extension is an error; use
+    /// [`SignedEnvelope::verify_with_extensions`] to accept some.
+    pub fn verify(&self, public_key: &[u8], expected_purpose: &str) -> Result<bool> {
+        self.verify_with_extensions(public_key, expected_purpose, &ExtensionValidators::new())
+    }
+
+    /// [`SignedEnvelope::verify`], checking extensions with `validators`
+    ///
+    /// A signed envelope whose extensions fail [`Extensions::check`] is an error.
+    pub fn verify_with_extensions(&self, public_key: &[u8], expected_purpose: &str, validators: &ExtensionValidators) -> Result<bool> {
+        self.verify_inner(public_key, expected_purpose, validators).context(|| {
+            ErrorContext::new("verify envelope")
+                .with_key_fingerprint(&self.signer_fingerprint)
+                .with_envelope_version(ENVELOPE_VERSION)
+        })
+    }
+
+    fn verify_inner(&self, public_key: &[u8], expected_purpose: &str, validators: &ExtensionValidators) -> Result<bool> {
+        let algorithm: SigAlgorithm = self.algorithm.parse()
+            .map_err(|_| PqcError::Verification(format!("Unsupported algorithm: {}", self.algorithm)))?;
+        fips::check_sig(algorithm)?;
+        if self.signer_fingerprint != key_fingerprint(public_key) || self.purpose != expected_purpose {
+            return Ok(false);
+        }
+        if !sign::verify_with(algorithm, &self.signing_bytes()?, &self.signature, public_key)? {
+            return Ok(false);
+        }
+        self.extensions.check(validators)?;
+        Ok(true)
+    }
+
+    /// Serialize to JSON
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode envelope: {}", e)))
+    }
+
+    /// Parse an envelope produced by [`SignedEnvelope::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "envelope")
+    }
+
+    /// Canonical JSON of every field except the signature
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(ENVELOPE_DOMAIN, &EnvelopeFields {
+            signer_fingerprint: &self.signer_fingerprint,
+            algorithm: &self.algorithm,
+            timestamp: self.timestamp,
+            purpose: &self.purpose,
+            payload: hex::encode(&self.payload),
+            fips_mode: self.fips_mode,
+            extensions: self.extensions.signing_fields(),
+        })
+    }
+}
+
+impl Validate for SignedEnvelope {
+    fn validate(&self) -> Result<()> {
+        let algorithm: SigAlgorithm = self.algorithm.parse()
+            .map_err(|_| PqcError::InvalidInput(format!("Unsupported algorithm: {}", self.algorithm)))?;
+        validation::check_fingerprint("Signer fingerprint", &self.signer_fingerprint)?;
+        validation::check_max_len("Envelope purpose", self.purpose.len(), validation::MAX_LABEL_LEN)?;
+        self.extensions.validate()?;
+        validation::check_signature_len("Envelope signature", algorithm, self.signature.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let keypair = sign::generate_keypair().unwrap();
+        let envelope = SignedEnvelope::sign(b"firmware-1.2.3", "firmware-release", &keypair.secret_key, &keypair.public_key).unwrap();
+
+        let decoded = SignedEnvelope::from_bytes(&envelope.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.signer_fingerprint, key_fingerprint(&keypair.public_key));
+        assert!(decoded.verify(&keypair.public_key, "firmware-release").unwrap());
+    }
+
+    #[test]
+    fn test_envelope_rejects_wrong_context() {
+        let keypair = sign::generate_keypair().unwrap();
+        let other = sign::generate_keypair().unwrap();
+        let mut envelope = SignedEnvelope::sign(b"payload", "config-push", &keypair.secret_key, &keypair.public_key).unwrap();
+
+        assert!(!envelope.verify(&keypair.public_key, "firmware-release").unwrap());
+        assert!(!envelope.verify(&other.public_key, "config-push").unwrap());
+
+        envelope.timestamp += 1;
+        assert!(!envelope.verify(&keypair.public_key, "config-push").unwrap());
+
+        envelope.algorithm = "Falcon-512".to_string();
+        let err = envelope.verify(&keypair.public_key, "config-push").unwrap_err();
+        assert!(matches!(err.root(), PqcError::Verification(_)));
+        assert_eq!(err.context().unwrap().key_fingerprint, Some(envelope.signer_fingerprint.clone()));
+    }
+
+    #[test]
+    fn test_envelope_extensions() {
+        let keypair = sign::generate_keypair().unwrap();
+        let algorithm = CipherSuite::active_default().sig;
+        let mut extensions = Extensions::new();
+        extensions.insert("1.3.6.1.4.1.55555.20", false, b"batch 42").unwrap();
+        let envelope = SignedEnvelope::sign_with_extensions(algorithm, b"fw", "firmware-release", 1000, extensions.clone(), &keypair.secret_key, &keypair.public_key).unwrap();
+        let decoded = SignedEnvelope::from_bytes(&envelope.to_bytes().unwrap()).unwrap();
+        assert!(decoded.verify(&keypair.public_key, "firmware-release").unwrap());
+
+        let mut stripped = decoded.clone();
+        stripped.extensions = Extensions::new();
+        assert!(!stripped.verify(&keypair.public_key, "firmware-release").unwrap());
+
+        // Nobody here understands this critical extension
+        extensions.insert("1.3.6.1.4.1.55555.21", true, b"").unwrap();
+        let critical = SignedEnvelope::sign_with_extensions(algorithm, b"fw", "firmware-release", 1000, extensions, &keypair.secret_key, &keypair.public_key).unwrap();
+        let err = critical.verify(&keypair.public_key, "firmware-release").unwrap_err();
+        assert!(matches!(err.root(), PqcError::Verification(_)));
+    }
+}
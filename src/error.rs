@@ -1,30 +1,282 @@
+use std::fmt;
+use serde::Serialize;
 use thiserror::Error;
+use crate::util::redact::redact;
 
-#[derive(Error, Debug)]
+/// Errors from every operation in the crate
+///
+/// Messages pass through [`redact`] when formatted, with `Display` or
+/// `Debug`, so key or plaintext bytes quoted by a backend never reach logs.
+#[derive(Error)]
 pub enum PqcError {
-    #[error("Key generation failed: {0}")]
+    #[error("Key generation failed: {}", redact(.0))]
     KeyGeneration(String),
     
-    #[error("Encryption failed: {0}")]
+    #[error("Encryption failed: {}", redact(.0))]
     Encryption(String),
     
-    #[error("Decryption failed: {0}")]
+    #[error("Decryption failed: {}", redact(.0))]
     Decryption(String),
     
-    #[error("Signature generation failed: {0}")]
+    #[error("Signature generation failed: {}", redact(.0))]
     Signing(String),
     
-    #[error("Signature verification failed: {0}")]
+    #[error("Signature verification failed: {}", redact(.0))]
     Verification(String),
     
-    #[error("Invalid input: {0}")]
+    #[error("Invalid input: {}", redact(.0))]
     InvalidInput(String),
     
-    #[error("I/O error: {0}")]
+    #[error("I/O error: {}", redact(.0))]
     Io(String),
     
-    #[error("Invalid key: {0}")]
+    #[error("Invalid key: {}", redact(.0))]
     InvalidKey(String),
+    
+    #[error("Invalid seed manifest: {}", redact(.0))]
+    InvalidSeedManifest(String),
+    
+    #[error("Seed exhausted: {}", redact(.0))]
+    SeedExhausted(String),
+    
+    #[error("Protocol error: {}", redact(.0))]
+    Protocol(String),
+    
+    #[error("Key policy violation: {}", redact(.0))]
+    KeyPolicy(String),
+    
+    #[error("Not approved in FIPS mode: {}", redact(.0))]
+    NotApproved(String),
+
+    #[error("Crypto backend initialization failed: {}", redact(.0))]
+    BackendInit(String),
+
+    /// A service's memory budget is spent; retry after backing off
+    #[error("Service overloaded: {}", redact(.0))]
+    Overloaded(String),
+
+    /// Stopped by a `CancelToken` or its deadline
+    #[error("Operation cancelled: {}", redact(.0))]
+    Cancelled(String),
+
+    /// Another error plus what was being done when it happened
+    ///
+    /// Breaking change: `KeyManager`, `SignedEnvelope::verify` and
+    /// `api::reencrypt_envelope` used to return the underlying variant and
+    /// now wrap it here, so a `match` on e.g. `PqcError::Verification` no
+    /// longer sees their errors. Match on [`PqcError::root`] instead.
+    #[error("{context}: {inner}")]
+    Context { context: ErrorContext, inner: Box<PqcError> },
+}
+
+/// Like the derived `Debug`, with every message redacted
+impl fmt::Debug for PqcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (variant, message) = match self {
+            PqcError::KeyGeneration(m) => ("KeyGeneration", m),
+            PqcError::Encryption(m) => ("Encryption", m),
+            PqcError::Decryption(m) => ("Decryption", m),
+            PqcError::Signing(m) => ("Signing", m),
+            PqcError::Verification(m) => ("Verification", m),
+            PqcError::InvalidInput(m) => ("InvalidInput", m),
+            PqcError::Io(m) => ("Io", m),
+            PqcError::InvalidKey(m) => ("InvalidKey", m),
+            PqcError::InvalidSeedManifest(m) => ("InvalidSeedManifest", m),
+            PqcError::SeedExhausted(m) => ("SeedExhausted", m),
+            PqcError::Protocol(m) => ("Protocol", m),
+            PqcError::KeyPolicy(m) => ("KeyPolicy", m),
+            PqcError::NotApproved(m) => ("NotApproved", m),
+            PqcError::BackendInit(m) => ("BackendInit", m),
+            PqcError::Overloaded(m) => ("Overloaded", m),
+            PqcError::Cancelled(m) => ("Cancelled", m),
+            PqcError::Context { context, inner } => {
+                return f.debug_struct("Context").field("context", context).field("inner", inner).finish();
+            }
+        };
+        f.debug_tuple(variant).field(&redact(message)).finish()
+    }
+}
+
+/// What an operation was doing when it failed
+///
+/// Attach one with [`ResultExt::context`] or [`PqcError::with_context`];
+/// services add their request ID with [`PqcError::with_correlation_id`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ErrorContext {
+    pub operation: Option<String>,
+    pub key_fingerprint: Option<String>,
+    pub envelope_version: Option<u32>,
+    pub correlation_id: Option<String>,
+}
+
+impl ErrorContext {
+    pub fn new(operation: &str) -> Self {
+        Self { operation: Some(operation.to_string()), ..Self::default() }
+    }
+
+    pub fn with_key_fingerprint(mut self, fingerprint: &str) -> Self {
+        self.key_fingerprint = Some(fingerprint.to_string());
+        self
+    }
+
+    pub fn with_envelope_version(mut self, version: u32) -> Self {
+        self.envelope_version = Some(version);
+        self
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: &str) -> Self {
+        self.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut details = Vec::new();
+        if let Some(fingerprint) = &self.key_fingerprint {
+            // The first 16 hex digits are enough to find the key in logs
+            details.push(format!("key {}", fingerprint.chars().take(16).collect::<String>()));
+        }
+        if let Some(version) = self.envelope_version {
+            details.push(format!("envelope v{}", version));
+        }
+        if let Some(id) = &self.correlation_id {
+            details.push(format!("correlation {}", id));
+        }
+
+        f.write_str(self.operation.as_deref().unwrap_or("operation failed"))?;
+        if !details.is_empty() {
+            write!(f, " [{}]", details.join(", "))?;
+        }
+        Ok(())
+    }
 }
 
-pub type Result<T> = std::result::Result<T, PqcError>;
\ No newline at end of file
+impl PqcError {
+    /// Wrap this error with `context`
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        PqcError::Context { context, inner: Box::new(self) }
+    }
+
+    /// Tag the error with a caller-supplied request or trace ID
+    pub fn with_correlation_id(self, correlation_id: &str) -> Self {
+        match self {
+            PqcError::Context { mut context, inner } if context.correlation_id.is_none() => {
+                context.correlation_id = Some(correlation_id.to_string());
+                PqcError::Context { context, inner }
+            }
+            other => other.with_context(ErrorContext::default().with_correlation_id(correlation_id)),
+        }
+    }
+
+    /// Contexts from the outermost inwards
+    pub fn contexts(&self) -> impl Iterator<Item = &ErrorContext> {
+        std::iter::successors(Some(self), |e| match e {
+            PqcError::Context { inner, .. } => Some(inner),
+            _ => None,
+        })
+        .filter_map(|e| match e {
+            PqcError::Context { context, .. } => Some(context),
+            _ => None,
+        })
+    }
+
+    /// The outermost context, if any
+    pub fn context(&self) -> Option<&ErrorContext> {
+        self.contexts().next()
+    }
+
+    /// The first correlation ID in the chain
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.contexts().find_map(|c| c.correlation_id.as_deref())
+    }
+
+    /// The underlying error with every context stripped; match on this
+    pub fn root(&self) -> &PqcError {
+        match self {
+            PqcError::Context { inner, .. } => inner.root(),
+            other => other,
+        }
+    }
+}
+
+/// Attach an [`ErrorContext`] to the error of a `Result`
+pub trait ResultExt<T> {
+    /// Build the context only on failure
+    fn context<F: FnOnce() -> ErrorContext>(self, f: F) -> Result<T>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn context<F: FnOnce() -> ErrorContext>(self, f: F) -> Result<T> {
+        self.map_err(|e| e.with_context(f()))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, PqcError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::redact::MIN_BYTE_LIST;
+
+    #[test]
+    fn test_context_chain() {
+        let fingerprint = "ab".repeat(32);
+        let result: Result<()> = Err(PqcError::Verification("bad signature".to_string()));
+        let err = result
+            .context(|| ErrorContext::new("verify envelope").with_key_fingerprint(&fingerprint).with_envelope_version(1))
+            .unwrap_err()
+            .with_correlation_id("req-42");
+
+        assert_eq!(
+            err.to_string(),
+            "verify envelope [key abababababababab, envelope v1, correlation req-42]: Signature verification failed: bad signature"
+        );
+        assert!(matches!(err.root(), PqcError::Verification(_)));
+        assert_eq!(err.correlation_id(), Some("req-42"));
+        assert_eq!(err.context().unwrap().key_fingerprint.as_deref(), Some(fingerprint.as_str()));
+
+        let outer = err.with_context(ErrorContext::new("open fleet report")).with_correlation_id("req-43");
+        assert_eq!(outer.contexts().count(), 2);
+        assert_eq!(outer.correlation_id(), Some("req-43"));
+        assert!(matches!(PqcError::Io("x".to_string()).root(), PqcError::Io(_)));
+    }
+
+    #[test]
+    fn test_formatted_errors_never_contain_canary_secrets() {
+        let canary: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(37) ^ 0xa5).collect();
+        let hex_canary = hex::encode(&canary);
+        let list_canary = format!("{:?}", &canary[..MIN_BYTE_LIST]).trim_end_matches(']').to_string();
+        let upper_canary = hex_canary.to_uppercase();
+
+        // A 32-byte secret, e.g. an AES key or KEM shared secret, named as if it were a digest
+        let short_canary = hex::encode(&canary[..32]);
+        let short_list_canary = format!("{:x?}", &canary[..4]).trim_end_matches(']').to_string();
+
+        let mut errors = vec![
+            PqcError::Decryption(format!("backend error: {:?}", canary)),
+            PqcError::Encryption(format!("shared secret {} for key fingerprint {}", short_canary, "ab".repeat(32))),
+            PqcError::InvalidKey(format!("aes key={} sha256", short_canary)),
+            PqcError::Decryption(format!("tag mismatch {:x?}", &canary[..4])),
+            PqcError::InvalidKey(format!("key {} rejected", hex_canary)),
+            PqcError::Encryption(format!("shared secret {}", upper_canary)),
+            PqcError::Io(format!("wrote {:#?}", &canary[..16])),
+        ];
+        // Errors raised by real parsers given secret input
+        let json = format!("{{\"ciphertext\": \"{}\"}}", hex_canary);
+        errors.push(crate::validation::from_json::<crate::api::EncryptedEntropy>(json.as_bytes(), "envelope").err().unwrap());
+        let wrapped: Vec<PqcError> = errors.iter()
+            .map(|e| PqcError::Protocol(e.to_string()).with_context(ErrorContext::new("open envelope")).with_correlation_id("req-1"))
+            .collect();
+
+        for err in errors.iter().chain(&wrapped) {
+            for formatted in [err.to_string(), format!("{:?}", err), format!("{:#?}", err)] {
+                let compact: String = formatted.chars().filter(|c| !c.is_whitespace()).collect();
+                assert!(!formatted.contains(&hex_canary) && !formatted.contains(&upper_canary), "{}", formatted);
+                assert!(!formatted.contains(&short_canary), "{}", formatted);
+                assert!(!compact.contains(&short_list_canary.replace(' ', "")), "{}", formatted);
+                assert!(!compact.contains(&list_canary.replace(' ', "")), "{}", formatted);
+            }
+        }
+    }
+}
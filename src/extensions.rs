@@ -0,0 +1,232 @@
+//! Custom extensions on envelopes, certificates and authentication requests
+//!
+//! [`Extensions`] maps dotted-decimal identifiers in the style of ASN.1 OIDs,
+//! e.g. `1.3.6.1.4.1.55555.1`, to opaque byte values. They are covered by the
+//! artifact's signature, so customer metadata can travel inside the standard
+//! formats without forking them.
+//!
+//! After the signature checks out, each extension is passed to the
+//! validator for its identifier in the [`ExtensionValidators`] the verifier
+//! handed to the verify call. An extension without a validator is ignored
+//! unless it is marked critical, in which case the artifact is rejected, as
+//! in X.509. Validators are never process-wide, so one component cannot
+//! make another accept a critical extension it does not understand.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::validation::{self, Validate};
+
+/// Most extensions one artifact may carry
+pub const MAX_EXTENSIONS: usize = 32;
+
+/// Longest extension value, in bytes
+pub const MAX_EXTENSION_LEN: usize = 4096;
+
+/// Most arcs in an extension identifier
+const MAX_ARCS: usize = 32;
+
+/// One extension value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Extension {
+    /// Verifiers without a validator for this extension must reject the artifact
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub critical: bool,
+    #[serde(with = "crate::util::bytes")]
+    pub value: Vec<u8>,
+}
+
+/// Extensions of one artifact, keyed by identifier
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Extensions(BTreeMap<String, Extension>);
+
+/// How an [`Extension`] is covered by a signature
+#[derive(Serialize)]
+pub(crate) struct ExtensionFields {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    critical: bool,
+    value: String,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the extension `id`
+    pub fn insert(&mut self, id: &str, critical: bool, value: &[u8]) -> Result<()> {
+        validate_id(id)?;
+        validation::check_max_len("Extension value", value.len(), MAX_EXTENSION_LEN)?;
+        if self.0.len() == MAX_EXTENSIONS && !self.0.contains_key(id) {
+            return Err(PqcError::InvalidInput(format!("More than {} extensions", MAX_EXTENSIONS)));
+        }
+        self.0.insert(id.to_string(), Extension { critical, value: value.to_vec() });
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Extension> {
+        self.0.get(id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Extension)> {
+        self.0.iter().map(|(id, extension)| (id.as_str(), extension))
+    }
+
+    /// Run `validators` over a signed artifact's extensions
+    ///
+    /// Fails on the first value a validator refuses and on any critical
+    /// extension without a validator.
+    pub fn check(&self, validators: &ExtensionValidators) -> Result<()> {
+        for (id, extension) in &self.0 {
+            match validators.0.get(id) {
+                Some(validator) => validator.validate(&extension.value)
+                    .map_err(|e| PqcError::Verification(format!("Extension {} is invalid: {}", id, e)))?,
+                None if extension.critical => {
+                    return Err(PqcError::Verification(format!("Unrecognized critical extension {}", id)));
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn signing_fields(&self) -> BTreeMap<&str, ExtensionFields> {
+        self.0
+            .iter()
+            .map(|(id, extension)| (id.as_str(), ExtensionFields { critical: extension.critical, value: hex::encode(&extension.value) }))
+            .collect()
+    }
+}
+
+impl Validate for Extensions {
+    fn validate(&self) -> Result<()> {
+        if self.0.len() > MAX_EXTENSIONS {
+            return Err(PqcError::InvalidInput(format!("More than {} extensions", MAX_EXTENSIONS)));
+        }
+        for (id, extension) in &self.0 {
+            validate_id(id)?;
+            validation::check_max_len("Extension value", extension.value.len(), MAX_EXTENSION_LEN)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check `id` is dotted decimal, like `1.3.6.1.4.1.55555.1`
+pub fn validate_id(id: &str) -> Result<()> {
+    let invalid = || PqcError::InvalidInput(format!("Invalid extension identifier {:?}", id));
+    let arcs: Vec<&str> = id.split('.').collect();
+    if id.len() > validation::MAX_LABEL_LEN || arcs.len() < 2 || arcs.len() > MAX_ARCS {
+        return Err(invalid());
+    }
+    for arc in &arcs {
+        if arc.is_empty() || !arc.bytes().all(|b| b.is_ascii_digit()) || (arc.len() > 1 && arc.starts_with('0')) {
+            return Err(invalid());
+        }
+    }
+    if !matches!(arcs[0], "0" | "1" | "2") {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Checks the value of one kind of extension
+pub trait ExtensionValidator: Send + Sync {
+    fn validate(&self, value: &[u8]) -> Result<()>;
+}
+
+impl<F> ExtensionValidator for F
+where
+    F: Fn(&[u8]) -> Result<()> + Send + Sync,
+{
+    fn validate(&self, value: &[u8]) -> Result<()> {
+        self(value)
+    }
+}
+
+/// The extensions one verifier understands, keyed by identifier
+#[derive(Clone, Default)]
+pub struct ExtensionValidators(BTreeMap<String, Arc<dyn ExtensionValidator>>);
+
+impl ExtensionValidators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check extension `id` with `validator`, replacing any earlier one
+    ///
+    /// Adding a validator is also how a verifier declares that it
+    /// understands a critical extension.
+    pub fn with(mut self, id: &str, validator: Arc<dyn ExtensionValidator>) -> Result<Self> {
+        validate_id(id)?;
+        self.0.insert(id.to_string(), validator);
+        Ok(self)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+}
+
+impl fmt::Debug for ExtensionValidators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifiers() {
+        for id in ["1.3.6.1.4.1.55555.1", "2.999", "0.0"] {
+            validate_id(id).unwrap();
+        }
+        for id in ["", "1", "1..2", "1.02", "3.1", "1.a", "1.2."] {
+            assert!(validate_id(id).is_err(), "{}", id);
+        }
+        let mut extensions = Extensions::new();
+        assert!(extensions.insert("1.2", false, &[0; MAX_EXTENSION_LEN + 1]).is_err());
+        for i in 0..MAX_EXTENSIONS {
+            extensions.insert(&format!("1.2.{}", i), false, b"").unwrap();
+        }
+        assert!(extensions.insert("1.3", false, b"").is_err());
+        extensions.insert("1.2.0", true, b"replaced").unwrap();
+    }
+
+    #[test]
+    fn test_critical_extensions_need_a_validator() {
+        let none = ExtensionValidators::new();
+        let mut extensions = Extensions::new();
+        extensions.insert("1.3.6.1.4.1.55555.901", false, b"ignored").unwrap();
+        extensions.check(&none).unwrap();
+
+        extensions.insert("1.3.6.1.4.1.55555.902", true, b"site-7").unwrap();
+        assert!(matches!(extensions.check(&none), Err(PqcError::Verification(_))));
+
+        let sites = ExtensionValidators::new().with("1.3.6.1.4.1.55555.902", Arc::new(|value: &[u8]| {
+            if value.starts_with(b"site-") {
+                Ok(())
+            } else {
+                Err(PqcError::InvalidInput("not a site tag".to_string()))
+            }
+        })).unwrap();
+        extensions.check(&sites).unwrap();
+        // Another verifier's validators do not leak into this one
+        assert!(extensions.check(&none).is_err());
+        extensions.insert("1.3.6.1.4.1.55555.902", true, b"nowhere").unwrap();
+        assert!(extensions.check(&sites).unwrap_err().to_string().contains("not a site tag"));
+        assert!(ExtensionValidators::new().with("1..2", Arc::new(|_: &[u8]| Ok(()))).is_err());
+    }
+}
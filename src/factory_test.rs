@@ -0,0 +1,273 @@
+//! Round-trip hardware test for factory QA stations
+//!
+//! The station drives a device over any [`Transport`]: the device generates
+//! a throwaway credential, decrypts a random payload sealed to it, and signs
+//! a random challenge. [`run`] is the station side and [`respond`] is a
+//! reference device side, which firmware simulators can use as-is. The
+//! temporary credential never leaves the device and is dropped at the end.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use crate::api::{self, AuthRequest, DeviceCredentials, EncryptedEntropy};
+use crate::error::{PqcError, Result};
+use crate::hash::{self, HashAlgorithm};
+use crate::selftest::SelfTestStatus;
+use crate::suite::CipherSuite;
+use crate::transport::Transport;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+
+const PAYLOAD_LEN: usize = 64;
+const CHALLENGE_LEN: usize = 32;
+
+/// Station → device
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FactoryRequest {
+    /// Generate a temporary credential under `suite`
+    Provision { suite: CipherSuite },
+    /// Decrypt with the temporary credential and return the payload's SHA-256
+    Decrypt { envelope: Box<EncryptedEntropy> },
+    /// Sign `nonce` as an authentication request
+    Sign {
+        #[serde(with = "crate::util::bytes")]
+        nonce: Vec<u8>,
+    },
+    /// Drop the temporary credential
+    Finish,
+}
+
+/// Device → station
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FactoryResponse {
+    Provisioned {
+        device_id: String,
+        #[serde(with = "crate::util::bytes")]
+        kyber_public_key: Vec<u8>,
+        #[serde(with = "crate::util::bytes")]
+        dilithium_public_key: Vec<u8>,
+    },
+    Decrypted { sha256: String },
+    Signed { request: AuthRequest },
+    Error { message: String },
+}
+
+impl Validate for FactoryRequest {
+    fn validate(&self) -> Result<()> {
+        match self {
+            FactoryRequest::Decrypt { envelope } => envelope.validate(),
+            FactoryRequest::Sign { nonce } => validation::check_max_len("Factory test nonce", nonce.len(), validation::MAX_NONCE_LEN),
+            FactoryRequest::Provision { .. } | FactoryRequest::Finish => Ok(()),
+        }
+    }
+}
+
+impl Validate for FactoryResponse {
+    fn validate(&self) -> Result<()> {
+        match self {
+            FactoryResponse::Provisioned { device_id, kyber_public_key, dilithium_public_key } => {
+                validate_device_id(device_id)?;
+                validation::check_any_public_key_len("Device Kyber public key", kyber_public_key.len())?;
+                validation::check_any_public_key_len("Device Dilithium public key", dilithium_public_key.len())
+            }
+            FactoryResponse::Decrypted { sha256 } => validation::check_fingerprint("Decrypted payload SHA-256", sha256),
+            FactoryResponse::Signed { request } => request.validate(),
+            FactoryResponse::Error { .. } => Ok(()),
+        }
+    }
+}
+
+/// Outcome of one step of the round trip
+#[derive(Debug, Clone, Serialize)]
+pub struct FactoryStep {
+    pub name: &'static str,
+    pub status: SelfTestStatus,
+}
+
+/// Everything [`run`] checked on one device
+#[derive(Debug, Clone, Serialize)]
+pub struct FactoryTestReport {
+    pub device_id: Option<String>,
+    pub suite: CipherSuite,
+    pub steps: Vec<FactoryStep>,
+    pub duration: Duration,
+}
+
+impl FactoryTestReport {
+    /// True when every step passed; skipped steps count as failures here
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.status == SelfTestStatus::Passed)
+    }
+}
+
+impl fmt::Display for FactoryTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let device_id = self.device_id.as_deref().unwrap_or("<no response>");
+        writeln!(f, "Factory round trip with device {} ({})", device_id, self.suite)?;
+        for step in &self.steps {
+            // A step that could not run fails the device, so don't call it skipped
+            let status = match &step.status {
+                SelfTestStatus::Passed => "PASS".to_string(),
+                SelfTestStatus::Failed(reason) => format!("FAIL ({})", reason),
+                SelfTestStatus::Skipped(reason) => format!("NOT RUN ({})", reason),
+            };
+            writeln!(f, "{:<28} {}", step.name, status)?;
+        }
+        write!(f, "Device {} {} factory QA in {:?}", device_id, if self.passed() { "passed" } else { "FAILED" }, self.duration)
+    }
+}
+
+/// Station side: run the round trip against the device behind `transport`
+pub fn run<T: Transport>(transport: &mut T, suite: &CipherSuite) -> FactoryTestReport {
+    let start = Instant::now();
+    let mut steps = Vec::new();
+    let step = |name, result: Result<()>| FactoryStep {
+        name,
+        status: result.map_or_else(|e| SelfTestStatus::Failed(e.to_string()), |_| SelfTestStatus::Passed),
+    };
+
+    let provisioned = provision(transport, suite);
+    let device_id = provisioned.as_ref().ok().map(|(device_id, _, _)| device_id.clone());
+    match provisioned {
+        Ok((device_id, kyber_public_key, dilithium_public_key)) => {
+            steps.push(step("Provision temp credential", Ok(())));
+            steps.push(step("Encrypt/decrypt payload", decrypt_round_trip(transport, suite, &kyber_public_key)));
+            steps.push(step("Sign/verify challenge", sign_round_trip(transport, suite, &device_id, &dilithium_public_key)));
+            // The verdict is already in; a device that drops the link here still passed
+            let _ = transport.send_json(&FactoryRequest::Finish);
+        }
+        Err(e) => {
+            steps.push(step("Provision temp credential", Err(e)));
+            for name in ["Encrypt/decrypt payload", "Sign/verify challenge"] {
+                steps.push(FactoryStep { name, status: SelfTestStatus::Skipped("no credential".to_string()) });
+            }
+        }
+    }
+
+    FactoryTestReport { device_id, suite: *suite, steps, duration: start.elapsed() }
+}
+
+/// Device side: answer station requests with a fresh credential until told to finish
+pub fn respond<T: Transport>(transport: &mut T, device_id: &str) -> Result<()> {
+    let mut session: Option<(CipherSuite, DeviceCredentials)> = None;
+    loop {
+        let response = match transport.receive_json::<FactoryRequest>()? {
+            FactoryRequest::Finish => return Ok(()),
+            FactoryRequest::Provision { suite } => DeviceCredentials::generate_with_suite(&suite).map(|credentials| {
+                let response = FactoryResponse::Provisioned {
+                    device_id: device_id.to_string(),
+                    kyber_public_key: credentials.kyber_public_key.clone(),
+                    dilithium_public_key: credentials.dilithium_public_key.clone(),
+                };
+                session = Some((suite, credentials));
+                response
+            }),
+            FactoryRequest::Decrypt { envelope } => credentials(&session).and_then(|(suite, credentials)| {
+                let payload = api::decrypt_entropy_with_suite(&envelope, &credentials.kyber_secret_key, suite)?;
+                Ok(FactoryResponse::Decrypted { sha256: hex::encode(hash::digest(HashAlgorithm::Sha256, &payload)) })
+            }),
+            FactoryRequest::Sign { nonce } => credentials(&session).and_then(|(suite, credentials)| {
+                let request = api::create_auth_request_with_suite(device_id, &nonce, &credentials.dilithium_secret_key, suite)?;
+                Ok(FactoryResponse::Signed { request })
+            }),
+        };
+        transport.send_json(&response.unwrap_or_else(|e| FactoryResponse::Error { message: e.to_string() }))?;
+    }
+}
+
+fn credentials(session: &Option<(CipherSuite, DeviceCredentials)>) -> Result<(&CipherSuite, &DeviceCredentials)> {
+    session.as_ref()
+        .map(|(suite, credentials)| (suite, credentials))
+        .ok_or_else(|| PqcError::Protocol("No temporary credential provisioned".to_string()))
+}
+
+fn exchange<T: Transport>(transport: &mut T, request: &FactoryRequest) -> Result<FactoryResponse> {
+    transport.send_json(request)?;
+    match transport.receive_json()? {
+        FactoryResponse::Error { message } => Err(PqcError::Protocol(format!("Device reported: {}", message))),
+        response => Ok(response),
+    }
+}
+
+fn unexpected<T>() -> Result<T> {
+    Err(PqcError::Protocol("Unexpected response from device".to_string()))
+}
+
+fn provision<T: Transport>(transport: &mut T, suite: &CipherSuite) -> Result<(String, Vec<u8>, Vec<u8>)> {
+    let FactoryResponse::Provisioned { device_id, kyber_public_key, dilithium_public_key } =
+        exchange(transport, &FactoryRequest::Provision { suite: *suite })?
+    else {
+        return unexpected();
+    };
+    if kyber_public_key.len() != suite.kem.public_key_len()? || dilithium_public_key.len() != suite.sig.public_key_len()? {
+        return Err(PqcError::Protocol(format!("Device returned keys of the wrong size for {}", suite)));
+    }
+    Ok((device_id, kyber_public_key, dilithium_public_key))
+}
+
+fn decrypt_round_trip<T: Transport>(transport: &mut T, suite: &CipherSuite, kyber_public_key: &[u8]) -> Result<()> {
+    let mut payload = [0u8; PAYLOAD_LEN];
+    rand::thread_rng().fill_bytes(&mut payload);
+    let envelope = api::encrypt_entropy_with_suite(&payload, kyber_public_key, suite)?;
+
+    let FactoryResponse::Decrypted { sha256 } = exchange(transport, &FactoryRequest::Decrypt { envelope: Box::new(envelope) })? else {
+        return unexpected();
+    };
+    if !ct::ct_eq_hex(&sha256, &hex::encode(hash::digest(HashAlgorithm::Sha256, &payload))) {
+        return Err(PqcError::Decryption("Device decrypted a different payload".to_string()));
+    }
+    Ok(())
+}
+
+fn sign_round_trip<T: Transport>(transport: &mut T, suite: &CipherSuite, device_id: &str, dilithium_public_key: &[u8]) -> Result<()> {
+    let mut nonce = vec![0u8; CHALLENGE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let FactoryResponse::Signed { request } = exchange(transport, &FactoryRequest::Sign { nonce: nonce.clone() })? else {
+        return unexpected();
+    };
+    if request.device_id != device_id || request.nonce != nonce {
+        return Err(PqcError::Verification("Device signed a different challenge".to_string()));
+    }
+    if !api::verify_auth_request_with_suite(&request, dilithium_public_key, suite)? {
+        return Err(PqcError::Verification("Challenge signature does not verify".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MemoryTransport;
+
+    #[test]
+    fn test_round_trip_against_reference_device() {
+        let (mut station, mut device) = MemoryTransport::pair();
+        let handle = std::thread::spawn(move || respond(&mut device, "sensor-7"));
+
+        let report = run(&mut station, &CipherSuite::DEFAULT);
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.device_id.as_deref(), Some("sensor-7"));
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_broken_device_fails() {
+        let (mut station, mut device) = MemoryTransport::pair();
+        std::thread::spawn(move || {
+            let _: FactoryRequest = device.receive_json().unwrap();
+            device.send_json(&FactoryResponse::Error { message: "keygen failed".to_string() }).unwrap();
+        });
+
+        let report = run(&mut station, &CipherSuite::DEFAULT);
+        assert!(!report.passed());
+        assert!(matches!(report.steps[1].status, SelfTestStatus::Skipped(_)));
+        let text = report.to_string();
+        assert!(text.starts_with("Factory round trip with device <no response>"));
+        assert!(text.contains("NOT RUN (no credential)"));
+        assert!(text.lines().last().unwrap().starts_with("Device <no response> FAILED factory QA"));
+    }
+}
@@ -0,0 +1,121 @@
+//! FIPS mode: restrict algorithm choices to FIPS-approved ones
+//!
+//! In FIPS mode only ML-KEM, ML-DSA, AES-GCM and SHA-2 are accepted by the
+//! policy layer (suite-taking APIs, suite negotiation, hash policies and
+//! envelopes); everything else fails with [`PqcError::NotApproved`]. The
+//! functions that take no suite switch to [`CipherSuite::FIPS_DEFAULT`],
+//! and envelopes produced in FIPS mode carry a `fips_mode` marker.
+//!
+//! FIPS mode is process-wide and one-way. Turn it on with
+//! [`enable_fips_mode`] or by setting `PQC_ALGO_FIPS=1` before first use.
+//! This crate is not a validated module; the mode enforces algorithm
+//! choices only.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use crate::error::{PqcError, Result};
+use crate::hash::HashAlgorithm;
+use crate::kem::KemAlgorithm;
+use crate::selftest::{self_test, SelfTestReport};
+use crate::sign::SigAlgorithm;
+use crate::suite::{AeadAlgorithm, CipherSuite};
+
+/// Environment variable that turns FIPS mode on at startup
+pub const FIPS_ENV_VAR: &str = "PQC_ALGO_FIPS";
+
+static FIPS_MODE: AtomicBool = AtomicBool::new(false);
+static ENV_INIT: Once = Once::new();
+
+/// Whether FIPS mode is active
+pub fn fips_mode() -> bool {
+    ENV_INIT.call_once(|| {
+        if std::env::var(FIPS_ENV_VAR).is_ok_and(|v| env_enables(&v)) {
+            FIPS_MODE.store(true, Ordering::SeqCst);
+        }
+    });
+    FIPS_MODE.load(Ordering::SeqCst)
+}
+
+/// `1` or `true` in any case; anything else leaves FIPS mode off
+fn env_enables(value: &str) -> bool {
+    value == "1" || value.eq_ignore_ascii_case("true")
+}
+
+/// Run the power-on self tests and, if they pass, enter FIPS mode
+pub fn enable_fips_mode() -> Result<SelfTestReport> {
+    let report = self_test();
+    if !report.passed() {
+        return Err(PqcError::NotApproved(format!("Self tests failed:\n{}", report)));
+    }
+    fips_mode();
+    FIPS_MODE.store(true, Ordering::SeqCst);
+    Ok(report)
+}
+
+pub fn is_approved_kem(algorithm: KemAlgorithm) -> bool {
+    matches!(algorithm, KemAlgorithm::MlKem512 | KemAlgorithm::MlKem768 | KemAlgorithm::MlKem1024)
+}
+
+pub fn is_approved_sig(algorithm: SigAlgorithm) -> bool {
+    matches!(algorithm, SigAlgorithm::MlDsa44 | SigAlgorithm::MlDsa65 | SigAlgorithm::MlDsa87)
+}
+
+pub fn is_approved_aead(algorithm: AeadAlgorithm) -> bool {
+    matches!(algorithm, AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm)
+}
+
+pub fn is_approved_hash(algorithm: HashAlgorithm) -> bool {
+    matches!(algorithm, HashAlgorithm::Sha256 | HashAlgorithm::Sha384 | HashAlgorithm::Sha512)
+}
+
+pub fn is_approved_suite(suite: &CipherSuite) -> bool {
+    is_approved_kem(suite.kem) && is_approved_sig(suite.sig) && is_approved_aead(suite.aead) && is_approved_hash(suite.hash)
+}
+
+/// Reject a suite with any non-approved algorithm while in FIPS mode
+pub fn check_suite(suite: &CipherSuite) -> Result<()> {
+    if fips_mode() && !is_approved_suite(suite) {
+        return Err(PqcError::NotApproved(suite.to_string()));
+    }
+    Ok(())
+}
+
+/// Reject a non-approved signature algorithm while in FIPS mode
+pub fn check_sig(algorithm: SigAlgorithm) -> Result<()> {
+    if fips_mode() && !is_approved_sig(algorithm) {
+        return Err(PqcError::NotApproved(algorithm.to_string()));
+    }
+    Ok(())
+}
+
+/// Reject a non-approved hash while in FIPS mode
+pub fn check_hash(algorithm: HashAlgorithm) -> Result<()> {
+    if fips_mode() && !is_approved_hash(algorithm) {
+        return Err(PqcError::NotApproved(algorithm.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::SecurityLevel;
+
+    #[test]
+    fn test_approved_sets() {
+        assert!(is_approved_suite(&CipherSuite::FIPS_DEFAULT));
+        assert!(!is_approved_suite(&CipherSuite::DEFAULT));
+        for level in [SecurityLevel::L1, SecurityLevel::L3, SecurityLevel::L5] {
+            assert!(is_approved_suite(&level.fips_suite()));
+        }
+        assert!(!is_approved_hash(HashAlgorithm::Sha3_256));
+    }
+
+    #[test]
+    fn test_malformed_env_values_leave_fips_off() {
+        assert!(env_enables("1") && env_enables("TRUE"));
+        for value in ["", "0", "yes", "on", " 1", "1\n", "true1", "\u{0}"] {
+            assert!(!env_enables(value), "{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Firmware manifests and their verification in a bootloader
+//!
+//! A [`FirmwareManifest`] describes one firmware image: its version string,
+//! its security version for anti-rollback (see `rollback::FIRMWARE_COUNTER`),
+//! its length and its SHA-256. The manifest has a fixed binary layout
+//! rather than JSON, so a second-stage bootloader can check it with
+//! [`verify_firmware_signature`], which borrows from the input instead of
+//! copying it, and then hash the image with [`FirmwareManifest::verify_image`].
+//!
+//! Layout, integers big-endian:
+//!
+//! | bytes | field |
+//! |-------|-------|
+//! | 4 | magic `PQFW` |
+//! | 1 | format version, 1 |
+//! | 1 | signature algorithm id |
+//! | 8 | security version |
+//! | 8 | image length |
+//! | 32 | image SHA-256 |
+//! | 1 | version string length, at most [`MAX_VERSION_LEN`] |
+//! | n | version string, ASCII |
+//!
+//! The signature covers `PQC-Algo/v1/firmware-manifest`, a zero byte, and
+//! the manifest bytes.
+
+use crate::error::{PqcError, Result};
+use crate::sign::{self, SigAlgorithm};
+use crate::util::ct;
+use crate::{fips, hash};
+
+/// First bytes of every manifest
+pub const MANIFEST_MAGIC: &[u8; 4] = b"PQFW";
+
+/// Manifest layout written by this version
+pub const MANIFEST_FORMAT: u8 = 1;
+
+/// Longest version string a manifest can hold
+pub const MAX_VERSION_LEN: usize = 64;
+
+/// Domain label for manifest signatures
+const MANIFEST_DOMAIN: &[u8] = b"PQC-Algo/v1/firmware-manifest";
+
+/// Size of everything before the version string
+const FIXED_LEN: usize = 4 + 1 + 1 + 8 + 8 + 32 + 1;
+
+/// Description of one firmware image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareManifest<'a> {
+    pub version: &'a str,
+    /// Monotonic version used to refuse downgrades
+    pub security_version: u64,
+    pub image_len: u64,
+    pub image_sha256: [u8; 32],
+    /// Algorithm the manifest is signed with
+    pub algorithm: SigAlgorithm,
+}
+
+impl<'a> FirmwareManifest<'a> {
+    /// Manifest for `image`
+    pub fn for_image(version: &'a str, security_version: u64, image: &[u8], algorithm: SigAlgorithm) -> Result<Self> {
+        check_version(version)?;
+        Ok(Self {
+            version,
+            security_version,
+            image_len: image.len() as u64,
+            image_sha256: hash::sha256(&[image]),
+            algorithm,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        check_version(self.version)?;
+        let mut bytes = Vec::with_capacity(FIXED_LEN + self.version.len());
+        bytes.extend_from_slice(MANIFEST_MAGIC);
+        bytes.push(MANIFEST_FORMAT);
+        bytes.push(algorithm_id(self.algorithm));
+        bytes.extend_from_slice(&self.security_version.to_be_bytes());
+        bytes.extend_from_slice(&self.image_len.to_be_bytes());
+        bytes.extend_from_slice(&self.image_sha256);
+        bytes.push(self.version.len() as u8);
+        bytes.extend_from_slice(self.version.as_bytes());
+        Ok(bytes)
+    }
+
+    /// Read a manifest without checking its signature
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let malformed = |what: &str| PqcError::InvalidInput(format!("Malformed firmware manifest: {}", what));
+        if bytes.len() < FIXED_LEN || &bytes[..4] != MANIFEST_MAGIC {
+            return Err(malformed("bad magic or too short"));
+        }
+        if bytes[4] != MANIFEST_FORMAT {
+            return Err(malformed("unsupported format version"));
+        }
+        let algorithm = algorithm_from_id(bytes[5]).ok_or_else(|| malformed("unknown signature algorithm"))?;
+        let version_len = bytes[FIXED_LEN - 1] as usize;
+        if bytes.len() != FIXED_LEN + version_len {
+            return Err(malformed("length does not match version string"));
+        }
+        let version = std::str::from_utf8(&bytes[FIXED_LEN..]).map_err(|_| malformed("version is not UTF-8"))?;
+        check_version(version)?;
+
+        let mut security_version = [0u8; 8];
+        security_version.copy_from_slice(&bytes[6..14]);
+        let mut image_len = [0u8; 8];
+        image_len.copy_from_slice(&bytes[14..22]);
+        let mut image_sha256 = [0u8; 32];
+        image_sha256.copy_from_slice(&bytes[22..54]);
+        Ok(Self {
+            version,
+            security_version: u64::from_be_bytes(security_version),
+            image_len: u64::from_be_bytes(image_len),
+            image_sha256,
+            algorithm,
+        })
+    }
+
+    /// Check that `image` is the one this manifest describes
+    pub fn verify_image(&self, image: &[u8]) -> Result<()> {
+        if image.len() as u64 != self.image_len || !ct::ct_eq(&hash::sha256(&[image]), &self.image_sha256) {
+            return Err(PqcError::Verification(format!("Firmware image does not match manifest {}", self.version)));
+        }
+        Ok(())
+    }
+}
+
+/// Sign `manifest` for release; returns the manifest bytes and the signature
+pub fn sign_firmware_manifest(manifest: &FirmwareManifest<'_>, secret_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    fips::check_sig(manifest.algorithm)?;
+    let bytes = manifest.to_bytes()?;
+    let signature = sign::sign_with(manifest.algorithm, &signed_message(&bytes), secret_key)?;
+    Ok((bytes, signature))
+}
+
+/// Check a manifest's signature under the key built into the bootloader
+///
+/// Returns the parsed manifest, borrowing from `manifest`. Call
+/// [`FirmwareManifest::verify_image`] on the image next, and compare its
+/// `security_version` with the stored one before booting it.
+pub fn verify_firmware_signature<'a>(manifest: &'a [u8], signature: &[u8], trusted_public_key: &[u8]) -> Result<FirmwareManifest<'a>> {
+    let parsed = FirmwareManifest::parse(manifest)?;
+    fips::check_sig(parsed.algorithm)?;
+    let sizes = parsed.algorithm.sizes();
+    if signature.len() > sizes.signature || trusted_public_key.len() != sizes.public_key {
+        return Err(PqcError::Verification(format!("Signature or key does not fit {}", parsed.algorithm)));
+    }
+    if !sign::verify_with(parsed.algorithm, &signed_message(manifest), signature, trusted_public_key)? {
+        return Err(PqcError::Verification("Firmware manifest signature is invalid".to_string()));
+    }
+    Ok(parsed)
+}
+
+fn signed_message(manifest: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(MANIFEST_DOMAIN.len() + 1 + manifest.len());
+    message.extend_from_slice(MANIFEST_DOMAIN);
+    message.push(0);
+    message.extend_from_slice(manifest);
+    message
+}
+
+fn check_version(version: &str) -> Result<()> {
+    if version.is_empty() || version.len() > MAX_VERSION_LEN || !version.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(PqcError::InvalidInput(format!(
+            "Firmware versions must be 1 to {} printable ASCII characters", MAX_VERSION_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Stable on-disk identifier of each signature algorithm
+fn algorithm_id(algorithm: SigAlgorithm) -> u8 {
+    match algorithm {
+        SigAlgorithm::Dilithium2 => 1,
+        SigAlgorithm::Dilithium3 => 2,
+        SigAlgorithm::Dilithium5 => 3,
+        SigAlgorithm::MlDsa44 => 4,
+        SigAlgorithm::MlDsa65 => 5,
+        SigAlgorithm::MlDsa87 => 6,
+    }
+}
+
+fn algorithm_from_id(id: u8) -> Option<SigAlgorithm> {
+    SigAlgorithm::all().iter().copied().find(|&algorithm| algorithm_id(algorithm) == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify_manifest() {
+        let keypair = sign::generate_keypair_with(SigAlgorithm::MlDsa65).unwrap();
+        let image = vec![0x5au8; 4096];
+        let manifest = FirmwareManifest::for_image("2.4.1", 7, &image, SigAlgorithm::MlDsa65).unwrap();
+        let (bytes, signature) = sign_firmware_manifest(&manifest, &keypair.secret_key).unwrap();
+        assert_eq!(bytes.len(), FIXED_LEN + 5);
+
+        let verified = verify_firmware_signature(&bytes, &signature, &keypair.public_key).unwrap();
+        assert_eq!(verified, manifest);
+        verified.verify_image(&image).unwrap();
+        assert!(verified.verify_image(&image[1..]).is_err());
+        let mut patched = image.clone();
+        patched[100] ^= 1;
+        assert!(verified.verify_image(&patched).is_err());
+    }
+
+    #[test]
+    fn test_rejects_tampered_manifest_and_wrong_key() {
+        let keypair = sign::generate_keypair_with(SigAlgorithm::MlDsa65).unwrap();
+        let other = sign::generate_keypair_with(SigAlgorithm::MlDsa65).unwrap();
+        let manifest = FirmwareManifest::for_image("2.4.1", 7, b"image", SigAlgorithm::MlDsa65).unwrap();
+        let (bytes, signature) = sign_firmware_manifest(&manifest, &keypair.secret_key).unwrap();
+
+        let mut downgraded = bytes.clone();
+        downgraded[13] = 6;
+        assert!(matches!(verify_firmware_signature(&downgraded, &signature, &keypair.public_key), Err(PqcError::Verification(_))));
+        assert!(verify_firmware_signature(&bytes, &signature, &other.public_key).is_err());
+        // A different algorithm id changes which key sizes are accepted
+        let mut relabelled = bytes.clone();
+        relabelled[5] = algorithm_id(SigAlgorithm::Dilithium2);
+        assert!(verify_firmware_signature(&relabelled, &signature, &keypair.public_key).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_bytes() {
+        let manifest = FirmwareManifest::for_image("1.0", 1, b"image", SigAlgorithm::MlDsa44).unwrap();
+        let bytes = manifest.to_bytes().unwrap();
+        assert_eq!(FirmwareManifest::parse(&bytes).unwrap(), manifest);
+        assert!(FirmwareManifest::parse(&bytes[..bytes.len() - 1]).is_err());
+        assert!(FirmwareManifest::parse(b"PQFW").is_err());
+        let mut unknown = bytes.clone();
+        unknown[5] = 99;
+        assert!(FirmwareManifest::parse(&unknown).is_err());
+        assert!(FirmwareManifest::for_image("", 1, b"image", SigAlgorithm::MlDsa44).is_err());
+    }
+}
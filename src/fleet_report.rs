@@ -0,0 +1,177 @@
+//! Signed fleet health reports
+//!
+//! A report summarizes a batch of authentication attempts and is signed as
+//! a [`SignedEnvelope`], so the daily attestation summary cannot be edited
+//! after it leaves the service that produced it.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::canonical;
+use crate::envelope::SignedEnvelope;
+use crate::error::{PqcError, Result};
+use crate::validation::{self, validate_device_id, Validate};
+
+/// Envelope purpose for fleet health reports
+pub const FLEET_REPORT_PURPOSE: &str = "fleet-health-report";
+
+/// Result of one device authentication attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthOutcome {
+    Verified,
+    Failed,
+    /// The device's credentials have been revoked
+    Revoked,
+}
+
+/// One authentication attempt observed by the service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthObservation {
+    pub device_id: String,
+    pub outcome: AuthOutcome,
+    pub latency: Duration,
+}
+
+impl AuthObservation {
+    pub fn new(device_id: &str, outcome: AuthOutcome, latency: Duration) -> Self {
+        Self { device_id: device_id.to_string(), outcome, latency }
+    }
+}
+
+/// Verification latency statistics in microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub min_us: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencySummary {
+    pub fn from_latencies(latencies: &[Duration]) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        let mut micros: Vec<u64> = latencies.iter()
+            .map(|d| u64::try_from(d.as_micros()).unwrap_or(u64::MAX))
+            .collect();
+        micros.sort_unstable();
+
+        let percentile = |p: usize| micros[(micros.len() - 1) * p / 100];
+        let total: u128 = micros.iter().map(|&us| us as u128).sum();
+        Self {
+            min_us: micros[0],
+            mean_us: (total / micros.len() as u128) as u64,
+            p50_us: percentile(50),
+            p95_us: percentile(95),
+            max_us: micros[micros.len() - 1],
+        }
+    }
+}
+
+/// Summary of authentication results over a reporting period
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetHealthReport {
+    /// Start of the period, seconds since the Unix epoch
+    pub period_start: u64,
+    /// End of the period, seconds since the Unix epoch
+    pub period_end: u64,
+    pub total_attempts: u64,
+    pub verified_attempts: u64,
+    pub failed_attempts: u64,
+    pub revoked_attempts: u64,
+    /// Distinct devices seen in the period
+    pub devices_seen: u64,
+    /// Devices with at least one failed attempt, sorted
+    pub failed_devices: Vec<String>,
+    /// Devices that presented revoked credentials, sorted
+    pub revoked_devices: Vec<String>,
+    pub latency: LatencySummary,
+}
+
+impl FleetHealthReport {
+    /// Summarize a batch of observations
+    pub fn from_observations(period_start: u64, period_end: u64, observations: &[AuthObservation]) -> Self {
+        let count = |outcome| observations.iter().filter(|o| o.outcome == outcome).count() as u64;
+        let devices_with = |outcome| -> Vec<String> {
+            observations.iter()
+                .filter(|o| o.outcome == outcome)
+                .map(|o| o.device_id.clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        };
+        let devices_seen = observations.iter().map(|o| o.device_id.as_str()).collect::<BTreeSet<_>>().len();
+        let latencies: Vec<Duration> = observations.iter().map(|o| o.latency).collect();
+
+        Self {
+            period_start,
+            period_end,
+            total_attempts: observations.len() as u64,
+            verified_attempts: count(AuthOutcome::Verified),
+            failed_attempts: count(AuthOutcome::Failed),
+            revoked_attempts: count(AuthOutcome::Revoked),
+            devices_seen: devices_seen as u64,
+            failed_devices: devices_with(AuthOutcome::Failed),
+            revoked_devices: devices_with(AuthOutcome::Revoked),
+            latency: LatencySummary::from_latencies(&latencies),
+        }
+    }
+
+    /// Sign the report as an envelope with purpose [`FLEET_REPORT_PURPOSE`]
+    pub fn sign(&self, dilithium_secret_key: &[u8], dilithium_public_key: &[u8]) -> Result<SignedEnvelope> {
+        let payload = canonical::to_canonical_json(self)?;
+        SignedEnvelope::sign(&payload, FLEET_REPORT_PURPOSE, dilithium_secret_key, dilithium_public_key)
+    }
+
+    /// Verify a signed report and return its contents
+    pub fn open(envelope: &SignedEnvelope, dilithium_public_key: &[u8]) -> Result<Self> {
+        if !envelope.verify(dilithium_public_key, FLEET_REPORT_PURPOSE)? {
+            return Err(PqcError::Verification("Fleet report signature is invalid".to_string()));
+        }
+        validation::from_json(&envelope.payload, "fleet report")
+    }
+}
+
+impl Validate for FleetHealthReport {
+    fn validate(&self) -> Result<()> {
+        self.failed_devices.iter().chain(&self.revoked_devices).try_for_each(|device_id| validate_device_id(device_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign;
+
+    #[test]
+    fn test_report_summary_and_signature() {
+        let ms = Duration::from_millis;
+        let observations = vec![
+            AuthObservation::new("dev-a", AuthOutcome::Verified, ms(4)),
+            AuthObservation::new("dev-a", AuthOutcome::Verified, ms(6)),
+            AuthObservation::new("dev-b", AuthOutcome::Failed, ms(5)),
+            AuthObservation::new("dev-c", AuthOutcome::Revoked, ms(1)),
+            AuthObservation::new("dev-b", AuthOutcome::Failed, ms(20)),
+        ];
+        let report = FleetHealthReport::from_observations(1_700_000_000, 1_700_086_400, &observations);
+
+        assert_eq!(report.total_attempts, 5);
+        assert_eq!((report.verified_attempts, report.failed_attempts, report.revoked_attempts), (2, 2, 1));
+        assert_eq!(report.devices_seen, 3);
+        assert_eq!(report.failed_devices, vec!["dev-b"]);
+        assert_eq!(report.revoked_devices, vec!["dev-c"]);
+        assert_eq!((report.latency.min_us, report.latency.p50_us, report.latency.max_us), (1000, 5000, 20000));
+
+        let noc = sign::generate_keypair().unwrap();
+        let mut envelope = report.sign(&noc.secret_key, &noc.public_key).unwrap();
+        assert_eq!(FleetHealthReport::open(&envelope, &noc.public_key).unwrap(), report);
+
+        // Editing the summary invalidates it
+        let edited = String::from_utf8(envelope.payload.clone()).unwrap().replace("\"failed_attempts\":2", "\"failed_attempts\":0");
+        envelope.payload = edited.into_bytes();
+        assert!(matches!(FleetHealthReport::open(&envelope, &noc.public_key), Err(PqcError::Verification(_))));
+    }
+}
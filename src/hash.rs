@@ -0,0 +1,242 @@
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::{Sha3_256, Sha3_512};
+use crate::error::{PqcError, Result};
+
+/// Message digest algorithms available for fingerprints, manifests and prehashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    #[serde(rename = "sha256")]
+    Sha256,
+    #[serde(rename = "sha384")]
+    Sha384,
+    #[serde(rename = "sha512")]
+    Sha512,
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+    #[serde(rename = "sha3-512")]
+    Sha3_512,
+    #[cfg(feature = "blake3")]
+    #[serde(rename = "blake3")]
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Every algorithm compiled into this build
+    pub fn all() -> &'static [HashAlgorithm] {
+        &[
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+            HashAlgorithm::Sha3_256,
+            HashAlgorithm::Sha3_512,
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha384 => "sha384",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Sha3_256 => "sha3-256",
+            HashAlgorithm::Sha3_512 => "sha3-512",
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Digest length in bytes
+    pub fn output_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Sha3_256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 | HashAlgorithm::Sha3_512 => 64,
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => 32,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        HashAlgorithm::all()
+            .iter()
+            .copied()
+            .find(|alg| alg.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| PqcError::InvalidInput(format!("Unknown hash algorithm: {}", s)))
+    }
+}
+
+/// Incremental hasher for any [`HashAlgorithm`]
+#[derive(Clone)]
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Sha3_256(Sha3_256),
+    Sha3_512(Sha3_512),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha384 => Hasher::Sha384(Sha384::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Sha3_256 => Hasher::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::Sha3_512 => Hasher::Sha3_512(Sha3_512::new()),
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        match self {
+            Hasher::Sha256(h) => Digest::update(h, data),
+            Hasher::Sha384(h) => Digest::update(h, data),
+            Hasher::Sha512(h) => Digest::update(h, data),
+            Hasher::Sha3_256(h) => Digest::update(h, data),
+            Hasher::Sha3_512(h) => Digest::update(h, data),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+        self
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha384(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Sha3_256(h) => h.finalize().to_vec(),
+            Hasher::Sha3_512(h) => h.finalize().to_vec(),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Digest of `data` under `algorithm`
+pub fn digest(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Digest everything read from `reader` without buffering it in memory
+pub fn digest_reader<R: Read>(algorithm: HashAlgorithm, mut reader: R) -> Result<Vec<u8>> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)
+            .map_err(|e| PqcError::Io(format!("Failed to read input: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// SHA-256 over the concatenation of `parts`
+pub fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Which digest algorithms a deployment accepts, and which it uses by default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashPolicy {
+    pub default: HashAlgorithm,
+    pub allowed: Vec<HashAlgorithm>,
+}
+
+impl HashPolicy {
+    /// Policy allowing only `algorithm`
+    pub fn only(algorithm: HashAlgorithm) -> Self {
+        Self { default: algorithm, allowed: vec![algorithm] }
+    }
+
+    /// Reject algorithms the policy doesn't allow, or that FIPS mode forbids
+    pub fn check(&self, algorithm: HashAlgorithm) -> Result<()> {
+        crate::fips::check_hash(algorithm)?;
+        if self.allowed.contains(&algorithm) {
+            Ok(())
+        } else {
+            Err(PqcError::InvalidInput(format!("Hash algorithm {} is not allowed by policy", algorithm)))
+        }
+    }
+
+    /// Digest with the policy's default algorithm
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        digest(self.default, data)
+    }
+}
+
+impl Default for HashPolicy {
+    fn default() -> Self {
+        Self {
+            default: HashAlgorithm::Sha256,
+            allowed: vec![
+                HashAlgorithm::Sha256,
+                HashAlgorithm::Sha384,
+                HashAlgorithm::Sha512,
+                HashAlgorithm::Sha3_256,
+                HashAlgorithm::Sha3_512,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_answers() {
+        assert_eq!(
+            hex::encode(digest(HashAlgorithm::Sha256, b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex::encode(digest(HashAlgorithm::Sha3_256, b"abc")),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(sha256(&[b"a", b"bc"]).to_vec(), digest(HashAlgorithm::Sha256, b"abc"));
+    }
+
+    #[test]
+    fn test_output_lengths_and_names() {
+        for &alg in HashAlgorithm::all() {
+            assert_eq!(digest(alg, b"data").len(), alg.output_len());
+            assert_eq!(alg.name().parse::<HashAlgorithm>().unwrap(), alg);
+        }
+    }
+
+    #[test]
+    fn test_policy_check() {
+        let policy = HashPolicy::only(HashAlgorithm::Sha512);
+        assert!(policy.check(HashAlgorithm::Sha512).is_ok());
+        assert!(policy.check(HashAlgorithm::Sha256).is_err());
+    }
+}
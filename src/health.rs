@@ -0,0 +1,295 @@
+//! Service health for liveness and readiness endpoints
+//!
+//! [`QRNGEntropyService::health`](crate::qrng::QRNGEntropyService::health)
+//! collects the power-on self tests, backend version, entropy pool and
+//! memory budget into a [`HealthStatus`]. The self tests are rerun at most
+//! once per [`SELF_TEST_TTL`], so frequent probes stay cheap. Add registry
+//! connectivity with [`HealthStatus::with_registry`] and benchmark results
+//! with [`HealthStatus::with_capacity`], then serialize it as the endpoint's
+//! body.
+//!
+//! The service is [`HealthState::Unhealthy`] if the backend or a self test
+//! failed, and [`HealthState::Degraded`] while any [`DegradedReason`]
+//! applies. Degraded services still serve requests.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::backend::{self, BackendInfo};
+use crate::bench::BenchReport;
+use crate::budget::MemoryBudget;
+use crate::envelope::unix_now;
+use crate::fips;
+use crate::registry::{DeviceRegistry, RegistryStore};
+use crate::seed_lifecycle::SeedStatus;
+use crate::selftest::{self, SelfTestReport};
+
+/// How long a self-test report is reused before the tests run again
+pub const SELF_TEST_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Share of a seed's wear-out budget left below which the service is degraded
+const SEED_WEAR_WARNING_PERCENT: u64 = 10;
+
+/// Share of the memory budget in use at or above which the service is degraded
+const MEMORY_PRESSURE_PERCENT: usize = 90;
+
+/// Overall verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Healthy,
+    /// Serving, with the problems listed in [`HealthStatus::degraded`]
+    Degraded,
+    /// Must not serve; take it out of rotation
+    Unhealthy,
+}
+
+/// Why a serving service is degraded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DegradedReason {
+    /// Running on OS entropy because the quantum seeds are missing
+    OsEntropy,
+    /// The seeds are close to their wear-out limit
+    SeedWearOut,
+    /// Most of the memory budget is held by in-flight requests
+    MemoryPressure,
+    RegistryUnreachable,
+}
+
+/// The loaded seeds and how much of them is left
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntropyPoolHealth {
+    pub seed_fingerprint: String,
+    pub os_entropy: bool,
+    pub bytes_derived: u64,
+    pub keys_derived: u64,
+    pub remaining_bytes: u64,
+    pub remaining_keys: u64,
+}
+
+/// Memory budget usage, for services that have one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryHealth {
+    pub in_use: usize,
+    pub limit: usize,
+    pub rejected: u64,
+}
+
+/// Result of pinging the device registry's store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RegistryHealth {
+    pub reachable: bool,
+    pub latency_us: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Body of a health endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    pub state: HealthState,
+    pub checked_at: u64,
+    /// liboqs version and enabled algorithms; absent if liboqs failed to initialize
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<BackendInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_error: Option<String>,
+    pub fips_mode: bool,
+    pub self_test: SelfTestReport,
+    pub entropy_pool: EntropyPoolHealth,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<RegistryHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<BenchReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded: Vec<DegradedReason>,
+}
+
+/// The last self-test report, rerun once it is older than its TTL
+pub(crate) struct SelfTestCache {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, SelfTestReport)>>,
+}
+
+impl SelfTestCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self { ttl, last: Mutex::new(None) }
+    }
+
+    /// The cached report, or a fresh one if it expired; concurrent callers wait for one run
+    pub(crate) fn report(&self) -> SelfTestReport {
+        let mut last = self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match last.as_ref() {
+            Some((ran_at, report)) if ran_at.elapsed() < self.ttl => report.clone(),
+            _ => {
+                let report = selftest::self_test();
+                *last = Some((Instant::now(), report.clone()));
+                report
+            }
+        }
+    }
+}
+
+impl HealthStatus {
+    /// Summarize a service's self tests, seeds and memory budget
+    pub(crate) fn collect(self_test: SelfTestReport, seeds: &SeedStatus, budget: Option<&MemoryBudget>) -> Self {
+        let (backend, backend_error) = match backend::backend_info() {
+            Ok(info) => (Some(info), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let mut status = Self {
+            state: HealthState::Healthy,
+            checked_at: unix_now(),
+            backend,
+            backend_error,
+            fips_mode: fips::fips_mode(),
+            self_test,
+            entropy_pool: EntropyPoolHealth {
+                seed_fingerprint: seeds.fingerprint.clone(),
+                os_entropy: seeds.degraded,
+                bytes_derived: seeds.usage.bytes_derived,
+                keys_derived: seeds.usage.keys_derived,
+                remaining_bytes: seeds.remaining_bytes,
+                remaining_keys: seeds.remaining_keys,
+            },
+            memory: budget.map(|budget| MemoryHealth { in_use: budget.in_use(), limit: budget.limit(), rejected: budget.rejected() }),
+            registry: None,
+            capacity: None,
+            degraded: Vec::new(),
+        };
+        if seeds.degraded {
+            status.degraded.push(DegradedReason::OsEntropy);
+        }
+        let nearly_worn = |remaining: u64, max: u64| remaining < max / 100 * SEED_WEAR_WARNING_PERCENT;
+        if !seeds.degraded
+            && (nearly_worn(seeds.remaining_bytes, seeds.policy.max_bytes) || nearly_worn(seeds.remaining_keys, seeds.policy.max_keys))
+        {
+            status.degraded.push(DegradedReason::SeedWearOut);
+        }
+        if status.memory.is_some_and(|m| m.in_use.saturating_mul(100) >= m.limit.saturating_mul(MEMORY_PRESSURE_PERCENT)) {
+            status.degraded.push(DegradedReason::MemoryPressure);
+        }
+        status.refresh_state();
+        status
+    }
+
+    /// Ping `registry` and record whether it answered, replacing any earlier ping
+    pub fn with_registry<S: RegistryStore>(mut self, registry: &DeviceRegistry<S>) -> Self {
+        let start = Instant::now();
+        let result = registry.ping();
+        let latency_us = u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX);
+        self.degraded.retain(|reason| *reason != DegradedReason::RegistryUnreachable);
+        if result.is_err() {
+            self.degraded.push(DegradedReason::RegistryUnreachable);
+        }
+        self.registry = Some(RegistryHealth { reachable: result.is_ok(), latency_us, error: result.err().map(|e| e.to_string()) });
+        self.refresh_state();
+        self
+    }
+
+    /// Report measured throughput, e.g. from a [`bench::measure`](crate::bench::measure) run at startup
+    pub fn with_capacity(mut self, report: BenchReport) -> Self {
+        self.capacity = Some(report);
+        self
+    }
+
+    /// Whether a load balancer should keep sending requests
+    pub fn is_serving(&self) -> bool {
+        self.state != HealthState::Unhealthy
+    }
+
+    /// 200 while serving, 503 otherwise
+    pub fn http_status(&self) -> u16 {
+        if self.is_serving() { 200 } else { 503 }
+    }
+
+    fn refresh_state(&mut self) {
+        self.state = if self.backend.is_none() || !self.self_test.passed() {
+            HealthState::Unhealthy
+        } else if !self.degraded.is_empty() {
+            HealthState::Degraded
+        } else {
+            HealthState::Healthy
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{PqcError, Result};
+    use crate::registry::DeviceRecord;
+    use crate::seed_lifecycle::{SeedUsage, WearOutPolicy};
+
+    struct Unreachable;
+
+    impl RegistryStore for Unreachable {
+        fn get(&self, _: &str, _: &str) -> Result<Option<DeviceRecord>> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+        fn put(&self, _: &DeviceRecord) -> Result<()> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+        fn insert_if_absent(&self, _: &DeviceRecord) -> Result<bool> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+        fn replace_all(&self, _: &[DeviceRecord], _: u64) -> Result<usize> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+        fn delete(&self, _: &str, _: &str) -> Result<bool> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+        fn list(&self, _: &str) -> Result<Vec<DeviceRecord>> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+        fn tenants(&self) -> Result<Vec<String>> {
+            Err(PqcError::Io("connection refused".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_healthy_until_registry_fails() {
+        let seeds = SeedStatus::new(&"ab".repeat(32), SeedUsage::default(), WearOutPolicy::UNLIMITED);
+        let status = HealthStatus::collect(selftest::self_test(), &seeds, None).with_registry(&DeviceRegistry::in_memory());
+        assert_eq!(status.state, HealthState::Healthy);
+        assert!(status.registry.as_ref().unwrap().reachable);
+
+        let status = status.with_registry(&DeviceRegistry::new(Unreachable));
+        assert_eq!(status.state, HealthState::Degraded);
+        assert_eq!(status.degraded, [DegradedReason::RegistryUnreachable]);
+        assert_eq!(status.http_status(), 200);
+        let status = status.with_registry(&DeviceRegistry::new(Unreachable));
+        assert_eq!(status.degraded, [DegradedReason::RegistryUnreachable]);
+        assert_eq!(status.clone().with_registry(&DeviceRegistry::in_memory()).state, HealthState::Healthy);
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["state"], "degraded");
+        assert!(json["registry"]["error"].as_str().unwrap().contains("connection refused"));
+        assert!(json["backend"]["liboqs_version"].is_string());
+    }
+
+    #[test]
+    fn test_degraded_reasons() {
+        let usage = SeedUsage { bytes_derived: 950, keys_derived: 1 };
+        let seeds = SeedStatus::new(&"ab".repeat(32), usage, WearOutPolicy::new(1000, 100));
+        let budget = MemoryBudget::new(100);
+        let _held = budget.try_reserve(95).unwrap();
+        let status = HealthStatus::collect(selftest::self_test(), &seeds, Some(&budget));
+        assert_eq!(status.degraded, [DegradedReason::SeedWearOut, DegradedReason::MemoryPressure]);
+        assert_eq!(status.memory.unwrap().in_use, 95);
+    }
+
+    #[test]
+    fn test_self_tests_are_cached() {
+        let cache = SelfTestCache::new(Duration::from_secs(60));
+        let first = cache.report();
+        assert!(first.passed());
+        assert_eq!(cache.report().duration, first.duration);
+
+        let expired = SelfTestCache::new(Duration::ZERO);
+        let _ = expired.report();
+        assert!(expired.last.lock().unwrap().is_some());
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha512};
+use crate::error::{PqcError, Result};
+use crate::xof::Xof;
+use zeroize::Zeroizing;
+
+/// Prefix of every derivation label used by this crate
+pub const LABEL_PREFIX: &str = "PQC-Algo/v1/";
+
+/// Length of AES-256-GCM keys taken from KEM shared secrets
+pub const ENVELOPE_KEY_LEN: usize = 32;
+
+/// Build a crate-style label: `PQC-Algo/v1/<purpose>`
+///
+/// Use as the HKDF `info` or KMAC customization string so keys derived for
+/// different purposes from the same secret never collide.
+pub fn label(purpose: &str) -> Vec<u8> {
+    format!("{}{}", LABEL_PREFIX, purpose).into_bytes()
+}
+
+/// HKDF-SHA256 (RFC 5869) extract-and-expand to `len` bytes
+pub fn hkdf_sha256(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut okm = vec![0u8; len];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut okm)
+        .map_err(|_| PqcError::InvalidInput(format!("HKDF-SHA256 cannot produce {} bytes", len)))?;
+    Ok(okm)
+}
+
+/// HKDF-SHA512 (RFC 5869) extract-and-expand to `len` bytes
+pub fn hkdf_sha512(ikm: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut okm = vec![0u8; len];
+    Hkdf::<Sha512>::new(Some(salt), ikm)
+        .expand(info, &mut okm)
+        .map_err(|_| PqcError::InvalidInput(format!("HKDF-SHA512 cannot produce {} bytes", len)))?;
+    Ok(okm)
+}
+
+/// KMAC256 (NIST SP 800-185) of `data` under `key`, `len` bytes long
+pub fn kmac256(key: &[u8], data: &[u8], customization: &[u8], len: usize) -> Vec<u8> {
+    const RATE: usize = 136;
+
+    let mut padded = left_encode(RATE as u64);
+    padded.extend_from_slice(&left_encode((key.len() as u64) * 8));
+    padded.extend_from_slice(key);
+    let rem = padded.len() % RATE;
+    if rem != 0 {
+        padded.resize(padded.len() + RATE - rem, 0);
+    }
+
+    let mut xof = Xof::cshake256(b"KMAC", customization);
+    xof.update(&padded)
+        .update(data)
+        .update(&right_encode((len as u64) * 8));
+    xof.finalize(len)
+}
+
+/// AES-256-GCM key for an `EncryptedEntropy` envelope from its KEM shared secret
+///
+/// The envelope format keys AES directly with the first 32 bytes of the
+/// Kyber shared secret, which is already a uniformly random KDF output.
+pub fn envelope_key(shared_secret: &[u8]) -> Result<[u8; ENVELOPE_KEY_LEN]> {
+    if shared_secret.len() < ENVELOPE_KEY_LEN {
+        return Err(PqcError::InvalidKey(format!(
+            "Shared secret must be at least {} bytes", ENVELOPE_KEY_LEN
+        )));
+    }
+    let mut key = [0u8; ENVELOPE_KEY_LEN];
+    key.copy_from_slice(&shared_secret[..ENVELOPE_KEY_LEN]);
+    Ok(key)
+}
+
+/// Derive an application sub-key from a KEM shared secret
+///
+/// HKDF-SHA256 with an empty salt and `label(purpose)` as info. Distinct
+/// purposes give independent keys, and none of them equal the envelope key.
+pub fn derive_subkey(shared_secret: &[u8], purpose: &str, len: usize) -> Result<Vec<u8>> {
+    hkdf_sha256(shared_secret, &[], &label(purpose), len)
+}
+
+/// Well-known sub-key paths
+pub const SUBKEY_STORAGE: &str = "storage";
+pub const SUBKEY_TELEMETRY: &str = "telemetry";
+pub const SUBKEY_OTA: &str = "ota";
+
+/// Root of a device's symmetric key tree
+///
+/// Sub-keys are addressed by `/`-separated paths such as `"ota/firmware"`.
+/// Each segment is one HKDF-SHA256 step labelled `key-path/<segment>`, so
+/// `derive_path("ota/firmware")` equals
+/// `derive_path("ota")?.derive_path("firmware")` and a subtree can be
+/// handed to a component without exposing its siblings.
+#[derive(Clone)]
+pub struct DeviceMasterKey {
+    key: Zeroizing<[u8; ENVELOPE_KEY_LEN]>,
+}
+
+impl DeviceMasterKey {
+    /// Root the tree at a device master secret of at least 32 bytes
+    pub fn new(master_secret: &[u8]) -> Result<Self> {
+        if master_secret.len() < ENVELOPE_KEY_LEN {
+            return Err(PqcError::InvalidKey(format!(
+                "Device master secret must be at least {} bytes", ENVELOPE_KEY_LEN
+            )));
+        }
+        Ok(Self::from_okm(hkdf_sha256(master_secret, &[], &label("device-master"), ENVELOPE_KEY_LEN)?))
+    }
+
+    /// Derive the key at `path` below this one
+    ///
+    /// Unrelated to [`derive_subkey`]: the segments are labelled
+    /// `key-path/<segment>`, so a path never yields that function's key.
+    pub fn derive_path(&self, path: &str) -> Result<Self> {
+        let mut key = self.clone();
+        for segment in path.split('/') {
+            if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_') {
+                return Err(PqcError::InvalidInput(format!("Invalid key path: {:?}", path)));
+            }
+            let info = label(&format!("key-path/{}", segment));
+            key = Self::from_okm(hkdf_sha256(key.key.as_slice(), &[], &info, ENVELOPE_KEY_LEN)?);
+        }
+        Ok(key)
+    }
+
+    /// The raw 32-byte key, e.g. for AES-256-GCM or HMAC
+    pub fn expose_secret(&self) -> &[u8] {
+        self.key.as_slice()
+    }
+
+    fn from_okm(okm: Vec<u8>) -> Self {
+        let okm = Zeroizing::new(okm);
+        let mut key = Zeroizing::new([0u8; ENVELOPE_KEY_LEN]);
+        key.copy_from_slice(&okm);
+        Self { key }
+    }
+}
+
+impl fmt::Debug for DeviceMasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DeviceMasterKey([redacted])")
+    }
+}
+
+fn left_encode(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    let mut out = vec![(8 - skip) as u8];
+    out.extend_from_slice(&bytes[skip..]);
+    out
+}
+
+fn right_encode(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    let mut out = bytes[skip..].to_vec();
+    out.push((8 - skip) as u8);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkdf_sha256_rfc5869_case1() {
+        let ikm = [0x0bu8; 22];
+        let salt = hex::decode("000102030405060708090a0b0c").unwrap();
+        let info = hex::decode("f0f1f2f3f4f5f6f7f8f9").unwrap();
+        let okm = hkdf_sha256(&ikm, &salt, &info, 42).unwrap();
+        assert_eq!(
+            hex::encode(okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+    }
+
+    #[test]
+    fn test_kmac256_sp800_185_sample4() {
+        let key: Vec<u8> = (0x40..=0x5f).collect();
+        let mac = kmac256(&key, &[0x00, 0x01, 0x02, 0x03], b"My Tagged Application", 64);
+        assert_eq!(
+            hex::encode(mac),
+            "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7\
+             f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"
+        );
+    }
+
+    #[test]
+    fn test_subkeys_are_separated() {
+        let secret = [3u8; 32];
+        let a = derive_subkey(&secret, "telemetry", 32).unwrap();
+        let b = derive_subkey(&secret, "config", 32).unwrap();
+        assert_ne!(a, b);
+        assert_ne!(a.as_slice(), envelope_key(&secret).unwrap().as_slice());
+        assert!(hkdf_sha512(&secret, &[], b"", 255 * 64 + 1).is_err());
+    }
+
+    #[test]
+    fn test_key_paths() {
+        let master = DeviceMasterKey::new(&[9u8; 32]).unwrap();
+        let ota = master.derive_path(SUBKEY_OTA).unwrap();
+        let firmware = master.derive_path("ota/firmware").unwrap();
+        assert_eq!(firmware.expose_secret(), ota.derive_path("firmware").unwrap().expose_secret());
+        assert_ne!(master.derive_path(SUBKEY_STORAGE).unwrap().expose_secret(), master.derive_path(SUBKEY_TELEMETRY).unwrap().expose_secret());
+        assert_ne!(ota.expose_secret(), master.expose_secret());
+
+        for bad in ["", "ota/", "/ota", "OTA", "ota//x", "ota firmware"] {
+            assert!(master.derive_path(bad).is_err(), "{:?}", bad);
+        }
+        assert!(DeviceMasterKey::new(&[1u8; 16]).is_err());
+        assert_eq!(format!("{:?}", master), "DeviceMasterKey([redacted])");
+    }
+}
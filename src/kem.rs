@@ -1,58 +1,351 @@
+use std::fmt;
+#[cfg(feature = "liboqs")]
+use std::ptr::NonNull;
+use std::str::FromStr;
+#[cfg(feature = "liboqs")]
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
 use crate::error::{PqcError, Result};
-use oqs::kem::{Kem, Algorithm};
+#[cfg(feature = "liboqs")]
+use oqs::kem::Algorithm;
+
+/// Shared secret length of every supported KEM
+pub const SHARED_SECRET_LEN: usize = 32;
+
+/// Byte sizes of one KEM parameter set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KemSizes {
+    pub public_key: usize,
+    pub secret_key: usize,
+    pub ciphertext: usize,
+    pub shared_secret: usize,
+}
+
+/// Kyber and ML-KEM (FIPS 203) parameter sets
+///
+/// New parameter sets may be added in minor releases, so downstream matches
+/// need a wildcard arm. Convert to and from `oqs::kem::Algorithm` with
+/// `From`/`TryFrom` if you also use liboqs directly (feature `liboqs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum KemAlgorithm {
+    Kyber512,
+    Kyber768,
+    Kyber1024,
+    MlKem512,
+    MlKem768,
+    MlKem1024,
+}
+
+impl KemAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KemAlgorithm::Kyber512 => "Kyber512",
+            KemAlgorithm::Kyber768 => "Kyber768",
+            KemAlgorithm::Kyber1024 => "Kyber1024",
+            KemAlgorithm::MlKem512 => "ML-KEM-512",
+            KemAlgorithm::MlKem768 => "ML-KEM-768",
+            KemAlgorithm::MlKem1024 => "ML-KEM-1024",
+        }
+    }
+
+    #[cfg(feature = "liboqs")]
+    pub(crate) fn oqs(&self) -> Algorithm {
+        match self {
+            KemAlgorithm::Kyber512 => Algorithm::Kyber512,
+            KemAlgorithm::Kyber768 => Algorithm::Kyber768,
+            KemAlgorithm::Kyber1024 => Algorithm::Kyber1024,
+            KemAlgorithm::MlKem512 => Algorithm::MlKem512,
+            KemAlgorithm::MlKem768 => Algorithm::MlKem768,
+            KemAlgorithm::MlKem1024 => Algorithm::MlKem1024,
+        }
+    }
+
+    /// Public key length in bytes, as the linked liboqs reports it
+    pub fn public_key_len(&self) -> Result<usize> {
+        raw_backend(*self, PqcError::InvalidInput).map(|kem| kem.lengths().0)
+    }
+
+    /// Ciphertext length in bytes, as the linked liboqs reports it
+    pub fn ciphertext_len(&self) -> Result<usize> {
+        raw_backend(*self, PqcError::InvalidInput).map(|kem| kem.lengths().1)
+    }
+
+    /// Whether the linked liboqs was built with this parameter set
+    #[cfg(feature = "liboqs")]
+    pub fn is_enabled(&self) -> bool {
+        self.oqs().is_enabled()
+    }
+
+    /// Always false without the `liboqs` feature
+    #[cfg(not(feature = "liboqs"))]
+    pub fn is_enabled(&self) -> bool {
+        false
+    }
+
+    /// Exact buffer sizes, usable in constant expressions such as array lengths
+    pub const fn sizes(&self) -> KemSizes {
+        let (public_key, secret_key, ciphertext) = match self {
+            KemAlgorithm::Kyber512 | KemAlgorithm::MlKem512 => (800, 1632, 768),
+            KemAlgorithm::Kyber768 | KemAlgorithm::MlKem768 => (1184, 2400, 1088),
+            KemAlgorithm::Kyber1024 | KemAlgorithm::MlKem1024 => (1568, 3168, 1568),
+        };
+        KemSizes { public_key, secret_key, ciphertext, shared_secret: SHARED_SECRET_LEN }
+    }
+
+    pub fn all() -> &'static [KemAlgorithm] {
+        &[
+            KemAlgorithm::Kyber512,
+            KemAlgorithm::Kyber768,
+            KemAlgorithm::Kyber1024,
+            KemAlgorithm::MlKem512,
+            KemAlgorithm::MlKem768,
+            KemAlgorithm::MlKem1024,
+        ]
+    }
+}
+
+impl fmt::Display for KemAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(feature = "liboqs")]
+impl From<KemAlgorithm> for Algorithm {
+    fn from(algorithm: KemAlgorithm) -> Self {
+        algorithm.oqs()
+    }
+}
+
+#[cfg(feature = "liboqs")]
+impl TryFrom<Algorithm> for KemAlgorithm {
+    type Error = PqcError;
+
+    fn try_from(algorithm: Algorithm) -> Result<Self> {
+        match algorithm {
+            Algorithm::Kyber512 => Ok(KemAlgorithm::Kyber512),
+            Algorithm::Kyber768 => Ok(KemAlgorithm::Kyber768),
+            Algorithm::Kyber1024 => Ok(KemAlgorithm::Kyber1024),
+            Algorithm::MlKem512 => Ok(KemAlgorithm::MlKem512),
+            Algorithm::MlKem768 => Ok(KemAlgorithm::MlKem768),
+            Algorithm::MlKem1024 => Ok(KemAlgorithm::MlKem1024),
+            other => Err(PqcError::InvalidInput(format!("Unsupported KEM algorithm: {:?}", other))),
+        }
+    }
+}
+
+impl FromStr for KemAlgorithm {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        KemAlgorithm::all()
+            .iter()
+            .copied()
+            .find(|alg| alg.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| PqcError::InvalidInput(format!("Unknown KEM algorithm: {}", s)))
+    }
+}
 
 pub struct KyberKeyPair {
     pub public_key: Vec<u8>,
     pub secret_key: Vec<u8>,
 }
 
+/// Prints the public key fingerprint, never the secret key
+impl fmt::Debug for KyberKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KyberKeyPair")
+            .field("fingerprint", &crate::envelope::key_fingerprint(&self.public_key))
+            .field("secret_key", &format_args!("[redacted; {} bytes]", self.secret_key.len()))
+            .finish()
+    }
+}
+
+/// liboqs's descriptor for one parameter set, created on first use and kept for the process
+#[cfg(feature = "liboqs")]
+struct RawKem(NonNull<oqs_sys::kem::OQS_KEM>);
+
+/// Stands in for the descriptor without liboqs, where [`raw_backend`] always fails
+#[cfg(not(feature = "liboqs"))]
+enum RawKem {}
+
+// SAFETY: liboqs never mutates a descriptor after `OQS_KEM_new` returns it
+#[cfg(feature = "liboqs")]
+unsafe impl Send for RawKem {}
+#[cfg(feature = "liboqs")]
+unsafe impl Sync for RawKem {}
+
+/// The descriptor every operation calls through, so they allocate nothing per call
+#[cfg(feature = "liboqs")]
+fn raw_backend(algorithm: KemAlgorithm, error: impl FnOnce(String) -> PqcError) -> Result<&'static RawKem> {
+    // One slot per variant, indexed by discriminant
+    static DESCRIPTORS: [OnceLock<Option<RawKem>>; 6] = [const { OnceLock::new() }; 6];
+    crate::backend::init()?;
+    DESCRIPTORS[algorithm as usize]
+        // SAFETY: `to_id` is a static NUL-terminated method name
+        .get_or_init(|| NonNull::new(unsafe { oqs_sys::kem::OQS_KEM_new(algorithm.oqs().to_id()) }).map(RawKem))
+        .as_ref()
+        .ok_or_else(|| error(format!("{} is not enabled in the linked liboqs", algorithm)))
+}
+
+#[cfg(not(feature = "liboqs"))]
+fn raw_backend(_: KemAlgorithm, _: impl FnOnce(String) -> PqcError) -> Result<&'static RawKem> {
+    Err(crate::backend::unavailable())
+}
+
+/// Every buffer passed in must have exactly the parameter set's size
+#[cfg(feature = "liboqs")]
+impl RawKem {
+    /// `(public key, ciphertext)` lengths liboqs reports
+    fn lengths(&self) -> (usize, usize) {
+        // SAFETY: descriptors live for the rest of the process
+        let kem = unsafe { self.0.as_ref() };
+        (kem.length_public_key, kem.length_ciphertext)
+    }
+
+    unsafe fn keypair(&self, public_key: &mut [u8], secret_key: &mut [u8]) -> Result<()> {
+        let status = oqs_sys::kem::OQS_KEM_keypair(self.0.as_ptr(), public_key.as_mut_ptr(), secret_key.as_mut_ptr());
+        crate::backend::check_status(status, PqcError::KeyGeneration)
+    }
+
+    unsafe fn encapsulate(&self, public_key: &[u8], ciphertext: &mut [u8], shared_secret: &mut [u8]) -> Result<()> {
+        let status = oqs_sys::kem::OQS_KEM_encaps(self.0.as_ptr(), ciphertext.as_mut_ptr(), shared_secret.as_mut_ptr(), public_key.as_ptr());
+        crate::backend::check_status(status, PqcError::Encryption)
+    }
+
+    unsafe fn decapsulate(&self, secret_key: &[u8], ciphertext: &[u8], shared_secret: &mut [u8]) -> Result<()> {
+        let status = oqs_sys::kem::OQS_KEM_decaps(self.0.as_ptr(), shared_secret.as_mut_ptr(), ciphertext.as_ptr(), secret_key.as_ptr());
+        crate::backend::check_status(status, PqcError::Decryption)
+    }
+}
+
+#[cfg(not(feature = "liboqs"))]
+impl RawKem {
+    fn lengths(&self) -> (usize, usize) {
+        match *self {}
+    }
+
+    unsafe fn keypair(&self, _: &mut [u8], _: &mut [u8]) -> Result<()> {
+        match *self {}
+    }
+
+    unsafe fn encapsulate(&self, _: &[u8], _: &mut [u8], _: &mut [u8]) -> Result<()> {
+        match *self {}
+    }
+
+    unsafe fn decapsulate(&self, _: &[u8], _: &[u8], _: &mut [u8]) -> Result<()> {
+        match *self {}
+    }
+}
+
 pub fn generate_keypair() -> Result<KyberKeyPair> {
-    let kem = Kem::new(Algorithm::Kyber1024)
-        .map_err(|e| PqcError::KeyGeneration(format!("{:?}", e)))?;
-    
-    let (pk, sk) = kem.keypair()
-        .map_err(|e| PqcError::KeyGeneration(format!("{:?}", e)))?;
-    
-    Ok(KyberKeyPair {
-        public_key: pk.into_vec(),
-        secret_key: sk.into_vec(),
-    })
+    generate_keypair_with(KemAlgorithm::Kyber1024)
+}
+
+pub fn generate_keypair_with(algorithm: KemAlgorithm) -> Result<KyberKeyPair> {
+    let sizes = algorithm.sizes();
+    let kem = raw_backend(algorithm, PqcError::KeyGeneration)?;
+    let mut keypair = KyberKeyPair { public_key: vec![0; sizes.public_key], secret_key: vec![0; sizes.secret_key] };
+    // SAFETY: both buffers have the parameter set's sizes
+    unsafe { kem.keypair(&mut keypair.public_key, &mut keypair.secret_key) }?;
+    Ok(keypair)
+}
+
+/// Generate `n` keypairs in parallel
+pub fn generate_keypairs(n: usize) -> Result<Vec<KyberKeyPair>> {
+    generate_keypairs_with_progress(n, |_, _| {})
+}
+
+/// Generate `n` keypairs in parallel, reporting `(completed, total)` progress
+pub fn generate_keypairs_with_progress<P>(n: usize, progress: P) -> Result<Vec<KyberKeyPair>>
+where
+    P: Fn(usize, usize) + Sync,
+{
+    crate::pool::run_parallel(n, |_| generate_keypair(), progress)
+}
+
+/// Generate up to `n` keypairs in parallel, stopping early once `token` is cancelled
+pub fn generate_keypairs_cancellable(n: usize, token: &crate::cancel::CancelToken) -> crate::cancel::Partial<KyberKeyPair> {
+    crate::pool::run_parallel_cancellable(n, |_| generate_keypair(), |_, _| {}, token)
 }
 
 pub fn encapsulate(public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
-    let kem = Kem::new(Algorithm::Kyber1024)
-        .map_err(|e| PqcError::Encryption(format!("{:?}", e)))?;
-    
-    // Convert byte slice to PublicKeyRef using the kem method
-    let pk_ref = kem.public_key_from_bytes(public_key)
-        .ok_or_else(|| PqcError::Encryption("Invalid public key length".to_string()))?;
-    
-    let (ciphertext, shared_secret) = kem.encapsulate(pk_ref)
-        .map_err(|e| PqcError::Encryption(format!("{:?}", e)))?;
-    
-    Ok((ciphertext.into_vec(), shared_secret.into_vec()))
+    encapsulate_with(KemAlgorithm::Kyber1024, public_key)
+}
+
+pub fn encapsulate_with(algorithm: KemAlgorithm, public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let sizes = algorithm.sizes();
+    let mut ciphertext = vec![0; sizes.ciphertext];
+    let mut shared_secret = vec![0; sizes.shared_secret];
+    encapsulate_into_with(algorithm, public_key, &mut ciphertext, &mut shared_secret)?;
+    Ok((ciphertext, shared_secret))
 }
 
 pub fn decapsulate(secret_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
-    let kem = Kem::new(Algorithm::Kyber1024)
-        .map_err(|e| PqcError::Decryption(format!("{:?}", e)))?;
-    
-    // Convert byte slices to SecretKeyRef and CiphertextRef using kem methods
-    let sk_ref = kem.secret_key_from_bytes(secret_key)
-        .ok_or_else(|| PqcError::Decryption("Invalid secret key length".to_string()))?;
-    let ct_ref = kem.ciphertext_from_bytes(ciphertext)
-        .ok_or_else(|| PqcError::Decryption("Invalid ciphertext length".to_string()))?;
-    
-    let shared_secret = kem.decapsulate(sk_ref, ct_ref)
-        .map_err(|e| PqcError::Decryption(format!("{:?}", e)))?;
-    
-    Ok(shared_secret.into_vec())
+    decapsulate_with(KemAlgorithm::Kyber1024, secret_key, ciphertext)
+}
+
+pub fn decapsulate_with(algorithm: KemAlgorithm, secret_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let mut shared_secret = vec![0; SHARED_SECRET_LEN];
+    decapsulate_into_with(algorithm, secret_key, ciphertext, &mut shared_secret)?;
+    Ok(shared_secret)
+}
+
+/// [`encapsulate`] into caller-provided buffers
+pub fn encapsulate_into(public_key: &[u8], ciphertext_out: &mut [u8], shared_secret_out: &mut [u8]) -> Result<()> {
+    encapsulate_into_with(KemAlgorithm::Kyber1024, public_key, ciphertext_out, shared_secret_out)
+}
+
+/// Encapsulate into buffers of exactly `algorithm.sizes()` bytes
+///
+/// Sizes are checked before any work is done. liboqs writes straight into
+/// the buffers; after the parameter set's first use nothing is allocated.
+pub fn encapsulate_into_with(algorithm: KemAlgorithm, public_key: &[u8], ciphertext_out: &mut [u8], shared_secret_out: &mut [u8]) -> Result<()> {
+    let sizes = algorithm.sizes();
+    check_buffer("Public key", public_key.len(), sizes.public_key, PqcError::Encryption)?;
+    check_buffer("Ciphertext buffer", ciphertext_out.len(), sizes.ciphertext, PqcError::Encryption)?;
+    check_buffer("Shared secret buffer", shared_secret_out.len(), sizes.shared_secret, PqcError::Encryption)?;
+    let kem = raw_backend(algorithm, PqcError::Encryption)?;
+    // SAFETY: every buffer was checked against the parameter set's sizes above
+    unsafe { kem.encapsulate(public_key, ciphertext_out, shared_secret_out) }
+}
+
+/// [`decapsulate`] into a caller-provided buffer
+pub fn decapsulate_into(secret_key: &[u8], ciphertext: &[u8], shared_secret_out: &mut [u8]) -> Result<()> {
+    decapsulate_into_with(KemAlgorithm::Kyber1024, secret_key, ciphertext, shared_secret_out)
+}
+
+/// Decapsulate into a buffer of exactly [`SHARED_SECRET_LEN`] bytes, without allocating
+pub fn decapsulate_into_with(algorithm: KemAlgorithm, secret_key: &[u8], ciphertext: &[u8], shared_secret_out: &mut [u8]) -> Result<()> {
+    let sizes = algorithm.sizes();
+    check_buffer("Secret key", secret_key.len(), sizes.secret_key, PqcError::Decryption)?;
+    check_buffer("Ciphertext", ciphertext.len(), sizes.ciphertext, PqcError::Decryption)?;
+    check_buffer("Shared secret buffer", shared_secret_out.len(), sizes.shared_secret, PqcError::Decryption)?;
+    let kem = raw_backend(algorithm, PqcError::Decryption)?;
+    // SAFETY: every buffer was checked against the parameter set's sizes above
+    unsafe { kem.decapsulate(secret_key, ciphertext, shared_secret_out) }
+}
+
+fn check_buffer(what: &str, len: usize, expected: usize, error: fn(String) -> PqcError) -> Result<()> {
+    if len != expected {
+        return Err(error(format!("{} is {} bytes, expected {}", what, len, expected)));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "liboqs")]
+    #[test]
+    fn test_oqs_conversion_roundtrip() {
+        for &algorithm in KemAlgorithm::all() {
+            assert_eq!(KemAlgorithm::try_from(Algorithm::from(algorithm)).unwrap(), algorithm);
+        }
+    }
+
     #[test]
     fn test_kyber_roundtrip() {
         let keypair = generate_keypair().unwrap();
@@ -61,4 +354,42 @@ mod tests {
         
         assert_eq!(shared_secret_sender, shared_secret_receiver);
     }
+
+    #[test]
+    fn test_sizes_match_backend_and_into_roundtrip() {
+        for &algorithm in KemAlgorithm::all() {
+            let sizes = algorithm.sizes();
+            assert_eq!(sizes.public_key, algorithm.public_key_len().unwrap());
+            assert_eq!(sizes.ciphertext, algorithm.ciphertext_len().unwrap());
+        }
+
+        let keypair = generate_keypair_with(KemAlgorithm::MlKem768).unwrap();
+        let mut ciphertext = [0u8; KemAlgorithm::MlKem768.sizes().ciphertext];
+        let mut sent = [0u8; SHARED_SECRET_LEN];
+        let mut received = [0u8; SHARED_SECRET_LEN];
+        encapsulate_into_with(KemAlgorithm::MlKem768, &keypair.public_key, &mut ciphertext, &mut sent).unwrap();
+        decapsulate_into_with(KemAlgorithm::MlKem768, &keypair.secret_key, &ciphertext, &mut received).unwrap();
+        assert_eq!(sent, received);
+        assert_eq!(decapsulate_with(KemAlgorithm::MlKem768, &keypair.secret_key, &ciphertext).unwrap(), sent);
+        assert!(encapsulate_into_with(KemAlgorithm::MlKem768, &keypair.public_key, &mut ciphertext[..10], &mut sent).is_err());
+        assert!(decapsulate_into_with(KemAlgorithm::MlKem768, &keypair.secret_key[..10], &ciphertext, &mut received).is_err());
+    }
+
+    #[test]
+    fn test_generate_keypairs() {
+        let keypairs = generate_keypairs(4).unwrap();
+        assert_eq!(keypairs.len(), 4);
+        assert_ne!(keypairs[0].public_key, keypairs[1].public_key);
+    }
+
+    #[test]
+    fn test_kyber512_roundtrip() {
+        let keypair = generate_keypair_with(KemAlgorithm::Kyber512).unwrap();
+        let (ciphertext, sent) = encapsulate_with(KemAlgorithm::Kyber512, &keypair.public_key).unwrap();
+        assert_eq!(decapsulate_with(KemAlgorithm::Kyber512, &keypair.secret_key, &ciphertext).unwrap(), sent);
+        
+        // Keys of one parameter set are rejected by another
+        assert!(encapsulate(&keypair.public_key).is_err());
+        assert_eq!("kyber768".parse::<KemAlgorithm>().unwrap(), KemAlgorithm::Kyber768);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,222 @@
+//! Cache of Dilithium verification keys keyed by fingerprint
+//!
+//! Hot devices authenticate many times per minute; caching their validated
+//! public keys skips the length check and fingerprint hash on every request.
+//! Verification goes through `sign`'s per-algorithm liboqs descriptor, which
+//! is opened once per process rather than once per call.
+//! Entries expire after a TTL and the least recently used entry is evicted
+//! once the cache is full.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::api::{self, AuthRequest};
+use crate::cancel::{CancelToken, Partial};
+use crate::envelope::{key_fingerprint, SignedEnvelope};
+use crate::error::{PqcError, Result};
+use crate::sign::{self, SigAlgorithm};
+use crate::pool;
+
+/// Default lifetime of a cached key
+pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Default maximum number of cached keys
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// A Dilithium public key whose length has been checked and fingerprint computed
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerificationKey {
+    fingerprint: String,
+    public_key: Vec<u8>,
+}
+
+impl VerificationKey {
+    pub fn new(public_key: &[u8]) -> Result<Self> {
+        let expected = SigAlgorithm::Dilithium3.public_key_len()
+            .map_err(|e| PqcError::InvalidKey(e.to_string()))?;
+        if public_key.len() != expected {
+            return Err(PqcError::InvalidKey(format!(
+                "Expected {} byte public key, got {}", expected, public_key.len()
+            )));
+        }
+        Ok(Self { fingerprint: key_fingerprint(public_key), public_key: public_key.to_vec() })
+    }
+
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<bool> {
+        sign::verify(message, signature, &self.public_key)
+    }
+
+    pub fn verify_auth_request(&self, request: &AuthRequest) -> Result<bool> {
+        api::verify_auth_request(request, &self.public_key)
+    }
+
+    pub fn verify_envelope(&self, envelope: &SignedEnvelope, expected_purpose: &str) -> Result<bool> {
+        envelope.verify(&self.public_key, expected_purpose)
+    }
+}
+
+struct CacheEntry {
+    key: Arc<VerificationKey>,
+    inserted: Instant,
+    last_used: Instant,
+}
+
+/// Thread-safe TTL + LRU cache of [`VerificationKey`]s
+pub struct VerificationKeyCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl Default for VerificationKeyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl VerificationKeyCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self { ttl, max_entries: max_entries.max(1), entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Validate and cache a public key, returning the shared entry
+    pub fn insert(&self, public_key: &[u8]) -> Result<Arc<VerificationKey>> {
+        let key = Arc::new(VerificationKey::new(public_key)?);
+        let now = Instant::now();
+        let mut entries = self.lock();
+
+        if !entries.contains_key(&key.fingerprint) && entries.len() >= self.max_entries {
+            entries.retain(|_, e| now.duration_since(e.inserted) < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter()
+                    .min_by_key(|(_, e)| e.last_used)
+                    .map(|(fp, _)| fp.clone());
+                if let Some(fp) = oldest {
+                    entries.remove(&fp);
+                }
+            }
+        }
+
+        entries.insert(key.fingerprint.clone(), CacheEntry { key: Arc::clone(&key), inserted: now, last_used: now });
+        Ok(key)
+    }
+
+    /// Look up a key by fingerprint; expired entries are dropped
+    pub fn get(&self, fingerprint: &str) -> Option<Arc<VerificationKey>> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        let entry = entries.get_mut(fingerprint)?;
+        if now.duration_since(entry.inserted) >= self.ttl {
+            entries.remove(fingerprint);
+            return None;
+        }
+        entry.last_used = now;
+        Some(Arc::clone(&entry.key))
+    }
+
+    /// Cached key for `public_key`, inserting it on a miss
+    pub fn get_or_insert(&self, public_key: &[u8]) -> Result<Arc<VerificationKey>> {
+        match self.get(&key_fingerprint(public_key)) {
+            Some(key) => Ok(key),
+            None => self.insert(public_key),
+        }
+    }
+
+    pub fn remove(&self, fingerprint: &str) -> bool {
+        self.lock().remove(fingerprint).is_some()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Verify auth requests in parallel against cached keys
+    ///
+    /// Each item names the signer by fingerprint. A fingerprint missing from
+    /// the cache yields an `InvalidKey` error for that item only.
+    pub fn verify_auth_batch(&self, requests: &[(&str, &AuthRequest)]) -> Vec<Result<bool>> {
+        // Jobs never fail and the token never fires, so every slot is filled
+        self.verify_auth_batch_cancellable(requests, &CancelToken::new()).results.into_iter().flatten().collect()
+    }
+
+    /// [`VerificationKeyCache::verify_auth_batch`] that stops early once `token` is cancelled
+    ///
+    /// Slots for requests never verified are `None`.
+    pub fn verify_auth_batch_cancellable(&self, requests: &[(&str, &AuthRequest)], token: &CancelToken) -> Partial<Result<bool>> {
+        pool::run_parallel_cancellable(requests.len(), |i| {
+            let (fingerprint, request) = requests[i];
+            // Per-item errors are carried in the inner results
+            Ok(match self.get(fingerprint) {
+                Some(key) => key.verify_auth_request(request),
+                None => Err(PqcError::InvalidKey(format!("No cached key for {}", fingerprint))),
+            })
+        }, |_, _| {}, token)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CacheEntry>> {
+        self.entries.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_ttl_and_eviction() {
+        let keys: Vec<_> = (0..3).map(|_| sign::generate_keypair().unwrap()).collect();
+        let cache = VerificationKeyCache::new(Duration::from_secs(60), 2);
+
+        let a = cache.insert(&keys[0].public_key).unwrap();
+        let b = cache.insert(&keys[1].public_key).unwrap();
+        assert!(cache.get(a.fingerprint()).is_some());
+
+        // `b` is now least recently used and makes room for the third key
+        cache.insert(&keys[2].public_key).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(b.fingerprint()).is_none());
+        assert!(cache.get(a.fingerprint()).is_some());
+
+        let short = VerificationKeyCache::new(Duration::ZERO, 2);
+        let key = short.insert(&keys[0].public_key).unwrap();
+        assert!(short.get(key.fingerprint()).is_none());
+
+        assert!(matches!(cache.insert(b"short"), Err(PqcError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_verify_auth_batch() {
+        let device = sign::generate_keypair().unwrap();
+        let cache = VerificationKeyCache::default();
+        let key = cache.insert(&device.public_key).unwrap();
+
+        let good = api::create_auth_request("device-1", b"n1", &device.secret_key).unwrap();
+        let mut bad = api::create_auth_request("device-1", b"n2", &device.secret_key).unwrap();
+        bad.nonce = b"n3".to_vec();
+
+        let results = cache.verify_auth_batch(&[
+            (key.fingerprint(), &good),
+            (key.fingerprint(), &bad),
+            ("unknown", &good),
+        ]);
+        assert!(results[0].as_ref().unwrap());
+        assert!(!results[1].as_ref().unwrap());
+        assert!(matches!(results[2], Err(PqcError::InvalidKey(_))));
+    }
+}
@@ -0,0 +1,219 @@
+//! Compact encoding of Kyber / ML-KEM public keys for bulk storage
+//!
+//! A Kyber or ML-KEM public key is `t || rho`. `rho` is the 32-byte seed
+//! that expands to the public matrix and is already stored as a seed. `t` is
+//! `k` polynomials of 256 coefficients mod 3329 in 12 bits each, though a
+//! coefficient only carries log2(3329) ≈ 11.7 bits of information. The
+//! compressed form is `rho || t` with each polynomial packed in radix 3329:
+//! 375 bytes instead of 384. A Kyber1024 key shrinks from 1568 to 1532 bytes.
+//! Across a million-device registry that saves about 36 MB. Decompression
+//! gives back the exact original bytes.
+//!
+//! Dilithium and ML-DSA public keys gain nothing here: `t1` uses every value
+//! of its 10-bit fields and the rest of the key is already a seed. Store
+//! those keys as-is.
+
+use crate::error::{PqcError, Result};
+use crate::kem::KemAlgorithm;
+
+const Q: u128 = 3329;
+const N: usize = 256;
+const RHO_LEN: usize = 32;
+const POLY_BYTES: usize = 384;
+const PACKED_POLY_BYTES: usize = 375;
+
+/// Coefficients packed per radix-3329 group, and the bits each group takes
+///
+/// 3329^7 < 2^82, so seven coefficients fit in 82 bits rather than 84. The
+/// last four coefficients of a polynomial fit in 47 bits.
+const GROUP: usize = 7;
+const GROUP_BITS: u32 = 82;
+const TAIL_BITS: u32 = 47;
+
+/// Compressed length of a public key for `algorithm`
+pub fn compressed_len(algorithm: KemAlgorithm) -> usize {
+    RHO_LEN + rank(algorithm) * PACKED_POLY_BYTES
+}
+
+/// Pack a public key as `rho || packed t`
+pub fn compress_kem_public_key(algorithm: KemAlgorithm, public_key: &[u8]) -> Result<Vec<u8>> {
+    let k = rank(algorithm);
+    if public_key.len() != k * POLY_BYTES + RHO_LEN {
+        return Err(PqcError::InvalidKey(format!("{} public key must be {} bytes", algorithm, k * POLY_BYTES + RHO_LEN)));
+    }
+    let (t, rho) = public_key.split_at(k * POLY_BYTES);
+
+    let mut compressed = rho.to_vec();
+    for poly in t.chunks(POLY_BYTES) {
+        let coefficients = decode_12(poly)
+            .ok_or_else(|| PqcError::InvalidKey(format!("Not a canonical {} public key", algorithm)))?;
+        compressed.extend_from_slice(&pack(&coefficients));
+    }
+    Ok(compressed)
+}
+
+/// Restore the exact public key packed by [`compress_kem_public_key`]
+pub fn decompress_kem_public_key(algorithm: KemAlgorithm, compressed: &[u8]) -> Result<Vec<u8>> {
+    if compressed.len() != compressed_len(algorithm) {
+        return Err(PqcError::InvalidKey(format!("Compressed {} public key must be {} bytes", algorithm, compressed_len(algorithm))));
+    }
+    let (rho, packed) = compressed.split_at(RHO_LEN);
+
+    let mut public_key = Vec::with_capacity(rank(algorithm) * POLY_BYTES + RHO_LEN);
+    for poly in packed.chunks(PACKED_POLY_BYTES) {
+        let coefficients = unpack(poly)
+            .ok_or_else(|| PqcError::InvalidKey(format!("Malformed compressed {} public key", algorithm)))?;
+        public_key.extend_from_slice(&encode_12(&coefficients));
+    }
+    public_key.extend_from_slice(rho);
+    Ok(public_key)
+}
+
+/// Number of polynomials in `t`
+fn rank(algorithm: KemAlgorithm) -> usize {
+    match algorithm {
+        KemAlgorithm::Kyber512 | KemAlgorithm::MlKem512 => 2,
+        KemAlgorithm::Kyber768 | KemAlgorithm::MlKem768 => 3,
+        KemAlgorithm::Kyber1024 | KemAlgorithm::MlKem1024 => 4,
+    }
+}
+
+/// FIPS 203 ByteDecode_12; `None` if a coefficient is not below q
+fn decode_12(bytes: &[u8]) -> Option<[u16; N]> {
+    let mut coefficients = [0u16; N];
+    for (pair, chunk) in coefficients.chunks_mut(2).zip(bytes.chunks(3)) {
+        pair[0] = u16::from(chunk[0]) | (u16::from(chunk[1] & 0x0f) << 8);
+        pair[1] = u16::from(chunk[1] >> 4) | (u16::from(chunk[2]) << 4);
+    }
+    coefficients.iter().all(|&c| u128::from(c) < Q).then_some(coefficients)
+}
+
+/// FIPS 203 ByteEncode_12
+fn encode_12(coefficients: &[u16; N]) -> Vec<u8> {
+    coefficients.chunks(2)
+        .flat_map(|pair| [pair[0] as u8, ((pair[0] >> 8) | (pair[1] << 4)) as u8, (pair[1] >> 4) as u8])
+        .collect()
+}
+
+fn pack(coefficients: &[u16; N]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    for group in coefficients.chunks(GROUP) {
+        let value = group.iter().rev().fold(0u128, |acc, &c| acc * Q + u128::from(c));
+        writer.write(value, if group.len() == GROUP { GROUP_BITS } else { TAIL_BITS });
+    }
+    writer.finish()
+}
+
+/// Inverse of [`pack`]; `None` for values no key could have produced
+fn unpack(bytes: &[u8]) -> Option<[u16; N]> {
+    let mut reader = BitReader { bytes, acc: 0, bits: 0 };
+    let mut coefficients = [0u16; N];
+    for group in coefficients.chunks_mut(GROUP) {
+        let mut value = reader.read(if group.len() == GROUP { GROUP_BITS } else { TAIL_BITS });
+        for c in group.iter_mut() {
+            *c = (value % Q) as u16;
+            value /= Q;
+        }
+        if value != 0 {
+            return None;
+        }
+    }
+    // Only the padding bit of the last byte is left, and it must be zero
+    (reader.acc == 0 && reader.bytes.is_empty()).then_some(coefficients)
+}
+
+/// Little-endian bit packing
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u128,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u128, bits: u32) {
+        self.acc |= value << self.bits;
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    acc: u128,
+    bits: u32,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: u32) -> u128 {
+        while self.bits < bits {
+            if let Some((&byte, rest)) = self.bytes.split_first() {
+                self.acc |= u128::from(byte) << self.bits;
+                self.bytes = rest;
+            }
+            self.bits += 8;
+        }
+        let value = self.acc & ((1u128 << bits) - 1);
+        self.acc >>= bits;
+        self.bits -= bits;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    /// A well-formed key: random coefficients below q, including q - 1, and a random seed
+    fn public_key(algorithm: KemAlgorithm) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        let mut key = Vec::new();
+        for _ in 0..rank(algorithm) {
+            let mut coefficients = [0u16; N];
+            coefficients.iter_mut().for_each(|c| *c = rng.gen_range(0..Q as u16));
+            coefficients[0] = Q as u16 - 1;
+            key.extend_from_slice(&encode_12(&coefficients));
+        }
+        key.extend_from_slice(&rng.gen::<[u8; RHO_LEN]>());
+        key
+    }
+
+    #[test]
+    fn test_compression_roundtrip() {
+        for &algorithm in KemAlgorithm::all() {
+            let key = public_key(algorithm);
+            let compressed = compress_kem_public_key(algorithm, &key).unwrap();
+            assert_eq!(compressed.len(), compressed_len(algorithm));
+            assert!(compressed.len() < key.len());
+            assert_eq!(decompress_kem_public_key(algorithm, &compressed).unwrap(), key);
+        }
+        assert_eq!(compressed_len(KemAlgorithm::Kyber1024), 1532);
+    }
+
+    #[test]
+    fn test_rejects_non_canonical_keys() {
+        let algorithm = KemAlgorithm::MlKem768;
+        let mut key = public_key(algorithm);
+        key[0] = 0xff;
+        key[1] |= 0x0f;
+        assert!(matches!(compress_kem_public_key(algorithm, &key), Err(PqcError::InvalidKey(_))));
+        assert!(compress_kem_public_key(algorithm, &key[1..]).is_err());
+
+        let mut compressed = compress_kem_public_key(algorithm, &public_key(algorithm)).unwrap();
+        let last = compressed.len() - 1;
+        compressed[last] |= 0x80;
+        assert!(decompress_kem_public_key(algorithm, &compressed).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Opaque key handles for long-running servers
+//!
+//! Keys are registered once and then referred to by [`KeyId`]. Secret key
+//! bytes never leave the manager; every operation checks the key's kind
+//! and [`KeyPolicy`] first. The manager is `Sync` and is meant to be
+//! shared across worker threads behind an `Arc`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::api::{self, AuthRequest, EncryptedEntropy};
+use crate::envelope::{key_fingerprint, unix_now, SignedEnvelope};
+use crate::error::{ErrorContext, PqcError, Result, ResultExt};
+use crate::lifecycle::{self, LifecycleEvent, LifecycleEventKind};
+use crate::kem::KemAlgorithm;
+use crate::sign::SigAlgorithm;
+use crate::{clock, kem, sign, DilithiumKeyPair, KyberKeyPair};
+
+/// Opaque handle to a key held by a [`KeyManager`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct KeyId(u64);
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key-{:016x}", self.0)
+    }
+}
+
+/// What a registered key can be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    /// Kyber keypair: encrypt to it, decrypt with it
+    Kyber,
+    /// Dilithium keypair: sign with it
+    Dilithium,
+}
+
+/// Restrictions enforced on every use of a key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyPolicy {
+    /// Maximum number of secret-key operations, if limited
+    pub max_uses: Option<u64>,
+    /// Unix time after which the key is refused, if any
+    pub not_after: Option<u64>,
+}
+
+impl KeyPolicy {
+    pub fn with_max_uses(mut self, max_uses: u64) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    pub fn with_not_after(mut self, not_after: u64) -> Self {
+        self.not_after = Some(not_after);
+        self
+    }
+}
+
+struct ManagedKey {
+    kind: KeyKind,
+    public_key: Vec<u8>,
+    secret_key: Zeroizing<Vec<u8>>,
+    policy: KeyPolicy,
+    uses: AtomicU64,
+}
+
+impl ManagedKey {
+    fn context(&self, operation: &str) -> ErrorContext {
+        ErrorContext::new(operation).with_key_fingerprint(&key_fingerprint(&self.public_key))
+    }
+}
+
+/// Registry of keys addressed by [`KeyId`]
+#[derive(Default)]
+pub struct KeyManager {
+    keys: RwLock<HashMap<KeyId, Arc<ManagedKey>>>,
+    next_id: AtomicU64,
+}
+
+impl KeyManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take ownership of a Kyber keypair
+    pub fn register_kyber(&self, keypair: KyberKeyPair, policy: KeyPolicy) -> KeyId {
+        self.insert(KeyKind::Kyber, keypair.public_key, keypair.secret_key, policy)
+    }
+
+    /// Take ownership of a Dilithium keypair
+    pub fn register_dilithium(&self, keypair: DilithiumKeyPair, policy: KeyPolicy) -> KeyId {
+        self.insert(KeyKind::Dilithium, keypair.public_key, keypair.secret_key, policy)
+    }
+
+    /// Generate a Kyber keypair inside the manager
+    pub fn generate_kyber(&self, policy: KeyPolicy) -> Result<KeyId> {
+        let keypair = kem::generate_keypair()?;
+        lifecycle::emit(LifecycleEvent::generated(&keypair.public_key, KemAlgorithm::Kyber1024.name()));
+        Ok(self.register_kyber(keypair, policy))
+    }
+
+    /// Generate a Dilithium keypair inside the manager
+    pub fn generate_dilithium(&self, policy: KeyPolicy) -> Result<KeyId> {
+        let keypair = sign::generate_keypair()?;
+        lifecycle::emit(LifecycleEvent::generated(&keypair.public_key, SigAlgorithm::Dilithium3.name()));
+        Ok(self.register_dilithium(keypair, policy))
+    }
+
+    /// Drop a key; its secret bytes are zeroized once in-flight operations finish
+    pub fn remove(&self, id: KeyId) -> bool {
+        let Some(key) = self.keys.write().unwrap_or_else(|p| p.into_inner()).remove(&id) else {
+            return false;
+        };
+        lifecycle::emit(LifecycleEvent::new(LifecycleEventKind::Destroyed, &key_fingerprint(&key.public_key))
+            .with_detail(&format!("{} removed from key manager", id)));
+        true
+    }
+
+    pub fn contains(&self, id: KeyId) -> bool {
+        self.keys.read().unwrap_or_else(|p| p.into_inner()).contains_key(&id)
+    }
+
+    pub fn kind(&self, id: KeyId) -> Result<KeyKind> {
+        Ok(self.get(id)?.kind)
+    }
+
+    pub fn public_key(&self, id: KeyId) -> Result<Vec<u8>> {
+        Ok(self.get(id)?.public_key.clone())
+    }
+
+    /// Number of secret-key operations performed so far
+    pub fn use_count(&self, id: KeyId) -> Result<u64> {
+        Ok(self.get(id)?.uses.load(Ordering::Relaxed))
+    }
+
+    /// Encrypt entropy to a registered Kyber public key
+    pub fn encrypt_entropy(&self, id: KeyId, entropy: &[u8]) -> Result<EncryptedEntropy> {
+        let key = self.get_kind(id, KeyKind::Kyber)?;
+        api::encrypt_entropy_for_device(entropy, &key.public_key).context(|| key.context("encrypt entropy"))
+    }
+
+    /// Decrypt entropy with a registered Kyber secret key
+    pub fn decrypt_entropy(&self, id: KeyId, encrypted: &EncryptedEntropy) -> Result<Vec<u8>> {
+        let key = self.use_secret(id, KeyKind::Kyber)?;
+        api::decrypt_entropy(encrypted, &key.secret_key).context(|| key.context("decrypt entropy"))
+    }
+
+    /// Recover a Kyber shared secret
+    pub fn decapsulate(&self, id: KeyId, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let key = self.use_secret(id, KeyKind::Kyber)?;
+        kem::decapsulate(&key.secret_key, ciphertext).map(Zeroizing::new).context(|| key.context("decapsulate"))
+    }
+
+    /// Sign a raw message with a registered Dilithium key
+    pub fn sign(&self, id: KeyId, message: &[u8]) -> Result<Vec<u8>> {
+        let key = self.use_secret(id, KeyKind::Dilithium)?;
+        sign::sign(message, &key.secret_key).context(|| key.context("sign"))
+    }
+
+    /// Sign a payload into a [`SignedEnvelope`]
+    pub fn sign_envelope(&self, id: KeyId, payload: &[u8], purpose: &str) -> Result<SignedEnvelope> {
+        let key = self.use_secret(id, KeyKind::Dilithium)?;
+        SignedEnvelope::sign(payload, purpose, &key.secret_key, &key.public_key).context(|| key.context("sign envelope"))
+    }
+
+    /// Build a signed authentication request
+    pub fn create_auth_request(&self, id: KeyId, device_id: &str, nonce: &[u8]) -> Result<AuthRequest> {
+        let key = self.use_secret(id, KeyKind::Dilithium)?;
+        api::create_auth_request(device_id, nonce, &key.secret_key).context(|| key.context("create auth request"))
+    }
+
+    /// Verify an authentication request against a registered Dilithium key
+    pub fn verify_auth_request(&self, id: KeyId, request: &AuthRequest) -> Result<bool> {
+        let key = self.get_kind(id, KeyKind::Dilithium)?;
+        api::verify_auth_request(request, &key.public_key).context(|| key.context("verify auth request"))
+    }
+
+    fn insert(&self, kind: KeyKind, public_key: Vec<u8>, secret_key: Vec<u8>, policy: KeyPolicy) -> KeyId {
+        let id = KeyId(self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let key = ManagedKey {
+            kind,
+            public_key,
+            secret_key: Zeroizing::new(secret_key),
+            policy,
+            uses: AtomicU64::new(0),
+        };
+        self.keys.write().unwrap_or_else(|p| p.into_inner()).insert(id, Arc::new(key));
+        id
+    }
+
+    fn get(&self, id: KeyId) -> Result<Arc<ManagedKey>> {
+        self.keys.read().unwrap_or_else(|p| p.into_inner())
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| PqcError::InvalidKey(format!("Unknown key {}", id)))
+    }
+
+    fn get_kind(&self, id: KeyId, kind: KeyKind) -> Result<Arc<ManagedKey>> {
+        let key = self.get(id)?;
+        if key.kind != kind {
+            return Err(PqcError::KeyPolicy(format!("{} is a {:?} key, not {:?}", id, key.kind, kind)));
+        }
+        if key.policy.not_after.is_some_and(|t| clock::validity().is_expired(t, unix_now())) {
+            return Err(PqcError::KeyPolicy(format!("{} has expired", id)));
+        }
+        Ok(key)
+    }
+
+    /// Check policy and count one secret-key operation
+    fn use_secret(&self, id: KeyId, kind: KeyKind) -> Result<Arc<ManagedKey>> {
+        let key = self.get_kind(id, kind)?;
+        let max_uses = key.policy.max_uses.unwrap_or(u64::MAX);
+        key.uses
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < max_uses).then_some(n + 1))
+            .map_err(|_| PqcError::KeyPolicy(format!("{} reached its limit of {} uses", id, max_uses)))?;
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_by_key_id() {
+        let manager = KeyManager::new();
+        let kyber = manager.generate_kyber(KeyPolicy::default()).unwrap();
+        let dilithium = manager.generate_dilithium(KeyPolicy::default()).unwrap();
+
+        let encrypted = manager.encrypt_entropy(kyber, b"entropy").unwrap();
+        assert_eq!(manager.decrypt_entropy(kyber, &encrypted).unwrap(), b"entropy");
+
+        let request = manager.create_auth_request(dilithium, "gateway-01", b"nonce").unwrap();
+        assert!(manager.verify_auth_request(dilithium, &request).unwrap());
+        assert_eq!(manager.use_count(dilithium).unwrap(), 1);
+
+        // Kinds are not interchangeable
+        assert!(matches!(manager.sign(kyber, b"msg"), Err(PqcError::KeyPolicy(_))));
+
+        assert!(manager.remove(kyber));
+        assert!(matches!(manager.encrypt_entropy(kyber, b"x"), Err(PqcError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_policy_enforced() {
+        let manager = KeyManager::new();
+        let limited = manager.register_dilithium(sign::generate_keypair().unwrap(), KeyPolicy::default().with_max_uses(2));
+        manager.sign(limited, b"one").unwrap();
+        manager.sign(limited, b"two").unwrap();
+        assert!(matches!(manager.sign(limited, b"three"), Err(PqcError::KeyPolicy(_))));
+
+        let expired = manager.register_dilithium(sign::generate_keypair().unwrap(), KeyPolicy::default().with_not_after(1));
+        assert!(matches!(manager.sign(expired, b"late"), Err(PqcError::KeyPolicy(_))));
+    }
+}
@@ -0,0 +1,326 @@
+//! Wrapped delivery of server-generated device keys
+//!
+//! Devices that cannot generate keys (and so cannot use `csr`) still get
+//! their secrets from the service, but never in the clear. The service wraps
+//! the full [`DeviceCredentials`] under a key unique to the device: either a
+//! KEM public key burned in at the factory, or a pre-shared key. The AEAD key
+//! is derived over the device ID, suite and public keys, so a wrapped blob
+//! only opens for the device and metadata it was made for. The provisioning
+//! authority signs the whole blob, KEM ciphertext included, so a device only
+//! installs credentials the service made: anyone can encapsulate to a
+//! factory KEM public key, but not sign for the authority.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::api::DeviceCredentials;
+use crate::cert::CertificateAuthority;
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::lifecycle::{self, LifecycleEvent, LifecycleEventKind};
+use crate::secret::SecretKey;
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::{canonical, fips, kdf, kem};
+
+/// Shortest pre-shared transport key accepted
+pub const MIN_PSK_LEN: usize = 32;
+
+const SALT_LEN: usize = 32;
+
+/// Domain label for the authority's signature over wrapped credentials
+const WRAP_DOMAIN: &str = "PQC-Algo/v1/key-transport";
+
+/// Device-unique key the credentials are wrapped under
+pub enum TransportKey {
+    /// The device's factory KEM public key
+    Kem(Vec<u8>),
+    /// A secret shared with the device out of band, named by `id`
+    Psk { id: String, key: SecretKey },
+}
+
+/// How a [`WrappedCredentials`] key was established
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum TransportMethod {
+    Kem {
+        #[serde(with = "crate::util::bytes")]
+        kem_ciphertext: Vec<u8>,
+    },
+    Psk {
+        psk_id: String,
+        #[serde(with = "crate::util::bytes")]
+        salt: Vec<u8>,
+    },
+}
+
+/// Server-generated credentials, encrypted for one device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedCredentials {
+    pub device_id: String,
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kyber_public_key: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub dilithium_public_key: Vec<u8>,
+    pub transport: TransportMethod,
+    /// AEAD ciphertext of the full credentials, secrets included
+    #[serde(with = "crate::util::bytes")]
+    pub wrapped: Vec<u8>,
+    /// Algorithm of the authority signature
+    pub algorithm: SigAlgorithm,
+    pub signer_fingerprint: String,
+    /// Authority signature over every field above
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Metadata bound into the wrapping key
+#[derive(Serialize)]
+struct WrapFields<'a> {
+    device_id: &'a str,
+    suite: &'a CipherSuite,
+    kyber_public_key: String,
+    dilithium_public_key: String,
+    transport: &'a TransportMethod,
+}
+
+/// Fields of a [`WrappedCredentials`] covered by the authority signature
+#[derive(Serialize)]
+struct SignedFields<'a> {
+    #[serde(flatten)]
+    wrap: WrapFields<'a>,
+    wrapped: String,
+    algorithm: &'a str,
+    signer_fingerprint: &'a str,
+}
+
+/// Wrap `credentials` for `device_id` under the active default suite, signed by `authority`
+pub fn wrap_credentials(
+    device_id: &str,
+    credentials: &DeviceCredentials,
+    transport_key: &TransportKey,
+    authority: &CertificateAuthority,
+) -> Result<WrappedCredentials> {
+    wrap_credentials_with_suite(device_id, credentials, transport_key, authority, &CipherSuite::active_default())
+}
+
+/// [`wrap_credentials`] for credentials generated under `suite`
+///
+/// The credentials' keys must be `suite`'s, which also does the transport
+/// KEM and the AEAD.
+pub fn wrap_credentials_with_suite(
+    device_id: &str,
+    credentials: &DeviceCredentials,
+    transport_key: &TransportKey,
+    authority: &CertificateAuthority,
+    suite: &CipherSuite,
+) -> Result<WrappedCredentials> {
+    validate_device_id(device_id)?;
+    fips::check_suite(suite)?;
+    fips::check_sig(authority.algorithm)?;
+    validation::check_exact_len("Kyber public key", credentials.kyber_public_key.len(), suite.kem.public_key_len()?)?;
+    validation::check_exact_len("Dilithium public key", credentials.dilithium_public_key.len(), suite.sig.public_key_len()?)?;
+    let (transport, ikm) = match transport_key {
+        TransportKey::Kem(public_key) => {
+            let (kem_ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, public_key)?;
+            (TransportMethod::Kem { kem_ciphertext }, Zeroizing::new(shared_secret))
+        }
+        TransportKey::Psk { id, key } => {
+            check_psk(key)?;
+            let mut salt = vec![0u8; SALT_LEN];
+            rand::thread_rng().fill_bytes(&mut salt);
+            (TransportMethod::Psk { psk_id: id.clone(), salt }, Zeroizing::new(key.to_vec()))
+        }
+    };
+
+    let mut wrapped = WrappedCredentials {
+        device_id: device_id.to_string(),
+        suite: *suite,
+        kyber_public_key: credentials.kyber_public_key.clone(),
+        dilithium_public_key: credentials.dilithium_public_key.clone(),
+        transport,
+        wrapped: Vec::new(),
+        algorithm: authority.algorithm,
+        signer_fingerprint: authority.fingerprint(),
+        signature: Vec::new(),
+    };
+    let plaintext = Zeroizing::new(serde_json::to_vec(&credentials.expose_secret_serde())
+        .map_err(|e| PqcError::InvalidInput(format!("Failed to encode credentials: {}", e)))?);
+    wrapped.wrapped = suite.aead.seal(&wrapped.wrapping_key(&ikm)?, &plaintext)?;
+    wrapped.signature = authority.sign(&wrapped.signing_bytes()?)?;
+    let method = match &wrapped.transport {
+        TransportMethod::Kem { .. } => "wrapped under device KEM key",
+        TransportMethod::Psk { .. } => "wrapped under device PSK",
+    };
+    lifecycle::emit(LifecycleEvent::new(LifecycleEventKind::Exported, &key_fingerprint(&wrapped.dilithium_public_key))
+        .with_subject(device_id)
+        .with_detail(method));
+    Ok(wrapped)
+}
+
+impl WrappedCredentials {
+    /// Recover the credentials with the device's KEM secret key or PSK
+    ///
+    /// Fails unless `authority_public_key` signed the blob, if it was made
+    /// for other metadata or another key, or if the recovered keys don't
+    /// match the public keys it advertises.
+    pub fn unwrap(&self, transport_secret: &[u8], authority_public_key: &[u8]) -> Result<DeviceCredentials> {
+        fips::check_suite(&self.suite)?;
+        fips::check_sig(self.algorithm)?;
+        self.validate()?;
+        if !ct::ct_eq_hex(&self.signer_fingerprint, &key_fingerprint(authority_public_key)) {
+            return Err(PqcError::Verification("Wrapped credentials were signed by a different authority".to_string()));
+        }
+        if !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, authority_public_key)? {
+            return Err(PqcError::Verification("Wrapped credentials signature does not verify".to_string()));
+        }
+        let ikm = match &self.transport {
+            TransportMethod::Kem { kem_ciphertext } => {
+                Zeroizing::new(kem::decapsulate_with(self.suite.kem, transport_secret, kem_ciphertext)?)
+            }
+            TransportMethod::Psk { .. } => {
+                check_psk(transport_secret)?;
+                Zeroizing::new(transport_secret.to_vec())
+            }
+        };
+        let plaintext = Zeroizing::new(self.suite.aead.open(&self.wrapping_key(&ikm)?, &self.wrapped)?);
+        let credentials: DeviceCredentials = serde_json::from_slice(&plaintext)
+            .map_err(|e| PqcError::InvalidInput(format!("Malformed wrapped credentials: {}", e)))?;
+        if !ct::ct_eq(&credentials.kyber_public_key, &self.kyber_public_key)
+            || !ct::ct_eq(&credentials.dilithium_public_key, &self.dilithium_public_key)
+        {
+            return Err(PqcError::Verification("Wrapped credentials do not match their public keys".to_string()));
+        }
+        Ok(credentials)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode wrapped credentials: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "wrapped credentials")
+    }
+
+    fn wrap_fields(&self) -> WrapFields<'_> {
+        WrapFields {
+            device_id: &self.device_id,
+            suite: &self.suite,
+            kyber_public_key: hex::encode(&self.kyber_public_key),
+            dilithium_public_key: hex::encode(&self.dilithium_public_key),
+            transport: &self.transport,
+        }
+    }
+
+    fn wrapping_key(&self, ikm: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let salt = match &self.transport {
+            TransportMethod::Psk { salt, .. } => salt.as_slice(),
+            TransportMethod::Kem { .. } => &[],
+        };
+        let mut info = kdf::label("key-transport");
+        info.extend_from_slice(&canonical::to_canonical_json(&self.wrap_fields())?);
+        kdf::hkdf_sha256(ikm, salt, &info, self.suite.aead.key_len()).map(Zeroizing::new)
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(WRAP_DOMAIN, &SignedFields {
+            wrap: self.wrap_fields(),
+            wrapped: hex::encode(&self.wrapped),
+            algorithm: self.algorithm.name(),
+            signer_fingerprint: &self.signer_fingerprint,
+        })
+    }
+}
+
+impl Validate for WrappedCredentials {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_exact_len("Wrapped Kyber public key", self.kyber_public_key.len(), self.suite.kem.public_key_len()?)?;
+        validation::check_exact_len("Wrapped Dilithium public key", self.dilithium_public_key.len(), self.suite.sig.public_key_len()?)?;
+        match &self.transport {
+            TransportMethod::Kem { kem_ciphertext } => {
+                validation::check_exact_len("Transport KEM ciphertext", kem_ciphertext.len(), self.suite.kem.ciphertext_len()?)?
+            }
+            TransportMethod::Psk { psk_id, salt } => {
+                validation::check_max_len("Transport PSK ID", psk_id.len(), validation::MAX_LABEL_LEN)?;
+                validation::check_exact_len("Transport salt", salt.len(), SALT_LEN)?
+            }
+        }
+        validation::check_fingerprint("Signer fingerprint", &self.signer_fingerprint)?;
+        validation::check_signature_len("Wrapped credentials signature", self.algorithm, self.signature.len())?;
+        validation::check_sealed_len("Wrapped credentials", self.wrapped.len())
+    }
+}
+
+fn check_psk(key: &[u8]) -> Result<()> {
+    if key.len() < MIN_PSK_LEN {
+        return Err(PqcError::InvalidKey(format!(
+            "Transport PSK is {} bytes, need at least {}", key.len(), MIN_PSK_LEN
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kem_and_psk_round_trip() {
+        let authority = CertificateAuthority::generate("factory-ca").unwrap();
+        let credentials = DeviceCredentials::generate().unwrap();
+        let factory_key = kem::generate_keypair().unwrap();
+        let wrapped = wrap_credentials("meter-3", &credentials, &TransportKey::Kem(factory_key.public_key.clone()), &authority).unwrap();
+        let decoded = WrappedCredentials::from_bytes(&wrapped.to_bytes().unwrap()).unwrap();
+        let unwrapped = decoded.unwrap(&factory_key.secret_key, authority.public_key()).unwrap();
+        assert_eq!(unwrapped.dilithium_secret_key.expose_secret(), credentials.dilithium_secret_key.expose_secret());
+
+        let psk = TransportKey::Psk { id: "batch-12/meter-3".to_string(), key: vec![7u8; 32].into() };
+        let wrapped = wrap_credentials("meter-3", &credentials, &psk, &authority).unwrap();
+        let unwrapped = wrapped.unwrap(&[7u8; 32], authority.public_key()).unwrap();
+        assert_eq!(unwrapped.kyber_secret_key.expose_secret(), credentials.kyber_secret_key.expose_secret());
+        assert!(wrapped.unwrap(&[8u8; 32], authority.public_key()).is_err());
+    }
+
+    #[test]
+    fn test_wrapped_credentials_bind_metadata() {
+        let authority = CertificateAuthority::generate("factory-ca").unwrap();
+        let credentials = DeviceCredentials::generate().unwrap();
+        let psk = TransportKey::Psk { id: "meter-3".to_string(), key: vec![7u8; 32].into() };
+        let wrapped = wrap_credentials("meter-3", &credentials, &psk, &authority).unwrap();
+
+        let mut retargeted = wrapped.clone();
+        retargeted.device_id = "meter-4".to_string();
+        assert!(retargeted.unwrap(&[7u8; 32], authority.public_key()).is_err());
+
+        let short = TransportKey::Psk { id: "meter-3".to_string(), key: vec![7u8; 16].into() };
+        assert!(matches!(wrap_credentials("meter-3", &credentials, &short, &authority), Err(PqcError::InvalidKey(_))));
+        let other_suite = CipherSuite { kem: kem::KemAlgorithm::Kyber512, ..CipherSuite::DEFAULT };
+        assert!(wrap_credentials_with_suite("meter-3", &credentials, &psk, &authority, &other_suite).is_err());
+    }
+
+    #[test]
+    fn test_substituted_kem_ciphertext_is_rejected() {
+        let authority = CertificateAuthority::generate("factory-ca").unwrap();
+        let impostor = CertificateAuthority::generate("factory-ca").unwrap();
+        let factory_key = kem::generate_keypair().unwrap();
+        let transport_key = TransportKey::Kem(factory_key.public_key.clone());
+        let wrapped = wrap_credentials("meter-3", &DeviceCredentials::generate().unwrap(), &transport_key, &authority).unwrap();
+
+        // Anyone can encapsulate to the factory key and wrap credentials of their own
+        let planted = wrap_credentials("meter-3", &DeviceCredentials::generate().unwrap(), &transport_key, &impostor).unwrap();
+        assert!(matches!(planted.unwrap(&factory_key.secret_key, authority.public_key()), Err(PqcError::Verification(_))));
+        let mut spliced = planted.clone();
+        spliced.signer_fingerprint = wrapped.signer_fingerprint.clone();
+        spliced.signature = wrapped.signature.clone();
+        assert!(matches!(spliced.unwrap(&factory_key.secret_key, authority.public_key()), Err(PqcError::Verification(_))));
+
+        let mut swapped_ciphertext = wrapped.clone();
+        swapped_ciphertext.transport = planted.transport.clone();
+        assert!(matches!(swapped_ciphertext.unwrap(&factory_key.secret_key, authority.public_key()), Err(PqcError::Verification(_))));
+    }
+}
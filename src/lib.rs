@@ -1,11 +1,96 @@
 pub mod error;
+pub mod backend;
+pub mod clock;
 pub mod kem;
 pub mod sign;
+pub mod provider;
 pub mod binary_loader;
 pub mod qrng;
+pub mod conditioning;
+pub mod provenance;
+pub mod entropy_label;
+pub mod entropy_source;
+pub mod paths;
+pub mod params;
+pub mod pool;
+pub mod manifest;
+pub mod archive;
+pub mod seed_lifecycle;
+pub mod pool_state;
+pub mod derived;
+pub mod xof;
+pub mod kdf;
+pub mod key_compression;
+pub mod hash;
+pub mod envelope;
+pub mod extensions;
+pub mod cert;
+pub mod csr;
+pub mod attestation;
+pub mod key_transport;
+pub mod proxy;
+pub mod enrollment;
+pub mod lifecycle;
+pub mod policy;
+pub mod analysis;
+pub mod status;
+pub mod delegation;
+pub mod rollback;
+pub mod firmware;
+pub mod ceremony;
+pub mod canonical;
+pub mod porcelain;
+pub mod validation;
+pub mod transport;
+pub mod ble;
+pub mod key_manager;
+pub mod sign_queue;
+pub mod key_cache;
+pub mod registry;
+pub mod decap_cache;
+pub mod fleet_report;
+pub mod suite;
+pub mod negotiation;
+pub mod sig_migration;
+pub mod selftest;
+pub mod bench;
+pub mod health;
+pub mod fips;
+pub mod util;
+pub mod secret;
+pub mod backup;
+pub mod budget;
+pub mod cancel;
+pub mod mnemonic;
+pub mod sealed_box;
+pub mod config_push;
+pub mod log_upload;
+pub mod padding;
+pub mod deniable;
+pub mod factory_test;
+pub mod randomness_tests;
+pub mod demo;
+pub mod streaming;
+#[cfg(feature = "watch")]
+pub mod seed_watch;
+#[cfg(feature = "fec")]
+pub mod chunked;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "codec")]
+pub mod compat;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
-pub use error::{PqcError, Result};
-pub use kem::KyberKeyPair;
-pub use sign::DilithiumKeyPair; 
+pub use error::{ErrorContext, PqcError, Result, ResultExt};
+pub use backend::{backend_info, capabilities, BackendInfo, Capabilities};
+pub use kem::{KemAlgorithm, KyberKeyPair};
+pub use sign::{DilithiumKeyPair, SigAlgorithm, SignOptions, SigningMode};
 pub mod api;
-pub use api::{DeviceCredentials, EncryptedEntropy, AuthRequest};
\ No newline at end of file
+pub mod roles;
+pub use api::{DeviceCredentials, EncryptedEntropy, AuthRequest};
+pub use roles::{Authenticator, EntropyConsumer, EntropyProducer, Verifier};
+pub use secret::SecretKey;
+pub use key_manager::{KeyId, KeyManager, KeyPolicy};
+pub use suite::{CipherSuite, SecurityLevel};
+pub use selftest::{self_test, SelfTestReport};
\ No newline at end of file
@@ -0,0 +1,408 @@
+//! Key lifecycle events for central monitoring
+//!
+//! Key generation, export, rotation, revocation and destruction each emit a
+//! [`LifecycleEvent`] to every installed [`LifecycleSink`]. Generation is
+//! reported where a key becomes a credential (`DeviceCredentials`,
+//! `KeyManager`, `CertificateAuthority`), not by the raw `kem` and `sign`
+//! keygen, so throwaway keys in self tests and benchmarks stay silent. Sinks are
+//! process-wide, like FIPS mode: install them once at startup with
+//! [`add_sink`]. [`JsonLinesSink`] writes to any file or pipe;
+//! `SyslogSink` and `JournaldSink` (feature `syslog`, Unix only) send to the
+//! local syslog daemon and the systemd journal.
+//!
+//! Events never carry secret material. Delivery is best effort: a failing
+//! sink does not fail the key operation, but is counted in
+//! [`failed_deliveries`] so monitoring can alert on it.
+
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+
+/// What happened to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    Generated,
+    /// Secret material left its holder, e.g. as a backup or wrapped transport blob
+    Exported,
+    /// A new key took over from an old one
+    Rotated,
+    Revoked,
+    /// Secret material was dropped from a key store
+    Destroyed,
+}
+
+impl LifecycleEventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LifecycleEventKind::Generated => "key-generated",
+            LifecycleEventKind::Exported => "key-exported",
+            LifecycleEventKind::Rotated => "key-rotated",
+            LifecycleEventKind::Revoked => "key-revoked",
+            LifecycleEventKind::Destroyed => "key-destroyed",
+        }
+    }
+
+    /// Syslog severity: warning for events that widen exposure or end trust, notice otherwise
+    pub fn severity(&self) -> u8 {
+        match self {
+            LifecycleEventKind::Exported | LifecycleEventKind::Revoked => 4,
+            _ => 5,
+        }
+    }
+}
+
+impl fmt::Display for LifecycleEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One lifecycle event; identifies the key by public key fingerprint only
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub kind: LifecycleEventKind,
+    pub timestamp: u64,
+    /// Hex SHA-256 of the public key
+    pub fingerprint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>,
+    /// Device or certificate subject the key belongs to, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl LifecycleEvent {
+    pub fn new(kind: LifecycleEventKind, fingerprint: &str) -> Self {
+        Self {
+            kind,
+            timestamp: unix_now(),
+            fingerprint: fingerprint.to_string(),
+            algorithm: None,
+            subject: None,
+            detail: None,
+        }
+    }
+
+    /// A `Generated` event for `public_key`
+    pub(crate) fn generated(public_key: &[u8], algorithm: &str) -> Self {
+        Self::new(LifecycleEventKind::Generated, &key_fingerprint(public_key)).with_algorithm(algorithm)
+    }
+
+    pub fn with_algorithm(mut self, algorithm: &str) -> Self {
+        self.algorithm = Some(algorithm.to_string());
+        self
+    }
+
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    /// One-line human-readable summary
+    pub fn message(&self) -> String {
+        let mut message = format!("{} {}", self.kind, self.fingerprint);
+        if let Some(algorithm) = &self.algorithm {
+            message.push_str(&format!(" ({})", algorithm));
+        }
+        if let Some(subject) = &self.subject {
+            message.push_str(&format!(" for {}", subject));
+        }
+        if let Some(detail) = &self.detail {
+            message.push_str(&format!(": {}", detail));
+        }
+        message
+    }
+}
+
+/// Destination for lifecycle events
+pub trait LifecycleSink: Send + Sync {
+    fn record(&self, event: &LifecycleEvent) -> Result<()>;
+}
+
+/// Handle for removing a sink installed with [`add_sink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkId(u64);
+
+static SINKS: RwLock<Vec<(SinkId, Arc<dyn LifecycleSink>)>> = RwLock::new(Vec::new());
+static NEXT_SINK_ID: AtomicU64 = AtomicU64::new(0);
+static FAILED_DELIVERIES: AtomicU64 = AtomicU64::new(0);
+
+/// Send every future event to `sink` as well
+pub fn add_sink(sink: Arc<dyn LifecycleSink>) -> SinkId {
+    let id = SinkId(NEXT_SINK_ID.fetch_add(1, Ordering::Relaxed));
+    SINKS.write().unwrap_or_else(|p| p.into_inner()).push((id, sink));
+    id
+}
+
+pub fn remove_sink(id: SinkId) -> bool {
+    let mut sinks = SINKS.write().unwrap_or_else(|p| p.into_inner());
+    let before = sinks.len();
+    sinks.retain(|(sink_id, _)| *sink_id != id);
+    sinks.len() != before
+}
+
+/// Deliver `event` to every installed sink
+pub fn emit(event: LifecycleEvent) {
+    // Snapshot so a sink may add or remove sinks without deadlocking
+    let sinks: Vec<_> = SINKS.read().unwrap_or_else(|p| p.into_inner()).iter().map(|(_, sink)| sink.clone()).collect();
+    for sink in sinks {
+        if sink.record(&event).is_err() {
+            FAILED_DELIVERIES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Events any sink failed to record since startup
+pub fn failed_deliveries() -> u64 {
+    FAILED_DELIVERIES.load(Ordering::Relaxed)
+}
+
+/// Keeps events in memory, for tests and in-process dashboards
+#[derive(Default)]
+pub struct MemorySink {
+    events: Mutex<Vec<LifecycleEvent>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<LifecycleEvent> {
+        self.events.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+}
+
+impl LifecycleSink for MemorySink {
+    fn record(&self, event: &LifecycleEvent) -> Result<()> {
+        self.events.lock().unwrap_or_else(|p| p.into_inner()).push(event.clone());
+        Ok(())
+    }
+}
+
+/// Writes each event as one line of JSON, e.g. to a file a log shipper tails
+pub struct JsonLinesSink<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer: Mutex::new(writer) }
+    }
+}
+
+impl<W: Write + Send> LifecycleSink for JsonLinesSink<W> {
+    fn record(&self, event: &LifecycleEvent) -> Result<()> {
+        let mut line = serde_json::to_vec(event)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode lifecycle event: {}", e)))?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().unwrap_or_else(|p| p.into_inner());
+        writer.write_all(&line).and_then(|_| writer.flush())
+            .map_err(|e| PqcError::Io(format!("Failed to write lifecycle event: {}", e)))
+    }
+}
+
+#[cfg(all(unix, feature = "syslog"))]
+pub use self::unix_sinks::{JournaldSink, SyslogSink};
+
+#[cfg(all(unix, feature = "syslog"))]
+mod unix_sinks {
+    use std::os::unix::net::UnixDatagram;
+    use std::path::Path;
+    use super::{LifecycleEvent, LifecycleSink};
+    use crate::error::{PqcError, Result};
+
+    /// Syslog facility `authpriv`, for security-relevant messages
+    const FACILITY_AUTHPRIV: u8 = 10;
+
+    /// Structured-data ID, under the enterprise number reserved for documentation
+    const SD_ID: &str = "pqc@32473";
+
+    /// RFC 5424 messages to the local syslog daemon's datagram socket
+    pub struct SyslogSink {
+        socket: UnixDatagram,
+        app_name: String,
+    }
+
+    impl SyslogSink {
+        /// Connect to `/dev/log`
+        pub fn new(app_name: &str) -> Result<Self> {
+            Self::connect(Path::new("/dev/log"), app_name)
+        }
+
+        pub fn connect(path: &Path, app_name: &str) -> Result<Self> {
+            let socket = connect(path)?;
+            Ok(Self { socket, app_name: app_name.to_string() })
+        }
+    }
+
+    impl LifecycleSink for SyslogSink {
+        fn record(&self, event: &LifecycleEvent) -> Result<()> {
+            send(&self.socket, syslog_message(event, &self.app_name, std::process::id()).as_bytes())
+        }
+    }
+
+    /// systemd journal native protocol, with the event fields as `PQC_*` journal fields
+    pub struct JournaldSink {
+        socket: UnixDatagram,
+        identifier: String,
+    }
+
+    impl JournaldSink {
+        /// Connect to `/run/systemd/journal/socket`
+        pub fn new(identifier: &str) -> Result<Self> {
+            Self::connect(Path::new("/run/systemd/journal/socket"), identifier)
+        }
+
+        pub fn connect(path: &Path, identifier: &str) -> Result<Self> {
+            let socket = connect(path)?;
+            Ok(Self { socket, identifier: identifier.to_string() })
+        }
+    }
+
+    impl LifecycleSink for JournaldSink {
+        fn record(&self, event: &LifecycleEvent) -> Result<()> {
+            send(&self.socket, journal_message(event, &self.identifier).as_bytes())
+        }
+    }
+
+    fn connect(path: &Path) -> Result<UnixDatagram> {
+        let socket = UnixDatagram::unbound()
+            .map_err(|e| PqcError::Io(format!("Failed to create log socket: {}", e)))?;
+        socket.connect(path)
+            .map_err(|e| PqcError::Io(format!("Failed to connect to {}: {}", path.display(), e)))?;
+        Ok(socket)
+    }
+
+    fn send(socket: &UnixDatagram, message: &[u8]) -> Result<()> {
+        socket.send(message)
+            .map(|_| ())
+            .map_err(|e| PqcError::Io(format!("Failed to send lifecycle event: {}", e)))
+    }
+
+    /// `<PRI>1 - - APP PROCID MSGID [SD] MSG`; the daemon adds time and host
+    pub(super) fn syslog_message(event: &LifecycleEvent, app_name: &str, pid: u32) -> String {
+        let mut params = vec![("fingerprint", event.fingerprint.as_str())];
+        params.extend(event.algorithm.as_deref().map(|v| ("algorithm", v)));
+        params.extend(event.subject.as_deref().map(|v| ("subject", v)));
+        let timestamp = event.timestamp.to_string();
+        params.push(("timestamp", &timestamp));
+        let data: String = params.iter().map(|(name, value)| format!(" {}=\"{}\"", name, escape_param(value))).collect();
+        format!(
+            "<{}>1 - - {} {} {} [{}{}] {}",
+            FACILITY_AUTHPRIV * 8 + event.kind.severity(),
+            app_name, pid, event.kind, SD_ID, data, event.message()
+        )
+    }
+
+    pub(super) fn journal_message(event: &LifecycleEvent, identifier: &str) -> String {
+        let mut fields = vec![
+            ("MESSAGE", event.message()),
+            ("PRIORITY", event.kind.severity().to_string()),
+            ("SYSLOG_IDENTIFIER", identifier.to_string()),
+            ("PQC_EVENT", event.kind.to_string()),
+            ("PQC_KEY_FINGERPRINT", event.fingerprint.clone()),
+            ("PQC_TIMESTAMP", event.timestamp.to_string()),
+        ];
+        fields.extend(event.algorithm.clone().map(|v| ("PQC_ALGORITHM", v)));
+        fields.extend(event.subject.clone().map(|v| ("PQC_SUBJECT", v)));
+        fields.extend(event.detail.clone().map(|v| ("PQC_DETAIL", v)));
+        fields.iter().map(|(name, value)| format!("{}={}\n", name, value.replace('\n', " "))).collect()
+    }
+
+    /// Escape `"`, `\` and `]` in an SD-PARAM value (RFC 5424 §6.3.3)
+    fn escape_param(value: &str) -> String {
+        value.chars().fold(String::new(), |mut out, c| {
+            if matches!(c, '"' | '\\' | ']') {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::{KeyManager, KeyPolicy};
+    use crate::sign;
+
+    #[test]
+    fn test_sinks_receive_events() {
+        let memory = Arc::new(MemorySink::new());
+        let id = add_sink(memory.clone());
+        let raw = key_fingerprint(&sign::generate_keypair().unwrap().public_key);
+        let manager = KeyManager::new();
+        let key = manager.generate_dilithium(KeyPolicy::default()).unwrap();
+        let fingerprint = key_fingerprint(&manager.public_key(key).unwrap());
+        assert!(remove_sink(id));
+        emit(LifecycleEvent::new(LifecycleEventKind::Destroyed, &fingerprint));
+
+        let events = memory.events();
+        assert!(!events.iter().any(|e| e.fingerprint == raw));
+        let ours: Vec<_> = events.into_iter().filter(|e| e.fingerprint == fingerprint).collect();
+        assert_eq!(ours.len(), 1);
+        assert_eq!(ours[0].kind, LifecycleEventKind::Generated);
+        assert_eq!(ours[0].algorithm.as_deref(), Some("Dilithium3"));
+    }
+
+    /// Adds another sink the first time it records
+    struct Spawning(Mutex<Option<SinkId>>);
+
+    impl LifecycleSink for Spawning {
+        fn record(&self, _: &LifecycleEvent) -> Result<()> {
+            let mut child = self.0.lock().unwrap();
+            if child.is_none() {
+                *child = Some(add_sink(Arc::new(MemorySink::new())));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sink_may_add_sinks() {
+        let spawning = Arc::new(Spawning(Mutex::new(None)));
+        let id = add_sink(spawning.clone());
+        emit(LifecycleEvent::new(LifecycleEventKind::Revoked, "cd34"));
+        assert!(remove_sink(id));
+        let child = spawning.0.lock().unwrap().take().unwrap();
+        assert!(remove_sink(child));
+    }
+
+    #[test]
+    fn test_json_lines_sink() {
+        let sink = JsonLinesSink::new(Vec::new());
+        let event = LifecycleEvent::new(LifecycleEventKind::Revoked, "ab12").with_subject("sensor-7").with_detail("stolen");
+        sink.record(&event).unwrap();
+        sink.record(&event).unwrap();
+        let output = sink.writer.into_inner().unwrap();
+        let lines: Vec<_> = output.split(|&b| b == b'\n').filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(serde_json::from_slice::<LifecycleEvent>(lines[0]).unwrap(), event);
+    }
+
+    #[cfg(all(unix, feature = "syslog"))]
+    #[test]
+    fn test_syslog_and_journal_formats() {
+        let event = LifecycleEvent::new(LifecycleEventKind::Exported, "ab12")
+            .with_subject("meter \"3\"")
+            .with_detail("wrapped for transport");
+        let line = unix_sinks::syslog_message(&event, "pqc-algo", 42);
+        assert!(line.starts_with("<84>1 - - pqc-algo 42 key-exported [pqc@32473 fingerprint=\"ab12\" subject=\"meter \\\"3\\\"\""));
+        let journal = unix_sinks::journal_message(&event, "pqc-algo");
+        assert!(journal.contains("PRIORITY=4\n") && journal.contains("PQC_EVENT=key-exported\n"));
+    }
+}
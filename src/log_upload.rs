@@ -0,0 +1,359 @@
+//! Confidential log upload from devices
+//!
+//! A device seals a log bundle for the service with [`seal_logs`]: one KEM
+//! encapsulation to the service key, then the bundle split into chunks of
+//! at most [`DEFAULT_CHUNK_LEN`] bytes, each sealed separately so chunks
+//! can be uploaded over small frames and retried one at a time. A signed
+//! [`LogUploadHeader`] lists the SHA-256 of every sealed chunk, and the
+//! AEAD key is bound to the device and service fingerprints as in
+//! `sealed_box::AuthBox`. The device signs with its [`Authenticator`]; the
+//! service needs only its KEM key, as an [`EntropyConsumer`], and the
+//! device's [`Verifier`] to check everything with [`open_logs`] and get the
+//! plaintext plus [`LogProvenance`] to store alongside it.
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::hash;
+use crate::roles::{Authenticator, EntropyConsumer, Verifier};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::{canonical, fips, kdf, kem, sign};
+
+/// Domain label for log upload header signatures
+const HEADER_DOMAIN: &str = "PQC-Algo/v1/log-upload";
+
+/// Domain label for the associated data of each sealed chunk
+const CHUNK_AAD_DOMAIN: &str = "PQC-Algo/v1/log-chunk";
+
+/// Plaintext bytes per chunk unless the caller picks another size
+pub const DEFAULT_CHUNK_LEN: usize = 64 * 1024;
+
+/// Most chunks in one bundle
+pub const MAX_CHUNKS: usize = 4096;
+
+/// Signed description of a sealed log bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogUploadHeader {
+    pub device_id: String,
+    /// Random hex identifier tying the chunks to this header
+    pub bundle_id: String,
+    pub created_at: u64,
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kem_ciphertext: Vec<u8>,
+    pub service_fingerprint: String,
+    pub signer_fingerprint: String,
+    /// Hex SHA-256 of each sealed chunk, in order
+    pub chunk_digests: Vec<String>,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`LogUploadHeader`] covered by its signature
+#[derive(Serialize)]
+struct HeaderFields<'a> {
+    device_id: &'a str,
+    bundle_id: &'a str,
+    created_at: u64,
+    suite: &'a CipherSuite,
+    kem_ciphertext: String,
+    service_fingerprint: &'a str,
+    signer_fingerprint: &'a str,
+    chunk_digests: &'a [String],
+}
+
+/// Associated data of one sealed chunk
+#[derive(Serialize)]
+struct ChunkAad<'a> {
+    bundle_id: &'a str,
+    index: usize,
+    count: usize,
+}
+
+impl LogUploadHeader {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode log upload header: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "log upload header")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(HEADER_DOMAIN, &HeaderFields {
+            device_id: &self.device_id,
+            bundle_id: &self.bundle_id,
+            created_at: self.created_at,
+            suite: &self.suite,
+            kem_ciphertext: hex::encode(&self.kem_ciphertext),
+            service_fingerprint: &self.service_fingerprint,
+            signer_fingerprint: &self.signer_fingerprint,
+            chunk_digests: &self.chunk_digests,
+        })
+    }
+}
+
+impl Validate for LogUploadHeader {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validation::check_exact_len("Bundle ID", self.bundle_id.len(), 32)?;
+        validation::check_exact_len("KEM ciphertext", self.kem_ciphertext.len(), self.suite.kem.ciphertext_len()?)?;
+        validation::check_fingerprint("Service fingerprint", &self.service_fingerprint)?;
+        validation::check_fingerprint("Log signer fingerprint", &self.signer_fingerprint)?;
+        if self.chunk_digests.is_empty() || self.chunk_digests.len() > MAX_CHUNKS {
+            return Err(PqcError::InvalidInput(format!("Log bundles have 1 to {} chunks", MAX_CHUNKS)));
+        }
+        for digest in &self.chunk_digests {
+            validation::check_fingerprint("Chunk digest", digest)?;
+        }
+        validation::check_signature_len("Log upload signature", self.suite.sig, self.signature.len())
+    }
+}
+
+/// One sealed piece of a log bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogChunk {
+    pub bundle_id: String,
+    pub index: usize,
+    #[serde(with = "crate::util::bytes")]
+    pub sealed: Vec<u8>,
+}
+
+impl LogChunk {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode log chunk: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "log chunk")
+    }
+}
+
+impl Validate for LogChunk {
+    fn validate(&self) -> Result<()> {
+        validation::check_exact_len("Bundle ID", self.bundle_id.len(), 32)?;
+        validation::check_sealed_len("Sealed log chunk", self.sealed.len())
+    }
+}
+
+/// A sealed bundle: the header and its chunks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogUpload {
+    pub header: LogUploadHeader,
+    pub chunks: Vec<LogChunk>,
+}
+
+/// Who sent a bundle and what it contained, for storage next to the logs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogProvenance {
+    pub device_id: String,
+    pub bundle_id: String,
+    /// Unix time the device sealed the bundle, by its own clock
+    pub created_at: u64,
+    /// Unix time the service opened it
+    pub received_at: u64,
+    pub signer_fingerprint: String,
+    pub suite: CipherSuite,
+    pub chunk_count: usize,
+    pub size: usize,
+    /// Hex SHA-256 of the decrypted logs
+    pub sha256: String,
+}
+
+/// Seal `logs` for the service and sign them as `device`
+pub fn seal_logs(logs: &[u8], device: &Authenticator, service_kyber_public_key: &[u8]) -> Result<LogUpload> {
+    seal_logs_with(logs, DEFAULT_CHUNK_LEN, device, service_kyber_public_key, &CipherSuite::active_default())
+}
+
+/// [`seal_logs`] with an explicit chunk size and suite
+pub fn seal_logs_with(
+    logs: &[u8],
+    chunk_len: usize,
+    device: &Authenticator,
+    service_kyber_public_key: &[u8],
+    suite: &CipherSuite,
+) -> Result<LogUpload> {
+    fips::check_suite(suite)?;
+    if chunk_len == 0 || chunk_len > validation::MAX_MESSAGE_LEN / 4 {
+        return Err(PqcError::InvalidInput(format!(
+            "Chunk length must be 1 to {} bytes", validation::MAX_MESSAGE_LEN / 4
+        )));
+    }
+    let count = logs.len().div_ceil(chunk_len).max(1);
+    if count > MAX_CHUNKS {
+        return Err(PqcError::InvalidInput(format!(
+            "{} bytes of logs need {} chunks, maximum is {}", logs.len(), count, MAX_CHUNKS
+        )));
+    }
+
+    let mut id = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut id);
+    let bundle_id = hex::encode(id);
+    let signer_fingerprint = key_fingerprint(device.public_key());
+    let service_fingerprint = key_fingerprint(service_kyber_public_key);
+    let (kem_ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, service_kyber_public_key)?;
+    let key = upload_key(&Zeroizing::new(shared_secret), &signer_fingerprint, &service_fingerprint, suite)?;
+
+    let pieces: Vec<&[u8]> = if logs.is_empty() { vec![&[]] } else { logs.chunks(chunk_len).collect() };
+    let mut chunks = Vec::with_capacity(count);
+    for (index, piece) in pieces.into_iter().enumerate() {
+        let sealed = suite.aead.seal_with_aad(&key, piece, &chunk_aad(&bundle_id, index, count)?)?;
+        chunks.push(LogChunk { bundle_id: bundle_id.clone(), index, sealed });
+    }
+
+    let mut header = LogUploadHeader {
+        device_id: device.device_id().to_string(),
+        bundle_id,
+        created_at: unix_now(),
+        suite: *suite,
+        kem_ciphertext,
+        service_fingerprint,
+        signer_fingerprint,
+        chunk_digests: chunks.iter().map(|chunk| hex::encode(hash::sha256(&[&chunk.sealed]))).collect(),
+        signature: Vec::new(),
+    };
+    header.signature = sign::sign_with(suite.sig, &header.signing_bytes()?, device.secret_key())?;
+    Ok(LogUpload { header, chunks })
+}
+
+/// Verify a bundle from `device` and decrypt it
+///
+/// The header's `device_id` is chosen by the sender, so it must match the
+/// verifier's device ID as well as its key; look the verifier up from your
+/// own device records, not from the header. `chunks` may arrive in any
+/// order but every chunk listed in the header must be present exactly once.
+pub fn open_logs(header: &LogUploadHeader, chunks: &[LogChunk], service: &EntropyConsumer, device: &Verifier) -> Result<(Zeroizing<Vec<u8>>, LogProvenance)> {
+    header.validate()?;
+    let suite = &header.suite;
+    fips::check_suite(suite)?;
+    if header.device_id != device.device_id() {
+        return Err(PqcError::Verification(format!(
+            "Log bundle is from {}, expected {}", header.device_id, device.device_id()
+        )));
+    }
+    if !ct::ct_eq_hex(&header.signer_fingerprint, &key_fingerprint(device.public_key()))
+        || !sign::verify_with(suite.sig, &header.signing_bytes()?, &header.signature, device.public_key())?
+    {
+        return Err(PqcError::Verification("Log bundle was not signed by the device".to_string()));
+    }
+    if !ct::ct_eq_hex(&header.service_fingerprint, &key_fingerprint(service.public_key())) {
+        return Err(PqcError::Verification("Log bundle is sealed to another service key".to_string()));
+    }
+
+    let count = header.chunk_digests.len();
+    let mut ordered: Vec<Option<&LogChunk>> = vec![None; count];
+    for chunk in chunks {
+        chunk.validate()?;
+        if chunk.bundle_id != header.bundle_id || chunk.index >= count {
+            return Err(PqcError::Verification(format!("Chunk {} does not belong to bundle {}", chunk.index, header.bundle_id)));
+        }
+        if ordered[chunk.index].replace(chunk).is_some() {
+            return Err(PqcError::Verification(format!("Chunk {} was received twice", chunk.index)));
+        }
+    }
+
+    let shared_secret = Zeroizing::new(kem::decapsulate_with(suite.kem, service.secret_key(), &header.kem_ciphertext)?);
+    let key = upload_key(&shared_secret, &header.signer_fingerprint, &header.service_fingerprint, suite)?;
+    let mut logs = Zeroizing::new(Vec::new());
+    for (index, slot) in ordered.into_iter().enumerate() {
+        let chunk = slot.ok_or_else(|| PqcError::Verification(format!("Chunk {} of {} is missing", index, count)))?;
+        if !ct::ct_eq_hex(&header.chunk_digests[index], &hex::encode(hash::sha256(&[&chunk.sealed]))) {
+            return Err(PqcError::Verification(format!("Chunk {} does not match the signed header", index)));
+        }
+        let plaintext = Zeroizing::new(suite.aead.open_with_aad(&key, &chunk.sealed, &chunk_aad(&header.bundle_id, index, count)?)?);
+        logs.extend_from_slice(&plaintext);
+    }
+
+    let provenance = LogProvenance {
+        device_id: header.device_id.clone(),
+        bundle_id: header.bundle_id.clone(),
+        created_at: header.created_at,
+        received_at: unix_now(),
+        signer_fingerprint: header.signer_fingerprint.clone(),
+        suite: *suite,
+        chunk_count: count,
+        size: logs.len(),
+        sha256: hex::encode(hash::sha256(&[&logs])),
+    };
+    Ok((logs, provenance))
+}
+
+/// AEAD key bound to the device and the service
+fn upload_key(shared_secret: &[u8], signer_fingerprint: &str, service_fingerprint: &str, suite: &CipherSuite) -> Result<Zeroizing<Vec<u8>>> {
+    let mut info = kdf::label("log-upload");
+    info.extend_from_slice(format!("/{}/{}", signer_fingerprint, service_fingerprint).as_bytes());
+    kdf::hkdf_sha256(shared_secret, &[], &info, suite.aead.key_len()).map(Zeroizing::new)
+}
+
+fn chunk_aad(bundle_id: &str, index: usize, count: usize) -> Result<Vec<u8>> {
+    canonical::signing_bytes(CHUNK_AAD_DOMAIN, &ChunkAad { bundle_id, index, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceCredentials;
+
+    /// The device's signing role and the service's KEM role
+    fn parties() -> (Authenticator, EntropyConsumer) {
+        let (_, device) = DeviceCredentials::generate().unwrap().into_roles("sensor-1").unwrap();
+        let (service, _) = DeviceCredentials::generate().unwrap().into_roles("log-service").unwrap();
+        (device, service)
+    }
+
+    #[test]
+    fn test_seal_and_open_chunked_logs() {
+        let (device, service) = parties();
+        let logs: Vec<u8> = (0..10_000u32).flat_map(|i| format!("line {}\n", i).into_bytes()).collect();
+
+        let upload = seal_logs_with(&logs, 4096, &device, service.public_key(), &CipherSuite::active_default()).unwrap();
+        assert_eq!(upload.chunks.len(), logs.len().div_ceil(4096));
+        let header = LogUploadHeader::from_bytes(&upload.header.to_bytes().unwrap()).unwrap();
+        let mut chunks: Vec<LogChunk> = upload.chunks.iter().map(|c| LogChunk::from_bytes(&c.to_bytes().unwrap()).unwrap()).collect();
+        chunks.reverse();
+
+        let (opened, provenance) = open_logs(&header, &chunks, &service, &device.verifier()).unwrap();
+        assert_eq!(opened.as_slice(), logs.as_slice());
+        assert_eq!(provenance.device_id, "sensor-1");
+        assert_eq!(provenance.size, logs.len());
+        assert_eq!(provenance.sha256, hex::encode(hash::sha256(&[&logs])));
+    }
+
+    #[test]
+    fn test_open_rejects_tampering() {
+        let (device, service) = parties();
+        let (other_device, other_service) = parties();
+        let upload = seal_logs_with(&[7u8; 300], 100, &device, service.public_key(), &CipherSuite::active_default()).unwrap();
+        let open = |chunks: &[LogChunk]| open_logs(&upload.header, chunks, &service, &device.verifier());
+
+        assert!(matches!(open(&upload.chunks[..2]), Err(PqcError::Verification(_))));
+        let mut swapped = upload.chunks.clone();
+        swapped.swap(0, 1);
+        (swapped[0].index, swapped[1].index) = (0, 1);
+        assert!(open(&swapped).is_err());
+        let mut flipped = upload.chunks.clone();
+        flipped[1].sealed[20] ^= 1;
+        assert!(open(&flipped).is_err());
+
+        assert!(open_logs(&upload.header, &upload.chunks, &service, &other_device.verifier()).is_err());
+        assert!(open_logs(&upload.header, &upload.chunks, &other_service, &device.verifier()).is_err());
+        // Right key, but the header names a different device
+        let renamed = Verifier::new("sensor-2", device.public_key()).unwrap();
+        assert!(matches!(open_logs(&upload.header, &upload.chunks, &service, &renamed), Err(PqcError::Verification(_))));
+    }
+
+    #[test]
+    fn test_empty_bundle_has_one_chunk() {
+        let (device, service) = parties();
+        let upload = seal_logs(b"", &device, service.public_key()).unwrap();
+        assert_eq!(upload.chunks.len(), 1);
+        let (opened, _) = open_logs(&upload.header, &upload.chunks, &service, &device.verifier()).unwrap();
+        assert!(opened.is_empty());
+    }
+}
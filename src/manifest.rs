@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::canonical;
+use crate::hash::{self, HashAlgorithm};
+use crate::provenance::EntropyProvenance;
+use crate::sign;
+use crate::util::ct;
+
+/// Domain label for signed seed manifests
+const MANIFEST_DOMAIN: &str = "PQC-Algo/v1/seed-manifest";
+
+/// Provenance record for one quantum seed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeedManifestEntry {
+    /// File name of the seed, without directory
+    pub filename: String,
+    /// Lowercase hex SHA-256 of the file contents
+    pub sha256: String,
+    /// Where the seed came from (device serial, vendor, upload id)
+    pub source: String,
+    /// Acquisition time in seconds since the Unix epoch
+    pub acquired_at: u64,
+}
+
+impl SeedManifestEntry {
+    /// Build an entry by hashing a seed file on disk
+    pub fn from_file<P: AsRef<Path>>(path: P, source: &str, acquired_at: u64) -> Result<Self> {
+        let path = path.as_ref();
+        let filename = file_name(path)?;
+        let sha256 = sha256_file(path)?;
+        Ok(Self { filename, sha256, source: source.to_string(), acquired_at })
+    }
+}
+
+/// Signed list of seed files the QRNG is allowed to start from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeedManifest {
+    pub entries: Vec<SeedManifestEntry>,
+    /// How the listed seeds are conditioned and how much entropy they carry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<EntropyProvenance>,
+    /// Dilithium signature over the entries and provenance
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// The signed part of a manifest that carries provenance
+#[derive(Serialize)]
+struct ManifestFields<'a> {
+    entries: &'a [SeedManifestEntry],
+    provenance: &'a EntropyProvenance,
+}
+
+impl SeedManifest {
+    /// Create an unsigned manifest
+    pub fn new(entries: Vec<SeedManifestEntry>) -> Self {
+        Self { entries, provenance: None, signature: Vec::new() }
+    }
+
+    /// Attach an entropy provenance report; sign the manifest afterwards
+    pub fn with_provenance(mut self, provenance: EntropyProvenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Sign the manifest entries with a Dilithium secret key
+    pub fn sign(&mut self, dilithium_secret_key: &[u8]) -> Result<()> {
+        self.signature = sign::sign(&self.signing_bytes()?, dilithium_secret_key)?;
+        Ok(())
+    }
+
+    /// Check the manifest signature against the issuing authority's public key
+    pub fn verify_signature(&self, dilithium_public_key: &[u8]) -> Result<bool> {
+        if self.signature.is_empty() {
+            return Ok(false);
+        }
+        sign::verify(&self.signing_bytes()?, &self.signature, dilithium_public_key)
+    }
+
+    /// Check that a seed file is listed and its contents match the recorded hash
+    pub fn verify_file<P: AsRef<Path>>(&self, path: P) -> Result<&SeedManifestEntry> {
+        let path = path.as_ref();
+        let contents = fs::read(path)
+            .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        self.verify_contents(path, &contents)
+    }
+
+    /// Check `contents`, already read from `path`, against the entry for its file name
+    ///
+    /// Use this when the bytes are about to be used, so they cannot change
+    /// between the check and the load.
+    pub fn verify_contents<P: AsRef<Path>>(&self, path: P, contents: &[u8]) -> Result<&SeedManifestEntry> {
+        let filename = file_name(path.as_ref())?;
+        let entry = self.entries.iter()
+            .find(|e| e.filename == filename)
+            .ok_or_else(|| PqcError::InvalidSeedManifest(format!("{} is not listed", filename)))?;
+
+        let actual = hex::encode(hash::digest(HashAlgorithm::Sha256, contents));
+        if !ct::ct_eq_hex(&actual, &entry.sha256) {
+            return Err(PqcError::InvalidSeedManifest(format!("{} does not match its recorded SHA-256", filename)));
+        }
+        Ok(entry)
+    }
+
+    /// Verify the signature and every listed seed file in one step
+    pub fn verify_seeds<P: AsRef<Path>>(&self, seed_files: &[P], dilithium_public_key: &[u8]) -> Result<()> {
+        self.check_signature(dilithium_public_key)?;
+        for path in seed_files {
+            self.verify_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// [`SeedManifest::verify_signature`], failing unless it is valid
+    pub fn check_signature(&self, dilithium_public_key: &[u8]) -> Result<()> {
+        if !self.verify_signature(dilithium_public_key)? {
+            return Err(PqcError::InvalidSeedManifest("Signature verification failed".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Load a manifest from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| PqcError::InvalidSeedManifest(e.to_string()))
+    }
+
+    /// Save the manifest as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| PqcError::InvalidSeedManifest(e.to_string()))?;
+        fs::write(path, contents)
+            .map_err(|e| PqcError::Io(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// Canonical JSON of the signed fields under the manifest domain label
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        match &self.provenance {
+            // Without provenance the bytes are unchanged, so older manifests still verify
+            None => canonical::signing_bytes(MANIFEST_DOMAIN, &self.entries),
+            Some(provenance) => canonical::signing_bytes(MANIFEST_DOMAIN, &ManifestFields { entries: &self.entries, provenance }),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| PqcError::InvalidInput(format!("No file name in {}", path.display())))
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let contents = fs::read(path)
+        .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(hex::encode(hash::digest(HashAlgorithm::Sha256, &contents)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_sign_and_verify_seeds() {
+        let dir = std::env::temp_dir().join(format!("pqc_manifest_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let seed = dir.join("kyber.bin");
+        fs::write(&seed, "0123456789abcdef".repeat(32)).unwrap();
+
+        let authority = sign::generate_keypair().unwrap();
+        let entry = SeedManifestEntry::from_file(&seed, "qrng-lab-01", 1_700_000_000).unwrap();
+        let mut manifest = SeedManifest::new(vec![entry]);
+        manifest.sign(&authority.secret_key).unwrap();
+
+        assert!(manifest.verify_seeds(&[&seed], &authority.public_key).is_ok());
+
+        // Swapping the seed contents must be caught
+        fs::write(&seed, "fedcba9876543210".repeat(32)).unwrap();
+        let result = manifest.verify_seeds(&[&seed], &authority.public_key);
+        assert!(matches!(result, Err(PqcError::InvalidSeedManifest(_))));
+
+        // Editing the manifest invalidates the signature
+        manifest.entries[0].source = "somewhere-else".to_string();
+        assert!(!manifest.verify_signature(&authority.public_key).unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_provenance_is_covered_by_signature() {
+        let dir = std::env::temp_dir().join(format!("pqc_manifest_provenance_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (kyber, dilithium) = (dir.join("kyber.bin"), dir.join("dilithium.bin"));
+        fs::write(&kyber, "0123456789abcdef".repeat(32)).unwrap();
+        fs::write(&dilithium, "fedcba9876543210".repeat(32)).unwrap();
+
+        let authority = sign::generate_keypair().unwrap();
+        let provenance = crate::qrng::QRNG::new(&kyber, &dilithium).unwrap().provenance();
+        let entries = [&kyber, &dilithium].map(|path| SeedManifestEntry::from_file(path, "qrng-lab-01", 1_700_000_000).unwrap());
+        let mut manifest = SeedManifest::new(entries.to_vec()).with_provenance(provenance);
+        manifest.sign(&authority.secret_key).unwrap();
+
+        let loaded: SeedManifest = serde_json::from_str(&serde_json::to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(loaded.provenance.as_ref().unwrap().sources.len(), 2);
+        assert!(loaded.verify_seeds(&[&kyber, &dilithium], &authority.public_key).is_ok());
+
+        manifest.provenance.as_mut().unwrap().entropy_upper_bound_bits = 0;
+        assert!(!manifest.verify_signature(&authority.public_key).unwrap());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,63 @@
+//! Word mnemonics for paper backups of master seeds
+//!
+//! A 32-byte seed encodes to 24 words from the BIP39 English list, with the
+//! last word carrying an 8-bit SHA-256 checksum. Only the seed itself is
+//! encoded; the BIP39 passphrase-to-seed stretching step is not used.
+
+use bip39::Mnemonic;
+use zeroize::Zeroizing;
+use crate::error::{PqcError, Result};
+
+/// Length of a master seed in bytes
+pub const MASTER_SEED_LEN: usize = 32;
+
+/// Number of words in a master seed mnemonic
+pub const MNEMONIC_WORDS: usize = 24;
+
+/// Encode a master seed as 24 space-separated words
+pub fn encode(seed: &[u8; MASTER_SEED_LEN]) -> Result<Zeroizing<String>> {
+    let mnemonic = Mnemonic::from_entropy(seed)
+        .map_err(|e| PqcError::InvalidInput(format!("Cannot encode seed: {}", e)))?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Decode and checksum-verify a mnemonic produced by [`encode`]
+///
+/// Words are matched case-insensitively and extra whitespace is ignored.
+pub fn decode(words: &str) -> Result<Zeroizing<[u8; MASTER_SEED_LEN]>> {
+    let normalized = Zeroizing::new(words.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase());
+    let count = normalized.split(' ').count();
+    if count != MNEMONIC_WORDS {
+        return Err(PqcError::InvalidInput(format!("Expected {} words, got {}", MNEMONIC_WORDS, count)));
+    }
+    let mnemonic = Mnemonic::parse(normalized.as_str())
+        .map_err(|e| PqcError::InvalidInput(format!("Invalid mnemonic: {}", e)))?;
+    let (entropy, len) = mnemonic.to_entropy_array();
+    let entropy = Zeroizing::new(entropy);
+
+    let mut seed = Zeroizing::new([0u8; MASTER_SEED_LEN]);
+    seed.copy_from_slice(&entropy[..len]);
+    Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mnemonic_roundtrip_and_checksum() {
+        // BIP39 test vector: 32 zero bytes
+        let words = encode(&[0u8; 32]).unwrap();
+        assert_eq!(words.split(' ').count(), 24);
+        assert!(words.ends_with("abandon art"));
+        assert_eq!(*decode(&words.to_uppercase()).unwrap(), [0u8; 32]);
+
+        let seed = [0x7fu8; 32];
+        let words = encode(&seed).unwrap();
+        assert_eq!(*decode(&format!("  {}\n", *words)).unwrap(), seed);
+
+        let swapped = words.replacen(words.split(' ').next().unwrap(), "zoo", 1);
+        assert!(decode(&swapped).is_err());
+        assert!(decode("abandon abandon").is_err());
+    }
+}
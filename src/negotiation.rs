@@ -0,0 +1,139 @@
+//! Cipher suite negotiation between devices and the service
+//!
+//! The device sends a [`SuiteOffer`] listing the suites it supports. The
+//! service picks one according to its [`SuitePolicy`] and answers with a
+//! [`SuiteSelection`] that carries a hash of the offer, so the device can
+//! check that its offer reached the service intact before using the suite.
+//! Send the selection inside a `SignedEnvelope` so an attacker who strips
+//! suites from the offer cannot also rewrite the answer.
+
+use serde::{Deserialize, Serialize};
+use crate::{canonical, fips};
+use crate::error::{PqcError, Result};
+use crate::hash::{self, HashAlgorithm};
+use crate::suite::{CipherSuite, SecurityLevel};
+use crate::validation::validate_device_id;
+
+/// Domain label for offer hashes
+const OFFER_DOMAIN: &str = "PQC-Algo/v1/suite-offer";
+
+/// Device → service: the suites this device can run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuiteOffer {
+    pub device_id: String,
+    /// Supported suites, most preferred first
+    pub suites: Vec<CipherSuite>,
+}
+
+impl SuiteOffer {
+    pub fn new(device_id: &str, suites: Vec<CipherSuite>) -> Result<Self> {
+        validate_device_id(device_id)?;
+        if suites.is_empty() {
+            return Err(PqcError::InvalidInput("A suite offer needs at least one suite".to_string()));
+        }
+        Ok(Self { device_id: device_id.to_string(), suites })
+    }
+
+    /// Hex SHA-256 over the canonical offer
+    pub fn digest(&self) -> Result<String> {
+        let bytes = canonical::signing_bytes(OFFER_DOMAIN, self)?;
+        Ok(hex::encode(hash::digest(HashAlgorithm::Sha256, &bytes)))
+    }
+
+    /// Check the service's answer to this offer and return the agreed suite
+    pub fn accept(&self, selection: &SuiteSelection) -> Result<CipherSuite> {
+        if selection.device_id != self.device_id || selection.offer_digest != self.digest()? {
+            return Err(PqcError::Protocol("Suite selection does not answer this offer".to_string()));
+        }
+        if !self.suites.contains(&selection.suite) {
+            return Err(PqcError::Protocol(format!("Service selected unoffered suite {}", selection.suite)));
+        }
+        Ok(selection.suite)
+    }
+}
+
+/// Service → device: the suite chosen for this session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuiteSelection {
+    pub device_id: String,
+    pub suite: CipherSuite,
+    /// [`SuiteOffer::digest`] of the offer being answered
+    pub offer_digest: String,
+}
+
+/// Suites the service accepts, most preferred first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuitePolicy {
+    preferred: Vec<CipherSuite>,
+}
+
+impl Default for SuitePolicy {
+    /// Level 5, then level 3, then the crate default suite
+    fn default() -> Self {
+        Self::from_levels(&[SecurityLevel::L5, SecurityLevel::L3]).with_suite(CipherSuite::DEFAULT)
+    }
+}
+
+impl SuitePolicy {
+    pub fn new(preferred: Vec<CipherSuite>) -> Self {
+        Self { preferred }
+    }
+
+    pub fn from_levels(levels: &[SecurityLevel]) -> Self {
+        Self::new(levels.iter().map(|level| level.suite()).collect())
+    }
+
+    /// Also accept `suite`, after every suite already listed
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        if !self.preferred.contains(&suite) {
+            self.preferred.push(suite);
+        }
+        self
+    }
+
+    pub fn preferred(&self) -> &[CipherSuite] {
+        &self.preferred
+    }
+
+    /// Pick the service's most preferred suite that the device also offers
+    ///
+    /// In FIPS mode suites with non-approved algorithms are never selected.
+    pub fn select(&self, offer: &SuiteOffer) -> Result<SuiteSelection> {
+        validate_device_id(&offer.device_id)?;
+        let fips_mode = fips::fips_mode();
+        let suite = self.preferred.iter()
+            .filter(|suite| !fips_mode || fips::is_approved_suite(suite))
+            .find(|suite| offer.suites.contains(suite))
+            .copied()
+            .ok_or_else(|| PqcError::Protocol(format!("No mutually supported suite for {}", offer.device_id)))?;
+        Ok(SuiteSelection { device_id: offer.device_id.clone(), suite, offer_digest: offer.digest()? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_preference_wins() {
+        let offer = SuiteOffer::new("sensor-7", vec![SecurityLevel::L1.suite(), SecurityLevel::L3.suite()]).unwrap();
+        let policy = SuitePolicy::from_levels(&[SecurityLevel::L5, SecurityLevel::L3, SecurityLevel::L1]);
+
+        let selection = policy.select(&offer).unwrap();
+        assert_eq!(selection.suite, SecurityLevel::L3.suite());
+        assert_eq!(offer.accept(&selection).unwrap(), SecurityLevel::L3.suite());
+
+        let strict = SuitePolicy::from_levels(&[SecurityLevel::L5]);
+        assert!(matches!(strict.select(&offer), Err(PqcError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_tampered_offer_detected() {
+        let offer = SuiteOffer::new("sensor-7", vec![SecurityLevel::L5.suite(), SecurityLevel::L1.suite()]).unwrap();
+
+        // An attacker strips the strong suite in transit
+        let stripped = SuiteOffer::new("sensor-7", vec![SecurityLevel::L1.suite()]).unwrap();
+        let selection = SuitePolicy::default().with_suite(SecurityLevel::L1.suite()).select(&stripped).unwrap();
+        assert!(matches!(offer.accept(&selection), Err(PqcError::Protocol(_))));
+    }
+}
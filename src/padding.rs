@@ -0,0 +1,88 @@
+//! Fixed-size padding for messages on observable links
+//!
+//! JSON length varies with the device ID, nonce and signature algorithm, so
+//! a passive listener on the radio link can tell devices apart by message
+//! size alone. Padding every message of a kind to one fixed length hides
+//! that. A padded message is `len (u32 BE) || message || zeros`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::error::{PqcError, Result};
+use crate::validation::{self, Validate};
+
+/// Padded length for `AuthRequest`s and their responses
+///
+/// Large enough for a Dilithium5 request with a 64-byte device ID and a
+/// 64-byte nonce.
+pub const AUTH_PADDED_LEN: usize = 20 * 1024;
+
+const LEN_PREFIX: usize = 4;
+
+/// Pad `message` to exactly `padded_len` bytes
+pub fn pad(message: &[u8], padded_len: usize) -> Result<Vec<u8>> {
+    if message.len() + LEN_PREFIX > padded_len || message.len() > u32::MAX as usize {
+        return Err(PqcError::InvalidInput(format!(
+            "{} byte message does not fit in {} padded bytes", message.len(), padded_len
+        )));
+    }
+    let mut padded = Vec::with_capacity(padded_len);
+    padded.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    padded.extend_from_slice(message);
+    padded.resize(padded_len, 0);
+    Ok(padded)
+}
+
+/// Strip padding added by [`pad`]
+pub fn unpad(padded: &[u8]) -> Result<Vec<u8>> {
+    let malformed = || PqcError::InvalidInput("Malformed padded message".to_string());
+    let (prefix, rest) = padded.split_first_chunk::<LEN_PREFIX>().ok_or_else(malformed)?;
+    let len = u32::from_be_bytes(*prefix) as usize;
+    if len > rest.len() || rest[len..].iter().any(|&b| b != 0) {
+        return Err(malformed());
+    }
+    Ok(rest[..len].to_vec())
+}
+
+/// Serialize `value` as JSON padded to `padded_len`
+pub fn to_padded_json<T: Serialize>(value: &T, padded_len: usize) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(value)
+        .map_err(|e| PqcError::InvalidInput(format!("Failed to encode message: {}", e)))?;
+    pad(&json, padded_len)
+}
+
+/// Decode output of [`to_padded_json`]
+pub fn from_padded_json<T: DeserializeOwned + Validate>(padded: &[u8]) -> Result<T> {
+    validation::from_json(&unpad(padded)?, "message")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{create_auth_request, AuthRequest};
+    use crate::sign;
+
+    #[test]
+    fn test_auth_requests_pad_to_one_size() {
+        let keypair = sign::generate_keypair().unwrap();
+        let short = create_auth_request("d1", &[1; 32], &keypair.secret_key).unwrap();
+        let long = create_auth_request(&"d".repeat(64), &[255; 32], &keypair.secret_key).unwrap();
+
+        let short_padded = to_padded_json(&short, AUTH_PADDED_LEN).unwrap();
+        let long_padded = to_padded_json(&long, AUTH_PADDED_LEN).unwrap();
+        assert_eq!(short_padded.len(), long_padded.len());
+
+        let decoded: AuthRequest = from_padded_json(&long_padded).unwrap();
+        assert_eq!(decoded.device_id, long.device_id);
+    }
+
+    #[test]
+    fn test_malformed_padding() {
+        assert!(pad(&[0; 10], 13).is_err());
+        let mut padded = pad(b"hi", 16).unwrap();
+        assert_eq!(unpad(&padded).unwrap(), b"hi");
+        padded[15] = 1;
+        assert!(unpad(&padded).is_err());
+        assert!(unpad(&[0, 0, 1]).is_err());
+        assert!(unpad(&[0, 0, 0, 9, 1]).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use crate::cert::Certificate;
+use crate::extensions::Extensions;
+use crate::kem::KemAlgorithm;
+use crate::sign::SigAlgorithm;
+use crate::suite::{CipherSuite, SecurityLevel, SEAL_OVERHEAD};
+use crate::validation::MAX_DEVICE_ID_LEN;
+use crate::fips;
+
+/// Clock the CPU estimates in [`Overhead`] assume: a Cortex-M4 at 168 MHz
+pub const REFERENCE_CLOCK_MHZ: u32 = 168;
+
+/// Wire sizes and estimated CPU cost of one suite
+///
+/// Times are rough cycle-count estimates at [`REFERENCE_CLOCK_MHZ`], not
+/// measurements. Use [`Overhead::at_clock`] for another clock and measure
+/// on the real part before committing to a budget.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Overhead {
+    pub suite: CipherSuite,
+    pub kem_public_key: usize,
+    pub kem_ciphertext: usize,
+    pub sig_public_key: usize,
+    pub signature: usize,
+    /// Bytes an `EncryptedEntropy` adds to its payload: KEM ciphertext, nonce and tag
+    pub envelope: usize,
+    /// Upper bound on a JSON `Certificate` for a key of this suite
+    pub certificate: usize,
+    pub clock_mhz: u32,
+    pub kem_keygen_ms: f64,
+    pub encapsulate_ms: f64,
+    pub decapsulate_ms: f64,
+    pub sig_keygen_ms: f64,
+    pub sign_ms: f64,
+    pub verify_ms: f64,
+}
+
+impl Overhead {
+    /// Bytes on the link per delivery: one entropy envelope to the device and one signature back
+    pub fn exchange_bytes(&self) -> usize {
+        self.envelope + self.signature
+    }
+
+    /// Device CPU per delivery: decapsulate the envelope and sign the reply
+    pub fn exchange_ms(&self) -> f64 {
+        self.decapsulate_ms + self.sign_ms
+    }
+
+    /// Whether one delivery stays within `max_bytes` on the link and `max_ms` on the device
+    pub fn fits_budget(&self, max_bytes: usize, max_ms: f64) -> bool {
+        self.exchange_bytes() <= max_bytes && self.exchange_ms() <= max_ms
+    }
+
+    /// The same estimates for a device clocked at `clock_mhz`
+    pub fn at_clock(&self, clock_mhz: u32) -> Self {
+        let scale = self.clock_mhz as f64 / clock_mhz.max(1) as f64;
+        Self {
+            clock_mhz,
+            kem_keygen_ms: self.kem_keygen_ms * scale,
+            encapsulate_ms: self.encapsulate_ms * scale,
+            decapsulate_ms: self.decapsulate_ms * scale,
+            sig_keygen_ms: self.sig_keygen_ms * scale,
+            sign_ms: self.sign_ms * scale,
+            verify_ms: self.verify_ms * scale,
+            ..*self
+        }
+    }
+}
+
+/// Sizes and cost estimates for `suite`
+pub fn overhead(suite: &CipherSuite) -> Overhead {
+    let kem = suite.kem.sizes();
+    let sig = suite.sig.sizes();
+    let (kem_keygen, encapsulate, decapsulate) = kem_kilocycles(suite.kem);
+    let (sig_keygen, sign, verify) = sig_kilocycles(suite.sig);
+    let ms = |kilocycles: u32| kilocycles as f64 / REFERENCE_CLOCK_MHZ as f64;
+    Overhead {
+        suite: *suite,
+        kem_public_key: kem.public_key,
+        kem_ciphertext: kem.ciphertext,
+        sig_public_key: sig.public_key,
+        signature: sig.signature,
+        envelope: kem.ciphertext + SEAL_OVERHEAD,
+        certificate: certificate_bound(suite.sig),
+        clock_mhz: REFERENCE_CLOCK_MHZ,
+        kem_keygen_ms: ms(kem_keygen),
+        encapsulate_ms: ms(encapsulate),
+        decapsulate_ms: ms(decapsulate),
+        sig_keygen_ms: ms(sig_keygen),
+        sign_ms: ms(sign),
+        verify_ms: ms(verify),
+    }
+}
+
+/// Strongest level suite whose delivery fits the budget at `clock_mhz`
+///
+/// Considers the [`SecurityLevel`] suites, or their ML-KEM/ML-DSA versions
+/// in FIPS mode, from L5 down.
+pub fn largest_suite_within(max_bytes: usize, max_ms: f64, clock_mhz: u32) -> Option<CipherSuite> {
+    [SecurityLevel::L5, SecurityLevel::L3, SecurityLevel::L1]
+        .into_iter()
+        .map(|level| if fips::fips_mode() { level.fips_suite() } else { level.suite() })
+        .find(|suite| overhead(suite).at_clock(clock_mhz).fits_budget(max_bytes, max_ms))
+}
+
+/// Keygen, encapsulate and decapsulate, thousands of Cortex-M4 cycles
+///
+/// Estimates, rounded up to the order the pqm4 project reports for its
+/// optimized Cortex-M4 implementations; they are not taken from a specific
+/// benchmark run. Round 3 and FIPS parameter sets share a row.
+const fn kem_kilocycles(kem: KemAlgorithm) -> (u32, u32, u32) {
+    match kem {
+        KemAlgorithm::Kyber512 | KemAlgorithm::MlKem512 => (460, 570, 530),
+        KemAlgorithm::Kyber768 | KemAlgorithm::MlKem768 => (760, 910, 860),
+        KemAlgorithm::Kyber1024 | KemAlgorithm::MlKem1024 => (1210, 1380, 1310),
+    }
+}
+
+/// Keygen, average sign and verify, thousands of Cortex-M4 cycles
+///
+/// Estimates on the same basis as [`kem_kilocycles`]; signing time varies
+/// widely between calls because of rejection sampling.
+const fn sig_kilocycles(sig: SigAlgorithm) -> (u32, u32, u32) {
+    match sig {
+        SigAlgorithm::Dilithium2 | SigAlgorithm::MlDsa44 => (1600, 4100, 1600),
+        SigAlgorithm::Dilithium3 | SigAlgorithm::MlDsa65 => (2800, 6600, 2700),
+        SigAlgorithm::Dilithium5 | SigAlgorithm::MlDsa87 => (4800, 8800, 4800),
+    }
+}
+
+/// JSON size of a certificate without extensions, with every byte at its longest encoding
+fn certificate_bound(algorithm: SigAlgorithm) -> usize {
+    let sizes = algorithm.sizes();
+    let longest_name = "x".repeat(MAX_DEVICE_ID_LEN);
+    let certificate = Certificate {
+        serial: "f".repeat(32),
+        subject: longest_name.clone(),
+        public_key: vec![u8::MAX; sizes.public_key],
+        issuer: longest_name,
+        issuer_fingerprint: "f".repeat(64),
+        algorithm,
+        not_before: u64::MAX,
+        not_after: u64::MAX,
+        is_authority: false,
+        signature: vec![u8::MAX; sizes.signature],
+        extensions: Extensions::new(),
+    };
+    certificate.to_bytes().map_or(0, |bytes| bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overhead_grows_with_level() {
+        let l1 = overhead(&SecurityLevel::L1.suite());
+        let l5 = overhead(&SecurityLevel::L5.suite());
+        assert_eq!(l1.envelope, 768 + SEAL_OVERHEAD);
+        assert_eq!(l5.signature, 4595);
+        assert!(l5.exchange_bytes() > l1.exchange_bytes() && l5.exchange_ms() > l1.exchange_ms());
+        assert!(l1.certificate > 4 * (l1.sig_public_key + l1.signature));
+        let slow = l1.at_clock(REFERENCE_CLOCK_MHZ / 2);
+        assert!((slow.sign_ms - 2.0 * l1.sign_ms).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_largest_suite_within_budget() {
+        let level = |level: SecurityLevel| if fips::fips_mode() { level.fips_suite() } else { level.suite() };
+        assert_eq!(largest_suite_within(usize::MAX, f64::MAX, REFERENCE_CLOCK_MHZ), Some(level(SecurityLevel::L5)));
+        let l3 = overhead(&level(SecurityLevel::L3));
+        let budget = largest_suite_within(l3.exchange_bytes(), l3.exchange_ms(), REFERENCE_CLOCK_MHZ);
+        assert_eq!(budget, Some(level(SecurityLevel::L3)));
+        assert_eq!(largest_suite_within(1000, f64::MAX, REFERENCE_CLOCK_MHZ), None);
+    }
+}
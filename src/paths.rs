@@ -0,0 +1,260 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::error::{PqcError, Result};
+
+/// Application directory name used under the platform data directory
+pub const APP_DIR_NAME: &str = "pqc-algo";
+
+/// Default file name of the Kyber quantum seed
+pub const KYBER_SEED_FILE: &str = "kyber.bin";
+
+/// Default file name of the Dilithium quantum seed
+pub const DILITHIUM_SEED_FILE: &str = "dilithium.bin";
+
+/// Environment variable that overrides the platform data directory
+pub const DATA_DIR_ENV: &str = "PQC_ALGO_DATA_DIR";
+
+/// Platform data directory for this crate
+///
+/// - Linux/BSD: `$XDG_DATA_HOME/pqc-algo` or `~/.local/share/pqc-algo`
+/// - macOS: `~/Library/Application Support/pqc-algo`
+/// - Windows: `%APPDATA%\pqc-algo`
+///
+/// `PQC_ALGO_DATA_DIR` takes precedence on every platform.
+pub fn default_data_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(DATA_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    platform_data_dir().map(|dir| dir.join(APP_DIR_NAME))
+}
+
+#[cfg(target_os = "windows")]
+fn platform_data_dir() -> Result<PathBuf> {
+    env::var_os("APPDATA")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| PqcError::Io("APPDATA is not set".to_string()))
+}
+
+#[cfg(target_os = "macos")]
+fn platform_data_dir() -> Result<PathBuf> {
+    home_dir().map(|home| home.join("Library").join("Application Support"))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn platform_data_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        let dir = PathBuf::from(dir);
+        // The XDG spec says relative paths must be ignored
+        if dir.is_absolute() {
+            return Ok(dir);
+        }
+    }
+    home_dir().map(|home| home.join(".local").join("share"))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn home_dir() -> Result<PathBuf> {
+    env::var_os("HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| PqcError::Io("HOME is not set".to_string()))
+}
+
+/// Directory holding persisted device keys
+pub fn keystore_dir() -> Result<PathBuf> {
+    Ok(default_data_dir()?.join("keystore"))
+}
+
+/// Directory holding quantum seed files
+pub fn seed_dir() -> Result<PathBuf> {
+    Ok(default_data_dir()?.join("seeds"))
+}
+
+/// Default location of the Kyber quantum seed
+pub fn default_kyber_seed_path() -> Result<PathBuf> {
+    Ok(seed_dir()?.join(KYBER_SEED_FILE))
+}
+
+/// Default location of the Dilithium quantum seed
+pub fn default_dilithium_seed_path() -> Result<PathBuf> {
+    Ok(seed_dir()?.join(DILITHIUM_SEED_FILE))
+}
+
+/// Create a directory (and parents) readable only by the current user
+///
+/// Only the levels this call creates are tightened to 0700; directories that
+/// already exist, such as `/tmp` or the user's home, keep their mode.
+pub fn ensure_private_dir(dir: &Path) -> Result<()> {
+    let missing: Vec<&Path> = dir.ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .take_while(|p| !p.exists())
+        .collect();
+    fs::create_dir_all(dir)
+        .map_err(|e| PqcError::Io(format!("Failed to create {}: {}", dir.display(), e)))?;
+    for created in missing.into_iter().rev() {
+        set_permissions(created, 0o700)?;
+    }
+    Ok(())
+}
+
+/// Write a file readable only by the current user (0600 on Unix)
+pub fn write_private_file(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        ensure_private_dir(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)
+        .map_err(|e| PqcError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+    // The mode above only applies on creation, so tighten pre-existing files too
+    set_permissions(path, 0o600)?;
+    file.write_all(data)
+        .map_err(|e| PqcError::Io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Create an empty owner-only file with a random name next to `path`
+///
+/// The file is opened with `create_new`, so an existing file or symlink is
+/// never followed or overwritten. Write it, then move it over `path` with
+/// [`persist_private_temp`]; remove it on failure.
+pub fn create_private_temp(path: &Path) -> Result<(fs::File, PathBuf)> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = parent.join(format!(".{}.{}.tmp", name, hex::encode(rand::random::<[u8; 8]>())));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(&tmp)
+        .map_err(|e| PqcError::Io(format!("Failed to create {}: {}", tmp.display(), e)))?;
+    Ok((file, tmp))
+}
+
+/// Sync `file`, rename it from `tmp` to `path` and sync the directory
+pub fn persist_private_temp(file: fs::File, tmp: &Path, path: &Path) -> Result<()> {
+    let io_error = |e: std::io::Error| PqcError::Io(format!("Failed to write {}: {}", path.display(), e));
+    file.sync_all().map_err(io_error)?;
+    drop(file);
+    fs::rename(tmp, path).map_err(io_error)?;
+    sync_dir(path)
+}
+
+/// Replace `path` with `data` so that a crash leaves either the old or the new contents
+pub fn write_private_file_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        ensure_private_dir(parent)?;
+    }
+    let (mut file, tmp) = create_private_temp(path)?;
+    let result = file.write_all(data)
+        .map_err(|e| PqcError::Io(format!("Failed to write {}: {}", tmp.display(), e)))
+        .and_then(|_| persist_private_temp(file, &tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Make a rename in `path`'s directory durable
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| PqcError::Io(format!("Failed to sync {}: {}", parent.display(), e)))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
+    // Directories can't be opened for syncing; NTFS journals the rename
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_permissions(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| PqcError::Io(format!("Failed to set permissions on {}: {}", path.display(), e)))
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_path: &Path, _mode: u32) -> Result<()> {
+    // %APPDATA% is already scoped to the user profile by its ACL
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_paths_live_under_data_dir() {
+        let data_dir = default_data_dir().unwrap();
+        let kyber = default_kyber_seed_path().unwrap();
+        let dilithium = default_dilithium_seed_path().unwrap();
+
+        assert!(kyber.starts_with(&data_dir));
+        assert!(dilithium.starts_with(&data_dir));
+        assert_eq!(kyber.file_name().unwrap(), KYBER_SEED_FILE);
+        assert_eq!(dilithium.file_name().unwrap(), DILITHIUM_SEED_FILE);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("pqc_paths_test_{}", std::process::id()));
+        let path = dir.join("secret.bin");
+        write_private_file(&path, b"secret").unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read(&path).unwrap(), b"secret");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_existing_parent_dir_keeps_its_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("pqc_paths_existing_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        write_private_file(&dir.join("secret.bin"), b"secret").unwrap();
+        write_private_file_atomic(&dir.join("nested").join("state.bin"), b"state").unwrap();
+
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o755);
+        assert_eq!(fs::metadata(dir.join("nested")).unwrap().permissions().mode() & 0o777, 0o700);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_atomic_write_replaces_without_leftovers() {
+        let dir = env::temp_dir().join(format!("pqc_paths_atomic_{}", std::process::id()));
+        let path = dir.join("state.bin");
+        write_private_file_atomic(&path, b"old").unwrap();
+        write_private_file_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let (_file, tmp) = create_private_temp(&path).unwrap();
+        assert!(tmp.starts_with(&dir) && tmp != path);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
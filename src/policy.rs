@@ -0,0 +1,307 @@
+//! Deployment policy over stored artifacts, with a dry-run evaluator
+//!
+//! A [`Policy`] lists the rules a deployment holds its envelopes, keys and
+//! certificates to. [`evaluate`] checks artifacts against those rules for an
+//! [`Operation`] and returns a [`Decision`] listing every violation, without
+//! performing the operation or touching any key, so operators can preflight
+//! a migration across a whole store. [`Decision::into_result`] turns the same
+//! decision into an error for callers that enforce rather than report.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::api::EncryptedEntropy;
+use crate::cert::Certificate;
+use crate::envelope::{unix_now, SignedEnvelope};
+use crate::error::{PqcError, Result};
+use crate::clock::{self, ValidityOptions};
+use crate::fips;
+use crate::kem::KemAlgorithm;
+use crate::registry::DeviceRecord;
+use crate::sign::SigAlgorithm;
+use crate::suite::{CipherSuite, SecurityLevel};
+
+/// What the caller intends to do with the artifacts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum Operation {
+    /// Produce new artifacts; every rule applies
+    Encrypt,
+    Sign,
+    /// Read existing artifacts; legacy envelopes without a suite marker are tolerated
+    Decrypt,
+    Verify,
+    /// Re-encrypt or re-issue the artifacts under `target`, which must itself satisfy the policy
+    Migrate { target: CipherSuite },
+}
+
+impl Operation {
+    fn produces(&self) -> bool {
+        !matches!(self, Operation::Decrypt | Operation::Verify)
+    }
+}
+
+/// Something a policy can judge or `analysis::lint` can inspect
+#[derive(Clone, Copy)]
+pub enum Artifact<'a> {
+    Entropy(&'a EncryptedEntropy),
+    Envelope(&'a SignedEnvelope),
+    Certificate(&'a Certificate),
+    Device(&'a DeviceRecord),
+    Suite(CipherSuite),
+    /// Decoded quantum seed bytes; only linted, no policy rule applies
+    Seed(&'a [u8]),
+    /// Blob from `DeviceCredentials::export_encrypted`; only linted
+    Backup(&'a [u8]),
+}
+
+/// Rules a deployment's artifacts must follow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    /// Lowest NIST category accepted for KEM and signature algorithms
+    pub min_level: SecurityLevel,
+    /// Refuse algorithms FIPS mode would refuse, whether or not it is on
+    pub require_fips: bool,
+    #[serde(default)]
+    pub denied_kems: Vec<KemAlgorithm>,
+    #[serde(default)]
+    pub denied_sigs: Vec<SigAlgorithm>,
+    /// Oldest signed envelope accepted, if limited
+    #[serde(default)]
+    pub max_envelope_age: Option<Duration>,
+    /// Certificates must stay valid at least this long after the check
+    #[serde(default)]
+    pub min_certificate_remaining: Duration,
+}
+
+impl Default for Policy {
+    /// Level 1 and up, no denied algorithms, FIPS only when FIPS mode is on
+    fn default() -> Self {
+        Self {
+            min_level: SecurityLevel::L1,
+            require_fips: fips::fips_mode(),
+            denied_kems: Vec::new(),
+            denied_sigs: Vec::new(),
+            max_envelope_age: None,
+            min_certificate_remaining: Duration::ZERO,
+        }
+    }
+}
+
+/// One broken rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Stable rule identifier such as `min-security-level`
+    pub rule: String,
+    /// Index into the evaluated artifacts; `None` for the operation itself
+    pub artifact: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.artifact {
+            Some(index) => write!(f, "[{}] artifact {}: {}", self.rule, index, self.message),
+            None => write!(f, "[{}] {}", self.rule, self.message),
+        }
+    }
+}
+
+/// Outcome of evaluating an operation against a policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    pub operation: Operation,
+    pub evaluated_at: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl Decision {
+    pub fn allowed(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// `Err(PqcError::KeyPolicy)` listing every violation, for enforcing callers
+    pub fn into_result(self) -> Result<()> {
+        if self.allowed() {
+            return Ok(());
+        }
+        let list: Vec<String> = self.violations.iter().map(Violation::to_string).collect();
+        Err(PqcError::KeyPolicy(format!("Policy forbids {:?}: {}", self.operation, list.join("; "))))
+    }
+}
+
+/// Evaluate against [`Policy::default`]
+pub fn evaluate(operation: Operation, artifacts: &[Artifact<'_>]) -> Decision {
+    Policy::default().evaluate(operation, artifacts)
+}
+
+impl Policy {
+    /// Report every rule `artifacts` would break under `operation`; performs nothing
+    pub fn evaluate(&self, operation: Operation, artifacts: &[Artifact<'_>]) -> Decision {
+        self.evaluate_at(operation, artifacts, unix_now(), &clock::validity())
+    }
+
+    /// [`Policy::evaluate`] at an explicit time and clock tolerance
+    pub fn evaluate_at(&self, operation: Operation, artifacts: &[Artifact<'_>], now: u64, validity: &ValidityOptions) -> Decision {
+        let mut violations = Vec::new();
+        if let Operation::Migrate { target } = operation {
+            self.check_suite(&target, None, &mut violations);
+        }
+        for (index, artifact) in artifacts.iter().enumerate() {
+            self.check_artifact(operation, artifact, index, now, validity, &mut violations);
+        }
+        Decision { operation, evaluated_at: now, violations }
+    }
+
+    fn check_artifact(
+        &self,
+        operation: Operation,
+        artifact: &Artifact<'_>,
+        index: usize,
+        now: u64,
+        validity: &ValidityOptions,
+        out: &mut Vec<Violation>,
+    ) {
+        let at = Some(index);
+        match artifact {
+            Artifact::Suite(suite) => self.check_suite(suite, at, out),
+            Artifact::Device(record) => self.check_suite(&record.suite, at, out),
+            Artifact::Entropy(entropy) => match &entropy.suite {
+                Some(suite) => self.check_suite(suite, at, out),
+                None if operation.produces() => out.push(violation("suite-marker", at,
+                    "Envelope predates suite markers; re-encrypt it to record its suite".to_string())),
+                None => {}
+            },
+            Artifact::Envelope(envelope) => {
+                match SigAlgorithm::from_str(&envelope.algorithm) {
+                    Ok(algorithm) => self.check_sig(algorithm, at, out),
+                    Err(_) => out.push(violation("unknown-algorithm", at,
+                        format!("Unknown signature algorithm {}", envelope.algorithm))),
+                }
+                if let Some(max_age) = self.max_envelope_age {
+                    let allowed = max_age.saturating_add(validity.max_skew);
+                    if now.saturating_sub(envelope.timestamp) > allowed.as_secs() {
+                        out.push(violation("envelope-age", at,
+                            format!("Envelope signed at {} is older than {}s", envelope.timestamp, max_age.as_secs())));
+                    }
+                }
+            }
+            Artifact::Certificate(certificate) => {
+                self.check_sig(certificate.algorithm, at, out);
+                let needed_until = now.saturating_add(self.min_certificate_remaining.as_secs());
+                if validity.is_premature(certificate.not_before, now) || validity.is_expired(certificate.not_after, needed_until) {
+                    out.push(violation("certificate-validity", at, format!(
+                        "Certificate for {} is valid {}..{}, needed through {}",
+                        certificate.subject, certificate.not_before, certificate.not_after, needed_until
+                    )));
+                }
+            }
+            Artifact::Seed(_) | Artifact::Backup(_) => {}
+        }
+    }
+
+    fn check_suite(&self, suite: &CipherSuite, at: Option<usize>, out: &mut Vec<Violation>) {
+        self.check_kem(suite.kem, at, out);
+        self.check_sig(suite.sig, at, out);
+        if self.require_fips && !(fips::is_approved_aead(suite.aead) && fips::is_approved_hash(suite.hash)) {
+            out.push(violation("fips-approved", at, format!("Suite {} uses non-approved AEAD or hash", suite)));
+        }
+    }
+
+    fn check_kem(&self, kem: KemAlgorithm, at: Option<usize>, out: &mut Vec<Violation>) {
+        if kem_level(kem) < self.min_level {
+            out.push(violation("min-security-level", at, format!("{} is below {}", kem, self.min_level)));
+        }
+        if self.require_fips && !fips::is_approved_kem(kem) {
+            out.push(violation("fips-approved", at, format!("{} is not FIPS-approved", kem)));
+        }
+        if self.denied_kems.contains(&kem) {
+            out.push(violation("denied-algorithm", at, format!("{} is denied", kem)));
+        }
+    }
+
+    fn check_sig(&self, sig: SigAlgorithm, at: Option<usize>, out: &mut Vec<Violation>) {
+        if sig_level(sig) < self.min_level {
+            out.push(violation("min-security-level", at, format!("{} is below {}", sig, self.min_level)));
+        }
+        if self.require_fips && !fips::is_approved_sig(sig) {
+            out.push(violation("fips-approved", at, format!("{} is not FIPS-approved", sig)));
+        }
+        if self.denied_sigs.contains(&sig) {
+            out.push(violation("denied-algorithm", at, format!("{} is denied", sig)));
+        }
+    }
+}
+
+fn violation(rule: &str, artifact: Option<usize>, message: String) -> Violation {
+    Violation { rule: rule.to_string(), artifact, message }
+}
+
+fn kem_level(kem: KemAlgorithm) -> SecurityLevel {
+    match kem {
+        KemAlgorithm::Kyber512 | KemAlgorithm::MlKem512 => SecurityLevel::L1,
+        KemAlgorithm::Kyber768 | KemAlgorithm::MlKem768 => SecurityLevel::L3,
+        KemAlgorithm::Kyber1024 | KemAlgorithm::MlKem1024 => SecurityLevel::L5,
+    }
+}
+
+fn sig_level(sig: SigAlgorithm) -> SecurityLevel {
+    match sig {
+        SigAlgorithm::Dilithium2 | SigAlgorithm::MlDsa44 => SecurityLevel::L1,
+        SigAlgorithm::Dilithium3 | SigAlgorithm::MlDsa65 => SecurityLevel::L3,
+        SigAlgorithm::Dilithium5 | SigAlgorithm::MlDsa87 => SecurityLevel::L5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy_entropy() -> EncryptedEntropy {
+        EncryptedEntropy { ciphertext: vec![1], encrypted_data: vec![2], suite: None, fips_mode: false, label: None, recipient: None }
+    }
+
+    #[test]
+    fn test_dry_run_reports_each_violation() {
+        let policy = Policy { min_level: SecurityLevel::L3, denied_sigs: vec![SigAlgorithm::Dilithium3], ..Policy::default() };
+        let legacy = legacy_entropy();
+        let artifacts = [
+            Artifact::Suite(SecurityLevel::L1.suite()),
+            Artifact::Entropy(&legacy),
+            Artifact::Suite(SecurityLevel::L5.suite()),
+        ];
+
+        let decision = policy.evaluate(Operation::Encrypt, &artifacts);
+        let rules: Vec<_> = decision.violations.iter().map(|v| (v.rule.as_str(), v.artifact)).collect();
+        assert_eq!(rules, [
+            ("min-security-level", Some(0)),
+            ("min-security-level", Some(0)),
+            ("suite-marker", Some(1)),
+        ]);
+        assert!(matches!(decision.into_result(), Err(PqcError::KeyPolicy(_))));
+
+        // Reading legacy envelopes is fine; migrating to a denied suite is not
+        assert!(policy.evaluate(Operation::Decrypt, &artifacts[1..]).allowed());
+        let migrate = policy.evaluate(Operation::Migrate { target: SecurityLevel::L3.suite() }, &artifacts[2..]);
+        assert_eq!(migrate.violations.len(), 1);
+        assert_eq!((migrate.violations[0].rule.as_str(), migrate.violations[0].artifact), ("denied-algorithm", None));
+    }
+
+    #[test]
+    fn test_certificate_and_fips_rules() {
+        let authority = crate::cert::CertificateAuthority::generate("CA").unwrap();
+        let certificate = authority.issue_at("sensor-7", b"key", 1000, 2000, false).unwrap();
+        let policy = Policy {
+            require_fips: true,
+            min_certificate_remaining: Duration::from_secs(600),
+            ..Policy::default()
+        };
+        let artifacts = [Artifact::Certificate(&certificate)];
+        assert!(policy.evaluate_at(Operation::Verify, &artifacts, 1500, &ValidityOptions::STRICT).violations.iter().any(|v| v.rule == "certificate-validity"));
+        assert!(!policy.evaluate_at(Operation::Verify, &artifacts, 1200, &ValidityOptions::STRICT).violations.iter().any(|v| v.rule == "certificate-validity"));
+
+        let fips = policy.evaluate_at(Operation::Encrypt, &[Artifact::Suite(SecurityLevel::L3.fips_suite())], 1200, &ValidityOptions::STRICT);
+        assert!(fips.allowed());
+    }
+}
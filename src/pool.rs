@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use crate::cancel::{CancelToken, Partial};
+use crate::error::{PqcError, Result};
+
+/// Number of worker threads used for batch operations
+pub fn worker_count() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// Run `job` `n` times across a pool of worker threads
+///
+/// Results are returned in index order. `progress` is called with
+/// `(completed, total)` after each job finishes. The first error stops
+/// the remaining workers and is returned.
+pub(crate) fn run_parallel<T, F, P>(n: usize, job: F, progress: P) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(usize) -> Result<T> + Sync,
+    P: Fn(usize, usize) + Sync,
+{
+    run_parallel_cancellable(n, job, progress, &CancelToken::new()).into_result()
+}
+
+/// [`run_parallel`] that stops starting jobs once `token` is cancelled
+///
+/// Jobs that finished are kept, so the caller gets everything done before
+/// the first error or the cancellation.
+pub(crate) fn run_parallel_cancellable<T, F, P>(n: usize, job: F, progress: P, token: &CancelToken) -> Partial<T>
+where
+    T: Send,
+    F: Fn(usize) -> Result<T> + Sync,
+    P: Fn(usize, usize) + Sync,
+{
+    let workers = worker_count().min(n.max(1));
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let first_error = Mutex::new(None);
+    let stop = |e: PqcError| {
+        failed.store(true, Ordering::Relaxed);
+        first_error.lock().unwrap_or_else(|p| p.into_inner()).get_or_insert(e);
+    };
+
+    let finished: Vec<(usize, T)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut local = Vec::new();
+                    while !failed.load(Ordering::Relaxed) {
+                        if let Err(e) = token.check() {
+                            stop(e);
+                            break;
+                        }
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= n {
+                            break;
+                        }
+                        match job(index) {
+                            Ok(value) => {
+                                local.push((index, value));
+                                let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                                progress(done, n);
+                            }
+                            Err(e) => {
+                                stop(e);
+                                break;
+                            }
+                        }
+                    }
+                    local
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("keygen worker panicked"))
+            .collect()
+    });
+
+    let mut results: Vec<Option<T>> = (0..n).map(|_| None).collect();
+    for (index, value) in finished {
+        results[index] = Some(value);
+    }
+    let stopped = first_error.into_inner().unwrap_or_else(|p| p.into_inner());
+    Partial { results, stopped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_parallel_preserves_order() {
+        let calls = AtomicUsize::new(0);
+        let results = run_parallel(100, |i| Ok(i * 2), |_, total| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            assert_eq!(total, 100);
+        }).unwrap();
+
+        assert_eq!(results, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(calls.load(Ordering::Relaxed), 100);
+    }
+
+    #[test]
+    fn test_run_parallel_propagates_error() {
+        let result = run_parallel(50, |i| {
+            if i == 7 {
+                Err(PqcError::KeyGeneration("boom".to_string()))
+            } else {
+                Ok(i)
+            }
+        }, |_, _| {});
+
+        assert!(matches!(result, Err(PqcError::KeyGeneration(_))));
+    }
+
+    #[test]
+    fn test_run_parallel_cancellable_keeps_finished_jobs() {
+        let token = CancelToken::new();
+        let partial = run_parallel_cancellable(1000, |i| {
+            if i == 10 {
+                token.cancel();
+            }
+            Ok(i)
+        }, |_, _| {}, &token);
+
+        assert!(matches!(partial.stopped, Some(PqcError::Cancelled(_))));
+        assert!(partial.completed() > 0 && partial.completed() < 1000);
+        assert_eq!(partial.results[10], Some(10));
+        assert!(partial.into_completed().iter().all(|(i, value)| i == value));
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::Path;
+use crate::error::{PqcError, Result};
+use crate::api::{decrypt_with_aes, encrypt_with_aes};
+use crate::hash;
+
+/// Version byte at the start of the plaintext state record
+const STATE_VERSION: u8 = 1;
+
+/// Serialized state length: version + counter + digest
+const STATE_LEN: usize = 1 + 8 + 32;
+
+/// Entropy pool position that survives restarts
+///
+/// `digest` is a one-way ratchet over the quantum seeds; `counter` counts
+/// how many times it has been advanced. Neither reveals earlier RNG output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolState {
+    pub counter: u64,
+    pub digest: [u8; 32],
+}
+
+impl PoolState {
+    /// Fresh pool derived directly from the combined quantum seed
+    pub fn initial(combined_seed: &[u8]) -> Self {
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&combined_seed[..32]);
+        Self { counter: 0, digest }
+    }
+
+    /// Advance the ratchet one step
+    pub fn advance(&mut self) {
+        self.counter += 1;
+        self.digest = hash::sha256(&[b"QRNG_POOL_RATCHET_", &self.digest, &self.counter.to_be_bytes()]);
+    }
+
+    /// ChaCha20 seed for the current pool position
+    pub fn rng_seed(&self) -> [u8; 32] {
+        hash::sha256(&[b"QRNG_POOL_RNG_SEED_", &self.digest])
+    }
+
+    /// Encrypt the state with AES-256-GCM under `key`
+    pub fn seal(&self, key: &[u8; 32]) -> Result<Vec<u8>> {
+        let mut plaintext = Vec::with_capacity(STATE_LEN);
+        plaintext.push(STATE_VERSION);
+        plaintext.extend_from_slice(&self.counter.to_be_bytes());
+        plaintext.extend_from_slice(&self.digest);
+        encrypt_with_aes(key, &plaintext)
+    }
+
+    /// Decrypt state written by [`PoolState::seal`]
+    pub fn open(sealed: &[u8], key: &[u8; 32]) -> Result<Self> {
+        let plaintext = decrypt_with_aes(key, sealed)?;
+        if plaintext.len() != STATE_LEN || plaintext[0] != STATE_VERSION {
+            return Err(PqcError::Decryption("Unrecognized pool state format".to_string()));
+        }
+        let mut counter = [0u8; 8];
+        counter.copy_from_slice(&plaintext[1..9]);
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&plaintext[9..]);
+        Ok(Self { counter: u64::from_be_bytes(counter), digest })
+    }
+
+    /// Load sealed state from disk, `None` if the file doesn't exist yet
+    pub fn load(path: &Path, key: &[u8; 32]) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let sealed = std::fs::read(path)
+            .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::open(&sealed, key).map(Some)
+    }
+
+    /// Seal the state and durably replace `path` with it, owner-only
+    ///
+    /// The file is written through a synced temporary and renamed into
+    /// place, so a crash leaves either the previous state or this one.
+    pub fn save(&self, path: &Path, key: &[u8; 32]) -> Result<()> {
+        crate::paths::write_private_file_atomic(path, &self.seal(key)?)
+    }
+
+    /// The state `steps` advances ahead of this one
+    pub fn ahead(&self, steps: u64) -> Self {
+        let mut state = *self;
+        for _ in 0..steps {
+            state.advance();
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let key = [7u8; 32];
+        let mut state = PoolState::initial(&[1u8; 32]);
+        state.advance();
+
+        let sealed = state.seal(&key).unwrap();
+        assert_eq!(PoolState::open(&sealed, &key).unwrap(), state);
+        assert!(PoolState::open(&sealed, &[8u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_advance_changes_rng_seed() {
+        let mut state = PoolState::initial(&[1u8; 32]);
+        let before = state.rng_seed();
+        state.advance();
+        assert_eq!(state.counter, 1);
+        assert_ne!(state.rng_seed(), before);
+    }
+}
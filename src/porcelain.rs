@@ -0,0 +1,134 @@
+//! Stable line-oriented output for scripts
+//!
+//! Human-readable reports change wording between releases. The porcelain
+//! format does not, so scripts can parse it instead of screen-scraping (`\t`
+//! stands for a TAB):
+//!
+//! ```text
+//! porcelain\t1
+//! first_bytes\t0a1b2c
+//! key_type\tpublic
+//! size\t1568
+//! ```
+//!
+//! - The first line is `porcelain`, a TAB and [`PORCELAIN_VERSION`]. The
+//!   version only changes if existing lines would be read differently.
+//! - Every other line is a key, a TAB and a value.
+//! - Keys are the report's serialized field names joined with `.`; array
+//!   elements use their index, e.g. `problems.0`. Object keys appear in byte
+//!   order and array elements in index order.
+//! - Strings are written as-is except that `\`, TAB, CR and LF are escaped
+//!   as `\\`, `\t`, `\r` and `\n`. Keys are escaped the same way.
+//! - Numbers are plain decimal with `.` as the separator, booleans are
+//!   `true` and `false`, and nulls and empty arrays or objects are omitted.
+//!
+//! Nothing depends on the locale. New keys may be added in any release, so
+//! parsers should ignore keys they do not know.
+
+use serde::Serialize;
+use serde_json::Value;
+use crate::error::{PqcError, Result};
+
+/// Version on the first line of porcelain output
+pub const PORCELAIN_VERSION: u32 = 1;
+
+/// Render `value` in the porcelain format
+pub fn to_porcelain<T: Serialize>(value: &T) -> Result<String> {
+    let value = serde_json::to_value(value)
+        .map_err(|e| PqcError::InvalidInput(format!("Failed to encode porcelain output: {}", e)))?;
+    let mut out = format!("porcelain\t{}\n", PORCELAIN_VERSION);
+    write_value("", &value, &mut out);
+    Ok(out)
+}
+
+fn write_value(key: &str, value: &Value, out: &mut String) {
+    let child = |name: &str| if key.is_empty() { escape(name) } else { format!("{}.{}", key, escape(name)) };
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => write_line(key, &b.to_string(), out),
+        // Rust's f64 Display never uses an exponent, unlike serde_json's
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() => write_line(key, &f.to_string(), out),
+            _ => write_line(key, &n.to_string(), out),
+        },
+        Value::String(s) => write_line(key, &escape(s), out),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                write_value(&child(&i.to_string()), item, out);
+            }
+        }
+        Value::Object(map) => {
+            for (name, item) in map {
+                write_value(&child(name), item, out);
+            }
+        }
+    }
+}
+
+fn write_line(key: &str, value: &str, out: &mut String) {
+    // A bare scalar has no field name
+    out.push_str(if key.is_empty() { "value" } else { key });
+    out.push('\t');
+    out.push_str(value);
+    out.push('\n');
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Sample {
+        name: &'static str,
+        size: u64,
+        entropy: f64,
+        passed: bool,
+        missing: Option<u8>,
+        problems: Vec<&'static str>,
+    }
+
+    #[test]
+    fn test_porcelain_lines() {
+        let sample = Sample {
+            name: "a\tb\nc\\d",
+            size: 1568,
+            entropy: 7.5,
+            passed: false,
+            missing: None,
+            problems: vec!["first", "second"],
+        };
+        assert_eq!(to_porcelain(&sample).unwrap(), "porcelain\t1\n\
+            entropy\t7.5\n\
+            name\ta\\tb\\nc\\\\d\n\
+            passed\tfalse\n\
+            problems.0\tfirst\n\
+            problems.1\tsecond\n\
+            size\t1568\n");
+    }
+
+    #[test]
+    fn test_nested_keys_are_escaped() {
+        let value = serde_json::json!({ "fields": { "KEM ciphertext": "1088 bytes", "a\tb": 1 }, "empty": [] });
+        let out = to_porcelain(&value).unwrap();
+        assert!(out.contains("fields.KEM ciphertext\t1088 bytes\n"));
+        assert!(out.contains("fields.a\\tb\t1\n"));
+        assert!(!out.contains("empty"));
+        assert_eq!(to_porcelain(&3).unwrap(), "porcelain\t1\nvalue\t3\n");
+        assert_eq!(to_porcelain(&1e21).unwrap(), "porcelain\t1\nvalue\t1000000000000000000000\n");
+        assert_eq!(to_porcelain(&1.5e-7).unwrap(), "porcelain\t1\nvalue\t0.00000015\n");
+    }
+}
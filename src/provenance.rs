@@ -0,0 +1,215 @@
+//! Entropy provenance reports
+//!
+//! [`EntropyProvenance`] documents where a QRNG's output comes from: the raw
+//! seed sources, the conditioning they went through, an upper bound on their
+//! entropy and when the pool was last reseeded. It is what `QRNG::seed_info` prints,
+//! and it can be signed on its own as a [`SignedEnvelope`] or attached to a
+//! `SeedManifest` for customers who require provenance documentation.
+//!
+//! The bound comes from each raw seed's byte frequencies, capped at the bits
+//! the conditioning leaves, and the master seed is credited with at most 256
+//! bits. It is not a min-entropy estimate: a seed with structure the byte
+//! histogram cannot see, such as a counter, still scores high. Use it to
+//! catch broken seeds, and rely on the source's own SP 800-90B assessment
+//! for how much entropy it actually carries.
+//!
+//! A QRNG running on OS entropy because its seeds were missing reports
+//! `degraded: true`, and the flag is covered by any signature over the report.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::canonical;
+use crate::conditioning::ConditioningPipeline;
+use crate::envelope::SignedEnvelope;
+use crate::error::{PqcError, Result};
+use crate::mnemonic::MASTER_SEED_LEN;
+use crate::randomness_tests;
+use crate::validation::{self, Validate};
+
+/// Envelope purpose for signed provenance reports
+pub const PROVENANCE_PURPOSE: &str = "entropy-provenance";
+
+/// Name of the entropy bound recorded in reports
+pub const ENTROPY_BOUND_ESTIMATOR: &str = "byte-frequency-upper-bound";
+
+/// Most seed sources a signed report may list
+const MAX_PROVENANCE_SOURCES: usize = 16;
+
+/// One raw seed that feeds the master seed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenanceSource {
+    /// Role of the seed, e.g. `kyber` or `dilithium`
+    pub name: String,
+    pub raw_bytes: u64,
+    /// Bytes left after conditioning
+    pub conditioned_bytes: u64,
+    /// Most entropy the source can contribute, by [`randomness_tests::entropy_upper_bound`]
+    pub entropy_upper_bound_bits: u64,
+}
+
+impl ProvenanceSource {
+    /// Describe `raw`, bounding its entropy
+    pub fn measure(name: &str, raw: &[u8], conditioned_bytes: usize) -> Self {
+        let bound = (randomness_tests::entropy_upper_bound(raw) * raw.len() as f64).floor() as u64;
+        Self {
+            name: name.to_string(),
+            raw_bytes: raw.len() as u64,
+            conditioned_bytes: conditioned_bytes as u64,
+            entropy_upper_bound_bits: bound.min(conditioned_bytes as u64 * 8),
+        }
+    }
+
+    /// The operating system RNG, credited with full entropy for `bytes`
+    pub fn os_rng(bytes: usize) -> Self {
+        Self {
+            name: "os-rng".to_string(),
+            raw_bytes: bytes as u64,
+            conditioned_bytes: bytes as u64,
+            entropy_upper_bound_bits: bytes as u64 * 8,
+        }
+    }
+
+    /// A master seed of `bytes` restored from its mnemonic backup
+    ///
+    /// The raw seeds are gone, so it is credited with full entropy like the
+    /// master seed it was written down from.
+    pub fn mnemonic(bytes: usize) -> Self {
+        Self { name: "mnemonic".to_string(), ..Self::os_rng(bytes) }
+    }
+}
+
+/// Where a QRNG's entropy comes from and how it was processed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntropyProvenance {
+    /// Fingerprint of the combined seed material
+    pub seed_fingerprint: String,
+    /// A single `mnemonic` source for a QRNG restored from its backup
+    pub sources: Vec<ProvenanceSource>,
+    pub conditioning: ConditioningPipeline,
+    /// How the seeds are combined into the master seed
+    pub combiner: String,
+    pub estimator: String,
+    /// Upper bound on the master seed's entropy, at most 256
+    pub entropy_upper_bound_bits: u64,
+    pub rng: String,
+    /// Pool refreshes since the seeds were first loaded
+    pub reseed_count: u64,
+    /// Time of the last reseed, seconds since the Unix epoch
+    pub last_reseed_at: u64,
+    pub bytes_derived: u64,
+    pub keys_derived: u64,
+    /// Running on OS entropy because the quantum seeds were missing
+    #[serde(default)]
+    pub degraded: bool,
+}
+
+impl EntropyProvenance {
+    /// Bound the master seed by the sources' combined bounds, capped at its size
+    pub fn master_seed_bound(sources: &[ProvenanceSource]) -> u64 {
+        sources
+            .iter()
+            .map(|source| source.entropy_upper_bound_bits)
+            .sum::<u64>()
+            .min(MASTER_SEED_LEN as u64 * 8)
+    }
+
+    /// Sign the report as an envelope with purpose [`PROVENANCE_PURPOSE`]
+    pub fn sign(&self, dilithium_secret_key: &[u8], dilithium_public_key: &[u8]) -> Result<SignedEnvelope> {
+        let payload = canonical::to_canonical_json(self)?;
+        SignedEnvelope::sign(&payload, PROVENANCE_PURPOSE, dilithium_secret_key, dilithium_public_key)
+    }
+
+    /// Verify a signed report and return its contents
+    pub fn open(envelope: &SignedEnvelope, dilithium_public_key: &[u8]) -> Result<Self> {
+        if !envelope.verify(dilithium_public_key, PROVENANCE_PURPOSE)? {
+            return Err(PqcError::Verification("Entropy provenance signature is invalid".to_string()));
+        }
+        validation::from_json(&envelope.payload, "entropy provenance")
+    }
+}
+
+impl Validate for EntropyProvenance {
+    fn validate(&self) -> Result<()> {
+        validation::check_max_len("Seed fingerprint", self.seed_fingerprint.len(), validation::MAX_LABEL_LEN)?;
+        validation::check_max_len("Provenance sources", self.sources.len(), MAX_PROVENANCE_SOURCES)?;
+        for source in &self.sources {
+            validation::check_max_len("Provenance source name", source.name.len(), validation::MAX_LABEL_LEN)?;
+        }
+        for (what, text) in [("Combiner", &self.combiner), ("Estimator", &self.estimator), ("RNG", &self.rng)] {
+            validation::check_max_len(what, text.len(), validation::MAX_LABEL_LEN)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EntropyProvenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "QRNG Quantum Seed Info:")?;
+        if self.degraded {
+            writeln!(f, "- DEGRADED: quantum seeds missing, running on OS entropy")?;
+        }
+        for source in &self.sources {
+            writeln!(
+                f,
+                "- {} seed: {} bytes ({} conditioned, at most {} bits of entropy)",
+                capitalize(&source.name), source.raw_bytes, source.conditioned_bytes, source.entropy_upper_bound_bits
+            )?;
+        }
+        writeln!(f, "- Conditioning: {}", self.conditioning.describe())?;
+        writeln!(f, "- Combined entropy: {}, at most {} bits ({})", self.combiner, self.entropy_upper_bound_bits, self.estimator)?;
+        writeln!(f, "- RNG: {}", self.rng)?;
+        writeln!(f, "- Reseeds: {}, last at {}", self.reseed_count, self.last_reseed_at)?;
+        write!(f, "- Derived so far: {} bytes, {} keys", self.bytes_derived, self.keys_derived)
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign;
+
+    fn sample() -> EntropyProvenance {
+        let raw: Vec<u8> = (0..=255u8).collect();
+        let sources = vec![ProvenanceSource::measure("kyber", &raw, 256), ProvenanceSource::measure("dilithium", &raw, 16)];
+        EntropyProvenance {
+            seed_fingerprint: "ab".repeat(32),
+            entropy_upper_bound_bits: EntropyProvenance::master_seed_bound(&sources),
+            sources,
+            conditioning: "sha256".parse().unwrap(),
+            combiner: "SHA256 hash".to_string(),
+            estimator: ENTROPY_BOUND_ESTIMATOR.to_string(),
+            rng: "ChaCha20".to_string(),
+            reseed_count: 3,
+            last_reseed_at: 1_700_000_000,
+            bytes_derived: 64,
+            keys_derived: 1,
+            degraded: false,
+        }
+    }
+
+    #[test]
+    fn test_estimates_are_capped() {
+        let provenance = sample();
+        assert!(provenance.sources[0].entropy_upper_bound_bits > 128);
+        assert_eq!(provenance.sources[1].entropy_upper_bound_bits, 128);
+        assert_eq!(provenance.entropy_upper_bound_bits, 256);
+        assert_eq!(ProvenanceSource::measure("stuck", &[0u8; 64], 64).entropy_upper_bound_bits, 0);
+    }
+
+    #[test]
+    fn test_signed_report_round_trip() {
+        let authority = sign::generate_keypair().unwrap();
+        let provenance = sample();
+        let envelope = provenance.sign(&authority.secret_key, &authority.public_key).unwrap();
+        assert_eq!(EntropyProvenance::open(&envelope, &authority.public_key).unwrap(), provenance);
+
+        let other = sign::generate_keypair().unwrap();
+        assert!(matches!(EntropyProvenance::open(&envelope, &other.public_key), Err(PqcError::Verification(_))));
+        assert!(provenance.to_string().contains("- Conditioning: raw -> sha256"));
+    }
+}
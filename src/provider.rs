@@ -0,0 +1,103 @@
+//! Injectable KEM, signature and clock providers
+//!
+//! Code built on this crate can depend on [`KemProvider`], [`SigProvider`]
+//! and [`Clock`] instead of calling `kem`, `sign` and the system clock
+//! directly. In production, pass [`Liboqs`] and [`SystemClock`]. In unit
+//! tests, pass the fakes from `test_utils` (feature `test-utils`), which need
+//! neither liboqs nor real time.
+
+use crate::error::Result;
+use crate::kem::{self, KemAlgorithm, KyberKeyPair};
+use crate::sign::{self, DilithiumKeyPair, SigAlgorithm};
+
+pub use crate::clock::{Clock, SystemClock};
+
+/// Key encapsulation
+pub trait KemProvider: Send + Sync {
+    fn kem_algorithm(&self) -> KemAlgorithm;
+
+    fn generate_kem_keypair(&self) -> Result<KyberKeyPair>;
+
+    /// `(ciphertext, shared_secret)` for `public_key`
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)>;
+
+    fn decapsulate(&self, secret_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Signatures
+pub trait SigProvider: Send + Sync {
+    fn sig_algorithm(&self) -> SigAlgorithm;
+
+    fn generate_sig_keypair(&self) -> Result<DilithiumKeyPair>;
+
+    fn sign(&self, message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>>;
+
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool>;
+}
+
+/// The liboqs implementations behind `kem` and `sign`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Liboqs {
+    pub kem: KemAlgorithm,
+    pub sig: SigAlgorithm,
+}
+
+impl Default for Liboqs {
+    /// The algorithms used by `kem::generate_keypair` and `sign::generate_keypair`
+    fn default() -> Self {
+        Self { kem: KemAlgorithm::Kyber1024, sig: SigAlgorithm::Dilithium3 }
+    }
+}
+
+impl KemProvider for Liboqs {
+    fn kem_algorithm(&self) -> KemAlgorithm {
+        self.kem
+    }
+
+    fn generate_kem_keypair(&self) -> Result<KyberKeyPair> {
+        kem::generate_keypair_with(self.kem)
+    }
+
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        kem::encapsulate_with(self.kem, public_key)
+    }
+
+    fn decapsulate(&self, secret_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        kem::decapsulate_with(self.kem, secret_key, ciphertext)
+    }
+}
+
+impl SigProvider for Liboqs {
+    fn sig_algorithm(&self) -> SigAlgorithm {
+        self.sig
+    }
+
+    fn generate_sig_keypair(&self) -> Result<DilithiumKeyPair> {
+        sign::generate_keypair_with(self.sig)
+    }
+
+    fn sign(&self, message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
+        sign::sign_with(self.sig, message, secret_key)
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+        sign::verify_with(self.sig, message, signature, public_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liboqs_round_trips() {
+        let oqs = Liboqs::default();
+        let keypair = oqs.generate_kem_keypair().unwrap();
+        let (ciphertext, secret) = oqs.encapsulate(&keypair.public_key).unwrap();
+        assert_eq!(oqs.decapsulate(&keypair.secret_key, &ciphertext).unwrap(), secret);
+
+        let signer = oqs.generate_sig_keypair().unwrap();
+        let signature = oqs.sign(b"message", &signer.secret_key).unwrap();
+        assert!(oqs.verify(b"message", &signature, &signer.public_key).unwrap());
+    }
+}
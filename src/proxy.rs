@@ -0,0 +1,255 @@
+//! Re-encapsulation at an edge gateway for store-and-forward delivery
+//!
+//! When devices are offline, the service encrypts their entropy to a
+//! gateway instead. The gateway holds the envelopes and, once a device
+//! checks in, a [`ReencapsulationProxy`] has its [`GatewayKey`] open each one
+//! and encrypt it to the device's own KEM key.
+//!
+//! Opening and re-sealing both happen behind [`GatewayKey::reencapsulate`],
+//! so a key in a secure element keeps the shared secret, the AEAD key and
+//! the plaintext inside it; only ciphertext crosses the boundary. Callers
+//! get at most a [`SealedPlaintext`] inside a callback, which reports the
+//! payload's length and digest. The held envelope's source label is carried
+//! over; see [`EncryptedEntropy::verify_forwarded_label`].
+
+use std::fmt;
+use zeroize::Zeroizing;
+use crate::api::{self, EncryptedEntropy};
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::secret::SecretKey;
+use crate::suite::CipherSuite;
+use crate::validation::Validate;
+use crate::{hash, kdf, kem};
+
+/// The gateway's KEM key pair, wherever the secret key is kept
+pub trait GatewayKey {
+    fn public_key(&self) -> &[u8];
+
+    /// Suite that envelopes addressed to this key are sealed under
+    fn suite(&self) -> CipherSuite;
+
+    /// Open `envelope` and seal its payload to the device, inside the key's boundary
+    ///
+    /// `check` must see the payload's [`SealedPlaintext`] measurements before
+    /// it is sealed, and an error from it refuses the delivery. The envelope
+    /// has already been checked to be addressed to this key under its suite.
+    fn reencapsulate(
+        &self,
+        envelope: &EncryptedEntropy,
+        device_kyber_public_key: &[u8],
+        device_suite: &CipherSuite,
+        check: &mut dyn FnMut(&SealedPlaintext) -> Result<()>,
+    ) -> Result<EncryptedEntropy>;
+}
+
+/// A gateway key held in process memory, for gateways without a secure element
+pub struct SoftwareGatewayKey {
+    public_key: Vec<u8>,
+    secret_key: SecretKey,
+    suite: CipherSuite,
+}
+
+impl SoftwareGatewayKey {
+    pub fn new(public_key: &[u8], secret_key: SecretKey) -> Self {
+        Self { public_key: public_key.to_vec(), secret_key, suite: CipherSuite::active_default() }
+    }
+
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.suite = suite;
+        self
+    }
+}
+
+impl GatewayKey for SoftwareGatewayKey {
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn suite(&self) -> CipherSuite {
+        self.suite
+    }
+
+    fn reencapsulate(
+        &self,
+        envelope: &EncryptedEntropy,
+        device_kyber_public_key: &[u8],
+        device_suite: &CipherSuite,
+        check: &mut dyn FnMut(&SealedPlaintext) -> Result<()>,
+    ) -> Result<EncryptedEntropy> {
+        let shared_secret = Zeroizing::new(kem::decapsulate_with(self.suite.kem, &self.secret_key, &envelope.ciphertext)?);
+        let key = Zeroizing::new(kdf::envelope_key(&shared_secret)?);
+        let plaintext = Zeroizing::new(self.suite.aead.open_with_aad(key.as_slice(), &envelope.encrypted_data, &envelope.aad()?)?);
+        check(&SealedPlaintext::measure(&plaintext))?;
+        api::encrypt_entropy_with_suite(&plaintext, device_kyber_public_key, device_suite)
+    }
+}
+
+/// Prints the public key fingerprint, never the secret key
+impl fmt::Debug for SoftwareGatewayKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftwareGatewayKey")
+            .field("fingerprint", &key_fingerprint(&self.public_key))
+            .field("secret_key", &self.secret_key)
+            .field("suite", &self.suite)
+            .finish()
+    }
+}
+
+/// What a gateway may learn about a payload it forwards: its length and digest
+pub struct SealedPlaintext {
+    len: usize,
+    sha256: [u8; 32],
+}
+
+impl SealedPlaintext {
+    /// Measure `plaintext` inside the key's boundary
+    pub fn measure(plaintext: &[u8]) -> Self {
+        Self { len: plaintext.len(), sha256: hash::sha256(&[plaintext]) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Hex SHA-256, so audit logs can match deliveries without holding them
+    pub fn sha256(&self) -> String {
+        hex::encode(self.sha256)
+    }
+}
+
+impl fmt::Debug for SealedPlaintext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SealedPlaintext({} bytes, redacted)", self.len)
+    }
+}
+
+/// Opens envelopes addressed to a gateway and re-encrypts them to devices
+pub struct ReencapsulationProxy<K> {
+    key: K,
+}
+
+impl<K: GatewayKey> ReencapsulationProxy<K> {
+    pub fn new(key: K) -> Self {
+        Self { key }
+    }
+
+    pub fn gateway_fingerprint(&self) -> String {
+        key_fingerprint(self.key.public_key())
+    }
+
+    /// Re-encrypt `envelope` to the device under the active default suite
+    pub fn forward(&self, envelope: &EncryptedEntropy, device_kyber_public_key: &[u8]) -> Result<EncryptedEntropy> {
+        self.forward_with(envelope, device_kyber_public_key, &CipherSuite::active_default(), |_| Ok(()))
+    }
+
+    /// Re-encrypt `envelope` to the device under `device_suite`
+    ///
+    /// `check` sees the opened payload as a [`SealedPlaintext`] before it is
+    /// re-encrypted, and can refuse the delivery by returning an error, for
+    /// example when a payload is shorter than the device expects.
+    pub fn forward_with<F>(
+        &self,
+        envelope: &EncryptedEntropy,
+        device_kyber_public_key: &[u8],
+        device_suite: &CipherSuite,
+        check: F,
+    ) -> Result<EncryptedEntropy>
+    where
+        F: FnOnce(&SealedPlaintext) -> Result<()>,
+    {
+        envelope.validate()?;
+        let suite = self.key.suite();
+        if let Some(sealed) = &envelope.suite {
+            if *sealed != suite {
+                return Err(PqcError::Decryption(format!("Sealed under {}, gateway key uses {}", sealed, suite)));
+            }
+        }
+        envelope.check_recipient(self.key.public_key())?;
+        let mut check = Some(check);
+        let mut check_once = |plaintext: &SealedPlaintext| match check.take() {
+            Some(check) => check(plaintext),
+            None => Err(PqcError::Protocol("Gateway key measured the payload twice".to_string())),
+        };
+        let mut forwarded = self.key.reencapsulate(envelope, device_kyber_public_key, device_suite, &mut check_once)?;
+        if check.is_some() {
+            return Err(PqcError::Protocol("Gateway key sealed the payload without checking it".to_string()));
+        }
+        forwarded.label = envelope.label.as_ref().map(|label| label.forwarded(envelope));
+        Ok(forwarded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceCredentials;
+    use crate::suite::SecurityLevel;
+
+    fn gateway() -> (ReencapsulationProxy<SoftwareGatewayKey>, Vec<u8>) {
+        let keypair = kem::generate_keypair().unwrap();
+        let key = SoftwareGatewayKey::new(&keypair.public_key, keypair.secret_key.into());
+        (ReencapsulationProxy::new(key), keypair.public_key)
+    }
+
+    #[test]
+    fn test_forward_to_offline_device() {
+        let (proxy, gateway_public_key) = gateway();
+        let device = DeviceCredentials::generate().unwrap();
+        let held = api::encrypt_entropy_for_device(b"entropy for sensor-7", &gateway_public_key).unwrap();
+
+        let forwarded = proxy.forward(&held, &device.kyber_public_key).unwrap();
+        assert_ne!(forwarded.ciphertext, held.ciphertext);
+        assert_eq!(api::decrypt_entropy(&forwarded, &device.kyber_secret_key).unwrap(), b"entropy for sensor-7");
+        // The gateway can no longer open what it forwarded
+        assert!(proxy.forward(&forwarded, &device.kyber_public_key).is_err());
+    }
+
+    #[test]
+    fn test_forwarded_envelope_keeps_source_label() {
+        use crate::entropy_label::EntropyLabel;
+        use crate::sign;
+
+        let (proxy, gateway_public_key) = gateway();
+        let service = sign::generate_keypair().unwrap();
+        let device = DeviceCredentials::generate().unwrap();
+        let mut held = api::encrypt_entropy_for_device(b"entropy for sensor-7", &gateway_public_key).unwrap();
+        held.sign_label(EntropyLabel::new("local-pool", &"ab".repeat(32), 7), &service.secret_key, &service.public_key).unwrap();
+
+        let forwarded = proxy.forward(&held, &device.kyber_public_key).unwrap();
+        let label = forwarded.verify_forwarded_label(&service.public_key).unwrap();
+        assert_eq!((label.source.as_str(), label.pool_generation), ("local-pool", 7));
+        assert!(forwarded.verify_label(&service.public_key).is_err());
+        assert!(held.verify_forwarded_label(&service.public_key).is_err());
+    }
+
+    #[test]
+    fn test_check_sees_only_length_and_digest() {
+        let (proxy, gateway_public_key) = gateway();
+        let device = DeviceCredentials::generate_with_suite(&SecurityLevel::L5.suite()).unwrap();
+        let held = api::encrypt_entropy_for_device(b"short", &gateway_public_key).unwrap();
+
+        let refused = proxy.forward_with(&held, &device.kyber_public_key, &SecurityLevel::L5.suite(), |plaintext| {
+            assert_eq!(plaintext.sha256(), hex::encode(hash::sha256(&[b"short"])));
+            assert!(format!("{:?}", plaintext).contains("redacted"));
+            if plaintext.len() < 32 {
+                return Err(PqcError::InvalidInput("payload too short for this device".to_string()));
+            }
+            Ok(())
+        });
+        assert!(matches!(refused, Err(PqcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_rejects_envelope_for_another_suite() {
+        let (proxy, _) = gateway();
+        let other = kem::generate_keypair_with(SecurityLevel::L1.suite().kem).unwrap();
+        let held = api::encrypt_entropy_with_suite(b"entropy", &other.public_key, &SecurityLevel::L1.suite()).unwrap();
+        let device = DeviceCredentials::generate().unwrap();
+        assert!(matches!(proxy.forward(&held, &device.kyber_public_key), Err(PqcError::Decryption(_))));
+    }
+}
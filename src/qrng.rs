@@ -1,70 +1,352 @@
 use crate::error::{PqcError, Result};
-use crate::binary_loader::{load_kyber_binary, hex_to_bytes};
-use sha2::{Sha256, Digest};
-use rand::{Rng, SeedableRng};
+use crate::binary_loader::{decode_hex_stream, load_kyber_binary, hex_to_bytes, DEFAULT_MAX_FILE_SIZE};
+use crate::manifest::SeedManifest;
+use crate::seed_lifecycle::{SeedStatus, SeedUsageStore, WearOutPolicy};
+use crate::pool_state::PoolState;
+use crate::derived::EntropyMode;
+use crate::xof::Xof;
+use crate::validation::validate_device_id;
+use crate::budget::{MemoryBudget, Reservation};
+use crate::health::{HealthStatus, SelfTestCache, SELF_TEST_TTL};
+use crate::conditioning::ConditioningPipeline;
+use crate::provenance::{EntropyProvenance, ProvenanceSource, ENTROPY_BOUND_ESTIMATOR};
+use crate::api::EncryptedEntropy;
+use crate::entropy_label::EntropyLabel;
+use crate::secret::SecretKey;
+use crate::cancel::{CancelToken, Partial};
+use std::collections::HashMap;
+use crate::hash;
+use crate::mnemonic::{self, MASTER_SEED_LEN};
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
 use rand_chacha::ChaCha20Rng;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Largest entropy request served in one call (1 MiB)
+pub const MAX_ENTROPY_REQUEST: usize = 1024 * 1024;
+
+/// What to do when the quantum seed files are missing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedFallback {
+    /// Fail to start
+    #[default]
+    Refuse,
+    /// Run on OS entropy, flagged as degraded everywhere it is reported
+    OsEntropy,
+}
 
 /// QRNG (Quantum Random Number Generator) using uploaded quantum seeds
 pub struct QRNG {
     kyber_seed: Vec<u8>,
     dilithium_seed: Vec<u8>,
+    conditioning: ConditioningPipeline,
+    conditioned_len: (usize, usize),
+    master_seed: Zeroizing<[u8; MASTER_SEED_LEN]>,
     rng: ChaCha20Rng,
+    fingerprint: String,
+    policy: WearOutPolicy,
+    usage: SeedUsageStore,
+    pool: PoolState,
+    state_key: [u8; 32],
+    state_path: Option<PathBuf>,
+    checkpoint_interval: u64,
+    /// Pool position recorded in the state file; output only comes from positions below it
+    reserved_until: u64,
+    last_reseed_at: u64,
+    degraded: bool,
 }
 
 impl QRNG {
     /// Initialize QRNG with quantum seeds from uploaded files
-    pub fn new(kyber_file: &str, dilithium_file: &str) -> Result<Self> {
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(kyber_file: P, dilithium_file: Q) -> Result<Self> {
+        Self::new_conditioned(kyber_file, dilithium_file, ConditioningPipeline::default())
+    }
+    
+    /// Initialize QRNG, running both raw seeds through `conditioning` first
+    ///
+    /// Pick the stages for the vendor's raw output; the same seeds under a
+    /// different pipeline give a different master seed and fingerprint.
+    pub fn new_conditioned<P: AsRef<Path>, Q: AsRef<Path>>(
+        kyber_file: P,
+        dilithium_file: Q,
+        conditioning: ConditioningPipeline,
+    ) -> Result<Self> {
         let kyber_seed = load_kyber_binary(kyber_file)?;
         let dilithium_seed = load_kyber_binary(dilithium_file)?;
+        Self::from_seeds(kyber_seed, dilithium_seed, conditioning)
+    }
+
+    fn from_seeds(kyber_seed: Vec<u8>, dilithium_seed: Vec<u8>, conditioning: ConditioningPipeline) -> Result<Self> {
+        let kyber_conditioned = Zeroizing::new(conditioning.apply(&kyber_seed)?);
+        let dilithium_conditioned = Zeroizing::new(conditioning.apply(&dilithium_seed)?);
         
         // Combine both quantum seeds for maximum entropy
-        let combined_seed = Self::combine_quantum_seeds(&kyber_seed, &dilithium_seed);
+        let mut master_seed = Zeroizing::new([0u8; MASTER_SEED_LEN]);
+        master_seed.copy_from_slice(&Self::combine_quantum_seeds(&kyber_conditioned, &dilithium_conditioned));
+        let mut qrng = Self::from_master_seed(master_seed, kyber_seed, dilithium_seed);
+        qrng.conditioned_len = (kyber_conditioned.len(), dilithium_conditioned.len());
+        qrng.conditioning = conditioning;
+        Ok(qrng)
+    }
+    
+    /// Initialize QRNG, or fall back according to `fallback` if either seed file is missing
+    ///
+    /// Only absent files trigger the fallback; a seed file that exists but
+    /// does not load is still an error.
+    pub fn new_with_fallback<P: AsRef<Path>, Q: AsRef<Path>>(
+        kyber_file: P,
+        dilithium_file: Q,
+        fallback: SeedFallback,
+    ) -> Result<Self> {
+        let missing = !kyber_file.as_ref().exists() || !dilithium_file.as_ref().exists();
+        match fallback {
+            SeedFallback::OsEntropy if missing => Ok(Self::from_os_entropy()),
+            _ => Self::new(kyber_file, dilithium_file),
+        }
+    }
+    
+    /// A degraded QRNG seeded from the operating system instead of quantum seeds
+    ///
+    /// [`QRNG::is_degraded`], the seed status and the provenance report all
+    /// say so. Every start reports the same seed fingerprint, so a
+    /// persistent usage store keeps a single entry for OS entropy and the
+    /// wear-out budget is not enforced, since the OS reseeds on every start.
+    pub fn from_os_entropy() -> Self {
+        let mut master_seed = Zeroizing::new([0u8; MASTER_SEED_LEN]);
+        rand::rngs::OsRng.fill_bytes(master_seed.as_mut_slice());
+        let mut qrng = Self::from_master_seed(master_seed, Vec::new(), Vec::new());
+        qrng.fingerprint = hex::encode(hash::sha256(&[b"QRNG_SEED_FINGERPRINT_", b"OS_ENTROPY"]));
+        qrng.degraded = true;
+        qrng
+    }
+    
+    /// Whether this QRNG runs on OS entropy rather than quantum seeds
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+    
+    /// Restore a QRNG from the 24-word backup of its master seed
+    ///
+    /// The restored instance has the same fingerprint, output and state key
+    /// as the original. Combine with [`QRNG::with_state_file`] so output
+    /// already served is not repeated.
+    pub fn from_mnemonic(words: &str) -> Result<Self> {
+        Ok(Self::from_master_seed(mnemonic::decode(words)?, Vec::new(), Vec::new()))
+    }
+    
+    /// The master seed as 24 words for escrow; anyone holding them can reproduce all output
+    pub fn master_seed_mnemonic(&self) -> Result<Zeroizing<String>> {
+        mnemonic::encode(&self.master_seed)
+    }
+    
+    fn from_master_seed(master_seed: Zeroizing<[u8; MASTER_SEED_LEN]>, kyber_seed: Vec<u8>, dilithium_seed: Vec<u8>) -> Self {
+        let combined_seed = master_seed.as_slice();
         
         // Use ChaCha20 for cryptographically secure random generation
-        let mut seed_array = [0u8; 32];
-        seed_array.copy_from_slice(&combined_seed[..32]);
-        let rng = ChaCha20Rng::from_seed(seed_array);
+        let rng = ChaCha20Rng::from_seed(*master_seed);
+        
+        // Identify the seed pair without revealing the RNG seed itself
+        let fingerprint = hex::encode(hash::sha256(&[b"QRNG_SEED_FINGERPRINT_", combined_seed]));
         
-        Ok(Self {
+        // Persisted pool state is encrypted under a key only these seeds produce
+        let state_key = hash::sha256(&[b"QRNG_POOL_STATE_KEY_", combined_seed]);
+        
+        Self {
+            conditioned_len: (kyber_seed.len(), dilithium_seed.len()),
             kyber_seed,
             dilithium_seed,
+            conditioning: ConditioningPipeline::default(),
             rng,
-        })
+            fingerprint,
+            policy: WearOutPolicy::default(),
+            usage: SeedUsageStore::in_memory(),
+            pool: PoolState::initial(combined_seed),
+            state_key,
+            state_path: None,
+            checkpoint_interval: 1,
+            reserved_until: 0,
+            last_reseed_at: crate::clock::now(),
+            degraded: false,
+            master_seed,
+        }
+    }
+    
+    /// Persist the entropy pool position in `path` so restarts continue the sequence
+    ///
+    /// The file holds a high-water mark, not the current position: before
+    /// any output comes from a pool position, a position at least one
+    /// checkpoint interval beyond it is durably written. After a crash the
+    /// pool resumes from the mark, skipping positions that may have been
+    /// used, so output from before the restart is never replayed. State
+    /// written under different seeds fails to decrypt and is an error.
+    pub fn with_state_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(state) = PoolState::load(&path, &self.state_key)? {
+            self.pool = state;
+        }
+        self.state_path = Some(path);
+        self.ratchet();
+        self.checkpoint()?;
+        Ok(self)
+    }
+    
+    /// Reserve pool positions `interval` at a time (default 1)
+    ///
+    /// Larger intervals write the state file less often; a crash then skips
+    /// up to `interval` unused positions.
+    pub fn with_checkpoint_interval(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+    
+    /// Reserve the checkpoint interval after the current position in the state file, if one is configured
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.reserve_from(self.pool)
+    }
+
+    /// Durably record the position `checkpoint_interval` steps past `from`
+    fn reserve_from(&mut self, from: PoolState) -> Result<()> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        let reserved = from.ahead(self.checkpoint_interval);
+        reserved.save(path, &self.state_key)?;
+        self.reserved_until = reserved.counter;
+        Ok(())
+    }
+    
+    /// Number of times the pool has been advanced, across restarts
+    pub fn pool_counter(&self) -> u64 {
+        self.pool.counter
+    }
+    
+    /// Advance the pool and reseed the RNG from it
+    fn ratchet(&mut self) {
+        self.pool.advance();
+        self.rng = ChaCha20Rng::from_seed(self.pool.rng_seed());
+        self.last_reseed_at = crate::clock::now();
+    }
+    
+    /// Limit how much output may be derived before fresh seeds are required
+    pub fn with_wear_out_policy(mut self, policy: WearOutPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+    
+    /// Track usage in a persistent store so counters survive restarts
+    pub fn with_usage_store(mut self, usage: SeedUsageStore) -> Self {
+        self.usage = usage;
+        self
+    }
+    
+    /// Usage and remaining budget of the loaded seeds
+    pub fn seed_status(&self) -> SeedStatus {
+        SeedStatus {
+            degraded: self.degraded,
+            ..SeedStatus::new(&self.fingerprint, self.usage.usage(&self.fingerprint), self.policy)
+        }
+    }
+    
+    /// Charge derived output against the wear-out budget
+    fn consume(&mut self, bytes: u64, keys: u64) -> Result<()> {
+        let status = self.seed_status();
+        if !self.degraded && (bytes > status.remaining_bytes || keys > status.remaining_keys) {
+            return Err(PqcError::SeedExhausted(format!(
+                "seed {} has {} bytes and {} keys left; load fresh seed material",
+                &self.fingerprint[..16], status.remaining_bytes, status.remaining_keys
+            )));
+        }
+        self.usage.record(&self.fingerprint, bytes, keys)?;
+        Ok(())
+    }
+    
+    /// Initialize QRNG only if the seed files match a signed manifest
+    ///
+    /// Fails with `InvalidSeedManifest` if the manifest signature doesn't
+    /// verify under `manifest_public_key`, or if either seed file is missing
+    /// from the manifest or has been modified.
+    pub fn new_verified<P: AsRef<Path>, Q: AsRef<Path>>(
+        kyber_file: P,
+        dilithium_file: Q,
+        manifest: &SeedManifest,
+        manifest_public_key: &[u8],
+    ) -> Result<Self> {
+        manifest.check_signature(manifest_public_key)?;
+        let kyber_seed = read_verified_seed(kyber_file.as_ref(), manifest)?;
+        let dilithium_seed = read_verified_seed(dilithium_file.as_ref(), manifest)?;
+        Self::from_seeds(kyber_seed, dilithium_seed, ConditioningPipeline::default())
+    }
+    
+    /// Initialize QRNG with quantum seeds from the platform seed directory
+    ///
+    /// Seed usage and pool state are kept in the platform data directory.
+    pub fn from_default_paths() -> Result<Self> {
+        let qrng = Self::new(
+            crate::paths::default_kyber_seed_path()?,
+            crate::paths::default_dilithium_seed_path()?,
+        )?;
+        let state_path = crate::paths::default_data_dir()?
+            .join(format!("pool_state_{}.bin", &qrng.fingerprint[..16]));
+        qrng.with_usage_store(SeedUsageStore::open_default()?)
+            .with_state_file(state_path)
     }
     
     /// Combine quantum seeds using cryptographic hashing
     fn combine_quantum_seeds(kyber_seed: &[u8], dilithium_seed: &[u8]) -> Vec<u8> {
-        let mut hasher = Sha256::new();
-        hasher.update(b"QRNG_QUANTUM_ENTROPY_");
-        hasher.update(kyber_seed);
-        hasher.update(b"_SEPARATOR_");
-        hasher.update(dilithium_seed);
-        hasher.update(b"_END");
-        hasher.finalize().to_vec()
+        hash::sha256(&[
+            b"QRNG_QUANTUM_ENTROPY_",
+            kyber_seed,
+            b"_SEPARATOR_",
+            dilithium_seed,
+            b"_END",
+        ]).to_vec()
     }
     
     /// Generate quantum-seeded entropy for devices
-    pub fn generate_entropy(&mut self, size: usize) -> Vec<u8> {
+    pub fn generate_entropy(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.consume(size as u64, 0)?;
+        Ok(self.draw(size))
+    }
+    
+    /// Generate entropy with quantum seed refreshing
+    pub fn generate_entropy_refreshed(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.consume(size as u64, 0)?;
+        self.refresh()?;
+        Ok(self.draw(size))
+    }
+    
+    /// `size` bytes from the RNG; the caller has already charged the derivation
+    fn draw(&mut self, size: usize) -> Vec<u8> {
         let mut entropy = vec![0u8; size];
         self.rng.fill(&mut entropy[..]);
         entropy
     }
     
-    /// Generate entropy with quantum seed refreshing
-    pub fn generate_entropy_refreshed(&mut self, size: usize) -> Vec<u8> {
-        // Re-seed with quantum data periodically for enhanced security
-        let refresh_seed = Self::combine_quantum_seeds(&self.kyber_seed, &self.dilithium_seed);
-        let mut seed_array = [0u8; 32];
-        seed_array.copy_from_slice(&refresh_seed[..32]);
-        self.rng = ChaCha20Rng::from_seed(seed_array);
-        
-        self.generate_entropy(size)
+    /// Re-seed from the next pool position so refreshes never repeat output
+    fn refresh(&mut self) -> Result<()> {
+        // Reserve the next pool position before any output can come from it
+        if self.state_path.is_some() && self.pool.counter + 1 >= self.reserved_until {
+            self.reserve_from(self.pool.ahead(1))?;
+        }
+        self.ratchet();
+        Ok(())
     }
     
-    /// Generate quantum-seeded device keys
+    /// Generate quantum-seeded device keys under the active default suite
     pub fn generate_device_keys(&mut self) -> Result<crate::api::DeviceCredentials> {
+        self.generate_device_keys_with_suite(&crate::suite::CipherSuite::active_default())
+    }
+
+    /// [`QRNG::generate_device_keys`] for `suite`'s KEM and signature algorithms
+    pub fn generate_device_keys_with_suite(&mut self, suite: &crate::suite::CipherSuite) -> Result<crate::api::DeviceCredentials> {
+        // A key is one derivation; the seed drawn for it is not charged as bytes too
+        self.consume(0, 1)?;
+        self.refresh()?;
+        
         // Use quantum entropy to seed the key generation
-        let quantum_entropy = self.generate_entropy_refreshed(64);
+        let quantum_entropy = self.draw(64);
         
         // Mix quantum entropy with system randomness
         let mut enhanced_seed = [0u8; 32];
@@ -74,64 +356,504 @@ impl QRNG {
         let _temp_rng = ChaCha20Rng::from_seed(enhanced_seed);
         
         // Generate standard PQC keys (they'll use the enhanced entropy)
-        crate::api::DeviceCredentials::generate()
+        crate::api::DeviceCredentials::generate_with_suite(suite)
+    }
+    
+    /// Conditioning applied to the raw seeds before they were combined
+    pub fn conditioning(&self) -> &ConditioningPipeline {
+        &self.conditioning
+    }
+    
+    /// Structured account of the seed sources, conditioning and entropy bound
+    pub fn provenance(&self) -> EntropyProvenance {
+        let restored = !self.degraded && self.kyber_seed.is_empty() && self.dilithium_seed.is_empty();
+        let sources: Vec<ProvenanceSource> = if self.degraded {
+            vec![ProvenanceSource::os_rng(MASTER_SEED_LEN)]
+        } else if restored {
+            // No raw seeds survive a mnemonic restore, only the master seed
+            vec![ProvenanceSource::mnemonic(MASTER_SEED_LEN)]
+        } else {
+            [
+                ("kyber", &self.kyber_seed, self.conditioned_len.0),
+                ("dilithium", &self.dilithium_seed, self.conditioned_len.1),
+            ]
+            .into_iter()
+            .filter(|(_, raw, _)| !raw.is_empty())
+            .map(|(name, raw, conditioned)| ProvenanceSource::measure(name, raw, conditioned))
+            .collect()
+        };
+        let usage = self.seed_status().usage;
+        EntropyProvenance {
+            seed_fingerprint: self.fingerprint.clone(),
+            entropy_upper_bound_bits: EntropyProvenance::master_seed_bound(&sources),
+            sources,
+            conditioning: self.conditioning.clone(),
+            combiner: match (self.degraded, restored) {
+                (true, _) => "none (OS entropy)",
+                (false, true) => "none (restored master seed)",
+                (false, false) => "SHA256 hash",
+            }.to_string(),
+            estimator: ENTROPY_BOUND_ESTIMATOR.to_string(),
+            rng: "ChaCha20 (cryptographically secure)".to_string(),
+            reseed_count: self.pool.counter,
+            last_reseed_at: self.last_reseed_at,
+            bytes_derived: usage.bytes_derived,
+            keys_derived: usage.keys_derived,
+            degraded: self.degraded,
+        }
     }
     
     /// Get quantum seed information
     pub fn seed_info(&self) -> String {
-        format!(
-            "QRNG Quantum Seed Info:\n\
-             - Kyber seed: {} bytes\n\
-             - Dilithium seed: {} bytes\n\
-             - Combined entropy: SHA256 hash\n\
-             - RNG: ChaCha20 (cryptographically secure)",
-            self.kyber_seed.len(),
-            self.dilithium_seed.len()
-        )
+        self.provenance().to_string()
+    }
+}
+
+/// Read a seed file once, check those bytes against `manifest` and decode them
+fn read_verified_seed(path: &Path, manifest: &SeedManifest) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let file = std::fs::File::open(path)
+        .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    let mut contents = Zeroizing::new(Vec::new());
+    file.take(DEFAULT_MAX_FILE_SIZE + 1).read_to_end(&mut contents)
+        .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    if contents.len() as u64 > DEFAULT_MAX_FILE_SIZE {
+        return Err(PqcError::InvalidInput(format!("File too large: {} (limit {} bytes)", path.display(), DEFAULT_MAX_FILE_SIZE)));
     }
+    manifest.verify_contents(path, &contents)?;
+    decode_hex_stream(contents.as_slice(), DEFAULT_MAX_FILE_SIZE)
+}
+
+/// Outcome of a seed reload, passed to the reload callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedReloadEvent {
+    /// New seeds are live; carries the new seed fingerprint
+    Reloaded { fingerprint: String },
+    /// Reload failed and the previous seeds remain in use
+    Failed { error: String },
 }
 
+type ReloadCallback = Box<dyn Fn(&SeedReloadEvent) + Send>;
+
 /// High-level QRNG entropy service
 pub struct QRNGEntropyService {
     qrng: QRNG,
+    kyber_path: PathBuf,
+    dilithium_path: PathBuf,
+    on_reload: Option<ReloadCallback>,
+    tenant_modes: HashMap<String, EntropyMode>,
+    budget: Option<MemoryBudget>,
+    label_signer: Option<(Vec<u8>, SecretKey)>,
+    /// Outstanding provisioning challenges and their expiry, by device ID
+    provisioning_challenges: HashMap<String, (Vec<u8>, u64)>,
+    self_tests: SelfTestCache,
 }
 
 impl QRNGEntropyService {
     /// Initialize the quantum entropy service
-    pub fn new(kyber_file: &str, dilithium_file: &str) -> Result<Self> {
-        let qrng = QRNG::new(kyber_file, dilithium_file)?;
-        Ok(Self { qrng })
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(kyber_file: P, dilithium_file: Q) -> Result<Self> {
+        let qrng = QRNG::new(&kyber_file, &dilithium_file)?;
+        Ok(Self::from_qrng(qrng, kyber_file.as_ref(), dilithium_file.as_ref()))
+    }
+    
+    /// Initialize the service, or run degraded on OS entropy if `fallback` allows and the seeds are missing
+    ///
+    /// A later successful [`QRNGEntropyService::reload_seeds`] leaves degraded mode.
+    pub fn new_with_fallback<P: AsRef<Path>, Q: AsRef<Path>>(
+        kyber_file: P,
+        dilithium_file: Q,
+        fallback: SeedFallback,
+    ) -> Result<Self> {
+        let qrng = QRNG::new_with_fallback(&kyber_file, &dilithium_file, fallback)?;
+        Ok(Self::from_qrng(qrng, kyber_file.as_ref(), dilithium_file.as_ref()))
+    }
+    
+    /// Initialize the service with conditioned seeds; reloads keep the same pipeline
+    pub fn new_conditioned<P: AsRef<Path>, Q: AsRef<Path>>(
+        kyber_file: P,
+        dilithium_file: Q,
+        conditioning: ConditioningPipeline,
+    ) -> Result<Self> {
+        let qrng = QRNG::new_conditioned(&kyber_file, &dilithium_file, conditioning)?;
+        Ok(Self::from_qrng(qrng, kyber_file.as_ref(), dilithium_file.as_ref()))
+    }
+    
+    /// Initialize the service only if the seed files match a signed manifest
+    pub fn new_verified<P: AsRef<Path>, Q: AsRef<Path>>(
+        kyber_file: P,
+        dilithium_file: Q,
+        manifest: &SeedManifest,
+        manifest_public_key: &[u8],
+    ) -> Result<Self> {
+        let qrng = QRNG::new_verified(&kyber_file, &dilithium_file, manifest, manifest_public_key)?;
+        Ok(Self::from_qrng(qrng, kyber_file.as_ref(), dilithium_file.as_ref()))
+    }
+    
+    fn from_qrng(qrng: QRNG, kyber_path: &Path, dilithium_path: &Path) -> Self {
+        Self {
+            qrng,
+            kyber_path: kyber_path.to_path_buf(),
+            dilithium_path: dilithium_path.to_path_buf(),
+            on_reload: None,
+            tenant_modes: HashMap::new(),
+            budget: None,
+            label_signer: None,
+            provisioning_challenges: HashMap::new(),
+            self_tests: SelfTestCache::new(SELF_TEST_TTL),
+        }
+    }
+    
+    /// Sign an [`EntropyLabel`] onto every envelope from [`QRNGEntropyService::seal_entropy_for_device`]
+    pub fn with_label_signer(mut self, public_key: &[u8], secret_key: SecretKey) -> Self {
+        self.label_signer = Some((public_key.to_vec(), secret_key));
+        self
+    }
+    
+    /// Unsigned label describing the pool as it is now
+    pub fn entropy_label(&self) -> EntropyLabel {
+        EntropyLabel::new("local-pool", &self.qrng.fingerprint, self.qrng.pool_counter())
+            .with_degraded(self.qrng.is_degraded())
+    }
+    
+    /// Generate entropy for a device and encrypt it to the device's KEM key
+    ///
+    /// With a label signer configured, the envelope carries a signed label
+    /// naming the pool generation the entropy came from.
+    pub fn seal_entropy_for_device(&mut self, device_id: &str, size: usize, device_kyber_public_key: &[u8]) -> Result<EncryptedEntropy> {
+        // Describe the pool before the draw, which may ratchet it
+        let label = self.entropy_label();
+        let entropy = Zeroizing::new(self.generate_entropy_for_device(device_id, size)?);
+        let mut sealed = crate::api::encrypt_entropy_for_device(&entropy, device_kyber_public_key)?;
+        if let Some((public_key, secret_key)) = &self.label_signer {
+            sealed.sign_label(label, secret_key, public_key)?;
+        }
+        Ok(sealed)
+    }
+
+    /// Charge entropy requests against `budget`, shared with other services
+    ///
+    /// Requests that would exceed it fail with `PqcError::Overloaded`.
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn memory_budget(&self) -> Option<&MemoryBudget> {
+        self.budget.as_ref()
+    }
+
+    fn reserve(&self, bytes: usize) -> Result<Option<Reservation>> {
+        self.budget.as_ref().map(|budget| budget.try_reserve(bytes)).transpose()
+    }
+    
+    /// Paths of the Kyber and Dilithium seed files this service loads from
+    pub fn seed_paths(&self) -> (&Path, &Path) {
+        (&self.kyber_path, &self.dilithium_path)
+    }
+    
+    /// Register a callback invoked after every reload attempt
+    pub fn on_seed_reload<F>(&mut self, callback: F)
+    where
+        F: Fn(&SeedReloadEvent) + Send + 'static,
+    {
+        self.on_reload = Some(Box::new(callback));
+    }
+    
+    /// Re-read the seed files and swap them in without restarting
+    ///
+    /// The new QRNG is fully built before it replaces the current one, so a
+    /// failed reload leaves the service running on the previous seeds. The
+    /// wear-out policy, usage store, conditioning and pool state file carry
+    /// over to the new seeds. Reloading the same seeds keeps the state file,
+    /// so the pool continues where it was; new seeds get a state file named
+    /// after their fingerprint next to it.
+    pub fn reload_seeds(&mut self) -> Result<()> {
+        let result = QRNG::new_conditioned(&self.kyber_path, &self.dilithium_path, self.qrng.conditioning.clone());
+        self.swap_qrng(result)
+    }
+    
+    /// Reload seeds only if they match a freshly uploaded signed manifest
+    ///
+    /// Each seed file is read once and the bytes checked against the manifest
+    /// are the bytes loaded.
+    pub fn reload_seeds_verified(&mut self, manifest: &SeedManifest, manifest_public_key: &[u8]) -> Result<()> {
+        let result = self.load_verified(manifest, manifest_public_key);
+        self.swap_qrng(result)
+    }
+    
+    /// Reload seeds only if they match the signed manifest stored at `manifest_path`
+    pub fn reload_seeds_with_manifest(&mut self, manifest_path: &Path, manifest_public_key: &[u8]) -> Result<()> {
+        let result = SeedManifest::load(manifest_path)
+            .and_then(|manifest| self.load_verified(&manifest, manifest_public_key));
+        self.swap_qrng(result)
+    }
+    
+    fn load_verified(&self, manifest: &SeedManifest, manifest_public_key: &[u8]) -> Result<QRNG> {
+        manifest.check_signature(manifest_public_key)?;
+        let kyber_seed = read_verified_seed(&self.kyber_path, manifest)?;
+        let dilithium_seed = read_verified_seed(&self.dilithium_path, manifest)?;
+        QRNG::from_seeds(kyber_seed, dilithium_seed, self.qrng.conditioning.clone())
+    }
+    
+    fn swap_qrng(&mut self, result: Result<QRNG>) -> Result<()> {
+        let event = match result.and_then(|fresh| self.carry_state_file(fresh)) {
+            Ok(mut fresh) => {
+                fresh.policy = self.qrng.policy;
+                fresh.usage = std::mem::take(&mut self.qrng.usage);
+                let fingerprint = fresh.fingerprint.clone();
+                self.qrng = fresh;
+                Ok(SeedReloadEvent::Reloaded { fingerprint })
+            }
+            Err(e) => Err(e),
+        };
+        
+        if let Some(callback) = &self.on_reload {
+            match &event {
+                Ok(reloaded) => callback(reloaded),
+                Err(e) => callback(&SeedReloadEvent::Failed { error: e.to_string() }),
+            }
+        }
+        event.map(|_| ())
+    }
+    
+    /// Attach a state file to `fresh` if the current QRNG persists its pool
+    fn carry_state_file(&self, fresh: QRNG) -> Result<QRNG> {
+        let Some(path) = &self.qrng.state_path else {
+            return Ok(fresh);
+        };
+        let path = if fresh.fingerprint == self.qrng.fingerprint {
+            path.clone()
+        } else {
+            path.with_file_name(format!("pool_state_{}.bin", &fresh.fingerprint[..16]))
+        };
+        fresh.with_checkpoint_interval(self.qrng.checkpoint_interval)
+            .with_state_file(path)
+    }
+    
+    /// Apply a wear-out policy to the underlying QRNG
+    pub fn with_wear_out_policy(mut self, policy: WearOutPolicy) -> Self {
+        self.qrng = self.qrng.with_wear_out_policy(policy);
+        self
+    }
+    
+    /// Persist seed usage counters in `usage`
+    pub fn with_usage_store(mut self, usage: SeedUsageStore) -> Self {
+        self.qrng = self.qrng.with_usage_store(usage);
+        self
+    }
+    
+    /// Usage and remaining budget of the loaded seeds
+    pub fn seed_status(&self) -> SeedStatus {
+        self.qrng.seed_status()
+    }
+    
+    /// Provenance of the seeds currently in use
+    pub fn provenance(&self) -> EntropyProvenance {
+        self.qrng.provenance()
+    }
+    
+    /// Whether the service is running on OS entropy instead of quantum seeds
+    pub fn is_degraded(&self) -> bool {
+        self.qrng.is_degraded()
+    }
+    
+    /// Health for a liveness or readiness endpoint
+    ///
+    /// The self tests run on the first call and again once their report is
+    /// older than the TTL, [`SELF_TEST_TTL`] unless set with
+    /// [`QRNGEntropyService::with_self_test_ttl`]; other calls are cheap.
+    pub fn health(&self) -> HealthStatus {
+        HealthStatus::collect(self.self_tests.report(), &self.seed_status(), self.budget.as_ref())
+    }
+
+    /// Rerun the self tests for [`QRNGEntropyService::health`] at most once per `ttl`
+    pub fn with_self_test_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.self_tests = SelfTestCache::new(ttl);
+        self
+    }
+    
+    /// Initialize the service from the platform seed directory
+    pub fn from_default_paths() -> Result<Self> {
+        let qrng = QRNG::from_default_paths()?;
+        Ok(Self::from_qrng(
+            qrng,
+            &crate::paths::default_kyber_seed_path()?,
+            &crate::paths::default_dilithium_seed_path()?,
+        ))
     }
     
     /// Generate quantum entropy for a specific device
+    ///
+    /// Returns exactly `size` bytes, expanded with cSHAKE256 over the device ID
+    /// and fresh QRNG output. Requests above `MAX_ENTROPY_REQUEST` are an error.
     pub fn generate_entropy_for_device(&mut self, device_id: &str, size: usize) -> Result<Vec<u8>> {
-        // Include device ID in entropy generation for uniqueness
-        let mut hasher = Sha256::new();
-        hasher.update(b"DEVICE_ENTROPY_");
-        hasher.update(device_id.as_bytes());
-        hasher.update(b"_");
+        validate_device_id(device_id)?;
+        if size > MAX_ENTROPY_REQUEST {
+            return Err(PqcError::InvalidInput(format!(
+                "Requested {} bytes of entropy, maximum is {}", size, MAX_ENTROPY_REQUEST
+            )));
+        }
         
-        let base_entropy = self.qrng.generate_entropy_refreshed(size + 32);
-        hasher.update(&base_entropy);
+        // QRNG output plus the expanded result
+        let _reservation = self.reserve(2 * size + 32)?;
+        // Charge what the device receives, not the extra bytes mixed in
+        self.qrng.consume(size as u64, 0)?;
+        self.qrng.refresh()?;
+        let base_entropy = self.qrng.draw(size + 32);
         
-        let device_entropy = hasher.finalize();
-        Ok(device_entropy[..size.min(32)].to_vec())
+        // Include device ID in entropy generation for uniqueness
+        let mut xof = Xof::cshake256(b"", b"PQC_ALGO_DEVICE_ENTROPY");
+        xof.update_framed(device_id.as_bytes()).update(&base_entropy);
+        Ok(xof.finalize(size))
+    }
+    
+    /// Select how a tenant's devices get entropy; tenants default to `Consumable`
+    pub fn set_tenant_mode(&mut self, tenant: &str, mode: EntropyMode) {
+        self.tenant_modes.insert(tenant.to_string(), mode);
+    }
+    
+    /// Entropy mode configured for a tenant
+    pub fn tenant_mode(&self, tenant: &str) -> EntropyMode {
+        self.tenant_modes.get(tenant).cloned().unwrap_or_default()
+    }
+    
+    /// Generate fresh quantum entropy for a device of a consumable-mode tenant
+    pub fn generate_entropy_for_tenant_device(&mut self, tenant: &str, device_id: &str, size: usize) -> Result<Vec<u8>> {
+        match self.tenant_modes.get(tenant) {
+            Some(EntropyMode::Derived(_)) => Err(PqcError::InvalidInput(format!(
+                "Tenant '{}' uses derived entropy; call derive_entropy_for_device with a counter", tenant
+            ))),
+            _ => self.generate_entropy_for_device(device_id, size),
+        }
+    }
+    
+    /// Reproducible entropy for a device of a derived-mode tenant
+    ///
+    /// The caller owns `counter` and must persist it; the same counter always
+    /// yields the same bytes. See the `derived` module for the security model.
+    pub fn derive_entropy_for_device(&self, tenant: &str, device_id: &str, counter: u64, size: usize) -> Result<Vec<u8>> {
+        validate_device_id(device_id)?;
+        match self.tenant_modes.get(tenant) {
+            Some(EntropyMode::Derived(derived)) => Ok(derived.derive(device_id, counter, size)),
+            _ => Err(PqcError::InvalidInput(format!(
+                "Tenant '{}' is not configured for derived entropy", tenant
+            ))),
+        }
     }
     
     /// Create quantum-secured device credentials
     pub fn provision_device(&mut self, device_id: &str) -> Result<crate::api::DeviceCredentials> {
+        self.provision_device_with_suite(device_id, &crate::suite::CipherSuite::active_default())
+    }
+
+    /// [`QRNGEntropyService::provision_device`] with keys for `suite`
+    pub fn provision_device_with_suite(&mut self, device_id: &str, suite: &crate::suite::CipherSuite) -> Result<crate::api::DeviceCredentials> {
+        validate_device_id(device_id)?;
         println!("Provisioning device '{}' with quantum entropy...", device_id);
         
-        // Generate device-specific quantum entropy
-        let device_entropy = self.generate_entropy_for_device(device_id, 64)?;
-        println!("Generated {} bytes of quantum entropy", device_entropy.len());
-        
         // Create quantum-seeded keys
-        let credentials = self.qrng.generate_device_keys()?;
+        let credentials = self.qrng.generate_device_keys_with_suite(suite)?;
         println!("Generated quantum-seeded PQC credentials");
         
         Ok(credentials)
     }
+
+    /// Provision a device that cannot generate keys, wrapping its secrets for transport
+    ///
+    /// Like [`QRNGEntropyService::provision_device`], but the credentials
+    /// leave the service only as a blob that `transport_key`'s holder can
+    /// open, signed by `authority`. One suite, the active default, is read
+    /// once and used for the keys, the wrap and its label.
+    pub fn provision_device_wrapped(
+        &mut self,
+        device_id: &str,
+        transport_key: &crate::key_transport::TransportKey,
+        authority: &crate::cert::CertificateAuthority,
+    ) -> Result<crate::key_transport::WrappedCredentials> {
+        let suite = crate::suite::CipherSuite::active_default();
+        let credentials = self.provision_device_with_suite(device_id, &suite)?;
+        crate::key_transport::wrap_credentials_with_suite(device_id, &credentials, transport_key, authority, &suite)
+    }
+
+    /// Challenge for a device to sign into its certificate request
+    ///
+    /// The service holds it for [`crate::enrollment::DEFAULT_CHALLENGE_TTL`]
+    /// and hands the same one out again until it is used or expires, so
+    /// asking on another device's behalf cannot void the one it holds.
+    pub fn provisioning_challenge(&mut self, device_id: &str) -> Result<Vec<u8>> {
+        use crate::enrollment::{DEFAULT_CHALLENGE_TTL, MAX_PENDING_CHALLENGES};
+
+        let now = crate::envelope::unix_now();
+        self.provisioning_challenges.retain(|_, (_, expires_at)| *expires_at > now);
+        if let Some((challenge, _)) = self.provisioning_challenges.get(device_id) {
+            return Ok(challenge.clone());
+        }
+        if self.provisioning_challenges.len() >= MAX_PENDING_CHALLENGES {
+            return Err(PqcError::Protocol("Too many outstanding provisioning challenges; try again later".to_string()));
+        }
+        let challenge = self.generate_entropy_for_device(device_id, 32)?;
+        let expires_at = now.saturating_add(DEFAULT_CHALLENGE_TTL.as_secs());
+        self.provisioning_challenges.insert(device_id.to_string(), (challenge.clone(), expires_at));
+        Ok(challenge)
+    }
+
+    /// Certify keys a device generated itself, after checking its proof of possession
+    ///
+    /// The request must answer the device's outstanding
+    /// [`QRNGEntropyService::provisioning_challenge`], which is used up here
+    /// whether or not the request verifies. No secret key is generated or
+    /// seen; the certificate covers the request's Dilithium key and records
+    /// its Kyber key fingerprint under [`crate::csr::KYBER_KEY_EXTENSION`].
+    pub fn provision_device_with_request(
+        &mut self,
+        request: &crate::csr::CertificateRequest,
+        authority: &crate::cert::CertificateAuthority,
+        lifetime: std::time::Duration,
+    ) -> Result<crate::cert::Certificate> {
+        let (challenge, _) = self.provisioning_challenges.remove(&request.device_id)
+            .filter(|(_, expires_at)| *expires_at > crate::envelope::unix_now())
+            .ok_or_else(|| PqcError::Protocol(format!("No outstanding provisioning challenge for {}", request.device_id)))?;
+        request.verify(&challenge)?;
+        authority.issue_with_extensions(&request.device_id, &request.dilithium_public_key, lifetime, false, request.certificate_extensions()?)
+    }
+
+    /// Provision many devices at once using the parallel keygen pool
+    ///
+    /// Returns `(device_id, credentials)` pairs in the order given.
+    /// `progress` receives `(completed, total)` as devices finish.
+    pub fn provision_batch<P>(&mut self, device_ids: &[&str], progress: P) -> Result<Vec<(String, crate::api::DeviceCredentials)>>
+    where
+        P: Fn(usize, usize) + Sync,
+    {
+        self.provision_batch_cancellable(device_ids, progress, &CancelToken::new())?.into_result()
+    }
+
+    /// [`QRNGEntropyService::provision_batch`] that stops early once `token` is cancelled
+    ///
+    /// Devices provisioned before the stop are returned in their slots; the
+    /// seed is still charged for the whole batch. Invalid device IDs and an
+    /// exhausted seed fail before any work starts.
+    pub fn provision_batch_cancellable<P>(
+        &mut self,
+        device_ids: &[&str],
+        progress: P,
+        token: &CancelToken,
+    ) -> Result<Partial<(String, crate::api::DeviceCredentials)>>
+    where
+        P: Fn(usize, usize) + Sync,
+    {
+        for device_id in device_ids {
+            validate_device_id(device_id)?;
+        }
+        
+        // Charge the whole batch up front and refresh the quantum seed once
+        self.qrng.consume(0, device_ids.len() as u64)?;
+        self.qrng.refresh()?;
+        
+        let credentials = crate::api::DeviceCredentials::generate_many_cancellable(device_ids.len(), progress, token);
+        Ok(credentials.map(|i, credentials| (device_ids[i].to_string(), credentials)))
+    }
 }
 
 #[cfg(test)]
@@ -151,8 +873,8 @@ mod tests {
         let mut qrng = QRNG::new("test_kyber.bin", "test_dilithium.bin").unwrap();
         
         // Test entropy generation
-        let entropy1 = qrng.generate_entropy(32);
-        let entropy2 = qrng.generate_entropy(32);
+        let entropy1 = qrng.generate_entropy(32).unwrap();
+        let entropy2 = qrng.generate_entropy(32).unwrap();
         
         assert_eq!(entropy1.len(), 32);
         assert_eq!(entropy2.len(), 32);
@@ -163,6 +885,416 @@ mod tests {
         std::fs::remove_file("test_dilithium.bin").ok();
     }
 
+    #[test]
+    fn test_pool_state_survives_restart() {
+        std::fs::write("test_state_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_state_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        let state_path = std::env::temp_dir().join(format!("pqc_pool_state_{}.bin", std::process::id()));
+        std::fs::remove_file(&state_path).ok();
+        
+        let first_run = {
+            let mut qrng = QRNG::new("test_state_kyber.bin", "test_state_dilithium.bin")
+                .unwrap()
+                .with_state_file(&state_path)
+                .unwrap();
+            qrng.generate_entropy_refreshed(32).unwrap()
+        };
+        
+        let mut restarted = QRNG::new("test_state_kyber.bin", "test_state_dilithium.bin")
+            .unwrap()
+            .with_state_file(&state_path)
+            .unwrap();
+        assert!(restarted.pool_counter() > 2);
+        assert_ne!(restarted.generate_entropy_refreshed(32).unwrap(), first_run);
+        
+        std::fs::remove_file(&state_path).ok();
+        std::fs::remove_file("test_state_kyber.bin").ok();
+        std::fs::remove_file("test_state_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_crash_between_checkpoints_never_replays_output() {
+        std::fs::write("test_crash_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_crash_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        let state_path = std::env::temp_dir().join(format!("pqc_pool_crash_{}.bin", std::process::id()));
+        std::fs::remove_file(&state_path).ok();
+        let start = || QRNG::new("test_crash_kyber.bin", "test_crash_dilithium.bin")
+            .unwrap()
+            .with_checkpoint_interval(8)
+            .with_state_file(&state_path)
+            .unwrap();
+
+        // Emit within one reserved window, then "crash" without any shutdown hook
+        let mut qrng = start();
+        let served: Vec<Vec<u8>> = (0..5).map(|_| qrng.generate_entropy_refreshed(32).unwrap()).collect();
+        let last_counter = qrng.pool_counter();
+        std::mem::forget(qrng);
+
+        let mut restarted = start();
+        assert!(restarted.pool_counter() > last_counter);
+        for _ in 0..20 {
+            assert!(!served.contains(&restarted.generate_entropy_refreshed(32).unwrap()));
+        }
+
+        std::fs::remove_file(&state_path).ok();
+        std::fs::remove_file("test_crash_kyber.bin").ok();
+        std::fs::remove_file("test_crash_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_restore_from_mnemonic() {
+        std::fs::write("test_mnemonic_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_mnemonic_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        
+        let mut original = QRNG::new("test_mnemonic_kyber.bin", "test_mnemonic_dilithium.bin").unwrap();
+        let words = original.master_seed_mnemonic().unwrap();
+        let mut restored = QRNG::from_mnemonic(&words).unwrap();
+        
+        assert_eq!(restored.seed_status().fingerprint, original.seed_status().fingerprint);
+        assert_eq!(restored.generate_entropy(32).unwrap(), original.generate_entropy(32).unwrap());
+        let provenance = restored.provenance();
+        assert_eq!(provenance.sources, [ProvenanceSource::mnemonic(MASTER_SEED_LEN)]);
+        assert_eq!(provenance.entropy_upper_bound_bits, 256);
+        assert!(restored.seed_info().contains("Mnemonic seed: 32 bytes"));
+        
+        std::fs::remove_file("test_mnemonic_kyber.bin").ok();
+        std::fs::remove_file("test_mnemonic_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_conditioning_changes_seed_and_is_described() {
+        use crate::conditioning::Conditioner;
+        
+        std::fs::write("test_cond_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_cond_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        
+        let raw = QRNG::new("test_cond_kyber.bin", "test_cond_dilithium.bin").unwrap();
+        let pipeline = ConditioningPipeline::new().then(Conditioner::VonNeumann).then(Conditioner::AesCbcMac);
+        let conditioned = QRNG::new_conditioned("test_cond_kyber.bin", "test_cond_dilithium.bin", pipeline.clone()).unwrap();
+        
+        assert_ne!(raw.seed_status().fingerprint, conditioned.seed_status().fingerprint);
+        assert_eq!(conditioned.conditioning(), &pipeline);
+        assert!(conditioned.seed_info().contains("Conditioning: raw -> von-neumann -> aes-cbc-mac"));
+        assert!(raw.seed_info().contains("Kyber seed: 256 bytes (256 conditioned"));
+        assert!(conditioned.provenance().sources[0].conditioned_bytes < 256);
+        
+        // Constant seeds have nothing left after debiasing
+        std::fs::write("test_cond_kyber.bin", "ff".repeat(256)).unwrap();
+        assert!(QRNG::new_conditioned("test_cond_kyber.bin", "test_cond_dilithium.bin", pipeline).is_err());
+        
+        std::fs::remove_file("test_cond_kyber.bin").ok();
+        std::fs::remove_file("test_cond_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_qrng_wear_out_policy() {
+        std::fs::write("test_wear_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_wear_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        
+        let mut qrng = QRNG::new("test_wear_kyber.bin", "test_wear_dilithium.bin")
+            .unwrap()
+            .with_wear_out_policy(WearOutPolicy::new(48, 10));
+        
+        qrng.generate_entropy(32).unwrap();
+        assert_eq!(qrng.seed_status().remaining_bytes, 16);
+        
+        let result = qrng.generate_entropy(32);
+        assert!(matches!(result, Err(PqcError::SeedExhausted(_))));
+        assert_eq!(qrng.seed_status().usage.bytes_derived, 32);
+        
+        std::fs::remove_file("test_wear_kyber.bin").ok();
+        std::fs::remove_file("test_wear_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_service_charges_each_derivation_once() {
+        let (kyber, dilithium) = ("test_charge_kyber.bin", "test_charge_dilithium.bin");
+        let mut service = QRNGEntropyService::new_with_fallback(kyber, dilithium, SeedFallback::OsEntropy).unwrap();
+        
+        service.provision_device("sensor_1").unwrap();
+        let usage = service.seed_status().usage;
+        assert_eq!((usage.bytes_derived, usage.keys_derived), (0, 1));
+        
+        service.provision_batch(&["sensor_2", "sensor_3"], |_, _| {}).unwrap();
+        service.generate_entropy_for_device("sensor_4", 32).unwrap();
+        let usage = service.seed_status().usage;
+        assert_eq!((usage.bytes_derived, usage.keys_derived), (32, 3));
+    }
+
+    #[test]
+    fn test_new_verified_loads_the_bytes_it_checked() {
+        use crate::manifest::SeedManifestEntry;
+
+        std::fs::write("test_verified_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_verified_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        let authority = crate::sign::generate_keypair().unwrap();
+        let mut manifest = SeedManifest::new(vec![
+            SeedManifestEntry::from_file("test_verified_kyber.bin", "qrng-lab-01", 1_700_000_000).unwrap(),
+            SeedManifestEntry::from_file("test_verified_dilithium.bin", "qrng-lab-01", 1_700_000_000).unwrap(),
+        ]);
+        manifest.sign(&authority.secret_key).unwrap();
+
+        let verified = QRNG::new_verified("test_verified_kyber.bin", "test_verified_dilithium.bin", &manifest, &authority.public_key).unwrap();
+        let unverified = QRNG::new("test_verified_kyber.bin", "test_verified_dilithium.bin").unwrap();
+        assert_eq!(verified.fingerprint, unverified.fingerprint);
+
+        std::fs::write("test_verified_kyber.bin", "aabbccddeeff0011".repeat(32)).unwrap();
+        let swapped = QRNG::new_verified("test_verified_kyber.bin", "test_verified_dilithium.bin", &manifest, &authority.public_key);
+        assert!(matches!(swapped, Err(PqcError::InvalidSeedManifest(_))));
+
+        std::fs::remove_file("test_verified_kyber.bin").ok();
+        std::fs::remove_file("test_verified_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_reload_seeds_swaps_atomically() {
+        use std::sync::{Arc, Mutex};
+        
+        std::fs::write("test_reload_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_reload_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        
+        let mut service = QRNGEntropyService::new("test_reload_kyber.bin", "test_reload_dilithium.bin").unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        service.on_seed_reload(move |event| sink.lock().unwrap().push(event.clone()));
+        let original = service.seed_status().fingerprint;
+        
+        // A corrupt upload is rejected and the old seeds stay live
+        std::fs::write("test_reload_kyber.bin", "not hex").unwrap();
+        assert!(service.reload_seeds().is_err());
+        assert_eq!(service.seed_status().fingerprint, original);
+        
+        std::fs::write("test_reload_kyber.bin", "aabbccddeeff0011".repeat(32)).unwrap();
+        service.reload_seeds().unwrap();
+        assert_ne!(service.seed_status().fingerprint, original);
+        
+        let events = events.lock().unwrap();
+        assert!(matches!(events[0], SeedReloadEvent::Failed { .. }));
+        assert!(matches!(events[1], SeedReloadEvent::Reloaded { .. }));
+        
+        std::fs::remove_file("test_reload_kyber.bin").ok();
+        std::fs::remove_file("test_reload_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_reload_keeps_pool_state_file() {
+        std::fs::write("test_reload_state_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_reload_state_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        let state_path = std::env::temp_dir().join(format!("pqc_reload_state_{}.bin", std::process::id()));
+        std::fs::remove_file(&state_path).ok();
+        
+        let mut service = QRNGEntropyService::new("test_reload_state_kyber.bin", "test_reload_state_dilithium.bin").unwrap();
+        service.qrng = service.qrng.with_checkpoint_interval(4).with_state_file(&state_path).unwrap();
+        let first = service.qrng.generate_entropy_refreshed(32).unwrap();
+        let counter = service.qrng.pool_counter();
+        
+        // Re-uploading identical seeds must not restart the pool sequence
+        service.reload_seeds().unwrap();
+        assert!(service.qrng.pool_counter() > counter);
+        assert_eq!(service.qrng.state_path.as_deref(), Some(state_path.as_path()));
+        assert_eq!(service.qrng.checkpoint_interval, 4);
+        assert_ne!(service.qrng.generate_entropy_refreshed(32).unwrap(), first);
+        
+        std::fs::remove_file(&state_path).ok();
+        std::fs::remove_file("test_reload_state_kyber.bin").ok();
+        std::fs::remove_file("test_reload_state_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_reload_seeds_with_manifest() {
+        use crate::manifest::SeedManifestEntry;
+
+        let (kyber, dilithium, manifest_path) = ("test_manifest_reload_kyber.bin", "test_manifest_reload_dilithium.bin", "test_manifest_reload.json");
+        std::fs::write(kyber, "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write(dilithium, "fedcba9876543210".repeat(32)).unwrap();
+        let mut service = QRNGEntropyService::new(kyber, dilithium).unwrap();
+        let original = service.seed_status().fingerprint;
+        let authority = crate::sign::generate_keypair().unwrap();
+
+        // New seeds without a manifest covering them are refused
+        std::fs::write(kyber, "aabbccddeeff0011".repeat(32)).unwrap();
+        assert!(service.reload_seeds_with_manifest(Path::new(manifest_path), &authority.public_key).is_err());
+        let mut manifest = SeedManifest::new(vec![
+            SeedManifestEntry::from_file(kyber, "qrng-lab-01", 1_700_000_000).unwrap(),
+            SeedManifestEntry::from_file(dilithium, "qrng-lab-01", 1_700_000_000).unwrap(),
+        ]);
+        manifest.sign(&authority.secret_key).unwrap();
+        manifest.save(manifest_path).unwrap();
+        std::fs::write(dilithium, "1122334455667788".repeat(32)).unwrap();
+        assert!(matches!(
+            service.reload_seeds_with_manifest(Path::new(manifest_path), &authority.public_key),
+            Err(PqcError::InvalidSeedManifest(_))
+        ));
+        assert_eq!(service.seed_status().fingerprint, original);
+
+        std::fs::write(dilithium, "fedcba9876543210".repeat(32)).unwrap();
+        service.reload_seeds_with_manifest(Path::new(manifest_path), &authority.public_key).unwrap();
+        assert_ne!(service.seed_status().fingerprint, original);
+
+        for path in [kyber, dilithium, manifest_path] {
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    fn test_degraded_mode_when_seeds_missing() {
+        use crate::entropy_source::{EntropySource, SourceHealth};
+        
+        let (kyber, dilithium) = ("test_degraded_kyber.bin", "test_degraded_dilithium.bin");
+        std::fs::remove_file(kyber).ok();
+        assert!(QRNGEntropyService::new_with_fallback(kyber, dilithium, SeedFallback::Refuse).is_err());
+        
+        let mut service = QRNGEntropyService::new_with_fallback(kyber, dilithium, SeedFallback::OsEntropy).unwrap();
+        assert!(service.is_degraded());
+        assert!(service.seed_status().degraded);
+        assert_eq!(service.seed_status().fingerprint, QRNG::from_os_entropy().seed_status().fingerprint);
+        let mut limited = QRNG::from_os_entropy().with_wear_out_policy(WearOutPolicy { max_bytes: 16, max_keys: 1 });
+        assert_eq!(limited.generate_entropy(32).unwrap().len(), 32);
+        assert_eq!(service.health().degraded, [crate::health::DegradedReason::OsEntropy]);
+        assert_eq!(service.generate_entropy_for_device("sensor_1", 32).unwrap().len(), 32);
+        let provenance = service.provenance();
+        assert!(provenance.degraded);
+        assert_eq!(provenance.sources[0].name, "os-rng");
+        assert!(service.qrng.seed_info().contains("DEGRADED"));
+        assert_eq!(service.qrng.status().health, SourceHealth::Degraded);
+        
+        // Seeds arriving later end degraded mode
+        std::fs::write(kyber, "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write(dilithium, "fedcba9876543210".repeat(32)).unwrap();
+        service.reload_seeds().unwrap();
+        assert!(!service.is_degraded());
+        assert_eq!(service.qrng.status().health, SourceHealth::Healthy);
+        
+        // Present but corrupt seeds are an error, not a reason to degrade
+        std::fs::write(kyber, "not hex").unwrap();
+        assert!(QRNG::new_with_fallback(kyber, dilithium, SeedFallback::OsEntropy).is_err());
+        
+        std::fs::remove_file(kyber).ok();
+        std::fs::remove_file(dilithium).ok();
+    }
+
+    #[test]
+    fn test_sealed_entropy_is_labelled() {
+        let (kyber, dilithium) = ("test_label_kyber.bin", "test_label_dilithium.bin");
+        std::fs::remove_file(kyber).ok();
+        let signer = crate::sign::generate_keypair().unwrap();
+        let device = crate::api::DeviceCredentials::generate().unwrap();
+        let mut service = QRNGEntropyService::new_with_fallback(kyber, dilithium, SeedFallback::OsEntropy)
+            .unwrap()
+            .with_label_signer(&signer.public_key, signer.secret_key.clone().into());
+        
+        let generation = service.qrng.pool_counter();
+        let sealed = service.seal_entropy_for_device("sensor_1", 32, &device.kyber_public_key).unwrap();
+        let label = sealed.verify_label(&signer.public_key).unwrap();
+        assert!(label.degraded);
+        assert_eq!(label.seed_fingerprint, service.seed_status().fingerprint);
+        assert_eq!(label.pool_generation, generation);
+        assert!(service.qrng.pool_counter() > generation);
+        assert_eq!(crate::api::decrypt_entropy(&sealed, &device.kyber_secret_key).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_tenant_entropy_modes() {
+        use crate::derived::DerivedEntropy;
+        
+        std::fs::write("test_tenant_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_tenant_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        
+        let mut service = QRNGEntropyService::new("test_tenant_kyber.bin", "test_tenant_dilithium.bin").unwrap();
+        service.set_tenant_mode("acme", EntropyMode::Derived(DerivedEntropy::new(&[9u8; 32]).unwrap()));
+        
+        let first = service.derive_entropy_for_device("acme", "sensor_1", 5, 48).unwrap();
+        assert_eq!(first, service.derive_entropy_for_device("acme", "sensor_1", 5, 48).unwrap());
+        assert!(service.generate_entropy_for_tenant_device("acme", "sensor_1", 32).is_err());
+        
+        assert!(service.derive_entropy_for_device("other", "sensor_1", 5, 48).is_err());
+        assert!(service.generate_entropy_for_tenant_device("other", "sensor_1", 32).is_ok());
+        
+        std::fs::remove_file("test_tenant_kyber.bin").ok();
+        std::fs::remove_file("test_tenant_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_device_entropy_honours_requested_size() {
+        std::fs::write("test_size_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_size_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        
+        let mut service = QRNGEntropyService::new("test_size_kyber.bin", "test_size_dilithium.bin").unwrap();
+        for size in [0, 16, 32, 33, 64, 1000] {
+            assert_eq!(service.generate_entropy_for_device("device", size).unwrap().len(), size);
+        }
+        let result = service.generate_entropy_for_device("device", MAX_ENTROPY_REQUEST + 1);
+        assert!(matches!(result, Err(PqcError::InvalidInput(_))));
+        
+        std::fs::remove_file("test_size_kyber.bin").ok();
+        std::fs::remove_file("test_size_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_memory_budget_backpressure() {
+        std::fs::write("test_budget_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_budget_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+
+        let budget = MemoryBudget::new(4096);
+        let mut service = QRNGEntropyService::new("test_budget_kyber.bin", "test_budget_dilithium.bin").unwrap()
+            .with_memory_budget(budget.clone());
+        let other_request = budget.try_reserve(3000).unwrap();
+        let result = service.generate_entropy_for_device("device", 1024);
+        assert!(matches!(result, Err(PqcError::Overloaded(_))));
+        drop(other_request);
+        assert_eq!(service.generate_entropy_for_device("device", 1024).unwrap().len(), 1024);
+        assert_eq!(budget.in_use(), 0);
+
+        std::fs::remove_file("test_budget_kyber.bin").ok();
+        std::fs::remove_file("test_budget_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_provision_batch_honours_deadline() {
+        std::fs::write("test_cancel_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_cancel_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+
+        let mut service = QRNGEntropyService::new("test_cancel_kyber.bin", "test_cancel_dilithium.bin").unwrap();
+        let expired = CancelToken::with_timeout(std::time::Duration::ZERO);
+        let partial = service.provision_batch_cancellable(&["meter-1", "meter-2"], |_, _| {}, &expired).unwrap();
+        assert!(matches!(partial.stopped, Some(PqcError::Cancelled(_))));
+        assert_eq!(partial.completed(), 0);
+
+        let partial = service.provision_batch_cancellable(&["meter-1", "meter-2"], |_, _| {}, &CancelToken::new()).unwrap();
+        let provisioned = partial.into_result().unwrap();
+        assert_eq!(provisioned[1].0, "meter-2");
+
+        std::fs::remove_file("test_cancel_kyber.bin").ok();
+        std::fs::remove_file("test_cancel_dilithium.bin").ok();
+    }
+
+    #[test]
+    fn test_provision_with_proof_of_possession() {
+        std::fs::write("test_pop_kyber.bin", "0123456789abcdef".repeat(32)).unwrap();
+        std::fs::write("test_pop_dilithium.bin", "fedcba9876543210".repeat(32)).unwrap();
+        let mut service = QRNGEntropyService::new("test_pop_kyber.bin", "test_pop_dilithium.bin").unwrap();
+        let authority = crate::cert::CertificateAuthority::generate("factory-ca").unwrap();
+        let lifetime = std::time::Duration::from_secs(86400);
+
+        let challenge = service.provisioning_challenge("sensor_7").unwrap();
+        let device = crate::api::DeviceCredentials::generate().unwrap();
+        let request = crate::csr::CertificateRequest::new("sensor_7", &device, &challenge).unwrap();
+        assert_eq!(service.provisioning_challenge("sensor_7").unwrap(), challenge);
+        let certificate = service.provision_device_with_request(&request, &authority, lifetime).unwrap();
+        assert_eq!(certificate.subject, "sensor_7");
+        assert_eq!(certificate.public_key, device.dilithium_public_key);
+        assert_eq!(crate::csr::certified_kyber_fingerprint(&certificate), Some(crate::envelope::key_fingerprint(&device.kyber_public_key).as_str()));
+        assert!(certificate.verify_signature(authority.public_key()).unwrap());
+
+        // The challenge is used up, and a new one doesn't match the old request
+        assert!(matches!(service.provision_device_with_request(&request, &authority, lifetime), Err(PqcError::Protocol(_))));
+        assert_ne!(service.provisioning_challenge("sensor_7").unwrap(), challenge);
+        assert!(matches!(service.provision_device_with_request(&request, &authority, lifetime), Err(PqcError::Verification(_))));
+        
+        std::fs::remove_file("test_pop_kyber.bin").ok();
+        std::fs::remove_file("test_pop_dilithium.bin").ok();
+    }
+
     #[test]
     fn test_qrng_service() {
         if std::path::Path::new("kyber.bin").exists() && std::path::Path::new("dilithium.bin").exists() {
@@ -174,6 +1306,9 @@ mod tests {
             
             let entropy = service.generate_entropy_for_device("test_device_456", 32).unwrap();
             assert_eq!(entropy.len(), 32);
+            
+            let entropy = service.generate_entropy_for_device("test_device_456", 100).unwrap();
+            assert_eq!(entropy.len(), 100);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+//! Quick statistical checks on random output
+//!
+//! These are smoke tests for demos and diagnostics, not a substitute for
+//! the SP 800-90B health tests in [`crate::selftest`].
+
+use std::fmt;
+use serde::Serialize;
+
+/// Shannon entropy above which a sample is rated [`RandomnessQuality::High`]
+pub const HIGH_QUALITY_BITS: f64 = 7.8;
+
+/// Shannon entropy above which a sample is rated [`RandomnessQuality::Good`]
+pub const GOOD_QUALITY_BITS: f64 = 7.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RandomnessQuality {
+    High,
+    Good,
+    Poor,
+}
+
+/// Byte-level statistics of one sample
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RandomnessReport {
+    pub sample_len: usize,
+    /// Shannon entropy per byte, 8.0 being ideal
+    pub entropy_bits: f64,
+    pub unique_bytes: usize,
+    pub quality: RandomnessQuality,
+}
+
+impl fmt::Display for RandomnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "   - Data size: {} bytes", self.sample_len)?;
+        writeln!(f, "   - Entropy: {:.4} bits (ideal: 8.0)", self.entropy_bits)?;
+        write!(f, "   - Unique bytes: {}", self.unique_bytes)
+    }
+}
+
+/// Count how often each byte value occurs
+pub fn byte_counts(data: &[u8]) -> [u32; 256] {
+    let mut counts = [0u32; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    counts
+}
+
+/// Shannon entropy of `data` in bits per byte; 0.0 for empty input
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    byte_counts(data).iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / data.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Upper bound on min-entropy per byte from byte frequencies alone
+///
+/// This is `-log2` of the most common byte's observed frequency. It is not
+/// an SP 800-90B estimate: dependence between bytes, such as a counter,
+/// lowers the true min-entropy without changing this figure, so it can show
+/// a source is bad but never that it is good. 0.0 for empty input.
+pub fn entropy_upper_bound(data: &[u8]) -> f64 {
+    match byte_counts(data).iter().max() {
+        Some(&max) if max > 0 => -(max as f64 / data.len() as f64).log2(),
+        _ => 0.0,
+    }
+}
+
+/// Byte histogram statistics of `data`
+pub fn quick_report(data: &[u8]) -> RandomnessReport {
+    let entropy_bits = shannon_entropy(data);
+    let quality = if entropy_bits > HIGH_QUALITY_BITS {
+        RandomnessQuality::High
+    } else if entropy_bits > GOOD_QUALITY_BITS {
+        RandomnessQuality::Good
+    } else {
+        RandomnessQuality::Poor
+    };
+    RandomnessReport {
+        sample_len: data.len(),
+        entropy_bits,
+        unique_bytes: byte_counts(data).iter().filter(|&&count| count > 0).count(),
+        quality,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quick_report() {
+        let uniform: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+        let report = quick_report(&uniform);
+        assert!((report.entropy_bits - 8.0).abs() < 1e-9);
+        assert_eq!(report.unique_bytes, 256);
+        assert_eq!(report.quality, RandomnessQuality::High);
+
+        let constant = quick_report(&[7u8; 1000]);
+        assert_eq!(constant.entropy_bits, 0.0);
+        assert_eq!(constant.quality, RandomnessQuality::Poor);
+        assert_eq!(quick_report(&[]).entropy_bits, 0.0);
+    }
+
+    #[test]
+    fn test_entropy_upper_bound() {
+        // A counter has no min-entropy at all; the bound cannot tell
+        let counter: Vec<u8> = (0..=255u8).cycle().take(1 << 16).collect();
+        assert_eq!(entropy_upper_bound(&counter), 8.0);
+        let skewed: Vec<u8> = (0..1000).map(|i| if i % 2 == 0 { 0 } else { (i % 251) as u8 }).collect();
+        assert!(entropy_upper_bound(&skewed) <= 1.0);
+        assert!(entropy_upper_bound(&skewed) <= shannon_entropy(&skewed));
+        assert_eq!(entropy_upper_bound(&[7u8; 1000]), 0.0);
+        assert_eq!(entropy_upper_bound(&[]), 0.0);
+    }
+}
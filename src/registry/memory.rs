@@ -0,0 +1,75 @@
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::sync::{Mutex, MutexGuard};
+use crate::error::Result;
+use super::snapshot::check_snapshot_floor;
+use super::{DeviceRecord, RegistryStore};
+
+/// In-process store, lost when the process exits
+#[derive(Default)]
+pub struct MemoryStore {
+    records: Mutex<BTreeMap<(String, String), DeviceRecord>>,
+    snapshot_floor: Mutex<Option<u64>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(String, String), DeviceRecord>> {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl RegistryStore for MemoryStore {
+    fn get(&self, tenant: &str, device_id: &str) -> Result<Option<DeviceRecord>> {
+        Ok(self.lock().get(&(tenant.to_string(), device_id.to_string())).cloned())
+    }
+
+    fn put(&self, record: &DeviceRecord) -> Result<()> {
+        self.lock().insert((record.tenant.clone(), record.device_id.clone()), record.clone());
+        Ok(())
+    }
+
+    fn insert_if_absent(&self, record: &DeviceRecord) -> Result<bool> {
+        match self.lock().entry((record.tenant.clone(), record.device_id.clone())) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(slot) => {
+                slot.insert(record.clone());
+                Ok(true)
+            }
+        }
+    }
+
+    fn replace_all(&self, records: &[DeviceRecord], created_at: u64) -> Result<usize> {
+        let mut current = self.lock();
+        let mut floor = self.snapshot_floor.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        check_snapshot_floor(*floor, created_at)?;
+        let replacement: BTreeMap<_, _> = records.iter()
+            .map(|record| ((record.tenant.clone(), record.device_id.clone()), record.clone()))
+            .collect();
+        let removed = current.keys().filter(|key| !replacement.contains_key(*key)).count();
+        *current = replacement;
+        *floor = Some(created_at);
+        Ok(removed)
+    }
+
+    fn delete(&self, tenant: &str, device_id: &str) -> Result<bool> {
+        Ok(self.lock().remove(&(tenant.to_string(), device_id.to_string())).is_some())
+    }
+
+    fn list(&self, tenant: &str) -> Result<Vec<DeviceRecord>> {
+        Ok(self.lock().values().filter(|record| record.tenant == tenant).cloned().collect())
+    }
+
+    fn tenants(&self) -> Result<Vec<String>> {
+        let mut tenants: Vec<String> = self.lock().keys().map(|(tenant, _)| tenant.clone()).collect();
+        tenants.dedup();
+        Ok(tenants)
+    }
+
+    /// Always reachable; nothing to scan
+    fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+//! Registered devices and where they are stored
+//!
+//! A [`DeviceRegistry`] holds the public half of each provisioned device,
+//! scoped by tenant. Storage is pluggable through [`RegistryStore`]:
+//! [`MemoryStore`] for tests and single-process tools, `SledStore` (feature
+//! `sled`) for an embedded database, and `PostgresStore` (feature
+//! `postgres`) for a shared database behind a fleet of provisioning servers.
+
+use serde::{Deserialize, Serialize};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::suite::CipherSuite;
+use crate::validation::{self, validate_device_id, validate_tenant_id, Validate};
+
+mod memory;
+mod snapshot;
+#[cfg(feature = "sled")]
+mod sled_store;
+#[cfg(feature = "postgres")]
+mod postgres;
+
+pub use memory::MemoryStore;
+pub use snapshot::{tree_hash, RegistrySnapshot, SnapshotImport, MAX_SNAPSHOT_LEN};
+#[cfg(feature = "sled")]
+pub use sled_store::SledStore;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+
+/// Public record of one provisioned device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceRecord {
+    pub tenant: String,
+    pub device_id: String,
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kyber_public_key: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub dilithium_public_key: Vec<u8>,
+    /// Unix time the device was registered
+    pub registered_at: u64,
+}
+
+impl DeviceRecord {
+    pub fn new(tenant: &str, device_id: &str, suite: CipherSuite, kyber_public_key: Vec<u8>, dilithium_public_key: Vec<u8>) -> Self {
+        Self {
+            tenant: tenant.to_string(),
+            device_id: device_id.to_string(),
+            suite,
+            kyber_public_key,
+            dilithium_public_key,
+            registered_at: unix_now(),
+        }
+    }
+
+    /// Hex SHA-256 of the device's Dilithium public key
+    pub fn fingerprint(&self) -> String {
+        key_fingerprint(&self.dilithium_public_key)
+    }
+
+    #[cfg(any(feature = "sled", feature = "postgres"))]
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode device record: {}", e)))
+    }
+
+    #[cfg(any(feature = "sled", feature = "postgres"))]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "device record")
+    }
+}
+
+impl Validate for DeviceRecord {
+    fn validate(&self) -> Result<()> {
+        validate_tenant_id(&self.tenant)?;
+        validate_device_id(&self.device_id)?;
+        validation::check_any_public_key_len("Device Kyber public key", self.kyber_public_key.len())?;
+        validation::check_any_public_key_len("Device Dilithium public key", self.dilithium_public_key.len())
+    }
+}
+
+/// Persistence for [`DeviceRecord`]s, keyed by `(tenant, device_id)`
+///
+/// Implementations only store what they are given; validation happens in
+/// [`DeviceRegistry`]. Apart from `tenants`, every method only sees
+/// records of the tenant it is passed.
+pub trait RegistryStore: Send + Sync {
+    fn get(&self, tenant: &str, device_id: &str) -> Result<Option<DeviceRecord>>;
+
+    /// Insert or replace the record for `(record.tenant, record.device_id)`
+    fn put(&self, record: &DeviceRecord) -> Result<()>;
+
+    /// Insert `record` only if its `(tenant, device_id)` is free, as one atomic step
+    ///
+    /// False if a record was already there, which is left untouched.
+    fn insert_if_absent(&self, record: &DeviceRecord) -> Result<bool>;
+
+    /// Make the store hold exactly `records`, as one atomic step
+    ///
+    /// `created_at` is kept as the store's snapshot floor: if a later
+    /// snapshot was already applied, nothing is written and this fails.
+    /// Returns how many stored records were not in `records`.
+    fn replace_all(&self, records: &[DeviceRecord], created_at: u64) -> Result<usize>;
+
+    /// Remove a record; false if there was none
+    fn delete(&self, tenant: &str, device_id: &str) -> Result<bool>;
+
+    /// All records of `tenant`, ordered by device ID
+    fn list(&self, tenant: &str) -> Result<Vec<DeviceRecord>>;
+
+    /// Every tenant with at least one record, in order
+    fn tenants(&self) -> Result<Vec<String>>;
+
+    /// Check the backing store is reachable, as cheaply as the store allows
+    ///
+    /// The default lists every tenant; stores with a cheaper probe should override it.
+    fn ping(&self) -> Result<()> {
+        self.tenants().map(drop)
+    }
+}
+
+/// Registered devices on top of a [`RegistryStore`]
+pub struct DeviceRegistry<S: RegistryStore = MemoryStore> {
+    store: S,
+}
+
+impl DeviceRegistry {
+    /// Registry that lives only as long as the process
+    pub fn in_memory() -> Self {
+        Self::new(MemoryStore::new())
+    }
+}
+
+impl<S: RegistryStore> DeviceRegistry<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Add a new device; an ID already registered for the tenant is rejected
+    pub fn register(&self, record: DeviceRecord) -> Result<DeviceRecord> {
+        validate_tenant_id(&record.tenant)?;
+        validate_device_id(&record.device_id)?;
+        if record.kyber_public_key.len() != record.suite.kem.public_key_len()?
+            || record.dilithium_public_key.len() != record.suite.sig.public_key_len()?
+        {
+            return Err(PqcError::InvalidKey(format!("Device keys do not match {}", record.suite)));
+        }
+        if !self.store.insert_if_absent(&record)? {
+            return Err(PqcError::InvalidInput(format!(
+                "Device {} is already registered for tenant {}", record.device_id, record.tenant
+            )));
+        }
+        Ok(record)
+    }
+
+    pub fn get(&self, tenant: &str, device_id: &str) -> Result<Option<DeviceRecord>> {
+        self.store.get(tenant, device_id)
+    }
+
+    /// Forget a device; false if it was not registered
+    pub fn remove(&self, tenant: &str, device_id: &str) -> Result<bool> {
+        self.store.delete(tenant, device_id)
+    }
+
+    pub fn devices(&self, tenant: &str) -> Result<Vec<DeviceRecord>> {
+        self.store.list(tenant)
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Check the store is reachable, e.g. for a health endpoint
+    pub fn ping(&self) -> Result<()> {
+        self.store.ping()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceCredentials;
+
+    pub(super) fn record(tenant: &str, device_id: &str) -> DeviceRecord {
+        let credentials = DeviceCredentials::generate().unwrap();
+        DeviceRecord::new(tenant, device_id, CipherSuite::DEFAULT, credentials.kyber_public_key.clone(), credentials.dilithium_public_key.clone())
+    }
+
+    /// Behaviour every store must have
+    pub(super) fn exercise_store<S: RegistryStore>(store: S) {
+        let registry = DeviceRegistry::new(store);
+        let sensor = registry.register(record("acme", "sensor-7")).unwrap();
+        registry.register(record("acme", "sensor-1")).unwrap();
+        registry.register(record("globex", "sensor-7")).unwrap();
+
+        assert_eq!(registry.get("acme", "sensor-7").unwrap(), Some(sensor.clone()));
+        assert!(registry.register(record("acme", "sensor-7")).is_err());
+        assert!(!registry.store().insert_if_absent(&record("acme", "sensor-7")).unwrap());
+        assert_eq!(registry.get("acme", "sensor-7").unwrap(), Some(sensor));
+        let acme: Vec<String> = registry.devices("acme").unwrap().into_iter().map(|r| r.device_id).collect();
+        assert_eq!(acme, ["sensor-1", "sensor-7"]);
+        assert_eq!(registry.store().tenants().unwrap(), ["acme", "globex"]);
+
+        assert!(registry.remove("acme", "sensor-7").unwrap());
+        assert!(!registry.remove("acme", "sensor-7").unwrap());
+        assert_eq!(registry.get("acme", "sensor-7").unwrap(), None);
+        assert!(registry.get("globex", "sensor-7").unwrap().is_some());
+
+        let replacement = [record("acme", "sensor-1"), record("initech", "gw-1")];
+        assert_eq!(registry.store().replace_all(&replacement, 100).unwrap(), 1);
+        assert_eq!(registry.store().tenants().unwrap(), ["acme", "initech"]);
+        assert_eq!(registry.get("acme", "sensor-1").unwrap().as_ref(), Some(&replacement[0]));
+        assert!(matches!(registry.store().replace_all(&[], 99), Err(PqcError::Verification(_))));
+        assert_eq!(registry.store().tenants().unwrap(), ["acme", "initech"]);
+    }
+
+    /// Concurrent registrations of one ID: exactly one wins and its record stays
+    pub(super) fn exercise_concurrent_register<S: RegistryStore>(store: S) {
+        let registry = DeviceRegistry::new(store);
+        let candidates: Vec<DeviceRecord> = (0..8).map(|_| record("acme", "sensor-race")).collect();
+        let winners: Vec<DeviceRecord> = std::thread::scope(|scope| {
+            let handles: Vec<_> = candidates.iter().map(|candidate| scope.spawn(|| registry.register(candidate.clone()).ok())).collect();
+            handles.into_iter().filter_map(|handle| handle.join().unwrap()).collect()
+        });
+        assert_eq!(winners.len(), 1);
+        assert_eq!(registry.get("acme", "sensor-race").unwrap().as_ref(), winners.first());
+        registry.remove("acme", "sensor-race").unwrap();
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise_store(MemoryStore::new());
+        exercise_concurrent_register(MemoryStore::new());
+    }
+
+    #[test]
+    fn test_register_validates() {
+        let registry = DeviceRegistry::in_memory();
+        assert!(registry.register(record("acme", "bad id")).is_err());
+        assert!(registry.register(record("", "sensor-7")).is_err());
+
+        let mut wrong_keys = record("acme", "sensor-7");
+        wrong_keys.kyber_public_key.truncate(10);
+        assert!(matches!(registry.register(wrong_keys), Err(PqcError::InvalidKey(_))));
+    }
+}
@@ -0,0 +1,206 @@
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::Row;
+use tokio::runtime::{Builder, Runtime};
+use crate::error::{PqcError, Result};
+use super::snapshot::check_snapshot_floor;
+use super::{DeviceRecord, RegistryStore};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS pqc_device_registry (
+    tenant TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    record TEXT NOT NULL,
+    PRIMARY KEY (tenant, device_id)
+)";
+
+const CREATE_SNAPSHOT_TABLE: &str = "CREATE TABLE IF NOT EXISTS pqc_registry_snapshot (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    created_at BIGINT NOT NULL
+)";
+
+/// Store in a PostgreSQL table, `pqc_device_registry`
+///
+/// The table, and `pqc_registry_snapshot` holding the snapshot floor, are
+/// created on first connect. The store drives sqlx on its own
+/// single-threaded Tokio runtime, so it can be used from synchronous code;
+/// don't call it from inside another Tokio runtime.
+pub struct PostgresStore {
+    runtime: Runtime,
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    /// Connect to `url`, e.g. `postgres://provisioning@db/devices`
+    pub fn connect(url: &str) -> Result<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| PqcError::Io(format!("Failed to start registry runtime: {}", e)))?;
+        let pool = runtime.block_on(async {
+            let pool = PgPoolOptions::new().max_connections(4).connect(url).await?;
+            sqlx::query(CREATE_TABLE).execute(&pool).await?;
+            sqlx::query(CREATE_SNAPSHOT_TABLE).execute(&pool).await?;
+            Ok::<_, sqlx::Error>(pool)
+        })
+        .map_err(db_error)?;
+        Ok(Self { runtime, pool })
+    }
+}
+
+fn db_error(e: sqlx::Error) -> PqcError {
+    PqcError::Io(format!("Registry database error: {}", e))
+}
+
+impl RegistryStore for PostgresStore {
+    fn get(&self, tenant: &str, device_id: &str) -> Result<Option<DeviceRecord>> {
+        let row = self.runtime.block_on(
+            sqlx::query("SELECT record FROM pqc_device_registry WHERE tenant = $1 AND device_id = $2")
+                .bind(tenant)
+                .bind(device_id)
+                .fetch_optional(&self.pool),
+        )
+        .map_err(db_error)?;
+        row.map(|row| DeviceRecord::from_bytes(row.try_get::<&str, _>("record").map_err(db_error)?.as_bytes()))
+            .transpose()
+    }
+
+    fn put(&self, record: &DeviceRecord) -> Result<()> {
+        let json = String::from_utf8(record.to_bytes()?)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode device record: {}", e)))?;
+        self.runtime.block_on(
+            sqlx::query(
+                "INSERT INTO pqc_device_registry (tenant, device_id, record) VALUES ($1, $2, $3)
+                 ON CONFLICT (tenant, device_id) DO UPDATE SET record = EXCLUDED.record",
+            )
+            .bind(&record.tenant)
+            .bind(&record.device_id)
+            .bind(json)
+            .execute(&self.pool),
+        )
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn insert_if_absent(&self, record: &DeviceRecord) -> Result<bool> {
+        let json = String::from_utf8(record.to_bytes()?)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode device record: {}", e)))?;
+        let result = self.runtime.block_on(
+            sqlx::query(
+                "INSERT INTO pqc_device_registry (tenant, device_id, record) VALUES ($1, $2, $3)
+                 ON CONFLICT (tenant, device_id) DO NOTHING",
+            )
+            .bind(&record.tenant)
+            .bind(&record.device_id)
+            .bind(json)
+            .execute(&self.pool),
+        )
+        .map_err(db_error)?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    fn replace_all(&self, records: &[DeviceRecord], created_at: u64) -> Result<usize> {
+        let json = records.iter()
+            .map(|record| String::from_utf8(record.to_bytes()?)
+                .map_err(|e| PqcError::InvalidInput(format!("Failed to encode device record: {}", e))))
+            .collect::<Result<Vec<_>>>()?;
+        let tenants: Vec<&str> = records.iter().map(|record| record.tenant.as_str()).collect();
+        let device_ids: Vec<&str> = records.iter().map(|record| record.device_id.as_str()).collect();
+        let created = i64::try_from(created_at)
+            .map_err(|_| PqcError::InvalidInput(format!("Snapshot time {} is out of range", created_at)))?;
+
+        self.runtime.block_on(async {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query("LOCK TABLE pqc_registry_snapshot IN EXCLUSIVE MODE").execute(&mut *tx).await?;
+            let floor: Option<i64> = sqlx::query_scalar("SELECT created_at FROM pqc_registry_snapshot")
+                .fetch_optional(&mut *tx)
+                .await?;
+            if let Err(e) = check_snapshot_floor(floor.map(|floor| floor as u64), created_at) {
+                return Ok(Err(e));
+            }
+            let removed = sqlx::query(
+                "DELETE FROM pqc_device_registry r WHERE NOT EXISTS (
+                     SELECT 1 FROM UNNEST($1::text[], $2::text[]) AS s(tenant, device_id)
+                     WHERE s.tenant = r.tenant AND s.device_id = r.device_id)",
+            )
+            .bind(&tenants)
+            .bind(&device_ids)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            sqlx::query(
+                "INSERT INTO pqc_device_registry (tenant, device_id, record)
+                 SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+                 ON CONFLICT (tenant, device_id) DO UPDATE SET record = EXCLUDED.record",
+            )
+            .bind(&tenants)
+            .bind(&device_ids)
+            .bind(&json)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                "INSERT INTO pqc_registry_snapshot (id, created_at) VALUES (TRUE, $1)
+                 ON CONFLICT (id) DO UPDATE SET created_at = EXCLUDED.created_at",
+            )
+            .bind(created)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(Ok(removed as usize))
+        })
+        .map_err(db_error)?
+    }
+
+    fn delete(&self, tenant: &str, device_id: &str) -> Result<bool> {
+        let result = self.runtime.block_on(
+            sqlx::query("DELETE FROM pqc_device_registry WHERE tenant = $1 AND device_id = $2")
+                .bind(tenant)
+                .bind(device_id)
+                .execute(&self.pool),
+        )
+        .map_err(db_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn list(&self, tenant: &str) -> Result<Vec<DeviceRecord>> {
+        let rows = self.runtime.block_on(
+            sqlx::query("SELECT record FROM pqc_device_registry WHERE tenant = $1 ORDER BY device_id COLLATE \"C\"")
+                .bind(tenant)
+                .fetch_all(&self.pool),
+        )
+        .map_err(db_error)?;
+        rows.iter()
+            .map(|row| DeviceRecord::from_bytes(row.try_get::<&str, _>("record").map_err(db_error)?.as_bytes()))
+            .collect()
+    }
+
+    fn tenants(&self) -> Result<Vec<String>> {
+        let rows = self.runtime.block_on(
+            sqlx::query("SELECT DISTINCT tenant FROM pqc_device_registry ORDER BY tenant COLLATE \"C\"")
+                .fetch_all(&self.pool),
+        )
+        .map_err(db_error)?;
+        rows.iter().map(|row| row.try_get::<String, _>("tenant").map_err(db_error)).collect()
+    }
+
+    fn ping(&self) -> Result<()> {
+        self.runtime.block_on(sqlx::query("SELECT 1").execute(&self.pool)).map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::tests::{exercise_concurrent_register, exercise_store};
+
+    /// Runs against the database named by `PQC_TEST_POSTGRES_URL`; its registry tables are cleared first
+    #[test]
+    #[ignore = "needs a PostgreSQL server in PQC_TEST_POSTGRES_URL"]
+    fn test_postgres_store() {
+        let url = std::env::var("PQC_TEST_POSTGRES_URL").expect("PQC_TEST_POSTGRES_URL is not set");
+        let store = PostgresStore::connect(&url).unwrap();
+        store.runtime.block_on(sqlx::query("DELETE FROM pqc_device_registry").execute(&store.pool)).unwrap();
+        store.runtime.block_on(sqlx::query("DELETE FROM pqc_registry_snapshot").execute(&store.pool)).unwrap();
+        exercise_store(store);
+        exercise_concurrent_register(PostgresStore::connect(&url).unwrap());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::path::Path;
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::{IVec, Transactional};
+use crate::error::{PqcError, Result};
+use super::snapshot::check_snapshot_floor;
+use super::{DeviceRecord, RegistryStore};
+
+/// Key of the last imported snapshot's `created_at` in the meta tree
+const SNAPSHOT_FLOOR: &[u8] = b"snapshot-floor";
+
+/// Store in an embedded sled database
+///
+/// Records live in the `devices` tree under `tenant || 0x00 || device_id`,
+/// which IDs cannot contain, and the snapshot floor in `registry-meta`.
+/// Every write is flushed before returning.
+pub struct SledStore {
+    tree: sled::Tree,
+    meta: sled::Tree,
+}
+
+impl SledStore {
+    /// Open or create the database at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        let db = sled::open(path)
+            .map_err(|e| PqcError::Io(format!("Failed to open registry {}: {}", path.display(), e)))?;
+        Self::from_db(&db)
+    }
+
+    /// Use the `devices` and `registry-meta` trees of an already open database
+    pub fn from_db(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree("devices").map_err(db_error)?;
+        let meta = db.open_tree("registry-meta").map_err(db_error)?;
+        Ok(Self { tree, meta })
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.tree.flush().map(|_| ()).map_err(db_error)
+    }
+}
+
+fn key(tenant: &str, device_id: &str) -> Vec<u8> {
+    let mut key = tenant_prefix(tenant);
+    key.extend_from_slice(device_id.as_bytes());
+    key
+}
+
+fn tenant_prefix(tenant: &str) -> Vec<u8> {
+    let mut prefix = tenant.as_bytes().to_vec();
+    prefix.push(0);
+    prefix
+}
+
+fn db_error(e: sled::Error) -> PqcError {
+    PqcError::Io(format!("Registry database error: {}", e))
+}
+
+fn decode_floor(bytes: &[u8]) -> Result<u64> {
+    <[u8; 8]>::try_from(bytes)
+        .map(u64::from_be_bytes)
+        .map_err(|_| PqcError::InvalidInput("Malformed registry snapshot floor".to_string()))
+}
+
+impl RegistryStore for SledStore {
+    fn get(&self, tenant: &str, device_id: &str) -> Result<Option<DeviceRecord>> {
+        self.tree.get(key(tenant, device_id))
+            .map_err(db_error)?
+            .map(|bytes| DeviceRecord::from_bytes(&bytes))
+            .transpose()
+    }
+
+    fn put(&self, record: &DeviceRecord) -> Result<()> {
+        self.tree.insert(key(&record.tenant, &record.device_id), record.to_bytes()?).map_err(db_error)?;
+        self.flush()
+    }
+
+    fn insert_if_absent(&self, record: &DeviceRecord) -> Result<bool> {
+        let swapped = self.tree
+            .compare_and_swap(key(&record.tenant, &record.device_id), None as Option<&[u8]>, Some(record.to_bytes()?))
+            .map_err(db_error)?;
+        self.flush()?;
+        Ok(swapped.is_ok())
+    }
+
+    fn replace_all(&self, records: &[DeviceRecord], created_at: u64) -> Result<usize> {
+        let incoming = records.iter()
+            .map(|record| Ok((key(&record.tenant, &record.device_id), record.to_bytes()?)))
+            .collect::<Result<Vec<_>>>()?;
+        let keep: HashSet<&[u8]> = incoming.iter().map(|(key, _)| key.as_slice()).collect();
+        let existing = self.tree.iter().keys().collect::<std::result::Result<Vec<_>, _>>().map_err(db_error)?;
+        let stale: Vec<&IVec> = existing.iter().filter(|key| !keep.contains(key.as_ref())).collect();
+
+        let removed = (&self.tree, &self.meta)
+            .transaction(|(devices, meta)| {
+                meta.get(SNAPSHOT_FLOOR)?
+                    .map(|bytes| decode_floor(&bytes))
+                    .transpose()
+                    .and_then(|floor| check_snapshot_floor(floor, created_at))
+                    .map_err(ConflictableTransactionError::Abort)?;
+                let mut removed = 0;
+                for key in &stale {
+                    if devices.remove(*key)?.is_some() {
+                        removed += 1;
+                    }
+                }
+                for (key, value) in &incoming {
+                    devices.insert(key.as_slice(), value.as_slice())?;
+                }
+                meta.insert(SNAPSHOT_FLOOR, &created_at.to_be_bytes()[..])?;
+                Ok(removed)
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => db_error(e),
+            })?;
+        self.flush()?;
+        Ok(removed)
+    }
+
+    fn delete(&self, tenant: &str, device_id: &str) -> Result<bool> {
+        let removed = self.tree.remove(key(tenant, device_id)).map_err(db_error)?.is_some();
+        self.flush()?;
+        Ok(removed)
+    }
+
+    fn list(&self, tenant: &str) -> Result<Vec<DeviceRecord>> {
+        self.tree.scan_prefix(tenant_prefix(tenant))
+            .map(|entry| entry.map_err(db_error).and_then(|(_, bytes)| DeviceRecord::from_bytes(&bytes)))
+            .collect()
+    }
+
+    fn tenants(&self) -> Result<Vec<String>> {
+        let mut tenants: Vec<String> = Vec::new();
+        for key in self.tree.iter().keys() {
+            let key = key.map_err(db_error)?;
+            let tenant = key.split(|&b| b == 0).next().unwrap_or_default();
+            if tenants.last().map(String::as_bytes) != Some(tenant) {
+                tenants.push(String::from_utf8_lossy(tenant).into_owned());
+            }
+        }
+        Ok(tenants)
+    }
+
+    /// Read at most one key instead of scanning the tree
+    fn ping(&self) -> Result<()> {
+        self.tree.first().map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::tests::{exercise_concurrent_register, exercise_store};
+
+    #[test]
+    fn test_sled_store() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        exercise_store(SledStore::from_db(&db).unwrap());
+        exercise_concurrent_register(SledStore::from_db(&db).unwrap());
+    }
+}
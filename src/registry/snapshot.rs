@@ -0,0 +1,275 @@
+//! Signed snapshots for replicating a registry to edge verifiers
+//!
+//! A snapshot carries every device record, sorted by tenant and device ID,
+//! and a Merkle tree hash over them. The provisioning authority signs the
+//! tree hash with the snapshot's metadata, so an edge node that only trusts
+//! the authority's public key can check the whole registry before importing it.
+
+use serde::{Deserialize, Serialize};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::hash::{self, HashAlgorithm};
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, validate_tenant_id, Validate};
+use crate::{canonical, fips};
+use super::{DeviceRecord, DeviceRegistry, RegistryStore};
+
+/// Domain label for registry snapshot signatures
+const SNAPSHOT_DOMAIN: &str = "PQC-Algo/v1/registry-snapshot";
+
+/// Largest serialized snapshot [`RegistrySnapshot::from_bytes`] will parse (256 MiB)
+pub const MAX_SNAPSHOT_LEN: usize = 256 * 1024 * 1024;
+
+/// Every record of a registry, signed by the provisioning authority
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrySnapshot {
+    pub created_at: u64,
+    pub algorithm: SigAlgorithm,
+    pub authority_fingerprint: String,
+    /// Hex Merkle root over `records`, see [`tree_hash`]
+    pub tree_hash: String,
+    pub records: Vec<DeviceRecord>,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`RegistrySnapshot`] covered by its signature
+#[derive(Serialize)]
+struct SnapshotFields<'a> {
+    created_at: u64,
+    algorithm: &'a str,
+    authority_fingerprint: &'a str,
+    tree_hash: &'a str,
+    record_count: usize,
+}
+
+/// What [`DeviceRegistry::import_snapshot`] changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SnapshotImport {
+    /// `created_at` of the imported snapshot
+    pub created_at: u64,
+    pub records: usize,
+    /// Local records that were not in the snapshot and have been deleted
+    pub removed: usize,
+}
+
+impl RegistrySnapshot {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode registry snapshot: {}", e)))
+    }
+
+    /// Parse at most [`MAX_SNAPSHOT_LEN`] bytes; call [`RegistrySnapshot::verify`] before importing
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json_with_limit(bytes, "registry snapshot", MAX_SNAPSHOT_LEN)
+    }
+
+    /// Check the authority's signature and that the records match the tree hash
+    pub fn verify(&self, authority_public_key: &[u8]) -> Result<()> {
+        fips::check_sig(self.algorithm)?;
+        if !ct::ct_eq_hex(&self.authority_fingerprint, &key_fingerprint(authority_public_key)) {
+            return Err(PqcError::Verification("Snapshot was signed by a different authority".to_string()));
+        }
+        if !self.records.windows(2).all(|pair| sort_key(&pair[0]) < sort_key(&pair[1])) {
+            return Err(PqcError::Verification("Snapshot records are unsorted or duplicated".to_string()));
+        }
+        if !ct::ct_eq_hex(&self.tree_hash, &tree_hash(&self.records)?) {
+            return Err(PqcError::Verification("Snapshot records do not match the tree hash".to_string()));
+        }
+        if !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, authority_public_key)? {
+            return Err(PqcError::Verification("Snapshot signature does not verify".to_string()));
+        }
+        Ok(())
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(SNAPSHOT_DOMAIN, &SnapshotFields {
+            created_at: self.created_at,
+            algorithm: self.algorithm.name(),
+            authority_fingerprint: &self.authority_fingerprint,
+            tree_hash: &self.tree_hash,
+            record_count: self.records.len(),
+        })
+    }
+}
+
+impl Validate for RegistrySnapshot {
+    fn validate(&self) -> Result<()> {
+        validation::check_fingerprint("Snapshot authority fingerprint", &self.authority_fingerprint)?;
+        validation::check_fingerprint("Snapshot tree hash", &self.tree_hash)?;
+        self.records.iter().try_for_each(DeviceRecord::validate)?;
+        validation::check_signature_len("Snapshot signature", self.algorithm, self.signature.len())
+    }
+}
+
+impl<S: RegistryStore> DeviceRegistry<S> {
+    /// Export every record as a snapshot signed by the provisioning authority
+    pub fn export_snapshot(&self, authority_secret_key: &[u8], authority_public_key: &[u8]) -> Result<RegistrySnapshot> {
+        self.export_snapshot_with(CipherSuite::active_default().sig, authority_secret_key, authority_public_key)
+    }
+
+    /// [`DeviceRegistry::export_snapshot`] with an explicit signature algorithm
+    pub fn export_snapshot_with(&self, algorithm: SigAlgorithm, authority_secret_key: &[u8], authority_public_key: &[u8]) -> Result<RegistrySnapshot> {
+        fips::check_sig(algorithm)?;
+        let mut records = Vec::new();
+        for tenant in self.store.tenants()? {
+            records.extend(self.store.list(&tenant)?);
+        }
+        records.sort_by(|a, b| sort_key(a).cmp(&sort_key(b)));
+
+        let mut snapshot = RegistrySnapshot {
+            created_at: unix_now(),
+            algorithm,
+            authority_fingerprint: key_fingerprint(authority_public_key),
+            tree_hash: tree_hash(&records)?,
+            records,
+            signature: Vec::new(),
+        };
+        snapshot.signature = sign::sign_with(algorithm, &snapshot.signing_bytes()?, authority_secret_key)?;
+        Ok(snapshot)
+    }
+
+    /// Verify `snapshot` and make this registry an exact copy of it
+    ///
+    /// Records missing from the snapshot are deleted. Nothing is written
+    /// unless the whole snapshot verifies, and the store swaps its contents
+    /// in one step. A snapshot older than the last one imported is refused,
+    /// so a replayed snapshot cannot bring back removed devices.
+    pub fn import_snapshot(&self, snapshot: &RegistrySnapshot, authority_public_key: &[u8]) -> Result<SnapshotImport> {
+        snapshot.verify(authority_public_key)?;
+        for record in &snapshot.records {
+            validate_tenant_id(&record.tenant)?;
+            validate_device_id(&record.device_id)?;
+        }
+
+        let removed = self.store.replace_all(&snapshot.records, snapshot.created_at)?;
+        Ok(SnapshotImport { created_at: snapshot.created_at, records: snapshot.records.len(), removed })
+    }
+}
+
+fn sort_key(record: &DeviceRecord) -> (&str, &str) {
+    (&record.tenant, &record.device_id)
+}
+
+/// Fail if a snapshot newer than `created_at` was already imported
+pub(super) fn check_snapshot_floor(floor: Option<u64>, created_at: u64) -> Result<()> {
+    match floor {
+        Some(floor) if created_at < floor => Err(PqcError::Verification(format!(
+            "Snapshot from {} is older than the last one imported, from {}", created_at, floor
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Hex RFC 6962 Merkle Tree Hash (section 2.1) over the records' canonical JSON
+///
+/// Leaves are `SHA-256(0x00 || canonical JSON of the record)` and inner
+/// nodes `SHA-256(0x01 || left || right)`. Pairing nodes level by level and
+/// moving an odd one up unchanged gives the RFC's split at the largest power
+/// of two. No records hash to SHA-256 of the empty string.
+pub fn tree_hash(records: &[DeviceRecord]) -> Result<String> {
+    let mut level = records.iter()
+        .map(|record| {
+            let mut leaf = vec![0u8];
+            leaf.extend_from_slice(&canonical::to_canonical_json(record)?);
+            Ok(hash::digest(HashAlgorithm::Sha256, &leaf))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if level.is_empty() {
+        return Ok(hex::encode(hash::digest(HashAlgorithm::Sha256, &[])));
+    }
+
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash::digest(HashAlgorithm::Sha256, &[&[1u8][..], left, right].concat()),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    Ok(hex::encode(&level[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::tests::record;
+
+    #[test]
+    fn test_snapshot_replication() {
+        let authority = sign::generate_keypair().unwrap();
+        let primary = DeviceRegistry::in_memory();
+        for (tenant, device_id) in [("acme", "sensor-7"), ("acme", "sensor-1"), ("globex", "gw-1")] {
+            primary.register(record(tenant, device_id)).unwrap();
+        }
+        let snapshot = primary.export_snapshot(&authority.secret_key, &authority.public_key).unwrap();
+        assert_eq!(snapshot.records.len(), 3);
+
+        let edge = DeviceRegistry::in_memory();
+        edge.register(record("acme", "decommissioned")).unwrap();
+        let import = edge.import_snapshot(&snapshot, &authority.public_key).unwrap();
+        assert_eq!((import.records, import.removed), (3, 1));
+        assert_eq!(edge.devices("acme").unwrap(), primary.devices("acme").unwrap());
+        assert_eq!(edge.devices("globex").unwrap(), primary.devices("globex").unwrap());
+
+        let mut stale = snapshot.clone();
+        stale.created_at -= 60;
+        stale.records.retain(|record| record.tenant == "acme");
+        stale.tree_hash = tree_hash(&stale.records).unwrap();
+        stale.signature = sign::sign_with(stale.algorithm, &stale.signing_bytes().unwrap(), &authority.secret_key).unwrap();
+        stale.verify(&authority.public_key).unwrap();
+        assert!(matches!(edge.import_snapshot(&stale, &authority.public_key), Err(PqcError::Verification(_))));
+        assert_eq!(edge.devices("globex").unwrap(), primary.devices("globex").unwrap());
+    }
+
+    /// RFC 6962 section 2.1, written out recursively
+    fn rfc6962_root(leaves: &[Vec<u8>]) -> Vec<u8> {
+        match leaves {
+            [] => hash::digest(HashAlgorithm::Sha256, &[]),
+            [leaf] => hash::digest(HashAlgorithm::Sha256, &[&[0u8][..], leaf].concat()),
+            _ => {
+                let split = leaves.len().next_power_of_two() / 2;
+                let (left, right) = (rfc6962_root(&leaves[..split]), rfc6962_root(&leaves[split..]));
+                hash::digest(HashAlgorithm::Sha256, &[&[1u8][..], &left, &right].concat())
+            }
+        }
+    }
+
+    #[test]
+    fn test_tree_hash_is_rfc6962() {
+        let records: Vec<DeviceRecord> = (0..7).map(|i| record("acme", &format!("sensor-{}", i))).collect();
+        for n in 0..=records.len() {
+            let leaves: Vec<Vec<u8>> = records[..n].iter().map(|r| canonical::to_canonical_json(r).unwrap()).collect();
+            assert_eq!(tree_hash(&records[..n]).unwrap(), hex::encode(rfc6962_root(&leaves)));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_rejects_tampering() {
+        let authority = sign::generate_keypair().unwrap();
+        let other = sign::generate_keypair().unwrap();
+        let primary = DeviceRegistry::in_memory();
+        primary.register(record("acme", "sensor-7")).unwrap();
+        primary.register(record("acme", "sensor-8")).unwrap();
+        let snapshot = primary.export_snapshot(&authority.secret_key, &authority.public_key).unwrap();
+        snapshot.verify(&authority.public_key).unwrap();
+        assert!(snapshot.verify(&other.public_key).is_err());
+
+        let mut swapped_key = snapshot.clone();
+        swapped_key.records[0].dilithium_public_key = other.public_key.clone();
+        assert!(matches!(swapped_key.verify(&authority.public_key), Err(PqcError::Verification(_))));
+
+        let mut dropped = snapshot.clone();
+        dropped.records.pop();
+        dropped.tree_hash = tree_hash(&dropped.records).unwrap();
+        assert!(matches!(dropped.verify(&authority.public_key), Err(PqcError::Verification(_))));
+
+        let edge = DeviceRegistry::in_memory();
+        edge.register(record("acme", "sensor-1")).unwrap();
+        assert!(edge.import_snapshot(&dropped, &authority.public_key).is_err());
+        assert_eq!(edge.devices("acme").unwrap().len(), 1);
+    }
+}
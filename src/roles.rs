@@ -0,0 +1,273 @@
+//! Capability-scoped clients that each hold only the keys their role needs
+//!
+//! [`DeviceCredentials`] carries both key pairs with both secret keys, which
+//! is right for the device and wrong for everything else. Service code
+//! should hold one of these instead:
+//!
+//! | role | holds | does |
+//! |------|-------|------|
+//! | [`EntropyProducer`] | device KEM public key | encrypts entropy to the device |
+//! | [`EntropyConsumer`] | KEM secret key | decrypts entropy and log uploads sent to it |
+//! | [`Authenticator`] | signature secret key | signs authentication requests, log uploads and renewals |
+//! | [`Verifier`] | device signature public key | verifies authentication requests |
+//!
+//! A verification-only node built from a [`Verifier`] never has a secret key
+//! in memory. The public-key roles serialize, so they can be shipped to such
+//! nodes as configuration.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::api::{self, AuthRequest, DeviceCredentials, EncryptedEntropy};
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::secret::SecretKey;
+use crate::suite::CipherSuite;
+use crate::validation::validate_device_id;
+
+/// Encrypts entropy to one device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntropyProducer {
+    #[serde(with = "crate::util::bytes")]
+    device_kyber_public_key: Vec<u8>,
+    suite: CipherSuite,
+}
+
+impl EntropyProducer {
+    pub fn new(device_kyber_public_key: &[u8]) -> Self {
+        Self { device_kyber_public_key: device_kyber_public_key.to_vec(), suite: CipherSuite::active_default() }
+    }
+
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    pub fn encrypt(&self, entropy: &[u8]) -> Result<EncryptedEntropy> {
+        api::encrypt_entropy_with_suite(entropy, &self.device_kyber_public_key, &self.suite)
+    }
+
+    pub fn recipient_fingerprint(&self) -> String {
+        key_fingerprint(&self.device_kyber_public_key)
+    }
+}
+
+/// Decrypts entropy sent to its KEM key
+pub struct EntropyConsumer {
+    kyber_public_key: Vec<u8>,
+    kyber_secret_key: SecretKey,
+    suite: CipherSuite,
+}
+
+impl EntropyConsumer {
+    pub fn new(kyber_public_key: &[u8], kyber_secret_key: SecretKey) -> Self {
+        Self { kyber_public_key: kyber_public_key.to_vec(), kyber_secret_key, suite: CipherSuite::active_default() }
+    }
+
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    pub fn decrypt(&self, encrypted: &EncryptedEntropy) -> Result<Vec<u8>> {
+        api::decrypt_entropy_with_suite(encrypted, &self.kyber_secret_key, &self.suite)
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.kyber_public_key
+    }
+
+    pub(crate) fn secret_key(&self) -> &SecretKey {
+        &self.kyber_secret_key
+    }
+
+    /// The producer that encrypts to this consumer
+    pub fn producer(&self) -> EntropyProducer {
+        EntropyProducer::new(&self.kyber_public_key).with_suite(self.suite)
+    }
+}
+
+/// Prints the public key fingerprint, never the secret key
+impl fmt::Debug for EntropyConsumer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntropyConsumer")
+            .field("kyber_fingerprint", &key_fingerprint(&self.kyber_public_key))
+            .field("kyber_secret_key", &self.kyber_secret_key)
+            .field("suite", &self.suite)
+            .finish()
+    }
+}
+
+/// Signs authentication requests as one device
+pub struct Authenticator {
+    device_id: String,
+    dilithium_public_key: Vec<u8>,
+    dilithium_secret_key: SecretKey,
+    suite: CipherSuite,
+}
+
+impl Authenticator {
+    pub fn new(device_id: &str, dilithium_public_key: &[u8], dilithium_secret_key: SecretKey) -> Result<Self> {
+        validate_device_id(device_id)?;
+        Ok(Self {
+            device_id: device_id.to_string(),
+            dilithium_public_key: dilithium_public_key.to_vec(),
+            dilithium_secret_key,
+            suite: CipherSuite::active_default(),
+        })
+    }
+
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    pub fn authenticate(&self, nonce: &[u8]) -> Result<AuthRequest> {
+        api::create_auth_request_with_suite(&self.device_id, nonce, &self.dilithium_secret_key, &self.suite)
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.dilithium_public_key
+    }
+
+    pub(crate) fn secret_key(&self) -> &SecretKey {
+        &self.dilithium_secret_key
+    }
+
+    /// The verifier for this device's requests
+    pub fn verifier(&self) -> Verifier {
+        Verifier { device_id: self.device_id.clone(), dilithium_public_key: self.dilithium_public_key.clone(), suite: self.suite }
+    }
+}
+
+/// Prints the public key fingerprint, never the secret key
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("device_id", &self.device_id)
+            .field("dilithium_fingerprint", &key_fingerprint(&self.dilithium_public_key))
+            .field("dilithium_secret_key", &self.dilithium_secret_key)
+            .field("suite", &self.suite)
+            .finish()
+    }
+}
+
+/// Verifies one device's authentication requests; holds no secrets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verifier {
+    device_id: String,
+    #[serde(with = "crate::util::bytes")]
+    dilithium_public_key: Vec<u8>,
+    suite: CipherSuite,
+}
+
+impl Verifier {
+    pub fn new(device_id: &str, dilithium_public_key: &[u8]) -> Result<Self> {
+        validate_device_id(device_id)?;
+        Ok(Self {
+            device_id: device_id.to_string(),
+            dilithium_public_key: dilithium_public_key.to_vec(),
+            suite: CipherSuite::active_default(),
+        })
+    }
+
+    pub fn with_suite(mut self, suite: CipherSuite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.dilithium_public_key
+    }
+
+    /// Check that `request` is from this verifier's device and carries `nonce`
+    pub fn verify(&self, request: &AuthRequest, nonce: &[u8]) -> Result<()> {
+        if request.device_id != self.device_id {
+            return Err(PqcError::Verification(format!(
+                "Auth request is from {}, expected {}", request.device_id, self.device_id
+            )));
+        }
+        if !crate::util::ct::ct_eq(&request.nonce, nonce) {
+            return Err(PqcError::Verification("Auth request answers a different nonce".to_string()));
+        }
+        if !api::verify_auth_request_with_suite(request, &self.dilithium_public_key, &self.suite)? {
+            return Err(PqcError::Verification(format!("Auth request signature from {} is invalid", self.device_id)));
+        }
+        Ok(())
+    }
+}
+
+impl DeviceCredentials {
+    /// Split into the device's two secret-key roles, consuming the credentials
+    pub fn into_roles(self, device_id: &str) -> Result<(EntropyConsumer, Authenticator)> {
+        let authenticator = Authenticator::new(device_id, &self.dilithium_public_key, self.dilithium_secret_key)?;
+        let consumer = EntropyConsumer::new(&self.kyber_public_key, self.kyber_secret_key);
+        Ok((consumer, authenticator))
+    }
+
+    /// The public-key roles a service needs for this device
+    pub fn service_roles(&self, device_id: &str) -> Result<(EntropyProducer, Verifier)> {
+        Ok((EntropyProducer::new(&self.kyber_public_key), Verifier::new(device_id, &self.dilithium_public_key)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::SecurityLevel;
+
+    #[test]
+    fn test_roles_round_trip() {
+        let device = DeviceCredentials::generate().unwrap();
+        let (producer, verifier) = device.service_roles("sensor-1").unwrap();
+        let (consumer, authenticator) = device.into_roles("sensor-1").unwrap();
+
+        let encrypted = producer.encrypt(b"entropy").unwrap();
+        assert_eq!(consumer.decrypt(&encrypted).unwrap(), b"entropy");
+        assert_eq!(consumer.producer(), producer);
+
+        let request = authenticator.authenticate(b"nonce-1").unwrap();
+        verifier.verify(&request, b"nonce-1").unwrap();
+        assert_eq!(authenticator.verifier(), verifier);
+    }
+
+    #[test]
+    fn test_verifier_rejects_other_device_and_nonce() {
+        let device = DeviceCredentials::generate().unwrap();
+        let other = DeviceCredentials::generate().unwrap();
+        let (_, verifier) = device.service_roles("sensor-1").unwrap();
+        let (_, authenticator) = device.into_roles("sensor-1").unwrap();
+        let (_, impostor) = other.into_roles("sensor-1").unwrap();
+
+        let request = authenticator.authenticate(b"nonce-1").unwrap();
+        assert!(matches!(verifier.verify(&request, b"nonce-2"), Err(PqcError::Verification(_))));
+        let mut renamed = AuthRequest::from_bytes(&request.to_bytes().unwrap()).unwrap();
+        renamed.device_id = "sensor-2".to_string();
+        assert!(verifier.verify(&renamed, b"nonce-1").is_err());
+        assert!(verifier.verify(&impostor.authenticate(b"nonce-1").unwrap(), b"nonce-1").is_err());
+    }
+
+    #[test]
+    fn test_public_roles_serialize_without_secrets() {
+        let suite = SecurityLevel::L5.suite();
+        let device = DeviceCredentials::generate_with_suite(&suite).unwrap();
+        let (producer, verifier) = device.service_roles("sensor-1").unwrap();
+        let producer = producer.with_suite(suite);
+        let verifier = verifier.with_suite(suite);
+        let json = serde_json::to_string(&(&producer, &verifier)).unwrap();
+        let (producer_back, verifier_back): (EntropyProducer, Verifier) = serde_json::from_str(&json).unwrap();
+        assert_eq!((&producer_back, &verifier_back), (&producer, &verifier));
+
+        let (consumer, _) = device.into_roles("sensor-1").unwrap();
+        let consumer = consumer.with_suite(suite);
+        assert_eq!(consumer.decrypt(&producer.encrypt(b"entropy").unwrap()).unwrap(), b"entropy");
+        assert!(format!("{:?}", consumer).contains("redacted"));
+    }
+}
@@ -0,0 +1,405 @@
+//! Monotonic counters that protect device state against rollback
+//!
+//! A device keeps named counters, such as [`AUTH_COUNTER`] and
+//! [`FIRMWARE_COUNTER`], in a [`CounterStore`] and signs their current value
+//! in a [`CounterToken`]. The verifier remembers the highest value seen per
+//! device and counter in a [`CounterLedger`] and rejects tokens that go
+//! backwards. A device restored from an old backup, or an attacker replaying
+//! an old token, is therefore caught.
+//!
+//! Each token is bound to a context chosen by the verifier, for example the
+//! auth request nonce or the digest of the firmware image being approved.
+//! The device persists a new value before signing it, so a crash can skip a
+//! value but never reuse one.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::sign::{self, SigAlgorithm};
+use crate::util::ct;
+use crate::validation::{self, validate_device_id, Validate};
+use crate::{canonical, fips};
+
+/// Domain label for counter token signatures
+const COUNTER_DOMAIN: &str = "PQC-Algo/v1/monotonic-counter";
+
+/// Counter advanced by every authentication
+pub const AUTH_COUNTER: &str = "auth";
+
+/// Security version of the installed firmware
+pub const FIRMWARE_COUNTER: &str = "firmware";
+
+/// A device's signed statement of one counter's current value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CounterToken {
+    pub device_id: String,
+    pub counter: String,
+    pub value: u64,
+    /// Verifier-chosen binding, such as an auth nonce or firmware digest
+    #[serde(with = "crate::util::bytes")]
+    pub context: Vec<u8>,
+    pub signer_fingerprint: String,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`CounterToken`] covered by its signature
+#[derive(Serialize)]
+struct CounterFields<'a> {
+    device_id: &'a str,
+    counter: &'a str,
+    value: u64,
+    context: String,
+    signer_fingerprint: &'a str,
+    algorithm: &'a str,
+}
+
+impl CounterToken {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode counter token: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "counter token")
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(COUNTER_DOMAIN, &CounterFields {
+            device_id: &self.device_id,
+            counter: &self.counter,
+            value: self.value,
+            context: hex::encode(&self.context),
+            signer_fingerprint: &self.signer_fingerprint,
+            algorithm: self.algorithm.name(),
+        })
+    }
+}
+
+impl Validate for CounterToken {
+    fn validate(&self) -> Result<()> {
+        validate_device_id(&self.device_id)?;
+        validate_counter_name(&self.counter)?;
+        validation::check_max_len("Counter context", self.context.len(), validation::MAX_NONCE_LEN)?;
+        validation::check_fingerprint("Counter signer fingerprint", &self.signer_fingerprint)?;
+        validation::check_signature_len("Counter signature", self.algorithm, self.signature.len())
+    }
+}
+
+fn validate_counter_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > validation::MAX_LABEL_LEN {
+        return Err(PqcError::InvalidInput(format!(
+            "Counter names must be 1 to {} bytes", validation::MAX_LABEL_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Device-side persistence of counter values
+pub trait CounterStore: Send + Sync {
+    /// Current value of `counter`, 0 if it was never advanced
+    fn load(&self, counter: &str) -> Result<u64>;
+
+    /// Persist `value` so that `load` returns it after a restart
+    fn store(&self, counter: &str, value: u64) -> Result<()>;
+}
+
+/// Verifier-side record of the highest value seen per device and counter
+pub trait CounterLedger: Send + Sync {
+    fn last(&self, device_id: &str, counter: &str) -> Result<Option<u64>>;
+
+    /// Record `value` if it is above the last one, atomically; false if not
+    fn advance(&self, device_id: &str, counter: &str, value: u64) -> Result<bool>;
+}
+
+/// Counters held in memory, for verifiers and tests
+#[derive(Default)]
+pub struct MemoryCounters {
+    values: Mutex<BTreeMap<(String, String), u64>>,
+}
+
+impl MemoryCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(String, String), u64>> {
+        self.values.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// On a device, every counter lives under an empty device ID
+impl CounterStore for MemoryCounters {
+    fn load(&self, counter: &str) -> Result<u64> {
+        Ok(self.last("", counter)?.unwrap_or(0))
+    }
+
+    fn store(&self, counter: &str, value: u64) -> Result<()> {
+        self.lock().insert((String::new(), counter.to_string()), value);
+        Ok(())
+    }
+}
+
+impl CounterLedger for MemoryCounters {
+    fn last(&self, device_id: &str, counter: &str) -> Result<Option<u64>> {
+        Ok(self.lock().get(&(device_id.to_string(), counter.to_string())).copied())
+    }
+
+    fn advance(&self, device_id: &str, counter: &str, value: u64) -> Result<bool> {
+        let mut values = self.lock();
+        let key = (device_id.to_string(), counter.to_string());
+        if values.get(&key).is_some_and(|&last| value <= last) {
+            return Ok(false);
+        }
+        values.insert(key, value);
+        Ok(true)
+    }
+}
+
+/// Device counters in a JSON file, written with owner-only permissions
+///
+/// Each store replaces the file atomically and syncs it and its directory,
+/// so a crash leaves either the old or the new values.
+pub struct FileCounterStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileCounterStore {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), lock: Mutex::new(()) }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, u64>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let bytes = std::fs::read(&self.path)
+            .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", self.path.display(), e)))?;
+        let counters: CounterFile = validation::from_json(&bytes, "counter file")?;
+        Ok(counters.0)
+    }
+}
+
+/// Contents of a [`FileCounterStore`]: counter name to value
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct CounterFile(BTreeMap<String, u64>);
+
+impl Validate for CounterFile {
+    fn validate(&self) -> Result<()> {
+        self.0.keys().try_for_each(|name| validate_counter_name(name))
+    }
+}
+
+impl CounterStore for FileCounterStore {
+    fn load(&self, counter: &str) -> Result<u64> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        Ok(self.read()?.get(counter).copied().unwrap_or(0))
+    }
+
+    fn store(&self, counter: &str, value: u64) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let mut values = self.read()?;
+        values.insert(counter.to_string(), value);
+        let json = serde_json::to_vec(&values)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode counters: {}", e)))?;
+        crate::paths::write_private_file_atomic(&self.path, &json)
+    }
+}
+
+/// The device side: advances counters and signs them
+pub struct DeviceCounter<S: CounterStore> {
+    device_id: String,
+    store: S,
+    lock: Mutex<()>,
+}
+
+impl<S: CounterStore> DeviceCounter<S> {
+    pub fn new(device_id: &str, store: S) -> Result<Self> {
+        validate_device_id(device_id)?;
+        Ok(Self { device_id: device_id.to_string(), store, lock: Mutex::new(()) })
+    }
+
+    /// Advance `counter` by one and sign the new value
+    pub fn next(&self, counter: &str, context: &[u8], secret_key: &[u8], public_key: &[u8]) -> Result<CounterToken> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let value = self.store.load(counter)?.checked_add(1)
+            .ok_or_else(|| PqcError::InvalidInput(format!("Counter {} is exhausted", counter)))?;
+        self.store.store(counter, value)?;
+        self.sign(counter, value, context, secret_key, public_key)
+    }
+
+    /// Raise `counter` to `value` and sign it; lower values are refused
+    ///
+    /// For version-like counters such as [`FIRMWARE_COUNTER`], where the
+    /// same value may be reported many times.
+    pub fn attest(&self, counter: &str, value: u64, context: &[u8], secret_key: &[u8], public_key: &[u8]) -> Result<CounterToken> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let current = self.store.load(counter)?;
+        if value < current {
+            return Err(PqcError::InvalidInput(format!(
+                "Counter {} is at {}, refusing to roll back to {}", counter, current, value
+            )));
+        }
+        if value > current {
+            self.store.store(counter, value)?;
+        }
+        self.sign(counter, value, context, secret_key, public_key)
+    }
+
+    pub fn current(&self, counter: &str) -> Result<u64> {
+        self.store.load(counter)
+    }
+
+    fn sign(&self, counter: &str, value: u64, context: &[u8], secret_key: &[u8], public_key: &[u8]) -> Result<CounterToken> {
+        validate_counter_name(counter)?;
+        validation::check_max_len("Counter context", context.len(), validation::MAX_NONCE_LEN)?;
+        let algorithm = SigAlgorithm::for_secret_key(secret_key)?;
+        fips::check_sig(algorithm)?;
+        let mut token = CounterToken {
+            device_id: self.device_id.clone(),
+            counter: counter.to_string(),
+            value,
+            context: context.to_vec(),
+            signer_fingerprint: key_fingerprint(public_key),
+            algorithm,
+            signature: Vec::new(),
+        };
+        token.signature = sign::sign_with(algorithm, &token.signing_bytes()?, secret_key)?;
+        Ok(token)
+    }
+}
+
+/// The verifier side: checks tokens and rejects regressions
+pub struct CounterVerifier<L: CounterLedger> {
+    ledger: L,
+}
+
+impl<L: CounterLedger> CounterVerifier<L> {
+    pub fn new(ledger: L) -> Self {
+        Self { ledger }
+    }
+
+    /// Accept `token` only if its value is above every value seen before
+    ///
+    /// Use for counters advanced with [`DeviceCounter::next`], where a
+    /// repeated value means a replay.
+    pub fn verify(&self, token: &CounterToken, device_public_key: &[u8], context: &[u8]) -> Result<u64> {
+        self.check(token, device_public_key, context)?;
+        if !self.ledger.advance(&token.device_id, &token.counter, token.value)? {
+            return Err(self.regression(token)?);
+        }
+        Ok(token.value)
+    }
+
+    /// Accept `token` if its value is not below any value seen before
+    ///
+    /// Use for counters set with [`DeviceCounter::attest`].
+    pub fn verify_version(&self, token: &CounterToken, device_public_key: &[u8], context: &[u8]) -> Result<u64> {
+        self.check(token, device_public_key, context)?;
+        if self.ledger.last(&token.device_id, &token.counter)? != Some(token.value)
+            && !self.ledger.advance(&token.device_id, &token.counter, token.value)?
+        {
+            return Err(self.regression(token)?);
+        }
+        Ok(token.value)
+    }
+
+    pub fn ledger(&self) -> &L {
+        &self.ledger
+    }
+
+    fn check(&self, token: &CounterToken, device_public_key: &[u8], context: &[u8]) -> Result<()> {
+        token.validate()?;
+        fips::check_sig(token.algorithm)?;
+        if !ct::ct_eq(&token.context, context) {
+            return Err(PqcError::Verification("Counter token is bound to another context".to_string()));
+        }
+        if !ct::ct_eq_hex(&token.signer_fingerprint, &key_fingerprint(device_public_key))
+            || !sign::verify_with(token.algorithm, &token.signing_bytes()?, &token.signature, device_public_key)?
+        {
+            return Err(PqcError::Verification("Counter token was not signed by the device".to_string()));
+        }
+        Ok(())
+    }
+
+    fn regression(&self, token: &CounterToken) -> Result<PqcError> {
+        let last = self.ledger.last(&token.device_id, &token.counter)?.unwrap_or(0);
+        Ok(PqcError::Verification(format!(
+            "Counter {} of {} went back to {} after {}", token.counter, token.device_id, token.value, last
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::CipherSuite;
+
+    #[test]
+    fn test_auth_counter_rejects_replay_and_rollback() {
+        let keypair = sign::generate_keypair_with(CipherSuite::active_default().sig).unwrap();
+        let device = DeviceCounter::new("sensor-1", MemoryCounters::new()).unwrap();
+        let verifier = CounterVerifier::new(MemoryCounters::new());
+
+        let first = device.next(AUTH_COUNTER, b"nonce-1", &keypair.secret_key, &keypair.public_key).unwrap();
+        let second = device.next(AUTH_COUNTER, b"nonce-2", &keypair.secret_key, &keypair.public_key).unwrap();
+        assert_eq!(verifier.verify(&second, &keypair.public_key, b"nonce-2").unwrap(), 2);
+        // An older token, or the same one again, is refused
+        assert!(matches!(verifier.verify(&first, &keypair.public_key, b"nonce-1"), Err(PqcError::Verification(_))));
+        assert!(verifier.verify(&second, &keypair.public_key, b"nonce-2").is_err());
+
+        let third = device.next(AUTH_COUNTER, b"nonce-3", &keypair.secret_key, &keypair.public_key).unwrap();
+        assert!(verifier.verify(&third, &keypair.public_key, b"nonce-4").is_err());
+        let parsed = CounterToken::from_bytes(&third.to_bytes().unwrap()).unwrap();
+        assert_eq!(verifier.verify(&parsed, &keypair.public_key, b"nonce-3").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_firmware_version_may_repeat_but_not_drop() {
+        let keypair = sign::generate_keypair_with(CipherSuite::active_default().sig).unwrap();
+        let device = DeviceCounter::new("sensor-1", MemoryCounters::new()).unwrap();
+        let verifier = CounterVerifier::new(MemoryCounters::new());
+        let digest = [7u8; 32];
+
+        let v4 = device.attest(FIRMWARE_COUNTER, 4, &digest, &keypair.secret_key, &keypair.public_key).unwrap();
+        assert_eq!(verifier.verify_version(&v4, &keypair.public_key, &digest).unwrap(), 4);
+        assert_eq!(verifier.verify_version(&v4, &keypair.public_key, &digest).unwrap(), 4);
+        assert!(device.attest(FIRMWARE_COUNTER, 3, &digest, &keypair.secret_key, &keypair.public_key).is_err());
+
+        // A token from a device restored to an older state
+        let restored = DeviceCounter::new("sensor-1", MemoryCounters::new()).unwrap();
+        let v3 = restored.attest(FIRMWARE_COUNTER, 3, &digest, &keypair.secret_key, &keypair.public_key).unwrap();
+        assert!(verifier.verify_version(&v3, &keypair.public_key, &digest).is_err());
+        assert_eq!(verifier.ledger().last("sensor-1", FIRMWARE_COUNTER).unwrap(), Some(4));
+    }
+
+    #[test]
+    fn test_tokens_use_the_key_algorithm() {
+        let algorithm = if CipherSuite::active_default().sig == SigAlgorithm::MlDsa65 { SigAlgorithm::MlDsa87 } else { SigAlgorithm::MlDsa65 };
+        let keypair = sign::generate_keypair_with(algorithm).unwrap();
+        let device = DeviceCounter::new("sensor-1", MemoryCounters::new()).unwrap();
+        let token = device.next(AUTH_COUNTER, b"nonce-1", &keypair.secret_key, &keypair.public_key).unwrap();
+        assert_eq!(token.algorithm, algorithm);
+        let verifier = CounterVerifier::new(MemoryCounters::new());
+        assert_eq!(verifier.verify(&token, &keypair.public_key, b"nonce-1").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_file_store_persists_counters() {
+        let path = std::env::temp_dir().join(format!("pqc_counters_{}.json", std::process::id()));
+        let store = FileCounterStore::new(&path);
+        assert_eq!(store.load(AUTH_COUNTER).unwrap(), 0);
+        store.store(AUTH_COUNTER, 9).unwrap();
+        store.store(FIRMWARE_COUNTER, 2).unwrap();
+        let reopened = FileCounterStore::new(&path);
+        assert_eq!(reopened.load(AUTH_COUNTER).unwrap(), 9);
+        assert_eq!(reopened.load(FIRMWARE_COUNTER).unwrap(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
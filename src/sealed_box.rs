@@ -0,0 +1,195 @@
+//! Post-quantum sealed boxes
+//!
+//! An [`AuthBox`] is the post-quantum counterpart of libsodium's
+//! authenticated box. The sender encapsulates a fresh KEM secret to the
+//! recipient, seals the message under a key derived from it, and signs the
+//! KEM ciphertext and sealed payload. The AEAD key also depends on both
+//! parties' fingerprints, so a third party who strips the signature and
+//! re-signs the box as themselves produces a box that no longer opens.
+//!
+//! An [`AnonymousBox`] is the counterpart of libsodium's sealed box: a
+//! fresh encapsulation and nothing else. It carries no sender key,
+//! fingerprint or signature, so boxes from different senders cannot be
+//! linked to each other, and the recipient learns nothing about who sent one.
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::api::DeviceCredentials;
+use crate::envelope::key_fingerprint;
+use crate::error::{PqcError, Result};
+use crate::suite::CipherSuite;
+use crate::util::ct;
+use crate::{canonical, fips, kdf, kem, sign};
+
+/// Domain label for authenticated box signatures
+const AUTH_BOX_DOMAIN: &str = "PQC-Algo/v1/auth-box";
+
+/// An encrypted message that also proves who sent it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthBox {
+    pub sender_fingerprint: String,
+    pub recipient_fingerprint: String,
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kem_ciphertext: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub sealed: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of an [`AuthBox`] covered by its signature
+#[derive(Serialize)]
+struct AuthBoxFields<'a> {
+    sender_fingerprint: &'a str,
+    recipient_fingerprint: &'a str,
+    suite: &'a CipherSuite,
+    kem_ciphertext: String,
+    sealed: String,
+}
+
+impl AuthBox {
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(AUTH_BOX_DOMAIN, &AuthBoxFields {
+            sender_fingerprint: &self.sender_fingerprint,
+            recipient_fingerprint: &self.recipient_fingerprint,
+            suite: &self.suite,
+            kem_ciphertext: hex::encode(&self.kem_ciphertext),
+            sealed: hex::encode(&self.sealed),
+        })
+    }
+}
+
+/// Encrypt `message` to `recipient_kyber_public_key` and sign it as `sender`
+pub fn seal(sender: &DeviceCredentials, recipient_kyber_public_key: &[u8], message: &[u8]) -> Result<AuthBox> {
+    seal_with_suite(sender, recipient_kyber_public_key, message, &CipherSuite::active_default())
+}
+
+/// [`seal`] under an explicit suite; `sender`'s keys must belong to it
+pub fn seal_with_suite(sender: &DeviceCredentials, recipient_kyber_public_key: &[u8], message: &[u8], suite: &CipherSuite) -> Result<AuthBox> {
+    fips::check_suite(suite)?;
+    let sender_fingerprint = key_fingerprint(&sender.dilithium_public_key);
+    let recipient_fingerprint = key_fingerprint(recipient_kyber_public_key);
+
+    let (kem_ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, recipient_kyber_public_key)?;
+    let shared_secret = Zeroizing::new(shared_secret);
+    let key = box_key(&shared_secret, &sender_fingerprint, &recipient_fingerprint, suite)?;
+
+    let mut sealed_box = AuthBox {
+        sender_fingerprint,
+        recipient_fingerprint,
+        suite: *suite,
+        kem_ciphertext,
+        sealed: suite.aead.seal(&key, message)?,
+        signature: Vec::new(),
+    };
+    sealed_box.signature = sign::sign_with(suite.sig, &sealed_box.signing_bytes()?, &sender.dilithium_secret_key)?;
+    Ok(sealed_box)
+}
+
+/// Verify that `sender_dilithium_public_key` sent the box, then decrypt it
+pub fn open(sealed_box: &AuthBox, recipient_kyber_secret_key: &[u8], sender_dilithium_public_key: &[u8]) -> Result<Vec<u8>> {
+    let suite = &sealed_box.suite;
+    fips::check_suite(suite)?;
+    if !ct::ct_eq_hex(&sealed_box.sender_fingerprint, &key_fingerprint(sender_dilithium_public_key))
+        || !sign::verify_with(suite.sig, &sealed_box.signing_bytes()?, &sealed_box.signature, sender_dilithium_public_key)?
+    {
+        return Err(PqcError::Verification("Box was not signed by the expected sender".to_string()));
+    }
+
+    let shared_secret = Zeroizing::new(kem::decapsulate_with(suite.kem, recipient_kyber_secret_key, &sealed_box.kem_ciphertext)?);
+    let key = box_key(&shared_secret, &sealed_box.sender_fingerprint, &sealed_box.recipient_fingerprint, suite)?;
+    suite.aead.open(&key, &sealed_box.sealed)
+}
+
+/// An encrypted message with no sender identity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnonymousBox {
+    pub suite: CipherSuite,
+    #[serde(with = "crate::util::bytes")]
+    pub kem_ciphertext: Vec<u8>,
+    #[serde(with = "crate::util::bytes")]
+    pub sealed: Vec<u8>,
+}
+
+/// Encrypt `message` to `recipient_kyber_public_key` without identifying the sender
+pub fn seal_anonymous(recipient_kyber_public_key: &[u8], message: &[u8]) -> Result<AnonymousBox> {
+    seal_anonymous_with_suite(recipient_kyber_public_key, message, &CipherSuite::active_default())
+}
+
+/// [`seal_anonymous`] under an explicit suite
+pub fn seal_anonymous_with_suite(recipient_kyber_public_key: &[u8], message: &[u8], suite: &CipherSuite) -> Result<AnonymousBox> {
+    fips::check_suite(suite)?;
+    let (kem_ciphertext, shared_secret) = kem::encapsulate_with(suite.kem, recipient_kyber_public_key)?;
+    let shared_secret = Zeroizing::new(shared_secret);
+    let key = anonymous_box_key(&shared_secret, suite)?;
+    Ok(AnonymousBox { suite: *suite, sealed: suite.aead.seal(&key, message)?, kem_ciphertext })
+}
+
+/// Decrypt an [`AnonymousBox`]; anyone could have sent it
+pub fn open_anonymous(sealed_box: &AnonymousBox, recipient_kyber_secret_key: &[u8]) -> Result<Vec<u8>> {
+    let suite = &sealed_box.suite;
+    fips::check_suite(suite)?;
+    let shared_secret = Zeroizing::new(kem::decapsulate_with(suite.kem, recipient_kyber_secret_key, &sealed_box.kem_ciphertext)?);
+    let key = anonymous_box_key(&shared_secret, suite)?;
+    suite.aead.open(&key, &sealed_box.sealed)
+}
+
+fn anonymous_box_key(shared_secret: &[u8], suite: &CipherSuite) -> Result<Zeroizing<Vec<u8>>> {
+    kdf::hkdf_sha256(shared_secret, &[], &kdf::label("anonymous-box"), suite.aead.key_len()).map(Zeroizing::new)
+}
+
+/// AEAD key bound to both parties
+fn box_key(shared_secret: &[u8], sender_fingerprint: &str, recipient_fingerprint: &str, suite: &CipherSuite) -> Result<Zeroizing<Vec<u8>>> {
+    let mut info = kdf::label("auth-box");
+    info.extend_from_slice(format!("/{}/{}", sender_fingerprint, recipient_fingerprint).as_bytes());
+    kdf::hkdf_sha256(shared_secret, &[], &info, suite.aead.key_len()).map(Zeroizing::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_box_roundtrip() {
+        let sender = DeviceCredentials::generate().unwrap();
+        let recipient = DeviceCredentials::generate().unwrap();
+
+        let sealed_box = seal(&sender, &recipient.kyber_public_key, b"config v7").unwrap();
+        let opened = open(&sealed_box, &recipient.kyber_secret_key, &sender.dilithium_public_key).unwrap();
+        assert_eq!(opened, b"config v7");
+    }
+
+    #[test]
+    fn test_auth_box_rejects_forgery() {
+        let sender = DeviceCredentials::generate().unwrap();
+        let recipient = DeviceCredentials::generate().unwrap();
+        let mallory = DeviceCredentials::generate().unwrap();
+        let sealed_box = seal(&sender, &recipient.kyber_public_key, b"config v7").unwrap();
+
+        let mut tampered = sealed_box.clone();
+        tampered.sealed[20] ^= 1;
+        assert!(matches!(open(&tampered, &recipient.kyber_secret_key, &sender.dilithium_public_key), Err(PqcError::Verification(_))));
+        assert!(open(&sealed_box, &recipient.kyber_secret_key, &mallory.dilithium_public_key).is_err());
+
+        // Mallory re-signs the box under their own key; the derived key no longer matches
+        let mut resigned = sealed_box.clone();
+        resigned.sender_fingerprint = key_fingerprint(&mallory.dilithium_public_key);
+        resigned.signature = sign::sign_with(resigned.suite.sig, &resigned.signing_bytes().unwrap(), &mallory.dilithium_secret_key).unwrap();
+        assert!(matches!(open(&resigned, &recipient.kyber_secret_key, &mallory.dilithium_public_key), Err(PqcError::Decryption(_))));
+    }
+
+    #[test]
+    fn test_anonymous_box() {
+        let recipient = DeviceCredentials::generate().unwrap();
+        let first = seal_anonymous(&recipient.kyber_public_key, b"telemetry").unwrap();
+        let second = seal_anonymous(&recipient.kyber_public_key, b"telemetry").unwrap();
+
+        assert_ne!(first.kem_ciphertext, second.kem_ciphertext);
+        assert_eq!(open_anonymous(&first, &recipient.kyber_secret_key).unwrap(), b"telemetry");
+
+        let mut tampered = second.clone();
+        tampered.sealed[15] ^= 1;
+        assert!(matches!(open_anonymous(&tampered, &recipient.kyber_secret_key), Err(PqcError::Decryption(_))));
+    }
+}
@@ -0,0 +1,108 @@
+//! Secret key bytes that stay out of logs
+//!
+//! [`SecretKey`] zeroizes on drop, prints as `[redacted]` and has no
+//! `Serialize` impl, so a secret can't end up in a log line or JSON dump
+//! by accident. Code that really has to persist one opts in per field with
+//! `#[serde(with = "expose_secret_serde")]`.
+
+use std::fmt;
+use std::ops::Deref;
+use zeroize::Zeroizing;
+use crate::util::ct;
+
+/// Secret key material
+#[derive(Clone)]
+pub struct SecretKey(Zeroizing<Vec<u8>>);
+
+impl SecretKey {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    /// The raw secret bytes
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretKey {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SecretKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Constant-time, like every other secret comparison in the crate
+impl PartialEq for SecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        ct::ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SecretKey {}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey([redacted; {} bytes])", self.0.len())
+    }
+}
+
+/// Serde adapter that writes secret key bytes in the clear
+///
+/// Only use it for storage that is itself protected, e.g. an encrypted
+/// keystore. The format matches a plain `Vec<u8>` field.
+pub mod expose_secret_serde {
+    use serde::{Deserializer, Serializer};
+    use super::SecretKey;
+
+    pub fn serialize<K, S>(key: &K, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: AsRef<[u8]>,
+        S: Serializer,
+    {
+        crate::util::bytes::serialize(key.as_ref(), serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SecretKey, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        crate::util::bytes::deserialize(deserializer).map(SecretKey::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Stored {
+        #[serde(with = "expose_secret_serde")]
+        key: SecretKey,
+    }
+
+    #[test]
+    fn test_redaction_and_explicit_serde() {
+        let key = SecretKey::new(vec![0xAB; 4]);
+        assert_eq!(format!("{:?}", key), "SecretKey([redacted; 4 bytes])");
+
+        let json = serde_json::to_string(&Stored { key: key.clone() }).unwrap();
+        assert_eq!(json, r#"{"key":[171,171,171,171]}"#);
+        let stored: Stored = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.key, key);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::paths;
+
+/// How much output has been derived from one quantum seed
+///
+/// Each derivation is charged once: entropy handed out counts as bytes, a
+/// generated key pair as one key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeedUsage {
+    pub bytes_derived: u64,
+    pub keys_derived: u64,
+}
+
+/// Limits after which a seed is considered worn out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WearOutPolicy {
+    pub max_bytes: u64,
+    pub max_keys: u64,
+}
+
+impl WearOutPolicy {
+    /// No limits; seeds never wear out
+    pub const UNLIMITED: Self = Self { max_bytes: u64::MAX, max_keys: u64::MAX };
+
+    pub fn new(max_bytes: u64, max_keys: u64) -> Self {
+        Self { max_bytes, max_keys }
+    }
+}
+
+impl Default for WearOutPolicy {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// Snapshot of a seed's usage against its wear-out policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedStatus {
+    /// SHA-256 fingerprint of the combined seed material
+    pub fingerprint: String,
+    pub usage: SeedUsage,
+    pub policy: WearOutPolicy,
+    pub remaining_bytes: u64,
+    pub remaining_keys: u64,
+    /// Running on OS entropy because no quantum seeds were loaded
+    pub degraded: bool,
+}
+
+impl SeedStatus {
+    pub fn new(fingerprint: &str, usage: SeedUsage, policy: WearOutPolicy) -> Self {
+        Self {
+            fingerprint: fingerprint.to_string(),
+            usage,
+            policy,
+            remaining_bytes: policy.max_bytes.saturating_sub(usage.bytes_derived),
+            remaining_keys: policy.max_keys.saturating_sub(usage.keys_derived),
+            degraded: false,
+        }
+    }
+
+    /// Whether fresh seed material is required
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining_bytes == 0 || self.remaining_keys == 0
+    }
+}
+
+/// Usage counters for every seed fingerprint, persisted as JSON
+#[derive(Debug, Default)]
+pub struct SeedUsageStore {
+    path: Option<PathBuf>,
+    counters: HashMap<String, SeedUsage>,
+}
+
+impl SeedUsageStore {
+    /// Counters kept in memory only
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Counters loaded from and saved to `path`; a missing file starts empty
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let counters = if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| PqcError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+            serde_json::from_str(&contents)
+                .map_err(|e| PqcError::InvalidInput(format!("Corrupt seed usage file: {}", e)))?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { path: Some(path), counters })
+    }
+
+    /// Counters in the platform data directory
+    pub fn open_default() -> Result<Self> {
+        Self::open(paths::default_data_dir()?.join("seed_usage.json"))
+    }
+
+    pub fn usage(&self, fingerprint: &str) -> SeedUsage {
+        self.counters.get(fingerprint).copied().unwrap_or_default()
+    }
+
+    /// Add to a seed's counters and persist them
+    pub fn record(&mut self, fingerprint: &str, bytes: u64, keys: u64) -> Result<SeedUsage> {
+        let usage = self.counters.entry(fingerprint.to_string()).or_default();
+        usage.bytes_derived = usage.bytes_derived.saturating_add(bytes);
+        usage.keys_derived = usage.keys_derived.saturating_add(keys);
+        let usage = *usage;
+        self.save()?;
+        Ok(usage)
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_vec_pretty(&self.counters)
+            .map_err(|e| PqcError::Io(format!("Failed to encode seed usage: {}", e)))?;
+        paths::write_private_file(path, &contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_remaining_budget() {
+        let policy = WearOutPolicy::new(100, 2);
+        let usage = SeedUsage { bytes_derived: 60, keys_derived: 2 };
+        let status = SeedStatus::new("abc", usage, policy);
+
+        assert_eq!(status.remaining_bytes, 40);
+        assert_eq!(status.remaining_keys, 0);
+        assert!(status.is_exhausted());
+    }
+
+    #[test]
+    fn test_usage_store_persists() {
+        let path = std::env::temp_dir().join(format!("pqc_seed_usage_{}.json", std::process::id()));
+        fs::remove_file(&path).ok();
+
+        let mut store = SeedUsageStore::open(&path).unwrap();
+        store.record("seed-a", 64, 1).unwrap();
+        store.record("seed-a", 32, 0).unwrap();
+
+        let reopened = SeedUsageStore::open(&path).unwrap();
+        assert_eq!(reopened.usage("seed-a"), SeedUsage { bytes_derived: 96, keys_derived: 1 });
+        assert_eq!(reopened.usage("seed-b"), SeedUsage::default());
+
+        fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::error::{PqcError, Result};
+use crate::qrng::QRNGEntropyService;
+
+/// Watches seed files and reloads the service when they change
+///
+/// Dropping the watcher stops it.
+pub struct SeedWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Start watching the service's seed files and their signed manifest for new uploads
+///
+/// Only files renamed into place are picked up, so uploads must be written
+/// under a temporary name and renamed over the seed or manifest; a file being
+/// written in place is never read half-finished. Every reload is checked
+/// against the manifest at `manifest_path` under `manifest_public_key`, so
+/// seeds that arrive before their manifest fail once and load when the
+/// manifest lands. Reload results are reported through
+/// `QRNGEntropyService::on_seed_reload`.
+pub fn watch_seeds(
+    service: Arc<Mutex<QRNGEntropyService>>,
+    manifest_path: &Path,
+    manifest_public_key: &[u8],
+) -> Result<SeedWatcher> {
+    let (kyber_path, dilithium_path) = {
+        let guard = service.lock().unwrap_or_else(|p| p.into_inner());
+        let (kyber, dilithium) = guard.seed_paths();
+        (absolute(kyber)?, absolute(dilithium)?)
+    };
+    let manifest_path = absolute(manifest_path)?;
+    let watched_files = [kyber_path, dilithium_path, manifest_path.clone()];
+    let dirs = watch_dirs(&watched_files);
+    let manifest_public_key = manifest_public_key.to_vec();
+
+    let handler_service = Arc::clone(&service);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        if renamed_into_place(&event).iter().any(|p| watched_files.contains(p)) {
+            let mut guard = handler_service.lock().unwrap_or_else(|p| p.into_inner());
+            // Failures reach the caller through the reload callback
+            let _ = guard.reload_seeds_with_manifest(&manifest_path, &manifest_public_key);
+        }
+    })
+    .map_err(|e| PqcError::Io(format!("Failed to start seed watcher: {}", e)))?;
+
+    for dir in dirs {
+        watcher.watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| PqcError::Io(format!("Failed to watch {}: {}", dir.display(), e)))?;
+    }
+
+    Ok(SeedWatcher { _watcher: watcher })
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    std::path::absolute(path)
+        .map_err(|e| PqcError::Io(format!("Failed to resolve {}: {}", path.display(), e)))
+}
+
+fn watch_dirs(files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|p| p.parent().map(Path::to_path_buf))
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Paths a file was renamed to by `event`; creates and writes are ignored
+fn renamed_into_place(event: &Event) -> &[PathBuf] {
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event.paths.get(1..).unwrap_or(&[]),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To | RenameMode::Any)) => &event.paths,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange};
+
+    #[test]
+    fn test_only_renames_are_picked_up() {
+        let seed = PathBuf::from("/seeds/kyber.bin");
+        let written = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(seed.clone());
+        let created = Event::new(EventKind::Create(CreateKind::File)).add_path(seed.clone());
+        assert!(renamed_into_place(&written).is_empty());
+        assert!(renamed_into_place(&created).is_empty());
+
+        let moved_to = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::To))).add_path(seed.clone());
+        assert_eq!(renamed_into_place(&moved_to), std::slice::from_ref(&seed));
+        let moved_away = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(seed)
+            .add_path(PathBuf::from("/seeds/kyber.bin.old"));
+        assert_eq!(renamed_into_place(&moved_away), [PathBuf::from("/seeds/kyber.bin.old")]);
+    }
+
+    #[test]
+    fn test_watch_dirs_are_unique() {
+        let files = [PathBuf::from("/a/kyber.bin"), PathBuf::from("/b/manifest.json"), PathBuf::from("/a/dilithium.bin")];
+        assert_eq!(watch_dirs(&files), [PathBuf::from("/a"), PathBuf::from("/b")]);
+    }
+}
@@ -0,0 +1,370 @@
+//! Power-on self tests
+//!
+//! [`self_test`] runs known-answer tests for the symmetric primitives,
+//! pairwise consistency tests for every enabled KEM and signature parameter
+//! set, and SP 800-90B style health tests on the system RNG. ML-KEM and
+//! ML-DSA also get fixed-vector KATs, so a backend that is consistent with
+//! itself but not with FIPS 203/204 still fails. liboqs does not expose
+//! seeded key generation, so these check decapsulation and verification
+//! against the vectors in `src/selftest/`. Run it before serving traffic and
+//! refuse to start if [`SelfTestReport::passed`] is false.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce};
+use rand::RngCore;
+use serde::Serialize;
+use crate::hash::{self, HashAlgorithm};
+use crate::kem::{self, KemAlgorithm};
+use crate::sign::{self, SigAlgorithm};
+use crate::suite::AeadAlgorithm;
+use crate::kdf;
+use crate::util::ct;
+
+/// Bytes sampled for the RNG health tests
+const RNG_SAMPLE_LEN: usize = 4096;
+
+/// Min-entropy per byte assumed by the health test cutoffs
+const ASSESSED_ENTROPY_BITS: u32 = 4;
+
+/// Repetition count cutoff: 1 + ceil(20 / H) for a 2^-20 false alarm rate
+const RCT_CUTOFF: usize = 6;
+
+/// Adaptive proportion window and cutoff: 1 + CRITBINOM(512, 2^-4, 1 - 2^-20)
+const APT_WINDOW: usize = 512;
+const APT_CUTOFF: usize = 62;
+
+/// FIPS 203 decapsulation vectors: `sk`, `ct`, `ss`, and the implicit
+/// rejection secret for `ct` with its first byte flipped
+const KEM_VECTORS: [(KemAlgorithm, &str); 3] = [
+    (KemAlgorithm::MlKem512, include_str!("selftest/ml_kem_512.kat")),
+    (KemAlgorithm::MlKem768, include_str!("selftest/ml_kem_768.kat")),
+    (KemAlgorithm::MlKem1024, include_str!("selftest/ml_kem_1024.kat")),
+];
+
+/// FIPS 204 verification vectors: `pk`, `msg` and a pure ML-DSA `sig` with an empty context
+const SIG_VECTORS: [(SigAlgorithm, &str); 3] = [
+    (SigAlgorithm::MlDsa44, include_str!("selftest/ml_dsa_44.kat")),
+    (SigAlgorithm::MlDsa65, include_str!("selftest/ml_dsa_65.kat")),
+    (SigAlgorithm::MlDsa87, include_str!("selftest/ml_dsa_87.kat")),
+];
+
+/// How a self test checks its primitive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestKind {
+    KnownAnswer,
+    PairwiseConsistency,
+    HealthTest,
+}
+
+/// Result of one self test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "snake_case")]
+pub enum SelfTestStatus {
+    Passed,
+    Failed(String),
+    /// The algorithm is not compiled into this liboqs build
+    Skipped(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestResult {
+    pub name: String,
+    pub kind: SelfTestKind,
+    pub status: SelfTestStatus,
+}
+
+/// Every self test that ran and how long the run took
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub results: Vec<SelfTestResult>,
+    pub duration: Duration,
+}
+
+impl SelfTestReport {
+    /// True when no test failed; skipped tests do not count as failures
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestResult> {
+        self.results.iter().filter(|r| matches!(r.status, SelfTestStatus::Failed(_)))
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            let status = match &result.status {
+                SelfTestStatus::Passed => "PASS".to_string(),
+                SelfTestStatus::Failed(reason) => format!("FAIL ({})", reason),
+                SelfTestStatus::Skipped(reason) => format!("SKIP ({})", reason),
+            };
+            writeln!(f, "{:<28} {}", result.name, status)?;
+        }
+        write!(f, "{} in {:?}", if self.passed() { "PASSED" } else { "FAILED" }, self.duration)
+    }
+}
+
+/// Run all power-on self tests
+pub fn self_test() -> SelfTestReport {
+    let start = Instant::now();
+    let mut results = Vec::new();
+
+    let known_answer = |name: &str, check: fn() -> Result<(), String>| SelfTestResult {
+        name: name.to_string(),
+        kind: SelfTestKind::KnownAnswer,
+        status: check().map_or_else(SelfTestStatus::Failed, |_| SelfTestStatus::Passed),
+    };
+    results.push(known_answer("SHA-256", kat_sha256));
+    results.push(known_answer("SHA-512", kat_sha512));
+    results.push(known_answer("SHA3-256", kat_sha3_256));
+    results.push(known_answer("HKDF-SHA256", kat_hkdf_sha256));
+    results.push(known_answer("AES-128-GCM", kat_aes128_gcm));
+    results.push(known_answer("AES-256-GCM", kat_aes256_gcm));
+    results.push(known_answer("AEAD round trip", aead_round_trip));
+
+    for (algorithm, vectors) in KEM_VECTORS {
+        let name = format!("{} KAT", algorithm.name());
+        results.push(pqc(&name, SelfTestKind::KnownAnswer, algorithm.is_enabled(), || kem_kat(algorithm, vectors)));
+    }
+    for (algorithm, vectors) in SIG_VECTORS {
+        let name = format!("{} KAT", algorithm.name());
+        results.push(pqc(&name, SelfTestKind::KnownAnswer, algorithm.is_enabled(), || sig_kat(algorithm, vectors)));
+    }
+    for &algorithm in KemAlgorithm::all() {
+        results.push(pqc(algorithm.name(), SelfTestKind::PairwiseConsistency, algorithm.is_enabled(), || kem_pct(algorithm)));
+    }
+    for &algorithm in SigAlgorithm::all() {
+        results.push(pqc(algorithm.name(), SelfTestKind::PairwiseConsistency, algorithm.is_enabled(), || sig_pct(algorithm)));
+    }
+
+    let mut sample = vec![0u8; RNG_SAMPLE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut sample);
+    results.push(health("RNG repetition count", repetition_count_test(&sample)));
+    results.push(health("RNG adaptive proportion", adaptive_proportion_test(&sample)));
+
+    SelfTestReport { results, duration: start.elapsed() }
+}
+
+/// A PQC self test, skipped when liboqs was built without the algorithm
+fn pqc(name: &str, kind: SelfTestKind, enabled: bool, check: impl FnOnce() -> Result<(), String>) -> SelfTestResult {
+    let status = if !enabled {
+        SelfTestStatus::Skipped("not enabled in liboqs".to_string())
+    } else {
+        check().map_or_else(SelfTestStatus::Failed, |_| SelfTestStatus::Passed)
+    };
+    SelfTestResult { name: name.to_string(), kind, status }
+}
+
+fn health(name: &str, result: Result<(), String>) -> SelfTestResult {
+    SelfTestResult {
+        name: name.to_string(),
+        kind: SelfTestKind::HealthTest,
+        status: result.map_or_else(SelfTestStatus::Failed, |_| SelfTestStatus::Passed),
+    }
+}
+
+fn expect_hex(actual: &[u8], expected: &str) -> Result<(), String> {
+    if hex::encode(actual) == expected {
+        Ok(())
+    } else {
+        Err(format!("expected {}, got {}", expected, hex::encode(actual)))
+    }
+}
+
+fn kat_sha256() -> Result<(), String> {
+    expect_hex(&hash::digest(HashAlgorithm::Sha256, b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+}
+
+fn kat_sha512() -> Result<(), String> {
+    expect_hex(
+        &hash::digest(HashAlgorithm::Sha512, b"abc"),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+    )
+}
+
+fn kat_sha3_256() -> Result<(), String> {
+    expect_hex(&hash::digest(HashAlgorithm::Sha3_256, b"abc"), "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532")
+}
+
+/// RFC 5869 test case 1
+fn kat_hkdf_sha256() -> Result<(), String> {
+    let salt: Vec<u8> = (0x00..=0x0c).collect();
+    let info: Vec<u8> = (0xf0..=0xf9).collect();
+    let okm = kdf::hkdf_sha256(&[0x0b; 22], &salt, &info, 42).map_err(|e| e.to_string())?;
+    expect_hex(&okm, "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865")
+}
+
+/// GCM spec test case 2: zero key, zero IV, one zero block
+fn kat_aes128_gcm() -> Result<(), String> {
+    let cipher = Aes128Gcm::new_from_slice(&[0u8; 16]).map_err(|e| e.to_string())?;
+    let out = cipher.encrypt(&Nonce::from([0u8; 12]), [0u8; 16].as_slice()).map_err(|e| e.to_string())?;
+    expect_hex(&out, "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf")
+}
+
+/// GCM spec test case 14: zero key, zero IV, one zero block
+fn kat_aes256_gcm() -> Result<(), String> {
+    let cipher = Aes256Gcm::new_from_slice(&[0u8; 32]).map_err(|e| e.to_string())?;
+    let out = cipher.encrypt(&Nonce::from([0u8; 12]), [0u8; 16].as_slice()).map_err(|e| e.to_string())?;
+    expect_hex(&out, "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919")
+}
+
+/// The hex value of the `name = ...` line in a vector file
+fn vector<'a>(vectors: &'a str, name: &str) -> Result<&'a str, String> {
+    vectors
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(" = "))
+        .ok_or_else(|| format!("vector file has no {}", name))
+}
+
+fn vector_bytes(vectors: &str, name: &str) -> Result<Vec<u8>, String> {
+    hex::decode(vector(vectors, name)?).map_err(|e| format!("{}: {}", name, e))
+}
+
+/// Decapsulate a fixed ciphertext, and a corrupted one to check implicit rejection
+fn kem_kat(algorithm: KemAlgorithm, vectors: &str) -> Result<(), String> {
+    let secret_key = vector_bytes(vectors, "sk")?;
+    let mut ciphertext = vector_bytes(vectors, "ct")?;
+    let secret = kem::decapsulate_with(algorithm, &secret_key, &ciphertext).map_err(|e| e.to_string())?;
+    expect_hex(&secret, vector(vectors, "ss")?)?;
+    ciphertext[0] ^= 1;
+    let rejected = kem::decapsulate_with(algorithm, &secret_key, &ciphertext).map_err(|e| e.to_string())?;
+    expect_hex(&rejected, vector(vectors, "rejected")?).map_err(|e| format!("implicit rejection: {}", e))
+}
+
+/// Verify a fixed signature, then the same signature with one bit flipped
+fn sig_kat(algorithm: SigAlgorithm, vectors: &str) -> Result<(), String> {
+    let public_key = vector_bytes(vectors, "pk")?;
+    let message = vector_bytes(vectors, "msg")?;
+    let mut signature = vector_bytes(vectors, "sig")?;
+    if !sign::verify_with(algorithm, &message, &signature, &public_key).map_err(|e| e.to_string())? {
+        return Err("known-good signature rejected".to_string());
+    }
+    signature[0] ^= 1;
+    if sign::verify_with(algorithm, &message, &signature, &public_key).unwrap_or(false) {
+        return Err("corrupted signature verified".to_string());
+    }
+    Ok(())
+}
+
+fn aead_round_trip() -> Result<(), String> {
+    let key = [0x42u8; 32];
+    for aead in [AeadAlgorithm::Aes128Gcm, AeadAlgorithm::Aes256Gcm] {
+        let mut sealed = aead.seal(&key, b"self-test").map_err(|e| e.to_string())?;
+        if aead.open(&key, &sealed).map_err(|e| e.to_string())? != b"self-test" {
+            return Err(format!("{} round trip mismatch", aead.name()));
+        }
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        if aead.open(&key, &sealed).is_ok() {
+            return Err(format!("{} accepted a forged tag", aead.name()));
+        }
+    }
+    Ok(())
+}
+
+fn kem_pct(algorithm: KemAlgorithm) -> Result<(), String> {
+    let keypair = kem::generate_keypair_with(algorithm).map_err(|e| e.to_string())?;
+    let (mut ciphertext, sent) = kem::encapsulate_with(algorithm, &keypair.public_key).map_err(|e| e.to_string())?;
+    let received = kem::decapsulate_with(algorithm, &keypair.secret_key, &ciphertext).map_err(|e| e.to_string())?;
+    if !ct::ct_eq(&sent, &received) {
+        return Err("shared secrets differ".to_string());
+    }
+    ciphertext[0] ^= 1;
+    if kem::decapsulate_with(algorithm, &keypair.secret_key, &ciphertext).is_ok_and(|secret| ct::ct_eq(&secret, &sent)) {
+        return Err("tampered ciphertext produced the same secret".to_string());
+    }
+    Ok(())
+}
+
+fn sig_pct(algorithm: SigAlgorithm) -> Result<(), String> {
+    let keypair = sign::generate_keypair_with(algorithm).map_err(|e| e.to_string())?;
+    let signature = sign::sign_with(algorithm, b"self-test", &keypair.secret_key).map_err(|e| e.to_string())?;
+    if !sign::verify_with(algorithm, b"self-test", &signature, &keypair.public_key).map_err(|e| e.to_string())? {
+        return Err("valid signature rejected".to_string());
+    }
+    if sign::verify_with(algorithm, b"self-tesT", &signature, &keypair.public_key).unwrap_or(false) {
+        return Err("signature verified for a different message".to_string());
+    }
+    Ok(())
+}
+
+/// Run both SP 800-90B continuous health tests over an RNG sample
+///
+/// For monitoring a generator between self tests; samples should be at
+/// least 512 bytes so the adaptive proportion test sees a full window.
+pub fn rng_health_check(sample: &[u8]) -> Result<(), String> {
+    repetition_count_test(sample)?;
+    adaptive_proportion_test(sample)
+}
+
+/// SP 800-90B 4.4.1: no value may repeat `RCT_CUTOFF` times in a row
+fn repetition_count_test(sample: &[u8]) -> Result<(), String> {
+    let mut run = 1;
+    for pair in sample.windows(2) {
+        run = if pair[0] == pair[1] { run + 1 } else { 1 };
+        if run >= RCT_CUTOFF {
+            return Err(format!("byte {:#04x} repeated {} times", pair[1], run));
+        }
+    }
+    Ok(())
+}
+
+/// SP 800-90B 4.4.2: the first value of each window may not recur `APT_CUTOFF` times in it
+fn adaptive_proportion_test(sample: &[u8]) -> Result<(), String> {
+    for window in sample.chunks_exact(APT_WINDOW) {
+        let count = window.iter().filter(|&&b| b == window[0]).count();
+        if count >= APT_CUTOFF {
+            return Err(format!(
+                "byte {:#04x} seen {} times in {} (assumed {} bits/byte)",
+                window[0], count, APT_WINDOW, ASSESSED_ENTROPY_BITS
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = self_test();
+        assert!(report.passed(), "{}", report);
+        assert!(report.results.iter().any(|r| r.name == "ML-KEM-1024"));
+        assert!(report.results.iter().any(|r| r.name == "ML-DSA-65 KAT" && r.kind == SelfTestKind::KnownAnswer));
+    }
+
+    #[test]
+    fn test_vectors_match_parameter_sizes() {
+        for (algorithm, vectors) in KEM_VECTORS {
+            let sizes = algorithm.sizes();
+            assert_eq!(vector_bytes(vectors, "sk").unwrap().len(), sizes.secret_key);
+            assert_eq!(vector_bytes(vectors, "ct").unwrap().len(), sizes.ciphertext);
+            assert_eq!(vector_bytes(vectors, "ss").unwrap().len(), sizes.shared_secret);
+            assert_eq!(vector_bytes(vectors, "rejected").unwrap().len(), sizes.shared_secret);
+        }
+        for (algorithm, vectors) in SIG_VECTORS {
+            let sizes = algorithm.sizes();
+            assert_eq!(vector_bytes(vectors, "pk").unwrap().len(), sizes.public_key);
+            assert_eq!(vector_bytes(vectors, "sig").unwrap().len(), sizes.signature);
+            assert!(vector(vectors, "seed").is_err());
+        }
+    }
+
+    #[test]
+    fn test_health_tests_catch_stuck_source() {
+        let mut sample = vec![0u8; RNG_SAMPLE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut sample);
+        assert!(repetition_count_test(&sample).is_ok());
+
+        sample[100..106].fill(0xaa);
+        assert!(repetition_count_test(&sample).is_err());
+        assert!(adaptive_proportion_test(&[7u8; APT_WINDOW]).is_err());
+        assert!(rng_health_check(&[7u8; APT_WINDOW]).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Dual-format signature verification during an algorithm migration
+//!
+//! While a fleet moves from one signature algorithm to another, devices on
+//! old firmware still sign with the legacy algorithm. A [`DualVerifier`]
+//! accepts either algorithm until the policy's grace period ends, reports
+//! which one each signature used, and keeps counters so operators can see
+//! when the legacy share reaches zero.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::envelope::unix_now;
+use crate::error::Result;
+use crate::sign::{self, SigAlgorithm};
+
+/// Legacy and successor algorithms plus the end of the grace period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationPolicy {
+    pub legacy: SigAlgorithm,
+    pub successor: SigAlgorithm,
+    /// Unix time after which legacy signatures are refused; `None` keeps accepting them
+    pub legacy_until: Option<u64>,
+}
+
+impl Default for MigrationPolicy {
+    /// Dilithium3 → ML-DSA-65 with no deadline set yet
+    fn default() -> Self {
+        Self::new(SigAlgorithm::Dilithium3, SigAlgorithm::MlDsa65)
+    }
+}
+
+impl MigrationPolicy {
+    pub fn new(legacy: SigAlgorithm, successor: SigAlgorithm) -> Self {
+        Self { legacy, successor, legacy_until: None }
+    }
+
+    pub fn with_legacy_until(mut self, unix_time: u64) -> Self {
+        self.legacy_until = Some(unix_time);
+        self
+    }
+
+    pub fn legacy_accepted_at(&self, unix_time: u64) -> bool {
+        self.legacy_until.is_none_or(|until| unix_time <= until)
+    }
+}
+
+/// A device's registered public keys, one per algorithm
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeviceKeys<'a> {
+    pub legacy: Option<&'a [u8]>,
+    pub successor: Option<&'a [u8]>,
+}
+
+/// What happened to one signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationEvent {
+    /// Signature verified under `algorithm`
+    Accepted { algorithm: SigAlgorithm, legacy: bool },
+    /// A valid legacy signature arrived after the grace period
+    LegacyRefused { algorithm: SigAlgorithm },
+}
+
+type EventHook = Box<dyn Fn(&MigrationEvent) + Send + Sync>;
+
+/// Verifier that accepts legacy or successor signatures per a [`MigrationPolicy`]
+pub struct DualVerifier {
+    policy: MigrationPolicy,
+    on_event: Option<EventHook>,
+    legacy_accepted: AtomicU64,
+    successor_accepted: AtomicU64,
+    legacy_refused: AtomicU64,
+}
+
+impl DualVerifier {
+    pub fn new(policy: MigrationPolicy) -> Self {
+        Self {
+            policy,
+            on_event: None,
+            legacy_accepted: AtomicU64::new(0),
+            successor_accepted: AtomicU64::new(0),
+            legacy_refused: AtomicU64::new(0),
+        }
+    }
+
+    /// Called for every accepted or refused signature, e.g. to log it
+    pub fn on_event<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&MigrationEvent) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Box::new(hook));
+        self
+    }
+
+    pub fn policy(&self) -> &MigrationPolicy {
+        &self.policy
+    }
+
+    /// Verify at the current time; returns the algorithm that verified, if any
+    pub fn verify(&self, message: &[u8], signature: &[u8], keys: DeviceKeys<'_>) -> Result<Option<SigAlgorithm>> {
+        self.verify_at(unix_now(), message, signature, keys)
+    }
+
+    /// Verify as of `unix_time`
+    pub fn verify_at(&self, unix_time: u64, message: &[u8], signature: &[u8], keys: DeviceKeys<'_>) -> Result<Option<SigAlgorithm>> {
+        if let Some(key) = keys.successor {
+            if matches!(sign::verify_with(self.policy.successor, message, signature, key), Ok(true)) {
+                self.successor_accepted.fetch_add(1, Ordering::Relaxed);
+                self.emit(MigrationEvent::Accepted { algorithm: self.policy.successor, legacy: false });
+                return Ok(Some(self.policy.successor));
+            }
+        }
+
+        if let Some(key) = keys.legacy {
+            if matches!(sign::verify_with(self.policy.legacy, message, signature, key), Ok(true)) {
+                if !self.policy.legacy_accepted_at(unix_time) {
+                    self.legacy_refused.fetch_add(1, Ordering::Relaxed);
+                    self.emit(MigrationEvent::LegacyRefused { algorithm: self.policy.legacy });
+                    return Ok(None);
+                }
+                self.legacy_accepted.fetch_add(1, Ordering::Relaxed);
+                self.emit(MigrationEvent::Accepted { algorithm: self.policy.legacy, legacy: true });
+                return Ok(Some(self.policy.legacy));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// `(legacy accepted, successor accepted, legacy refused)` since creation
+    pub fn counts(&self) -> (u64, u64, u64) {
+        (
+            self.legacy_accepted.load(Ordering::Relaxed),
+            self.successor_accepted.load(Ordering::Relaxed),
+            self.legacy_refused.load(Ordering::Relaxed),
+        )
+    }
+
+    fn emit(&self, event: MigrationEvent) {
+        if let Some(hook) = &self.on_event {
+            hook(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_accepts_both_until_deadline() {
+        let old = sign::generate_keypair_with(SigAlgorithm::Dilithium3).unwrap();
+        let new = sign::generate_keypair_with(SigAlgorithm::MlDsa65).unwrap();
+        let keys = DeviceKeys { legacy: Some(&old.public_key), successor: Some(&new.public_key) };
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&events);
+        let verifier = DualVerifier::new(MigrationPolicy::default().with_legacy_until(1_000))
+            .on_event(move |e| log.lock().unwrap().push(*e));
+
+        let legacy_sig = sign::sign_with(SigAlgorithm::Dilithium3, b"fw", &old.secret_key).unwrap();
+        let new_sig = sign::sign_with(SigAlgorithm::MlDsa65, b"fw", &new.secret_key).unwrap();
+
+        assert_eq!(verifier.verify_at(999, b"fw", &legacy_sig, keys).unwrap(), Some(SigAlgorithm::Dilithium3));
+        assert_eq!(verifier.verify_at(999, b"fw", &new_sig, keys).unwrap(), Some(SigAlgorithm::MlDsa65));
+        assert_eq!(verifier.verify_at(1_001, b"fw", &legacy_sig, keys).unwrap(), None);
+        assert_eq!(verifier.verify_at(1_001, b"other", &new_sig, keys).unwrap(), None);
+
+        assert_eq!(verifier.counts(), (1, 1, 1));
+        assert_eq!(events.lock().unwrap().last(), Some(&MigrationEvent::LegacyRefused { algorithm: SigAlgorithm::Dilithium3 }));
+    }
+}
@@ -1,57 +1,513 @@
+use std::fmt;
+#[cfg(feature = "liboqs")]
+use std::ptr::NonNull;
+use std::str::FromStr;
+#[cfg(feature = "liboqs")]
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
 use crate::error::{PqcError, Result};
-use oqs::sig::{Sig, Algorithm};
+use crate::hash::HashAlgorithm;
+#[cfg(feature = "liboqs")]
+use oqs::sig::Algorithm;
+
+/// Domain separator for prehashed signatures
+const PREHASH_DOMAIN: &[u8] = b"PQC_ALGO_PREHASH_V1";
+
+/// Byte sizes of one signature parameter set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigSizes {
+    pub public_key: usize,
+    pub secret_key: usize,
+    /// Largest signature; [`sign_into`] reports the actual length
+    pub signature: usize,
+}
+
+/// Dilithium and ML-DSA (FIPS 204) parameter sets
+///
+/// New parameter sets may be added in minor releases, so downstream matches
+/// need a wildcard arm. Convert to and from `oqs::sig::Algorithm` with
+/// `From`/`TryFrom` if you also use liboqs directly (feature `liboqs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum SigAlgorithm {
+    Dilithium2,
+    Dilithium3,
+    Dilithium5,
+    MlDsa44,
+    MlDsa65,
+    MlDsa87,
+}
+
+impl SigAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SigAlgorithm::Dilithium2 => "Dilithium2",
+            SigAlgorithm::Dilithium3 => "Dilithium3",
+            SigAlgorithm::Dilithium5 => "Dilithium5",
+            SigAlgorithm::MlDsa44 => "ML-DSA-44",
+            SigAlgorithm::MlDsa65 => "ML-DSA-65",
+            SigAlgorithm::MlDsa87 => "ML-DSA-87",
+        }
+    }
+
+    #[cfg(feature = "liboqs")]
+    pub(crate) fn oqs(&self) -> Algorithm {
+        match self {
+            SigAlgorithm::Dilithium2 => Algorithm::Dilithium2,
+            SigAlgorithm::Dilithium3 => Algorithm::Dilithium3,
+            SigAlgorithm::Dilithium5 => Algorithm::Dilithium5,
+            SigAlgorithm::MlDsa44 => Algorithm::MlDsa44,
+            SigAlgorithm::MlDsa65 => Algorithm::MlDsa65,
+            SigAlgorithm::MlDsa87 => Algorithm::MlDsa87,
+        }
+    }
+
+    /// Public key length in bytes, as the linked liboqs reports it
+    pub fn public_key_len(&self) -> Result<usize> {
+        raw_backend(*self, PqcError::InvalidInput).map(|sig| sig.lengths().0)
+    }
+
+    /// Maximum signature length in bytes, as the linked liboqs reports it
+    pub fn signature_len(&self) -> Result<usize> {
+        raw_backend(*self, PqcError::InvalidInput).map(|sig| sig.lengths().1)
+    }
+
+    /// Exact buffer sizes, usable in constant expressions such as array lengths
+    pub const fn sizes(&self) -> SigSizes {
+        let (public_key, secret_key, signature) = match self {
+            SigAlgorithm::Dilithium2 => (1312, 2528, 2420),
+            SigAlgorithm::Dilithium3 => (1952, 4000, 3293),
+            SigAlgorithm::Dilithium5 => (2592, 4864, 4595),
+            SigAlgorithm::MlDsa44 => (1312, 2560, 2420),
+            SigAlgorithm::MlDsa65 => (1952, 4032, 3309),
+            SigAlgorithm::MlDsa87 => (2592, 4896, 4627),
+        };
+        SigSizes { public_key, secret_key, signature }
+    }
+
+    /// How the linked liboqs signs with this parameter set
+    ///
+    /// liboqs fixes the mode at build time: the round 3 Dilithium sets sign
+    /// deterministically and the FIPS 204 ML-DSA sets sign hedged.
+    pub fn signing_mode(&self) -> SigningMode {
+        match self {
+            SigAlgorithm::Dilithium2 | SigAlgorithm::Dilithium3 | SigAlgorithm::Dilithium5 => SigningMode::Deterministic,
+            SigAlgorithm::MlDsa44 | SigAlgorithm::MlDsa65 | SigAlgorithm::MlDsa87 => SigningMode::Hedged,
+        }
+    }
+
+    /// Whether the linked liboqs was built with this parameter set
+    #[cfg(feature = "liboqs")]
+    pub fn is_enabled(&self) -> bool {
+        self.oqs().is_enabled()
+    }
+
+    /// Always false without the `liboqs` feature
+    #[cfg(not(feature = "liboqs"))]
+    pub fn is_enabled(&self) -> bool {
+        false
+    }
+
+    /// The parameter set `secret_key` belongs to
+    ///
+    /// Every set has its own secret key length, so a signing key names its
+    /// algorithm even where public key lengths coincide.
+    pub fn for_secret_key(secret_key: &[u8]) -> Result<SigAlgorithm> {
+        Self::all().iter().copied()
+            .find(|algorithm| algorithm.sizes().secret_key == secret_key.len())
+            .ok_or_else(|| PqcError::InvalidKey(format!("No signature algorithm has {}-byte secret keys", secret_key.len())))
+    }
+
+    pub fn all() -> &'static [SigAlgorithm] {
+        &[
+            SigAlgorithm::Dilithium2,
+            SigAlgorithm::Dilithium3,
+            SigAlgorithm::Dilithium5,
+            SigAlgorithm::MlDsa44,
+            SigAlgorithm::MlDsa65,
+            SigAlgorithm::MlDsa87,
+        ]
+    }
+}
+
+impl fmt::Display for SigAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(feature = "liboqs")]
+impl From<SigAlgorithm> for Algorithm {
+    fn from(algorithm: SigAlgorithm) -> Self {
+        algorithm.oqs()
+    }
+}
+
+#[cfg(feature = "liboqs")]
+impl TryFrom<Algorithm> for SigAlgorithm {
+    type Error = PqcError;
+
+    fn try_from(algorithm: Algorithm) -> Result<Self> {
+        match algorithm {
+            Algorithm::Dilithium2 => Ok(SigAlgorithm::Dilithium2),
+            Algorithm::Dilithium3 => Ok(SigAlgorithm::Dilithium3),
+            Algorithm::Dilithium5 => Ok(SigAlgorithm::Dilithium5),
+            Algorithm::MlDsa44 => Ok(SigAlgorithm::MlDsa44),
+            Algorithm::MlDsa65 => Ok(SigAlgorithm::MlDsa65),
+            Algorithm::MlDsa87 => Ok(SigAlgorithm::MlDsa87),
+            other => Err(PqcError::InvalidInput(format!("Unsupported signature algorithm: {:?}", other))),
+        }
+    }
+}
+
+impl FromStr for SigAlgorithm {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        SigAlgorithm::all()
+            .iter()
+            .copied()
+            .find(|alg| alg.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| PqcError::InvalidInput(format!("Unknown signature algorithm: {}", s)))
+    }
+}
+
+/// Whether signing mixes in fresh randomness
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningMode {
+    /// Fresh randomness in every signature; harder to attack with faults and side channels
+    Hedged,
+    /// The same key and message always give the same signature
+    Deterministic,
+}
+
+impl fmt::Display for SigningMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SigningMode::Hedged => "hedged",
+            SigningMode::Deterministic => "deterministic",
+        })
+    }
+}
+
+/// Options for [`sign_with_options`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignOptions<'a> {
+    /// Mode the signature must be made in; `None` accepts the algorithm's own
+    pub mode: Option<SigningMode>,
+    /// Public key to check each signature against before returning it
+    pub verify_key: Option<&'a [u8]>,
+}
+
+impl<'a> SignOptions<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refuse to sign unless the algorithm signs in `mode`
+    pub fn mode(mut self, mode: SigningMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Verify each signature under `public_key` and sign again once if it fails
+    ///
+    /// A fault injected during signing can produce a signature that leaks
+    /// the secret key; checking it first keeps it from leaving the device.
+    /// Each signature costs one extra verification (see `benches/sign.rs`).
+    pub fn verify_after_sign(mut self, public_key: &'a [u8]) -> Self {
+        self.verify_key = Some(public_key);
+        self
+    }
+}
 
 pub struct DilithiumKeyPair {
     pub public_key: Vec<u8>,
     pub secret_key: Vec<u8>,
 }
 
+/// Prints the public key fingerprint, never the secret key
+impl fmt::Debug for DilithiumKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DilithiumKeyPair")
+            .field("fingerprint", &crate::envelope::key_fingerprint(&self.public_key))
+            .field("secret_key", &format_args!("[redacted; {} bytes]", self.secret_key.len()))
+            .finish()
+    }
+}
+
+/// liboqs's descriptor for one parameter set, created on first use and kept for the process
+#[cfg(feature = "liboqs")]
+struct RawSig(NonNull<oqs_sys::sig::OQS_SIG>);
+
+/// Stands in for the descriptor without liboqs, where [`raw_backend`] always fails
+#[cfg(not(feature = "liboqs"))]
+enum RawSig {}
+
+// SAFETY: liboqs never mutates a descriptor after `OQS_SIG_new` returns it
+#[cfg(feature = "liboqs")]
+unsafe impl Send for RawSig {}
+#[cfg(feature = "liboqs")]
+unsafe impl Sync for RawSig {}
+
+/// The descriptor every operation calls through, so they allocate nothing per call
+#[cfg(feature = "liboqs")]
+fn raw_backend(algorithm: SigAlgorithm, error: impl FnOnce(String) -> PqcError) -> Result<&'static RawSig> {
+    // One slot per variant, indexed by discriminant
+    static DESCRIPTORS: [OnceLock<Option<RawSig>>; 6] = [const { OnceLock::new() }; 6];
+    crate::backend::init()?;
+    DESCRIPTORS[algorithm as usize]
+        // SAFETY: `to_id` is a static NUL-terminated method name
+        .get_or_init(|| NonNull::new(unsafe { oqs_sys::sig::OQS_SIG_new(algorithm.oqs().to_id()) }).map(RawSig))
+        .as_ref()
+        .ok_or_else(|| error(format!("{} is not enabled in the linked liboqs", algorithm)))
+}
+
+#[cfg(not(feature = "liboqs"))]
+fn raw_backend(_: SigAlgorithm, _: impl FnOnce(String) -> PqcError) -> Result<&'static RawSig> {
+    Err(crate::backend::unavailable())
+}
+
+/// Keys and signature buffers passed in must have the parameter set's sizes
+#[cfg(feature = "liboqs")]
+impl RawSig {
+    /// `(public key, maximum signature)` lengths liboqs reports
+    fn lengths(&self) -> (usize, usize) {
+        // SAFETY: descriptors live for the rest of the process
+        let sig = unsafe { self.0.as_ref() };
+        (sig.length_public_key, sig.length_signature)
+    }
+
+    unsafe fn keypair(&self, public_key: &mut [u8], secret_key: &mut [u8]) -> Result<()> {
+        let status = oqs_sys::sig::OQS_SIG_keypair(self.0.as_ptr(), public_key.as_mut_ptr(), secret_key.as_mut_ptr());
+        crate::backend::check_status(status, PqcError::KeyGeneration)
+    }
+
+    /// Returns the signature length
+    unsafe fn sign(&self, message: &[u8], secret_key: &[u8], signature: &mut [u8]) -> Result<usize> {
+        let mut len = 0usize;
+        let status = oqs_sys::sig::OQS_SIG_sign(
+            self.0.as_ptr(), signature.as_mut_ptr(), &mut len, message.as_ptr(), message.len(), secret_key.as_ptr()
+        );
+        crate::backend::check_status(status, PqcError::Signing)?;
+        Ok(len)
+    }
+
+    unsafe fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+        let status = oqs_sys::sig::OQS_SIG_verify(
+            self.0.as_ptr(), message.as_ptr(), message.len(), signature.as_ptr(), signature.len(), public_key.as_ptr()
+        );
+        matches!(status, oqs_sys::common::OQS_STATUS::OQS_SUCCESS)
+    }
+}
+
+#[cfg(not(feature = "liboqs"))]
+impl RawSig {
+    fn lengths(&self) -> (usize, usize) {
+        match *self {}
+    }
+
+    unsafe fn keypair(&self, _: &mut [u8], _: &mut [u8]) -> Result<()> {
+        match *self {}
+    }
+
+    unsafe fn sign(&self, _: &[u8], _: &[u8], _: &mut [u8]) -> Result<usize> {
+        match *self {}
+    }
+
+    unsafe fn verify(&self, _: &[u8], _: &[u8], _: &[u8]) -> bool {
+        match *self {}
+    }
+}
+
 pub fn generate_keypair() -> Result<DilithiumKeyPair> {
-    let sig = Sig::new(Algorithm::Dilithium3)
-        .map_err(|e| PqcError::KeyGeneration(format!("{:?}", e)))?;
-    
-    let (pk, sk) = sig.keypair()
-        .map_err(|e| PqcError::KeyGeneration(format!("{:?}", e)))?;
-    
-    Ok(DilithiumKeyPair {
-        public_key: pk.into_vec(),
-        secret_key: sk.into_vec(),
-    })
+    generate_keypair_with(SigAlgorithm::Dilithium3)
+}
+
+pub fn generate_keypair_with(algorithm: SigAlgorithm) -> Result<DilithiumKeyPair> {
+    let sizes = algorithm.sizes();
+    let sig = raw_backend(algorithm, PqcError::KeyGeneration)?;
+    let mut keypair = DilithiumKeyPair { public_key: vec![0; sizes.public_key], secret_key: vec![0; sizes.secret_key] };
+    // SAFETY: both buffers have the parameter set's sizes
+    unsafe { sig.keypair(&mut keypair.public_key, &mut keypair.secret_key) }?;
+    Ok(keypair)
 }
 
 pub fn sign(message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
-    let sig = Sig::new(Algorithm::Dilithium3)
-        .map_err(|e| PqcError::Signing(format!("{:?}", e)))?;
-    
-    let sk_ref = sig.secret_key_from_bytes(secret_key)
-        .ok_or_else(|| PqcError::Signing("Invalid secret key length".to_string()))?;
-    
-    let signature = sig.sign(message, sk_ref)
-        .map_err(|e| PqcError::Signing(format!("{:?}", e)))?;
-    
-    Ok(signature.into_vec())
+    sign_with(SigAlgorithm::Dilithium3, message, secret_key)
+}
+
+pub fn sign_with(algorithm: SigAlgorithm, message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
+    let mut signature = vec![0; algorithm.sizes().signature];
+    let len = sign_into_with(algorithm, message, secret_key, &mut signature)?;
+    signature.truncate(len);
+    Ok(signature)
+}
+
+/// [`sign`] into a caller-provided buffer; returns the signature length
+pub fn sign_into(message: &[u8], secret_key: &[u8], signature_out: &mut [u8]) -> Result<usize> {
+    sign_into_with(SigAlgorithm::Dilithium3, message, secret_key, signature_out)
+}
+
+/// Sign into a buffer of at least `algorithm.sizes().signature` bytes
+///
+/// The sizes are checked before signing. liboqs writes straight into the
+/// buffer; after the parameter set's first use nothing is allocated.
+pub fn sign_into_with(algorithm: SigAlgorithm, message: &[u8], secret_key: &[u8], signature_out: &mut [u8]) -> Result<usize> {
+    let sizes = algorithm.sizes();
+    if secret_key.len() != sizes.secret_key {
+        return Err(PqcError::Signing("Invalid secret key length".to_string()));
+    }
+    if signature_out.len() < sizes.signature {
+        return Err(PqcError::Signing(format!(
+            "Signature buffer is {} bytes, {} needs {}", signature_out.len(), algorithm, sizes.signature
+        )));
+    }
+    let sig = raw_backend(algorithm, PqcError::Signing)?;
+    // SAFETY: the key and buffer were checked against the parameter set's sizes above
+    unsafe { sig.sign(message, secret_key, signature_out) }
+}
+
+/// Sign with explicit [`SignOptions`]
+///
+/// liboqs can't switch modes at runtime, so asking for a mode the algorithm
+/// doesn't use is an error rather than a silent fallback. Use ML-DSA for
+/// hedged signatures on devices and Dilithium for reproducible release
+/// artifacts.
+pub fn sign_with_options(algorithm: SigAlgorithm, message: &[u8], secret_key: &[u8], options: &SignOptions) -> Result<Vec<u8>> {
+    if let Some(mode) = options.mode {
+        if mode != algorithm.signing_mode() {
+            return Err(PqcError::InvalidInput(format!(
+                "{} signs {}, not {}, with the linked liboqs", algorithm, algorithm.signing_mode(), mode
+            )));
+        }
+    }
+    let Some(public_key) = options.verify_key else {
+        return sign_with(algorithm, message, secret_key);
+    };
+
+    // A glitch rarely hits twice in a row; a second failure means a real problem
+    for _ in 0..2 {
+        let signature = sign_with(algorithm, message, secret_key)?;
+        let valid = verify_with(algorithm, message, &signature, public_key)
+            .map_err(|e| PqcError::Signing(e.to_string()))?;
+        if valid {
+            return Ok(signature);
+        }
+    }
+    Err(PqcError::Signing("Signature failed verification twice; possible fault or mismatched public key".to_string()))
 }
 
 pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
-    let sig = Sig::new(Algorithm::Dilithium3)
-        .map_err(|e| PqcError::Verification(format!("{:?}", e)))?;
-    
-    let pk_ref = sig.public_key_from_bytes(public_key)
-        .ok_or_else(|| PqcError::Verification("Invalid public key length".to_string()))?;
-    
-    let sig_ref = sig.signature_from_bytes(signature)
-        .ok_or_else(|| PqcError::Verification("Invalid signature length".to_string()))?;
-    
-    match sig.verify(message, sig_ref, pk_ref) {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
+    verify_with(SigAlgorithm::Dilithium3, message, signature, public_key)
+}
+
+/// Verify through the parameter set's cached liboqs descriptor, so hot paths such as
+/// [`crate::key_cache`] don't open the algorithm on every call
+pub fn verify_with(algorithm: SigAlgorithm, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+    let sizes = algorithm.sizes();
+    if public_key.len() != sizes.public_key {
+        return Err(PqcError::Verification("Invalid public key length".to_string()));
     }
+    if signature.len() > sizes.signature {
+        return Err(PqcError::Verification("Invalid signature length".to_string()));
+    }
+    let sig = raw_backend(algorithm, PqcError::Verification)?;
+    // SAFETY: the public key length was checked above and liboqs reads `signature.len()` bytes
+    Ok(unsafe { sig.verify(message, signature, public_key) })
+}
+
+/// Sign a message digest computed elsewhere, e.g. by streaming a firmware image
+///
+/// The digest algorithm is bound into the signed data, so a signature made
+/// over a SHA-256 digest can't be replayed as one over a different hash.
+pub fn sign_prehashed(digest: &[u8], algorithm: HashAlgorithm, secret_key: &[u8]) -> Result<Vec<u8>> {
+    let message = prehash_message(digest, algorithm)
+        .map_err(|e| PqcError::Signing(e.to_string()))?;
+    sign(&message, secret_key)
+}
+
+/// Verify a signature produced by [`sign_prehashed`]
+pub fn verify_prehashed(digest: &[u8], algorithm: HashAlgorithm, signature: &[u8], public_key: &[u8]) -> Result<bool> {
+    let message = prehash_message(digest, algorithm)
+        .map_err(|e| PqcError::Verification(e.to_string()))?;
+    verify(&message, signature, public_key)
+}
+
+fn prehash_message(digest: &[u8], algorithm: HashAlgorithm) -> Result<Vec<u8>> {
+    if digest.len() != algorithm.output_len() {
+        return Err(PqcError::InvalidInput(format!(
+            "{} digest must be {} bytes, got {}", algorithm, algorithm.output_len(), digest.len()
+        )));
+    }
+    let name = algorithm.name().as_bytes();
+    let mut message = PREHASH_DOMAIN.to_vec();
+    message.push(name.len() as u8);
+    message.extend_from_slice(name);
+    message.extend_from_slice(digest);
+    Ok(message)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sizes_match_backend_and_sign_into() {
+        for &algorithm in SigAlgorithm::all() {
+            assert_eq!(algorithm.sizes().public_key, algorithm.public_key_len().unwrap());
+            assert_eq!(algorithm.sizes().signature, algorithm.signature_len().unwrap());
+            let keypair = generate_keypair_with(algorithm).unwrap();
+            assert_eq!(SigAlgorithm::for_secret_key(&keypair.secret_key).unwrap(), algorithm);
+        }
+        assert!(matches!(SigAlgorithm::for_secret_key(&[0u8; 100]), Err(PqcError::InvalidKey(_))));
+
+        let keypair = generate_keypair_with(SigAlgorithm::MlDsa65).unwrap();
+        let mut signature = [0u8; SigAlgorithm::MlDsa65.sizes().signature];
+        let len = sign_into_with(SigAlgorithm::MlDsa65, b"telemetry", &keypair.secret_key, &mut signature).unwrap();
+        assert!(verify_with(SigAlgorithm::MlDsa65, b"telemetry", &signature[..len], &keypair.public_key).unwrap());
+        assert!(sign_into_with(SigAlgorithm::MlDsa65, b"telemetry", &keypair.secret_key, &mut signature[..100]).is_err());
+    }
+
+    #[cfg(feature = "liboqs")]
+    #[test]
+    fn test_oqs_conversion() {
+        for &algorithm in SigAlgorithm::all() {
+            assert_eq!(SigAlgorithm::try_from(Algorithm::from(algorithm)).unwrap(), algorithm);
+        }
+        assert!(matches!(SigAlgorithm::try_from(Algorithm::Falcon512), Err(PqcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_signing_mode_option() {
+        let keypair = generate_keypair_with(SigAlgorithm::MlDsa65).unwrap();
+        let hedged = SignOptions::new().mode(SigningMode::Hedged);
+        let signature = sign_with_options(SigAlgorithm::MlDsa65, b"telemetry", &keypair.secret_key, &hedged).unwrap();
+        assert!(verify_with(SigAlgorithm::MlDsa65, b"telemetry", &signature, &keypair.public_key).unwrap());
+
+        let deterministic = SignOptions::new().mode(SigningMode::Deterministic);
+        assert!(matches!(
+            sign_with_options(SigAlgorithm::MlDsa65, b"telemetry", &keypair.secret_key, &deterministic),
+            Err(PqcError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_after_sign() {
+        let keypair = generate_keypair().unwrap();
+        let other = generate_keypair().unwrap();
+        let options = SignOptions::new().verify_after_sign(&keypair.public_key);
+        let signature = sign_with_options(SigAlgorithm::Dilithium3, b"firmware", &keypair.secret_key, &options).unwrap();
+        assert!(verify(b"firmware", &signature, &keypair.public_key).unwrap());
+
+        let mismatched = SignOptions::new().verify_after_sign(&other.public_key);
+        assert!(matches!(
+            sign_with_options(SigAlgorithm::Dilithium3, b"firmware", &keypair.secret_key, &mismatched),
+            Err(PqcError::Signing(_))
+        ));
+    }
+
     #[test]
     fn test_dilithium_sign_verify() {
         let keypair = generate_keypair().unwrap();
@@ -75,5 +531,32 @@ mod tests {
         let is_valid = verify(tampered_message, &signature, &keypair.public_key).unwrap();
         
         assert!(!is_valid);
+
+        let mut oversized = signature.clone();
+        oversized.push(0);
+        assert!(matches!(verify(message, &oversized, &keypair.public_key), Err(PqcError::Verification(_))));
+        assert!(matches!(verify(message, &signature, &keypair.public_key[1..]), Err(PqcError::Verification(_))));
+    }
+
+    #[test]
+    fn test_prehashed_sign_verify() {
+        let keypair = generate_keypair().unwrap();
+        let image = vec![0xa5u8; 1 << 20];
+        let digest = crate::hash::digest_reader(HashAlgorithm::Sha512, image.as_slice()).unwrap();
+        
+        let signature = sign_prehashed(&digest, HashAlgorithm::Sha512, &keypair.secret_key).unwrap();
+        assert!(verify_prehashed(&digest, HashAlgorithm::Sha512, &signature, &keypair.public_key).unwrap());
+        
+        // The same bytes claimed under another algorithm must not verify
+        assert!(!verify_prehashed(&digest, HashAlgorithm::Sha3_512, &signature, &keypair.public_key).unwrap());
+        assert!(sign_prehashed(&digest[..32], HashAlgorithm::Sha512, &keypair.secret_key).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dilithium5_sign_verify() {
+        let keypair = generate_keypair_with(SigAlgorithm::Dilithium5).unwrap();
+        let signature = sign_with(SigAlgorithm::Dilithium5, b"level 5", &keypair.secret_key).unwrap();
+        assert!(verify_with(SigAlgorithm::Dilithium5, b"level 5", &signature, &keypair.public_key).unwrap());
+        assert!(verify(b"level 5", &signature, &keypair.public_key).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Prioritized, rate-limited signing for busy signing hosts
+//!
+//! A [`SigningQueue`] sits in front of a [`KeyManager`]. Callers submit
+//! messages with a [`Priority`] and get a [`SigningTicket`] to wait on.
+//! Workers take up to the batch size of jobs per wake-up: highest priority
+//! first, in arrival order within a priority. A job that has waited the
+//! aging interval at its priority moves up one level, so a steady stream of
+//! high-priority work delays low-priority jobs but cannot starve them.
+//!
+//! A [`RateLimit`] on a key caps how fast its jobs are taken. Jobs over the
+//! limit stay queued without holding up other keys, so a burst of auth
+//! responses on one key cannot starve firmware signing on another.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::cancel::CancelToken;
+use crate::error::{PqcError, Result};
+use crate::key_manager::{KeyId, KeyManager};
+
+/// Jobs a worker takes per wake-up unless configured otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 16;
+
+/// Queued jobs beyond which submissions are refused unless configured otherwise
+pub const DEFAULT_MAX_PENDING: usize = 4096;
+
+/// Wait at one priority after which a job moves up a level, unless configured otherwise
+pub const DEFAULT_AGING: Duration = Duration::from_secs(5);
+
+/// How long an idle worker sleeps before checking rate limits again
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+/// Order in which queued jobs are signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Bulk work that can wait, e.g. re-signing archives
+    Low,
+    Normal,
+    /// Latency-sensitive work, e.g. firmware releases or auth responses
+    High,
+}
+
+/// Token bucket for one key: up to `burst` jobs at once, refilled at `per_second`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    per_second: u32,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Fails if `per_second` is zero, which would strand the key's jobs
+    pub fn new(per_second: u32, burst: u32) -> Result<Self> {
+        if per_second == 0 {
+            return Err(PqcError::InvalidInput("Rate limit must allow at least one job per second".to_string()));
+        }
+        Ok(Self { per_second, burst: burst.max(1) })
+    }
+
+    pub fn per_second(&self) -> u32 {
+        self.per_second
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self { limit, tokens: f64::from(limit.burst), refilled: now }
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(self.limit.per_second)).min(f64::from(self.limit.burst));
+        self.refilled = self.refilled.max(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+struct Job {
+    key: KeyId,
+    message: Vec<u8>,
+    reply: Sender<Result<Vec<u8>>>,
+    /// When the job reached its current priority
+    queued: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    queues: BTreeMap<Priority, VecDeque<Job>>,
+    pending: usize,
+    buckets: HashMap<KeyId, Bucket>,
+}
+
+impl State {
+    /// Move jobs that waited `aging` at their priority up one level
+    fn promote(&mut self, aging: Duration, now: Instant) {
+        // Normal first, so a job moves at most one level per call
+        for (priority, target) in [(Priority::Normal, Priority::High), (Priority::Low, Priority::Normal)] {
+            let Some(queue) = self.queues.get_mut(&priority) else { continue };
+            // Queues are in arrival order, so aged jobs are at the front
+            let mut aged = Vec::new();
+            while queue.front().is_some_and(|job| now.saturating_duration_since(job.queued) >= aging) {
+                aged.extend(queue.pop_front());
+            }
+            let target = self.queues.entry(target).or_default();
+            for mut job in aged {
+                job.queued = now;
+                target.push_back(job);
+            }
+        }
+    }
+
+    /// Remove up to `max` jobs that their keys' rate limits allow now
+    fn take(&mut self, max: usize, now: Instant) -> Vec<Job> {
+        let mut taken = Vec::new();
+        // Keys whose bucket ran dry; their later jobs are skipped without a check
+        let mut exhausted = HashSet::new();
+        for queue in self.queues.values_mut().rev() {
+            let mut i = 0;
+            while i < queue.len() && taken.len() < max {
+                let key = queue[i].key;
+                let allowed = !exhausted.contains(&key) && self.buckets.get_mut(&key).is_none_or(|bucket| bucket.try_take(now));
+                if allowed {
+                    taken.extend(queue.remove(i));
+                } else {
+                    exhausted.insert(key);
+                    i += 1;
+                }
+            }
+        }
+        self.pending -= taken.len();
+        taken
+    }
+}
+
+/// Result of one submitted job
+pub struct SigningTicket {
+    reply: Receiver<Result<Vec<u8>>>,
+}
+
+impl SigningTicket {
+    /// Block until the job is signed
+    pub fn wait(self) -> Result<Vec<u8>> {
+        self.reply.recv().unwrap_or_else(|_| Err(shut_down()))
+    }
+
+    /// The signature if the job has finished, without blocking
+    pub fn try_wait(&self) -> Option<Result<Vec<u8>>> {
+        match self.reply.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(shut_down())),
+        }
+    }
+}
+
+fn shut_down() -> PqcError {
+    PqcError::Cancelled("Signing queue shut down before the job ran".to_string())
+}
+
+/// Signing jobs for keys in one [`KeyManager`], shared by submitters and workers
+pub struct SigningQueue {
+    manager: Arc<KeyManager>,
+    batch_size: usize,
+    max_pending: usize,
+    aging: Duration,
+    state: Mutex<State>,
+    ready: Condvar,
+}
+
+impl SigningQueue {
+    pub fn new(manager: Arc<KeyManager>) -> Self {
+        Self {
+            manager,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_pending: DEFAULT_MAX_PENDING,
+            aging: DEFAULT_AGING,
+            state: Mutex::new(State::default()),
+            ready: Condvar::new(),
+        }
+    }
+
+    /// Jobs a worker signs per wake-up; larger batches trade latency for throughput
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Refuse submissions with [`PqcError::Overloaded`] while this many jobs wait
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Move a job up one priority after it has waited `aging` at its current one
+    pub fn with_aging(mut self, aging: Duration) -> Self {
+        self.aging = aging;
+        self
+    }
+
+    /// Limit how fast jobs for `key` are signed
+    pub fn with_rate_limit(self, key: KeyId, limit: RateLimit) -> Self {
+        self.lock().buckets.insert(key, Bucket::new(limit, Instant::now()));
+        self
+    }
+
+    /// Queue `message` for signing with `key`
+    pub fn submit(&self, key: KeyId, message: &[u8], priority: Priority) -> Result<SigningTicket> {
+        let (reply, receiver) = mpsc::channel();
+        let mut state = self.lock();
+        if state.pending >= self.max_pending {
+            return Err(PqcError::Overloaded(format!("{} signing jobs already queued", state.pending)));
+        }
+        state.queues.entry(priority).or_default().push_back(Job { key, message: message.to_vec(), reply, queued: Instant::now() });
+        state.pending += 1;
+        drop(state);
+        self.ready.notify_one();
+        Ok(SigningTicket { reply: receiver })
+    }
+
+    /// Jobs waiting to be signed
+    pub fn pending(&self) -> usize {
+        self.lock().pending
+    }
+
+    /// Sign one batch; returns how many jobs it took
+    pub fn run_batch(&self) -> usize {
+        self.run_batch_at(Instant::now())
+    }
+
+    /// [`SigningQueue::run_batch`] with rate limits evaluated at `now`
+    pub fn run_batch_at(&self, now: Instant) -> usize {
+        let jobs = {
+            let mut state = self.lock();
+            state.promote(self.aging, now);
+            state.take(self.batch_size, now)
+        };
+        for job in &jobs {
+            // The submitter may have stopped waiting; the job is done either way
+            let _ = job.reply.send(self.manager.sign(job.key, &job.message));
+        }
+        jobs.len()
+    }
+
+    /// Work through the queue until `token` is cancelled
+    ///
+    /// Run one per signing thread. Sleeps while the queue is empty or every
+    /// queued job is over its key's rate limit.
+    pub fn run(&self, token: &CancelToken) {
+        while !token.is_cancelled() {
+            if self.run_batch() == 0 {
+                let state = self.lock();
+                let _ = self.ready.wait_timeout(state, IDLE_WAIT);
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPolicy;
+    use crate::sign;
+
+    fn manager_with_keys(n: usize) -> (Arc<KeyManager>, Vec<KeyId>) {
+        let manager = Arc::new(KeyManager::new());
+        let ids = (0..n).map(|_| manager.register_dilithium(sign::generate_keypair().unwrap(), KeyPolicy::default())).collect();
+        (manager, ids)
+    }
+
+    #[test]
+    fn test_batches_follow_priority() {
+        let (manager, ids) = manager_with_keys(1);
+        let queue = SigningQueue::new(Arc::clone(&manager)).with_batch_size(2);
+        let low = queue.submit(ids[0], b"archive", Priority::Low).unwrap();
+        let normal = queue.submit(ids[0], b"report", Priority::Normal).unwrap();
+        let high = queue.submit(ids[0], b"firmware", Priority::High).unwrap();
+
+        assert_eq!(queue.run_batch(), 2);
+        assert!(low.try_wait().is_none());
+        let signature = high.try_wait().unwrap().unwrap();
+        assert!(sign::verify(b"firmware", &signature, &manager.public_key(ids[0]).unwrap()).unwrap());
+        assert!(normal.try_wait().unwrap().is_ok());
+        assert_eq!(queue.run_batch(), 1);
+        assert!(low.wait().is_ok());
+    }
+
+    #[test]
+    fn test_rate_limited_key_does_not_starve_others() {
+        let (manager, ids) = manager_with_keys(2);
+        let (auth, firmware) = (ids[0], ids[1]);
+        let queue = SigningQueue::new(manager).with_rate_limit(auth, RateLimit::new(1, 2).unwrap());
+        let start = Instant::now();
+        let responses: Vec<_> = (0..5).map(|i| queue.submit(auth, &[i], Priority::High).unwrap()).collect();
+        let release = queue.submit(firmware, b"image digest", Priority::Low).unwrap();
+
+        assert_eq!(queue.run_batch_at(start), 3);
+        assert!(release.try_wait().unwrap().is_ok());
+        assert_eq!(queue.run_batch_at(start), 0);
+        assert_eq!(queue.run_batch_at(start + Duration::from_secs(1)), 1);
+        assert_eq!(queue.pending(), 2);
+        assert_eq!(responses.iter().filter(|ticket| ticket.try_wait().is_some()).count(), 3);
+        assert!(matches!(RateLimit::new(0, 10), Err(PqcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_waiting_jobs_age_upwards() {
+        let (manager, ids) = manager_with_keys(1);
+        let queue = SigningQueue::new(manager).with_batch_size(1).with_aging(Duration::from_secs(5));
+        let start = Instant::now();
+        let low = queue.submit(ids[0], b"archive", Priority::Low).unwrap();
+        let highs: Vec<_> = (0..3).map(|i| queue.submit(ids[0], &[i], Priority::High).unwrap()).collect();
+
+        assert_eq!(queue.run_batch_at(start), 1);
+        assert!(low.try_wait().is_none());
+        // Low reaches Normal after 5 s and High after 10 s, behind the High jobs already queued
+        assert_eq!(queue.run_batch_at(start + Duration::from_secs(5)), 1);
+        assert_eq!(queue.run_batch_at(start + Duration::from_secs(10)), 1);
+        assert!(low.try_wait().is_none());
+        assert_eq!(queue.run_batch_at(start + Duration::from_secs(10)), 1);
+        assert!(low.try_wait().unwrap().is_ok());
+        assert!(highs.iter().all(|ticket| ticket.try_wait().is_some()));
+    }
+
+    #[test]
+    fn test_worker_and_backpressure() {
+        let (manager, ids) = manager_with_keys(1);
+        let queue = Arc::new(SigningQueue::new(manager).with_max_pending(1));
+        let first = queue.submit(ids[0], b"one", Priority::Normal).unwrap();
+        assert!(matches!(queue.submit(ids[0], b"two", Priority::Normal), Err(PqcError::Overloaded(_))));
+
+        let token = CancelToken::new();
+        let worker = {
+            let (queue, token) = (Arc::clone(&queue), token.clone());
+            std::thread::spawn(move || queue.run(&token))
+        };
+        assert!(first.wait().is_ok());
+        assert!(queue.submit(ids[0], b"two", Priority::Normal).unwrap().wait().is_ok());
+        token.cancel();
+        worker.join().unwrap();
+
+        let orphan = queue.submit(ids[0], b"three", Priority::Normal).unwrap();
+        drop(Arc::try_unwrap(queue).ok().unwrap());
+        assert!(matches!(orphan.wait(), Err(PqcError::Cancelled(_))));
+    }
+}
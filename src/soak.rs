@@ -0,0 +1,335 @@
+use pqc_algo::entropy_source::EntropySource;
+use pqc_algo::fleet_report::LatencySummary;
+use pqc_algo::kem;
+use pqc_algo::qrng::QRNG;
+use pqc_algo::selftest::rng_health_check;
+use pqc_algo::sign;
+use pqc_algo::suite::{CipherSuite, SecurityLevel};
+use pqc_algo::util::ct;
+use rand::RngCore;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Bytes drawn from the source under test per health check
+const RNG_SAMPLE_LEN: usize = 4096;
+
+/// The entropy source being qualified
+enum Source {
+    /// The operating system RNG, when no seeds or device are given
+    System,
+    /// The local seed pool built from a Kyber and a Dilithium seed file
+    Seeds(PathBuf, PathBuf),
+    #[cfg(feature = "idquantique")]
+    Quantis(PathBuf),
+    #[cfg(feature = "comscire")]
+    ComScire(PathBuf),
+}
+
+impl Source {
+    fn open(&self) -> pqc_algo::error::Result<Box<dyn EntropySource>> {
+        Ok(match self {
+            Source::System => Box::new(SystemRng),
+            Source::Seeds(kyber, dilithium) => Box::new(QRNG::new(kyber, dilithium)?),
+            #[cfg(feature = "idquantique")]
+            Source::Quantis(path) => Box::new(pqc_algo::entropy_source::vendors::Quantis::open(path)?),
+            #[cfg(feature = "comscire")]
+            Source::ComScire(path) => Box::new(pqc_algo::entropy_source::vendors::ComScire::open(path)?),
+        })
+    }
+}
+
+struct SystemRng;
+
+impl EntropySource for SystemRng {
+    fn name(&self) -> &str {
+        "system-rng"
+    }
+
+    fn generate(&mut self, size: usize) -> pqc_algo::error::Result<Vec<u8>> {
+        let mut bytes = vec![0u8; size];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        Ok(bytes)
+    }
+}
+
+struct Options {
+    source: Source,
+    duration: Duration,
+    interval: Duration,
+    suite: CipherSuite,
+    max_rss_growth_kib: u64,
+    json: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            source: Source::System,
+            duration: Duration::from_secs(3600),
+            interval: Duration::from_secs(60),
+            suite: CipherSuite::active_default(),
+            max_rss_growth_kib: 64 * 1024,
+            json: false,
+        }
+    }
+}
+
+/// Parse `90`, `90s`, `15m` or `4h`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| format!("Invalid duration: {}", value))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return Err(format!("Invalid duration unit in {} (use s, m or h)", value)),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--duration" => options.duration = parse_duration(&value()?)?,
+            "--interval" => options.interval = parse_duration(&value()?)?,
+            "--level" => options.suite = value()?.parse::<SecurityLevel>().map_err(|e| e.to_string())?.suite(),
+            "--kem" => options.suite.kem = value()?.parse().map_err(|e: pqc_algo::error::PqcError| e.to_string())?,
+            "--sig" => options.suite.sig = value()?.parse().map_err(|e: pqc_algo::error::PqcError| e.to_string())?,
+            "--max-rss-growth-mib" => {
+                let mib: u64 = value()?.parse().map_err(|_| "--max-rss-growth-mib needs a number".to_string())?;
+                options.max_rss_growth_kib = mib * 1024;
+            }
+            "--seeds" => options.source = Source::Seeds(value()?.into(), value()?.into()),
+            #[cfg(feature = "idquantique")]
+            "--quantis" => options.source = Source::Quantis(value()?.into()),
+            #[cfg(feature = "comscire")]
+            "--comscire" => options.source = Source::ComScire(value()?.into()),
+            "--json" => options.json = true,
+            "-h" | "--help" => {
+                println!("Usage: soak [--duration 4h] [--interval 60s] [--level L3 | --kem ALG --sig ALG]");
+                println!("            [--seeds KYBER DILITHIUM | --quantis DEVICE | --comscire DEVICE]");
+                println!("            [--max-rss-growth-mib 64] [--json]");
+                println!("Loops keygen, encapsulate and sign with health checks on the entropy source until");
+                println!("the duration ends. Without --seeds or a device the system RNG is checked.");
+                println!("Exits non-zero on any failure or if resident memory grows past the limit.");
+                std::process::exit(0);
+            }
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Counts and latencies for one operation
+#[derive(Default)]
+struct OpStats {
+    count: u64,
+    failures: u64,
+    last_error: Option<String>,
+    /// Latencies since the last progress line, so memory stays flat
+    window: Vec<Duration>,
+    worst_p95_us: u64,
+    max_us: u64,
+}
+
+impl OpStats {
+    fn record<T>(&mut self, op: impl FnOnce() -> Result<T, String>) -> Option<T> {
+        let start = Instant::now();
+        let result = op();
+        self.window.push(start.elapsed());
+        self.count += 1;
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.failures += 1;
+                self.last_error = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Summarize and clear the current window
+    fn roll(&mut self) -> LatencySummary {
+        let summary = LatencySummary::from_latencies(&self.window);
+        self.worst_p95_us = self.worst_p95_us.max(summary.p95_us);
+        self.max_us = self.max_us.max(summary.max_us);
+        self.window.clear();
+        summary
+    }
+}
+
+#[derive(Serialize)]
+struct OpReport {
+    name: &'static str,
+    count: u64,
+    failures: u64,
+    worst_p95_us: u64,
+    max_us: u64,
+    last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SoakReport {
+    source: String,
+    kem: String,
+    sig: String,
+    elapsed_secs: u64,
+    iterations: u64,
+    operations: Vec<OpReport>,
+    /// Resident memory after the first interval and at the end, in KiB
+    rss_baseline_kib: Option<u64>,
+    rss_final_kib: Option<u64>,
+    rss_growth_kib: Option<u64>,
+    max_rss_growth_kib: u64,
+    passed: bool,
+}
+
+impl fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Soak {} after {}s and {} iterations ({} / {}, entropy from {})",
+            if self.passed { "PASSED" } else { "FAILED" }, self.elapsed_secs, self.iterations, self.kem, self.sig, self.source)?;
+        for op in &self.operations {
+            writeln!(f, "   - {:<12} {:>9} runs, {} failed, worst p95 {} us, max {} us",
+                op.name, op.count, op.failures, op.worst_p95_us, op.max_us)?;
+            if let Some(error) = &op.last_error {
+                writeln!(f, "     last error: {}", error)?;
+            }
+        }
+        match self.rss_growth_kib {
+            Some(growth) => write!(f, "   - RSS growth: {} KiB (limit {} KiB)", growth, self.max_rss_growth_kib),
+            None => write!(f, "   - RSS growth: not measured on this platform"),
+        }
+    }
+}
+
+/// Resident set size from /proc; `None` where that isn't available
+fn rss_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+    if let Err(e) = pqc_algo::backend::init() {
+        eprintln!("Failed to initialize liboqs: {}", e);
+        return ExitCode::FAILURE;
+    }
+    let mut source = match options.source.open() {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to open the entropy source: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let (kem_alg, sig_alg) = (options.suite.kem, options.suite.sig);
+    eprintln!("Soaking {} and {} with entropy from {} for {:?}", kem_alg, sig_alg, source.name(), options.duration);
+
+    let names = ["rng-health", "kem-keygen", "encapsulate", "decapsulate", "sig-keygen", "sign", "verify"];
+    let mut stats: Vec<OpStats> = names.iter().map(|_| OpStats::default()).collect();
+    let start = Instant::now();
+    let mut next_report = start + options.interval;
+    let mut iterations = 0u64;
+    let mut rss_baseline = None;
+    let message = b"pqc-algo soak test message";
+
+    while start.elapsed() < options.duration {
+        iterations += 1;
+        stats[0].record(|| {
+            let sample = source.generate(RNG_SAMPLE_LEN).map_err(|e| e.to_string())?;
+            rng_health_check(&sample)
+        });
+
+        let keypair = stats[1].record(|| kem::generate_keypair_with(kem_alg).map_err(|e| e.to_string()));
+        if let Some(keypair) = keypair {
+            let encapsulated = stats[2].record(|| kem::encapsulate_with(kem_alg, &keypair.public_key).map_err(|e| e.to_string()));
+            if let Some((ciphertext, shared_secret)) = encapsulated {
+                stats[3].record(|| {
+                    let decapsulated = kem::decapsulate_with(kem_alg, &keypair.secret_key, &ciphertext).map_err(|e| e.to_string())?;
+                    if !ct::ct_eq(&decapsulated, &shared_secret) {
+                        return Err("decapsulated shared secret does not match".to_string());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        let keypair = stats[4].record(|| sign::generate_keypair_with(sig_alg).map_err(|e| e.to_string()));
+        if let Some(keypair) = keypair {
+            let signature = stats[5].record(|| sign::sign_with(sig_alg, message, &keypair.secret_key).map_err(|e| e.to_string()));
+            if let Some(signature) = signature {
+                stats[6].record(|| match sign::verify_with(sig_alg, message, &signature, &keypair.public_key) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err("fresh signature did not verify".to_string()),
+                    Err(e) => Err(e.to_string()),
+                });
+            }
+        }
+
+        if Instant::now() >= next_report {
+            next_report += options.interval;
+            // Measure growth from after warm-up, not from process start
+            let rss = rss_kib();
+            rss_baseline = rss_baseline.or(rss);
+            let failures: u64 = stats.iter().map(|s| s.failures).sum();
+            let p95: Vec<String> = names.iter().zip(stats.iter_mut())
+                .skip(1)
+                .map(|(name, s)| format!("{}={}", name, s.roll().p95_us))
+                .collect();
+            stats[0].roll();
+            eprintln!("[{:>6}s] {} iterations, {} failures, rss {} KiB, p95 us: {}",
+                start.elapsed().as_secs(), iterations, failures,
+                rss.map_or("?".to_string(), |kib| kib.to_string()), p95.join(" "));
+        }
+    }
+
+    let rss_final = rss_kib();
+    let rss_growth = rss_baseline.zip(rss_final).map(|(baseline, now)| now.saturating_sub(baseline));
+    let operations: Vec<OpReport> = names.iter().zip(stats.iter_mut())
+        .map(|(&name, s)| {
+            s.roll();
+            OpReport { name, count: s.count, failures: s.failures, worst_p95_us: s.worst_p95_us, max_us: s.max_us, last_error: s.last_error.clone() }
+        })
+        .collect();
+    let passed = operations.iter().all(|op| op.failures == 0)
+        && rss_growth.is_none_or(|growth| growth <= options.max_rss_growth_kib);
+    let report = SoakReport {
+        source: source.name().to_string(),
+        kem: kem_alg.to_string(),
+        sig: sig_alg.to_string(),
+        elapsed_secs: start.elapsed().as_secs(),
+        iterations,
+        operations,
+        rss_baseline_kib: rss_baseline,
+        rss_final_kib: rss_final,
+        rss_growth_kib: rss_growth,
+        max_rss_growth_kib: options.max_rss_growth_kib,
+        passed,
+    };
+
+    if options.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Failed to encode report: {}", e),
+        }
+    } else {
+        println!("{}", report);
+    }
+    if passed { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
@@ -0,0 +1,394 @@
+//! Online certificate status, a lightweight alternative to shipping CRLs
+//!
+//! A constrained verifier asks "is this key still good?" with a
+//! [`StatusRequest`] and gets back a [`StatusResponse`] signed by the
+//! [`StatusResponder`] the entropy service runs. A response names one
+//! fingerprint, its [`CertStatus`], and a validity window. Responses to
+//! requests without a nonce carry none and can be cached until
+//! `next_update`, which lets a gateway answer for its devices with a
+//! [`StatusCache`]. A request with a nonce gets a fresh response that only
+//! that request accepts.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::envelope::{key_fingerprint, unix_now};
+use crate::error::{PqcError, Result};
+use crate::lifecycle::{self, LifecycleEvent, LifecycleEventKind};
+use crate::secret::SecretKey;
+use crate::sign::{self, SigAlgorithm};
+use crate::util::ct;
+use crate::validation::{self, Validate};
+use crate::clock::{self, ValidityOptions};
+use crate::{canonical, fips};
+
+/// Domain label for status response signatures
+const STATUS_DOMAIN: &str = "PQC-Algo/v1/status-response";
+
+/// How long responses stay valid unless the responder is configured otherwise
+pub const DEFAULT_VALIDITY: Duration = Duration::from_secs(3600);
+
+/// Status of one key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CertStatus {
+    Good,
+    Revoked { revoked_at: u64, reason: String },
+    /// The responder has never issued this key
+    Unknown,
+}
+
+/// Verifier → responder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusRequest {
+    /// Hex SHA-256 of the public key being checked
+    pub fingerprint: String,
+    /// Random bytes for a fresh response; empty for a cacheable one
+    #[serde(default, with = "crate::util::bytes")]
+    pub nonce: Vec<u8>,
+}
+
+impl StatusRequest {
+    pub fn new(public_key: &[u8]) -> Self {
+        Self { fingerprint: key_fingerprint(public_key), nonce: Vec::new() }
+    }
+
+    pub fn with_nonce(mut self, nonce: &[u8]) -> Self {
+        self.nonce = nonce.to_vec();
+        self
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode status request: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "status request")
+    }
+}
+
+impl Validate for StatusRequest {
+    fn validate(&self) -> Result<()> {
+        validation::check_fingerprint("Status request fingerprint", &self.fingerprint)?;
+        validation::check_max_len("Status request nonce", self.nonce.len(), validation::MAX_NONCE_LEN)
+    }
+}
+
+/// Responder → verifier
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub fingerprint: String,
+    pub status: CertStatus,
+    pub this_update: u64,
+    pub next_update: u64,
+    #[serde(with = "crate::util::bytes")]
+    pub nonce: Vec<u8>,
+    pub responder_fingerprint: String,
+    pub algorithm: SigAlgorithm,
+    #[serde(with = "crate::util::bytes")]
+    pub signature: Vec<u8>,
+}
+
+/// Fields of a [`StatusResponse`] covered by its signature
+#[derive(Serialize)]
+struct StatusFields<'a> {
+    fingerprint: &'a str,
+    status: &'a CertStatus,
+    this_update: u64,
+    next_update: u64,
+    nonce: String,
+    responder_fingerprint: &'a str,
+    algorithm: &'a str,
+}
+
+impl StatusResponse {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode status response: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        validation::from_json(bytes, "status response")
+    }
+
+    /// Check the response answers `request`, is signed by the responder and is current
+    pub fn verify(&self, request: &StatusRequest, responder_public_key: &[u8]) -> Result<&CertStatus> {
+        self.verify_at(request, responder_public_key, unix_now(), &clock::validity())
+    }
+
+    /// [`StatusResponse::verify`] at an explicit time and tolerance
+    pub fn verify_at(&self, request: &StatusRequest, responder_public_key: &[u8], now: u64, validity: &ValidityOptions) -> Result<&CertStatus> {
+        if !ct::ct_eq_hex(&self.fingerprint, &request.fingerprint) || !ct::ct_eq(&self.nonce, &request.nonce) {
+            return Err(PqcError::Verification("Status response does not answer this request".to_string()));
+        }
+        self.verify_signature(responder_public_key)?;
+        if !validity.contains(self.this_update, self.next_update, now) {
+            return Err(PqcError::Verification("Status response is outside its validity window".to_string()));
+        }
+        Ok(&self.status)
+    }
+
+    fn verify_signature(&self, responder_public_key: &[u8]) -> Result<()> {
+        fips::check_sig(self.algorithm)?;
+        if !ct::ct_eq_hex(&self.responder_fingerprint, &key_fingerprint(responder_public_key))
+            || !sign::verify_with(self.algorithm, &self.signing_bytes()?, &self.signature, responder_public_key)?
+        {
+            return Err(PqcError::Verification("Status response was not signed by the responder".to_string()));
+        }
+        Ok(())
+    }
+
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        canonical::signing_bytes(STATUS_DOMAIN, &StatusFields {
+            fingerprint: &self.fingerprint,
+            status: &self.status,
+            this_update: self.this_update,
+            next_update: self.next_update,
+            nonce: hex::encode(&self.nonce),
+            responder_fingerprint: &self.responder_fingerprint,
+            algorithm: self.algorithm.name(),
+        })
+    }
+}
+
+impl Validate for StatusResponse {
+    fn validate(&self) -> Result<()> {
+        validation::check_fingerprint("Status response fingerprint", &self.fingerprint)?;
+        if let CertStatus::Revoked { reason, .. } = &self.status {
+            validation::check_max_len("Revocation reason", reason.len(), validation::MAX_LABEL_LEN)?;
+        }
+        validation::check_max_len("Status response nonce", self.nonce.len(), validation::MAX_NONCE_LEN)?;
+        validation::check_fingerprint("Responder fingerprint", &self.responder_fingerprint)?;
+        validation::check_signature_len("Status response signature", self.algorithm, self.signature.len())
+    }
+}
+
+/// Answers status requests for the keys an authority has issued
+pub struct StatusResponder {
+    algorithm: SigAlgorithm,
+    public_key: Vec<u8>,
+    secret_key: SecretKey,
+    validity: Duration,
+    issued: HashSet<String>,
+    revoked: HashMap<String, (u64, String)>,
+}
+
+impl StatusResponder {
+    pub fn new(algorithm: SigAlgorithm, public_key: Vec<u8>, secret_key: SecretKey) -> Self {
+        Self {
+            algorithm,
+            public_key,
+            secret_key,
+            validity: DEFAULT_VALIDITY,
+            issued: HashSet::new(),
+            revoked: HashMap::new(),
+        }
+    }
+
+    /// How long each response may be relied on and cached
+    pub fn with_validity(mut self, validity: Duration) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// Record an issued key so it reports as good
+    pub fn add_issued(&mut self, public_key: &[u8]) {
+        self.issued.insert(key_fingerprint(public_key));
+    }
+
+    /// Revoke a key by fingerprint; it reports as revoked from now on
+    pub fn revoke(&mut self, fingerprint: &str, reason: &str) {
+        self.issued.insert(fingerprint.to_string());
+        if self.revoked.contains_key(fingerprint) {
+            return;
+        }
+        self.revoked.insert(fingerprint.to_string(), (unix_now(), reason.to_string()));
+        lifecycle::emit(LifecycleEvent::new(LifecycleEventKind::Revoked, fingerprint).with_detail(reason));
+    }
+
+    pub fn status(&self, fingerprint: &str) -> CertStatus {
+        match self.revoked.get(fingerprint) {
+            Some((revoked_at, reason)) => CertStatus::Revoked { revoked_at: *revoked_at, reason: reason.clone() },
+            None if self.issued.contains(fingerprint) => CertStatus::Good,
+            None => CertStatus::Unknown,
+        }
+    }
+
+    pub fn respond(&self, request: &StatusRequest) -> Result<StatusResponse> {
+        self.respond_at(request, unix_now())
+    }
+
+    /// [`StatusResponder::respond`] at an explicit time
+    pub fn respond_at(&self, request: &StatusRequest, now: u64) -> Result<StatusResponse> {
+        fips::check_sig(self.algorithm)?;
+        let mut response = StatusResponse {
+            fingerprint: request.fingerprint.clone(),
+            status: self.status(&request.fingerprint),
+            this_update: now,
+            next_update: now.saturating_add(self.validity.as_secs()),
+            nonce: request.nonce.clone(),
+            responder_fingerprint: key_fingerprint(&self.public_key),
+            algorithm: self.algorithm,
+            signature: Vec::new(),
+        };
+        response.signature = sign::sign_with(self.algorithm, &response.signing_bytes()?, &self.secret_key)?;
+        Ok(response)
+    }
+}
+
+/// Verified responses a gateway can hand out until they expire
+pub struct StatusCache {
+    responder_public_key: Vec<u8>,
+    entries: Mutex<HashMap<String, StatusResponse>>,
+}
+
+impl StatusCache {
+    pub fn new(responder_public_key: &[u8]) -> Self {
+        Self { responder_public_key: responder_public_key.to_vec(), entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Verify and store a cacheable (nonce-free) response
+    ///
+    /// A cached `Revoked` is never replaced by another status, and otherwise
+    /// the response with the newest `this_update` is kept; an older one is
+    /// verified and then ignored.
+    pub fn insert(&self, response: StatusResponse) -> Result<()> {
+        if !response.nonce.is_empty() {
+            return Err(PqcError::InvalidInput("Responses to nonce requests cannot be cached".to_string()));
+        }
+        response.verify_signature(&self.responder_public_key)?;
+        let rank = |response: &StatusResponse| (matches!(response.status, CertStatus::Revoked { .. }), response.this_update);
+        let mut entries = self.lock();
+        if entries.get(&response.fingerprint).is_none_or(|cached| rank(&response) >= rank(cached)) {
+            entries.insert(response.fingerprint.clone(), response);
+        }
+        Ok(())
+    }
+
+    /// A cached response still valid now, if any
+    pub fn get(&self, fingerprint: &str) -> Option<StatusResponse> {
+        self.get_at(fingerprint, unix_now(), &clock::validity())
+    }
+
+    /// [`StatusCache::get`] at an explicit time and tolerance
+    ///
+    /// Expired entries are dropped, except revocations, which stay so an
+    /// older `Good` cannot take their place.
+    pub fn get_at(&self, fingerprint: &str, now: u64, validity: &ValidityOptions) -> Option<StatusResponse> {
+        let mut entries = self.lock();
+        match entries.get(fingerprint) {
+            Some(response) if validity.is_expired(response.next_update, now) => {
+                if !matches!(response.status, CertStatus::Revoked { .. }) {
+                    entries.remove(fingerprint);
+                }
+                None
+            }
+            Some(response) if !validity.is_premature(response.this_update, now) => Some(response.clone()),
+            _ => None,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, StatusResponse>> {
+        self.entries.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn responder() -> (StatusResponder, Vec<u8>) {
+        let keypair = sign::generate_keypair().unwrap();
+        let public_key = keypair.public_key.clone();
+        (StatusResponder::new(SigAlgorithm::Dilithium3, keypair.public_key, keypair.secret_key.into()), public_key)
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        let (mut responder, responder_key) = responder();
+        let good = sign::generate_keypair().unwrap();
+        let lost = sign::generate_keypair().unwrap();
+        responder.add_issued(&good.public_key);
+        responder.add_issued(&lost.public_key);
+        responder.revoke(&key_fingerprint(&lost.public_key), "device reported stolen");
+
+        let request = StatusRequest::new(&good.public_key).with_nonce(b"n1");
+        assert_eq!(responder.respond(&request).unwrap().verify(&request, &responder_key).unwrap(), &CertStatus::Good);
+
+        let request = StatusRequest::new(&lost.public_key);
+        let status = responder.respond(&request).unwrap().verify(&request, &responder_key).unwrap().clone();
+        assert!(matches!(status, CertStatus::Revoked { reason, .. } if reason == "device reported stolen"));
+
+        let request = StatusRequest::new(b"never issued");
+        assert_eq!(responder.respond(&request).unwrap().verify(&request, &responder_key).unwrap(), &CertStatus::Unknown);
+    }
+
+    #[test]
+    fn test_status_rejects_replay_and_forgery() {
+        let (mut responder, responder_key) = responder();
+        let device = sign::generate_keypair().unwrap();
+        responder.add_issued(&device.public_key);
+
+        let first = StatusRequest::new(&device.public_key).with_nonce(b"n1");
+        let response = responder.respond_at(&first, 1000).unwrap();
+        let second = StatusRequest::new(&device.public_key).with_nonce(b"n2");
+        assert!(response.verify_at(&second, &responder_key, 1000, &ValidityOptions::STRICT).is_err());
+        assert!(response.verify_at(&first, &responder_key, 1000 + DEFAULT_VALIDITY.as_secs() + 1, &ValidityOptions::STRICT).is_err());
+
+        // A verifier that opts into clock skew accepts it just past the window
+        let skew = ValidityOptions::TOLERANT.max_skew.as_secs();
+        assert!(response.verify_at(&first, &responder_key, 1000 + DEFAULT_VALIDITY.as_secs() + skew, &ValidityOptions::TOLERANT).is_ok());
+
+        assert_eq!(StatusResponse::from_bytes(&response.to_bytes().unwrap()).unwrap(), response);
+        let mut oversized = response.clone();
+        oversized.nonce = vec![0u8; validation::MAX_NONCE_LEN + 1];
+        assert!(matches!(StatusResponse::from_bytes(&oversized.to_bytes().unwrap()), Err(PqcError::InvalidInput(_))));
+
+        let mut forged = response.clone();
+        forged.status = CertStatus::Unknown;
+        assert!(matches!(forged.verify_at(&first, &responder_key, 1000, &ValidityOptions::STRICT), Err(PqcError::Verification(_))));
+    }
+
+    #[test]
+    fn test_gateway_cache() {
+        let (mut responder, responder_key) = responder();
+        let device = sign::generate_keypair().unwrap();
+        responder.add_issued(&device.public_key);
+        let cache = StatusCache::new(&responder_key);
+        let fingerprint = key_fingerprint(&device.public_key);
+
+        let request = StatusRequest::new(&device.public_key);
+        cache.insert(responder.respond_at(&request, 1000).unwrap()).unwrap();
+        assert_eq!(cache.get_at(&fingerprint, 1500, &ValidityOptions::STRICT).unwrap().status, CertStatus::Good);
+        assert!(cache.get_at(&fingerprint, 1000 + DEFAULT_VALIDITY.as_secs() + 1, &ValidityOptions::STRICT).is_none());
+
+        assert!(cache.insert(responder.respond(&request.clone().with_nonce(b"n")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_cache_keeps_newest_and_never_unrevokes() {
+        let (mut responder, responder_key) = responder();
+        let device = sign::generate_keypair().unwrap();
+        responder.add_issued(&device.public_key);
+        let cache = StatusCache::new(&responder_key);
+        let fingerprint = key_fingerprint(&device.public_key);
+        let request = StatusRequest::new(&device.public_key);
+
+        let old_good = responder.respond_at(&request, 1000).unwrap();
+        let good_after_expiry = responder.respond_at(&request, 1800).unwrap();
+        cache.insert(responder.respond_at(&request, 2000).unwrap()).unwrap();
+        cache.insert(old_good.clone()).unwrap();
+        assert_eq!(cache.get_at(&fingerprint, 2100, &ValidityOptions::STRICT).unwrap().this_update, 2000);
+
+        responder.revoke(&fingerprint, "key compromised");
+        cache.insert(responder.respond_at(&request, 1500).unwrap()).unwrap();
+        cache.insert(old_good).unwrap();
+        assert!(matches!(cache.get_at(&fingerprint, 2100, &ValidityOptions::STRICT).unwrap().status, CertStatus::Revoked { .. }));
+
+        assert!(cache.get_at(&fingerprint, 1500 + DEFAULT_VALIDITY.as_secs() + 1, &ValidityOptions::STRICT).is_none());
+        cache.insert(good_after_expiry).unwrap();
+        assert!(matches!(cache.get_at(&fingerprint, 2100, &ValidityOptions::STRICT).unwrap().status, CertStatus::Revoked { .. }));
+    }
+}
@@ -0,0 +1,360 @@
+//! Security levels and the algorithm suites they select
+//!
+//! Integrators pick a [`SecurityLevel`] and get a consistent
+//! (KEM, signature, AEAD, hash) [`CipherSuite`] for it, instead of choosing
+//! each primitive separately.
+
+use std::fmt;
+use std::str::FromStr;
+use aes_gcm::aead::{Aead, AeadInPlace, KeyInit, Payload};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce, Tag};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::error::{PqcError, Result};
+use crate::hash::HashAlgorithm;
+use crate::kem::KemAlgorithm;
+use crate::sign::SigAlgorithm;
+
+pub(crate) const NONCE_LEN: usize = 12;
+
+/// Length of the authentication tag appended by every AEAD here
+pub(crate) const TAG_LEN: usize = 16;
+
+/// Bytes [`AeadAlgorithm::seal`] adds to a plaintext: the nonce and the tag
+pub const SEAL_OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+/// Authenticated encryption used for payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AeadAlgorithm {
+    Aes128Gcm,
+    Aes256Gcm,
+}
+
+impl AeadAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AeadAlgorithm::Aes128Gcm => "AES-128-GCM",
+            AeadAlgorithm::Aes256Gcm => "AES-256-GCM",
+        }
+    }
+
+    pub fn key_len(&self) -> usize {
+        match self {
+            AeadAlgorithm::Aes128Gcm => 16,
+            AeadAlgorithm::Aes256Gcm => 32,
+        }
+    }
+
+    /// Encrypt with a random nonce; output is `nonce || ciphertext || tag`
+    pub fn seal(&self, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        self.seal_with_aad(key, plaintext, &[])
+    }
+
+    /// [`AeadAlgorithm::seal`], also authenticating `aad` without encrypting it
+    pub fn seal_with_aad(&self, key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill(&mut nonce);
+        let mut result = nonce.to_vec();
+        result.extend_from_slice(&self.encrypt(key, &nonce, Payload { msg: plaintext, aad })?);
+        Ok(result)
+    }
+
+    /// Decrypt output of [`AeadAlgorithm::seal`]
+    pub fn open(&self, key: &[u8], ciphertext_with_nonce: &[u8]) -> Result<Vec<u8>> {
+        self.open_with_aad(key, ciphertext_with_nonce, &[])
+    }
+
+    /// Decrypt output of [`AeadAlgorithm::seal_with_aad`]; `aad` must match
+    pub fn open_with_aad(&self, key: &[u8], ciphertext_with_nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext_with_nonce.len() < NONCE_LEN {
+            return Err(PqcError::Decryption("Invalid ciphertext".to_string()));
+        }
+        let (nonce_bytes, ciphertext) = ciphertext_with_nonce.split_at(NONCE_LEN);
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(nonce_bytes);
+        self.decrypt(key, &nonce, Payload { msg: ciphertext, aad })
+    }
+
+    /// [`AeadAlgorithm::seal_with_aad`] into `out` without allocating
+    ///
+    /// `out` needs `plaintext.len() + SEAL_OVERHEAD` bytes. Returns the
+    /// number written; the output opens with [`AeadAlgorithm::open_with_aad`].
+    pub fn seal_into(&self, key: &[u8], plaintext: &[u8], aad: &[u8], out: &mut [u8]) -> Result<usize> {
+        let len = plaintext.len() + SEAL_OVERHEAD;
+        if out.len() < len {
+            return Err(PqcError::Encryption(format!("Output buffer is {} bytes, need {}", out.len(), len)));
+        }
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill(&mut nonce);
+        let (head, rest) = out[..len].split_at_mut(NONCE_LEN);
+        let (body, tag) = rest.split_at_mut(plaintext.len());
+        head.copy_from_slice(&nonce);
+        body.copy_from_slice(plaintext);
+        tag.copy_from_slice(&self.encrypt_in_place(key, &nonce, aad, body)?);
+        Ok(len)
+    }
+
+    /// [`AeadAlgorithm::open_with_aad`] into `out` without allocating
+    ///
+    /// `out` needs `sealed.len() - SEAL_OVERHEAD` bytes. Returns the
+    /// plaintext length.
+    pub fn open_into(&self, key: &[u8], sealed: &[u8], aad: &[u8], out: &mut [u8]) -> Result<usize> {
+        let len = sealed.len().checked_sub(SEAL_OVERHEAD)
+            .ok_or_else(|| PqcError::Decryption("Invalid ciphertext".to_string()))?;
+        if out.len() < len {
+            return Err(PqcError::Decryption(format!("Output buffer is {} bytes, need {}", out.len(), len)));
+        }
+        let (nonce_bytes, rest) = sealed.split_at(NONCE_LEN);
+        let (body, tag) = rest.split_at(len);
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(nonce_bytes);
+        out[..len].copy_from_slice(body);
+        self.decrypt_in_place(key, &nonce, aad, &mut out[..len], tag)?;
+        Ok(len)
+    }
+
+    /// Encrypt under a caller-managed nonce; output is `ciphertext || tag`
+    ///
+    /// Never reuse a nonce with the same key.
+    pub(crate) fn seal_with_nonce(&self, key: &[u8], nonce: &[u8; NONCE_LEN], plaintext: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt(key, nonce, Payload::from(plaintext))
+    }
+
+    /// Decrypt output of [`AeadAlgorithm::seal_with_nonce`]
+    pub(crate) fn open_with_nonce(&self, key: &[u8], nonce: &[u8; NONCE_LEN], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt(key, nonce, Payload::from(ciphertext))
+    }
+
+    fn encrypt(&self, key: &[u8], nonce: &[u8; NONCE_LEN], payload: Payload<'_, '_>) -> Result<Vec<u8>> {
+        let key = self.check_key(key).map_err(PqcError::Encryption)?;
+        let nonce = &Nonce::from(*nonce);
+        match self {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Encryption(format!("{}", e)))?
+                .encrypt(nonce, payload),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Encryption(format!("{}", e)))?
+                .encrypt(nonce, payload),
+        }
+        .map_err(|e| PqcError::Encryption(format!("{}", e)))
+    }
+
+    fn decrypt(&self, key: &[u8], nonce: &[u8; NONCE_LEN], payload: Payload<'_, '_>) -> Result<Vec<u8>> {
+        let key = self.check_key(key).map_err(PqcError::Decryption)?;
+        let nonce = &Nonce::from(*nonce);
+        match self {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Decryption(format!("{}", e)))?
+                .decrypt(nonce, payload),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Decryption(format!("{}", e)))?
+                .decrypt(nonce, payload),
+        }
+        .map_err(|e| PqcError::Decryption(format!("{}", e)))
+    }
+
+    fn encrypt_in_place(&self, key: &[u8], nonce: &[u8; NONCE_LEN], aad: &[u8], buffer: &mut [u8]) -> Result<[u8; TAG_LEN]> {
+        let key = self.check_key(key).map_err(PqcError::Encryption)?;
+        let nonce = &Nonce::from(*nonce);
+        let tag = match self {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Encryption(format!("{}", e)))?
+                .encrypt_in_place_detached(nonce, aad, buffer),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Encryption(format!("{}", e)))?
+                .encrypt_in_place_detached(nonce, aad, buffer),
+        }
+        .map_err(|e| PqcError::Encryption(format!("{}", e)))?;
+        let mut out = [0u8; TAG_LEN];
+        out.copy_from_slice(&tag);
+        Ok(out)
+    }
+
+    fn decrypt_in_place(&self, key: &[u8], nonce: &[u8; NONCE_LEN], aad: &[u8], buffer: &mut [u8], tag: &[u8]) -> Result<()> {
+        let key = self.check_key(key).map_err(PqcError::Decryption)?;
+        let nonce = &Nonce::from(*nonce);
+        let tag = &Tag::from(<[u8; TAG_LEN]>::try_from(tag)
+            .map_err(|_| PqcError::Decryption("Invalid tag length".to_string()))?);
+        match self {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Decryption(format!("{}", e)))?
+                .decrypt_in_place_detached(nonce, aad, buffer, tag),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| PqcError::Decryption(format!("{}", e)))?
+                .decrypt_in_place_detached(nonce, aad, buffer, tag),
+        }
+        .map_err(|e| PqcError::Decryption(format!("{}", e)))
+    }
+
+    /// The first `key_len` bytes of `key`, which must be at least that long
+    fn check_key<'a>(&self, key: &'a [u8]) -> std::result::Result<&'a [u8], String> {
+        key.get(..self.key_len())
+            .ok_or_else(|| format!("{} needs a {} byte key", self.name(), self.key_len()))
+    }
+}
+
+/// A consistent set of algorithms used together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CipherSuite {
+    pub kem: KemAlgorithm,
+    pub sig: SigAlgorithm,
+    pub aead: AeadAlgorithm,
+    pub hash: HashAlgorithm,
+}
+
+impl CipherSuite {
+    /// The suite used by the functions that take no suite argument
+    pub const DEFAULT: Self = Self {
+        kem: KemAlgorithm::Kyber1024,
+        sig: SigAlgorithm::Dilithium3,
+        aead: AeadAlgorithm::Aes256Gcm,
+        hash: HashAlgorithm::Sha256,
+    };
+
+    /// FIPS-approved counterpart of [`CipherSuite::DEFAULT`]
+    pub const FIPS_DEFAULT: Self = Self {
+        kem: KemAlgorithm::MlKem1024,
+        sig: SigAlgorithm::MlDsa65,
+        aead: AeadAlgorithm::Aes256Gcm,
+        hash: HashAlgorithm::Sha256,
+    };
+
+    pub fn for_level(level: SecurityLevel) -> Self {
+        level.suite()
+    }
+
+    /// [`CipherSuite::FIPS_DEFAULT`] in FIPS mode, otherwise [`CipherSuite::DEFAULT`]
+    pub fn active_default() -> Self {
+        if crate::fips::fips_mode() {
+            Self::FIPS_DEFAULT
+        } else {
+            Self::DEFAULT
+        }
+    }
+}
+
+impl Default for CipherSuite {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}+{}+{}", self.kem, self.sig, self.aead.name(), self.hash)
+    }
+}
+
+/// NIST post-quantum security categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SecurityLevel {
+    /// Category 1, comparable to AES-128
+    L1,
+    /// Category 3, comparable to AES-192
+    L3,
+    /// Category 5, comparable to AES-256
+    L5,
+}
+
+impl SecurityLevel {
+    pub fn nist_category(&self) -> u8 {
+        match self {
+            SecurityLevel::L1 => 1,
+            SecurityLevel::L3 => 3,
+            SecurityLevel::L5 => 5,
+        }
+    }
+
+    /// Algorithms selected for this level
+    pub fn suite(&self) -> CipherSuite {
+        match self {
+            SecurityLevel::L1 => CipherSuite {
+                kem: KemAlgorithm::Kyber512,
+                sig: SigAlgorithm::Dilithium2,
+                aead: AeadAlgorithm::Aes128Gcm,
+                hash: HashAlgorithm::Sha256,
+            },
+            SecurityLevel::L3 => CipherSuite {
+                kem: KemAlgorithm::Kyber768,
+                sig: SigAlgorithm::Dilithium3,
+                aead: AeadAlgorithm::Aes256Gcm,
+                hash: HashAlgorithm::Sha384,
+            },
+            SecurityLevel::L5 => CipherSuite {
+                kem: KemAlgorithm::Kyber1024,
+                sig: SigAlgorithm::Dilithium5,
+                aead: AeadAlgorithm::Aes256Gcm,
+                hash: HashAlgorithm::Sha512,
+            },
+        }
+    }
+}
+
+impl SecurityLevel {
+    /// ML-KEM/ML-DSA suite for this level, usable in FIPS mode
+    pub fn fips_suite(&self) -> CipherSuite {
+        let (kem, sig) = match self {
+            SecurityLevel::L1 => (KemAlgorithm::MlKem512, SigAlgorithm::MlDsa44),
+            SecurityLevel::L3 => (KemAlgorithm::MlKem768, SigAlgorithm::MlDsa65),
+            SecurityLevel::L5 => (KemAlgorithm::MlKem1024, SigAlgorithm::MlDsa87),
+        };
+        CipherSuite { kem, sig, ..self.suite() }
+    }
+}
+
+impl fmt::Display for SecurityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L{}", self.nist_category())
+    }
+}
+
+impl FromStr for SecurityLevel {
+    type Err = PqcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.strip_prefix(['L', 'l']).unwrap_or(s) {
+            "1" => Ok(SecurityLevel::L1),
+            "3" => Ok(SecurityLevel::L3),
+            "5" => Ok(SecurityLevel::L5),
+            _ => Err(PqcError::InvalidInput(format!("Unknown security level: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_suites() {
+        let l5 = SecurityLevel::L5.suite();
+        assert_eq!((l5.kem, l5.sig, l5.aead), (KemAlgorithm::Kyber1024, SigAlgorithm::Dilithium5, AeadAlgorithm::Aes256Gcm));
+        assert_eq!("l1".parse::<SecurityLevel>().unwrap(), SecurityLevel::L1);
+        assert_eq!(SecurityLevel::L3.to_string(), "L3");
+    }
+
+    #[test]
+    fn test_aead_seal_open() {
+        for aead in [AeadAlgorithm::Aes128Gcm, AeadAlgorithm::Aes256Gcm] {
+            let key = vec![7u8; 32];
+            let sealed = aead.seal(&key, b"payload").unwrap();
+            assert_eq!(aead.open(&key, &sealed).unwrap(), b"payload");
+            assert!(aead.open(&[8u8; 32], &sealed).is_err());
+            assert!(aead.seal(&key[..8], b"payload").is_err());
+
+            let bound = aead.seal_with_aad(&key, b"payload", b"v7").unwrap();
+            assert_eq!(aead.open_with_aad(&key, &bound, b"v7").unwrap(), b"payload");
+            assert!(aead.open_with_aad(&key, &bound, b"v8").is_err());
+            assert!(aead.open(&key, &bound).is_err());
+
+            let mut sealed = [0u8; 7 + SEAL_OVERHEAD];
+            let mut opened = [0u8; 7];
+            assert_eq!(aead.seal_into(&key, b"payload", b"v7", &mut sealed).unwrap(), sealed.len());
+            assert_eq!(aead.open_with_aad(&key, &sealed, b"v7").unwrap(), b"payload");
+            assert_eq!(aead.open_into(&key, &bound, b"v7", &mut opened).unwrap(), 7);
+            assert_eq!(&opened, b"payload");
+            assert!(aead.open_into(&key, &bound, b"v8", &mut opened).is_err());
+            assert!(aead.seal_into(&key, b"payload", b"", &mut sealed[..20]).is_err());
+        }
+    }
+}
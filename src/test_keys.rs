@@ -11,7 +11,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("==================================");
     
     // Initialize OQS library
-    oqs::init();
+    pqc_algo::backend::init()?;
     
     // Read the key files
     println!("Reading key files...");
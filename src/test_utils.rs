@@ -0,0 +1,394 @@
+//! Fakes for unit-testing code built on this crate (feature `test-utils`)
+//!
+//! [`MockKemProvider`] and [`MockSigProvider`] stand in for [`Liboqs`]
+//! behind the `provider` traits, [`FakeEntropySource`] for an
+//! [`EntropySource`], [`InMemoryRegistry`] for a persistent registry and
+//! [`TestClock`] for the system clock. They touch no liboqs, files or
+//! network, and the same seed always gives the same keys and bytes. With
+//! default features off the crate builds without liboqs, and these fakes
+//! are the only working providers.
+//!
+//! The mocks are not cryptography: anyone can forge their signatures and
+//! recover their shared secrets. Their keys are 32 bytes, not the lengths
+//! of the algorithm they report. Never enable this feature in production
+//! builds.
+//!
+//! [`Liboqs`]: crate::provider::Liboqs
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use crate::clock::Clock;
+use crate::entropy_source::{EntropySource, SourceHealth, SourceStatus};
+use crate::error::{PqcError, Result};
+use crate::hash;
+use crate::kem::{KemAlgorithm, KyberKeyPair};
+use crate::provider::{KemProvider, SigProvider};
+use crate::registry::{DeviceRecord, DeviceRegistry, MemoryStore, RegistryStore};
+use crate::sign::{DilithiumKeyPair, SigAlgorithm};
+
+/// Length of every mock key, ciphertext, shared secret and signature
+pub const MOCK_KEY_LEN: usize = 32;
+
+/// Deterministic randomness, failure injection and a call counter
+struct Script {
+    rng: Mutex<ChaCha20Rng>,
+    failure: Mutex<Option<String>>,
+    calls: AtomicU64,
+}
+
+impl Script {
+    fn new(seed: u64) -> Self {
+        Self { rng: Mutex::new(ChaCha20Rng::seed_from_u64(seed)), failure: Mutex::new(None), calls: AtomicU64::new(0) }
+    }
+
+    /// Count the call and fail it if a failure is set
+    fn call(&self, error: fn(String) -> PqcError) -> Result<()> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match self.failure.lock().unwrap_or_else(|p| p.into_inner()).as_ref() {
+            Some(message) => Err(error(message.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; MOCK_KEY_LEN];
+        self.rng.lock().unwrap_or_else(|p| p.into_inner()).fill_bytes(&mut bytes);
+        bytes
+    }
+
+    fn set_failure(&self, message: Option<&str>) {
+        *self.failure.lock().unwrap_or_else(|p| p.into_inner()) = message.map(str::to_string);
+    }
+}
+
+fn mock_hash(label: &str, parts: &[&[u8]]) -> Vec<u8> {
+    let mut all: Vec<&[u8]> = vec![label.as_bytes()];
+    all.extend_from_slice(parts);
+    hash::sha256(&all).to_vec()
+}
+
+fn check_len(what: &str, bytes: &[u8], error: fn(String) -> PqcError) -> Result<()> {
+    if bytes.len() != MOCK_KEY_LEN {
+        return Err(error(format!("Mock {} must be {} bytes, got {}", what, MOCK_KEY_LEN, bytes.len())));
+    }
+    Ok(())
+}
+
+/// Hash-based stand-in for a KEM
+///
+/// The public key is a hash of the secret key and the ciphertext is the
+/// encapsulation randomness, so decapsulation only needs the secret key.
+pub struct MockKemProvider {
+    algorithm: KemAlgorithm,
+    script: Script,
+}
+
+impl MockKemProvider {
+    /// A mock reporting `algorithm`, seeded with 0
+    pub fn new(algorithm: KemAlgorithm) -> Self {
+        Self::seeded(algorithm, 0)
+    }
+
+    pub fn seeded(algorithm: KemAlgorithm, seed: u64) -> Self {
+        Self { algorithm, script: Script::new(seed) }
+    }
+
+    /// Fail every later call with `message`, or stop failing with `None`
+    pub fn set_failure(&self, message: Option<&str>) {
+        self.script.set_failure(message);
+    }
+
+    /// Calls made so far, failed ones included
+    pub fn calls(&self) -> u64 {
+        self.script.calls.load(Ordering::Relaxed)
+    }
+
+    fn public_key(secret_key: &[u8]) -> Vec<u8> {
+        mock_hash("mock-kem/public-key", &[secret_key])
+    }
+
+    fn shared_secret(public_key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        mock_hash("mock-kem/shared-secret", &[public_key, ciphertext])
+    }
+}
+
+impl KemProvider for MockKemProvider {
+    fn kem_algorithm(&self) -> KemAlgorithm {
+        self.algorithm
+    }
+
+    fn generate_kem_keypair(&self) -> Result<KyberKeyPair> {
+        self.script.call(PqcError::KeyGeneration)?;
+        let secret_key = self.script.bytes();
+        Ok(KyberKeyPair { public_key: Self::public_key(&secret_key), secret_key })
+    }
+
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        self.script.call(PqcError::Encryption)?;
+        check_len("public key", public_key, PqcError::Encryption)?;
+        let ciphertext = self.script.bytes();
+        let shared_secret = Self::shared_secret(public_key, &ciphertext);
+        Ok((ciphertext, shared_secret))
+    }
+
+    fn decapsulate(&self, secret_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.script.call(PqcError::Decryption)?;
+        check_len("secret key", secret_key, PqcError::Decryption)?;
+        check_len("ciphertext", ciphertext, PqcError::Decryption)?;
+        Ok(Self::shared_secret(&Self::public_key(secret_key), ciphertext))
+    }
+}
+
+/// Hash-based stand-in for a signature scheme
+///
+/// A signature is a hash of the public key and the message, so anyone who
+/// knows the public key can forge one.
+pub struct MockSigProvider {
+    algorithm: SigAlgorithm,
+    script: Script,
+}
+
+impl MockSigProvider {
+    /// A mock reporting `algorithm`, seeded with 0
+    pub fn new(algorithm: SigAlgorithm) -> Self {
+        Self::seeded(algorithm, 0)
+    }
+
+    pub fn seeded(algorithm: SigAlgorithm, seed: u64) -> Self {
+        Self { algorithm, script: Script::new(seed) }
+    }
+
+    /// Fail every later call with `message`, or stop failing with `None`
+    pub fn set_failure(&self, message: Option<&str>) {
+        self.script.set_failure(message);
+    }
+
+    /// Calls made so far, failed ones included
+    pub fn calls(&self) -> u64 {
+        self.script.calls.load(Ordering::Relaxed)
+    }
+
+    fn public_key(secret_key: &[u8]) -> Vec<u8> {
+        mock_hash("mock-sig/public-key", &[secret_key])
+    }
+
+    fn signature(public_key: &[u8], message: &[u8]) -> Vec<u8> {
+        mock_hash("mock-sig/signature", &[public_key, message])
+    }
+}
+
+impl SigProvider for MockSigProvider {
+    fn sig_algorithm(&self) -> SigAlgorithm {
+        self.algorithm
+    }
+
+    fn generate_sig_keypair(&self) -> Result<DilithiumKeyPair> {
+        self.script.call(PqcError::KeyGeneration)?;
+        let secret_key = self.script.bytes();
+        Ok(DilithiumKeyPair { public_key: Self::public_key(&secret_key), secret_key })
+    }
+
+    fn sign(&self, message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>> {
+        self.script.call(PqcError::Signing)?;
+        check_len("secret key", secret_key, PqcError::Signing)?;
+        Ok(Self::signature(&Self::public_key(secret_key), message))
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+        self.script.call(PqcError::Verification)?;
+        check_len("public key", public_key, PqcError::Verification)?;
+        Ok(signature == Self::signature(public_key, message).as_slice())
+    }
+}
+
+/// A [`DeviceRegistry`] over a [`MemoryStore`] that also accepts fixtures
+///
+/// `register` still checks key lengths against the record's suite, which
+/// asks liboqs. [`insert`](Self::insert) skips every check, so records
+/// holding mock keys can be set up without it.
+pub struct InMemoryRegistry {
+    registry: DeviceRegistry,
+}
+
+impl InMemoryRegistry {
+    pub fn new() -> Self {
+        Self { registry: DeviceRegistry::in_memory() }
+    }
+
+    /// A registry already holding `records`
+    pub fn with_devices(records: impl IntoIterator<Item = DeviceRecord>) -> Self {
+        let registry = Self::new();
+        for record in records {
+            registry.insert(record);
+        }
+        registry
+    }
+
+    /// Store `record` as is, replacing any record with the same tenant and ID
+    pub fn insert(&self, record: DeviceRecord) {
+        self.registry.store().put(&record).expect("MemoryStore::put does not fail");
+    }
+}
+
+impl Default for InMemoryRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for InMemoryRegistry {
+    type Target = DeviceRegistry<MemoryStore>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.registry
+    }
+}
+
+/// Entropy source with reproducible output and scripted outages
+pub struct FakeEntropySource {
+    name: String,
+    rng: ChaCha20Rng,
+    failures_left: u32,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    calls: u64,
+}
+
+impl FakeEntropySource {
+    /// A source named `fake` whose output is fixed by `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            name: "fake".to_string(),
+            rng: ChaCha20Rng::seed_from_u64(seed),
+            failures_left: 0,
+            consecutive_failures: 0,
+            last_error: None,
+            calls: 0,
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Fail the next `n` calls with an I/O error, as an unreachable remote would
+    pub fn fail_next(&mut self, n: u32) {
+        self.failures_left = n;
+    }
+
+    /// Calls made so far, failed ones included
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+}
+
+impl EntropySource for FakeEntropySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn generate(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.calls += 1;
+        if self.failures_left > 0 {
+            self.failures_left -= 1;
+            self.consecutive_failures += 1;
+            let error = PqcError::Io(format!("{} is unavailable (scripted failure)", self.name));
+            self.last_error = Some(error.to_string());
+            return Err(error);
+        }
+        self.consecutive_failures = 0;
+        let mut bytes = vec![0u8; size];
+        self.rng.fill_bytes(&mut bytes);
+        Ok(bytes)
+    }
+
+    fn status(&self) -> SourceStatus {
+        SourceStatus {
+            health: if self.consecutive_failures > 0 { SourceHealth::Unavailable } else { SourceHealth::Healthy },
+            consecutive_failures: self.consecutive_failures,
+            last_error: self.last_error.clone(),
+            ..SourceStatus::healthy(&self.name)
+        }
+    }
+}
+
+/// Clock that only moves when told to; clones share one time
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    now: Arc<AtomicU64>,
+}
+
+impl TestClock {
+    /// A clock stopped at Unix time `start`
+    pub fn new(start: u64) -> Self {
+        Self { now: Arc::new(AtomicU64::new(start)) }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::Relaxed);
+    }
+
+    /// Move forward by `by`, in whole seconds
+    pub fn advance(&self, by: Duration) {
+        self.now.fetch_add(by.as_secs(), Ordering::Relaxed);
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::CipherSuite;
+
+    #[test]
+    fn test_mock_providers_round_trip() {
+        let kem = MockKemProvider::new(KemAlgorithm::MlKem768);
+        let keypair = kem.generate_kem_keypair().unwrap();
+        let (ciphertext, secret) = kem.encapsulate(&keypair.public_key).unwrap();
+        assert_eq!(kem.decapsulate(&keypair.secret_key, &ciphertext).unwrap(), secret);
+        // Same seed, same keys
+        assert_eq!(MockKemProvider::new(KemAlgorithm::MlKem768).generate_kem_keypair().unwrap().public_key, keypair.public_key);
+
+        let sig = MockSigProvider::new(SigAlgorithm::MlDsa65);
+        let signer = sig.generate_sig_keypair().unwrap();
+        let signature = sig.sign(b"message", &signer.secret_key).unwrap();
+        assert!(sig.verify(b"message", &signature, &signer.public_key).unwrap());
+        assert!(!sig.verify(b"other", &signature, &signer.public_key).unwrap());
+
+        sig.set_failure(Some("HSM offline"));
+        assert!(matches!(sig.sign(b"message", &signer.secret_key), Err(PqcError::Signing(_))));
+        assert_eq!(sig.calls(), 5);
+    }
+
+    #[test]
+    fn test_fake_entropy_and_clock() {
+        let mut source = FakeEntropySource::new(7).with_name("remote-qrng");
+        source.fail_next(1);
+        assert!(source.generate(16).is_err());
+        assert_eq!(source.status().health, SourceHealth::Unavailable);
+        assert_eq!(source.generate(16).unwrap(), FakeEntropySource::new(7).generate(16).unwrap());
+        assert_eq!(source.status().health, SourceHealth::Healthy);
+
+        let clock = TestClock::new(1000);
+        clock.clone().advance(Duration::from_secs(60));
+        assert_eq!(clock.now(), 1060);
+    }
+
+    #[test]
+    fn test_in_memory_registry_accepts_mock_keys() {
+        let record = DeviceRecord::new("acme", "sensor-1", CipherSuite::DEFAULT, vec![1; MOCK_KEY_LEN], vec![2; MOCK_KEY_LEN]);
+        let registry = InMemoryRegistry::with_devices([record.clone()]);
+        assert_eq!(registry.get("acme", "sensor-1").unwrap(), Some(record));
+        assert_eq!(registry.devices("acme").unwrap().len(), 1);
+    }
+}
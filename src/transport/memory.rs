@@ -0,0 +1,53 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::error::{PqcError, Result};
+use super::{check_frame_len, Transport};
+
+/// In-process transport backed by channels, for tests and simulations
+pub struct MemoryTransport {
+    outgoing: Sender<Vec<u8>>,
+    incoming: Receiver<Vec<u8>>,
+}
+
+impl MemoryTransport {
+    /// Two connected endpoints; frames sent on one arrive on the other
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, b_rx) = channel();
+        let (b_tx, a_rx) = channel();
+        (
+            Self { outgoing: a_tx, incoming: a_rx },
+            Self { outgoing: b_tx, incoming: b_rx },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, frame: &[u8]) -> Result<()> {
+        check_frame_len(frame.len())?;
+        self.outgoing.send(frame.to_vec())
+            .map_err(|_| PqcError::Io("Peer disconnected".to_string()))
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>> {
+        self.incoming.recv()
+            .map_err(|_| PqcError::Io("Peer disconnected".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_pair_exchanges_frames() {
+        let (mut server, mut client) = MemoryTransport::pair();
+        client.send(b"hello").unwrap();
+        let request = crate::status::StatusRequest::new(b"device key");
+        server.send_json(&request).unwrap();
+
+        assert_eq!(server.receive().unwrap(), b"hello");
+        assert_eq!(client.receive_json::<crate::status::StatusRequest>().unwrap(), request);
+
+        drop(server);
+        assert!(client.receive().is_err());
+    }
+}
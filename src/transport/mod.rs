@@ -0,0 +1,89 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::error::{PqcError, Result};
+use crate::padding;
+use crate::validation::{self, Validate};
+
+mod memory;
+mod stream;
+#[cfg(feature = "serial")]
+pub mod serial;
+
+pub use memory::MemoryTransport;
+pub use stream::{StreamTransport, TcpTransport};
+#[cfg(unix)]
+pub use stream::UnixTransport;
+
+/// Largest frame any transport will accept (1 MiB)
+pub const MAX_FRAME_LEN: usize = 1024 * 1024;
+
+/// A link that carries whole frames between provisioning peers
+///
+/// Implementations handle their own framing; callers always send and
+/// receive complete messages such as serialized envelopes.
+pub trait Transport {
+    /// Send one frame
+    fn send(&mut self, frame: &[u8]) -> Result<()>;
+
+    /// Block until one frame arrives
+    fn receive(&mut self) -> Result<Vec<u8>>;
+
+    /// Send a value as a JSON frame
+    fn send_json<T: Serialize>(&mut self, value: &T) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let frame = serde_json::to_vec(value)
+            .map_err(|e| PqcError::InvalidInput(format!("Failed to encode frame: {}", e)))?;
+        self.send(&frame)
+    }
+
+    /// Receive one frame, first passing its length to `admit`, e.g. to reserve memory for it
+    ///
+    /// If `admit` refuses, its error is returned in the inner result and the
+    /// link stays usable for the next frame; the outer error is a transport
+    /// failure. Transports with a length prefix call `admit` before buffering
+    /// the body. The default receives the whole frame first.
+    fn receive_admitted<A>(&mut self, admit: impl FnOnce(usize) -> Result<A>) -> Result<Result<(Vec<u8>, A)>>
+    where
+        Self: Sized,
+    {
+        let frame = self.receive()?;
+        Ok(admit(frame.len()).map(|admitted| (frame, admitted)))
+    }
+
+    /// Receive a JSON frame, decode it and validate the result
+    fn receive_json<T: DeserializeOwned + Validate>(&mut self) -> Result<T>
+    where
+        Self: Sized,
+    {
+        validation::from_json(&self.receive()?, "frame")
+    }
+
+    /// Send a value as a JSON frame padded to exactly `padded_len` bytes
+    ///
+    /// See [`crate::padding`]; both peers must agree on the length.
+    fn send_json_padded<T: Serialize>(&mut self, value: &T, padded_len: usize) -> Result<()>
+    where
+        Self: Sized,
+    {
+        self.send(&padding::to_padded_json(value, padded_len)?)
+    }
+
+    /// Receive a frame sent with [`Transport::send_json_padded`]
+    fn receive_json_padded<T: DeserializeOwned + Validate>(&mut self) -> Result<T>
+    where
+        Self: Sized,
+    {
+        padding::from_padded_json(&self.receive()?)
+    }
+}
+
+pub(crate) fn check_frame_len(len: usize) -> Result<()> {
+    if len > MAX_FRAME_LEN {
+        return Err(PqcError::InvalidInput(format!(
+            "Frame of {} bytes exceeds limit of {}", len, MAX_FRAME_LEN
+        )));
+    }
+    Ok(())
+}
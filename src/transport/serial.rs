@@ -0,0 +1,330 @@
+//! Framed, acknowledged transport for serial/UART provisioning links
+//!
+//! Each frame on the wire is `COBS(kind || seq || payload || crc32) || 0x00`.
+//! Data frames are acknowledged by sequence number; a missing or negative
+//! acknowledgement causes a resend, up to the configured retry count.
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+use crc::{Crc, CRC_32_ISO_HDLC};
+use crate::error::{PqcError, Result};
+use super::{check_frame_len, Transport, MAX_FRAME_LEN};
+
+/// Default time to wait for an acknowledgement before resending
+pub const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Default number of send attempts per frame
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+const KIND_DATA: u8 = 0x01;
+const KIND_ACK: u8 = 0x02;
+const KIND_NAK: u8 = 0x03;
+
+/// kind + seq + crc32
+const FRAME_OVERHEAD: usize = 6;
+
+/// Largest COBS-encoded frame we will buffer before giving up on it
+const MAX_ENCODED_LEN: usize = MAX_FRAME_LEN + FRAME_OVERHEAD + MAX_FRAME_LEN / 254 + 2;
+
+/// Serial transport over any byte port whose reads time out
+///
+/// The port's read timeout doubles as the acknowledgement timeout.
+pub struct SerialTransport<P> {
+    port: P,
+    max_attempts: u32,
+    next_seq: u8,
+    last_received: Option<u8>,
+    rx: Vec<u8>,
+    pending: VecDeque<Vec<u8>>,
+}
+
+impl SerialTransport<Box<dyn serialport::SerialPort>> {
+    /// Open a serial device such as `/dev/ttyUSB0` or `COM3`
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(DEFAULT_ACK_TIMEOUT)
+            .open()
+            .map_err(|e| PqcError::Io(format!("Failed to open {}: {}", path, e)))?;
+        Ok(Self::new(port))
+    }
+}
+
+impl<P: Read + Write> SerialTransport<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            next_seq: 0,
+            last_received: None,
+            rx: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Number of times a frame is sent before giving up
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+
+    fn write_frame(&mut self, kind: u8, seq: u8, payload: &[u8]) -> Result<()> {
+        let mut raw = Vec::with_capacity(payload.len() + FRAME_OVERHEAD);
+        raw.push(kind);
+        raw.push(seq);
+        raw.extend_from_slice(payload);
+        raw.extend_from_slice(&CRC32.checksum(&raw).to_le_bytes());
+
+        let mut encoded = cobs_encode(&raw);
+        encoded.push(0);
+        self.port.write_all(&encoded)
+            .and_then(|_| self.port.flush())
+            .map_err(|e| PqcError::Io(format!("Failed to write serial frame: {}", e)))
+    }
+
+    /// Next delimited frame from the port, or `None` on timeout
+    fn read_raw(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(end) = self.rx.iter().position(|&b| b == 0) {
+                let encoded: Vec<u8> = self.rx.drain(..=end).collect();
+                return Ok(Some(encoded[..end].to_vec()));
+            }
+            if self.rx.len() > MAX_ENCODED_LEN {
+                // Line noise without a delimiter; discard and resync
+                self.rx.clear();
+            }
+
+            let mut buf = [0u8; 256];
+            match self.port.read(&mut buf) {
+                Ok(0) => return Err(PqcError::Io("Serial port closed".to_string())),
+                Ok(n) => self.rx.extend_from_slice(&buf[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => return Ok(None),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(PqcError::Io(format!("Failed to read serial frame: {}", e))),
+            }
+        }
+    }
+
+    /// Next frame that decodes and passes its CRC, NAKing any that fail
+    fn read_frame(&mut self) -> Result<Option<(u8, u8, Vec<u8>)>> {
+        loop {
+            let Some(encoded) = self.read_raw()? else {
+                return Ok(None);
+            };
+            if encoded.is_empty() {
+                continue;
+            }
+            if let Some(frame) = decode_frame(&encoded) {
+                return Ok(Some(frame));
+            }
+            self.write_frame(KIND_NAK, 0, &[])?;
+            return Ok(None);
+        }
+    }
+
+    /// Acknowledge a data frame and queue it unless it is a resend
+    fn accept_data(&mut self, seq: u8, payload: Vec<u8>) -> Result<()> {
+        self.write_frame(KIND_ACK, seq, &[])?;
+        if self.last_received != Some(seq) {
+            self.last_received = Some(seq);
+            self.pending.push_back(payload);
+        }
+        Ok(())
+    }
+}
+
+impl<P: Read + Write> Transport for SerialTransport<P> {
+    fn send(&mut self, frame: &[u8]) -> Result<()> {
+        check_frame_len(frame.len())?;
+        let seq = self.next_seq;
+
+        for _ in 0..self.max_attempts {
+            self.write_frame(KIND_DATA, seq, frame)?;
+
+            // Wait for our ACK; anything else means resend or keep listening
+            loop {
+                match self.read_frame()? {
+                    None => break,
+                    Some((KIND_ACK, acked, _)) if acked == seq => {
+                        self.next_seq = seq.wrapping_add(1);
+                        return Ok(());
+                    }
+                    Some((KIND_NAK, _, _)) => break,
+                    Some((KIND_DATA, peer_seq, payload)) => self.accept_data(peer_seq, payload)?,
+                    Some(_) => {}
+                }
+            }
+        }
+
+        Err(PqcError::Io(format!("No acknowledgement after {} attempts", self.max_attempts)))
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>> {
+        loop {
+            if let Some(payload) = self.pending.pop_front() {
+                return Ok(payload);
+            }
+            if let Some((KIND_DATA, seq, payload)) = self.read_frame()? {
+                self.accept_data(seq, payload)?;
+            }
+        }
+    }
+}
+
+fn decode_frame(encoded: &[u8]) -> Option<(u8, u8, Vec<u8>)> {
+    let raw = cobs_decode(encoded)?;
+    if raw.len() < FRAME_OVERHEAD {
+        return None;
+    }
+    let (body, crc) = raw.split_at(raw.len() - 4);
+    if CRC32.checksum(body).to_le_bytes() != crc {
+        return None;
+    }
+    Some((body[0], body[1], body[2..].to_vec()))
+}
+
+/// Consistent Overhead Byte Stuffing; the output contains no zero bytes
+fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    out.push(0);
+    let mut code = 1u8;
+
+    for &byte in data {
+        if byte != 0 {
+            out.push(byte);
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_index] = code;
+            code_index = out.len();
+            out.push(0);
+            code = 1;
+        }
+    }
+    out[code_index] = code;
+    out
+}
+
+fn cobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+
+    while i < data.len() {
+        let code = data[i] as usize;
+        let end = i + code;
+        if code == 0 || end > data.len() {
+            return None;
+        }
+        out.extend_from_slice(&data[i + 1..end]);
+        i = end;
+        if code < 0xFF && i < data.len() {
+            out.push(0);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+
+    /// One end of an in-memory serial line with a short read timeout
+    struct Line {
+        tx: Sender<Vec<u8>>,
+        rx: Receiver<Vec<u8>>,
+        buf: Vec<u8>,
+        corrupt_writes: usize,
+    }
+
+    fn line_pair() -> (Line, Line) {
+        let (a_tx, b_rx) = channel();
+        let (b_tx, a_rx) = channel();
+        let a = Line { tx: a_tx, rx: a_rx, buf: Vec::new(), corrupt_writes: 0 };
+        let b = Line { tx: b_tx, rx: b_rx, buf: Vec::new(), corrupt_writes: 0 };
+        (a, b)
+    }
+
+    impl Read for Line {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            if self.buf.is_empty() {
+                match self.rx.recv_timeout(Duration::from_millis(50)) {
+                    Ok(chunk) => self.buf = chunk,
+                    Err(_) => return Err(io::Error::new(ErrorKind::TimedOut, "timeout")),
+                }
+            }
+            let n = out.len().min(self.buf.len());
+            out[..n].copy_from_slice(&self.buf[..n]);
+            self.buf.drain(..n);
+            Ok(n)
+        }
+    }
+
+    impl Write for Line {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            let mut chunk = data.to_vec();
+            if self.corrupt_writes > 0 && chunk.len() > 4 {
+                self.corrupt_writes -= 1;
+                chunk[2] ^= 0x40;
+            }
+            self.tx.send(chunk).ok();
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_cobs_roundtrip() {
+        let cases: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0],
+            vec![0, 0, 1, 0],
+            (1..=255u8).collect(),
+            (0..600).map(|i| (i % 256) as u8).collect(),
+        ];
+        for data in cases {
+            let encoded = cobs_encode(&data);
+            assert!(!encoded.contains(&0));
+            assert_eq!(cobs_decode(&encoded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_serial_roundtrip_with_retry() {
+        let (a, mut b) = line_pair();
+        // First data frame is damaged in flight and must be resent
+        b.corrupt_writes = 1;
+
+        let device = std::thread::spawn(move || {
+            let mut device = SerialTransport::new(a);
+            let blob = device.receive().unwrap();
+            device.send(&blob.iter().rev().copied().collect::<Vec<_>>()).unwrap();
+            // Keep acknowledging until the host is done
+            device.receive().unwrap()
+        });
+
+        let mut host = SerialTransport::new(b);
+        host.send(b"personality").unwrap();
+        assert_eq!(host.receive().unwrap(), b"ytilanosrep");
+        host.send(b"done").unwrap();
+        assert_eq!(device.join().unwrap(), b"done");
+    }
+
+    #[test]
+    fn test_serial_gives_up_without_ack() {
+        let (a, _b) = line_pair();
+        let mut host = SerialTransport::new(a).with_max_attempts(2);
+        assert!(matches!(host.send(b"hello"), Err(PqcError::Io(_))));
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use crate::error::{PqcError, Result};
+use super::{check_frame_len, Transport};
+
+/// Length-prefixed frames over any byte stream
+///
+/// Each frame is a 4-byte big-endian length followed by the payload.
+pub struct StreamTransport<S> {
+    stream: S,
+}
+
+/// Framed transport over TCP
+pub type TcpTransport = StreamTransport<TcpStream>;
+
+/// Framed transport over a Unix domain socket
+#[cfg(unix)]
+pub type UnixTransport = StreamTransport<std::os::unix::net::UnixStream>;
+
+impl<S: Read + Write> StreamTransport<S> {
+    /// Wrap an already connected stream, e.g. one returned by `accept`
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// Unwrap the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let mut header = [0u8; 4];
+        self.stream.read_exact(&mut header)
+            .map_err(|e| PqcError::Io(format!("Failed to receive frame: {}", e)))?;
+        let len = u32::from_be_bytes(header) as usize;
+        check_frame_len(len)?;
+        Ok(len)
+    }
+
+    fn read_body(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut frame = vec![0u8; len];
+        self.stream.read_exact(&mut frame)
+            .map_err(|e| PqcError::Io(format!("Failed to receive frame: {}", e)))?;
+        Ok(frame)
+    }
+}
+
+impl TcpTransport {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| PqcError::Io(format!("Failed to connect: {}", e)))?;
+        stream.set_nodelay(true)
+            .map_err(|e| PqcError::Io(format!("Failed to configure socket: {}", e)))?;
+        Ok(Self::new(stream))
+    }
+}
+
+#[cfg(unix)]
+impl UnixTransport {
+    pub fn connect<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)
+            .map_err(|e| PqcError::Io(format!("Failed to connect: {}", e)))?;
+        Ok(Self::new(stream))
+    }
+}
+
+impl<S: Read + Write> Transport for StreamTransport<S> {
+    fn send(&mut self, frame: &[u8]) -> Result<()> {
+        check_frame_len(frame.len())?;
+        self.stream.write_all(&(frame.len() as u32).to_be_bytes())
+            .and_then(|_| self.stream.write_all(frame))
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| PqcError::Io(format!("Failed to send frame: {}", e)))
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_len()?;
+        self.read_body(len)
+    }
+
+    /// Calls `admit` with the length prefix; a refused body is skipped without being buffered
+    fn receive_admitted<A>(&mut self, admit: impl FnOnce(usize) -> Result<A>) -> Result<Result<(Vec<u8>, A)>> {
+        let len = self.read_len()?;
+        match admit(len) {
+            Ok(admitted) => Ok(Ok((self.read_body(len)?, admitted))),
+            Err(refused) => {
+                let skipped = io::copy(&mut (&mut self.stream).take(len as u64), &mut io::sink())
+                    .map_err(|e| PqcError::Io(format!("Failed to skip frame: {}", e)))?;
+                if skipped != len as u64 {
+                    return Err(PqcError::Io("Stream ended inside a frame".to_string()));
+                }
+                Ok(Err(refused))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use crate::transport::MAX_FRAME_LEN;
+
+    #[test]
+    fn test_tcp_roundtrip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut transport = TcpTransport::new(stream);
+            let frame = transport.receive().unwrap();
+            transport.send(&frame).unwrap();
+        });
+
+        let mut client = TcpTransport::connect(addr).unwrap();
+        client.send(b"echo me").unwrap();
+        assert_eq!(client.receive().unwrap(), b"echo me");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_oversized_frame_header_rejected() {
+        let mut input = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes().to_vec();
+        input.extend_from_slice(b"junk");
+        let mut transport = StreamTransport::new(std::io::Cursor::new(input));
+        assert!(matches!(transport.receive(), Err(PqcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_refused_frame_is_skipped() {
+        let mut input = Vec::new();
+        for frame in [&b"too large"[..], b"next"] {
+            input.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            input.extend_from_slice(frame);
+        }
+        let mut transport = StreamTransport::new(std::io::Cursor::new(input));
+        let refuse = |len: usize| if len > 4 { Err(PqcError::Overloaded(format!("{} bytes", len))) } else { Ok(len) };
+
+        assert!(matches!(transport.receive_admitted(refuse).unwrap(), Err(PqcError::Overloaded(_))));
+        assert_eq!(transport.receive_admitted(refuse).unwrap().unwrap(), (b"next".to_vec(), 4));
+    }
+}
@@ -0,0 +1,104 @@
+//! Serde helper for `Vec<u8>` fields, used as `#[serde(with = "crate::util::bytes")]`
+//!
+//! A plain `Vec<u8>` goes through serde as a sequence, which CBOR and
+//! MessagePack store as an array with one integer per byte. Serializing with
+//! `serialize_bytes` gives them a native byte string instead, while JSON
+//! still writes the same array of integers, so JSON artifacts and the
+//! canonical signing bytes are unchanged.
+//!
+//! Deserializing accepts both shapes, so CBOR and MessagePack written before
+//! byte strings were used still decode. A JSON string is rejected, as it was
+//! for a plain `Vec<u8>`.
+
+use std::fmt;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+/// Upper bound on the capacity reserved from a sequence's size hint
+const MAX_PREALLOC: usize = 4096;
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    // `deserialize_any` rather than `deserialize_byte_buf`, which serde_json
+    // would satisfy with the UTF-8 bytes of a string
+    deserializer.deserialize_any(BytesVisitor)
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte string or an array of bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_PREALLOC));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Blob {
+        #[serde(with = "crate::util::bytes")]
+        data: Vec<u8>,
+    }
+
+    /// The same field as a plain `Vec<u8>`, as it was encoded before
+    #[derive(Serialize)]
+    struct Legacy {
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn test_json_is_unchanged() {
+        let blob = Blob { data: vec![0, 7, 255] };
+        let json = serde_json::to_string(&blob).unwrap();
+        assert_eq!(json, serde_json::to_string(&Legacy { data: blob.data.clone() }).unwrap());
+        assert_eq!(serde_json::from_str::<Blob>(&json).unwrap(), blob);
+        assert!(serde_json::from_str::<Blob>(r#"{"data":"abc"}"#).is_err());
+        assert!(serde_json::from_str::<Blob>(r#"{"data":[256]}"#).is_err());
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_binary_formats_use_byte_strings() {
+        let blob = Blob { data: vec![0xab; 64] };
+        let legacy = Legacy { data: blob.data.clone() };
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&blob, &mut cbor).unwrap();
+        let mut old_cbor = Vec::new();
+        ciborium::into_writer(&legacy, &mut old_cbor).unwrap();
+        // Major type 2 with a one-byte length, then the raw bytes
+        assert!(cbor.windows(2).any(|w| w == [0x58, 64]));
+        assert!(cbor.len() < old_cbor.len());
+        assert_eq!(ciborium::from_reader::<Blob, _>(cbor.as_slice()).unwrap(), blob);
+        assert_eq!(ciborium::from_reader::<Blob, _>(old_cbor.as_slice()).unwrap(), blob);
+
+        let msgpack = rmp_serde::to_vec_named(&blob).unwrap();
+        let old_msgpack = rmp_serde::to_vec_named(&legacy).unwrap();
+        assert!(msgpack.len() < old_msgpack.len());
+        assert_eq!(rmp_serde::from_slice::<Blob>(&msgpack).unwrap(), blob);
+        assert_eq!(rmp_serde::from_slice::<Blob>(&old_msgpack).unwrap(), blob);
+    }
+}
@@ -0,0 +1,52 @@
+//! Constant-time comparisons
+//!
+//! Use these instead of `==` whenever one side is a secret or an
+//! authenticator: shared secrets, decrypted key material, MACs, tags and
+//! digests checked against a recorded value. Only the lengths are compared
+//! in variable time; they are treated as public.
+
+use subtle::ConstantTimeEq;
+use crate::error::{PqcError, Result};
+
+/// Compare two byte strings without leaking where they differ
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Check a received MAC or tag against the expected one
+pub fn verify_tag(expected: &[u8], received: &[u8]) -> Result<()> {
+    if expected.is_empty() || !ct_eq(expected, received) {
+        return Err(PqcError::Verification("Authentication tag mismatch".to_string()));
+    }
+    Ok(())
+}
+
+/// Compare two hex-encoded digests, ignoring case
+///
+/// Malformed hex never matches.
+pub fn ct_eq_hex(a: &str, b: &str) -> bool {
+    match (hex::decode(a), hex::decode(b)) {
+        (Ok(a), Ok(b)) => ct_eq(&a, &b),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparisons() {
+        assert!(ct_eq(b"secret", b"secret"));
+        assert!(!ct_eq(b"secret", b"secreT"));
+        assert!(!ct_eq(b"secret", b"secret!"));
+
+        assert!(verify_tag(&[7; 16], &[7; 16]).is_ok());
+        assert!(matches!(verify_tag(&[7; 16], &[7; 15]), Err(PqcError::Verification(_))));
+        assert!(verify_tag(&[], &[]).is_err());
+
+        assert!(ct_eq_hex("ABCDEF", "abcdef"));
+        assert!(!ct_eq_hex("abcdef", "abcde0"));
+        assert!(!ct_eq_hex("zz", "zz"));
+    }
+}
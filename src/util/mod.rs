@@ -0,0 +1,5 @@
+//! Small helpers shared across the crate
+
+pub mod bytes;
+pub mod ct;
+pub mod redact;
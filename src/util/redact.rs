@@ -0,0 +1,178 @@
+//! Scrubbing key and plaintext bytes out of messages
+//!
+//! Error messages are built from many sources, including liboqs and serde
+//! errors that can quote their input. [`redact`] is applied whenever a
+//! [`PqcError`](crate::error::PqcError) is formatted, with `Display` or
+//! `Debug`, and by the hook from [`install_panic_hook`].
+//!
+//! Three shapes are replaced:
+//!
+//! - Runs of [`MIN_HEX_RUN`] or more hex digits, the encoding of a 16-byte
+//!   key or longer. The one exception is a 64-digit SHA-256 that directly
+//!   follows a word naming it, such as `fingerprint: ` or `sha256=`; see
+//!   [`DIGEST_CONTEXT`].
+//! - Base64-looking runs of [`MIN_BASE64_RUN`] or more characters that mix
+//!   digits with upper and lower case letters, the encoding of 32 bytes.
+//! - `Debug`-formatted byte lists of [`MIN_BYTE_LIST`] or more elements,
+//!   decimal as with `{:?}` or hex as with `{:x?}` and `{:#x?}`, such as
+//!   `[12, 250, 7]` or `[c, fa, 7]`.
+
+use std::borrow::Cow;
+use std::panic;
+
+/// Shortest hex run that is replaced
+pub const MIN_HEX_RUN: usize = 32;
+
+/// Shortest base64 run that is replaced
+pub const MIN_BASE64_RUN: usize = 43;
+
+/// Shortest byte list that is replaced
+pub const MIN_BYTE_LIST: usize = 2;
+
+/// Words that mark the 64-digit hex run right after them as a public digest
+pub const DIGEST_CONTEXT: &[&str] = &["fingerprint", "sha256", "sha-256", "digest"];
+
+/// `message` with anything that looks like key or plaintext bytes replaced
+pub fn redact(message: &str) -> Cow<'_, str> {
+    match scrub(message) {
+        Some(scrubbed) => Cow::Owned(scrubbed),
+        None => Cow::Borrowed(message),
+    }
+}
+
+/// Redact panic messages before the previously installed hook prints them
+///
+/// Panics whose message needs no redaction go to the previous hook
+/// unchanged, so backtraces and test output look as usual.
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = info.payload().downcast_ref::<&str>().copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str));
+        match message.and_then(scrub) {
+            Some(redacted) => {
+                let location = info.location().map(|l| format!(" at {}", l)).unwrap_or_default();
+                eprintln!("thread panicked{}:\n{}", location, redacted);
+            }
+            None => previous(info),
+        }
+    }));
+}
+
+/// The redacted message, or `None` if nothing needed replacing
+fn scrub(message: &str) -> Option<String> {
+    let mut out = String::with_capacity(message.len());
+    let mut changed = false;
+    let mut pos = 0;
+    while let Some(c) = message[pos..].chars().next() {
+        if let Some((len, count)) = byte_list(&message[pos..]) {
+            out.push_str(&format!("[redacted; {} bytes]", count));
+            changed = true;
+            pos += len;
+        } else if is_token_char(c) {
+            let len = message[pos..].find(|c| !is_token_char(c)).unwrap_or(message.len() - pos);
+            changed |= scrub_token(message, pos, pos + len, &mut out);
+            pos += len;
+        } else {
+            out.push(c);
+            pos += c.len_utf8();
+        }
+    }
+    changed.then_some(out)
+}
+
+/// Append `message[start..end]` to `out` with secret-looking runs replaced
+fn scrub_token(message: &str, start: usize, end: usize, out: &mut String) -> bool {
+    let token = &message[start..end];
+    if looks_like_base64(token) {
+        out.push_str(&format!("[redacted; {} chars]", token.len()));
+        return true;
+    }
+    let mut changed = false;
+    let mut pos = start;
+    while pos < end {
+        let run = message[pos..end].find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(end - pos);
+        if run >= MIN_HEX_RUN && !is_named_digest(message, pos, run) {
+            out.push_str(&format!("[redacted; {} chars]", run));
+            changed = true;
+        } else {
+            out.push_str(&message[pos..pos + run]);
+        }
+        pos += run;
+        let other = message[pos..end].find(|c: char| c.is_ascii_hexdigit()).unwrap_or(end - pos);
+        out.push_str(&message[pos..pos + other]);
+        pos += other;
+    }
+    changed
+}
+
+fn looks_like_base64(token: &str) -> bool {
+    token.len() >= MIN_BASE64_RUN
+        && token.bytes().any(|b| b.is_ascii_digit())
+        && token.bytes().any(|b| b.is_ascii_uppercase())
+        && token.bytes().any(|b| b.is_ascii_lowercase())
+}
+
+/// Whether the hex run at `start` is a SHA-256 introduced by one of [`DIGEST_CONTEXT`]
+fn is_named_digest(message: &str, start: usize, len: usize) -> bool {
+    if len != 64 {
+        return false;
+    }
+    let before = message[..start].trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '=' | '"' | '\'' | '(' | '['));
+    let word_start = before.rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_')).map_or(0, |i| i + 1);
+    let word = before[word_start..].to_ascii_lowercase();
+    DIGEST_CONTEXT.iter().any(|name| word.ends_with(name))
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '_' | '-')
+}
+
+/// Length and element count of the byte list `s` starts with, if it has at least [`MIN_BYTE_LIST`] elements
+fn byte_list(s: &str) -> Option<(usize, usize)> {
+    let inner = s.strip_prefix('[')?;
+    let end = inner.find(']')?;
+    // `{:#?}` puts a comma after the last element
+    let list = inner[..end].trim_end().trim_end_matches(',');
+    let elements: Vec<&str> = list.split(',').map(str::trim).collect();
+    (elements.len() >= MIN_BYTE_LIST && elements.iter().all(|e| is_byte(e))).then_some((end + 2, elements.len()))
+}
+
+/// A byte as `{:?}`, `{:x?}`, `{:X?}` or `{:#x?}` print it
+fn is_byte(element: &str) -> bool {
+    let hex = element.strip_prefix("0x").unwrap_or(element);
+    let decimal = element.len() <= 3 && element.parse::<u8>().is_ok();
+    decimal || (!hex.is_empty() && hex.len() <= 2 && u8::from_str_radix(hex, 16).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_long_tokens_and_byte_lists() {
+        let fingerprint = "ab".repeat(32);
+        let kept = format!("key fingerprint {} not found in /var/lib/pqc-algo/keys, got [1]", fingerprint);
+        assert!(matches!(redact(&kept), Cow::Borrowed(_)));
+
+        let key = "0f".repeat(40);
+        assert_eq!(redact(&format!("bad key {}!", key)), "bad key [redacted; 80 chars]!");
+        let bytes = format!("{:?}", vec![7u8; 12]);
+        assert_eq!(redact(&format!("backend: Error({})", bytes)), "backend: Error([redacted; 12 bytes])");
+    }
+
+    #[test]
+    fn test_redacts_32_byte_secrets_short_lists_and_hex_lists() {
+        let secret = "5a".repeat(32);
+        assert_eq!(redact(&format!("shared secret {}", secret)), "shared secret [redacted; 64 chars]");
+        // Only the named digest is kept, not a secret that happens to follow it
+        assert_eq!(redact(&format!("fingerprint={} key={}", secret, secret)), format!("fingerprint={} key=[redacted; 64 chars]", secret));
+        assert_eq!(redact(&format!("aes key {}", "c3".repeat(16))), "aes key [redacted; 32 chars]");
+
+        for list in [format!("{:?}", [9u8, 200, 31]), format!("{:x?}", [9u8, 200, 31]), format!("{:#x?}", [9u8, 200, 31])] {
+            assert_eq!(redact(&format!("got {}", list)), "got [redacted; 3 bytes]", "{}", list);
+        }
+        let base64 = "q3Zx9M0kT2b7LwYp1VnR8sJcH4eGaU6fD5iKoBmXtAz=";
+        assert_eq!(redact(&format!("token {}", base64)), "token [redacted; 44 chars]");
+    }
+}
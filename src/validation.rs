@@ -0,0 +1,157 @@
+use serde::de::DeserializeOwned;
+use crate::error::{PqcError, Result};
+use crate::kem::KemAlgorithm;
+use crate::sign::SigAlgorithm;
+use crate::suite::{NONCE_LEN, TAG_LEN};
+
+/// Longest accepted device identifier, in bytes
+pub const MAX_DEVICE_ID_LEN: usize = 64;
+
+/// Largest serialized message any `from_bytes` will parse (1 MiB, as for transport frames)
+pub const MAX_MESSAGE_LEN: usize = crate::transport::MAX_FRAME_LEN;
+
+/// Longest accepted nonce or challenge, in bytes
+pub const MAX_NONCE_LEN: usize = 256;
+
+/// Longest accepted free-text field such as a purpose, subject or serial
+pub const MAX_LABEL_LEN: usize = 256;
+
+/// Structural checks on a value parsed from untrusted bytes
+///
+/// Run before any cryptography, so field lengths an attacker controls are
+/// bounded by the algorithm they claim rather than by the input size.
+pub trait Validate {
+    fn validate(&self) -> Result<()>;
+}
+
+/// Parse JSON `bytes` of at most [`MAX_MESSAGE_LEN`] and validate the result
+///
+/// `what` names the message in errors, e.g. "envelope".
+pub fn from_json<T: DeserializeOwned + Validate>(bytes: &[u8], what: &str) -> Result<T> {
+    from_json_with_limit(bytes, what, MAX_MESSAGE_LEN)
+}
+
+/// [`from_json`] with another size limit, for artifacts that outgrow one message
+pub(crate) fn from_json_with_limit<T: DeserializeOwned + Validate>(bytes: &[u8], what: &str, max_len: usize) -> Result<T> {
+    check_max_len(what, bytes.len(), max_len)?;
+    let value: T = serde_json::from_slice(bytes)
+        .map_err(|e| PqcError::InvalidInput(format!("Malformed {}: {}", what, e)))?;
+    value.validate()?;
+    Ok(value)
+}
+
+pub(crate) fn check_max_len(what: &str, len: usize, max: usize) -> Result<()> {
+    if len > max {
+        return Err(PqcError::InvalidInput(format!("{} is {} bytes, maximum is {}", what, len, max)));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_exact_len(what: &str, len: usize, expected: usize) -> Result<()> {
+    if len != expected {
+        return Err(PqcError::InvalidInput(format!("{} is {} bytes, expected {}", what, len, expected)));
+    }
+    Ok(())
+}
+
+/// A signature no longer than `algorithm` can produce
+pub(crate) fn check_signature_len(what: &str, algorithm: SigAlgorithm, len: usize) -> Result<()> {
+    check_max_len(what, len, algorithm.signature_len()?)
+}
+
+/// A signature no longer than any enabled algorithm can produce
+pub(crate) fn check_any_signature_len(what: &str, len: usize) -> Result<()> {
+    let max = SigAlgorithm::all().iter()
+        .filter(|alg| alg.is_enabled())
+        .filter_map(|alg| alg.signature_len().ok())
+        .max()
+        .unwrap_or(0);
+    check_max_len(what, len, max)
+}
+
+/// A public key no longer than any enabled KEM or signature key
+pub(crate) fn check_any_public_key_len(what: &str, len: usize) -> Result<()> {
+    let kem = KemAlgorithm::all().iter().filter(|alg| alg.is_enabled()).filter_map(|alg| alg.public_key_len().ok());
+    let sig = SigAlgorithm::all().iter().filter(|alg| alg.is_enabled()).filter_map(|alg| alg.public_key_len().ok());
+    check_max_len(what, len, kem.chain(sig).max().unwrap_or(0))
+}
+
+/// AEAD output holding at least a nonce and a tag
+pub(crate) fn check_sealed_len(what: &str, len: usize) -> Result<()> {
+    if len < NONCE_LEN + TAG_LEN {
+        return Err(PqcError::InvalidInput(format!("{} is {} bytes, too short to be sealed", what, len)));
+    }
+    Ok(())
+}
+
+/// A hex SHA-256 fingerprint
+pub(crate) fn check_fingerprint(what: &str, fingerprint: &str) -> Result<()> {
+    if fingerprint.len() != 64 || !fingerprint.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(PqcError::InvalidInput(format!("{} is not a SHA-256 fingerprint", what)));
+    }
+    Ok(())
+}
+
+/// Check that a device identifier is well formed
+///
+/// IDs must be 1 to 64 ASCII characters from `[A-Za-z0-9._-]` and start with
+/// a letter or digit. Separators, whitespace and control characters are
+/// rejected so an ID can never be confused with framing in a signed message,
+/// a log line or a file name.
+pub fn validate_device_id(device_id: &str) -> Result<()> {
+    validate_id("Device ID", device_id)
+}
+
+/// Check a tenant identifier; same rules as [`validate_device_id`]
+pub fn validate_tenant_id(tenant: &str) -> Result<()> {
+    validate_id("Tenant ID", tenant)
+}
+
+fn validate_id(kind: &str, id: &str) -> Result<()> {
+    if id.is_empty() {
+        return Err(PqcError::InvalidInput(format!("{} must not be empty", kind)));
+    }
+    if id.len() > MAX_DEVICE_ID_LEN {
+        return Err(PqcError::InvalidInput(format!(
+            "{} is {} bytes, maximum is {}", kind, id.len(), MAX_DEVICE_ID_LEN
+        )));
+    }
+    if !id.as_bytes()[0].is_ascii_alphanumeric() {
+        return Err(PqcError::InvalidInput(format!("{} must start with a letter or digit", kind)));
+    }
+    if let Some(bad) = id.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))) {
+        return Err(PqcError::InvalidInput(format!("{} contains invalid character {:?}", kind, bad)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_device_ids() {
+        for id in ["device_123", "quantum_device_001", "sensor-7.eu", "A", &"x".repeat(MAX_DEVICE_ID_LEN)] {
+            assert!(validate_device_id(id).is_ok(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_invalid_device_ids() {
+        for id in ["", "device|123", "dev ice", "_hidden", "-flag", "dévice", "id\n", "a/b", &"x".repeat(MAX_DEVICE_ID_LEN + 1)] {
+            assert!(matches!(validate_device_id(id), Err(PqcError::InvalidInput(_))), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn test_length_checks() {
+        assert!(check_signature_len("Signature", SigAlgorithm::MlDsa44, 10).is_ok());
+        let max = SigAlgorithm::MlDsa44.signature_len().unwrap();
+        assert!(check_signature_len("Signature", SigAlgorithm::MlDsa44, max + 1).is_err());
+        assert!(check_any_signature_len("Signature", 1 << 31).is_err());
+        assert!(check_sealed_len("Sealed data", NONCE_LEN + TAG_LEN).is_ok());
+        assert!(check_sealed_len("Sealed data", NONCE_LEN).is_err());
+        assert!(check_fingerprint("Fingerprint", &"ab".repeat(32)).is_ok());
+        assert!(check_fingerprint("Fingerprint", "ab").is_err());
+    }
+}
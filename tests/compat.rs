@@ -0,0 +1,29 @@
+//! Artifacts written by earlier releases must keep opening
+//!
+//! To snapshot a new release, run
+//! `cargo test --features codec --test compat -- --ignored` on a build
+//! linked against liboqs and commit `tests/corpus/<version>`.
+
+use std::path::PathBuf;
+use pqc_algo::compat;
+
+fn corpus_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("corpus")
+}
+
+#[test]
+fn corpus_still_opens() {
+    let report = compat::check_corpus(&corpus_root()).unwrap();
+    assert!(report.checked > 0, "compatibility corpus is empty");
+    for failure in &report.failures {
+        eprintln!("{} {}: {}", failure.crate_version, failure.file, failure.error);
+    }
+    assert!(report.is_ok(), "{} of {} corpus artifacts no longer open", report.failures.len(), report.checked);
+}
+
+#[test]
+#[ignore = "writes to tests/corpus"]
+fn snapshot_current_version() {
+    let manifest = compat::write_corpus(&corpus_root().join(env!("CARGO_PKG_VERSION"))).unwrap();
+    println!("{} artifacts for {}", manifest.entries.len(), manifest.crate_version);
+}
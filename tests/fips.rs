@@ -0,0 +1,59 @@
+//! FIPS mode is process-wide and cannot be turned off again, so it is
+//! exercised in its own test binary instead of next to the unit tests
+
+use pqc_algo::api::{self, DeviceCredentials};
+use pqc_algo::envelope::SignedEnvelope;
+use pqc_algo::error::{PqcError, Result};
+use pqc_algo::fips::{self, FIPS_ENV_VAR};
+use pqc_algo::hash::{HashAlgorithm, HashPolicy};
+use pqc_algo::sign::{self, SigAlgorithm};
+use pqc_algo::suite::CipherSuite;
+use pqc_algo::validation::{MAX_LABEL_LEN, MAX_MESSAGE_LEN};
+
+fn not_approved<T>(result: Result<T>) -> bool {
+    result.is_err_and(|e| matches!(e.root(), PqcError::NotApproved(_)))
+}
+
+fn invalid<T>(result: Result<T>) -> bool {
+    result.is_err_and(|e| matches!(e.root(), PqcError::InvalidInput(_)))
+}
+
+#[test]
+fn fips_mode_rejects_non_approved_malformed_and_oversized_input() {
+    // Set before anything reads the mode, as a deployment would
+    std::env::set_var(FIPS_ENV_VAR, "1");
+    assert!(fips::fips_mode());
+
+    // Approved defaults still work end to end
+    let device = DeviceCredentials::generate().unwrap();
+    let sealed = api::encrypt_entropy_for_device(b"entropy", &device.kyber_public_key).unwrap();
+    assert!(sealed.fips_mode);
+    assert_eq!(api::decrypt_entropy(&sealed, &device.kyber_secret_key).unwrap(), b"entropy");
+
+    // Pre-standard algorithms are refused
+    assert!(not_approved(DeviceCredentials::generate_with_suite(&CipherSuite::DEFAULT)));
+    assert!(not_approved(api::encrypt_entropy_with_suite(b"entropy", &device.kyber_public_key, &CipherSuite::DEFAULT)));
+    assert!(not_approved(HashPolicy::only(HashAlgorithm::Sha3_256).check(HashAlgorithm::Sha3_256)));
+    let signer = sign::generate_keypair_with(CipherSuite::FIPS_DEFAULT.sig).unwrap();
+    assert!(not_approved(SignedEnvelope::sign_with(SigAlgorithm::Dilithium3, b"payload", "config", 0, &signer.secret_key, &signer.public_key)));
+
+    // Tampered and malformed envelopes
+    let envelope = SignedEnvelope::sign(b"payload", "config", &signer.secret_key, &signer.public_key).unwrap();
+    assert!(envelope.fips_mode);
+    assert!(envelope.verify(&signer.public_key, "config").unwrap());
+    let stripped = SignedEnvelope { fips_mode: false, ..envelope.clone() };
+    assert!(!stripped.verify(&signer.public_key, "config").unwrap());
+    let downgraded = SignedEnvelope { algorithm: SigAlgorithm::Dilithium3.name().to_string(), ..envelope.clone() };
+    assert!(not_approved(downgraded.verify(&signer.public_key, "config")));
+    let unknown = SignedEnvelope { algorithm: "ML-DSA-65x".to_string(), ..envelope.clone() };
+    assert!(unknown.verify(&signer.public_key, "config").is_err());
+    assert!(invalid(SignedEnvelope::from_bytes(b"{\"algorithm\": 3")));
+    assert!(invalid(SignedEnvelope::from_bytes(&unknown.to_bytes().unwrap())));
+
+    // Oversized input is refused before it is parsed or verified
+    assert!(invalid(SignedEnvelope::from_bytes(&vec![b' '; MAX_MESSAGE_LEN + 1])));
+    let long_purpose = SignedEnvelope { purpose: "p".repeat(MAX_LABEL_LEN + 1), ..envelope.clone() };
+    assert!(invalid(SignedEnvelope::from_bytes(&long_purpose.to_bytes().unwrap())));
+    let padded = SignedEnvelope { signature: vec![0; envelope.signature.len() + 1], ..envelope };
+    assert!(invalid(SignedEnvelope::from_bytes(&padded.to_bytes().unwrap())));
+}